@@ -0,0 +1,204 @@
+//! Benchmarks working on real-world corpora.
+//!
+//! The following corpora are used, if available:
+//! - the Fedora 34 `vmlinux` image, in ELF (symbol table only), DWARF,
+//!   and Gsym form; downloaded and converted as part of the build when
+//!   the `generate-large-test-files` feature is enabled
+//! - a large Rust and a large C++ binary with debug information,
+//!   `bench-rust.bin` and `bench-cxx.bin`; downloaded as part of the
+//!   build when the `generate-large-test-files` feature is enabled and
+//!   verified against the digests pinned in `data/corpora.txt`
+//!
+//! Corpora that are not available are skipped.
+
+use std::path::Path;
+
+use blazesym::inspect;
+use blazesym::inspect::Inspector;
+use blazesym::symbolize::Elf;
+use blazesym::symbolize::GsymFile;
+use blazesym::symbolize::Input;
+use blazesym::symbolize::Source;
+use blazesym::symbolize::Symbolizer;
+use blazesym::Addr;
+
+use criterion::black_box;
+use criterion::measurement::Measurement;
+use criterion::BenchmarkGroup;
+use criterion::Throughput;
+
+
+/// The maximum number of addresses to symbolize per batch.
+const BATCH_SIZE: usize = 1024;
+
+
+/// A symbolization source along with addresses to symbolize in it.
+pub struct Corpus {
+    /// The name of the corpus.
+    pub name: &'static str,
+    /// The symbolization source.
+    pub src: Source<'static>,
+    /// Addresses of functions in the source, sorted.
+    pub addrs: Vec<Addr>,
+}
+
+
+/// Retrieve up to [`BATCH_SIZE`] addresses of functions in the ELF file
+/// at `path`, spread evenly over all functions.
+fn function_addrs(path: &Path) -> Vec<Addr> {
+    let src = inspect::Source::Elf(inspect::Elf::new(path));
+    let mut syms = Inspector::new()
+        .for_each(&src, Vec::new(), |mut syms, sym| {
+            if sym.sym_type == inspect::SymType::Function && sym.size > 0 {
+                // Symbolize an address in the middle of the function.
+                let () = syms.push(sym.addr + sym.size as Addr / 2);
+            }
+            syms
+        })
+        .unwrap();
+    let () = syms.sort_unstable();
+    let () = syms.dedup();
+
+    let step = (syms.len() / BATCH_SIZE).max(1);
+    syms.into_iter().step_by(step).take(BATCH_SIZE).collect()
+}
+
+
+/// Retrieve all available corpora.
+pub fn corpora() -> Vec<Corpus> {
+    let data = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+    let vmlinux = data.join("vmlinux-5.17.12-100.fc34.x86_64.elf");
+    let dwarf = data.join("vmlinux-5.17.12-100.fc34.x86_64.dwarf");
+    let gsym = data.join("vmlinux-5.17.12-100.fc34.x86_64.gsym");
+    let rust = data.join("bench-rust.bin");
+    let cxx = data.join("bench-cxx.bin");
+
+    let mut corpora = Vec::new();
+    if vmlinux.exists() {
+        let addrs = function_addrs(&vmlinux);
+        let mut elf = Elf::new(&vmlinux);
+        elf.debug_syms = false;
+        let () = corpora.push(Corpus {
+            name: "vmlinux-elf",
+            src: Source::Elf(elf),
+            addrs: addrs.clone(),
+        });
+
+        if dwarf.exists() {
+            let () = corpora.push(Corpus {
+                name: "vmlinux-dwarf",
+                src: Source::Elf(Elf::new(dwarf)),
+                addrs: addrs.clone(),
+            });
+        }
+        if gsym.exists() {
+            let () = corpora.push(Corpus {
+                name: "vmlinux-gsym",
+                src: Source::from(GsymFile::new(gsym)),
+                addrs,
+            });
+        }
+    } else {
+        eprintln!(
+            "{} not found; enable the `generate-large-test-files` feature to create it",
+            vmlinux.display()
+        );
+    }
+
+    for (name, path) in [("rust-dwarf", rust), ("cxx-dwarf", cxx)] {
+        if path.exists() {
+            let () = corpora.push(Corpus {
+                name,
+                addrs: function_addrs(&path),
+                src: Source::Elf(Elf::new(path)),
+            });
+        } else {
+            eprintln!("{} not found; skipping {name} corpus", path.display());
+        }
+    }
+    corpora
+}
+
+
+fn bench_name(kind: &str, corpus: &Corpus) -> String {
+    crate::bench_fn_name(&format!("{kind}_{}", corpus.name))
+}
+
+
+/// Symbolize a single address, including parsing of the source.
+fn symbolize_cold<M>(group: &mut BenchmarkGroup<'_, M>, corpus: &Corpus)
+where
+    M: Measurement,
+{
+    let addr = corpus.addrs[corpus.addrs.len() / 2];
+    group.throughput(Throughput::Elements(1));
+    group.bench_function(bench_name("cold", corpus), |b| {
+        b.iter(|| {
+            let symbolizer = Symbolizer::new();
+            let result = symbolizer
+                .symbolize_single(black_box(&corpus.src), black_box(Input::VirtOffset(addr)))
+                .unwrap();
+            let _result = black_box(result);
+        })
+    });
+}
+
+/// Symbolize a single address using a `Symbolizer` that already parsed
+/// the source.
+fn symbolize_warm<M>(group: &mut BenchmarkGroup<'_, M>, corpus: &Corpus)
+where
+    M: Measurement,
+{
+    let addr = corpus.addrs[corpus.addrs.len() / 2];
+    let symbolizer = Symbolizer::new();
+    let _result = symbolizer
+        .symbolize(&corpus.src, Input::VirtOffset(&corpus.addrs))
+        .unwrap();
+
+    group.throughput(Throughput::Elements(1));
+    group.bench_function(bench_name("warm", corpus), |b| {
+        b.iter(|| {
+            let result = symbolizer
+                .symbolize_single(black_box(&corpus.src), black_box(Input::VirtOffset(addr)))
+                .unwrap();
+            let _result = black_box(result);
+        })
+    });
+}
+
+/// Symbolize a batch of addresses using a `Symbolizer` that already
+/// parsed the source.
+fn symbolize_batch<M>(group: &mut BenchmarkGroup<'_, M>, corpus: &Corpus)
+where
+    M: Measurement,
+{
+    let symbolizer = Symbolizer::new();
+    let _result = symbolizer
+        .symbolize(&corpus.src, Input::VirtOffset(&corpus.addrs))
+        .unwrap();
+
+    group.throughput(Throughput::Elements(corpus.addrs.len() as u64));
+    group.bench_function(bench_name("batch", corpus), |b| {
+        b.iter(|| {
+            let results = symbolizer
+                .symbolize(
+                    black_box(&corpus.src),
+                    black_box(Input::VirtOffset(&corpus.addrs)),
+                )
+                .unwrap();
+            let _results = black_box(results);
+        })
+    });
+}
+
+
+pub fn benchmark<M>(group: &mut BenchmarkGroup<'_, M>, corpora: &[Corpus])
+where
+    M: Measurement,
+{
+    for corpus in corpora {
+        let () = symbolize_cold(group, corpus);
+        let () = symbolize_warm(group, corpus);
+        let () = symbolize_batch(group, corpus);
+    }
+}
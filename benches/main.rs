@@ -15,6 +15,7 @@ macro_rules! bench_sub_fn {
 }
 
 
+mod corpus;
 mod inspect;
 mod normalize;
 mod symbolize;
@@ -44,5 +45,12 @@ fn benchmark(c: &mut Criterion) {
     symbolize::benchmark(&mut group);
 }
 
-criterion_group!(benches, benchmark);
+fn corpus_benchmark(c: &mut Criterion) {
+    let corpora = corpus::corpora();
+    let mut group = c.benchmark_group("corpus");
+    group.warm_up_time(Duration::from_secs(1));
+    corpus::benchmark(&mut group, &corpora);
+}
+
+criterion_group!(benches, benchmark, corpus_benchmark);
 criterion_main!(benches);
@@ -0,0 +1,189 @@
+//! Benchmarks measuring the memory retained by a `Symbolizer` after
+//! symbolizing addresses in one of the available corpora.
+//!
+//! These benchmarks live in a separate target, because they require a
+//! counting global allocator that would skew all other measurements.
+
+#![allow(clippy::let_and_return, clippy::let_unit_value)]
+
+#[allow(dead_code)]
+#[path = "corpus.rs"]
+mod corpus;
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::sync::atomic::AtomicIsize;
+use std::sync::atomic::Ordering;
+
+use blazesym::symbolize::Input;
+use blazesym::symbolize::Symbolizer;
+
+use criterion::black_box;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::measurement::Measurement;
+use criterion::measurement::ValueFormatter;
+use criterion::BenchmarkGroup;
+use criterion::Criterion;
+use criterion::Throughput;
+
+use crate::corpus::Corpus;
+
+
+const BENCH_NAME_WIDTH: usize = 42;
+
+
+/// The number of bytes currently allocated on the heap.
+static ALLOCATED: AtomicIsize = AtomicIsize::new(0);
+
+
+/// A global allocator keeping track of the number of bytes allocated.
+struct CountingAlloc;
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let _prev = ALLOCATED.fetch_add(layout.size() as isize, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let () = unsafe { System.dealloc(ptr, layout) };
+        let _prev = ALLOCATED.fetch_sub(layout.size() as isize, Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new.is_null() {
+            let diff = new_size as isize - layout.size() as isize;
+            let _prev = ALLOCATED.fetch_add(diff, Ordering::Relaxed);
+        }
+        new
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+
+/// A criterion measurement reporting the growth of the heap, in bytes.
+///
+/// Note that memory mapped files are not accounted for.
+pub struct HeapBytes;
+
+impl Measurement for HeapBytes {
+    type Intermediate = isize;
+    type Value = f64;
+
+    fn start(&self) -> Self::Intermediate {
+        ALLOCATED.load(Ordering::Relaxed)
+    }
+
+    fn end(&self, start: Self::Intermediate) -> Self::Value {
+        (ALLOCATED.load(Ordering::Relaxed) - start) as f64
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1 + v2
+    }
+
+    fn zero(&self) -> Self::Value {
+        0.0
+    }
+
+    fn to_f64(&self, value: &Self::Value) -> f64 {
+        *value
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &BytesFormatter
+    }
+}
+
+
+struct BytesFormatter;
+
+impl BytesFormatter {
+    fn unit(typical: f64) -> (f64, &'static str) {
+        match typical.abs() {
+            x if x < 1024.0 => (1.0, "B"),
+            x if x < 1024.0 * 1024.0 => (1.0 / 1024.0, "KiB"),
+            _ => (1.0 / (1024.0 * 1024.0), "MiB"),
+        }
+    }
+}
+
+impl ValueFormatter for BytesFormatter {
+    fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+        let (factor, unit) = Self::unit(typical_value);
+        let () = values.iter_mut().for_each(|value| *value *= factor);
+        unit
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        let (unit, elements) = match throughput {
+            Throughput::Bytes(n) | Throughput::BytesDecimal(n) => ("B/B", *n),
+            Throughput::Elements(n) => ("B/elem", *n),
+        };
+        let () = values
+            .iter_mut()
+            .for_each(|value| *value /= elements as f64);
+        unit
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "B"
+    }
+}
+
+
+/// Symbolize all of a corpus' addresses and measure the memory retained
+/// by the `Symbolizer` afterwards.
+fn retained_memory(group: &mut BenchmarkGroup<'_, HeapBytes>, corpus: &Corpus) {
+    group.bench_function(
+        crate::bench_fn_name(&format!("retained_{}", corpus.name)),
+        |b| {
+            b.iter_with_large_drop(|| {
+                let symbolizer = Symbolizer::new();
+                let results = symbolizer
+                    .symbolize(
+                        black_box(&corpus.src),
+                        black_box(Input::VirtOffset(&corpus.addrs)),
+                    )
+                    .unwrap();
+                let () = drop(results);
+                symbolizer
+            })
+        },
+    );
+}
+
+
+fn bench_fn_name(name: &str) -> String {
+    format!("{name:<BENCH_NAME_WIDTH$}")
+}
+
+
+fn benchmark(c: &mut Criterion<HeapBytes>) {
+    let corpora = corpus::corpora();
+    let mut group = c.benchmark_group("memory");
+    group.sample_size(10);
+    for corpus in &corpora {
+        let () = retained_memory(&mut group, corpus);
+    }
+}
+
+criterion_group!(
+    name = memory;
+    config = Criterion::default().with_measurement(HeapBytes);
+    targets = benchmark
+);
+criterion_main!(memory);
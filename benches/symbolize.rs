@@ -155,6 +155,33 @@ where
     });
 }
 
+/// Symbolize a large batch of addresses in a DWARF file using multiple
+/// threads, on a warm symbolizer.
+fn symbolize_dwarf_par_no_setup<M>(b: &mut Bencher<'_, M>)
+where
+    M: Measurement,
+{
+    let dwarf_vmlinux = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("vmlinux-5.17.12-100.fc34.x86_64.dwarf");
+    let src = Source::Elf(Elf::new(dwarf_vmlinux));
+    let symbolizer = Symbolizer::new();
+    let addrs = (0..100_000)
+        .map(|i| 0xffffffff81000000 + i * 0x100)
+        .collect::<Vec<Addr>>();
+    // Warm up the caches.
+    let _result = symbolizer
+        .symbolize_par(&src, Input::VirtOffset(&addrs))
+        .unwrap();
+
+    let () = b.iter(|| {
+        let result = symbolizer
+            .symbolize_par(black_box(&src), black_box(Input::VirtOffset(&addrs)))
+            .unwrap();
+        let _result = black_box(result);
+    });
+}
+
 
 pub fn benchmark<M>(group: &mut BenchmarkGroup<'_, M>)
 where
@@ -166,4 +193,5 @@ where
     bench_fn!(group, symbolize_dwarf);
     bench_fn!(group, symbolize_gsym);
     bench_sub_fn!(group, symbolize_gsym_multi_no_setup);
+    bench_sub_fn!(group, symbolize_dwarf_par_no_setup);
 }
@@ -6,6 +6,7 @@ use std::ffi::OsString;
 use std::fs::copy;
 use std::fs::create_dir_all;
 use std::fs::hard_link;
+use std::fs::remove_file;
 use std::io::Error;
 use std::io::ErrorKind;
 use std::io::Result;
@@ -49,12 +50,23 @@ where
 
 /// Run a command with the provided arguments.
 fn run<C, A, S>(command: C, args: A) -> Result<()>
+where
+    C: AsRef<OsStr>,
+    A: IntoIterator<Item = S> + Clone,
+    S: AsRef<OsStr>,
+{
+    run_in(Path::new("."), command, args)
+}
+
+/// Run a command with the provided arguments in directory `dir`.
+fn run_in<C, A, S>(dir: &Path, command: C, args: A) -> Result<()>
 where
     C: AsRef<OsStr>,
     A: IntoIterator<Item = S> + Clone,
     S: AsRef<OsStr>,
 {
     let instance = Command::new(command.as_ref())
+        .current_dir(dir)
         .stdin(Stdio::null())
         .stdout(Stdio::null())
         .env_clear()
@@ -153,11 +165,63 @@ fn cc(src: &Path, dst: &str, options: &[&str]) {
     compile("cc", src, dst, options)
 }
 
+/// Compile `src` into `dst` using `cc`, from within the directory
+/// containing `src`.
+///
+/// Compared to [`cc`], the source file will reside in the compilation
+/// directory, meaning that debug information will reference it
+/// relative to said directory.
+fn cc_in_dir(src: &Path, dst: &str, options: &[&str]) {
+    let dir = src.parent().unwrap();
+    let dst = src.with_file_name(dst);
+    println!("cargo:rerun-if-changed={}", src.display());
+    println!("cargo:rerun-if-changed={}", dst.display());
+
+    let () = run_in(
+        dir,
+        "cc",
+        options.iter().map(OsStr::new).chain([
+            src.file_name().unwrap(),
+            "-o".as_ref(),
+            dst.file_name().unwrap(),
+        ]),
+    )
+    .unwrap_or_else(|err| panic!("failed to run `cc`: {err}"));
+
+    let () = adjust_mtime(&dst).unwrap();
+}
+
 /// Compile `src` into `dst` using `rustc`.
 fn rustc(src: &Path, dst: &str, options: &[&str]) {
     compile("rustc", src, dst, options)
 }
 
+/// Compile the LLVM IR in `src` into an object file `dst` for the
+/// target `triple` using `llc`.
+fn llc(src: &Path, dst: impl AsRef<OsStr>, triple: &str) {
+    let dst = src.with_file_name(dst);
+    println!("cargo:rerun-if-changed={}", src.display());
+    println!("cargo:rerun-if-changed={}", dst.display());
+    println!("cargo:rerun-if-env-changed=LLC");
+
+    let llc = env::var_os("LLC").unwrap_or_else(|| OsString::from("llc"));
+    let triple = format!("-mtriple={triple}");
+
+    let () = run(
+        llc,
+        [
+            OsStr::new(&triple),
+            OsStr::new("-filetype=obj"),
+            src.as_os_str(),
+            OsStr::new("-o"),
+            dst.as_os_str(),
+        ],
+    )
+    .expect("failed to run `llc`");
+
+    let () = adjust_mtime(&dst).unwrap();
+}
+
 /// Convert debug information contained in `src` into GSYM in `dst` using
 /// `llvm-gsymutil`.
 fn gsym(src: &Path, dst: impl AsRef<OsStr>) {
@@ -205,6 +269,103 @@ fn dwarf(src: &Path, dst: impl AsRef<OsStr>) {
     strip(src, dst, &["--keep-section=.debug_*"])
 }
 
+/// Create a copy of `src` at `dst`, with its debug sections compressed
+/// using the given compression algorithm.
+fn compress_debug(src: &Path, dst: impl AsRef<OsStr>, algorithm: &str) {
+    let dst = src.with_file_name(dst);
+    println!("cargo:rerun-if-changed={}", src.display());
+    println!("cargo:rerun-if-changed={}", dst.display());
+
+    let compress = format!("--compress-debug-sections={algorithm}");
+    let () = run(
+        "objcopy",
+        [OsStr::new(&compress), src.as_os_str(), dst.as_os_str()],
+    )
+    .expect("failed to run `objcopy`");
+
+    let () = adjust_mtime(&dst).unwrap();
+}
+
+/// Package the split DWARF files referenced by `src` into the DWARF
+/// package `dst`, removing the `.dwo` files `dwos` afterwards.
+fn dwp(src: &Path, dst: impl AsRef<OsStr>, dwos: &[&str]) {
+    let dst = src.with_file_name(dst);
+    println!("cargo:rerun-if-changed={}", src.display());
+    println!("cargo:rerun-if-changed={}", dst.display());
+
+    let () = run(
+        "dwp",
+        [
+            OsStr::new("-e"),
+            src.as_os_str(),
+            OsStr::new("-o"),
+            dst.as_os_str(),
+        ],
+    )
+    .expect("failed to run `dwp`");
+
+    for dwo in dwos {
+        let () = remove_file(src.with_file_name(dwo)).unwrap();
+    }
+    let () = adjust_mtime(&dst).unwrap();
+}
+
+/// Split the debug information of `src` into a separate file named
+/// `debug`, along with a stripped copy named `dst` referencing it via
+/// a `.gnu_debuglink` section.
+fn debug_link(src: &Path, debug: impl AsRef<OsStr>, dst: impl AsRef<OsStr>) {
+    let debug = src.with_file_name(debug);
+    let dst = src.with_file_name(dst);
+    println!("cargo:rerun-if-changed={}", src.display());
+    println!("cargo:rerun-if-changed={}", debug.display());
+    println!("cargo:rerun-if-changed={}", dst.display());
+
+    let () = run(
+        "objcopy",
+        [
+            OsStr::new("--only-keep-debug"),
+            src.as_os_str(),
+            debug.as_os_str(),
+        ],
+    )
+    .expect("failed to run `objcopy`");
+
+    let mut link = OsString::from("--add-gnu-debuglink=");
+    let () = link.push(debug.as_os_str());
+    let () = run(
+        "objcopy",
+        [
+            OsStr::new("--strip-debug"),
+            link.as_os_str(),
+            src.as_os_str(),
+            dst.as_os_str(),
+        ],
+    )
+    .expect("failed to run `objcopy`");
+
+    let () = adjust_mtime(&debug).unwrap();
+    let () = adjust_mtime(&dst).unwrap();
+}
+
+/// Put object files into a static archive using `ar`.
+fn ar(files: &[PathBuf], dst: &Path) {
+    for file in files {
+        println!("cargo:rerun-if-changed={}", file.display());
+    }
+    println!("cargo:rerun-if-changed={}", dst.display());
+
+    // `ar` would otherwise add to an existing archive.
+    let _result = remove_file(dst);
+    let () = run(
+        "ar",
+        [OsStr::new("rcs"), dst.as_os_str()]
+            .into_iter()
+            .chain(files.iter().map(|file| file.as_os_str())),
+    )
+    .expect("failed to run `ar`");
+    let () = adjust_mtime(dst).unwrap();
+}
+
 /// Unpack an xz compressed file.
 #[cfg(feature = "xz2")]
 fn unpack_xz(src: &Path, dst: &Path) {
@@ -235,6 +396,36 @@ fn unpack_xz(_src: &Path, _dst: &Path) {
     unimplemented!()
 }
 
+/// Compress a file using xz.
+#[cfg(feature = "xz2")]
+fn pack_xz(src: &Path, dst: &Path) {
+    use std::fs::File;
+    use std::io::copy;
+    use xz2::read::XzEncoder;
+
+    println!("cargo:rerun-if-changed={}", src.display());
+    println!("cargo:rerun-if-changed={}", dst.display());
+
+    let src_file = File::options().create(false).read(true).open(src).unwrap();
+    let mut encoder = XzEncoder::new(src_file, 6);
+
+    let mut dst_file = File::options()
+        .create(true)
+        .truncate(true)
+        .read(false)
+        .write(true)
+        .open(dst)
+        .unwrap();
+
+    let _bytes = copy(&mut encoder, &mut dst_file).unwrap();
+    let () = adjust_mtime(dst).unwrap();
+}
+
+#[cfg(not(feature = "xz2"))]
+fn pack_xz(_src: &Path, _dst: &Path) {
+    unimplemented!()
+}
+
 
 /// Put files in a zip archive, uncompressed.
 #[cfg(feature = "zip")]
@@ -313,6 +504,29 @@ fn prepare_test_files(crate_root: &Path) {
             "symbol-mangling-version=v0",
         ],
     );
+    // LLVM based compilers (such as `clang` and `rustc`) default to
+    // DWARF 5 with string and address indices
+    // (`.debug_str_offsets`, `.debug_addr`) as well as line tables
+    // referencing `.debug_line_str`.
+    rustc(
+        &src,
+        "test-rs-dwarf-v5.bin",
+        &[
+            "--crate-type=bin",
+            "-C",
+            "panic=abort",
+            "-C",
+            "link-arg=-nostartfiles",
+            "-C",
+            "opt-level=0",
+            "-C",
+            "debuginfo=2",
+            "-C",
+            "dwarf-version=5",
+            "-C",
+            "symbol-mangling-version=v0",
+        ],
+    );
 
     let src = crate_root.join("data").join("test-so.c");
     cc(
@@ -325,13 +539,51 @@ fn prepare_test_files(crate_root: &Path) {
         "libtest-so-no-separate-code.so",
         &["-shared", "-fPIC", "-Wl,--build-id=md5,-z,noseparate-code"],
     );
+    cc(
+        &src,
+        "libtest-so-dwarf.so",
+        &["-shared", "-fPIC", "-gdwarf-4", "-Wl,--build-id=sha1"],
+    );
+    let so = crate_root.join("data").join("libtest-so-dwarf.so");
+    elf(&so, "libtest-so-dwarf-stripped.so");
+    debug_link(
+        &so,
+        "libtest-so-dwarf.debug",
+        "libtest-so-dwarf-debuglink.so",
+    );
 
     let src = crate_root.join("data").join("test-exe.c");
     cc(&src, "test-no-debug.bin", &["-g0", "-Wl,--build-id=none"]);
     cc(&src, "test-dwarf-v2.bin", &["-gstrict-dwarf", "-gdwarf-2"]);
     cc(&src, "test-dwarf-v3.bin", &["-gstrict-dwarf", "-gdwarf-3"]);
     cc(&src, "test-dwarf-v4.bin", &["-gstrict-dwarf", "-gdwarf-4"]);
+    let bin = crate_root.join("data").join("test-dwarf-v4.bin");
+    compress_debug(&bin, "test-dwarf-v4-zlib.bin", "zlib");
+    compress_debug(&bin, "test-dwarf-v4-zstd.bin", "zstd");
     cc(&src, "test-dwarf-v5.bin", &["-gstrict-dwarf", "-gdwarf-5"]);
+    cc_in_dir(&src, "test-dwarf-v4-comp-dir.bin", &["-gdwarf-4"]);
+    cc_in_dir(&src, "test-dwarf-v5-comp-dir.bin", &["-gdwarf-5"]);
+    cc(
+        &src,
+        "test-dwarf-v4-split.bin",
+        &["-gsplit-dwarf", "-gdwarf-4"],
+    );
+    cc_in_dir(
+        &src,
+        "test-dwarf-v5-split.bin",
+        &["-gsplit-dwarf", "-gdwarf-5"],
+    );
+    cc(
+        &src,
+        "test-dwarf-v4-dwp.bin",
+        &["-gsplit-dwarf", "-gdwarf-4"],
+    );
+    let bin = crate_root.join("data").join("test-dwarf-v4-dwp.bin");
+    dwp(
+        &bin,
+        "test-dwarf-v4-dwp.bin.dwp",
+        &["test-dwarf-v4-dwp.bin-test-exe.dwo"],
+    );
 
     let src = crate_root.join("data").join("test-stable-addresses.c");
     let src_cu2 = crate_root.join("data").join("test-stable-addresses-cu2.c");
@@ -353,6 +605,8 @@ fn prepare_test_files(crate_root: &Path) {
             src_cu2,
         ],
     );
+    let map = crate_root.join("data").join("test-stable-addresses.map");
+    let map = format!("-Wl,-Map={}", map.to_str().unwrap());
     cc(
         &src,
         "test-stable-addresses-no-dwarf.bin",
@@ -361,6 +615,7 @@ fn prepare_test_files(crate_root: &Path) {
             "-T",
             ld_script,
             "-Wl,--build-id=none",
+            &map,
             "-O0",
             "-nostdlib",
             // TODO: Eventually we may want to make `cc` multi-input-file aware.
@@ -368,11 +623,34 @@ fn prepare_test_files(crate_root: &Path) {
         ],
     );
 
+    let options = ["-c", "-gdwarf-4", "-O0"];
+    cc(&src, "test-stable-addresses.o", &options);
+    cc(Path::new(src_cu2), "test-stable-addresses-cu2.o", &options);
+    let obj = crate_root.join("data").join("test-stable-addresses-cu2.o");
+    compress_debug(&obj, "test-stable-addresses-cu2-zlib.o", "zlib");
+    let files = [
+        crate_root.join("data").join("test-stable-addresses.o"),
+        crate_root.join("data").join("test-stable-addresses-cu2.o"),
+    ];
+    let dst = crate_root.join("data").join("libtest-stable-addresses.a");
+    ar(files.as_slice(), &dst);
+
     let src = crate_root.join("data").join("test-stable-addresses.bin");
     gsym(&src, "test-stable-addresses.gsym");
     dwarf(&src, "test-stable-addresses-dwarf-only.bin");
     strip(&src, "test-stable-addresses-stripped.bin", &[]);
 
+    let src = crate_root.join("data").join("test-cross-arch.ll");
+    llc(&src, "test-cross-arch-aarch64.o", "aarch64-linux-gnu");
+    llc(&src, "test-cross-arch-s390x.o", "s390x-linux-gnu");
+
+    let src = crate_root.join("data").join("test-kmod.c");
+    cc(&src, "test-kmod.ko", &["-c", "-gdwarf-4", "-O0"]);
+    let src = crate_root.join("data").join("test-kmod.ko");
+    let mut dst = src.clone();
+    assert!(dst.set_extension("ko.xz"));
+    pack_xz(&src, &dst);
+
     let src = crate_root.join("data").join("kallsyms.xz");
     let mut dst = src.clone();
     assert!(dst.set_extension(""));
@@ -412,6 +690,67 @@ fn prepare_test_files(crate_root: &Path) {
     zip(files.as_slice(), &dst);
 }
 
+/// A file to download, pinned to the SHA-256 digest of its contents.
+#[cfg(feature = "reqwest")]
+struct PinnedFile {
+    /// The name of the file inside of the `data/` directory.
+    name: String,
+    /// The expected SHA-256 digest, in hexadecimal form.
+    sha256: String,
+    /// The URL to download the file from.
+    url: String,
+}
+
+/// Parse the manifest of pinned benchmark corpora, `data/corpora.txt`.
+#[cfg(feature = "reqwest")]
+fn pinned_files(crate_root: &Path) -> Vec<PinnedFile> {
+    use std::fs::read_to_string;
+
+    let manifest = crate_root.join("data").join("corpora.txt");
+    println!("cargo:rerun-if-changed={}", manifest.display());
+
+    let contents = read_to_string(&manifest).unwrap();
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next(), fields.next(), fields.next()) {
+                (Some(name), Some(sha256), Some(url), None) => PinnedFile {
+                    name: name.to_string(),
+                    sha256: sha256.to_ascii_lowercase(),
+                    url: url.to_string(),
+                },
+                _ => panic!("malformed line in {}: `{line}`", manifest.display()),
+            }
+        })
+        .collect()
+}
+
+/// Calculate the SHA-256 digest of the file at `path` using
+/// `sha256sum`.
+#[cfg(feature = "reqwest")]
+fn sha256(path: &Path) -> String {
+    let output = Command::new("sha256sum")
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .expect("failed to run `sha256sum`");
+    assert!(
+        output.status.success(),
+        "`sha256sum` failed on {}",
+        path.display()
+    );
+
+    let output = String::from_utf8(output.stdout).unwrap();
+    output
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
 /// Download a multi-part file split into `part_count` pieces.
 #[cfg(feature = "reqwest")]
 fn download_multi_part(base_url: &reqwest::Url, part_count: usize, dst: &Path) {
@@ -421,11 +760,40 @@ fn download_multi_part(base_url: &reqwest::Url, part_count: usize, dst: &Path) {
     let mut dst = File::create(dst).unwrap();
     for part in 1..=part_count {
         let url = reqwest::Url::parse(&format!("{}.part{part}", base_url.as_str())).unwrap();
-        let response = reqwest::blocking::get(url).unwrap();
-        let _count = dst.write(&response.bytes().unwrap()).unwrap();
+        let response = reqwest::blocking::get(url)
+            .and_then(|response| response.error_for_status())
+            .unwrap();
+        let () = dst.write_all(&response.bytes().unwrap()).unwrap();
     }
 }
 
+/// Download `file` into the `data/` directory, unless it is present
+/// already, and verify its digest.
+#[cfg(feature = "reqwest")]
+fn download_pinned(crate_root: &Path, file: &PinnedFile) {
+    use std::fs::write;
+
+    let dst = crate_root.join("data").join(&file.name);
+    if dst.exists() && sha256(&dst) == file.sha256 {
+        return
+    }
+
+    let response = reqwest::blocking::get(&file.url)
+        .and_then(|response| response.error_for_status())
+        .unwrap_or_else(|err| panic!("failed to download {}: {err}", file.url));
+    let () = write(&dst, response.bytes().unwrap()).unwrap();
+
+    let digest = sha256(&dst);
+    if digest != file.sha256 {
+        let _result = remove_file(&dst);
+        panic!(
+            "{} has SHA-256 digest {digest}, but {} is pinned",
+            file.url, file.sha256
+        )
+    }
+    let () = adjust_mtime(&dst).unwrap();
+}
+
 /// Download large benchmark related files for later use.
 #[cfg(feature = "reqwest")]
 fn download_bench_files(crate_root: &Path) {
@@ -437,6 +805,10 @@ fn download_bench_files(crate_root: &Path) {
     let dst = crate_root.join("data").join(file);
     let () = download_multi_part(&large_file_url.join(file).unwrap(), 2, &dst);
     let () = adjust_mtime(&dst).unwrap();
+
+    for file in pinned_files(crate_root) {
+        let () = download_pinned(crate_root, &file);
+    }
 }
 
 #[cfg(not(feature = "reqwest"))]
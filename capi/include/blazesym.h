@@ -7,6 +7,143 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+/**
+ * An enum providing a rough classification of errors.
+ *
+ * C ABI compatible version of [`blazesym::ErrorKind`].
+ */
+enum blaze_err
+#ifdef __cplusplus
+  : int16_t
+#endif // __cplusplus
+ {
+  /**
+   * The operation was successful.
+   */
+  BLAZE_ERR_OK = 0,
+  /**
+   * An entity was not found, often a file.
+   */
+  BLAZE_ERR_NOT_FOUND = -2,
+  /**
+   * The operation lacked the necessary privileges to complete.
+   */
+  BLAZE_ERR_PERMISSION_DENIED = -1,
+  /**
+   * An entity already exists, often a file.
+   */
+  BLAZE_ERR_ALREADY_EXISTS = -17,
+  /**
+   * The operation needs to block to complete, but the blocking
+   * operation was requested to not occur.
+   */
+  BLAZE_ERR_WOULD_BLOCK = -11,
+  /**
+   * Data not valid for the operation were encountered.
+   */
+  BLAZE_ERR_INVALID_DATA = -22,
+  /**
+   * The I/O operation's timeout expired, causing it to be canceled.
+   */
+  BLAZE_ERR_TIMED_OUT = -110,
+  /**
+   * This operation is unsupported on this platform.
+   */
+  BLAZE_ERR_UNSUPPORTED = -95,
+  /**
+   * An operation could not be completed, because it failed
+   * to allocate enough memory.
+   */
+  BLAZE_ERR_OUT_OF_MEMORY = -12,
+  /**
+   * This operation was interrupted.
+   */
+  BLAZE_ERR_INTERRUPTED = -4,
+  /**
+   * A parameter was incorrect.
+   */
+  BLAZE_ERR_INVALID_INPUT = -256,
+  /**
+   * An error returned when an operation could not be completed
+   * because a call to `write` returned `Ok(0)`.
+   */
+  BLAZE_ERR_WRITE_ZERO = -257,
+  /**
+   * An error returned when an operation could not be completed
+   * because an "end of file" was reached prematurely.
+   */
+  BLAZE_ERR_UNEXPECTED_EOF = -258,
+  /**
+   * DWARF input data was invalid.
+   */
+  BLAZE_ERR_INVALID_DWARF = -259,
+  /**
+   * A custom error that does not fall under any other I/O error
+   * kind.
+   */
+  BLAZE_ERR_OTHER = -260,
+  /**
+   * The library encountered an internal error and had to abort the
+   * operation. This is always a bug in the library.
+   */
+  BLAZE_ERR_PANIC = -261,
+  /**
+   * The input is not in a format supported by the operation.
+   */
+  BLAZE_ERR_UNSUPPORTED_FORMAT = -262,
+  /**
+   * The build ID of a file did not match the expected one.
+   */
+  BLAZE_ERR_BUILD_ID_MISMATCH = -263,
+  /**
+   * Entering or leaving a Linux namespace failed.
+   */
+  BLAZE_ERR_NAMESPACE_FAILURE = -264,
+};
+#ifndef __cplusplus
+typedef int16_t blaze_err;
+#endif // __cplusplus
+
+/**
+ * The valid variant kind in [`blaze_kernel_meta`].
+ */
+typedef enum blaze_kernel_meta_kind {
+  /**
+   * [`blaze_kernel_meta_variant::unknown`] is valid.
+   */
+  BLAZE_KERNEL_META_UNKNOWN,
+  /**
+   * [`blaze_kernel_meta_variant::module`] is valid.
+   */
+  BLAZE_KERNEL_META_MODULE,
+} blaze_kernel_meta_kind;
+
+/**
+ * The way in which files of a process residing in a different mount
+ * namespace are accessed.
+ *
+ * C ABI compatible version of [`MountNsMode`].
+ */
+typedef enum blaze_mount_ns_mode {
+  /**
+   * Access files via `/proc/<pid>/root/`, leaving the mount
+   * namespace of the calling process untouched.
+   *
+   * This mode is safe to use from multi-threaded programs. Absolute
+   * symbolic links underneath `/proc/<pid>/root/` are resolved
+   * against the root directory of the calling process, though.
+   */
+  BLAZE_MOUNT_NS_MODE_PROC_ROOT,
+  /**
+   * Temporarily enter the mount namespace of the process using
+   * `setns(2)`.
+   *
+   * Doing so affects the calling process as a whole and fails if
+   * the process is multi-threaded.
+   */
+  BLAZE_MOUNT_NS_MODE_ENTER,
+} blaze_mount_ns_mode;
+
 /**
  * The type of a symbol.
  */
@@ -42,6 +179,14 @@ typedef enum blaze_user_meta_kind {
    * [`blaze_user_meta_variant::elf`] is valid.
    */
   BLAZE_USER_META_ELF,
+  /**
+   * [`blaze_user_meta_variant::macho`] is valid.
+   */
+  BLAZE_USER_META_MACHO,
+  /**
+   * [`blaze_user_meta_variant::pe`] is valid.
+   */
+  BLAZE_USER_META_PE,
 } blaze_user_meta_kind;
 
 /**
@@ -101,6 +246,24 @@ typedef struct blaze_inspect_elf_src {
  */
 typedef struct blaze_normalizer blaze_normalizer;
 
+/**
+ * Options for configuring `blaze_normalizer` objects.
+ */
+typedef struct blaze_normalizer_opts {
+  /**
+   * Whether to read and report build IDs as part of the
+   * normalization process.
+   */
+  bool build_ids;
+  /**
+   * Whether to compute a fallback identifier for ELF binaries that
+   * lack a GNU build ID.
+   *
+   * This setting only has an effect if `build_ids` is `true`.
+   */
+  bool build_id_fallback;
+} blaze_normalizer_opts;
+
 /**
  * C compatible version of [`Apk`].
  */
@@ -110,6 +273,12 @@ typedef struct blaze_user_meta_apk {
    * This member is always present.
    */
   char *path;
+  /**
+   * The path of the uncompressed ELF file inside the APK that the
+   * address belongs to, relative to the root of the archive. NULL
+   * if the address does not fall into such a file.
+   */
+  char *elf_path;
 } blaze_user_meta_apk;
 
 /**
@@ -130,6 +299,52 @@ typedef struct blaze_user_meta_elf {
   uint8_t *build_id;
 } blaze_user_meta_elf;
 
+/**
+ * C compatible version of [`MachO`].
+ */
+typedef struct blaze_user_meta_macho {
+  /**
+   * The path to the Mach-O file. This member is always present.
+   */
+  char *path;
+  /**
+   * Whether `uuid` contains the UUID of the Mach-O file.
+   */
+  bool has_uuid;
+  /**
+   * The UUID of the Mach-O file, if `has_uuid` is set.
+   */
+  uint8_t uuid[16];
+} blaze_user_meta_macho;
+
+/**
+ * C compatible version of [`Pe`].
+ */
+typedef struct blaze_user_meta_pe {
+  /**
+   * The path to the PE file. This member is always present.
+   */
+  char *path;
+  /**
+   * Whether the `pdb_*` members contain information about the PDB
+   * file referenced by the PE file.
+   */
+  bool has_pdb;
+  /**
+   * The GUID of the PDB file, if `has_pdb` is set.
+   */
+  uint8_t pdb_guid[16];
+  /**
+   * The age of the PDB file, if `has_pdb` is set.
+   */
+  uint32_t pdb_age;
+  /**
+   * The path to the PDB file, as recorded by the linker. NULL if
+   * `has_pdb` is not set.
+   */
+  char *pdb_path;
+} blaze_user_meta_pe;
+
 /**
  * C compatible version of [`Unknown`].
  */
@@ -152,6 +367,14 @@ typedef union blaze_user_meta_variant {
    * Valid on [`blaze_user_meta_kind::BLAZE_USER_META_ELF`].
    */
   struct blaze_user_meta_elf elf;
+  /**
+   * Valid on [`blaze_user_meta_kind::BLAZE_USER_META_MACHO`].
+   */
+  struct blaze_user_meta_macho macho;
+  /**
+   * Valid on [`blaze_user_meta_kind::BLAZE_USER_META_PE`].
+   */
+  struct blaze_user_meta_pe pe;
   /**
    * Valid on [`blaze_user_meta_kind::BLAZE_USER_META_UNKNOWN`].
    */
@@ -212,6 +435,77 @@ typedef struct blaze_normalized_user_output {
   struct blaze_normalized_output *outputs;
 } blaze_normalized_user_output;
 
+/**
+ * C compatible version of [`KernelModule`].
+ */
+typedef struct blaze_kernel_meta_module {
+  /**
+   * The name of the module, as reported in `/proc/modules`. This
+   * member is always present.
+   */
+  char *name;
+  /**
+   * The length of the build ID, in bytes.
+   */
+  size_t build_id_len;
+  /**
+   * The optional build ID of the module, if found.
+   */
+  uint8_t *build_id;
+} blaze_kernel_meta_module;
+
+/**
+ * The actual variant data in [`blaze_kernel_meta`].
+ */
+typedef union blaze_kernel_meta_variant {
+  /**
+   * Valid on [`blaze_kernel_meta_kind::BLAZE_KERNEL_META_MODULE`].
+   */
+  struct blaze_kernel_meta_module module;
+  /**
+   * Valid on [`blaze_kernel_meta_kind::BLAZE_KERNEL_META_UNKNOWN`].
+   */
+  struct blaze_user_meta_unknown unknown;
+} blaze_kernel_meta_variant;
+
+/**
+ * C ABI compatible version of [`KernelMeta`].
+ */
+typedef struct blaze_kernel_meta {
+  /**
+   * The variant kind that is present.
+   */
+  enum blaze_kernel_meta_kind kind;
+  /**
+   * The actual variant with its data.
+   */
+  union blaze_kernel_meta_variant variant;
+} blaze_kernel_meta;
+
+/**
+ * An object representing normalized kernel addresses.
+ *
+ * C ABI compatible version of [`KernelOutput`].
+ */
+typedef struct blaze_normalized_kernel_output {
+  /**
+   * The number of [`blaze_kernel_meta`] objects present in `metas`.
+   */
+  size_t meta_cnt;
+  /**
+   * An array of `meta_cnt` objects.
+   */
+  struct blaze_kernel_meta *metas;
+  /**
+   * The number of [`blaze_normalized_output`] objects present in `outputs`.
+   */
+  size_t output_cnt;
+  /**
+   * An array of `output_cnt` objects.
+   */
+  struct blaze_normalized_output *outputs;
+} blaze_normalized_kernel_output;
+
 /**
  * C ABI compatible version of [`blazesym::symbolize::Symbolizer`].
  *
@@ -238,10 +532,20 @@ typedef struct blaze_symbolizer_opts {
    * Whether or not to transparently demangle symbols.
    *
    * Demangling happens on a best-effort basis. Currently supported
-   * languages are Rust and C++ and the flag will have no effect if
-   * the underlying language does not mangle symbols (such as C).
+   * languages are Rust, C++, D, Swift, and Ada and the flag will have
+   * no effect if the underlying language does not mangle symbols
+   * (such as C).
    */
   bool demangle;
+  /**
+   * How to access files of processes residing in a different mount
+   * namespace, as relevant for process symbolization (e.g., using
+   * [`blaze_symbolize_process_virt_addrs`]).
+   *
+   * Multi-threaded programs must not use
+   * [`blaze_mount_ns_mode::BLAZE_MOUNT_NS_MODE_ENTER`].
+   */
+  enum blaze_mount_ns_mode mount_ns_mode;
 } blaze_symbolizer_opts;
 
 /**
@@ -331,6 +635,24 @@ typedef struct blaze_sym {
    * An array of `inlined_cnt` symbolized inlined function calls.
    */
   const struct blaze_symbolize_inlined_fn *inlined;
+  /**
+   * The path to the module in which the symbol was found, or NULL
+   * if unknown.
+   */
+  const char *module;
+  /**
+   * The length of the build ID, in bytes.
+   */
+  size_t build_id_len;
+  /**
+   * The build ID of the module, or NULL if not available.
+   */
+  const uint8_t *build_id;
+  /**
+   * The offset of the symbolized address inside the module's file,
+   * or 0 if it could not be determined.
+   */
+  uint64_t file_offset;
 } blaze_sym;
 
 /**
@@ -404,6 +726,22 @@ typedef struct blaze_symbolize_src_kernel {
    * to satisfy the request (if present).
    */
   bool debug_syms;
+  /**
+   * The path of a copy of `/proc/modules`, listing the loaded kernel
+   * modules.
+   *
+   * Passing `NULL` will result in `"/proc/modules"` if `kallsyms` is
+   * `NULL` as well and kernel modules being ignored otherwise.
+   */
+  const char *modules;
+  /**
+   * The path of the directory containing kernel module files and a
+   * `modules.dep` file.
+   *
+   * Passing `NULL` will result in `"/lib/modules/<release>/"` of the
+   * running kernel.
+   */
+  const char *module_dir;
 } blaze_symbolize_src_kernel;
 
 /**
@@ -457,6 +795,22 @@ typedef struct blaze_symbolize_src_gsym_file {
 extern "C" {
 #endif // __cplusplus
 
+/**
+ * Retrieve the error reported by the last fallible API function invoked
+ * on the calling thread.
+ *
+ * Functions reporting errors document this fact. Errors are typically
+ * signaled by the function returning `NULL`.
+ */
+blaze_err blaze_err_last(void);
+
+/**
+ * Retrieve a textual representation of the error code.
+ *
+ * The returned string is statically allocated and must not be freed.
+ */
+const char *blaze_err_str(blaze_err err);
+
 /**
  * Lookup symbol information in an ELF file.
  *
@@ -469,6 +823,9 @@ extern "C" {
  *
  * The returned pointer should be freed by [`blaze_inspect_syms_free`].
  *
+ * On error, `NULL` is returned and `blaze_err_last` can be used to
+ * retrieve the reason.
+ *
  * # Safety
  * The `inspector` object should have been created using
  * [`blaze_inspector_new`], `src` needs to point to a valid object, and `names`
@@ -517,6 +874,18 @@ void blaze_inspector_free(blaze_inspector *inspector);
  */
 blaze_normalizer *blaze_normalizer_new(void);
 
+/**
+ * Create an instance of a blazesym normalizer with configurable
+ * options.
+ *
+ * The returned pointer should be released using
+ * [`blaze_normalizer_free`] once it is no longer needed.
+ *
+ * # Safety
+ * `opts` needs to be a valid pointer.
+ */
+blaze_normalizer *blaze_normalizer_new_opts(const struct blaze_normalizer_opts *opts);
+
 /**
  * Free a blazesym normalizer.
  *
@@ -525,7 +894,7 @@ blaze_normalizer *blaze_normalizer_new(void);
  *
  * # Safety
  * The provided normalizer should have been created by
- * [`blaze_normalizer_new`].
+ * [`blaze_normalizer_new`] or [`blaze_normalizer_new_opts`].
  */
 void blaze_normalizer_free(blaze_normalizer *normalizer);
 
@@ -539,7 +908,8 @@ void blaze_normalizer_free(blaze_normalizer *normalizer);
  * result in slightly faster normalization.
  *
  * C ABI compatible version of [`Normalizer::normalize_user_addrs`].
- * Returns `NULL` on error. The resulting object should be freed using
+ * Returns `NULL` on error, in which case `blaze_err_last` can be used
+ * to retrieve the reason. The resulting object should be freed using
  * [`blaze_user_output_free`].
  *
  * # Safety
@@ -563,7 +933,8 @@ struct blaze_normalized_user_output *blaze_normalize_user_addrs(const blaze_norm
  * belongs. It may be `0` if they belong to the calling process.
  *
  * C ABI compatible version of [`Normalizer::normalize_user_addrs_sorted`].
- * Returns `NULL` on error. The resulting object should be freed using
+ * Returns `NULL` on error, in which case `blaze_err_last` can be used
+ * to retrieve the reason. The resulting object should be freed using
  * [`blaze_user_output_free`].
  *
  * # Safety
@@ -585,6 +956,36 @@ struct blaze_normalized_user_output *blaze_normalize_user_addrs_sorted(const bla
  */
 void blaze_user_output_free(struct blaze_normalized_user_output *output);
 
+/**
+ * Normalize a list of kernel addresses.
+ *
+ * Addresses belonging to a kernel module are normalized to an offset
+ * relative to the module's base address and reported along with the
+ * module's name and (if enabled) build ID. Other addresses are
+ * reported unchanged, with an associated unknown meta entry.
+ *
+ * C ABI compatible version of [`Normalizer::normalize_kernel_addrs`].
+ * Returns `NULL` on error, in which case `blaze_err_last` can be used
+ * to retrieve the reason. The resulting object should be freed using
+ * [`blaze_kernel_output_free`].
+ *
+ * # Safety
+ * Callers need to pass in a valid `addrs` pointer, pointing to memory of
+ * `addr_cnt` addresses.
+ */
+struct blaze_normalized_kernel_output *blaze_normalize_kernel_addrs(const blaze_normalizer *normalizer,
+                                                                    const uintptr_t *addrs,
+                                                                    size_t addr_cnt);
+
+/**
+ * Free an object as returned by [`blaze_normalize_kernel_addrs`].
+ *
+ * # Safety
+ * The provided object should have been created by
+ * [`blaze_normalize_kernel_addrs`].
+ */
+void blaze_kernel_output_free(struct blaze_normalized_kernel_output *output);
+
 /**
  * Create an instance of a symbolizer.
  */
@@ -615,6 +1016,9 @@ void blaze_symbolizer_free(blaze_symbolizer *symbolizer);
  * number of input addresses. The caller should free the returned array by
  * calling [`blaze_result_free`].
  *
+ * On error, `NULL` is returned and `blaze_err_last` can be used to
+ * retrieve the reason.
+ *
  * # Safety
  * `symbolizer` must have been allocated using [`blaze_symbolizer_new`] or
  * [`blaze_symbolizer_new_opts`]. `src` must point to a valid
@@ -633,6 +1037,9 @@ const struct blaze_result *blaze_symbolize_process_virt_addrs(blaze_symbolizer *
  * number of input addresses. The caller should free the returned array by
  * calling [`blaze_result_free`].
  *
+ * On error, `NULL` is returned and `blaze_err_last` can be used to
+ * retrieve the reason.
+ *
  * # Safety
  * `symbolizer` must have been allocated using [`blaze_symbolizer_new`] or
  * [`blaze_symbolizer_new_opts`]. `src` must point to a valid
@@ -651,6 +1058,9 @@ const struct blaze_result *blaze_symbolize_kernel_virt_addrs(blaze_symbolizer *s
  * number of input addresses. The caller should free the returned array by
  * calling [`blaze_result_free`].
  *
+ * On error, `NULL` is returned and `blaze_err_last` can be used to
+ * retrieve the reason.
+ *
  * # Safety
  * `symbolizer` must have been allocated using [`blaze_symbolizer_new`] or
  * [`blaze_symbolizer_new_opts`]. `src` must point to a valid
@@ -662,6 +1072,31 @@ const struct blaze_result *blaze_symbolize_elf_file_addrs(blaze_symbolizer *symb
                                                           const uintptr_t *addrs,
                                                           size_t addr_cnt);
 
+/**
+ * Symbolize file offsets in an ELF file.
+ *
+ * File offsets are converted to virtual offsets using the ELF file's
+ * program headers. This is useful for symbolizing the output of
+ * address normalization, for example.
+ *
+ * Return an array of [`blaze_result`] with the same size as the
+ * number of input offsets. The caller should free the returned array by
+ * calling [`blaze_result_free`].
+ *
+ * On error, `NULL` is returned and `blaze_err_last` can be used to
+ * retrieve the reason.
+ *
+ * # Safety
+ * `symbolizer` must have been allocated using [`blaze_symbolizer_new`] or
+ * [`blaze_symbolizer_new_opts`]. `src` must point to a valid
+ * [`blaze_symbolize_src_elf`] object. `offsets` must represent an array of
+ * `offset_cnt` objects.
+ */
+const struct blaze_result *blaze_symbolize_elf_file_offsets(blaze_symbolizer *symbolizer,
+                                                            const struct blaze_symbolize_src_elf *src,
+                                                            const uint64_t *offsets,
+                                                            size_t offset_cnt);
+
 /**
  * Symbolize file addresses using "raw" Gsym data.
  *
@@ -669,6 +1104,9 @@ const struct blaze_result *blaze_symbolize_elf_file_addrs(blaze_symbolizer *symb
  * number of input addresses. The caller should free the returned array by
  * calling [`blaze_result_free`].
  *
+ * On error, `NULL` is returned and `blaze_err_last` can be used to
+ * retrieve the reason.
+ *
  * # Safety
  * `symbolizer` must have been allocated using [`blaze_symbolizer_new`] or
  * [`blaze_symbolizer_new_opts`]. `src` must point to a valid
@@ -687,6 +1125,9 @@ const struct blaze_result *blaze_symbolize_gsym_data_file_addrs(blaze_symbolizer
  * number of input addresses. The caller should free the returned array by
  * calling [`blaze_result_free`].
  *
+ * On error, `NULL` is returned and `blaze_err_last` can be used to
+ * retrieve the reason.
+ *
  * # Safety
  * `symbolizer` must have been allocated using [`blaze_symbolizer_new`] or
  * [`blaze_symbolizer_new_opts`]. `src` must point to a valid
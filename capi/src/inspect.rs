@@ -24,7 +24,10 @@ use blazesym::inspect::SymInfo;
 use blazesym::inspect::SymType;
 use blazesym::Addr;
 
+use crate::blaze_err;
+use crate::catch_panic;
 use crate::from_cstr;
+use crate::set_last_err;
 use crate::slice_from_user_array;
 
 
@@ -172,6 +175,7 @@ fn convert_syms_list_to_c(syms_list: Vec<Vec<SymInfo>>) -> *const *const blaze_s
             sym_type,
             file_offset,
             obj_file_name,
+            section: _,
         } in syms
         {
             let name_ptr = str_ptr.cast();
@@ -237,6 +241,9 @@ fn convert_syms_list_to_c(syms_list: Vec<Vec<SymInfo>>) -> *const *const blaze_s
 ///
 /// The returned pointer should be freed by [`blaze_inspect_syms_free`].
 ///
+/// On error, `NULL` is returned and `blaze_err_last` can be used to
+/// retrieve the reason.
+///
 /// # Safety
 /// The `inspector` object should have been created using
 /// [`blaze_inspector_new`], `src` needs to point to a valid object, and `names`
@@ -248,25 +255,41 @@ pub unsafe extern "C" fn blaze_inspect_syms_elf(
     names: *const *const c_char,
     name_cnt: usize,
 ) -> *const *const blaze_sym_info {
-    // SAFETY: The caller ensures that the pointer is valid.
-    let inspector = unsafe { &*inspector };
-    // SAFETY: The caller ensures that the pointer is valid.
-    let src = Source::Elf(Elf::from(unsafe { &*src }));
-    // SAFETY: The caller ensures that the pointer is valid and the count
-    //         matches.
-    let names = unsafe { slice_from_user_array(names, name_cnt) };
-    let names = names
-        .iter()
-        .map(|&p| {
-            // SAFETY: The caller ensures that the pointer is valid.
-            unsafe { CStr::from_ptr(p) }.to_str().unwrap()
-        })
-        .collect::<Vec<_>>();
-    let result = inspector.lookup(&src, &names);
-    match result {
-        Ok(syms) => convert_syms_list_to_c(syms),
-        Err(_err) => ptr::null(),
-    }
+    catch_panic(ptr::null(), || {
+        // SAFETY: The caller ensures that the pointer is valid.
+        let inspector = unsafe { &*inspector };
+        // SAFETY: The caller ensures that the pointer is valid.
+        let src = Source::Elf(Elf::from(unsafe { &*src }));
+        // SAFETY: The caller ensures that the pointer is valid and the count
+        //         matches.
+        let names = unsafe { slice_from_user_array(names, name_cnt) };
+        let names = names
+            .iter()
+            .map(|&p| {
+                // SAFETY: The caller ensures that the pointer is valid.
+                unsafe { CStr::from_ptr(p) }.to_str()
+            })
+            .collect::<Result<Vec<_>, _>>();
+        let names = match names {
+            Ok(names) => names,
+            Err(_err) => {
+                let () = set_last_err(blaze_err::BLAZE_ERR_INVALID_INPUT);
+                return ptr::null()
+            }
+        };
+
+        let result = inspector.lookup(&src, &names);
+        match result {
+            Ok(syms) => {
+                let () = set_last_err(blaze_err::BLAZE_ERR_OK);
+                convert_syms_list_to_c(syms)
+            }
+            Err(err) => {
+                let () = set_last_err(err.kind().into());
+                ptr::null()
+            }
+        }
+    })
 }
 
 
@@ -278,13 +301,16 @@ pub unsafe extern "C" fn blaze_inspect_syms_elf(
 ///
 #[no_mangle]
 pub unsafe extern "C" fn blaze_inspect_syms_free(syms: *const *const blaze_sym_info) {
-    if syms.is_null() {
-        return
-    }
+    catch_panic((), || {
+        if syms.is_null() {
+            return
+        }
 
-    let raw_buf_with_sz = unsafe { (syms as *mut u8).offset(-(mem::size_of::<u64>() as isize)) };
-    let sz = unsafe { *(raw_buf_with_sz as *mut u64) } as usize + mem::size_of::<u64>();
-    unsafe { dealloc(raw_buf_with_sz, Layout::from_size_align(sz, 8).unwrap()) };
+        let raw_buf_with_sz =
+            unsafe { (syms as *mut u8).offset(-(mem::size_of::<u64>() as isize)) };
+        let sz = unsafe { *(raw_buf_with_sz as *mut u64) } as usize + mem::size_of::<u64>();
+        unsafe { dealloc(raw_buf_with_sz, Layout::from_size_align(sz, 8).unwrap()) };
+    })
 }
 
 
@@ -294,9 +320,12 @@ pub unsafe extern "C" fn blaze_inspect_syms_free(syms: *const *const blaze_sym_i
 /// [`blaze_inspector_free`] once it is no longer needed.
 #[no_mangle]
 pub extern "C" fn blaze_inspector_new() -> *mut blaze_inspector {
-    let inspector = Inspector::new();
-    let inspector_box = Box::new(inspector);
-    Box::into_raw(inspector_box)
+    catch_panic(ptr::null_mut(), || {
+        let inspector = Inspector::new();
+        let inspector_box = Box::new(inspector);
+        let () = set_last_err(blaze_err::BLAZE_ERR_OK);
+        Box::into_raw(inspector_box)
+    })
 }
 
 
@@ -310,11 +339,13 @@ pub extern "C" fn blaze_inspector_new() -> *mut blaze_inspector {
 /// [`blaze_inspector_new`].
 #[no_mangle]
 pub unsafe extern "C" fn blaze_inspector_free(inspector: *mut blaze_inspector) {
-    if !inspector.is_null() {
-        // SAFETY: The caller needs to ensure that `inspector` is a
-        //         valid pointer.
-        drop(unsafe { Box::from_raw(inspector) });
-    }
+    catch_panic((), || {
+        if !inspector.is_null() {
+            // SAFETY: The caller needs to ensure that `inspector` is a
+            //         valid pointer.
+            drop(unsafe { Box::from_raw(inspector) });
+        }
+    })
 }
 
 
@@ -330,6 +361,8 @@ mod tests {
 
     use test_log::test;
 
+    use crate::blaze_err_last;
+
 
     /// Exercise the `Debug` representation of various types.
     #[test]
@@ -407,6 +440,7 @@ mod tests {
             sym_type: SymType::Function,
             file_offset: Some(1337),
             obj_file_name: Some(Path::new("/tmp/foobar.so").into()),
+            section: None,
         }]];
         test(syms);
 
@@ -419,6 +453,7 @@ mod tests {
                 sym_type: SymType::Function,
                 file_offset: Some(1337),
                 obj_file_name: Some(Path::new("/tmp/foobar.so").into()),
+                section: None,
             },
             SymInfo {
                 name: "sym2".into(),
@@ -427,6 +462,7 @@ mod tests {
                 sym_type: SymType::Unknown,
                 file_offset: Some(1338),
                 obj_file_name: Some(Path::new("other.so").into()),
+                section: None,
             },
         ]];
         test(syms);
@@ -440,6 +476,7 @@ mod tests {
                 sym_type: SymType::Function,
                 file_offset: Some(1337),
                 obj_file_name: Some(Path::new("/tmp/foobar.so").into()),
+                section: None,
             }],
             vec![SymInfo {
                 name: "sym2".into(),
@@ -448,6 +485,7 @@ mod tests {
                 sym_type: SymType::Unknown,
                 file_offset: Some(1338),
                 obj_file_name: Some(Path::new("other.so").into()),
+                section: None,
             }],
         ];
         test(syms);
@@ -460,6 +498,7 @@ mod tests {
             sym_type: SymType::Function,
             file_offset: Some(1337),
             obj_file_name: Some(Path::new("/tmp/foobar.so").into()),
+            section: None,
         };
         let syms = vec![(0..200).map(|_| sym.clone()).collect()];
         test(syms);
@@ -505,4 +544,27 @@ mod tests {
         let () = unsafe { blaze_inspect_syms_free(result) };
         let () = unsafe { blaze_inspector_free(inspector) };
     }
+
+    /// Check that names that are not valid UTF-8 are reported as
+    /// invalid input.
+    #[test]
+    fn lookup_invalid_name() {
+        let test_dwarf = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join("data")
+            .join("test-stable-addresses-dwarf-only.bin");
+
+        let src = blaze_inspect_elf_src::from(Elf::new(test_dwarf));
+        let name = CString::new(b"fact\xfforial".to_vec()).unwrap();
+        let names = [name.as_ptr()];
+
+        let inspector = blaze_inspector_new();
+        let result =
+            unsafe { blaze_inspect_syms_elf(inspector, &*src, names.as_ptr(), names.len()) };
+        let () = unsafe { ManuallyDrop::into_inner(src).free() };
+        assert!(result.is_null());
+        assert_eq!(blaze_err_last(), blaze_err::BLAZE_ERR_INVALID_INPUT);
+
+        let () = unsafe { blaze_inspector_free(inspector) };
+    }
 }
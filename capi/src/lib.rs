@@ -14,14 +14,172 @@ mod normalize;
 #[allow(non_camel_case_types)]
 mod symbolize;
 
+use std::cell::Cell;
+use std::os::raw::c_char;
+use std::panic::catch_unwind;
+use std::panic::AssertUnwindSafe;
 use std::ptr::NonNull;
 use std::slice;
 
+use blazesym::ErrorKind;
+
 pub use inspect::*;
 pub use normalize::*;
 pub use symbolize::*;
 
 
+/// An enum providing a rough classification of errors.
+///
+/// C ABI compatible version of [`blazesym::ErrorKind`].
+#[allow(non_camel_case_types, clippy::enum_variant_names)]
+#[repr(i16)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum blaze_err {
+    /// The operation was successful.
+    BLAZE_ERR_OK = 0,
+    /// An entity was not found, often a file.
+    BLAZE_ERR_NOT_FOUND = -2,
+    /// The operation lacked the necessary privileges to complete.
+    BLAZE_ERR_PERMISSION_DENIED = -1,
+    /// An entity already exists, often a file.
+    BLAZE_ERR_ALREADY_EXISTS = -17,
+    /// The operation needs to block to complete, but the blocking
+    /// operation was requested to not occur.
+    BLAZE_ERR_WOULD_BLOCK = -11,
+    /// Data not valid for the operation were encountered.
+    BLAZE_ERR_INVALID_DATA = -22,
+    /// The I/O operation's timeout expired, causing it to be canceled.
+    BLAZE_ERR_TIMED_OUT = -110,
+    /// This operation is unsupported on this platform.
+    BLAZE_ERR_UNSUPPORTED = -95,
+    /// An operation could not be completed, because it failed
+    /// to allocate enough memory.
+    BLAZE_ERR_OUT_OF_MEMORY = -12,
+    /// This operation was interrupted.
+    BLAZE_ERR_INTERRUPTED = -4,
+    /// A parameter was incorrect.
+    BLAZE_ERR_INVALID_INPUT = -256,
+    /// An error returned when an operation could not be completed
+    /// because a call to `write` returned `Ok(0)`.
+    BLAZE_ERR_WRITE_ZERO = -257,
+    /// An error returned when an operation could not be completed
+    /// because an "end of file" was reached prematurely.
+    BLAZE_ERR_UNEXPECTED_EOF = -258,
+    /// DWARF input data was invalid.
+    BLAZE_ERR_INVALID_DWARF = -259,
+    /// A custom error that does not fall under any other I/O error
+    /// kind.
+    BLAZE_ERR_OTHER = -260,
+    /// The library encountered an internal error and had to abort the
+    /// operation. This is always a bug in the library.
+    BLAZE_ERR_PANIC = -261,
+    /// The input is not in a format supported by the operation.
+    BLAZE_ERR_UNSUPPORTED_FORMAT = -262,
+    /// The build ID of a file did not match the expected one.
+    BLAZE_ERR_BUILD_ID_MISMATCH = -263,
+    /// Entering or leaving a Linux namespace failed.
+    BLAZE_ERR_NAMESPACE_FAILURE = -264,
+}
+
+impl From<ErrorKind> for blaze_err {
+    fn from(other: ErrorKind) -> Self {
+        match other {
+            ErrorKind::NotFound => blaze_err::BLAZE_ERR_NOT_FOUND,
+            ErrorKind::PermissionDenied => blaze_err::BLAZE_ERR_PERMISSION_DENIED,
+            ErrorKind::AlreadyExists => blaze_err::BLAZE_ERR_ALREADY_EXISTS,
+            ErrorKind::WouldBlock => blaze_err::BLAZE_ERR_WOULD_BLOCK,
+            ErrorKind::InvalidInput => blaze_err::BLAZE_ERR_INVALID_INPUT,
+            ErrorKind::InvalidData => blaze_err::BLAZE_ERR_INVALID_DATA,
+            ErrorKind::InvalidDwarf => blaze_err::BLAZE_ERR_INVALID_DWARF,
+            ErrorKind::TimedOut => blaze_err::BLAZE_ERR_TIMED_OUT,
+            ErrorKind::Interrupted => blaze_err::BLAZE_ERR_INTERRUPTED,
+            ErrorKind::WriteZero => blaze_err::BLAZE_ERR_WRITE_ZERO,
+            ErrorKind::Unsupported => blaze_err::BLAZE_ERR_UNSUPPORTED,
+            ErrorKind::UnexpectedEof => blaze_err::BLAZE_ERR_UNEXPECTED_EOF,
+            ErrorKind::OutOfMemory => blaze_err::BLAZE_ERR_OUT_OF_MEMORY,
+            ErrorKind::UnsupportedFormat => blaze_err::BLAZE_ERR_UNSUPPORTED_FORMAT,
+            ErrorKind::BuildIdMismatch => blaze_err::BLAZE_ERR_BUILD_ID_MISMATCH,
+            ErrorKind::NamespaceFailure => blaze_err::BLAZE_ERR_NAMESPACE_FAILURE,
+            _ => blaze_err::BLAZE_ERR_OTHER,
+        }
+    }
+}
+
+
+thread_local! {
+    /// The error reported by the last fallible API function invoked on
+    /// the current thread.
+    static LAST_ERR: Cell<blaze_err> = const { Cell::new(blaze_err::BLAZE_ERR_OK) };
+}
+
+
+/// Retrieve the error reported by the last fallible API function invoked
+/// on the calling thread.
+///
+/// Functions reporting errors document this fact. Errors are typically
+/// signaled by the function returning `NULL`.
+#[no_mangle]
+pub extern "C" fn blaze_err_last() -> blaze_err {
+    LAST_ERR.with(|cell| cell.get())
+}
+
+/// Retrieve a textual representation of the error code.
+///
+/// The returned string is statically allocated and must not be freed.
+#[no_mangle]
+pub extern "C" fn blaze_err_str(err: blaze_err) -> *const c_char {
+    let s: &[u8] = match err {
+        blaze_err::BLAZE_ERR_OK => b"success\0",
+        blaze_err::BLAZE_ERR_NOT_FOUND => b"entity not found\0",
+        blaze_err::BLAZE_ERR_PERMISSION_DENIED => b"permission denied\0",
+        blaze_err::BLAZE_ERR_ALREADY_EXISTS => b"entity already exists\0",
+        blaze_err::BLAZE_ERR_WOULD_BLOCK => b"operation would block\0",
+        blaze_err::BLAZE_ERR_INVALID_DATA => b"invalid data\0",
+        blaze_err::BLAZE_ERR_TIMED_OUT => b"timed out\0",
+        blaze_err::BLAZE_ERR_UNSUPPORTED => b"unsupported\0",
+        blaze_err::BLAZE_ERR_OUT_OF_MEMORY => b"out of memory\0",
+        blaze_err::BLAZE_ERR_INTERRUPTED => b"operation interrupted\0",
+        blaze_err::BLAZE_ERR_INVALID_INPUT => b"invalid input parameter\0",
+        blaze_err::BLAZE_ERR_WRITE_ZERO => b"write zero\0",
+        blaze_err::BLAZE_ERR_UNEXPECTED_EOF => b"unexpected end of file\0",
+        blaze_err::BLAZE_ERR_INVALID_DWARF => b"DWARF data invalid\0",
+        blaze_err::BLAZE_ERR_OTHER => b"other error\0",
+        blaze_err::BLAZE_ERR_PANIC => b"internal error\0",
+        blaze_err::BLAZE_ERR_UNSUPPORTED_FORMAT => b"unsupported format\0",
+        blaze_err::BLAZE_ERR_BUILD_ID_MISMATCH => b"build ID mismatch\0",
+        blaze_err::BLAZE_ERR_NAMESPACE_FAILURE => b"namespace failure\0",
+    };
+    s.as_ptr().cast()
+}
+
+
+/// Set the error reported by [`blaze_err_last`].
+pub(crate) fn set_last_err(err: blaze_err) {
+    LAST_ERR.with(|cell| cell.set(err))
+}
+
+
+/// Run `f`, making sure that a panic does not unwind into the C caller.
+///
+/// If `f` panics, the last error is set to
+/// [`blaze_err::BLAZE_ERR_PANIC`] and `on_panic` is returned.
+pub(crate) fn catch_panic<T, F>(on_panic: T, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    // The functions we wrap only work on data that are either owned by
+    // them or provided by the caller, who is not able to observe broken
+    // invariants after a panic, so asserting unwind safety is fine.
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(value) => value,
+        Err(_panic) => {
+            let () = set_last_err(blaze_err::BLAZE_ERR_PANIC);
+            on_panic
+        }
+    }
+}
+
+
 /// "Safely" create a slice from a user provided array.
 pub(crate) unsafe fn slice_from_user_array<'t, T>(items: *const T, num_items: usize) -> &'t [T] {
     let items = if items.is_null() {
@@ -39,9 +197,46 @@ pub(crate) unsafe fn slice_from_user_array<'t, T>(items: *const T, num_items: us
 mod tests {
     use super::*;
 
+    use std::ffi::CStr;
     use std::ptr;
 
 
+    /// Check that panics are caught and reported as errors.
+    #[test]
+    fn panic_catching() {
+        let () = set_last_err(blaze_err::BLAZE_ERR_OK);
+        let value = catch_panic(ptr::null::<u8>(), || panic!("induced panic"));
+        assert_eq!(value, ptr::null());
+        assert_eq!(blaze_err_last(), blaze_err::BLAZE_ERR_PANIC);
+
+        let value = catch_panic(0, || 42);
+        assert_eq!(value, 42);
+    }
+
+    /// Check that we can convert error codes into their textual
+    /// representation.
+    #[test]
+    fn error_strings() {
+        let errs = [
+            (ErrorKind::NotFound, blaze_err::BLAZE_ERR_NOT_FOUND),
+            (ErrorKind::InvalidDwarf, blaze_err::BLAZE_ERR_INVALID_DWARF),
+            (
+                ErrorKind::UnsupportedFormat,
+                blaze_err::BLAZE_ERR_UNSUPPORTED_FORMAT,
+            ),
+            (ErrorKind::Other, blaze_err::BLAZE_ERR_OTHER),
+        ];
+        for (kind, expected) in errs {
+            let err = blaze_err::from(kind);
+            assert_eq!(err, expected);
+            let s = unsafe { CStr::from_ptr(blaze_err_str(err)) };
+            assert!(!s.to_bytes().is_empty());
+        }
+
+        let s = unsafe { CStr::from_ptr(blaze_err_str(blaze_err::BLAZE_ERR_PANIC)) };
+        assert_eq!(s.to_str().unwrap(), "internal error");
+    }
+
     /// Test the `slice_from_user_array` helper in the presence of various
     /// inputs.
     #[test]
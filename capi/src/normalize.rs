@@ -12,12 +12,21 @@ use std::slice;
 
 use blazesym::normalize::Apk;
 use blazesym::normalize::Elf;
+use blazesym::normalize::KernelMeta;
+use blazesym::normalize::KernelModule;
+use blazesym::normalize::KernelOutput;
+use blazesym::normalize::MachO;
 use blazesym::normalize::Normalizer;
+use blazesym::normalize::PdbInfo;
+use blazesym::normalize::Pe;
 use blazesym::normalize::Unknown;
 use blazesym::normalize::UserMeta;
 use blazesym::normalize::UserOutput;
 use blazesym::Addr;
 
+use crate::blaze_err;
+use crate::catch_panic;
+use crate::set_last_err;
 use crate::slice_from_user_array;
 
 
@@ -31,9 +40,58 @@ pub type blaze_normalizer = Normalizer;
 /// [`blaze_normalizer_free`] once it is no longer needed.
 #[no_mangle]
 pub extern "C" fn blaze_normalizer_new() -> *mut blaze_normalizer {
-    let normalizer = Normalizer::new();
-    let normalizer_box = Box::new(normalizer);
-    Box::into_raw(normalizer_box)
+    catch_panic(ptr::null_mut(), || {
+        let normalizer = Normalizer::new();
+        let normalizer_box = Box::new(normalizer);
+        let () = set_last_err(blaze_err::BLAZE_ERR_OK);
+        Box::into_raw(normalizer_box)
+    })
+}
+
+
+/// Options for configuring `blaze_normalizer` objects.
+#[repr(C)]
+#[derive(Debug)]
+pub struct blaze_normalizer_opts {
+    /// Whether to read and report build IDs as part of the
+    /// normalization process.
+    pub build_ids: bool,
+    /// Whether to compute a fallback identifier for ELF binaries that
+    /// lack a GNU build ID.
+    ///
+    /// This setting only has an effect if `build_ids` is `true`.
+    pub build_id_fallback: bool,
+}
+
+
+/// Create an instance of a blazesym normalizer with configurable
+/// options.
+///
+/// The returned pointer should be released using
+/// [`blaze_normalizer_free`] once it is no longer needed.
+///
+/// # Safety
+/// `opts` needs to be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn blaze_normalizer_new_opts(
+    opts: *const blaze_normalizer_opts,
+) -> *mut blaze_normalizer {
+    catch_panic(ptr::null_mut(), || {
+        // SAFETY: The caller ensures that the pointer is valid.
+        let opts = unsafe { &*opts };
+        let blaze_normalizer_opts {
+            build_ids,
+            build_id_fallback,
+        } = opts;
+
+        let normalizer = Normalizer::builder()
+            .enable_build_ids(*build_ids)
+            .enable_build_id_fallback(*build_id_fallback)
+            .build();
+        let normalizer_box = Box::new(normalizer);
+        let () = set_last_err(blaze_err::BLAZE_ERR_OK);
+        Box::into_raw(normalizer_box)
+    })
 }
 
 
@@ -44,14 +102,16 @@ pub extern "C" fn blaze_normalizer_new() -> *mut blaze_normalizer {
 ///
 /// # Safety
 /// The provided normalizer should have been created by
-/// [`blaze_normalizer_new`].
+/// [`blaze_normalizer_new`] or [`blaze_normalizer_new_opts`].
 #[no_mangle]
 pub unsafe extern "C" fn blaze_normalizer_free(normalizer: *mut blaze_normalizer) {
-    if !normalizer.is_null() {
-        // SAFETY: The caller needs to ensure that `normalizer` is a
-        //         valid pointer.
-        drop(unsafe { Box::from_raw(normalizer) });
-    }
+    catch_panic((), || {
+        if !normalizer.is_null() {
+            // SAFETY: The caller needs to ensure that `normalizer` is a
+            //         valid pointer.
+            drop(unsafe { Box::from_raw(normalizer) });
+        }
+    })
 }
 
 
@@ -84,6 +144,10 @@ pub enum blaze_user_meta_kind {
     BLAZE_USER_META_APK,
     /// [`blaze_user_meta_variant::elf`] is valid.
     BLAZE_USER_META_ELF,
+    /// [`blaze_user_meta_variant::macho`] is valid.
+    BLAZE_USER_META_MACHO,
+    /// [`blaze_user_meta_variant::pe`] is valid.
+    BLAZE_USER_META_PE,
 }
 
 
@@ -94,12 +158,17 @@ pub struct blaze_user_meta_apk {
     /// The canonical absolute path to the APK, including its name.
     /// This member is always present.
     pub path: *mut c_char,
+    /// The path of the uncompressed ELF file inside the APK that the
+    /// address belongs to, relative to the root of the archive. NULL
+    /// if the address does not fall into such a file.
+    pub elf_path: *mut c_char,
 }
 
 impl blaze_user_meta_apk {
     fn from(other: Apk) -> ManuallyDrop<Self> {
         let Apk {
             path,
+            elf_path,
             _non_exhaustive: (),
         } = other;
 
@@ -107,17 +176,29 @@ impl blaze_user_meta_apk {
             path: CString::new(path.into_os_string().into_vec())
                 .expect("encountered path with NUL bytes")
                 .into_raw(),
+            elf_path: elf_path
+                .map(|elf_path| {
+                    CString::new(elf_path.into_os_string().into_vec())
+                        .expect("encountered path with NUL bytes")
+                        .into_raw()
+                })
+                .unwrap_or_else(ptr::null_mut),
         };
         ManuallyDrop::new(slf)
     }
 
     unsafe fn free(self) {
-        let Self { path } = self;
+        let Self { path, elf_path } = self;
 
         let _apk = Apk {
             path: PathBuf::from(OsString::from_vec(
                 unsafe { CString::from_raw(path) }.into_bytes(),
             )),
+            elf_path: (!elf_path.is_null()).then(|| {
+                PathBuf::from(OsString::from_vec(
+                    unsafe { CString::from_raw(elf_path) }.into_bytes(),
+                ))
+            }),
             _non_exhaustive: (),
         };
     }
@@ -188,6 +269,135 @@ impl blaze_user_meta_elf {
 }
 
 
+/// C compatible version of [`MachO`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct blaze_user_meta_macho {
+    /// The path to the Mach-O file. This member is always present.
+    pub path: *mut c_char,
+    /// Whether `uuid` contains the UUID of the Mach-O file.
+    pub has_uuid: bool,
+    /// The UUID of the Mach-O file, if `has_uuid` is set.
+    pub uuid: [u8; 16],
+}
+
+impl blaze_user_meta_macho {
+    fn from(other: MachO) -> ManuallyDrop<Self> {
+        let MachO {
+            path,
+            uuid,
+            _non_exhaustive: (),
+        } = other;
+
+        let slf = Self {
+            path: CString::new(path.into_os_string().into_vec())
+                .expect("encountered path with NUL bytes")
+                .into_raw(),
+            has_uuid: uuid.is_some(),
+            uuid: uuid.unwrap_or_default(),
+        };
+        ManuallyDrop::new(slf)
+    }
+
+    unsafe fn free(self) {
+        let blaze_user_meta_macho {
+            path,
+            has_uuid,
+            uuid,
+        } = self;
+
+        let _macho = MachO {
+            path: PathBuf::from(OsString::from_vec(
+                unsafe { CString::from_raw(path) }.into_bytes(),
+            )),
+            uuid: has_uuid.then_some(uuid),
+            _non_exhaustive: (),
+        };
+    }
+}
+
+
+/// C compatible version of [`Pe`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct blaze_user_meta_pe {
+    /// The path to the PE file. This member is always present.
+    pub path: *mut c_char,
+    /// Whether the `pdb_*` members contain information about the PDB
+    /// file referenced by the PE file.
+    pub has_pdb: bool,
+    /// The GUID of the PDB file, if `has_pdb` is set.
+    pub pdb_guid: [u8; 16],
+    /// The age of the PDB file, if `has_pdb` is set.
+    pub pdb_age: u32,
+    /// The path to the PDB file, as recorded by the linker. NULL if
+    /// `has_pdb` is not set.
+    pub pdb_path: *mut c_char,
+}
+
+impl blaze_user_meta_pe {
+    fn from(other: Pe) -> ManuallyDrop<Self> {
+        let Pe {
+            path,
+            pdb,
+            _non_exhaustive: (),
+        } = other;
+
+        let (pdb_guid, pdb_age, pdb_path) = match pdb {
+            Some(PdbInfo {
+                guid,
+                age,
+                path,
+                _non_exhaustive: (),
+            }) => (
+                guid,
+                age,
+                CString::new(path.into_os_string().into_vec())
+                    .expect("encountered path with NUL bytes")
+                    .into_raw(),
+            ),
+            None => ([0; 16], 0, ptr::null_mut()),
+        };
+
+        let slf = Self {
+            path: CString::new(path.into_os_string().into_vec())
+                .expect("encountered path with NUL bytes")
+                .into_raw(),
+            has_pdb: !pdb_path.is_null(),
+            pdb_guid,
+            pdb_age,
+            pdb_path,
+        };
+        ManuallyDrop::new(slf)
+    }
+
+    unsafe fn free(self) {
+        let blaze_user_meta_pe {
+            path,
+            has_pdb,
+            pdb_guid,
+            pdb_age,
+            pdb_path,
+        } = self;
+
+        let _pe = Pe {
+            path: PathBuf::from(OsString::from_vec(
+                unsafe { CString::from_raw(path) }.into_bytes(),
+            )),
+            pdb: has_pdb.then(|| PdbInfo {
+                guid: pdb_guid,
+                age: pdb_age,
+                path: PathBuf::from(OsString::from_vec(
+                    unsafe { CString::from_raw(pdb_path) }.into_bytes(),
+                )),
+                _non_exhaustive: (),
+            }),
+            _non_exhaustive: (),
+        };
+    }
+}
+
+
 /// C compatible version of [`Unknown`].
 #[repr(C)]
 #[derive(Debug)]
@@ -219,6 +429,10 @@ pub union blaze_user_meta_variant {
     pub apk: ManuallyDrop<blaze_user_meta_apk>,
     /// Valid on [`blaze_user_meta_kind::BLAZE_USER_META_ELF`].
     pub elf: ManuallyDrop<blaze_user_meta_elf>,
+    /// Valid on [`blaze_user_meta_kind::BLAZE_USER_META_MACHO`].
+    pub macho: ManuallyDrop<blaze_user_meta_macho>,
+    /// Valid on [`blaze_user_meta_kind::BLAZE_USER_META_PE`].
+    pub pe: ManuallyDrop<blaze_user_meta_pe>,
     /// Valid on [`blaze_user_meta_kind::BLAZE_USER_META_UNKNOWN`].
     pub unknown: ManuallyDrop<blaze_user_meta_unknown>,
 }
@@ -255,6 +469,18 @@ impl blaze_user_meta {
                     elf: blaze_user_meta_elf::from(elf),
                 },
             },
+            UserMeta::MachO(macho) => Self {
+                kind: blaze_user_meta_kind::BLAZE_USER_META_MACHO,
+                variant: blaze_user_meta_variant {
+                    macho: blaze_user_meta_macho::from(macho),
+                },
+            },
+            UserMeta::Pe(pe) => Self {
+                kind: blaze_user_meta_kind::BLAZE_USER_META_PE,
+                variant: blaze_user_meta_variant {
+                    pe: blaze_user_meta_pe::from(pe),
+                },
+            },
             UserMeta::Unknown(unknown) => Self {
                 kind: blaze_user_meta_kind::BLAZE_USER_META_UNKNOWN,
                 variant: blaze_user_meta_variant {
@@ -274,6 +500,12 @@ impl blaze_user_meta {
             blaze_user_meta_kind::BLAZE_USER_META_ELF => {
                 ManuallyDrop::into_inner(unsafe { self.variant.elf }).free()
             }
+            blaze_user_meta_kind::BLAZE_USER_META_MACHO => {
+                ManuallyDrop::into_inner(unsafe { self.variant.macho }).free()
+            }
+            blaze_user_meta_kind::BLAZE_USER_META_PE => {
+                ManuallyDrop::into_inner(unsafe { self.variant.pe }).free()
+            }
             blaze_user_meta_kind::BLAZE_USER_META_UNKNOWN => {
                 ManuallyDrop::into_inner(unsafe { self.variant.unknown }).free()
             }
@@ -345,7 +577,8 @@ impl blaze_normalized_user_output {
 /// result in slightly faster normalization.
 ///
 /// C ABI compatible version of [`Normalizer::normalize_user_addrs`].
-/// Returns `NULL` on error. The resulting object should be freed using
+/// Returns `NULL` on error, in which case `blaze_err_last` can be used
+/// to retrieve the reason. The resulting object should be freed using
 /// [`blaze_user_output_free`].
 ///
 /// # Safety
@@ -358,19 +591,27 @@ pub unsafe extern "C" fn blaze_normalize_user_addrs(
     addrs: *const Addr,
     addr_cnt: usize,
 ) -> *mut blaze_normalized_user_output {
-    // SAFETY: The caller needs to ensure that `normalizer` is a valid
-    //         pointer.
-    let normalizer = unsafe { &*normalizer };
-    // SAFETY: The caller needs to ensure that `addrs` is a valid pointer and
-    //         that it points to `addr_cnt` elements.
-    let addrs = unsafe { slice_from_user_array(addrs, addr_cnt) };
-    let result = normalizer.normalize_user_addrs(pid.into(), addrs);
-    match result {
-        Ok(addrs) => Box::into_raw(Box::new(ManuallyDrop::into_inner(
-            blaze_normalized_user_output::from(addrs),
-        ))),
-        Err(_err) => ptr::null_mut(),
-    }
+    catch_panic(ptr::null_mut(), || {
+        // SAFETY: The caller needs to ensure that `normalizer` is a valid
+        //         pointer.
+        let normalizer = unsafe { &*normalizer };
+        // SAFETY: The caller needs to ensure that `addrs` is a valid pointer and
+        //         that it points to `addr_cnt` elements.
+        let addrs = unsafe { slice_from_user_array(addrs, addr_cnt) };
+        let result = normalizer.normalize_user_addrs(pid.into(), addrs);
+        match result {
+            Ok(addrs) => {
+                let () = set_last_err(blaze_err::BLAZE_ERR_OK);
+                Box::into_raw(Box::new(ManuallyDrop::into_inner(
+                    blaze_normalized_user_output::from(addrs),
+                )))
+            }
+            Err(err) => {
+                let () = set_last_err(err.kind().into());
+                ptr::null_mut()
+            }
+        }
+    })
 }
 
 
@@ -385,7 +626,8 @@ pub unsafe extern "C" fn blaze_normalize_user_addrs(
 /// belongs. It may be `0` if they belong to the calling process.
 ///
 /// C ABI compatible version of [`Normalizer::normalize_user_addrs_sorted`].
-/// Returns `NULL` on error. The resulting object should be freed using
+/// Returns `NULL` on error, in which case `blaze_err_last` can be used
+/// to retrieve the reason. The resulting object should be freed using
 /// [`blaze_user_output_free`].
 ///
 /// # Safety
@@ -398,19 +640,27 @@ pub unsafe extern "C" fn blaze_normalize_user_addrs_sorted(
     addrs: *const Addr,
     addr_cnt: usize,
 ) -> *mut blaze_normalized_user_output {
-    // SAFETY: The caller needs to ensure that `normalizer` is a valid
-    //         pointer.
-    let normalizer = unsafe { &*normalizer };
-    // SAFETY: The caller needs to ensure that `addrs` is a valid pointer and
-    //         that it points to `addr_cnt` elements.
-    let addrs = unsafe { slice_from_user_array(addrs, addr_cnt) };
-    let result = normalizer.normalize_user_addrs_sorted(pid.into(), addrs);
-    match result {
-        Ok(addrs) => Box::into_raw(Box::new(ManuallyDrop::into_inner(
-            blaze_normalized_user_output::from(addrs),
-        ))),
-        Err(_err) => ptr::null_mut(),
-    }
+    catch_panic(ptr::null_mut(), || {
+        // SAFETY: The caller needs to ensure that `normalizer` is a valid
+        //         pointer.
+        let normalizer = unsafe { &*normalizer };
+        // SAFETY: The caller needs to ensure that `addrs` is a valid pointer and
+        //         that it points to `addr_cnt` elements.
+        let addrs = unsafe { slice_from_user_array(addrs, addr_cnt) };
+        let result = normalizer.normalize_user_addrs_sorted(pid.into(), addrs);
+        match result {
+            Ok(addrs) => {
+                let () = set_last_err(blaze_err::BLAZE_ERR_OK);
+                Box::into_raw(Box::new(ManuallyDrop::into_inner(
+                    blaze_normalized_user_output::from(addrs),
+                )))
+            }
+            Err(err) => {
+                let () = set_last_err(err.kind().into());
+                ptr::null_mut()
+            }
+        }
+    })
 }
 
 /// Free an object as returned by [`blaze_normalize_user_addrs`] or
@@ -421,31 +671,300 @@ pub unsafe extern "C" fn blaze_normalize_user_addrs_sorted(
 /// [`blaze_normalize_user_addrs`] or [`blaze_normalize_user_addrs_sorted`].
 #[no_mangle]
 pub unsafe extern "C" fn blaze_user_output_free(output: *mut blaze_normalized_user_output) {
-    if output.is_null() {
-        return
+    catch_panic((), || {
+        if output.is_null() {
+            return
+        }
+
+        // SAFETY: The caller should make sure that `output` was created by one of
+        //         our blessed functions.
+        let user_output = unsafe { Box::from_raw(output) };
+        let addr_metas = unsafe {
+            Box::<[blaze_user_meta]>::from_raw(slice::from_raw_parts_mut(
+                user_output.metas,
+                user_output.meta_cnt,
+            ))
+        }
+        .into_vec();
+        let _norm_addrs = unsafe {
+            Box::<[blaze_normalized_output]>::from_raw(slice::from_raw_parts_mut(
+                user_output.outputs,
+                user_output.output_cnt,
+            ))
+        }
+        .into_vec();
+
+        for addr_meta in addr_metas {
+            let () = unsafe { addr_meta.free() };
+        }
+    })
+}
+
+
+/// The valid variant kind in [`blaze_kernel_meta`].
+#[repr(C)]
+#[derive(Debug)]
+pub enum blaze_kernel_meta_kind {
+    /// [`blaze_kernel_meta_variant::unknown`] is valid.
+    BLAZE_KERNEL_META_UNKNOWN,
+    /// [`blaze_kernel_meta_variant::module`] is valid.
+    BLAZE_KERNEL_META_MODULE,
+}
+
+
+/// C compatible version of [`KernelModule`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct blaze_kernel_meta_module {
+    /// The name of the module, as reported in `/proc/modules`. This
+    /// member is always present.
+    pub name: *mut c_char,
+    /// The length of the build ID, in bytes.
+    pub build_id_len: usize,
+    /// The optional build ID of the module, if found.
+    pub build_id: *mut u8,
+}
+
+impl blaze_kernel_meta_module {
+    fn from(other: KernelModule) -> ManuallyDrop<Self> {
+        let KernelModule {
+            name,
+            build_id,
+            _non_exhaustive: (),
+        } = other;
+
+        let slf = Self {
+            name: CString::new(name)
+                .expect("encountered module name with NUL bytes")
+                .into_raw(),
+            build_id_len: build_id
+                .as_ref()
+                .map(|build_id| build_id.len())
+                .unwrap_or(0),
+            build_id: build_id
+                .map(|build_id| {
+                    // SAFETY: We know the pointer is valid because it
+                    //         came from a `Box`.
+                    unsafe {
+                        Box::into_raw(build_id.into_boxed_slice())
+                            .as_mut()
+                            .unwrap()
+                            .as_mut_ptr()
+                    }
+                })
+                .unwrap_or_else(ptr::null_mut),
+        };
+        ManuallyDrop::new(slf)
     }
 
-    // SAFETY: The caller should make sure that `output` was created by one of
-    //         our blessed functions.
-    let user_output = unsafe { Box::from_raw(output) };
-    let addr_metas = unsafe {
-        Box::<[blaze_user_meta]>::from_raw(slice::from_raw_parts_mut(
-            user_output.metas,
-            user_output.meta_cnt,
-        ))
+    unsafe fn free(self) {
+        let blaze_kernel_meta_module {
+            name,
+            build_id_len,
+            build_id,
+        } = self;
+
+        let _name = unsafe { CString::from_raw(name) };
+        if !build_id.is_null() {
+            let _build_id =
+                unsafe { Box::<[u8]>::from_raw(slice::from_raw_parts_mut(build_id, build_id_len)) };
+        }
     }
-    .into_vec();
-    let _norm_addrs = unsafe {
-        Box::<[blaze_normalized_output]>::from_raw(slice::from_raw_parts_mut(
-            user_output.outputs,
-            user_output.output_cnt,
-        ))
+}
+
+
+/// The actual variant data in [`blaze_kernel_meta`].
+#[repr(C)]
+pub union blaze_kernel_meta_variant {
+    /// Valid on [`blaze_kernel_meta_kind::BLAZE_KERNEL_META_MODULE`].
+    pub module: ManuallyDrop<blaze_kernel_meta_module>,
+    /// Valid on [`blaze_kernel_meta_kind::BLAZE_KERNEL_META_UNKNOWN`].
+    pub unknown: ManuallyDrop<blaze_user_meta_unknown>,
+}
+
+impl Debug for blaze_kernel_meta_variant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct(stringify!(blaze_kernel_meta_variant))
+            .finish()
     }
-    .into_vec();
+}
 
-    for addr_meta in addr_metas {
-        let () = unsafe { addr_meta.free() };
+
+/// C ABI compatible version of [`KernelMeta`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct blaze_kernel_meta {
+    /// The variant kind that is present.
+    pub kind: blaze_kernel_meta_kind,
+    /// The actual variant with its data.
+    pub variant: blaze_kernel_meta_variant,
+}
+
+impl blaze_kernel_meta {
+    fn from(other: KernelMeta) -> ManuallyDrop<Self> {
+        let slf = match other {
+            KernelMeta::Module(module) => Self {
+                kind: blaze_kernel_meta_kind::BLAZE_KERNEL_META_MODULE,
+                variant: blaze_kernel_meta_variant {
+                    module: blaze_kernel_meta_module::from(module),
+                },
+            },
+            KernelMeta::Unknown(unknown) => Self {
+                kind: blaze_kernel_meta_kind::BLAZE_KERNEL_META_UNKNOWN,
+                variant: blaze_kernel_meta_variant {
+                    unknown: blaze_user_meta_unknown::from(unknown),
+                },
+            },
+            _ => unreachable!(),
+        };
+        ManuallyDrop::new(slf)
     }
+
+    unsafe fn free(self) {
+        match self.kind {
+            blaze_kernel_meta_kind::BLAZE_KERNEL_META_MODULE => {
+                ManuallyDrop::into_inner(unsafe { self.variant.module }).free()
+            }
+            blaze_kernel_meta_kind::BLAZE_KERNEL_META_UNKNOWN => {
+                ManuallyDrop::into_inner(unsafe { self.variant.unknown }).free()
+            }
+        }
+    }
+}
+
+
+/// An object representing normalized kernel addresses.
+///
+/// C ABI compatible version of [`KernelOutput`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct blaze_normalized_kernel_output {
+    /// The number of [`blaze_kernel_meta`] objects present in `metas`.
+    pub meta_cnt: usize,
+    /// An array of `meta_cnt` objects.
+    pub metas: *mut blaze_kernel_meta,
+    /// The number of [`blaze_normalized_output`] objects present in `outputs`.
+    pub output_cnt: usize,
+    /// An array of `output_cnt` objects.
+    pub outputs: *mut blaze_normalized_output,
+}
+
+impl blaze_normalized_kernel_output {
+    fn from(other: KernelOutput) -> ManuallyDrop<Self> {
+        let slf = Self {
+            meta_cnt: other.meta.len(),
+            metas: unsafe {
+                Box::into_raw(
+                    other
+                        .meta
+                        .into_iter()
+                        .map(blaze_kernel_meta::from)
+                        .map(ManuallyDrop::into_inner)
+                        .collect::<Vec<_>>()
+                        .into_boxed_slice(),
+                )
+                .as_mut()
+                .unwrap()
+                .as_mut_ptr()
+            },
+            output_cnt: other.outputs.len(),
+            outputs: unsafe {
+                Box::into_raw(
+                    other
+                        .outputs
+                        .into_iter()
+                        .map(blaze_normalized_output::from)
+                        .collect::<Vec<_>>()
+                        .into_boxed_slice(),
+                )
+                .as_mut()
+                .unwrap()
+                .as_mut_ptr()
+            },
+        };
+        ManuallyDrop::new(slf)
+    }
+}
+
+
+/// Normalize a list of kernel addresses.
+///
+/// Addresses belonging to a kernel module are normalized to an offset
+/// relative to the module's base address and reported along with the
+/// module's name and (if enabled) build ID. Other addresses are
+/// reported unchanged, with an associated unknown meta entry.
+///
+/// C ABI compatible version of [`Normalizer::normalize_kernel_addrs`].
+/// Returns `NULL` on error, in which case `blaze_err_last` can be used
+/// to retrieve the reason. The resulting object should be freed using
+/// [`blaze_kernel_output_free`].
+///
+/// # Safety
+/// Callers need to pass in a valid `addrs` pointer, pointing to memory of
+/// `addr_cnt` addresses.
+#[no_mangle]
+pub unsafe extern "C" fn blaze_normalize_kernel_addrs(
+    normalizer: *const blaze_normalizer,
+    addrs: *const Addr,
+    addr_cnt: usize,
+) -> *mut blaze_normalized_kernel_output {
+    catch_panic(ptr::null_mut(), || {
+        // SAFETY: The caller needs to ensure that `normalizer` is a valid
+        //         pointer.
+        let normalizer = unsafe { &*normalizer };
+        // SAFETY: The caller needs to ensure that `addrs` is a valid pointer and
+        //         that it points to `addr_cnt` elements.
+        let addrs = unsafe { slice_from_user_array(addrs, addr_cnt) };
+        let result = normalizer.normalize_kernel_addrs(addrs);
+        match result {
+            Ok(addrs) => {
+                let () = set_last_err(blaze_err::BLAZE_ERR_OK);
+                Box::into_raw(Box::new(ManuallyDrop::into_inner(
+                    blaze_normalized_kernel_output::from(addrs),
+                )))
+            }
+            Err(err) => {
+                let () = set_last_err(err.kind().into());
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Free an object as returned by [`blaze_normalize_kernel_addrs`].
+///
+/// # Safety
+/// The provided object should have been created by
+/// [`blaze_normalize_kernel_addrs`].
+#[no_mangle]
+pub unsafe extern "C" fn blaze_kernel_output_free(output: *mut blaze_normalized_kernel_output) {
+    catch_panic((), || {
+        if output.is_null() {
+            return
+        }
+
+        // SAFETY: The caller should make sure that `output` was created by one of
+        //         our blessed functions.
+        let kernel_output = unsafe { Box::from_raw(output) };
+        let metas = unsafe {
+            Box::<[blaze_kernel_meta]>::from_raw(slice::from_raw_parts_mut(
+                kernel_output.metas,
+                kernel_output.meta_cnt,
+            ))
+        }
+        .into_vec();
+        let _norm_addrs = unsafe {
+            Box::<[blaze_normalized_output]>::from_raw(slice::from_raw_parts_mut(
+                kernel_output.outputs,
+                kernel_output.output_cnt,
+            ))
+        }
+        .into_vec();
+
+        for meta in metas {
+            let () = unsafe { meta.free() };
+        }
+    })
 }
 
 
@@ -471,8 +990,12 @@ mod tests {
 
         let apk = blaze_user_meta_apk {
             path: ptr::null_mut(),
+            elf_path: ptr::null_mut(),
         };
-        assert_eq!(format!("{apk:?}"), "blaze_user_meta_apk { path: 0x0 }",);
+        assert_eq!(
+            format!("{apk:?}"),
+            "blaze_user_meta_apk { path: 0x0, elf_path: 0x0 }",
+        );
 
         let elf = blaze_user_meta_elf {
             path: ptr::null_mut(),
@@ -535,10 +1058,22 @@ mod tests {
     fn apk_conversion() {
         let apk = Apk {
             path: PathBuf::from("/tmp/archive.apk"),
+            elf_path: None,
             _non_exhaustive: (),
         };
 
         let apk_c = blaze_user_meta_apk::from(apk.clone());
+        assert!(apk_c.elf_path.is_null());
+        let () = unsafe { ManuallyDrop::into_inner(apk_c).free() };
+
+        let apk = Apk {
+            path: PathBuf::from("/tmp/archive.apk"),
+            elf_path: Some(PathBuf::from("lib/arm64-v8a/libfoo.so")),
+            _non_exhaustive: (),
+        };
+
+        let apk_c = blaze_user_meta_apk::from(apk.clone());
+        assert!(!apk_c.elf_path.is_null());
         let () = unsafe { ManuallyDrop::into_inner(apk_c).free() };
 
         let meta = UserMeta::Apk(apk);
@@ -564,6 +1099,97 @@ mod tests {
         let () = unsafe { ManuallyDrop::into_inner(meta_c).free() };
     }
 
+    /// Check that we can convert a [`MachO`] into a
+    /// [`blaze_user_meta_macho`] and back.
+    #[test]
+    fn macho_conversion() {
+        let macho = MachO {
+            path: PathBuf::from("/tmp/libfile.dylib"),
+            uuid: Some([0x42; 16]),
+            _non_exhaustive: (),
+        };
+
+        let macho_c = blaze_user_meta_macho::from(macho.clone());
+        assert!(macho_c.has_uuid);
+        assert_eq!(macho_c.uuid, [0x42; 16]);
+        let () = unsafe { ManuallyDrop::into_inner(macho_c).free() };
+
+        let meta = UserMeta::MachO(macho);
+        let meta_c = blaze_user_meta::from(meta);
+        let () = unsafe { ManuallyDrop::into_inner(meta_c).free() };
+    }
+
+    /// Check that we can convert a [`Pe`] into a [`blaze_user_meta_pe`]
+    /// and back.
+    #[test]
+    fn pe_conversion() {
+        let pe = Pe {
+            path: PathBuf::from("/tmp/library.dll"),
+            pdb: Some(PdbInfo {
+                guid: [0x42; 16],
+                age: 2,
+                path: PathBuf::from("library.pdb"),
+                _non_exhaustive: (),
+            }),
+            _non_exhaustive: (),
+        };
+
+        let pe_c = blaze_user_meta_pe::from(pe.clone());
+        assert!(pe_c.has_pdb);
+        assert_eq!(pe_c.pdb_guid, [0x42; 16]);
+        assert_eq!(pe_c.pdb_age, 2);
+        assert!(!pe_c.pdb_path.is_null());
+        let () = unsafe { ManuallyDrop::into_inner(pe_c).free() };
+
+        let pe_c = blaze_user_meta_pe::from(Pe {
+            pdb: None,
+            ..pe.clone()
+        });
+        assert!(!pe_c.has_pdb);
+        assert!(pe_c.pdb_path.is_null());
+        let () = unsafe { ManuallyDrop::into_inner(pe_c).free() };
+
+        let meta = UserMeta::Pe(pe);
+        let meta_c = blaze_user_meta::from(meta);
+        let () = unsafe { ManuallyDrop::into_inner(meta_c).free() };
+    }
+
+    /// Check that we can convert a [`KernelModule`] into a
+    /// [`blaze_kernel_meta_module`] and back.
+    #[test]
+    fn kernel_module_conversion() {
+        let module = KernelModule {
+            name: "kvm".to_string(),
+            build_id: None,
+            _non_exhaustive: (),
+        };
+
+        let module_c = blaze_kernel_meta_module::from(module.clone());
+        assert!(module_c.build_id.is_null());
+        assert_eq!(module_c.build_id_len, 0);
+        let () = unsafe { ManuallyDrop::into_inner(module_c).free() };
+
+        let module = KernelModule {
+            build_id: Some(vec![0x01, 0x02, 0x03, 0x04]),
+            ..module
+        };
+        let module_c = blaze_kernel_meta_module::from(module.clone());
+        assert_eq!(module_c.build_id_len, 4);
+        assert_eq!(
+            unsafe { slice::from_raw_parts(module_c.build_id, module_c.build_id_len) },
+            &[0x01, 0x02, 0x03, 0x04]
+        );
+        let () = unsafe { ManuallyDrop::into_inner(module_c).free() };
+
+        let meta = KernelMeta::Module(module);
+        let meta_c = blaze_kernel_meta::from(meta);
+        let () = unsafe { ManuallyDrop::into_inner(meta_c).free() };
+
+        let meta = KernelMeta::Unknown(Unknown::default());
+        let meta_c = blaze_kernel_meta::from(meta);
+        let () = unsafe { ManuallyDrop::into_inner(meta_c).free() };
+    }
+
     /// Make sure that we can create and free a normalizer instance.
     #[test]
     fn normalizer_creation() {
@@ -571,6 +1197,95 @@ mod tests {
         let () = unsafe { blaze_normalizer_free(normalizer) };
     }
 
+    /// Make sure that we can create and free a normalizer instance with
+    /// the provided options.
+    #[test]
+    fn normalizer_creation_with_opts() {
+        let opts = blaze_normalizer_opts {
+            build_ids: false,
+            build_id_fallback: false,
+        };
+        assert_eq!(
+            format!("{opts:?}"),
+            "blaze_normalizer_opts { build_ids: false, build_id_fallback: false }"
+        );
+
+        let normalizer = unsafe { blaze_normalizer_new_opts(&opts) };
+        assert_ne!(normalizer, ptr::null_mut());
+        let () = unsafe { blaze_normalizer_free(normalizer) };
+    }
+
+    /// Check that build IDs are reported (or not) as configured.
+    #[test]
+    fn normalize_user_addrs_build_ids() {
+        fn test(build_ids: bool) {
+            let opts = blaze_normalizer_opts {
+                build_ids,
+                build_id_fallback: false,
+            };
+            let normalizer = unsafe { blaze_normalizer_new_opts(&opts) };
+            assert_ne!(normalizer, ptr::null_mut());
+
+            let addrs = [normalize_user_addrs_build_ids as Addr];
+            let result = unsafe {
+                blaze_normalize_user_addrs(normalizer, 0, addrs.as_slice().as_ptr(), addrs.len())
+            };
+            assert_ne!(result, ptr::null_mut());
+
+            let user_addrs = unsafe { &*result };
+            assert_eq!(user_addrs.meta_cnt, 1);
+            let meta = unsafe { &*user_addrs.metas };
+            assert!(matches!(
+                meta.kind,
+                blaze_user_meta_kind::BLAZE_USER_META_ELF
+            ));
+            let elf = unsafe { &meta.variant.elf };
+            assert_eq!(!elf.build_id.is_null(), build_ids);
+            assert_eq!(elf.build_id_len != 0, build_ids);
+
+            let () = unsafe { blaze_user_output_free(result) };
+            let () = unsafe { blaze_normalizer_free(normalizer) };
+        }
+
+        test(true);
+        test(false);
+    }
+
+    /// Check that we can normalize kernel addresses.
+    #[test]
+    fn normalize_kernel_addrs() {
+        let normalizer = blaze_normalizer_new();
+        assert_ne!(normalizer, ptr::null_mut());
+
+        // No kernel module is ever loaded at such low addresses.
+        let addrs = [0x1, 0x2];
+        let result = unsafe {
+            blaze_normalize_kernel_addrs(normalizer, addrs.as_slice().as_ptr(), addrs.len())
+        };
+        if result.is_null() {
+            // `/proc/modules` may not be accessible in some
+            // environments.
+            let () = unsafe { blaze_normalizer_free(normalizer) };
+            return
+        }
+
+        let kernel_addrs = unsafe { &*result };
+        assert_eq!(kernel_addrs.meta_cnt, 1);
+        assert_eq!(kernel_addrs.output_cnt, 2);
+        let meta = unsafe { &*kernel_addrs.metas };
+        assert!(matches!(
+            meta.kind,
+            blaze_kernel_meta_kind::BLAZE_KERNEL_META_UNKNOWN
+        ));
+        let outputs =
+            unsafe { slice::from_raw_parts(kernel_addrs.outputs, kernel_addrs.output_cnt) };
+        assert_eq!(outputs[0].output, 0x1);
+        assert_eq!(outputs[1].output, 0x2);
+
+        let () = unsafe { blaze_kernel_output_free(result) };
+        let () = unsafe { blaze_normalizer_free(normalizer) };
+    }
+
     /// Check that we can normalize user space addresses.
     #[test]
     fn normalize_user_addrs() {
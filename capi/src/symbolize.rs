@@ -18,6 +18,7 @@ use blazesym::symbolize::GsymFile;
 use blazesym::symbolize::InlinedFn;
 use blazesym::symbolize::Input;
 use blazesym::symbolize::Kernel;
+use blazesym::symbolize::MountNsMode;
 use blazesym::symbolize::Process;
 use blazesym::symbolize::Source;
 use blazesym::symbolize::Sym;
@@ -25,6 +26,9 @@ use blazesym::symbolize::Symbolized;
 use blazesym::symbolize::Symbolizer;
 use blazesym::Addr;
 
+use crate::blaze_err;
+use crate::catch_panic;
+use crate::set_last_err;
 use crate::slice_from_user_array;
 
 
@@ -53,6 +57,7 @@ impl From<&blaze_symbolize_src_elf> for Elf {
         Self {
             path: unsafe { from_cstr(*path) },
             debug_syms: *debug_syms,
+            hot_ranges: None,
             _non_exhaustive: (),
         }
     }
@@ -83,6 +88,18 @@ pub struct blaze_symbolize_src_kernel {
     /// Whether or not to consult debug symbols from `kernel_image`
     /// to satisfy the request (if present).
     pub debug_syms: bool,
+    /// The path of a copy of `/proc/modules`, listing the loaded kernel
+    /// modules.
+    ///
+    /// Passing `NULL` will result in `"/proc/modules"` if `kallsyms` is
+    /// `NULL` as well and kernel modules being ignored otherwise.
+    pub modules: *const c_char,
+    /// The path of the directory containing kernel module files and a
+    /// `modules.dep` file.
+    ///
+    /// Passing `NULL` will result in `"/lib/modules/<release>/"` of the
+    /// running kernel.
+    pub module_dir: *const c_char,
 }
 
 impl From<&blaze_symbolize_src_kernel> for Kernel {
@@ -91,11 +108,16 @@ impl From<&blaze_symbolize_src_kernel> for Kernel {
             kallsyms,
             kernel_image,
             debug_syms,
+            modules,
+            module_dir,
         } = kernel;
         Self {
             kallsyms: (!kallsyms.is_null()).then(|| unsafe { from_cstr(*kallsyms) }),
             kernel_image: (!kernel_image.is_null()).then(|| unsafe { from_cstr(*kernel_image) }),
+            kaslr_offset: None,
             debug_syms: *debug_syms,
+            modules: (!modules.is_null()).then(|| unsafe { from_cstr(*modules) }),
+            module_dir: (!module_dir.is_null()).then(|| unsafe { from_cstr(*module_dir) }),
             _non_exhaustive: (),
         }
     }
@@ -243,6 +265,16 @@ pub struct blaze_sym {
     pub inlined_cnt: usize,
     /// An array of `inlined_cnt` symbolized inlined function calls.
     pub inlined: *const blaze_symbolize_inlined_fn,
+    /// The path to the module in which the symbol was found, or NULL
+    /// if unknown.
+    pub module: *const c_char,
+    /// The length of the build ID, in bytes.
+    pub build_id_len: usize,
+    /// The build ID of the module, or NULL if not available.
+    pub build_id: *const u8,
+    /// The offset of the symbolized address inside the module's file,
+    /// or 0 if it could not be determined.
+    pub file_offset: u64,
 }
 
 /// `blaze_result` is the result of symbolization for C API.
@@ -273,6 +305,38 @@ pub(crate) unsafe fn from_cstr(cstr: *const c_char) -> PathBuf {
 }
 
 
+/// The way in which files of a process residing in a different mount
+/// namespace are accessed.
+///
+/// C ABI compatible version of [`MountNsMode`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum blaze_mount_ns_mode {
+    /// Access files via `/proc/<pid>/root/`, leaving the mount
+    /// namespace of the calling process untouched.
+    ///
+    /// This mode is safe to use from multi-threaded programs. Absolute
+    /// symbolic links underneath `/proc/<pid>/root/` are resolved
+    /// against the root directory of the calling process, though.
+    BLAZE_MOUNT_NS_MODE_PROC_ROOT,
+    /// Temporarily enter the mount namespace of the process using
+    /// `setns(2)`.
+    ///
+    /// Doing so affects the calling process as a whole and fails if
+    /// the process is multi-threaded.
+    BLAZE_MOUNT_NS_MODE_ENTER,
+}
+
+impl From<blaze_mount_ns_mode> for MountNsMode {
+    fn from(mode: blaze_mount_ns_mode) -> Self {
+        match mode {
+            blaze_mount_ns_mode::BLAZE_MOUNT_NS_MODE_ENTER => MountNsMode::Enter,
+            blaze_mount_ns_mode::BLAZE_MOUNT_NS_MODE_PROC_ROOT => MountNsMode::ProcRoot,
+        }
+    }
+}
+
+
 /// Options for configuring `blaze_symbolizer` objects.
 #[repr(C)]
 #[derive(Debug)]
@@ -286,18 +350,29 @@ pub struct blaze_symbolizer_opts {
     /// Whether or not to transparently demangle symbols.
     ///
     /// Demangling happens on a best-effort basis. Currently supported
-    /// languages are Rust and C++ and the flag will have no effect if
-    /// the underlying language does not mangle symbols (such as C).
+    /// languages are Rust, C++, D, Swift, and Ada and the flag will have
+    /// no effect if the underlying language does not mangle symbols
+    /// (such as C).
     pub demangle: bool,
+    /// How to access files of processes residing in a different mount
+    /// namespace, as relevant for process symbolization (e.g., using
+    /// [`blaze_symbolize_process_virt_addrs`]).
+    ///
+    /// Multi-threaded programs must not use
+    /// [`blaze_mount_ns_mode::BLAZE_MOUNT_NS_MODE_ENTER`].
+    pub mount_ns_mode: blaze_mount_ns_mode,
 }
 
 
 /// Create an instance of a symbolizer.
 #[no_mangle]
 pub extern "C" fn blaze_symbolizer_new() -> *mut blaze_symbolizer {
-    let symbolizer = Symbolizer::new();
-    let symbolizer_box = Box::new(symbolizer);
-    Box::into_raw(symbolizer_box)
+    catch_panic(ptr::null_mut(), || {
+        let symbolizer = Symbolizer::new();
+        let symbolizer_box = Box::new(symbolizer);
+        let () = set_last_err(blaze_err::BLAZE_ERR_OK);
+        Box::into_raw(symbolizer_box)
+    })
 }
 
 /// Create an instance of a symbolizer with configurable options.
@@ -308,21 +383,26 @@ pub extern "C" fn blaze_symbolizer_new() -> *mut blaze_symbolizer {
 pub unsafe extern "C" fn blaze_symbolizer_new_opts(
     opts: *const blaze_symbolizer_opts,
 ) -> *mut blaze_symbolizer {
-    // SAFETY: The caller ensures that the pointer is valid.
-    let opts = unsafe { &*opts };
-    let blaze_symbolizer_opts {
-        code_info,
-        inlined_fns,
-        demangle,
-    } = opts;
-
-    let symbolizer = Symbolizer::builder()
-        .enable_code_info(*code_info)
-        .enable_inlined_fns(*inlined_fns)
-        .enable_demangling(*demangle)
-        .build();
-    let symbolizer_box = Box::new(symbolizer);
-    Box::into_raw(symbolizer_box)
+    catch_panic(ptr::null_mut(), || {
+        // SAFETY: The caller ensures that the pointer is valid.
+        let opts = unsafe { &*opts };
+        let blaze_symbolizer_opts {
+            code_info,
+            inlined_fns,
+            demangle,
+            mount_ns_mode,
+        } = opts;
+
+        let symbolizer = Symbolizer::builder()
+            .enable_code_info(*code_info)
+            .enable_inlined_fns(*inlined_fns)
+            .enable_demangling(*demangle)
+            .set_mount_ns_mode((*mount_ns_mode).into())
+            .build();
+        let symbolizer_box = Box::new(symbolizer);
+        let () = set_last_err(blaze_err::BLAZE_ERR_OK);
+        Box::into_raw(symbolizer_box)
+    })
 }
 
 /// Free an instance of blazesym a symbolizer for C API.
@@ -333,9 +413,11 @@ pub unsafe extern "C" fn blaze_symbolizer_new_opts(
 /// [`blaze_symbolizer_new_opts`].
 #[no_mangle]
 pub unsafe extern "C" fn blaze_symbolizer_free(symbolizer: *mut blaze_symbolizer) {
-    if !symbolizer.is_null() {
-        drop(unsafe { Box::from_raw(symbolizer) });
-    }
+    catch_panic((), || {
+        if !symbolizer.is_null() {
+            drop(unsafe { Box::from_raw(symbolizer) });
+        }
+    })
 }
 
 fn code_info_strtab_size(code_info: &Option<CodeInfo>) -> usize {
@@ -357,6 +439,13 @@ fn sym_strtab_size(sym: &Sym) -> usize {
     sym.name.len()
         + 1
         + code_info_strtab_size(&sym.code_info)
+        + sym
+            .module
+            .as_ref()
+            .map(|module| module.as_os_str().len() + 1)
+            .unwrap_or(0)
+        // Build IDs are stored NUL terminated as well, for simplicity.
+        + sym.build_id.as_ref().map(|id| id.len() + 1).unwrap_or(0)
         + sym
             .inlined
             .iter()
@@ -451,6 +540,18 @@ fn convert_symbolizedresults_to_c(results: Vec<Symbolized>) -> *const blaze_resu
                 convert_code_info(&sym.code_info, &mut sym_ref.code_info, &mut make_cstr);
                 sym_ref.inlined_cnt = sym.inlined.len();
                 sym_ref.inlined = inlined_last;
+                sym_ref.module = sym
+                    .module
+                    .as_ref()
+                    .map(|module| make_cstr(module.as_os_str()))
+                    .unwrap_or_else(ptr::null_mut);
+                sym_ref.build_id_len = sym.build_id.as_ref().map(|id| id.len()).unwrap_or(0);
+                sym_ref.build_id = sym
+                    .build_id
+                    .as_ref()
+                    .map(|id| make_cstr(OsStr::from_bytes(id)) as *const u8)
+                    .unwrap_or_else(ptr::null);
+                sym_ref.file_offset = sym.file_offset.unwrap_or(0);
 
                 for inlined in sym.inlined.iter() {
                     let inlined_ref = unsafe { &mut *inlined_last };
@@ -511,9 +612,18 @@ unsafe fn blaze_symbolize_impl(
     let result = symbolizer.symbolize(&src, input);
 
     match result {
-        Ok(results) if results.is_empty() => ptr::null(),
-        Ok(results) => convert_symbolizedresults_to_c(results),
-        Err(_err) => ptr::null(),
+        Ok(results) if results.is_empty() => {
+            let () = set_last_err(blaze_err::BLAZE_ERR_OK);
+            ptr::null()
+        }
+        Ok(results) => {
+            let () = set_last_err(blaze_err::BLAZE_ERR_OK);
+            convert_symbolizedresults_to_c(results)
+        }
+        Err(err) => {
+            let () = set_last_err(err.kind().into());
+            ptr::null()
+        }
     }
 }
 
@@ -524,6 +634,9 @@ unsafe fn blaze_symbolize_impl(
 /// number of input addresses. The caller should free the returned array by
 /// calling [`blaze_result_free`].
 ///
+/// On error, `NULL` is returned and `blaze_err_last` can be used to
+/// retrieve the reason.
+///
 /// # Safety
 /// `symbolizer` must have been allocated using [`blaze_symbolizer_new`] or
 /// [`blaze_symbolizer_new_opts`]. `src` must point to a valid
@@ -536,9 +649,11 @@ pub unsafe extern "C" fn blaze_symbolize_process_virt_addrs(
     addrs: *const Addr,
     addr_cnt: usize,
 ) -> *const blaze_result {
-    // SAFETY: The caller ensures that the pointer is valid.
-    let src = Source::from(Process::from(unsafe { &*src }));
-    unsafe { blaze_symbolize_impl(symbolizer, src, Input::AbsAddr(addrs), addr_cnt) }
+    catch_panic(ptr::null(), || {
+        // SAFETY: The caller ensures that the pointer is valid.
+        let src = Source::from(Process::from(unsafe { &*src }));
+        unsafe { blaze_symbolize_impl(symbolizer, src, Input::AbsAddr(addrs), addr_cnt) }
+    })
 }
 
 
@@ -548,6 +663,9 @@ pub unsafe extern "C" fn blaze_symbolize_process_virt_addrs(
 /// number of input addresses. The caller should free the returned array by
 /// calling [`blaze_result_free`].
 ///
+/// On error, `NULL` is returned and `blaze_err_last` can be used to
+/// retrieve the reason.
+///
 /// # Safety
 /// `symbolizer` must have been allocated using [`blaze_symbolizer_new`] or
 /// [`blaze_symbolizer_new_opts`]. `src` must point to a valid
@@ -560,9 +678,11 @@ pub unsafe extern "C" fn blaze_symbolize_kernel_virt_addrs(
     addrs: *const Addr,
     addr_cnt: usize,
 ) -> *const blaze_result {
-    // SAFETY: The caller ensures that the pointer is valid.
-    let src = Source::from(Kernel::from(unsafe { &*src }));
-    unsafe { blaze_symbolize_impl(symbolizer, src, Input::AbsAddr(addrs), addr_cnt) }
+    catch_panic(ptr::null(), || {
+        // SAFETY: The caller ensures that the pointer is valid.
+        let src = Source::from(Kernel::from(unsafe { &*src }));
+        unsafe { blaze_symbolize_impl(symbolizer, src, Input::AbsAddr(addrs), addr_cnt) }
+    })
 }
 
 
@@ -572,6 +692,9 @@ pub unsafe extern "C" fn blaze_symbolize_kernel_virt_addrs(
 /// number of input addresses. The caller should free the returned array by
 /// calling [`blaze_result_free`].
 ///
+/// On error, `NULL` is returned and `blaze_err_last` can be used to
+/// retrieve the reason.
+///
 /// # Safety
 /// `symbolizer` must have been allocated using [`blaze_symbolizer_new`] or
 /// [`blaze_symbolizer_new_opts`]. `src` must point to a valid
@@ -584,9 +707,44 @@ pub unsafe extern "C" fn blaze_symbolize_elf_file_addrs(
     addrs: *const Addr,
     addr_cnt: usize,
 ) -> *const blaze_result {
-    // SAFETY: The caller ensures that the pointer is valid.
-    let src = Source::from(Elf::from(unsafe { &*src }));
-    unsafe { blaze_symbolize_impl(symbolizer, src, Input::VirtOffset(addrs), addr_cnt) }
+    catch_panic(ptr::null(), || {
+        // SAFETY: The caller ensures that the pointer is valid.
+        let src = Source::from(Elf::from(unsafe { &*src }));
+        unsafe { blaze_symbolize_impl(symbolizer, src, Input::VirtOffset(addrs), addr_cnt) }
+    })
+}
+
+
+/// Symbolize file offsets in an ELF file.
+///
+/// File offsets are converted to virtual offsets using the ELF file's
+/// program headers. This is useful for symbolizing the output of
+/// address normalization, for example.
+///
+/// Return an array of [`blaze_result`] with the same size as the
+/// number of input offsets. The caller should free the returned array by
+/// calling [`blaze_result_free`].
+///
+/// On error, `NULL` is returned and `blaze_err_last` can be used to
+/// retrieve the reason.
+///
+/// # Safety
+/// `symbolizer` must have been allocated using [`blaze_symbolizer_new`] or
+/// [`blaze_symbolizer_new_opts`]. `src` must point to a valid
+/// [`blaze_symbolize_src_elf`] object. `offsets` must represent an array of
+/// `offset_cnt` objects.
+#[no_mangle]
+pub unsafe extern "C" fn blaze_symbolize_elf_file_offsets(
+    symbolizer: *mut blaze_symbolizer,
+    src: *const blaze_symbolize_src_elf,
+    offsets: *const u64,
+    offset_cnt: usize,
+) -> *const blaze_result {
+    catch_panic(ptr::null(), || {
+        // SAFETY: The caller ensures that the pointer is valid.
+        let src = Source::from(Elf::from(unsafe { &*src }));
+        unsafe { blaze_symbolize_impl(symbolizer, src, Input::FileOffset(offsets), offset_cnt) }
+    })
 }
 
 
@@ -596,6 +754,9 @@ pub unsafe extern "C" fn blaze_symbolize_elf_file_addrs(
 /// number of input addresses. The caller should free the returned array by
 /// calling [`blaze_result_free`].
 ///
+/// On error, `NULL` is returned and `blaze_err_last` can be used to
+/// retrieve the reason.
+///
 /// # Safety
 /// `symbolizer` must have been allocated using [`blaze_symbolizer_new`] or
 /// [`blaze_symbolizer_new_opts`]. `src` must point to a valid
@@ -608,11 +769,13 @@ pub unsafe extern "C" fn blaze_symbolize_gsym_data_file_addrs(
     addrs: *const Addr,
     addr_cnt: usize,
 ) -> *const blaze_result {
-    // SAFETY: The caller ensures that the pointer is valid. The `GsymData`
-    //         lifetime is entirely conjured up, but the object only needs to be
-    //         valid for the call.
-    let src = Source::from(GsymData::from(unsafe { &*src }));
-    unsafe { blaze_symbolize_impl(symbolizer, src, Input::VirtOffset(addrs), addr_cnt) }
+    catch_panic(ptr::null(), || {
+        // SAFETY: The caller ensures that the pointer is valid. The `GsymData`
+        //         lifetime is entirely conjured up, but the object only needs to be
+        //         valid for the call.
+        let src = Source::from(GsymData::from(unsafe { &*src }));
+        unsafe { blaze_symbolize_impl(symbolizer, src, Input::VirtOffset(addrs), addr_cnt) }
+    })
 }
 
 
@@ -622,6 +785,9 @@ pub unsafe extern "C" fn blaze_symbolize_gsym_data_file_addrs(
 /// number of input addresses. The caller should free the returned array by
 /// calling [`blaze_result_free`].
 ///
+/// On error, `NULL` is returned and `blaze_err_last` can be used to
+/// retrieve the reason.
+///
 /// # Safety
 /// `symbolizer` must have been allocated using [`blaze_symbolizer_new`] or
 /// [`blaze_symbolizer_new_opts`]. `src` must point to a valid
@@ -634,9 +800,11 @@ pub unsafe extern "C" fn blaze_symbolize_gsym_file_file_addrs(
     addrs: *const Addr,
     addr_cnt: usize,
 ) -> *const blaze_result {
-    // SAFETY: The caller ensures that the pointer is valid.
-    let src = Source::from(GsymFile::from(unsafe { &*src }));
-    unsafe { blaze_symbolize_impl(symbolizer, src, Input::VirtOffset(addrs), addr_cnt) }
+    catch_panic(ptr::null(), || {
+        // SAFETY: The caller ensures that the pointer is valid.
+        let src = Source::from(GsymFile::from(unsafe { &*src }));
+        unsafe { blaze_symbolize_impl(symbolizer, src, Input::VirtOffset(addrs), addr_cnt) }
+    })
 }
 
 
@@ -647,13 +815,16 @@ pub unsafe extern "C" fn blaze_symbolize_gsym_file_file_addrs(
 /// variants.
 #[no_mangle]
 pub unsafe extern "C" fn blaze_result_free(results: *const blaze_result) {
-    if results.is_null() {
-        return
-    }
+    catch_panic((), || {
+        if results.is_null() {
+            return
+        }
 
-    let raw_buf_with_sz = unsafe { (results as *mut u8).offset(-(mem::size_of::<u64>() as isize)) };
-    let sz = unsafe { *(raw_buf_with_sz as *mut u64) } as usize + mem::size_of::<u64>();
-    unsafe { dealloc(raw_buf_with_sz, Layout::from_size_align(sz, 8).unwrap()) };
+        let raw_buf_with_sz =
+            unsafe { (results as *mut u8).offset(-(mem::size_of::<u64>() as isize)) };
+        let sz = unsafe { *(raw_buf_with_sz as *mut u64) } as usize + mem::size_of::<u64>();
+        unsafe { dealloc(raw_buf_with_sz, Layout::from_size_align(sz, 8).unwrap()) };
+    })
 }
 
 
@@ -671,6 +842,9 @@ mod tests {
 
     use blazesym::inspect;
     use blazesym::symbolize::Reason;
+    use blazesym::symbolize::SrcLang;
+
+    use crate::blaze_err_last;
 
 
     /// Exercise the `Debug` representation of various types.
@@ -689,10 +863,12 @@ mod tests {
             kallsyms: ptr::null(),
             kernel_image: ptr::null(),
             debug_syms: true,
+            modules: ptr::null(),
+            module_dir: ptr::null(),
         };
         assert_eq!(
             format!("{kernel:?}"),
-            "blaze_symbolize_src_kernel { kallsyms: 0x0, kernel_image: 0x0, debug_syms: true }"
+            "blaze_symbolize_src_kernel { kallsyms: 0x0, kernel_image: 0x0, debug_syms: true, modules: 0x0, module_dir: 0x0 }"
         );
 
         let process = blaze_symbolize_src_process {
@@ -731,10 +907,14 @@ mod tests {
             },
             inlined_cnt: 0,
             inlined: ptr::null(),
+            module: ptr::null(),
+            build_id_len: 0,
+            build_id: ptr::null(),
+            file_offset: 0,
         };
         assert_eq!(
             format!("{sym:?}"),
-            "blaze_sym { name: 0x0, addr: 4919, offset: 24, code_info: blaze_symbolize_code_info { dir: 0x0, file: 0x0, line: 42, column: 1 }, inlined_cnt: 0, inlined: 0x0 }"
+            "blaze_sym { name: 0x0, addr: 4919, offset: 24, code_info: blaze_symbolize_code_info { dir: 0x0, file: 0x0, line: 42, column: 1 }, inlined_cnt: 0, inlined: 0x0, module: 0x0, build_id_len: 0, build_id: 0x0, file_offset: 0 }"
         );
 
         let inlined = blaze_symbolize_inlined_fn {
@@ -758,10 +938,11 @@ mod tests {
             code_info: false,
             inlined_fns: false,
             demangle: true,
+            mount_ns_mode: blaze_mount_ns_mode::BLAZE_MOUNT_NS_MODE_ENTER,
         };
         assert_eq!(
             format!("{opts:?}"),
-            "blaze_symbolizer_opts { code_info: false, inlined_fns: false, demangle: true }"
+            "blaze_symbolizer_opts { code_info: false, inlined_fns: false, demangle: true, mount_ns_mode: BLAZE_MOUNT_NS_MODE_ENTER }"
         );
     }
 
@@ -773,19 +954,27 @@ mod tests {
             kallsyms: ptr::null(),
             kernel_image: ptr::null(),
             debug_syms: true,
+            modules: ptr::null(),
+            module_dir: ptr::null(),
         };
         let kernel = Kernel::from(&kernel);
         assert_eq!(kernel.kallsyms, None);
         assert_eq!(kernel.kernel_image, None);
+        assert_eq!(kernel.modules, None);
+        assert_eq!(kernel.module_dir, None);
 
         let kernel = blaze_symbolize_src_kernel {
             kallsyms: b"/proc/kallsyms\0" as *const _ as *const c_char,
             kernel_image: b"/boot/image\0" as *const _ as *const c_char,
             debug_syms: false,
+            modules: b"/proc/modules\0" as *const _ as *const c_char,
+            module_dir: b"/lib/modules/6.5.0\0" as *const _ as *const c_char,
         };
         let kernel = Kernel::from(&kernel);
         assert_eq!(kernel.kallsyms, Some(PathBuf::from("/proc/kallsyms")));
         assert_eq!(kernel.kernel_image, Some(PathBuf::from("/boot/image")));
+        assert_eq!(kernel.modules, Some(PathBuf::from("/proc/modules")));
+        assert_eq!(kernel.module_dir, Some(PathBuf::from("/lib/modules/6.5.0")));
     }
 
     /// Test the Rust to C symbol conversion.
@@ -829,12 +1018,22 @@ mod tests {
                     code_info,
                     inlined_cnt,
                     inlined,
+                    module,
+                    build_id_len,
+                    build_id,
+                    file_offset,
                 } = sym;
 
                 let () = touch_cstr(*name);
                 let _x = touch(addr);
                 let _x = touch(offset);
                 let () = touch_code_info(code_info);
+                let () = touch_cstr(*module);
+                if !build_id.is_null() {
+                    let id = unsafe { slice::from_raw_parts(*build_id, *build_id_len) };
+                    let _x = black_box(id);
+                }
+                let _x = touch(file_offset);
 
                 for j in 0..*inlined_cnt {
                     let inlined_fn = unsafe { &*inlined.add(j) };
@@ -857,11 +1056,13 @@ mod tests {
             addr: 0x1337,
             offset: 0x1338,
             size: Some(42),
+            lang: SrcLang::Unknown,
             code_info: Some(CodeInfo {
                 dir: None,
                 file: OsStr::new("a-file").into(),
                 line: Some(42),
                 column: Some(43),
+                context: None,
                 _non_exhaustive: (),
             }),
             inlined: vec![InlinedFn {
@@ -871,11 +1072,15 @@ mod tests {
                     file: OsStr::new("another-file").into(),
                     line: Some(42),
                     column: Some(43),
+                    context: None,
                     _non_exhaustive: (),
                 }),
                 _non_exhaustive: (),
             }]
             .into_boxed_slice(),
+            module: Some(Path::new("/some/module").into()),
+            build_id: Some(vec![0x01, 0x02, 0x03].into()),
+            file_offset: Some(0x2337),
             _non_exhaustive: (),
         })];
         let result = convert_symbolizedresults_to_c(results);
@@ -890,6 +1095,7 @@ mod tests {
                 addr: 0x1337,
                 offset: 0x1338,
                 size: None,
+                lang: SrcLang::Unknown,
                 code_info: None,
                 inlined: vec![InlinedFn {
                     name: "inlined_fn".into(),
@@ -897,6 +1103,9 @@ mod tests {
                     _non_exhaustive: (),
                 }]
                 .into_boxed_slice(),
+                module: None,
+                build_id: None,
+                file_offset: None,
                 _non_exhaustive: (),
             }),
             Symbolized::Unknown(Reason::InvalidFileOffset),
@@ -921,6 +1130,7 @@ mod tests {
             code_info: false,
             inlined_fns: false,
             demangle: true,
+            mount_ns_mode: blaze_mount_ns_mode::BLAZE_MOUNT_NS_MODE_ENTER,
         };
         let symbolizer = unsafe { blaze_symbolizer_new_opts(&opts) };
         let () = unsafe { blaze_symbolizer_free(symbolizer) };
@@ -997,6 +1207,33 @@ mod tests {
         };
         test(symbolize, true);
 
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join("data")
+            .join("test-stable-addresses.bin");
+        let path_c = CString::new(path.to_str().unwrap()).unwrap();
+        let elf_src = blaze_symbolize_src_elf {
+            path: path_c.as_ptr(),
+            debug_syms: true,
+        };
+        let symbolize = |symbolizer, addrs, addr_cnt| {
+            // The binary's only loadable segment maps file offset
+            // 0x1000 to virtual address 0x2000000.
+            let offsets = unsafe { slice::from_raw_parts(addrs, addr_cnt) }
+                .iter()
+                .map(|addr| addr - 0x2000000 + 0x1000)
+                .collect::<Vec<_>>();
+            unsafe {
+                blaze_symbolize_elf_file_offsets(
+                    symbolizer,
+                    &elf_src,
+                    offsets.as_ptr(),
+                    offsets.len(),
+                )
+            }
+        };
+        test(symbolize, true);
+
         let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
             .join("..")
             .join("data")
@@ -1034,6 +1271,7 @@ mod tests {
                 code_info: true,
                 inlined_fns: true,
                 demangle: false,
+                mount_ns_mode: blaze_mount_ns_mode::BLAZE_MOUNT_NS_MODE_ENTER,
             };
 
             let path_c = CString::new(path.to_str().unwrap()).unwrap();
@@ -1081,6 +1319,7 @@ mod tests {
                 code_info: true,
                 inlined_fns: true,
                 demangle: true,
+                mount_ns_mode: blaze_mount_ns_mode::BLAZE_MOUNT_NS_MODE_ENTER,
             };
 
             let symbolizer = unsafe { blaze_symbolizer_new_opts(&opts) };
@@ -1150,34 +1389,69 @@ mod tests {
     /// Make sure that we can symbolize an address in a process.
     #[test]
     fn symbolize_in_process() {
-        let process_src = blaze_symbolize_src_process {
-            pid: 0,
-            debug_syms: true,
+        fn test(symbolizer: *mut blaze_symbolizer) {
+            let process_src = blaze_symbolize_src_process {
+                pid: 0,
+                debug_syms: true,
+            };
+
+            let addrs = [blaze_symbolizer_new as Addr];
+            let result = unsafe {
+                blaze_symbolize_process_virt_addrs(
+                    symbolizer,
+                    &process_src,
+                    addrs.as_ptr(),
+                    addrs.len(),
+                )
+            };
+
+            assert!(!result.is_null());
+
+            let result = unsafe { &*result };
+            assert_eq!(result.cnt, 1);
+            let syms = unsafe { slice::from_raw_parts(result.syms.as_ptr(), result.cnt) };
+            let sym = &syms[0];
+            assert_eq!(
+                unsafe { CStr::from_ptr(sym.name) },
+                CStr::from_bytes_with_nul(b"blaze_symbolizer_new\0").unwrap()
+            );
+
+            let () = unsafe { blaze_result_free(result) };
+            let () = unsafe { blaze_symbolizer_free(symbolizer) };
+        }
+
+        test(blaze_symbolizer_new());
+
+        // Files of the process may also be accessed without entering
+        // its mount namespace.
+        let opts = blaze_symbolizer_opts {
+            code_info: true,
+            inlined_fns: true,
+            demangle: true,
+            mount_ns_mode: blaze_mount_ns_mode::BLAZE_MOUNT_NS_MODE_PROC_ROOT,
         };
+        test(unsafe { blaze_symbolizer_new_opts(&opts) });
+    }
 
-        let symbolizer = blaze_symbolizer_new();
-        let addrs = [blaze_symbolizer_new as Addr];
-        let result = unsafe {
-            blaze_symbolize_process_virt_addrs(
-                symbolizer,
-                &process_src,
-                addrs.as_ptr(),
-                addrs.len(),
-            )
+    /// Check that errors are reported via `blaze_err_last`.
+    #[test]
+    fn symbolize_error_reporting() {
+        let path = CString::new("/does/not/exist").unwrap();
+        let elf_src = blaze_symbolize_src_elf {
+            path: path.as_ptr(),
+            debug_syms: false,
         };
 
-        assert!(!result.is_null());
+        let symbolizer = blaze_symbolizer_new();
+        assert_eq!(blaze_err_last(), blaze_err::BLAZE_ERR_OK);
 
-        let result = unsafe { &*result };
-        assert_eq!(result.cnt, 1);
-        let syms = unsafe { slice::from_raw_parts(result.syms.as_ptr(), result.cnt) };
-        let sym = &syms[0];
-        assert_eq!(
-            unsafe { CStr::from_ptr(sym.name) },
-            CStr::from_bytes_with_nul(b"blaze_symbolizer_new\0").unwrap()
-        );
+        let addrs = [0x1000];
+        let result = unsafe {
+            blaze_symbolize_elf_file_addrs(symbolizer, &elf_src, addrs.as_ptr(), addrs.len())
+        };
+        assert!(result.is_null());
+        assert_eq!(blaze_err_last(), blaze_err::BLAZE_ERR_NOT_FOUND);
 
-        let () = unsafe { blaze_result_free(result) };
         let () = unsafe { blaze_symbolizer_free(symbolizer) };
     }
 }
@@ -10,6 +10,7 @@ use clap::ArgAction;
 use clap::Args as Arguments;
 use clap::Parser;
 use clap::Subcommand;
+use clap::ValueEnum;
 
 
 /// Parse a PID from a string.
@@ -25,7 +26,7 @@ fn parse_pid(s: &str) -> Result<Pid> {
 }
 
 /// Parse an address from a string.
-fn parse_addr(s: &str) -> Result<Addr> {
+pub fn parse_addr(s: &str) -> Result<Addr> {
     // In our world addresses are always represented in hex, with or without 0x
     // prefix.
     Addr::from_str_radix(s.trim_start_matches("0x"), 16)
@@ -47,8 +48,9 @@ pub struct Args {
 
 #[derive(Debug, Subcommand)]
 pub enum Command {
+    /// Run the symbol cache daemon.
+    Daemon(Daemon),
     /// Normalize one or more addresses.
-    #[command(subcommand)]
     Normalize(Normalize),
     /// Symbolize one or more addresses.
     #[command(subcommand)]
@@ -56,9 +58,35 @@ pub enum Command {
 }
 
 
+/// A type representing the `daemon` command.
+#[derive(Debug, Arguments)]
+pub struct Daemon {
+    /// The path of the Unix domain socket to listen on.
+    #[clap(short, long)]
+    pub socket: PathBuf,
+    /// The directory in which to store symbol indexes.
+    ///
+    /// The directory should be backed by shared memory.
+    #[clap(long, default_value = "/dev/shm")]
+    pub shm_dir: PathBuf,
+}
+
+
 /// A type representing the `normalize` command.
+///
+/// User space addresses can be normalized either using the `user`
+/// sub-command or by providing a PID directly.
+#[derive(Debug, Arguments)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Normalize {
+    #[command(subcommand)]
+    pub command: Option<NormalizeCommand>,
+    #[command(flatten)]
+    pub user: Option<User>,
+}
+
 #[derive(Debug, Subcommand)]
-pub enum Normalize {
+pub enum NormalizeCommand {
     /// Normalize user space addresses.
     User(User),
 }
@@ -70,8 +98,14 @@ pub struct User {
     #[arg(value_parser = parse_pid)]
     pub pid: Pid,
     /// The addresses to normalize.
+    ///
+    /// If no addresses are provided, they are read from standard input,
+    /// separated by white spaces.
     #[arg(value_parser = parse_addr)]
     pub addrs: Vec<Addr>,
+    /// Emit output in JSON format.
+    #[clap(long)]
+    pub json: bool,
 }
 
 
@@ -83,6 +117,20 @@ pub enum Symbolize {
     Process(Process),
 }
 
+/// Options common to all symbolization sources.
+#[derive(Debug, Arguments)]
+pub struct SymbolizeOpts {
+    /// Disable the reporting of inlined function calls.
+    ///
+    /// Only the outermost function is reported for each address, which
+    /// may speed up symbolization.
+    #[clap(long)]
+    pub no_inlined_fns: bool,
+    /// Emit output in JSON format.
+    #[clap(long)]
+    pub json: bool,
+}
+
 #[derive(Debug, Arguments)]
 pub struct Elf {
     /// The path to the ELF file.
@@ -92,9 +140,12 @@ pub struct Elf {
     ///
     /// Addresses are assumed to already be normalized to the file
     /// itself (i.e., with relocation and address randomization effects
-    /// removed).
+    /// removed). If no addresses are provided, they are read from
+    /// standard input, separated by white spaces.
     #[arg(value_parser = parse_addr)]
     pub addrs: Vec<Addr>,
+    #[command(flatten)]
+    pub opts: SymbolizeOpts,
 }
 
 #[derive(Debug, Arguments)]
@@ -106,9 +157,12 @@ pub struct Gsym {
     ///
     /// Addresses are assumed to already be normalized to the file
     /// itself (i.e., with relocation and address randomization effects
-    /// removed).
+    /// removed). If no addresses are provided, they are read from
+    /// standard input, separated by white spaces.
     #[arg(value_parser = parse_addr)]
     pub addrs: Vec<Addr>,
+    #[command(flatten)]
+    pub opts: SymbolizeOpts,
 }
 
 #[derive(Debug, Arguments)]
@@ -118,6 +172,27 @@ pub struct Process {
     #[arg(value_parser = parse_pid)]
     pub pid: Pid,
     /// The addresses to symbolize.
+    ///
+    /// If no addresses are provided, they are read from standard input,
+    /// separated by white spaces.
     #[arg(value_parser = parse_addr)]
     pub addrs: Vec<Addr>,
+    /// How to access files of a process residing in a different mount
+    /// namespace (e.g., inside of a container).
+    #[clap(long, value_enum, default_value = "proc-root")]
+    pub mount_ns: MountNs,
+    #[command(flatten)]
+    pub opts: SymbolizeOpts,
+}
+
+/// The way in which files of a process residing in a different mount
+/// namespace are accessed.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum MountNs {
+    /// Temporarily enter the mount namespace of the process.
+    ///
+    /// Doing so requires the `CAP_SYS_ADMIN` capability.
+    Enter,
+    /// Access files via `/proc/<pid>/root/`.
+    ProcRoot,
 }
@@ -2,9 +2,14 @@
 
 mod args;
 
+use std::io::stdin;
+use std::io::Read as _;
+
+use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
 
+use blazesym::daemon::Server;
 use blazesym::normalize;
 use blazesym::normalize::Normalizer;
 use blazesym::symbolize;
@@ -13,6 +18,9 @@ use blazesym::Addr;
 
 use clap::Parser as _;
 
+use serde_json::json;
+use serde_json::Value;
+
 use tracing::subscriber::set_global_default as set_global_subscriber;
 use tracing_subscriber::filter::LevelFilter;
 use tracing_subscriber::fmt::format::FmtSpan;
@@ -39,32 +47,115 @@ fn format_build_id(build_id: Option<&[u8]>) -> String {
     }
 }
 
+/// Retrieve the addresses to work with: those provided on the command
+/// line or, if there are none, those read from standard input.
+fn input_addrs(addrs: Vec<Addr>) -> Result<Vec<Addr>> {
+    if !addrs.is_empty() {
+        return Ok(addrs)
+    }
+
+    let mut input = String::new();
+    let _count = stdin()
+        .read_to_string(&mut input)
+        .context("failed to read addresses from standard input")?;
+    input.split_whitespace().map(args::parse_addr).collect()
+}
+
+/// Print the provided JSON values as an array.
+fn print_json(values: Vec<Value>) -> Result<()> {
+    let json = serde_json::to_string(&Value::Array(values)).context("failed to format JSON")?;
+    println!("{json}");
+    Ok(())
+}
+
+/// The handler for the 'daemon' command.
+fn daemon(daemon: args::Daemon) -> Result<()> {
+    let args::Daemon { socket, shm_dir } = daemon;
+    let server = Server::bind(socket, shm_dir).context("failed to start cache daemon")?;
+    let () = server
+        .run()
+        .context("failed to serve cache daemon clients")?;
+    Ok(())
+}
+
+/// Convert the normalization result for `addr` into JSON.
+fn normalized_json(addr: Addr, output: u64, meta: &normalize::UserMeta) -> Value {
+    match meta {
+        normalize::UserMeta::Apk(normalize::Apk { path, elf_path, .. }) => json!({
+            "addr": format!("{addr:#x}"),
+            "type": "apk",
+            "file_offset": format!("{output:#x}"),
+            "path": path.to_string_lossy(),
+            "elf_path": elf_path.as_ref().map(|path| path.to_string_lossy()),
+        }),
+        normalize::UserMeta::Elf(normalize::Elf { path, build_id, .. }) => json!({
+            "addr": format!("{addr:#x}"),
+            "type": "elf",
+            "file_offset": format!("{output:#x}"),
+            "path": path.to_string_lossy(),
+            "build_id": build_id.as_deref().map(format_build_id_bytes),
+        }),
+        normalize::UserMeta::Unknown(normalize::Unknown { .. }) => json!({
+            "addr": format!("{addr:#x}"),
+            "type": "unknown",
+        }),
+        // This is a bug and should be reported as such.
+        _ => panic!("encountered unsupported user meta data: {meta:?}"),
+    }
+}
+
+/// The handler for the 'normalize' command.
 fn normalize(normalize: args::Normalize) -> Result<()> {
+    let args::Normalize { command, user } = normalize;
+    let args::User { pid, addrs, json } = match (command, user) {
+        (Some(args::NormalizeCommand::User(user)), None) | (None, Some(user)) => user,
+        (Some(..), Some(..)) | (None, None) => {
+            bail!("either a PID or a sub-command has to be provided")
+        }
+    };
+    let addrs = input_addrs(addrs)?;
+
     let normalizer = Normalizer::new();
-    match normalize {
-        args::Normalize::User(args::User { pid, addrs }) => {
-            let normalized = normalizer
-                .normalize_user_addrs(pid, addrs.as_slice())
-                .context("failed to normalize addresses")?;
-            for (addr, (output, meta_idx)) in addrs.iter().zip(&normalized.outputs) {
-                print!("{addr:#016x}: ");
-
-                let meta = &normalized.meta[*meta_idx];
-                match meta {
-                    normalize::UserMeta::Apk(normalize::Apk { path, .. }) => {
-                        println!("file offset {output:#x} in {}", path.display())
-                    }
-                    normalize::UserMeta::Elf(normalize::Elf { path, build_id, .. }) => {
-                        let build_id = format_build_id(build_id.as_deref());
-                        println!("file offset {output:#x} in {}{build_id}", path.display())
-                    }
-                    normalize::UserMeta::Unknown(normalize::Unknown { .. }) => {
-                        println!("<unknown>")
-                    }
-                    // This is a bug and should be reported as such.
-                    _ => panic!("encountered unsupported user meta data: {meta:?}"),
+    let normalized = normalizer
+        .normalize_user_addrs(pid, addrs.as_slice())
+        .context("failed to normalize addresses")?;
+
+    if json {
+        let values = addrs
+            .iter()
+            .zip(&normalized.outputs)
+            .map(|(addr, (output, meta_idx))| {
+                normalized_json(*addr, *output, &normalized.meta[*meta_idx])
+            })
+            .collect();
+        return print_json(values)
+    }
+
+    for (addr, (output, meta_idx)) in addrs.iter().zip(&normalized.outputs) {
+        print!("{addr:#016x}: ");
+
+        let meta = &normalized.meta[*meta_idx];
+        match meta {
+            normalize::UserMeta::Apk(normalize::Apk { path, elf_path, .. }) => {
+                if let Some(elf_path) = elf_path {
+                    println!(
+                        "file offset {output:#x} in {}!/{}",
+                        path.display(),
+                        elf_path.display()
+                    )
+                } else {
+                    println!("file offset {output:#x} in {}", path.display())
                 }
             }
+            normalize::UserMeta::Elf(normalize::Elf { path, build_id, .. }) => {
+                let build_id = format_build_id(build_id.as_deref());
+                println!("file offset {output:#x} in {}{build_id}", path.display())
+            }
+            normalize::UserMeta::Unknown(normalize::Unknown { .. }) => {
+                println!("<unknown>")
+            }
+            // This is a bug and should be reported as such.
+            _ => panic!("encountered unsupported user meta data: {meta:?}"),
         }
     }
     Ok(())
@@ -107,34 +198,107 @@ fn print_frame(
     }
 }
 
+/// Convert source code location information into JSON.
+fn code_info_json(code_info: &Option<symbolize::CodeInfo>) -> Value {
+    match code_info {
+        Some(code_info) => json!({
+            "path": code_info.to_path().to_string_lossy(),
+            "line": code_info.line,
+            "column": code_info.column,
+        }),
+        None => Value::Null,
+    }
+}
+
+/// Convert the symbolization result for `input_addr` into JSON.
+fn symbolized_json(input_addr: Addr, sym: &symbolize::Symbolized) -> Value {
+    match sym {
+        symbolize::Symbolized::Sym(symbolize::Sym {
+            name,
+            addr,
+            offset,
+            code_info,
+            inlined,
+            ..
+        }) => json!({
+            "addr": format!("{input_addr:#x}"),
+            "symbol": {
+                "name": name,
+                "addr": format!("{addr:#x}"),
+                "offset": format!("{offset:#x}"),
+                "code_info": code_info_json(code_info),
+                "inlined": inlined
+                    .iter()
+                    .map(|frame| json!({
+                        "name": frame.name,
+                        "code_info": code_info_json(&frame.code_info),
+                    }))
+                    .collect::<Vec<_>>(),
+            },
+        }),
+        symbolize::Symbolized::Unknown(reason) => json!({
+            "addr": format!("{input_addr:#x}"),
+            "symbol": null,
+            "reason": reason.to_string(),
+        }),
+    }
+}
+
 /// The handler for the 'symbolize' command.
 fn symbolize(symbolize: args::Symbolize) -> Result<()> {
-    let symbolizer = Symbolizer::new();
-    let (src, input, addrs) = match symbolize {
-        args::Symbolize::Elf(args::Elf { path, ref addrs }) => {
+    type MakeInput = for<'addrs> fn(&'addrs [Addr]) -> symbolize::Input<&'addrs [Addr]>;
+
+    let (src, addrs, make_input, mount_ns, opts) = match symbolize {
+        args::Symbolize::Elf(args::Elf { path, addrs, opts }) => {
             let src = symbolize::Source::from(symbolize::Elf::new(path));
-            let addrs = addrs.as_slice();
-            let input = symbolize::Input::VirtOffset(addrs);
-            (src, input, addrs)
+            let mount_ns = symbolize::MountNsMode::default();
+            let make_input: MakeInput = |addrs| symbolize::Input::VirtOffset(addrs);
+            (src, addrs, make_input, mount_ns, opts)
         }
-        args::Symbolize::Gsym(args::Gsym { path, ref addrs }) => {
+        args::Symbolize::Gsym(args::Gsym { path, addrs, opts }) => {
             let src = symbolize::Source::from(symbolize::GsymFile::new(path));
-            let addrs = addrs.as_slice();
-            let input = symbolize::Input::VirtOffset(addrs);
-            (src, input, addrs)
+            let mount_ns = symbolize::MountNsMode::default();
+            let make_input: MakeInput = |addrs| symbolize::Input::VirtOffset(addrs);
+            (src, addrs, make_input, mount_ns, opts)
         }
-        args::Symbolize::Process(args::Process { pid, ref addrs }) => {
+        args::Symbolize::Process(args::Process {
+            pid,
+            addrs,
+            mount_ns,
+            opts,
+        }) => {
             let src = symbolize::Source::from(symbolize::Process::new(pid));
-            let addrs = addrs.as_slice();
-            let input = symbolize::Input::AbsAddr(addrs);
-            (src, input, addrs)
+            let mount_ns = match mount_ns {
+                args::MountNs::Enter => symbolize::MountNsMode::Enter,
+                args::MountNs::ProcRoot => symbolize::MountNsMode::ProcRoot,
+            };
+            let make_input: MakeInput = |addrs| symbolize::Input::AbsAddr(addrs);
+            (src, addrs, make_input, mount_ns, opts)
         }
     };
+    let addrs = input_addrs(addrs)?;
 
+    let args::SymbolizeOpts {
+        no_inlined_fns,
+        json,
+    } = opts;
+    let symbolizer = Symbolizer::builder()
+        .enable_inlined_fns(!no_inlined_fns)
+        .set_mount_ns_mode(mount_ns)
+        .build();
     let syms = symbolizer
-        .symbolize(&src, input)
+        .symbolize(&src, make_input(&addrs))
         .context("failed to symbolize addresses")?;
 
+    if json {
+        let values = addrs
+            .iter()
+            .zip(&syms)
+            .map(|(input_addr, sym)| symbolized_json(*input_addr, sym))
+            .collect();
+        return print_json(values)
+    }
+
     for (input_addr, sym) in addrs.iter().copied().zip(syms) {
         match sym {
             symbolize::Symbolized::Sym(symbolize::Sym {
@@ -178,6 +342,7 @@ fn main() -> Result<()> {
         set_global_subscriber(subscriber).with_context(|| "failed to set tracing subscriber")?;
 
     match args.command {
+        args::Command::Daemon(daemon) => self::daemon(daemon),
         args::Command::Normalize(normalize) => self::normalize(normalize),
         args::Command::Symbolize(symbolize) => self::symbolize(symbolize),
     }
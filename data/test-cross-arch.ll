@@ -0,0 +1,43 @@
+; Test program used for checking symbolization of ELF files created for
+; architectures other than that of the host. The build script compiles
+; it into relocatable objects for aarch64 (little-endian) and s390x
+; (big-endian) using `llc`.
+
+define dso_local i32 @cross_arch_factorial(i32 %n) !dbg !10 {
+entry:
+  %cmp = icmp sle i32 %n, 1, !dbg !14
+  br i1 %cmp, label %done, label %recurse, !dbg !14
+
+recurse:
+  %sub = sub nsw i32 %n, 1, !dbg !15
+  %call = call i32 @cross_arch_factorial(i32 %sub), !dbg !15
+  %mul = mul nsw i32 %n, %call, !dbg !16
+  ret i32 %mul, !dbg !16
+
+done:
+  ret i32 1, !dbg !17
+}
+
+define dso_local i32 @cross_arch_main() !dbg !20 {
+entry:
+  %call = call i32 @cross_arch_factorial(i32 5), !dbg !21
+  ret i32 %call, !dbg !22
+}
+
+!llvm.dbg.cu = !{!0}
+!llvm.module.flags = !{!2, !3}
+
+!0 = distinct !DICompileUnit(language: DW_LANG_C99, file: !1, producer: "blazesym", isOptimized: false, runtimeVersion: 0, emissionKind: FullDebug)
+!1 = !DIFile(filename: "test-cross-arch.ll", directory: "/tmp")
+!2 = !{i32 7, !"Dwarf Version", i32 4}
+!3 = !{i32 2, !"Debug Info Version", i32 3}
+!10 = distinct !DISubprogram(name: "cross_arch_factorial", scope: !1, file: !1, line: 6, type: !11, scopeLine: 6, spFlags: DISPFlagDefinition, unit: !0)
+!11 = !DISubroutineType(types: !12)
+!12 = !{null}
+!14 = !DILocation(line: 8, scope: !10)
+!15 = !DILocation(line: 12, scope: !10)
+!16 = !DILocation(line: 14, scope: !10)
+!17 = !DILocation(line: 18, scope: !10)
+!20 = distinct !DISubprogram(name: "cross_arch_main", scope: !1, file: !1, line: 21, type: !11, scopeLine: 21, spFlags: DISPFlagDefinition, unit: !0)
+!21 = !DILocation(line: 23, scope: !20)
+!22 = !DILocation(line: 24, scope: !20)
@@ -0,0 +1,9 @@
+/* A stand-in for a kernel module, which is a relocatable object file. */
+
+int test_kmod_init(void) {
+  return 0;
+}
+
+int test_kmod_answer(void) {
+  return 42;
+}
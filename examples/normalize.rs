@@ -0,0 +1,68 @@
+use std::env;
+
+use anyhow::bail;
+use anyhow::Context as _;
+use anyhow::Result;
+
+use blazesym::normalize::Normalizer;
+use blazesym::normalize::UserMeta;
+use blazesym::Addr;
+
+const ADDR_WIDTH: usize = 16;
+
+
+fn format_build_id(build_id: Option<&[u8]>) -> String {
+    build_id
+        .map(|build_id| build_id.iter().map(|b| format!("{b:02x}")).collect())
+        .unwrap_or_else(|| "<no-build-id>".to_string())
+}
+
+
+fn main() -> Result<()> {
+    let args = env::args().collect::<Vec<_>>();
+
+    if args.len() < 3 {
+        bail!(
+            "Usage: {} <pid> <address>...
+Normalize addresses in the process of the given pid, and print
+the file offset, path, and build ID of the binary containing each.
+The output can be used for symbolizing the addresses on a different
+system.",
+            args.first().map(String::as_str).unwrap_or("normalize")
+        );
+    }
+
+    let pid = args[1]
+        .parse::<u32>()
+        .with_context(|| format!("failed to parse PID: {}", args[1]))?;
+    let addrs = args[2..]
+        .iter()
+        .map(|addr| {
+            Addr::from_str_radix(addr.trim_start_matches("0x"), 16)
+                .with_context(|| format!("failed to parse address: {addr}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let normalizer = Normalizer::new();
+    let normalized = normalizer
+        .normalize_user_addrs(pid.into(), &addrs)
+        .context("failed to normalize addresses")?;
+
+    for (addr, (file_offset, meta_idx)) in addrs.iter().zip(&normalized.outputs) {
+        match &normalized.meta[*meta_idx] {
+            UserMeta::Elf(elf) => println!(
+                "{addr:#0width$x}: {file_offset:#x} {} {}",
+                elf.path.display(),
+                format_build_id(elf.build_id.as_deref()),
+                width = ADDR_WIDTH
+            ),
+            UserMeta::Apk(apk) => println!(
+                "{addr:#0width$x}: {file_offset:#x} {}",
+                apk.path.display(),
+                width = ADDR_WIDTH
+            ),
+            _ => println!("{addr:#0width$x}: <unknown>", width = ADDR_WIDTH),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,39 @@
+#![no_main]
+
+use std::path::Path;
+
+use blazesym::symbolize::Elf;
+use blazesym::symbolize::Input;
+use blazesym::symbolize::Source;
+use blazesym::symbolize::Symbolizer;
+use blazesym::vfs::FileAccess;
+use blazesym::vfs::FileData;
+use blazesym::Error;
+use blazesym::Limits;
+
+use libfuzzer_sys::fuzz_target;
+
+
+/// A `FileAccess` implementation serving the fuzzer provided data for
+/// any path.
+#[derive(Debug)]
+struct FuzzInput(Vec<u8>);
+
+impl FileAccess for FuzzInput {
+    fn open(&self, _path: &Path) -> Result<FileData, Error> {
+        Ok(FileData::from_vec(self.0.clone(), 0))
+    }
+}
+
+
+fuzz_target!(|data: &[u8]| {
+    let symbolizer = Symbolizer::builder()
+        .set_limits(Some(Limits::default()))
+        .set_file_access(FuzzInput(data.to_vec()))
+        .build();
+    let src = Source::from(Elf::new("/fuzz-input"));
+    let addrs = (0..64).map(|i| 0x1000 * i).collect::<Vec<_>>();
+    if let Err(err) = symbolizer.symbolize(&src, Input::VirtOffset(&addrs)) {
+        assert!(!err.to_string().contains("panicked"), "{err:?}");
+    }
+});
@@ -0,0 +1,21 @@
+#![no_main]
+
+use blazesym::symbolize::GsymData;
+use blazesym::symbolize::Input;
+use blazesym::symbolize::Source;
+use blazesym::symbolize::Symbolizer;
+use blazesym::Limits;
+
+use libfuzzer_sys::fuzz_target;
+
+
+fuzz_target!(|data: &[u8]| {
+    let symbolizer = Symbolizer::builder()
+        .set_limits(Some(Limits::default()))
+        .build();
+    let src = Source::from(GsymData::new(data));
+    let addrs = (0..64).map(|i| 0x1000 * i).collect::<Vec<_>>();
+    if let Err(err) = symbolizer.symbolize(&src, Input::VirtOffset(&addrs)) {
+        assert!(!err.to_string().contains("panicked"), "{err:?}");
+    }
+});
@@ -0,0 +1,374 @@
+/// Support for static archives as created by `ar`.
+///
+/// There is no formal specification of the format, but it is described
+/// in ar(5) and the System V ABI. Both the GNU/System V and the BSD
+/// variants for storing long member names are supported.
+///
+/// Members are only aligned to two bytes and data may hence be
+/// misaligned. The member header consists solely of ASCII text.
+use std::cmp::min;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::path::Path;
+use std::str;
+
+use crate::mmap::Mmap;
+use crate::util::bytes_to_os_str;
+use crate::util::Pod;
+use crate::util::ReadRaw as _;
+use crate::Error;
+use crate::IntoError as _;
+use crate::Result;
+
+/// The magic string at the beginning of every archive.
+const ARCHIVE_MAGIC: &[u8; 8] = b"!<arch>\n";
+/// The magic string at the beginning of a "thin" archive, which only
+/// references its members instead of embedding them.
+const THIN_ARCHIVE_MAGIC: &[u8; 8] = b"!<thin>\n";
+/// The magic string at the end of each member header.
+const MEMBER_MAGIC: &[u8; 2] = b"`\n";
+/// The prefix of BSD style member names, which are stored directly
+/// after the header.
+const BSD_NAME_PREFIX: &[u8] = b"#1/";
+
+
+/// The header preceding each archive member.
+#[repr(C, packed)]
+struct MemberHeader {
+    /// The member name, padded with spaces.
+    name: [u8; 16],
+    /// Modification time stamp in decimal.
+    date: [u8; 12],
+    /// Owner ID in decimal.
+    uid: [u8; 6],
+    /// Group ID in decimal.
+    gid: [u8; 6],
+    /// File mode in octal.
+    mode: [u8; 8],
+    /// The size of the member data in decimal.
+    size: [u8; 10],
+    /// Magic value equal to MEMBER_MAGIC.
+    magic: [u8; 2],
+}
+
+// SAFETY: `MemberHeader` is valid for any bit pattern.
+unsafe impl Pod for MemberHeader {}
+
+
+/// Parse a space padded decimal number from an archive header field.
+fn parse_decimal(field: &[u8]) -> Option<usize> {
+    let field = str::from_utf8(field).ok()?.trim_end_matches(' ');
+    field.parse().ok()
+}
+
+
+/// Carries information on name and data corresponding to a member of
+/// an archive.
+pub(crate) struct Entry<'archive> {
+    /// The name of the member.
+    pub path: &'archive Path,
+    /// The offset of the data from the beginning of the archive.
+    pub data_offset: u64,
+    /// The member data.
+    pub data: &'archive [u8],
+}
+
+impl Debug for Entry<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let Entry {
+            path,
+            data_offset,
+            data,
+        } = self;
+
+        f.debug_struct(stringify!(Entry))
+            .field("path", path)
+            .field("data_offset", data_offset)
+            .field("data", &data.get(0..(min(data.len(), 32))))
+            .finish()
+    }
+}
+
+
+/// An iterator over the entries of an [`Archive`].
+pub(crate) struct EntryIter<'archive> {
+    /// The data of the archive.
+    archive_data: &'archive [u8],
+    /// The offset of the next member header.
+    offset: usize,
+    /// The GNU style long name table, if encountered already.
+    long_names: &'archive [u8],
+}
+
+impl<'archive> EntryIter<'archive> {
+    /// Look up a GNU style long name at the given offset in the name
+    /// table.
+    fn long_name(&self, offset: &[u8]) -> Option<&'archive [u8]> {
+        let offset = parse_decimal(offset)?;
+        let names = self.long_names.get(offset..)?;
+        let end = names.iter().position(|b| *b == b'\n')?;
+        let name = &names[..end];
+        Some(name.strip_suffix(b"/").unwrap_or(name))
+    }
+
+    /// Parse the next member, returning `None` for special members
+    /// (such as symbol tables) that are not to be reported.
+    fn parse_member(&mut self) -> Option<Result<Option<Entry<'archive>>>> {
+        let mut data = self.archive_data.get(self.offset..)?;
+        let start = self.offset;
+        let hdr = data.read_pod_ref::<MemberHeader>()?;
+        if &hdr.magic != MEMBER_MAGIC {
+            return Some(Err(Error::with_invalid_data(
+                "archive member header contains invalid magic",
+            )))
+        }
+
+        let size = parse_decimal(&hdr.size)?;
+        let data_offset = start + std::mem::size_of::<MemberHeader>();
+        let mut member = data.read_slice(size)?;
+        // Members are aligned to two bytes; the padding is not part of
+        // the member size.
+        self.offset = data_offset + size + size % 2;
+
+        let name = hdr.name.as_slice();
+        let (name, data_offset) = if name.starts_with(b"// ") {
+            self.long_names = member;
+            return Some(Ok(None))
+        } else if name.starts_with(b"/ ") || name.starts_with(b"/SYM64/ ") {
+            return Some(Ok(None))
+        } else if let Some(offset) = name.strip_prefix(b"/") {
+            let name = match self.long_name(offset) {
+                Some(name) => name,
+                None => {
+                    return Some(Err(Error::with_invalid_data(
+                        "archive member references invalid long name",
+                    )))
+                }
+            };
+            (name, data_offset)
+        } else if let Some(len) = name.strip_prefix(BSD_NAME_PREFIX) {
+            let len = parse_decimal(len)?;
+            let name = member.read_slice(len)?;
+            // The name may be padded with NUL bytes.
+            let end = name.iter().position(|b| *b == b'\0').unwrap_or(name.len());
+            (&name[..end], data_offset + len)
+        } else {
+            let end = name
+                .iter()
+                .position(|b| *b == b'/' || *b == b' ')
+                .unwrap_or(name.len());
+            (&name[..end], data_offset)
+        };
+
+        if name.starts_with(b"__.SYMDEF") {
+            return Some(Ok(None))
+        }
+
+        let path = match bytes_to_os_str(name) {
+            Some(path) => Path::new(path),
+            None => {
+                return Some(Err(Error::with_invalid_data(
+                    "archive member name is not valid on this platform",
+                )))
+            }
+        };
+
+        let entry = Entry {
+            path,
+            data_offset: data_offset as u64,
+            data: member,
+        };
+        Some(Ok(Some(entry)))
+    }
+}
+
+impl<'archive> Iterator for EntryIter<'archive> {
+    type Item = Result<Entry<'archive>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.offset >= self.archive_data.len() {
+                return None
+            }
+
+            match self.parse_member() {
+                Some(Ok(Some(entry))) => return Some(Ok(entry)),
+                Some(Ok(None)) => continue,
+                Some(Err(err)) => {
+                    // Don't attempt to parse anything after an error.
+                    self.offset = self.archive_data.len();
+                    return Some(Err(err))
+                }
+                None => {
+                    self.offset = self.archive_data.len();
+                    return Some(Err(Error::with_invalid_data(
+                        "failed to read archive member",
+                    )))
+                }
+            }
+        }
+    }
+}
+
+
+/// An open static archive.
+///
+/// Thin archives, which only reference their members, are not
+/// supported.
+#[derive(Debug)]
+pub(crate) struct Archive {
+    mmap: Mmap,
+}
+
+impl Archive {
+    /// Open an archive at the provided `path`.
+    #[cfg(test)]
+    pub fn open<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let mmap = Mmap::builder().open(path)?;
+        Self::with_mmap(mmap)
+    }
+
+    /// Create an `Archive` instance using the provided `Mmap`.
+    pub fn with_mmap(mmap: Mmap) -> Result<Self> {
+        let magic = mmap
+            .get(..ARCHIVE_MAGIC.len())
+            .ok_or_unexpected_eof(|| "archive is too small to contain magic")?;
+        if magic == THIN_ARCHIVE_MAGIC {
+            return Err(Error::with_unsupported("thin archives are not supported"))
+        }
+        if magic != ARCHIVE_MAGIC {
+            return Err(Error::with_unsupported_format(
+                "file is not an archive: invalid magic",
+            ))
+        }
+
+        let slf = Archive { mmap };
+        Ok(slf)
+    }
+
+    /// Create an iterator over the entries of the archive.
+    pub fn entries(&self) -> EntryIter<'_> {
+        EntryIter {
+            archive_data: &self.mmap,
+            offset: ARCHIVE_MAGIC.len(),
+            long_names: &[],
+        }
+    }
+
+    /// Retrieve the [`Mmap`] object used by this `Archive`.
+    #[inline]
+    pub fn mmap(&self) -> &Mmap {
+        &self.mmap
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write as _;
+
+    use tempfile::NamedTempFile;
+
+    use crate::ErrorKind;
+
+
+    /// Create a member header for the given name and size.
+    fn header(name: &str, size: usize) -> Vec<u8> {
+        let hdr = format!("{name:<16}{:<12}{:<6}{:<6}{:<8}{size:<10}`\n", 0, 0, 0, 644);
+        assert_eq!(hdr.len(), std::mem::size_of::<MemberHeader>());
+        hdr.into_bytes()
+    }
+
+    /// Exercise the `Debug` representation of various types.
+    #[test]
+    fn debug_repr() {
+        let mut file = NamedTempFile::new().unwrap();
+        let () = file.write_all(ARCHIVE_MAGIC).unwrap();
+        let archive = Archive::open(file.path()).unwrap();
+        assert_ne!(format!("{archive:?}"), "");
+
+        let entry = Entry {
+            path: Path::new("foo.o"),
+            data_offset: 68,
+            data: &[1, 2, 3],
+        };
+        assert_ne!(format!("{entry:?}"), "");
+    }
+
+    /// Check that we can iterate over the members of an archive using
+    /// the various naming schemes.
+    #[test]
+    fn member_iteration() {
+        let long_names = b"a-rather-long-member-name.o/\n\n";
+        let mut data = ARCHIVE_MAGIC.to_vec();
+        // A GNU style symbol table.
+        let () = data.extend(header("/", 4));
+        let () = data.extend(b"\0\0\0\0");
+        // The GNU style long name table.
+        let () = data.extend(header("//", long_names.len()));
+        let () = data.extend(long_names);
+        // A member with a short name and odd size.
+        let () = data.extend(header("short.o/", 3));
+        let () = data.extend(b"abc\n");
+        // A member referencing the long name table.
+        let () = data.extend(header("/0", 2));
+        let () = data.extend(b"de");
+        // A BSD style member.
+        let () = data.extend(header("#1/8", 10));
+        let () = data.extend(b"bsd.o\0\0\0fg");
+
+        let mut file = NamedTempFile::new().unwrap();
+        let () = file.write_all(&data).unwrap();
+        let archive = Archive::open(file.path()).unwrap();
+        let entries = archive
+            .entries()
+            .collect::<Result<Vec<_>>>()
+            .unwrap()
+            .into_iter()
+            .map(|entry| {
+                let offset = entry.data_offset as usize;
+                assert_eq!(&data[offset..offset + entry.data.len()], entry.data);
+                (entry.path.to_path_buf(), entry.data.to_vec())
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            entries,
+            vec![
+                (Path::new("short.o").to_path_buf(), b"abc".to_vec()),
+                (
+                    Path::new("a-rather-long-member-name.o").to_path_buf(),
+                    b"de".to_vec()
+                ),
+                (Path::new("bsd.o").to_path_buf(), b"fg".to_vec()),
+            ]
+        );
+    }
+
+    /// Check that we fail to open files that are not archives.
+    #[test]
+    fn invalid_archive() {
+        let mut file = NamedTempFile::new().unwrap();
+        let () = file.write_all(b"!<thin>\n").unwrap();
+        let err = Archive::open(file.path()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+
+        let mut file = NamedTempFile::new().unwrap();
+        let () = file.write_all(b"\x7fELF\0\0\0\0").unwrap();
+        let err = Archive::open(file.path()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedFormat);
+
+        let mut file = NamedTempFile::new().unwrap();
+        let () = file.write_all(ARCHIVE_MAGIC).unwrap();
+        let () = file.write_all(&header("foo.o/", 100)).unwrap();
+        let archive = Archive::open(file.path()).unwrap();
+        let err = archive.entries().next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,531 @@
+#[cfg(feature = "dwarf")]
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write as _;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[cfg(feature = "dwarf")]
+use crate::dwarf::DwarfResolver;
+#[cfg(feature = "dwarf")]
+use crate::dwarf::FnInfo;
+#[cfg(feature = "dwarf")]
+use crate::dwarf::InlinedFnInfo;
+use crate::elf::types::EM_AARCH64;
+use crate::elf::types::EM_MIPS;
+use crate::elf::types::EM_PPC64;
+use crate::elf::types::EM_RISCV;
+use crate::elf::types::EM_S390;
+use crate::elf::types::EM_X86_64;
+use crate::elf::types::PT_LOAD;
+use crate::elf::ElfParser;
+use crate::inspect::FindAddrOpts;
+use crate::mmap::Mmap;
+use crate::normalize::buildid::compute_fallback_build_id;
+use crate::normalize::buildid::BuildId;
+#[cfg(feature = "dwarf")]
+use crate::vfs::StdFileAccess;
+use crate::ErrorExt as _;
+use crate::Result;
+
+
+/// The size of a Breakpad debug identifier, excluding the "age".
+const DEBUG_ID_SIZE: usize = 16;
+
+
+/// A table of strings (file paths or function names), assigning each a
+/// unique number.
+#[derive(Debug, Default)]
+struct Table<T> {
+    nums: HashMap<T, u32>,
+    entries: Vec<T>,
+}
+
+impl<T> Table<T>
+where
+    T: Clone + Eq + std::hash::Hash,
+{
+    fn insert(&mut self, entry: T) -> u32 {
+        if let Some(num) = self.nums.get(&entry) {
+            return *num
+        }
+        let num = self.entries.len() as u32;
+        let () = self.entries.push(entry.clone());
+        let _prev = self.nums.insert(entry, num);
+        num
+    }
+}
+
+
+/// A row of a function's line table.
+#[derive(Debug)]
+struct Line {
+    addr: u64,
+    size: u64,
+    line: u32,
+    file: u32,
+}
+
+
+/// An `INLINE` record.
+#[derive(Debug)]
+struct Inline {
+    depth: u32,
+    call_line: u32,
+    call_file: u32,
+    origin: u32,
+    ranges: Vec<Range<u64>>,
+}
+
+
+/// A function to be described by a `FUNC` record.
+#[derive(Debug)]
+struct Func {
+    size: u64,
+    name: String,
+    lines: Vec<Line>,
+    inlines: Vec<Inline>,
+}
+
+
+/// Collect `INLINE` records for `inlined` functions at nesting level
+/// `depth`.
+///
+/// Ranges are clipped to `bounds`. Calls without any ranges or without
+/// a known call site are omitted, along with their children.
+#[cfg(feature = "dwarf")]
+fn collect_inlines(
+    inlined: &[InlinedFnInfo<'_>],
+    depth: u32,
+    bounds: &Range<u64>,
+    files: &mut Table<PathBuf>,
+    origins: &mut Table<String>,
+    inlines: &mut Vec<Inline>,
+) {
+    for inline in inlined {
+        let ranges = inline
+            .ranges
+            .iter()
+            .map(|range| range.start.max(bounds.start)..range.end.min(bounds.end))
+            .filter(|range| !range.is_empty())
+            .collect::<Vec<_>>();
+        let (dir, file) = match inline.call_file {
+            Some(call_file) if !ranges.is_empty() => call_file,
+            _ => continue,
+        };
+
+        let record = Inline {
+            depth,
+            call_line: inline.call_line,
+            call_file: files.insert(dir.join(file)),
+            origin: origins.insert(inline.name.unwrap_or("").to_string()),
+            ranges,
+        };
+        let () = inlines.push(record);
+        let () = collect_inlines(&inline.children, depth + 1, bounds, files, origins, inlines);
+    }
+}
+
+
+/// Format a Breakpad debug identifier from a build ID.
+///
+/// The first 16 bytes of the build ID are interpreted as a GUID, the
+/// first three fields of which are stored in little endian byte order.
+/// The identifier is followed by the "age", which is always zero for
+/// ELF files.
+fn debug_id(build_id: &[u8]) -> String {
+    let mut id = [0; DEBUG_ID_SIZE];
+    let len = build_id.len().min(DEBUG_ID_SIZE);
+    let () = id[..len].copy_from_slice(&build_id[..len]);
+    let () = id[0..4].reverse();
+    let () = id[4..6].reverse();
+    let () = id[6..8].reverse();
+
+    let mut s = hex(&id);
+    let () = s.push('0');
+    s
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{b:02X}")).collect()
+}
+
+/// Map an ELF machine type to the name Breakpad uses for it.
+fn arch_name(machine: u16) -> &'static str {
+    match machine {
+        EM_X86_64 => "x86_64",
+        EM_AARCH64 => "arm64",
+        EM_MIPS => "mips64",
+        EM_PPC64 => "ppc64",
+        EM_RISCV => "riscv64",
+        EM_S390 => "s390",
+        _ => "unknown",
+    }
+}
+
+
+/// A type for converting ELF files into Breakpad symbol files.
+///
+/// Functions are read from the ELF file's DWARF debug information (if
+/// the `dwarf` feature is enabled) and reported via `FUNC` records,
+/// along with their line tables and inlined function calls. Symbols
+/// from the ELF file's symbol tables not covered by debug information
+/// are reported via `PUBLIC` records.
+///
+/// As per Breakpad convention, all addresses are relative to the
+/// virtual address of the first loadable segment of the file. Stack
+/// unwinding information is not emitted.
+///
+/// # Examples
+/// ```
+/// # use std::path::Path;
+/// use blazesym::breakpad;
+/// use blazesym::symbolize::BreakpadData;
+/// use blazesym::symbolize::Input;
+/// use blazesym::symbolize::Source;
+/// use blazesym::symbolize::Symbolizer;
+///
+/// # let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+/// #     .join("data")
+/// #     .join("test-stable-addresses.bin");
+/// let data = breakpad::Builder::default().build(&path).unwrap();
+///
+/// let src = Source::from(BreakpadData::new(&data));
+/// let symbolizer = Symbolizer::new();
+/// let sym = symbolizer
+///     .symbolize_single(&src, Input::VirtOffset(0x100))
+///     .unwrap()
+///     .into_sym()
+///     .unwrap();
+/// assert_eq!(sym.name, "factorial");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Builder {
+    /// Whether to include source code location information.
+    code_info: bool,
+    /// Whether to include information about inlined function calls.
+    inlined_fns: bool,
+}
+
+impl Builder {
+    /// Enable/disable the inclusion of source code location
+    /// information.
+    pub fn enable_code_info(mut self, enable: bool) -> Builder {
+        self.code_info = enable;
+        self
+    }
+
+    /// Enable/disable the inclusion of inlined function information.
+    pub fn enable_inlined_fns(mut self, enable: bool) -> Builder {
+        self.inlined_fns = enable;
+        self
+    }
+
+    #[cfg(feature = "dwarf")]
+    fn add_dwarf_fns(
+        &self,
+        path: &Path,
+        parser: &Arc<ElfParser>,
+        funcs: &mut BTreeMap<u64, Func>,
+        files: &mut Table<PathBuf>,
+        origins: &mut Table<String>,
+    ) -> Result<()> {
+        let fs = Arc::new(StdFileAccess::default());
+        let resolver = DwarfResolver::from_parser(path, parser.clone(), fs, self.code_info, None)?;
+        let () = resolver.for_each_function(|info: FnInfo<'_>| {
+            let FnInfo {
+                name,
+                range,
+                lines: rows,
+                inlined,
+            } = info;
+
+            let mut lines = Vec::new();
+            if self.code_info {
+                for (idx, row) in rows.iter().enumerate() {
+                    let end = rows
+                        .get(idx + 1)
+                        .map(|next| next.addr)
+                        .unwrap_or(range.end)
+                        .min(range.end);
+                    let addr = row.addr.max(range.start);
+                    if addr >= end {
+                        continue
+                    }
+                    let (dir, file) = row.file;
+                    let line = Line {
+                        addr,
+                        size: end - addr,
+                        line: row.line,
+                        file: files.insert(dir.join(file)),
+                    };
+                    let () = lines.push(line);
+                }
+            }
+
+            let mut inlines = Vec::new();
+            if self.inlined_fns {
+                let () = collect_inlines(&inlined, 0, &range, files, origins, &mut inlines);
+            }
+
+            let func = Func {
+                size: range.end - range.start,
+                name: name.to_string(),
+                lines,
+                inlines,
+            };
+
+            match funcs.entry(range.start) {
+                Entry::Vacant(entry) => {
+                    let _func = entry.insert(func);
+                }
+                // The same code may be described by multiple functions
+                // (e.g., due to identical code folding). Prefer the one
+                // carrying line information.
+                Entry::Occupied(mut entry) => {
+                    if entry.get().lines.is_empty() && !func.lines.is_empty() {
+                        let _prev = entry.insert(func);
+                    }
+                }
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    fn add_elf_syms(
+        &self,
+        parser: &ElfParser,
+        funcs: &BTreeMap<u64, Func>,
+        publics: &mut BTreeMap<u64, String>,
+    ) -> Result<()> {
+        let () = parser.for_each_sym(&FindAddrOpts::default(), (), |(), sym| {
+            if sym.addr == 0 {
+                return
+            }
+            // Symbols falling into a function that we already know
+            // about are redundant.
+            if let Some((start, func)) = funcs.range(..=sym.addr).next_back() {
+                if sym.addr < start + func.size.max(1) {
+                    return
+                }
+            }
+            let _name = publics
+                .entry(sym.addr)
+                .or_insert_with(|| sym.name.to_string());
+        })?;
+        Ok(())
+    }
+
+    /// Create a Breakpad symbol file for the ELF file at `path`.
+    pub fn build(&self, path: &Path) -> Result<Vec<u8>> {
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        let mmap = Mmap::map(&file).with_context(|| format!("failed to map {}", path.display()))?;
+        let parser = Arc::new(ElfParser::from_mmap(mmap));
+
+        let mut publics = BTreeMap::new();
+        #[cfg(feature = "dwarf")]
+        let (funcs, files, origins) = {
+            let mut funcs = BTreeMap::new();
+            let mut files = Table::<PathBuf>::default();
+            let mut origins = Table::<String>::default();
+            let () = self
+                .add_dwarf_fns(path, &parser, &mut funcs, &mut files, &mut origins)
+                .context("failed to read DWARF debug information")?;
+            (funcs, files, origins)
+        };
+        // Without DWARF support, all we have are ELF symbols.
+        #[cfg(not(feature = "dwarf"))]
+        let (funcs, files, origins) = (
+            BTreeMap::new(),
+            Table::<PathBuf>::default(),
+            Table::<String>::default(),
+        );
+        let () = self.add_elf_syms(&parser, &funcs, &mut publics)?;
+
+        let build_id = match BuildId::from_parser(&parser)? {
+            Some(build_id) => Some(build_id.id),
+            None => compute_fallback_build_id(&parser)?,
+        }
+        .unwrap_or_default();
+        let base = parser
+            .program_headers()?
+            .iter()
+            .find(|phdr| phdr.p_type == PT_LOAD)
+            .map(|phdr| phdr.p_vaddr)
+            .unwrap_or(0);
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+
+        let mut data = Vec::new();
+        let () = writeln!(
+            &mut data,
+            "MODULE Linux {} {} {name}",
+            arch_name(parser.machine()?),
+            debug_id(&build_id),
+        )?;
+        if !build_id.is_empty() {
+            let () = writeln!(&mut data, "INFO CODE_ID {}", hex(&build_id))?;
+        }
+        for (num, path) in files.entries.iter().enumerate() {
+            let () = writeln!(&mut data, "FILE {num} {}", path.display())?;
+        }
+        for (num, name) in origins.entries.iter().enumerate() {
+            let () = writeln!(&mut data, "INLINE_ORIGIN {num} {name}")?;
+        }
+
+        for (addr, func) in funcs.range(base..) {
+            let Func {
+                size,
+                name,
+                lines,
+                inlines,
+            } = func;
+
+            let () = writeln!(&mut data, "FUNC {:x} {size:x} 0 {name}", addr - base)?;
+            for inline in inlines {
+                let Inline {
+                    depth,
+                    call_line,
+                    call_file,
+                    origin,
+                    ranges,
+                } = inline;
+
+                let () = write!(&mut data, "INLINE {depth} {call_line} {call_file} {origin}")?;
+                for range in ranges {
+                    let () = write!(
+                        &mut data,
+                        " {:x} {:x}",
+                        range.start - base,
+                        range.end - range.start
+                    )?;
+                }
+                let () = writeln!(&mut data)?;
+            }
+            for line in lines {
+                let Line {
+                    addr,
+                    size,
+                    line,
+                    file,
+                } = line;
+                let () = writeln!(&mut data, "{:x} {size:x} {line} {file}", addr - base)?;
+            }
+        }
+
+        for (addr, name) in publics.range(base..) {
+            let () = writeln!(&mut data, "PUBLIC {:x} 0 {name}", addr - base)?;
+        }
+        Ok(data)
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            code_info: true,
+            inlined_fns: true,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::ffi::OsStr;
+
+    use crate::breakpad::BreakpadResolver;
+    use crate::SymResolver as _;
+
+
+    /// Check that debug identifiers are formatted as Breakpad does.
+    #[test]
+    fn debug_id_formatting() {
+        let build_id = [
+            0xb5, 0xf0, 0xa0, 0xb8, 0xac, 0xe0, 0x4c, 0x8e, 0xf7, 0xef, 0x9a, 0x8e, 0x2e, 0x0c,
+            0x20, 0xd3, 0xaa, 0xbb, 0xcc, 0xdd,
+        ];
+        assert_eq!(debug_id(&build_id), "B8A0F0B5E0AC8E4CF7EF9A8E2E0C20D30");
+        assert_eq!(debug_id(&[0x01]), "000000010000000000000000000000000");
+        assert_eq!(hex(&[0xde, 0xad]), "DEAD");
+    }
+
+    /// Check that we can convert an ELF file into a Breakpad file and
+    /// look up symbols and source code information in the result.
+    #[cfg(feature = "dwarf")]
+    #[test]
+    fn elf_conversion() {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-stable-addresses.bin");
+        let data = Builder::default().build(&path).unwrap();
+        let text = String::from_utf8(data.clone()).unwrap();
+        assert!(
+            text.starts_with("MODULE Linux x86_64 "),
+            "{}",
+            text.lines().next().unwrap()
+        );
+        assert!(text.contains("\nFUNC 100 "), "{text}");
+
+        let resolver = BreakpadResolver::from_data(&data).unwrap();
+        // The ELF file's first loadable segment starts at 0x2000000.
+        let sym = resolver.find_sym(0x0).unwrap().unwrap();
+        assert_eq!(sym.name, "main");
+        let info = resolver.find_code_info(0x0, true).unwrap().unwrap();
+        assert_eq!(info.direct.1.line, Some(50));
+        assert_eq!(info.direct.1.file, OsStr::new("test-stable-addresses.c"));
+
+        let sym = resolver.find_sym(0x100).unwrap().unwrap();
+        assert_eq!(sym.name, "factorial");
+
+        let addr = 0x20a;
+        let sym = resolver.find_sym(addr).unwrap().unwrap();
+        assert_eq!(sym.name, "factorial_inline_test");
+
+        let info = resolver.find_code_info(addr, true).unwrap().unwrap();
+        assert_eq!(info.direct.1.line, Some(32));
+        assert_eq!(info.direct.1.file, OsStr::new("test-stable-addresses.c"));
+        assert_eq!(info.inlined.len(), 2);
+
+        let name = &info.inlined[0].0;
+        assert_eq!(*name, "factorial_inline_wrapper");
+        let frame = info.inlined[0].1.as_ref().unwrap();
+        assert_eq!(frame.file, OsStr::new("test-stable-addresses.c"));
+        assert_eq!(frame.line, Some(26));
+
+        let name = &info.inlined[1].0;
+        assert_eq!(*name, "factorial_2nd_layer_inline_wrapper");
+        let frame = info.inlined[1].1.as_ref().unwrap();
+        assert_eq!(frame.file, OsStr::new("test-stable-addresses.c"));
+        assert_eq!(frame.line, Some(21));
+    }
+
+    /// Check that disabling source code information omits line records
+    /// while still reporting symbols.
+    #[test]
+    fn elf_conversion_without_code_info() {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-stable-addresses.bin");
+        let data = Builder::default()
+            .enable_code_info(false)
+            .build(&path)
+            .unwrap();
+        let resolver = BreakpadResolver::from_data(&data).unwrap();
+
+        let sym = resolver.find_sym(0x100).unwrap().unwrap();
+        assert_eq!(sym.name, "factorial");
+        let info = resolver.find_code_info(0x100, true).unwrap();
+        assert_eq!(info, None);
+    }
+}
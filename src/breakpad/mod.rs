@@ -0,0 +1,20 @@
+//! Support for the Breakpad symbol file format.
+//!
+//! Breakpad symbol files (commonly carrying a `.sym` extension) are a
+//! line based text format describing the functions of a module along
+//! with their source code locations, including those of inlined
+//! function calls. They are the lingua franca of many crash reporting
+//! pipelines. Addresses contained in them are relative to the load
+//! address of the module they describe.
+//!
+//! Symbolization using Breakpad files is available via the
+//! [`Source::Breakpad`][crate::symbolize::Source::Breakpad] variant. The
+//! [`Builder`] type provided by this module can be used for converting
+//! ELF files into the Breakpad format.
+
+mod builder;
+mod parser;
+mod resolver;
+
+pub use builder::Builder;
+pub(crate) use resolver::BreakpadResolver;
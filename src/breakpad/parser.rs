@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::Addr;
+use crate::Error;
+use crate::Result;
+
+
+/// The `MODULE` record of a Breakpad file, identifying the module
+/// described.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Module {
+    /// The operating system the module was built for.
+    pub os: String,
+    /// The CPU architecture the module was built for.
+    pub arch: String,
+    /// The module's debug identifier.
+    pub id: String,
+    /// The name of the module.
+    pub name: String,
+}
+
+
+/// A row of a function's line table.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Line {
+    /// The first address covered by the row.
+    pub addr: Addr,
+    /// The number of bytes covered by the row.
+    pub size: u64,
+    /// The source code line number.
+    pub line: u32,
+    /// The number of the `FILE` record describing the source file.
+    pub file: u32,
+}
+
+
+/// A function call inlined into a function.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Inline {
+    /// The nesting depth of the inlined call, with zero referring to
+    /// calls directly inlined into the containing function.
+    pub depth: u32,
+    /// The line number of the call.
+    pub call_line: u32,
+    /// The number of the `FILE` record describing the source file
+    /// containing the call.
+    pub call_file: u32,
+    /// The number of the `INLINE_ORIGIN` record naming the function.
+    pub origin: u32,
+    /// The address ranges covered by the inlined code.
+    pub ranges: Vec<Range<Addr>>,
+}
+
+
+/// A function, as described by a `FUNC` record.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Func {
+    /// The address at which the function starts.
+    pub addr: Addr,
+    /// The size of the function, in bytes.
+    pub size: u64,
+    /// The name of the function.
+    pub name: String,
+    /// The function's line table, sorted by address.
+    pub lines: Vec<Line>,
+    /// The calls inlined into the function.
+    pub inlines: Vec<Inline>,
+}
+
+
+/// A symbol without size or debug information, as described by a
+/// `PUBLIC` record.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Public {
+    /// The address of the symbol.
+    pub addr: Addr,
+    /// The name of the symbol.
+    pub name: String,
+}
+
+
+/// The parsed contents of a Breakpad symbol file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct SymbolFile {
+    /// Information about the module described.
+    pub module: Option<Module>,
+    /// Source file paths, indexed by `FILE` record number.
+    pub files: HashMap<u32, String>,
+    /// Inlined function names, indexed by `INLINE_ORIGIN` record
+    /// number.
+    pub origins: HashMap<u32, String>,
+    /// Functions, in file order.
+    pub funcs: Vec<Func>,
+    /// Public symbols, in file order.
+    pub publics: Vec<Public>,
+}
+
+
+/// A helper for splitting a record into its whitespace separated
+/// fields.
+struct Fields<'dat> {
+    /// The remainder of the record.
+    rest: &'dat str,
+}
+
+impl<'dat> Fields<'dat> {
+    fn new(rest: &'dat str) -> Self {
+        Self { rest }
+    }
+
+    /// Retrieve the next field.
+    fn next(&mut self) -> Option<&'dat str> {
+        let rest = self.rest.trim_start_matches(' ');
+        if rest.is_empty() {
+            return None
+        }
+        let (field, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+        self.rest = rest;
+        Some(field)
+    }
+
+    /// Retrieve the next field, if it equals `flag`.
+    fn flag(&mut self, flag: &str) -> bool {
+        let mut fields = Fields::new(self.rest);
+        if fields.next() == Some(flag) {
+            self.rest = fields.rest;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Parse the next field as a hexadecimal number.
+    fn hex(&mut self) -> Option<u64> {
+        u64::from_str_radix(self.next()?, 16).ok()
+    }
+
+    /// Parse the next field as a decimal number.
+    fn dec<T>(&mut self) -> Option<T>
+    where
+        T: FromStr,
+    {
+        self.next()?.parse().ok()
+    }
+
+    /// Retrieve the remainder of the record, which may contain
+    /// whitespace.
+    fn rest(self) -> Option<&'dat str> {
+        let rest = self.rest.trim_start_matches(' ');
+        (!rest.is_empty()).then_some(rest)
+    }
+}
+
+
+fn parse_module(fields: &mut Fields<'_>) -> Option<Module> {
+    let module = Module {
+        os: fields.next()?.to_string(),
+        arch: fields.next()?.to_string(),
+        id: fields.next()?.to_string(),
+        // The module name may legitimately be missing.
+        name: Fields::new(fields.rest).rest().unwrap_or("").to_string(),
+    };
+    Some(module)
+}
+
+fn parse_func(mut fields: Fields<'_>) -> Option<Func> {
+    let _multiple = fields.flag("m");
+    let addr = fields.hex()?;
+    let size = fields.hex()?;
+    let _param_size = fields.hex()?;
+    let func = Func {
+        addr,
+        size,
+        name: fields.rest().unwrap_or("").to_string(),
+        lines: Vec::new(),
+        inlines: Vec::new(),
+    };
+    Some(func)
+}
+
+fn parse_public(mut fields: Fields<'_>) -> Option<Public> {
+    let _multiple = fields.flag("m");
+    let addr = fields.hex()?;
+    let _param_size = fields.hex()?;
+    let public = Public {
+        addr,
+        name: fields.rest().unwrap_or("").to_string(),
+    };
+    Some(public)
+}
+
+fn parse_inline(mut fields: Fields<'_>) -> Option<Inline> {
+    let depth = fields.dec()?;
+    let call_line = fields.dec()?;
+    let call_file = fields.dec()?;
+    let origin = fields.dec()?;
+    let mut ranges = Vec::new();
+    while let Some(addr) = fields.next() {
+        let addr = u64::from_str_radix(addr, 16).ok()?;
+        let size = fields.hex()?;
+        let () = ranges.push(addr..addr.saturating_add(size));
+    }
+
+    if ranges.is_empty() {
+        return None
+    }
+
+    let inline = Inline {
+        depth,
+        call_line,
+        call_file,
+        origin,
+        ranges,
+    };
+    Some(inline)
+}
+
+fn parse_line(mut fields: Fields<'_>) -> Option<Line> {
+    let line = Line {
+        addr: fields.hex()?,
+        size: fields.hex()?,
+        // Some producers emit negative line numbers for lines they
+        // could not attribute. We treat those as unknown.
+        line: fields
+            .dec::<i64>()
+            .map(|line| u32::try_from(line).unwrap_or(0))?,
+        file: fields.dec()?,
+    };
+    Some(line)
+}
+
+
+/// Parse the contents of a Breakpad symbol file.
+///
+/// Records not relevant to symbolization, such as stack unwinding
+/// information, are skipped.
+pub(crate) fn parse(data: &str) -> Result<SymbolFile> {
+    let mut file = SymbolFile::default();
+
+    for (idx, line) in data.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        let mut fields = Fields::new(line);
+        let record = match fields.next() {
+            Some(record) => record,
+            None => continue,
+        };
+        let malformed = || Error::with_invalid_data(format!("line {} is malformed", idx + 1));
+
+        if idx == 0 {
+            if record != "MODULE" {
+                return Err(Error::with_unsupported_format(
+                    "file is not a Breakpad symbol file",
+                ))
+            }
+            file.module = Some(parse_module(&mut fields).ok_or_else(malformed)?);
+            continue
+        }
+
+        match record {
+            "FILE" => {
+                let num = fields.dec().ok_or_else(malformed)?;
+                let path = fields.rest().unwrap_or("");
+                let _prev = file.files.insert(num, path.to_string());
+            }
+            "INLINE_ORIGIN" => {
+                let num = fields.dec().ok_or_else(malformed)?;
+                let name = fields.rest().unwrap_or("");
+                let _prev = file.origins.insert(num, name.to_string());
+            }
+            "FUNC" => {
+                let func = parse_func(fields).ok_or_else(malformed)?;
+                let () = file.funcs.push(func);
+            }
+            "PUBLIC" => {
+                let public = parse_public(fields).ok_or_else(malformed)?;
+                let () = file.publics.push(public);
+            }
+            "INLINE" => {
+                let inline = parse_inline(fields).ok_or_else(malformed)?;
+                let func = file.funcs.last_mut().ok_or_else(malformed)?;
+                let () = func.inlines.push(inline);
+            }
+            "MODULE" | "INFO" | "STACK" => (),
+            _ => {
+                // Anything else has to be a line record belonging to
+                // the most recent function.
+                let line = parse_line(Fields::new(line)).ok_or_else(malformed)?;
+                let func = file.funcs.last_mut().ok_or_else(malformed)?;
+                let () = func.lines.push(line);
+            }
+        }
+    }
+
+    for func in file.funcs.iter_mut() {
+        let () = func.lines.sort_by_key(|line| line.addr);
+    }
+    Ok(file)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ErrorKind;
+
+
+    /// Check that we can parse the various records of a Breakpad file.
+    #[test]
+    fn record_parsing() {
+        let data = r#"MODULE Linux x86_64 B8A0F0B5E0AC8E4CF7EF9A8E2E0C20D30 test.bin
+INFO CODE_ID B5F0A0B8ACE04C8EF7EF9A8E2E0C20D3
+FILE 0 /tmp/test.c
+FILE 1 /tmp/with space.h
+INLINE_ORIGIN 0 inlined_fn
+FUNC m 1000 30 0 main
+INLINE 0 12 1 0 1010 8 1020 4
+1000 10 10 0
+1010 8 3 1
+1018 18 -1 0
+PUBLIC 2000 0 _start
+STACK CFI INIT 1000 30 .cfa: $rsp 8 + .ra: .cfa -8 + ^
+"#;
+        let file = parse(data).unwrap();
+        let module = file.module.as_ref().unwrap();
+        assert_eq!(module.os, "Linux");
+        assert_eq!(module.arch, "x86_64");
+        assert_eq!(module.id, "B8A0F0B5E0AC8E4CF7EF9A8E2E0C20D30");
+        assert_eq!(module.name, "test.bin");
+
+        assert_eq!(file.files.get(&1).unwrap(), "/tmp/with space.h");
+        assert_eq!(file.origins.get(&0).unwrap(), "inlined_fn");
+
+        assert_eq!(file.funcs.len(), 1);
+        let func = &file.funcs[0];
+        assert_eq!(func.addr, 0x1000);
+        assert_eq!(func.size, 0x30);
+        assert_eq!(func.name, "main");
+        assert_eq!(
+            func.inlines,
+            vec![Inline {
+                depth: 0,
+                call_line: 12,
+                call_file: 1,
+                origin: 0,
+                ranges: vec![0x1010..0x1018, 0x1020..0x1024],
+            }]
+        );
+        assert_eq!(func.lines.len(), 3);
+        assert_eq!(
+            func.lines[1],
+            Line {
+                addr: 0x1010,
+                size: 8,
+                line: 3,
+                file: 1
+            }
+        );
+        assert_eq!(func.lines[2].line, 0);
+
+        assert_eq!(
+            file.publics,
+            vec![Public {
+                addr: 0x2000,
+                name: "_start".to_string()
+            }]
+        );
+    }
+
+    /// Make sure that we reject invalid data.
+    #[test]
+    fn invalid_data() {
+        let err = parse("7f5c3c001000 80 Interpreter\n").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedFormat);
+
+        let err = parse("MODULE Linux x86_64 0 test\nFUNC 1000 zz 0 main\n").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // Line records need a function they belong to.
+        let err = parse("MODULE Linux x86_64 0 test\n1000 10 10 0\n").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
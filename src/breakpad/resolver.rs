@@ -0,0 +1,318 @@
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::mem::swap;
+use std::path::Path;
+
+use crate::inspect::FindAddrOpts;
+use crate::inspect::SymInfo;
+use crate::inspect::SymType;
+use crate::symbolize::AddrCodeInfo;
+use crate::symbolize::CodeInfo;
+use crate::symbolize::IntSym;
+use crate::symbolize::Reason;
+use crate::symbolize::SrcLang;
+use crate::Addr;
+use crate::Error;
+use crate::Result;
+use crate::SymResolver;
+
+use super::parser::parse;
+use super::parser::Func;
+use super::parser::Inline;
+use super::parser::Public;
+use super::parser::SymbolFile;
+
+
+/// A symbol resolver backed by the contents of a Breakpad symbol file.
+pub(crate) struct BreakpadResolver {
+    /// The parsed symbol file, with functions and public symbols
+    /// sorted by address.
+    file: SymbolFile,
+}
+
+impl BreakpadResolver {
+    /// Create a new `BreakpadResolver` from the contents of a Breakpad
+    /// symbol file.
+    pub(crate) fn from_data(data: &[u8]) -> Result<Self> {
+        let data = std::str::from_utf8(data)
+            .map_err(|_err| Error::with_invalid_data("Breakpad file is not valid UTF-8"))?;
+        let mut file = parse(data)?;
+        let () = file.funcs.sort_by_key(|func| func.addr);
+        let () = file.publics.sort_by_key(|public| public.addr);
+        Ok(Self { file })
+    }
+
+    fn find_func(&self, addr: Addr) -> Option<&Func> {
+        let idx = self.file.funcs.partition_point(|func| func.addr <= addr);
+        let func = self.file.funcs[..idx].last()?;
+        (addr - func.addr < func.size.max(1)).then_some(func)
+    }
+
+    /// Find the public symbol covering `addr`.
+    ///
+    /// Public symbols do not have a size. We attribute an address to the
+    /// closest preceding public symbol, unless a function starts in
+    /// between the two (or at the same address as the public symbol).
+    fn find_public(&self, addr: Addr) -> Option<&Public> {
+        let idx = self
+            .file
+            .publics
+            .partition_point(|public| public.addr <= addr);
+        let public = self.file.publics[..idx].last()?;
+
+        let idx = self.file.funcs.partition_point(|func| func.addr <= addr);
+        match self.file.funcs[..idx].last() {
+            Some(func) if func.addr >= public.addr => None,
+            _ => Some(public),
+        }
+    }
+
+    fn code_info(&self, file: u32, line: u32) -> Option<CodeInfo<'_>> {
+        let path = Path::new(self.file.files.get(&file)?);
+        let info = CodeInfo {
+            dir: path.parent().map(Cow::Borrowed),
+            file: Cow::Borrowed(path.file_name().unwrap_or_else(|| OsStr::new(""))),
+            line: (line != 0).then_some(line),
+            column: None,
+            context: None,
+            _non_exhaustive: (),
+        };
+        Some(info)
+    }
+
+    /// Retrieve the stack of inlined calls covering `addr`, ordered from
+    /// outermost to innermost.
+    fn inline_stack(func: &Func, addr: Addr) -> Vec<&Inline> {
+        let mut stack = Vec::<&Inline>::new();
+        for inline in &func.inlines {
+            if inline.depth as usize != stack.len() {
+                continue
+            }
+            if inline.ranges.iter().any(|range| range.contains(&addr)) {
+                let () = stack.push(inline);
+            }
+        }
+        stack
+    }
+}
+
+impl SymResolver for BreakpadResolver {
+    fn find_sym(&self, addr: Addr) -> Result<Result<IntSym<'_>, Reason>> {
+        if self.file.funcs.is_empty() && self.file.publics.is_empty() {
+            return Ok(Err(Reason::MissingSyms))
+        }
+
+        if let Some(func) = self.find_func(addr) {
+            let sym = IntSym {
+                name: &func.name,
+                addr: func.addr,
+                size: usize::try_from(func.size).ok(),
+                lang: SrcLang::Unknown,
+            };
+            return Ok(Ok(sym))
+        }
+
+        let sym = self
+            .find_public(addr)
+            .map(|public| IntSym {
+                name: &public.name,
+                addr: public.addr,
+                size: None,
+                lang: SrcLang::Unknown,
+            })
+            .ok_or(Reason::UnknownAddr);
+        Ok(sym)
+    }
+
+    fn find_addr<'slf>(&'slf self, name: &str, opts: &FindAddrOpts) -> Result<Vec<SymInfo<'slf>>> {
+        if let SymType::Variable = opts.sym_type {
+            return Ok(Vec::new())
+        }
+
+        let funcs = self
+            .file
+            .funcs
+            .iter()
+            .filter(|func| func.name == name)
+            .map(|func| (func.addr, func.size, &func.name));
+        let publics = self
+            .file
+            .publics
+            .iter()
+            .filter(|public| public.name == name)
+            .map(|public| (public.addr, 0, &public.name));
+
+        let syms = funcs
+            .chain(publics)
+            .map(|(addr, size, name)| SymInfo {
+                name: Cow::Borrowed(name),
+                addr,
+                size: usize::try_from(size).unwrap_or(usize::MAX),
+                sym_type: SymType::Function,
+                file_offset: None,
+                obj_file_name: None,
+                section: None,
+            })
+            .collect();
+        Ok(syms)
+    }
+
+    fn find_code_info(&self, addr: Addr, inlined_fns: bool) -> Result<Option<AddrCodeInfo<'_>>> {
+        let func = match self.find_func(addr) {
+            Some(func) => func,
+            None => return Ok(None),
+        };
+        let idx = func.lines.partition_point(|line| line.addr <= addr);
+        let line = match func.lines[..idx].last() {
+            Some(line) if addr - line.addr < line.size.max(1) => line,
+            _ => return Ok(None),
+        };
+        let mut direct = match self.code_info(line.file, line.line) {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+
+        let mut inlined = Vec::new();
+        if inlined_fns {
+            let stack = Self::inline_stack(func, addr);
+            let () = inlined.reserve(stack.len());
+
+            for inline in stack {
+                let name = self
+                    .file
+                    .origins
+                    .get(&inline.origin)
+                    .map(String::as_str)
+                    .unwrap_or("");
+                let mut code_info = self.code_info(inline.call_file, inline.call_line);
+
+                // The call site of an inlined function is the source
+                // code location of the frame one level up. The line
+                // table describes the innermost frame.
+                if let Some((_last_name, ref mut last_code_info)) = inlined.last_mut() {
+                    let () = swap(&mut code_info, last_code_info);
+                } else if let Some(code_info) = &mut code_info {
+                    let () = swap(code_info, &mut direct);
+                }
+                let () = inlined.push((name, code_info));
+            }
+        }
+
+        let info = AddrCodeInfo {
+            direct: (None, direct),
+            inlined,
+        };
+        Ok(Some(info))
+    }
+}
+
+impl Debug for BreakpadResolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let name = self
+            .file
+            .module
+            .as_ref()
+            .map(|module| module.name.as_str())
+            .unwrap_or("<unknown-module>");
+        write!(f, "Breakpad {name}")
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    const DATA: &str = r#"MODULE Linux x86_64 000000000000000000000000000000000 test.bin
+FILE 0 /src/main.c
+FILE 1 /src/inc/util.h
+INLINE_ORIGIN 0 outer
+INLINE_ORIGIN 1 inner
+FUNC 1000 40 0 main
+INLINE 0 20 0 0 1010 20
+INLINE 1 5 1 1 1018 8
+1000 10 10 0
+1010 8 3 1
+1018 8 7 1
+1020 20 11 0
+PUBLIC 1000 0 main_public
+PUBLIC 2000 0 _start
+"#;
+
+
+    /// Exercise the `Debug` representation of various types.
+    #[test]
+    fn debug_repr() {
+        let resolver = BreakpadResolver::from_data(DATA.as_bytes()).unwrap();
+        assert_eq!(format!("{resolver:?}"), "Breakpad test.bin");
+    }
+
+    /// Check that symbols are looked up as expected.
+    #[test]
+    fn sym_lookup() {
+        let resolver = BreakpadResolver::from_data(DATA.as_bytes()).unwrap();
+        let find = |addr| {
+            resolver
+                .find_sym(addr)
+                .unwrap()
+                .map(|sym| (sym.name, sym.size))
+        };
+
+        assert_eq!(find(0x1000), Ok(("main", Some(0x40))));
+        assert_eq!(find(0x103f), Ok(("main", Some(0x40))));
+        // Past the end of `main` we must not report the public symbol
+        // at the same address.
+        assert_eq!(find(0x1040), Err(Reason::UnknownAddr));
+        assert_eq!(find(0x2000), Ok(("_start", None)));
+        assert_eq!(find(0x3000), Ok(("_start", None)));
+        assert_eq!(find(0x0), Err(Reason::UnknownAddr));
+
+        let syms = resolver
+            .find_addr("_start", &FindAddrOpts::default())
+            .unwrap();
+        assert_eq!(syms.len(), 1);
+        assert_eq!(syms[0].addr, 0x2000);
+
+        let resolver = BreakpadResolver::from_data(b"MODULE Linux x86_64 0 test\n").unwrap();
+        assert_eq!(
+            resolver.find_sym(0x1000).unwrap().map(|sym| sym.name),
+            Err(Reason::MissingSyms)
+        );
+    }
+
+    /// Check that source code information, including that of inlined
+    /// calls, is reported correctly.
+    #[test]
+    fn code_info_lookup() {
+        let resolver = BreakpadResolver::from_data(DATA.as_bytes()).unwrap();
+
+        let info = resolver.find_code_info(0x1004, true).unwrap().unwrap();
+        assert_eq!(info.direct.1.dir, Some(Cow::Borrowed(Path::new("/src"))));
+        assert_eq!(info.direct.1.file, OsStr::new("main.c"));
+        assert_eq!(info.direct.1.line, Some(10));
+        assert_eq!(info.inlined, Vec::new());
+
+        let info = resolver.find_code_info(0x101a, true).unwrap().unwrap();
+        assert_eq!(info.direct.1.file, OsStr::new("main.c"));
+        assert_eq!(info.direct.1.line, Some(20));
+        assert_eq!(info.inlined.len(), 2);
+        assert_eq!(info.inlined[0].0, "outer");
+        let frame = info.inlined[0].1.as_ref().unwrap();
+        assert_eq!(frame.file, OsStr::new("util.h"));
+        assert_eq!(frame.line, Some(5));
+        assert_eq!(info.inlined[1].0, "inner");
+        let frame = info.inlined[1].1.as_ref().unwrap();
+        assert_eq!(frame.file, OsStr::new("util.h"));
+        assert_eq!(frame.line, Some(7));
+
+        let info = resolver.find_code_info(0x101a, false).unwrap().unwrap();
+        assert_eq!(info.direct.1.line, Some(7));
+        assert_eq!(info.inlined, Vec::new());
+
+        assert_eq!(resolver.find_code_info(0x2000, true).unwrap(), None);
+    }
+}
@@ -0,0 +1,392 @@
+//! Support for reconstructing the memory map of a process from an ELF
+//! core file.
+
+use std::mem::size_of;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::elf::types::Elf64_Nhdr;
+use crate::elf::types::ET_CORE;
+use crate::elf::types::NT_FILE;
+use crate::elf::types::PF_R;
+use crate::elf::types::PF_W;
+use crate::elf::types::PF_X;
+use crate::elf::types::PT_LOAD;
+use crate::elf::types::PT_NOTE;
+use crate::elf::ElfParser;
+use crate::maps::EntryPath;
+use crate::maps::MapsEntry;
+use crate::maps::PathName;
+use crate::mmap::Mmap;
+use crate::util::bytes_to_os_str;
+use crate::util::ReadRaw as _;
+use crate::Addr;
+use crate::Error;
+use crate::IntoError as _;
+use crate::Result;
+
+
+/// A file backed mapping as recorded in an `NT_FILE` note.
+#[derive(Clone, Debug, PartialEq)]
+struct FileMapping {
+    /// The virtual address range covered by the mapping.
+    range: Range<Addr>,
+    /// The offset of the mapping in the file.
+    offset: u64,
+    /// The path of the file, as seen by the process.
+    path: PathBuf,
+}
+
+
+/// Parse the descriptor of an `NT_FILE` note.
+fn parse_nt_file(mut desc: &[u8]) -> Result<Vec<FileMapping>> {
+    // The descriptor has the following format:
+    // count, page_size, count * (start, end, page_offset), count * path
+    let count = desc
+        .read_u64()
+        .ok_or_invalid_data(|| "failed to read NT_FILE entry count")?;
+    let page_size = desc
+        .read_u64()
+        .ok_or_invalid_data(|| "failed to read NT_FILE page size")?;
+    let count = usize::try_from(count)
+        .ok()
+        .filter(|count| count.saturating_mul(3 * size_of::<u64>()) <= desc.len())
+        .ok_or_invalid_data(|| format!("NT_FILE note has invalid entry count {count}"))?;
+
+    let mut ranges = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (start, end, page_offset) = desc
+            .read_u64()
+            .and_then(|start| Some((start, desc.read_u64()?, desc.read_u64()?)))
+            .ok_or_invalid_data(|| "failed to read NT_FILE entry")?;
+        let () = ranges.push((start..end, page_offset.saturating_mul(page_size)));
+    }
+
+    ranges
+        .into_iter()
+        .map(|(range, offset)| {
+            let path = desc
+                .read_cstr()
+                .and_then(|path| bytes_to_os_str(path.to_bytes()))
+                .ok_or_invalid_data(|| "failed to read NT_FILE path")?;
+            let mapping = FileMapping {
+                range,
+                offset,
+                path: PathBuf::from(path),
+            };
+            Ok(mapping)
+        })
+        .collect()
+}
+
+/// Find and parse the `NT_FILE` note contained in the provided note
+/// segment.
+fn find_nt_file(data: &[u8]) -> Result<Option<Vec<FileMapping>>> {
+    // Notes in core files are always padded to four bytes.
+    let padded = |offset: usize| (offset + 3) & !3;
+    let mut offset = 0;
+
+    while offset < data.len() {
+        let mut bytes = &data[offset..];
+        let header = bytes
+            .read_pod::<Elf64_Nhdr>()
+            .ok_or_invalid_data(|| "failed to read ELF note header")?;
+        let name = bytes
+            .read_slice(header.n_namesz as _)
+            .ok_or_invalid_data(|| "failed to read ELF note name")?;
+        let name = name.strip_suffix(b"\0").unwrap_or(name);
+        let desc_offset = padded(offset + size_of::<Elf64_Nhdr>() + header.n_namesz as usize);
+        let desc = data
+            .get(desc_offset..)
+            .and_then(|mut bytes| bytes.read_slice(header.n_descsz as _))
+            .ok_or_invalid_data(|| "failed to read ELF note descriptor")?;
+        offset = padded(desc_offset + header.n_descsz as usize);
+
+        if name == b"CORE" && header.n_type == NT_FILE {
+            return parse_nt_file(desc).map(Some)
+        }
+    }
+    Ok(None)
+}
+
+/// Convert ELF segment flags into the mode representation used by
+/// [`MapsEntry`].
+fn flags_to_mode(flags: u32) -> u8 {
+    // The mode mirrors the `rwxp` permission string of a proc maps
+    // entry. Core files only ever contain private mappings.
+    let mut mode = 0b0001;
+    if flags & PF_R != 0 {
+        mode |= 0b1000;
+    }
+    if flags & PF_W != 0 {
+        mode |= 0b0100;
+    }
+    if flags & PF_X != 0 {
+        mode |= 0b0010;
+    }
+    mode
+}
+
+/// Reconstruct the memory map of the process captured in the core file
+/// represented by `mmap`.
+///
+/// File backed mappings are taken from the `NT_FILE` note and refer to
+/// files by the paths recorded therein. Loadable segments not backed by
+/// a file are reported as anonymous mappings. Entries are sorted by
+/// address.
+pub(crate) fn parse_maps(mmap: &Mmap) -> Result<Vec<MapsEntry>> {
+    let parser = ElfParser::from_mmap(mmap.clone());
+    let file_type = parser.file_type()?;
+    if file_type != ET_CORE {
+        return Err(Error::with_invalid_data(format!(
+            "ELF file is not a core file (type {file_type})"
+        )))
+    }
+
+    let phdrs = parser.program_headers()?;
+    let mut mappings = Vec::new();
+    for phdr in phdrs.iter().filter(|phdr| phdr.p_type == PT_NOTE) {
+        let data = usize::try_from(phdr.p_offset)
+            .ok()
+            .and_then(|offset| mmap.get(offset..))
+            .and_then(|mut data| data.read_slice(phdr.p_filesz as _))
+            .ok_or_invalid_data(|| "failed to read note segment of core file")?;
+        if let Some(nt_file) = find_nt_file(data)? {
+            mappings = nt_file;
+            break
+        }
+    }
+
+    let loads = phdrs
+        .iter()
+        .filter(|phdr| phdr.p_type == PT_LOAD)
+        .collect::<Vec<_>>();
+    let mode = |range: &Range<Addr>| {
+        loads
+            .iter()
+            .find(|phdr| phdr.p_vaddr == range.start)
+            // Lacking better information we assume file backed
+            // mappings to be readable.
+            .map_or(0b1001, |phdr| flags_to_mode(phdr.p_flags))
+    };
+
+    let mut entries = loads
+        .iter()
+        .filter(|phdr| {
+            !mappings
+                .iter()
+                .any(|mapping| mapping.range.start == phdr.p_vaddr)
+        })
+        .map(|phdr| MapsEntry {
+            range: phdr.p_vaddr..phdr.p_vaddr.saturating_add(phdr.p_memsz),
+            mode: flags_to_mode(phdr.p_flags),
+            offset: 0,
+            path_name: None,
+        })
+        .collect::<Vec<_>>();
+
+    let () = entries.extend(mappings.into_iter().map(|mapping| {
+        let FileMapping {
+            range,
+            offset,
+            path,
+        } = mapping;
+
+        MapsEntry {
+            mode: mode(&range),
+            range,
+            offset,
+            path_name: Some(PathName::Path(EntryPath {
+                maps_file: path.clone(),
+                symbolic_path: path,
+                deleted: false,
+            })),
+        }
+    }));
+    let () = entries.sort_by_key(|entry| entry.range.start);
+    Ok(entries)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write as _;
+    use std::path::Path;
+
+    use tempfile::NamedTempFile;
+
+    use crate::inspect;
+    use crate::inspect::Inspector;
+    use crate::symbolize;
+    use crate::symbolize::Reason;
+    use crate::symbolize::Symbolized;
+    use crate::symbolize::Symbolizer;
+    use crate::ErrorKind;
+
+
+    /// Create the contents of a minimal core file containing an
+    /// `NT_FILE` note describing `files` as well as loadable segments
+    /// for `loads`, each given as address range and segment flags.
+    fn core_file(files: &[(Range<Addr>, u64, &Path)], loads: &[(Range<Addr>, u32)]) -> Vec<u8> {
+        fn pad(data: &mut Vec<u8>) {
+            let () = data.resize((data.len() + 3) & !3, 0);
+        }
+
+        let page_size = 0x1000u64;
+        let mut desc = Vec::new();
+        let () = desc.extend((files.len() as u64).to_ne_bytes());
+        let () = desc.extend(page_size.to_ne_bytes());
+        for (range, offset, _path) in files {
+            let () = desc.extend(range.start.to_ne_bytes());
+            let () = desc.extend(range.end.to_ne_bytes());
+            let () = desc.extend((offset / page_size).to_ne_bytes());
+        }
+        for (_range, _offset, path) in files {
+            let () = desc.extend(path.to_str().unwrap().as_bytes());
+            let () = desc.push(0);
+        }
+
+        let mut notes = Vec::new();
+        // An unrelated note preceding the one of interest.
+        let () = notes.extend(5u32.to_ne_bytes());
+        let () = notes.extend(4u32.to_ne_bytes());
+        let () = notes.extend(1u32.to_ne_bytes());
+        let () = notes.extend(b"CORE\0");
+        let () = pad(&mut notes);
+        let () = notes.extend([0; 4]);
+        let () = notes.extend(5u32.to_ne_bytes());
+        let () = notes.extend((desc.len() as u32).to_ne_bytes());
+        let () = notes.extend(NT_FILE.to_ne_bytes());
+        let () = notes.extend(b"CORE\0");
+        let () = pad(&mut notes);
+        let () = notes.extend(&desc);
+        let () = pad(&mut notes);
+
+        let phnum = 1 + loads.len();
+        let phoff = 64u64;
+        let notes_off = phoff + 56 * phnum as u64;
+
+        let mut data = Vec::new();
+        let () = data.extend(b"\x7fELF\x02\x01\x01");
+        let () = data.resize(16, 0);
+        let () = data.extend(ET_CORE.to_ne_bytes());
+        let () = data.extend(62u16.to_ne_bytes());
+        let () = data.extend(1u32.to_ne_bytes());
+        let () = data.extend(0u64.to_ne_bytes());
+        let () = data.extend(phoff.to_ne_bytes());
+        let () = data.extend(0u64.to_ne_bytes());
+        let () = data.extend(0u32.to_ne_bytes());
+        let () = data.extend(64u16.to_ne_bytes());
+        let () = data.extend(56u16.to_ne_bytes());
+        let () = data.extend((phnum as u16).to_ne_bytes());
+        let () = data.extend(64u16.to_ne_bytes());
+        let () = data.extend(0u16.to_ne_bytes());
+        let () = data.extend(0u16.to_ne_bytes());
+        assert_eq!(data.len(), 64);
+
+        let mut phdr = |ty: u32, flags: u32, offset: u64, vaddr: u64, filesz: u64, memsz: u64| {
+            let () = data.extend(ty.to_ne_bytes());
+            let () = data.extend(flags.to_ne_bytes());
+            let () = data.extend(offset.to_ne_bytes());
+            let () = data.extend(vaddr.to_ne_bytes());
+            let () = data.extend(0u64.to_ne_bytes());
+            let () = data.extend(filesz.to_ne_bytes());
+            let () = data.extend(memsz.to_ne_bytes());
+            let () = data.extend(4u64.to_ne_bytes());
+        };
+        let () = phdr(PT_NOTE, 0, notes_off, 0, notes.len() as u64, 0);
+        for (range, flags) in loads {
+            let () = phdr(PT_LOAD, *flags, 0, range.start, 0, range.end - range.start);
+        }
+        let () = data.extend(notes);
+        data
+    }
+
+    /// Check that we can reconstruct the memory map of a process from
+    /// a core file.
+    #[test]
+    fn core_file_parsing() {
+        let files = [
+            (0x1000..0x3000, 0x0, Path::new("/usr/bin/true")),
+            (0x3000..0x4000, 0x2000, Path::new("/usr/bin/true")),
+        ];
+        let loads = [
+            (0x1000..0x3000, PF_R | PF_X),
+            (0x3000..0x4000, PF_R | PF_W),
+            (0x8000..0x9000, PF_R | PF_W),
+        ];
+        let data = core_file(&files, &loads);
+        let entries = parse_maps(&Mmap::from_vec(data)).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        assert_eq!(entries[0].range, 0x1000..0x3000);
+        assert_eq!(entries[0].mode, 0b1011);
+        assert_eq!(entries[0].offset, 0);
+        let path = entries[0].path_name.as_ref().unwrap().as_path().unwrap();
+        assert_eq!(path.maps_file, Path::new("/usr/bin/true"));
+        assert_eq!(path.symbolic_path, Path::new("/usr/bin/true"));
+
+        assert_eq!(entries[1].range, 0x3000..0x4000);
+        assert_eq!(entries[1].mode, 0b1101);
+        assert_eq!(entries[1].offset, 0x2000);
+
+        assert_eq!(entries[2].range, 0x8000..0x9000);
+        assert_eq!(entries[2].mode, 0b1101);
+        assert_eq!(entries[2].path_name, None);
+    }
+
+    /// Check that we can symbolize addresses using a core file.
+    #[test]
+    fn core_file_symbolization() {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("libtest-so.so");
+        let src = inspect::Source::Elf(inspect::Elf::new(&path));
+        let inspector = Inspector::new();
+        let results = inspector
+            .lookup(&src, &["the_answer"])
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 1);
+        let file_offset = results[0].file_offset.unwrap();
+
+        let base = 0x7f0000000000;
+        let files = [(base..base + 0x100000, 0, path.as_path())];
+        let loads = [
+            (base..base + 0x100000, PF_R | PF_X),
+            // A guard page.
+            (0x8000..0x9000, 0),
+        ];
+        let mut core = NamedTempFile::new().unwrap();
+        let () = core.write_all(&core_file(&files, &loads)).unwrap();
+
+        let src = symbolize::Source::from(symbolize::Coredump::new(core.path()));
+        let symbolizer = Symbolizer::new();
+        let addrs = [base + file_offset, 0x8000, 0x1000];
+        let results = symbolizer
+            .symbolize(&src, symbolize::Input::AbsAddr(&addrs))
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_sym().unwrap().name, "the_answer");
+        assert_eq!(
+            results[1],
+            Symbolized::Unknown(Reason::SpecialRegion(symbolize::SpecialRegion::Guard))
+        );
+        assert_eq!(results[2], Symbolized::Unknown(Reason::Unmapped));
+    }
+
+    /// Check that we reject ELF files that are not core files.
+    #[test]
+    fn non_core_file() {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("libtest-so.so");
+        let mmap = Mmap::builder().open(path).unwrap();
+        let err = parse_maps(&mmap).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,79 @@
+use std::env;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::os::unix::io::AsRawFd as _;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+use crate::mmap::Mmap;
+use crate::Error;
+use crate::ErrorExt as _;
+use crate::Result;
+
+use super::index::IndexResolver;
+use super::protocol::read_line;
+use super::protocol::send_with_fd;
+use super::protocol::Request;
+use super::protocol::Response;
+
+
+/// A client of the daemon, retrieving indexes from it.
+#[derive(Clone, Debug)]
+pub(crate) struct Client {
+    /// The path to the daemon's socket.
+    socket: PathBuf,
+}
+
+impl Client {
+    /// Create a new `Client` for the daemon listening on `socket`.
+    pub fn new(socket: PathBuf) -> Self {
+        Self { socket }
+    }
+
+    /// Retrieve a resolver for the index of the file referenced by
+    /// `request`.
+    pub fn index(&self, request: &Request) -> Result<IndexResolver> {
+        let path = request.path();
+        // The daemon has a different working directory than we do.
+        let request = if path.is_relative() {
+            let path = env::current_dir()?.join(path);
+            match request {
+                Request::Elf(..) => Request::Elf(path),
+                Request::Kallsyms(..) => Request::Kallsyms(path),
+            }
+        } else {
+            request.clone()
+        };
+
+        // The daemon only works with files that we can open ourselves.
+        let file = File::open(request.path())
+            .with_context(|| format!("failed to open {}", request.path().display()))?;
+        let stream = UnixStream::connect(&self.socket).with_context(|| {
+            format!(
+                "failed to connect to cache daemon at {}",
+                self.socket.display()
+            )
+        })?;
+        let () = send_with_fd(&stream, &request.encode()?, file.as_raw_fd())
+            .context("failed to send request to cache daemon")?;
+        let line = read_line(&mut BufReader::new(&stream))?;
+
+        match Response::decode(&line)? {
+            Response::Ok(index) => {
+                let file = File::open(&index)
+                    .with_context(|| format!("failed to open index {}", index.display()))?;
+                let mmap = Mmap::map(&file)
+                    .with_context(|| format!("failed to map index {}", index.display()))?;
+                IndexResolver::from_mmap(mmap, request.path().to_path_buf())
+            }
+            Response::Err(msg) => Err(Error::from(io::Error::new(io::ErrorKind::Other, msg)))
+                .with_context(|| {
+                    format!(
+                        "cache daemon failed to provide index for {}",
+                        request.path().display()
+                    )
+                }),
+        }
+    }
+}
@@ -0,0 +1,410 @@
+//! The on-disk format of symbol indexes shared by the daemon.
+//!
+//! An index is laid out as follows, with all values in native byte
+//! order (indexes never leave the host they were created on):
+//!
+//! ```text
+//! header:  magic (4 bytes) | version (u32) | flags (u32) | reserved (u32)
+//!          | entry count (u64) | string table offset (u64)
+//! entries: address (u64) | size (u64) | name offset (u32) | name length (u32)
+//! strings: concatenated symbol names, without separators
+//! ```
+//!
+//! Entries are sorted by address, allowing for lookups directly on the
+//! memory mapped file without any further parsing.
+
+use std::borrow::Cow;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::io::Write;
+use std::mem::size_of;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str;
+
+use crate::inspect::FindAddrOpts;
+use crate::inspect::SymInfo;
+use crate::inspect::SymType;
+use crate::mmap::Mmap;
+use crate::symbolize::AddrCodeInfo;
+use crate::symbolize::IntModule;
+use crate::symbolize::IntSym;
+use crate::symbolize::Reason;
+use crate::symbolize::SrcLang;
+use crate::util::find_match_or_lower_bound_by_key;
+use crate::util::Pod;
+use crate::util::ReadRaw as _;
+use crate::Addr;
+use crate::Error;
+use crate::IntoError as _;
+use crate::Result;
+use crate::SymResolver;
+
+
+/// The magic number identifying an index.
+const MAGIC: &[u8; 4] = b"BZSI";
+/// The version of the index format.
+const VERSION: u32 = 1;
+/// A flag indicating that entries carry meaningful sizes.
+const FLAG_SIZES: u32 = 0b1;
+/// The size of the index header, in bytes.
+const HEADER_SIZE: usize = 32;
+
+
+/// An entry of the index, describing a single symbol.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+struct Entry {
+    addr: u64,
+    size: u64,
+    name_off: u32,
+    name_len: u32,
+}
+
+// SAFETY: `Entry` is valid for any bit pattern.
+unsafe impl Pod for Entry {}
+
+
+/// A symbol to be stored in an index.
+#[derive(Clone, Debug)]
+pub(crate) struct IndexSym<'name> {
+    pub addr: Addr,
+    pub size: u64,
+    pub name: &'name str,
+}
+
+
+/// Write an index of the provided symbols to `writer`.
+///
+/// `sizes` indicates whether the symbols' sizes are meaningful.
+pub(crate) fn write_index<W>(syms: &mut [IndexSym<'_>], sizes: bool, writer: &mut W) -> Result<()>
+where
+    W: Write,
+{
+    // Sorting is stable, keeping the relative order of symbols at the
+    // same address intact.
+    let () = syms.sort_by_key(|sym| sym.addr);
+
+    let strtab_off = HEADER_SIZE + syms.len() * size_of::<Entry>();
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    let () = header.extend_from_slice(MAGIC);
+    let () = header.extend_from_slice(&VERSION.to_ne_bytes());
+    let flags = if sizes { FLAG_SIZES } else { 0 };
+    let () = header.extend_from_slice(&flags.to_ne_bytes());
+    let () = header.extend_from_slice(&0u32.to_ne_bytes());
+    let () = header.extend_from_slice(&(syms.len() as u64).to_ne_bytes());
+    let () = header.extend_from_slice(&(strtab_off as u64).to_ne_bytes());
+    debug_assert_eq!(header.len(), HEADER_SIZE);
+    let () = writer.write_all(&header)?;
+
+    let mut name_off = 0u32;
+    for sym in syms.iter() {
+        let name_len = u32::try_from(sym.name.len())
+            .ok()
+            .ok_or_invalid_input(|| "symbol name too long")?;
+        let () = writer.write_all(&sym.addr.to_ne_bytes())?;
+        let () = writer.write_all(&sym.size.to_ne_bytes())?;
+        let () = writer.write_all(&name_off.to_ne_bytes())?;
+        let () = writer.write_all(&name_len.to_ne_bytes())?;
+        name_off = name_off
+            .checked_add(name_len)
+            .ok_or_invalid_input(|| "symbol names exceed maximum index size")?;
+    }
+
+    for sym in syms.iter() {
+        let () = writer.write_all(sym.name.as_bytes())?;
+    }
+    Ok(())
+}
+
+
+/// A symbol resolver working on an index, as created by the daemon.
+pub(crate) struct IndexResolver {
+    /// The memory mapped index.
+    mmap: Mmap,
+    /// Whether entries carry meaningful sizes.
+    sizes: bool,
+    /// The number of entries in the index.
+    count: usize,
+    /// The offset of the string table.
+    strtab_off: usize,
+    /// The path of the file that the index was created for.
+    file_name: PathBuf,
+}
+
+impl IndexResolver {
+    /// Create an `IndexResolver` from the memory mapped index in `mmap`,
+    /// with `file_name` being the path of the file it was created for.
+    pub(crate) fn from_mmap(mmap: Mmap, file_name: PathBuf) -> Result<Self> {
+        let mut data = &*mmap;
+        let magic = data
+            .read_slice(MAGIC.len())
+            .ok_or_invalid_data(|| "failed to read index magic")?;
+        if magic != MAGIC {
+            return Err(Error::with_invalid_data("data is not a symbol index"))
+        }
+        let version = data
+            .read_u32()
+            .ok_or_invalid_data(|| "failed to read index version")?;
+        if version != VERSION {
+            return Err(Error::with_invalid_data(format!(
+                "encountered unsupported index version {version}"
+            )))
+        }
+        let flags = data
+            .read_u32()
+            .ok_or_invalid_data(|| "failed to read index flags")?;
+        let _reserved = data
+            .read_u32()
+            .ok_or_invalid_data(|| "failed to read index header")?;
+        let count = data
+            .read_u64()
+            .ok_or_invalid_data(|| "failed to read index entry count")?;
+        let strtab_off = data
+            .read_u64()
+            .ok_or_invalid_data(|| "failed to read index string table offset")?;
+
+        let count = usize::try_from(count).ok().ok_or_invalid_data(|| {
+            format!("index entry count ({count}) does not fit into usize")
+        })?;
+        let strtab_off = usize::try_from(strtab_off)
+            .ok()
+            .filter(|off| *off <= mmap.len())
+            .ok_or_invalid_data(|| "index string table offset out of bounds")?;
+
+        let slf = Self {
+            mmap,
+            sizes: flags & FLAG_SIZES != 0,
+            count,
+            strtab_off,
+            file_name,
+        };
+        // Make sure that the entries are accessible.
+        let _entries = slf.entries()?;
+        Ok(slf)
+    }
+
+    fn entries(&self) -> Result<&[Entry]> {
+        let mut data = self
+            .mmap
+            .get(HEADER_SIZE..self.strtab_off)
+            .ok_or_invalid_data(|| "index entries out of bounds")?;
+        data.read_pod_slice_ref::<Entry>(self.count)
+            .ok_or_invalid_data(|| "index entries are out of bounds or misaligned")
+    }
+
+    fn name(&self, entry: &Entry) -> Result<&str> {
+        let start = self.strtab_off + entry.name_off as usize;
+        let end = start + entry.name_len as usize;
+        let name = self
+            .mmap
+            .get(start..end)
+            .ok_or_invalid_data(|| "index symbol name out of bounds")?;
+        let name = str::from_utf8(name)
+            .ok()
+            .ok_or_invalid_data(|| "index symbol name is not valid UTF-8")?;
+        Ok(name)
+    }
+
+    /// Retrieve the path of the file that the index was created for.
+    pub(crate) fn file_name(&self) -> &Path {
+        &self.file_name
+    }
+
+    /// Retrieve the memory mapping backing the index.
+    pub(crate) fn mmap(&self) -> &Mmap {
+        &self.mmap
+    }
+}
+
+impl SymResolver for IndexResolver {
+    fn find_sym(&self, addr: Addr) -> Result<Result<IntSym<'_>, Reason>> {
+        let entries = self.entries()?;
+        if entries.is_empty() {
+            return Ok(Err(Reason::MissingSyms))
+        }
+
+        if let Some(idx) = find_match_or_lower_bound_by_key(entries, addr, |entry| entry.addr) {
+            for entry in entries[idx..].iter() {
+                if entry.addr > addr {
+                    break
+                }
+
+                // A size of zero indicates an unknown size, just as it
+                // does in ELF.
+                if !self.sizes || entry.size == 0 || addr < entry.addr.saturating_add(entry.size) {
+                    let sym = IntSym {
+                        name: self.name(entry)?,
+                        addr: entry.addr,
+                        size: self
+                            .sizes
+                            .then(|| usize::try_from(entry.size).unwrap_or(usize::MAX)),
+                        lang: SrcLang::Unknown,
+                    };
+                    return Ok(Ok(sym))
+                }
+            }
+        }
+        Ok(Err(Reason::UnknownAddr))
+    }
+
+    fn find_addr<'slf>(&'slf self, name: &str, opts: &FindAddrOpts) -> Result<Vec<SymInfo<'slf>>> {
+        if let SymType::Variable = opts.sym_type {
+            return Ok(Vec::new())
+        }
+
+        let mut syms = Vec::new();
+        for entry in self.entries()? {
+            if self.name(entry)? == name {
+                let sym = SymInfo {
+                    name: Cow::Borrowed(self.name(entry)?),
+                    addr: entry.addr,
+                    size: usize::try_from(entry.size).unwrap_or(usize::MAX),
+                    sym_type: SymType::Function,
+                    file_offset: None,
+                    obj_file_name: None,
+                    section: None,
+                };
+                let () = syms.push(sym);
+            }
+        }
+        Ok(syms)
+    }
+
+    fn find_code_info(&self, _addr: Addr, _inlined_fns: bool) -> Result<Option<AddrCodeInfo<'_>>> {
+        Ok(None)
+    }
+    fn module_info(&self, _addr: Addr) -> Result<IntModule<'_>> {
+        // The index only captures symbols, so all we know is the
+        // file it was created for.
+        let module = IntModule {
+            path: Some(&self.file_name),
+            ..Default::default()
+        };
+        Ok(module)
+    }
+}
+
+impl Debug for IndexResolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "IndexResolver({})", self.file_name.display())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::tempfile;
+
+    use crate::ErrorKind;
+
+
+    /// Write an index of `syms` to a file and map it.
+    fn index(syms: &mut [IndexSym<'_>], sizes: bool) -> IndexResolver {
+        let mut file = tempfile().unwrap();
+        let () = write_index(syms, sizes, &mut file).unwrap();
+        let mmap = Mmap::map(&file).unwrap();
+        IndexResolver::from_mmap(mmap, PathBuf::from("/foo")).unwrap()
+    }
+
+    /// Check that we can look up symbols in an index.
+    #[test]
+    fn index_lookup() {
+        let mut syms = [
+            IndexSym {
+                addr: 0x3000,
+                size: 0x10,
+                name: "baz",
+            },
+            IndexSym {
+                addr: 0x1000,
+                size: 0x100,
+                name: "foo",
+            },
+            IndexSym {
+                addr: 0x2000,
+                size: 0,
+                name: "bar",
+            },
+        ];
+        let resolver = index(&mut syms, true);
+        assert_eq!(format!("{resolver:?}"), "IndexResolver(/foo)");
+        assert_eq!(resolver.file_name(), Path::new("/foo"));
+
+        let sym = resolver.find_sym(0x1010).unwrap().unwrap();
+        assert_eq!(sym.name, "foo");
+        assert_eq!(sym.addr, 0x1000);
+        assert_eq!(sym.size, Some(0x100));
+        // Past the end of `foo`.
+        assert_eq!(
+            resolver.find_sym(0x1100).unwrap().err(),
+            Some(Reason::UnknownAddr)
+        );
+        // `bar` has no size and covers everything up to `baz`.
+        let sym = resolver.find_sym(0x2fff).unwrap().unwrap();
+        assert_eq!(sym.name, "bar");
+        let sym = resolver.find_sym(0x3000).unwrap().unwrap();
+        assert_eq!(sym.name, "baz");
+        assert_eq!(
+            resolver.find_sym(0x10).unwrap().err(),
+            Some(Reason::UnknownAddr)
+        );
+
+        let opts = FindAddrOpts::default();
+        let syms = resolver.find_addr("baz", &opts).unwrap();
+        assert_eq!(syms.len(), 1);
+        assert_eq!(syms[0].addr, 0x3000);
+        assert!(resolver.find_addr("qux", &opts).unwrap().is_empty());
+
+        // Without sizes, symbols extend to the next one.
+        let resolver = index(&mut syms_without_sizes(), false);
+        let sym = resolver.find_sym(0x1100).unwrap().unwrap();
+        assert_eq!(sym.name, "foo");
+        assert_eq!(sym.size, None);
+
+        let resolver = index(&mut [], false);
+        assert_eq!(
+            resolver.find_sym(0x1000).unwrap().err(),
+            Some(Reason::MissingSyms)
+        );
+    }
+
+    fn syms_without_sizes() -> Vec<IndexSym<'static>> {
+        vec![
+            IndexSym {
+                addr: 0x1000,
+                size: 0,
+                name: "foo",
+            },
+            IndexSym {
+                addr: 0x2000,
+                size: 0,
+                name: "bar",
+            },
+        ]
+    }
+
+    /// Check that we reject malformed indexes.
+    #[test]
+    fn invalid_index() {
+        let mut file = tempfile().unwrap();
+        let () = write_index(&mut syms_without_sizes(), false, &mut file).unwrap();
+        let mmap = Mmap::map(&file).unwrap();
+
+        for len in [0, 4, HEADER_SIZE, HEADER_SIZE + size_of::<Entry>()] {
+            let data = mmap.constrain(0..len as u64).unwrap();
+            let err = IndexResolver::from_mmap(data, PathBuf::new()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData, "{len}");
+        }
+
+        let mut data = mmap.to_vec();
+        data[0] = b'X';
+        let mut file = tempfile().unwrap();
+        let () = file.write_all(&data).unwrap();
+        let err = IndexResolver::from_mmap(Mmap::map(&file).unwrap(), PathBuf::new()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,190 @@
+//! A host-local daemon sharing parsed symbol information between
+//! processes.
+//!
+//! Many short-lived tools on a single host symbolizing addresses
+//! repeatedly parse the same inputs, most notably `libc` and the
+//! kernel's `/proc/kallsyms`. The [`Server`] provided by this module
+//! performs this parsing once per file version and shares the result
+//! via an index stored in shared memory. A
+//! [`Symbolizer`][crate::symbolize::Symbolizer] attaches to a running
+//! server by means of
+//! [`Builder::set_cache_daemon`][crate::symbolize::Builder::set_cache_daemon].
+//!
+//! ```no_run
+//! use blazesym::daemon::Server;
+//!
+//! let server = Server::bind("/run/blazesym.sock", "/dev/shm").unwrap();
+//! let () = server.run().unwrap();
+//! ```
+
+mod client;
+mod index;
+mod protocol;
+mod server;
+
+pub(crate) use client::Client;
+pub(crate) use index::IndexResolver;
+pub(crate) use protocol::Request;
+
+pub use server::Server;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::fs;
+    use std::io::BufReader;
+    use std::io::Write as _;
+    use std::os::unix::fs::MetadataExt as _;
+    use std::os::unix::fs::PermissionsExt as _;
+    use std::os::unix::net::UnixStream;
+    use std::path::Path;
+    use std::thread;
+
+    use tempfile::tempdir;
+
+    use crate::daemon::protocol::read_line;
+    use crate::daemon::protocol::Response;
+    use crate::symbolize::Reason;
+    use crate::ErrorKind;
+    use crate::SymResolver as _;
+
+
+    /// End-to-end test of index retrieval from a daemon.
+    #[test]
+    fn index_retrieval() {
+        let dir = tempdir().unwrap();
+        let server = Server::bind(dir.path().join("sock"), dir.path()).unwrap();
+        assert_ne!(format!("{server:?}"), "");
+        let client = Client::new(server.socket().to_path_buf());
+
+        let elf = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-stable-addresses-no-dwarf.bin");
+        let kallsyms = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("kallsyms");
+        let requests = [
+            Request::Elf(elf.clone()),
+            Request::Elf(elf),
+            Request::Kallsyms(kallsyms),
+        ];
+
+        // One more connection is made without going through the client.
+        let count = requests.len() + 1;
+        let index_count = thread::scope(|s| {
+            let handle = s.spawn(|| {
+                for _ in 0..count {
+                    let () = server.serve_one().unwrap();
+                }
+                fs::read_dir(dir.path())
+                    .unwrap()
+                    .filter(|entry| {
+                        let path = entry.as_ref().unwrap().path();
+                        path.extension().and_then(|ext| ext.to_str()) == Some("idx")
+                    })
+                    .count()
+            });
+
+            let resolver = client.index(&requests[0]).unwrap();
+            let sym = resolver.find_sym(0x2000100).unwrap().unwrap();
+            assert_eq!(sym.name, "factorial");
+            assert_eq!(sym.addr, 0x2000100);
+
+            // The index of the same file is shared.
+            let resolver = client.index(&requests[1]).unwrap();
+            assert_eq!(
+                resolver.find_sym(0x10).unwrap().err(),
+                Some(Reason::UnknownAddr)
+            );
+
+            let resolver = client.index(&requests[2]).unwrap();
+            let sym = resolver.find_sym(0xc0008001).unwrap().unwrap();
+            assert_eq!(sym.name, "stext");
+            assert_eq!(sym.size, None);
+
+            // Files that the client cannot open are never sent to the
+            // daemon.
+            let request = Request::Elf(dir.path().join("does-not-exist"));
+            let err = client.index(&request).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotFound);
+            assert!(format!("{err:#}").contains("does-not-exist"), "{err:#}");
+
+            // Requests not accompanied by a file descriptor are
+            // rejected.
+            let stream = UnixStream::connect(server.socket()).unwrap();
+            let () = (&stream).write_all(b"ELF /etc/shadow\n").unwrap();
+            let line = read_line(&mut BufReader::new(&stream)).unwrap();
+            match Response::decode(&line).unwrap() {
+                Response::Err(msg) => assert!(msg.contains("file descriptor"), "{msg}"),
+                response => panic!("unexpected response: {response:?}"),
+            }
+
+            handle.join().unwrap()
+        });
+        assert_eq!(index_count, 2);
+
+        // Indexes and socket are cleaned up once the server is gone.
+        let () = drop(server);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        // Connecting to a non-existent daemon fails.
+        let err = client.index(&requests[0]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+    }
+
+    /// Check that relative paths are resolved on the client side.
+    #[test]
+    fn relative_path_resolution() {
+        let dir = tempdir().unwrap();
+        let server = Server::bind(dir.path().join("sock"), dir.path()).unwrap();
+        let client = Client::new(server.socket().to_path_buf());
+
+        let cwd = env::current_dir().unwrap();
+        let elf = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-stable-addresses-no-dwarf.bin");
+        let relative = elf.strip_prefix(&cwd).unwrap().to_path_buf();
+        assert!(relative.is_relative());
+
+        thread::scope(|s| {
+            let _handle = s.spawn(|| server.serve_one().unwrap());
+            let resolver = client.index(&Request::Elf(relative.clone())).unwrap();
+            assert_eq!(resolver.file_name(), cwd.join(&relative));
+        });
+    }
+    /// Check that an idle client does not prevent others from being
+    /// served and that created indexes are only accessible by the
+    /// requesting user.
+    #[test]
+    fn idle_client() {
+        let dir = tempdir().unwrap();
+        // `Server::run` never returns, so the server has to outlive the
+        // test.
+        let server = Box::leak(Box::new(
+            Server::bind(dir.path().join("sock"), dir.path()).unwrap(),
+        ));
+        let client = Client::new(server.socket().to_path_buf());
+        let _handle = thread::spawn(|| server.run());
+
+        let _idle = UnixStream::connect(server.socket()).unwrap();
+
+        let elf = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-stable-addresses-no-dwarf.bin");
+        let resolver = client.index(&Request::Elf(elf)).unwrap();
+        let sym = resolver.find_sym(0x2000100).unwrap().unwrap();
+        assert_eq!(sym.name, "factorial");
+
+        let index = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.extension().and_then(|ext| ext.to_str()) == Some("idx"))
+            .unwrap();
+        let meta = fs::metadata(index).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+        assert_eq!(meta.uid(), unsafe { libc::geteuid() });
+    }
+}
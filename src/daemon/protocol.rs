@@ -0,0 +1,361 @@
+//! The wire protocol spoken between the daemon and its clients.
+//!
+//! The protocol is line based. A client sends a single request line of
+//! the form `<kind> <path>`, where `<kind>` is either `ELF` or
+//! `KALLSYMS`, and the daemon answers with either `OK <index path>` or
+//! `ERR <message>`, after which the connection is closed.
+//!
+//! Along with the request line the client passes an open file
+//! descriptor for the file in question, as `SCM_RIGHTS` ancillary
+//! data. The path is informational only: the daemon never opens files
+//! on behalf of clients.
+
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::io::Read as _;
+use std::io::Write as _;
+use std::mem;
+use std::os::unix::ffi::OsStrExt as _;
+use std::os::unix::io::AsRawFd as _;
+use std::os::unix::io::FromRawFd as _;
+use std::os::unix::io::RawFd;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::path::PathBuf;
+use std::ptr;
+
+use crate::Error;
+use crate::IntoError as _;
+use crate::Result;
+
+
+/// The maximum length of a line, including the terminating newline.
+const MAX_LINE_LEN: u64 = 64 * 1024;
+
+/// Flags used for sending messages; we don't want to be killed by
+/// `SIGPIPE` if the peer went away.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const SEND_FLAGS: libc::c_int = libc::MSG_NOSIGNAL;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const SEND_FLAGS: libc::c_int = 0;
+
+/// Flags used for receiving messages.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const RECV_FLAGS: libc::c_int = libc::MSG_CMSG_CLOEXEC;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const RECV_FLAGS: libc::c_int = 0;
+
+
+/// A buffer for ancillary data that is suitably aligned for a
+/// `cmsghdr` and large enough for a single file descriptor.
+#[repr(C)]
+union CmsgBuffer {
+    _align: libc::cmsghdr,
+    buf: [u8; 64],
+}
+
+
+/// Send `data` over `stream`, passing along `fd` as ancillary data.
+pub(crate) fn send_with_fd(stream: &UnixStream, data: &[u8], fd: RawFd) -> Result<()> {
+    let mut cmsg = CmsgBuffer { buf: [0; 64] };
+    let mut iov = libc::iovec {
+        iov_base: data.as_ptr() as *mut _,
+        iov_len: data.len(),
+    };
+    // SAFETY: `msghdr` is a plain C struct for which all zeroes is a
+    //         valid bit pattern.
+    let mut msg = unsafe { mem::zeroed::<libc::msghdr>() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    // SAFETY: `CMSG_SPACE` is a pure computation.
+    let space = unsafe { libc::CMSG_SPACE(mem::size_of::<RawFd>() as _) } as usize;
+    debug_assert!(space <= mem::size_of::<CmsgBuffer>());
+    msg.msg_control = &mut cmsg as *mut CmsgBuffer as *mut _;
+    msg.msg_controllen = space as _;
+
+    // SAFETY: The control buffer is suitably sized and aligned for a
+    //         single header carrying one file descriptor.
+    let () = unsafe {
+        let hdr = libc::CMSG_FIRSTHDR(&msg);
+        (*hdr).cmsg_level = libc::SOL_SOCKET;
+        (*hdr).cmsg_type = libc::SCM_RIGHTS;
+        (*hdr).cmsg_len = libc::CMSG_LEN(mem::size_of::<RawFd>() as _) as _;
+        ptr::write_unaligned(libc::CMSG_DATA(hdr) as *mut RawFd, fd)
+    };
+
+    // SAFETY: `msg` references valid buffers for the duration of the
+    //         call.
+    let rc = unsafe { libc::sendmsg(stream.as_raw_fd(), &msg, SEND_FLAGS) };
+    if rc < 0 {
+        return Err(Error::from(io::Error::last_os_error()))
+    }
+    // The file descriptor is attached to the first byte sent. Whatever
+    // did not make it can be sent as regular data.
+    let () = (&*stream).write_all(&data[rc as usize..])?;
+    Ok(())
+}
+
+/// Receive data from `stream` into `buf`, along with at most one file
+/// descriptor passed as ancillary data.
+pub(crate) fn recv_with_fd(stream: &UnixStream, buf: &mut [u8]) -> Result<(usize, Option<File>)> {
+    let mut cmsg = CmsgBuffer { buf: [0; 64] };
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr().cast(),
+        iov_len: buf.len(),
+    };
+    // SAFETY: `msghdr` is a plain C struct for which all zeroes is a
+    //         valid bit pattern.
+    let mut msg = unsafe { mem::zeroed::<libc::msghdr>() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = &mut cmsg as *mut CmsgBuffer as *mut _;
+    msg.msg_controllen = mem::size_of::<CmsgBuffer>() as _;
+
+    // SAFETY: `msg` references valid buffers for the duration of the
+    //         call.
+    let rc = unsafe { libc::recvmsg(stream.as_raw_fd(), &mut msg, RECV_FLAGS) };
+    if rc < 0 {
+        return Err(Error::from(io::Error::last_os_error()))
+    }
+
+    let mut files = Vec::new();
+    // SAFETY: The kernel filled in the control buffer referenced by
+    //         `msg` and we only access it through the `CMSG_*`
+    //         accessors.
+    let () = unsafe {
+        let mut hdr = libc::CMSG_FIRSTHDR(&msg);
+        while !hdr.is_null() {
+            if (*hdr).cmsg_level == libc::SOL_SOCKET && (*hdr).cmsg_type == libc::SCM_RIGHTS {
+                let data = libc::CMSG_DATA(hdr);
+                let len = (*hdr).cmsg_len as usize - (data as usize - hdr as usize);
+                for i in 0..len / mem::size_of::<RawFd>() {
+                    let fd = ptr::read_unaligned((data as *const RawFd).add(i));
+                    // Take ownership right away, so that the descriptor
+                    // is closed in all cases.
+                    let () = files.push(File::from_raw_fd(fd));
+                }
+            }
+            hdr = libc::CMSG_NXTHDR(&msg, hdr);
+        }
+    };
+
+    if msg.msg_flags & libc::MSG_CTRUNC != 0 || files.len() > 1 {
+        return Err(Error::with_invalid_data(
+            "received more than one file descriptor",
+        ))
+    }
+    let file = files.pop();
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    if let Some(file) = &file {
+        // SAFETY: `fcntl` is always safe to call.
+        let _rc = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) };
+    }
+    Ok((rc as usize, file))
+}
+
+
+/// Read a newline terminated line from `reader`, excluding the
+/// newline.
+pub(crate) fn read_line<R>(reader: &mut R) -> Result<Vec<u8>>
+where
+    R: BufRead,
+{
+    let mut line = Vec::new();
+    let _count = reader.take(MAX_LINE_LEN).read_until(b'\n', &mut line)?;
+    if line.pop() != Some(b'\n') {
+        return Err(Error::with_invalid_data(
+            "encountered unterminated or overly long protocol line",
+        ))
+    }
+    Ok(line)
+}
+
+/// Ensure that `path` can be transmitted as part of a line.
+fn check_path(path: &Path) -> Result<&[u8]> {
+    Some(path.as_os_str().as_bytes())
+        .filter(|bytes| !bytes.is_empty() && !bytes.contains(&b'\n'))
+        .ok_or_invalid_input(|| format!("path {} cannot be transmitted to daemon", path.display()))
+}
+
+
+/// A request for the index of a file.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum Request {
+    /// A request for the symbols of an ELF file.
+    Elf(PathBuf),
+    /// A request for the symbols of a file in kallsyms format.
+    Kallsyms(PathBuf),
+}
+
+impl Request {
+    /// Retrieve the path of the file that the request refers to.
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Elf(path) | Self::Kallsyms(path) => path,
+        }
+    }
+
+    /// Encode the request into a line, including the terminating
+    /// newline.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let kind = match self {
+            Self::Elf(..) => b"ELF ".as_slice(),
+            Self::Kallsyms(..) => b"KALLSYMS ".as_slice(),
+        };
+        let path = check_path(self.path())?;
+        Ok([kind, path, b"\n"].concat())
+    }
+
+    /// Decode a request from a line, excluding the terminating newline.
+    pub fn decode(line: &[u8]) -> Result<Self> {
+        let (ctor, path): (fn(PathBuf) -> Self, _) = if let Some(path) = line.strip_prefix(b"ELF ")
+        {
+            (Self::Elf, path)
+        } else if let Some(path) = line.strip_prefix(b"KALLSYMS ") {
+            (Self::Kallsyms, path)
+        } else {
+            return Err(Error::with_invalid_data(format!(
+                "encountered malformed request: {}",
+                String::from_utf8_lossy(line)
+            )))
+        };
+
+        if path.is_empty() {
+            return Err(Error::with_invalid_data("request is lacking a path"))
+        }
+        Ok(ctor(PathBuf::from(OsStr::from_bytes(path))))
+    }
+}
+
+
+/// The daemon's response to a [`Request`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Response {
+    /// The path to the requested index.
+    Ok(PathBuf),
+    /// An error message describing why the request failed.
+    Err(String),
+}
+
+impl Response {
+    /// Encode the response into a line, including the terminating
+    /// newline.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        match self {
+            Self::Ok(path) => Ok([b"OK ", check_path(path)?, b"\n"].concat()),
+            // Error messages may span multiple lines, but we only have
+            // one available.
+            Self::Err(msg) => Ok(format!("ERR {}\n", msg.replace('\n', " ")).into_bytes()),
+        }
+    }
+
+    /// Decode a response from a line, excluding the terminating
+    /// newline.
+    pub fn decode(line: &[u8]) -> Result<Self> {
+        if let Some(path) = line.strip_prefix(b"OK ") {
+            Ok(Self::Ok(PathBuf::from(OsStr::from_bytes(path))))
+        } else if let Some(msg) = line.strip_prefix(b"ERR ") {
+            Ok(Self::Err(String::from_utf8_lossy(msg).into_owned()))
+        } else {
+            Err(Error::with_invalid_data(format!(
+                "encountered malformed response: {}",
+                String::from_utf8_lossy(line)
+            )))
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Seek as _;
+
+    use crate::ErrorKind;
+
+
+    /// Check that requests and responses survive an encoding round
+    /// trip.
+    #[test]
+    fn encoding_round_trip() {
+        let requests = [
+            Request::Elf(PathBuf::from("/usr/lib/libc.so.6")),
+            Request::Kallsyms(PathBuf::from("/proc/kallsyms")),
+            Request::Elf(PathBuf::from("/tmp/with space")),
+        ];
+        for request in requests {
+            let mut line = request.encode().unwrap();
+            let () = line.push(b'x');
+            let line = read_line(&mut line.as_slice()).unwrap();
+            assert_eq!(Request::decode(&line).unwrap(), request);
+        }
+
+        let responses = [
+            Response::Ok(PathBuf::from("/dev/shm/blazesym.idx")),
+            Response::Err("something\nwent wrong".to_string()),
+        ];
+        for response in responses {
+            let line = response.encode().unwrap();
+            let line = read_line(&mut line.as_slice()).unwrap();
+            let decoded = Response::decode(&line).unwrap();
+            match (&response, decoded) {
+                (Response::Ok(..), decoded) => assert_eq!(decoded, response),
+                (Response::Err(..), decoded) => {
+                    assert_eq!(decoded, Response::Err("something went wrong".to_string()))
+                }
+            }
+        }
+    }
+
+    /// Check that we reject malformed protocol data.
+    #[test]
+    fn malformed_data() {
+        let err = Request::Elf(PathBuf::from("/foo\nbar"))
+            .encode()
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        let err = Request::Elf(PathBuf::new()).encode().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        for line in [b"".as_slice(), b"ELF", b"ELF ", b"FOO /bar"] {
+            let err = Request::decode(line).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+        let err = Response::decode(b"MAYBE /foo").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let err = read_line(&mut b"ELF /foo".as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let long = vec![b'x'; MAX_LINE_LEN as usize + 1];
+        let err = read_line(&mut long.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    /// Check that we can pass a file descriptor along with data.
+    #[test]
+    fn fd_passing() {
+        let (left, right) = UnixStream::pair().unwrap();
+        let mut file = tempfile::tempfile().unwrap();
+        let () = file.write_all(b"contents").unwrap();
+        let () = file.rewind().unwrap();
+
+        let () = send_with_fd(&left, b"hello", file.as_raw_fd()).unwrap();
+        let () = drop(file);
+        let mut buf = [0; 16];
+        let (count, file) = recv_with_fd(&right, &mut buf).unwrap();
+        assert_eq!(&buf[..count], b"hello");
+
+        let mut data = Vec::new();
+        let _count = file.unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"contents");
+
+        // Regular data does not carry a file descriptor.
+        let () = (&left).write_all(b"world").unwrap();
+        let (count, file) = recv_with_fd(&right, &mut buf).unwrap();
+        assert_eq!(&buf[..count], b"world");
+        assert!(file.is_none());
+    }
+}
@@ -0,0 +1,482 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs;
+use std::fs::File;
+use std::fs::Metadata;
+use std::fs::OpenOptions;
+use std::hash::BuildHasher as _;
+use std::hash::Hasher as _;
+use std::io;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Read as _;
+use std::io::Write as _;
+use std::os::unix::fs::MetadataExt as _;
+use std::os::unix::fs::OpenOptionsExt as _;
+use std::os::unix::io::AsRawFd as _;
+use std::os::unix::net::UnixListener;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::sync_channel;
+use std::sync::mpsc::Receiver;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::elf::ElfParser;
+use crate::inspect::FindAddrOpts;
+use crate::inspect::SymType;
+use crate::ksym::KSymResolver;
+use crate::log;
+use crate::mmap::Mmap;
+use crate::Error;
+use crate::ErrorExt as _;
+use crate::IntoError as _;
+use crate::Result;
+
+use super::index::write_index;
+use super::index::IndexSym;
+use super::protocol::read_line;
+use super::protocol::recv_with_fd;
+use super::protocol::Request;
+use super::protocol::Response;
+
+
+/// The time for which indexes of pseudo files (such as
+/// `/proc/kallsyms`) are reused.
+///
+/// Such files do not report meaningful meta data and so we cannot
+/// detect changes to them.
+const PSEUDO_FILE_TTL: Duration = Duration::from_secs(10);
+
+/// The maximum number of client connections served concurrently.
+const MAX_WORKERS: usize = 16;
+
+/// The time after which reading a request from or sending a response to
+/// a client is aborted.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+
+/// Generate a random number for use in file names.
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Retrieve the user ID of the process on the other end of `stream`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> Result<libc::uid_t> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: `cred` and `len` are valid for writes and `len` reflects
+    //         the size of `cred`.
+    let rc = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut _,
+            &mut len,
+        )
+    };
+    if rc < 0 {
+        return Err(Error::from(io::Error::last_os_error()))
+            .context("failed to retrieve peer credentials")
+    }
+    Ok(cred.uid)
+}
+
+/// Retrieve the user ID of the process on the other end of `stream`.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> Result<libc::uid_t> {
+    let mut uid = 0;
+    let mut gid = 0;
+    // SAFETY: `uid` and `gid` are valid for writes.
+    let rc = unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) };
+    if rc < 0 {
+        return Err(Error::from(io::Error::last_os_error()))
+            .context("failed to retrieve peer credentials")
+    }
+    Ok(uid)
+}
+
+
+/// Meta data identifying a specific version of a file.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct FileVersion {
+    dev: u64,
+    inode: u64,
+    size: u64,
+    mtime_sec: i64,
+    mtime_nsec: i64,
+}
+
+impl From<&Metadata> for FileVersion {
+    fn from(meta: &Metadata) -> Self {
+        Self {
+            dev: meta.dev(),
+            inode: meta.ino(),
+            size: meta.size(),
+            mtime_sec: meta.mtime(),
+            mtime_nsec: meta.mtime_nsec(),
+        }
+    }
+}
+
+
+/// The kind of index being cached.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Kind {
+    Elf,
+    Kallsyms,
+}
+
+
+/// An index created by the daemon.
+#[derive(Debug)]
+struct CachedIndex {
+    /// The path to the index file.
+    path: PathBuf,
+    /// The time at which the index was created.
+    created: Instant,
+}
+
+
+/// A host-local daemon serving symbol indexes from shared memory.
+///
+/// Symbolization of ELF files and kernel addresses generally requires
+/// parsing of the respective symbol tables, which is costly for large
+/// inputs such as `libc` or `/proc/kallsyms`. A `Server` performs this
+/// parsing once and stores the result in the form of an index in a
+/// directory backed by shared memory (typically `/dev/shm`). Clients,
+/// i.e., [`Symbolizer`][crate::symbolize::Symbolizer] instances
+/// configured via
+/// [`Builder::set_cache_daemon`][crate::symbolize::Builder::set_cache_daemon],
+/// connect to the server through a Unix domain socket and map the
+/// index instead of parsing the file themselves.
+///
+/// Indexes are shared between all requests of a user for the same
+/// version of a file, independent of the path used for referring to
+/// it. They are recreated once the file changes.
+///
+/// The server never opens files on behalf of clients. Rather, clients
+/// pass an open file descriptor along with each request, proving that
+/// they have access to the file. Each index is only readable by the
+/// user that requested it, as determined by the credentials of the
+/// connecting process. Serving users other than the one the server
+/// runs as hence requires the privilege to change file ownership
+/// (`CAP_CHOWN`).
+pub struct Server {
+    /// The listener accepting client connections.
+    listener: UnixListener,
+    /// The path to the socket the server is bound to.
+    socket: PathBuf,
+    /// The directory in which indexes are stored.
+    shm_dir: PathBuf,
+    /// The indexes created so far.
+    indexes: Mutex<HashMap<(Kind, libc::uid_t, FileVersion), CachedIndex>>,
+    /// The counter used for creating unique index file names.
+    next_id: AtomicUsize,
+}
+
+impl Server {
+    /// Create a `Server` listening on the Unix domain socket at `socket`
+    /// and storing indexes in `shm_dir`.
+    pub fn bind<P, Q>(socket: P, shm_dir: Q) -> Result<Self>
+    where
+        P: Into<PathBuf>,
+        Q: Into<PathBuf>,
+    {
+        let socket = socket.into();
+        let listener = UnixListener::bind(&socket)
+            .with_context(|| format!("failed to bind to socket {}", socket.display()))?;
+        let slf = Self {
+            listener,
+            socket,
+            shm_dir: shm_dir.into(),
+            indexes: Mutex::new(HashMap::new()),
+            next_id: AtomicUsize::new(0),
+        };
+        Ok(slf)
+    }
+
+    /// Retrieve the path to the socket the server is bound to.
+    #[inline]
+    pub fn socket(&self) -> &Path {
+        &self.socket
+    }
+
+    /// Serve client requests, indefinitely.
+    ///
+    /// Connections are served by a fixed number of worker threads. If
+    /// all of them are busy, new connections are only accepted once one
+    /// becomes available. This method only returns if accepting a
+    /// connection failed.
+    pub fn run(&self) -> Result<()> {
+        let (sender, receiver) = sync_channel::<UnixStream>(0);
+        let receiver = Mutex::new(receiver);
+
+        thread::scope(|s| {
+            for _ in 0..MAX_WORKERS {
+                let _handle = s.spawn(|| self.work(&receiver));
+            }
+
+            let result = self.listener.incoming().try_for_each(|stream| {
+                let stream = stream.context("failed to accept client connection")?;
+                // Sending can only fail if all workers are gone, which
+                // they never are while we hold on to the sender.
+                let _result = sender.send(stream);
+                Ok(())
+            });
+            // Let the workers terminate.
+            let () = drop(sender);
+            result
+        })
+    }
+
+    /// Serve connections received via `receiver` until the sending side
+    /// is dropped.
+    fn work(&self, receiver: &Mutex<Receiver<UnixStream>>) {
+        loop {
+            let result = receiver
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .recv();
+            match result {
+                Ok(stream) => self.serve_client(stream),
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Accept a single client connection and serve its request.
+    pub fn serve_one(&self) -> Result<()> {
+        let (stream, _addr) = self
+            .listener
+            .accept()
+            .context("failed to accept client connection")?;
+        let () = self.serve_client(stream);
+        Ok(())
+    }
+
+    fn serve_client(&self, stream: UnixStream) {
+        if let Err(err) = self.serve(&stream) {
+            log::warn!("failed to serve daemon client: {err}");
+        }
+    }
+
+    fn serve(&self, stream: &UnixStream) -> Result<()> {
+        // Make sure that idle or stuck clients don't tie up a worker
+        // indefinitely.
+        let () = stream
+            .set_read_timeout(Some(CLIENT_TIMEOUT))
+            .context("failed to set read timeout")?;
+        let () = stream
+            .set_write_timeout(Some(CLIENT_TIMEOUT))
+            .context("failed to set write timeout")?;
+        // The file descriptor is attached to the first chunk of data. Any
+        // remainder of the request is read as usual.
+        let mut buf = [0; 4096];
+        let (count, file) = recv_with_fd(stream, &mut buf).context("failed to receive request")?;
+        let line = read_line(&mut BufReader::new(buf[..count].chain(stream)))?;
+        let result = Request::decode(&line).and_then(|request| {
+            let file = file.ok_or_invalid_input(|| {
+                format!(
+                    "request for {} is lacking a file descriptor",
+                    request.path().display()
+                )
+            })?;
+            let uid = peer_uid(stream)?;
+            self.index(&request, &file, uid)
+        });
+        let response = match result {
+            Ok(path) => Response::Ok(path),
+            Err(err) => Response::Err(format!("{err:#}")),
+        };
+        let () = (&*stream)
+            .write_all(&response.encode()?)
+            .context("failed to send response")?;
+        Ok(())
+    }
+
+    /// Retrieve the path to the index for the file referenced by
+    /// `request` and opened as `file` by the user `uid`, creating it if
+    /// necessary.
+    fn index(&self, request: &Request, file: &File, uid: libc::uid_t) -> Result<PathBuf> {
+        let path = request.path();
+        let meta = file
+            .metadata()
+            .with_context(|| format!("failed to query meta data of {}", path.display()))?;
+        // Anything but a regular file (e.g., a pipe) could stall us
+        // indefinitely.
+        if !meta.is_file() {
+            return Err(Error::with_unsupported(format!(
+                "{} is not a regular file",
+                path.display()
+            )))
+        }
+        let kind = match request {
+            Request::Elf(..) => Kind::Elf,
+            Request::Kallsyms(..) => Kind::Kallsyms,
+        };
+        let key = (kind, uid, FileVersion::from(&meta));
+        let pseudo = meta.len() == 0;
+
+        let is_valid = |index: &CachedIndex| !pseudo || index.created.elapsed() < PSEUDO_FILE_TTL;
+
+        if let Some(index) = self.indexes().get(&key).filter(|index| is_valid(index)) {
+            return Ok(index.path.clone())
+        }
+
+        // Indexing can take a while and we don't want to block requests
+        // for other files in the meantime. Hence, we do not hold the
+        // lock while creating the index, accepting that concurrent
+        // requests for the same file may cause redundant work.
+        let index = CachedIndex {
+            path: self.create_index(request, file, uid)?,
+            created: Instant::now(),
+        };
+
+        let mut indexes = self.indexes();
+        if let Some(other) = indexes.get(&key).filter(|index| is_valid(index)) {
+            // Another request created an index for the same file in the
+            // meantime. Stick with that one, as clients may already be
+            // using it.
+            let _result = fs::remove_file(&index.path);
+            return Ok(other.path.clone())
+        }
+
+        let index_path = index.path.clone();
+        if let Some(prev) = indexes.insert(key, index) {
+            // Clients that already mapped the previous index can keep
+            // using it.
+            let _result = fs::remove_file(prev.path);
+        }
+        Ok(index_path)
+    }
+
+    /// Lock the map of created indexes.
+    fn indexes(&self) -> MutexGuard<'_, HashMap<(Kind, libc::uid_t, FileVersion), CachedIndex>> {
+        self.indexes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn create_index(&self, request: &Request, file: &File, uid: libc::uid_t) -> Result<PathBuf> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        // The directory is likely world-writable. Use a name that can't
+        // be guessed upfront and never open an existing file (or follow
+        // a symbolic link), lest we clobber somebody else's data.
+        let path = self.shm_dir.join(format!(
+            "blazesym-{}-{id}-{:016x}.idx",
+            process::id(),
+            random()
+        ));
+        let tmp_path = path.with_extension("tmp");
+        let tmp_file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&tmp_path)
+            .with_context(|| format!("failed to create index file {}", tmp_path.display()))?;
+        let mut writer = BufWriter::new(tmp_file);
+
+        let result = match request {
+            Request::Elf(elf) => {
+                let mmap =
+                    Mmap::map(file).with_context(|| format!("failed to map {}", elf.display()))?;
+                let parser = ElfParser::from_mmap(mmap);
+                let opts = FindAddrOpts {
+                    offset_in_file: false,
+                    sym_type: SymType::Function,
+                };
+                let syms = parser.for_each_sym(&opts, Vec::new(), |mut syms, sym| {
+                    let () = syms.push((sym.addr, sym.size as u64, sym.name.to_string()));
+                    syms
+                })?;
+                let mut syms = syms
+                    .iter()
+                    .map(|(addr, size, name)| IndexSym {
+                        addr: *addr,
+                        size: *size,
+                        name,
+                    })
+                    .collect::<Vec<_>>();
+                write_index(&mut syms, true, &mut writer)
+            }
+            Request::Kallsyms(kallsyms) => {
+                let resolver = KSymResolver::load_from_reader(file, kallsyms.clone())?;
+                let mut syms = resolver
+                    .syms()
+                    .iter()
+                    .map(|ksym| IndexSym {
+                        addr: ksym.addr,
+                        size: 0,
+                        name: &ksym.name,
+                    })
+                    .collect::<Vec<_>>();
+                write_index(&mut syms, false, &mut writer)
+            }
+        };
+
+        // Clients, which may run as a different user, map the index by
+        // path. Hand it over to the requesting user, but nobody else.
+        let result = result
+            .and_then(|()| writer.flush().map_err(Into::into))
+            .and_then(|()| {
+                // SAFETY: `geteuid` is always safe to call.
+                if uid == unsafe { libc::geteuid() } {
+                    return Ok(())
+                }
+                let fd = writer.get_ref().as_raw_fd();
+                // SAFETY: `fd` is a valid file descriptor. A group ID
+                //         of -1 leaves the group unchanged.
+                let rc = unsafe { libc::fchown(fd, uid, libc::gid_t::MAX) };
+                if rc < 0 {
+                    return Err(Error::from(io::Error::last_os_error()))
+                        .with_context(|| format!("failed to transfer index to user {uid}"))
+                }
+                Ok(())
+            })
+            .and_then(|()| fs::rename(&tmp_path, &path).map_err(Into::into));
+        if let Err(err) = result {
+            let _result = fs::remove_file(&tmp_path);
+            return Err(err)
+                .with_context(|| format!("failed to create index for {}", request.path().display()))
+        }
+        Ok(path)
+    }
+}
+
+impl Debug for Server {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct(stringify!(Server))
+            .field("socket", &self.socket)
+            .field("shm_dir", &self.shm_dir)
+            .finish()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let indexes = self
+            .indexes
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        for (_key, index) in indexes.drain() {
+            let _result = fs::remove_file(index.path);
+        }
+        let _result = fs::remove_file(&self.socket);
+    }
+}
@@ -0,0 +1,452 @@
+//! A client for the debuginfod protocol, as implemented by servers
+//! serving debug information for distribution provided binaries.
+//!
+//! See debuginfod(8) for a description of the protocol and
+//! debuginfod-find(1) for the environment variables used for
+//! configuring the client.
+use std::env;
+use std::fmt::Write as _;
+use std::fs::create_dir_all;
+use std::fs::rename;
+use std::fs::File;
+use std::io;
+use std::io::Read as _;
+use std::io::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use reqwest::blocking::Client as HttpClient;
+use reqwest::StatusCode;
+
+use crate::log;
+use crate::normalize::buildid::BuildId;
+use crate::scope;
+use crate::Error;
+use crate::ErrorExt as _;
+use crate::ErrorKind;
+use crate::IntoError as _;
+use crate::Result;
+
+
+/// The environment variable containing the space separated URLs of
+/// the servers to query.
+const URLS_VAR: &str = "DEBUGINFOD_URLS";
+/// The environment variable containing the path to the cache
+/// directory.
+const CACHE_PATH_VAR: &str = "DEBUGINFOD_CACHE_PATH";
+/// The environment variable containing the timeout for connecting to
+/// a server and for receiving data from it, in seconds.
+const TIMEOUT_VAR: &str = "DEBUGINFOD_TIMEOUT";
+/// The default timeout.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(90);
+/// The size of the chunks in which downloads are written to disk.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+
+/// Format a build ID as hexadecimal string, as used in URLs and the
+/// cache directory.
+fn hex(build_id: &[u8]) -> String {
+    build_id
+        .iter()
+        .fold(String::with_capacity(build_id.len() * 2), |mut s, b| {
+            let _result = write!(s, "{b:02x}");
+            s
+        })
+}
+
+/// Check that the ELF file at `path` has the build ID `build_id`.
+fn check_build_id(path: &Path, build_id: &[u8]) -> Result<()> {
+    let actual = BuildId::from_elf_path(&path)?;
+    if actual.as_ref().map(|actual| actual.id.as_slice()) != Some(build_id) {
+        return Err(Error::with_build_id_mismatch(format!(
+            "downloaded file does not have build ID {}",
+            hex(build_id)
+        )))
+    }
+    Ok(())
+}
+
+/// Convert an error reported by the HTTP client into our error type.
+fn http_error(err: reqwest::Error) -> Error {
+    let kind = if err.is_timeout() {
+        io::ErrorKind::TimedOut
+    } else {
+        io::ErrorKind::Other
+    };
+    Error::from(io::Error::new(kind, err))
+}
+
+/// Retrieve the default cache directory, as used by the reference
+/// client implementation.
+fn default_cache_dir() -> Option<PathBuf> {
+    let cache = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    Some(cache.join("debuginfod_client"))
+}
+
+
+/// A debuginfod client, fetching debug information by build ID and
+/// caching it on disk.
+#[derive(Debug)]
+pub(crate) struct Client {
+    /// The base URLs of the servers to query, in order.
+    urls: Vec<String>,
+    /// The directory in which to cache downloaded files.
+    cache_dir: PathBuf,
+    /// The HTTP client used for all requests.
+    http: HttpClient,
+}
+
+impl Client {
+    /// Create a new client querying the servers at `urls`, caching
+    /// downloaded files in `cache_dir`.
+    pub fn new(urls: Vec<String>, cache_dir: PathBuf, timeout: Duration) -> Result<Self> {
+        let http = HttpClient::builder()
+            .connect_timeout(timeout)
+            // For the blocking client, this timeout applies to waiting
+            // for a response and to each read of its body individually,
+            // i.e., it is an idle timeout and does not limit the
+            // duration of a download as a whole.
+            .timeout(timeout)
+            .build()
+            .map_err(http_error)
+            .context("failed to create HTTP client")?;
+
+        let slf = Self {
+            urls,
+            cache_dir,
+            http,
+        };
+        Ok(slf)
+    }
+
+    /// Create a new client configured via the environment, in the same
+    /// way as the reference client implementation.
+    ///
+    /// `None` is returned if no servers are configured.
+    pub fn from_env() -> Result<Option<Self>> {
+        let urls = env::var(URLS_VAR)
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|url| url.trim_end_matches('/').to_string())
+            .collect::<Vec<_>>();
+        if urls.is_empty() {
+            return Ok(None)
+        }
+
+        let cache_dir = env::var_os(CACHE_PATH_VAR)
+            .map(PathBuf::from)
+            .or_else(default_cache_dir)
+            .ok_or_invalid_input(|| "failed to determine debuginfod cache directory")?;
+        let timeout = env::var(TIMEOUT_VAR)
+            .ok()
+            .and_then(|timeout| timeout.trim().parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TIMEOUT);
+
+        Self::new(urls, cache_dir, timeout).map(Some)
+    }
+
+    /// Download the ELF file with build ID `build_id` at `url` to
+    /// `dst`.
+    ///
+    /// Returns `false` if the server does not have the file.
+    fn download(&self, url: &str, build_id: &[u8], dst: &Path) -> Result<bool> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let () = scope::check()?;
+        let mut request = self.http.get(url);
+        if let Some(remaining) = scope::remaining() {
+            // A per-request timeout limits the request as a whole,
+            // including the download of the response body.
+            request = request.timeout(remaining);
+        }
+
+        let mut response = request
+            .send()
+            .map_err(http_error)
+            .with_context(|| format!("failed to request {url}"))?;
+        match response.status() {
+            StatusCode::OK => (),
+            StatusCode::NOT_FOUND => return Ok(false),
+            status => {
+                return Err(Error::from(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("request for {url} failed with status {status}"),
+                )))
+            }
+        }
+
+        // Download to a temporary file first and only move it into
+        // place once complete, so that concurrent users never see
+        // partial data.
+        let tmp = dst.with_extension(format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let result = File::create(&tmp)
+            .with_context(|| format!("failed to create {}", tmp.display()))
+            .and_then(|mut file| {
+                let mut buffer = vec![0; CHUNK_SIZE];
+                loop {
+                    // Downloads may take a while. Make sure to honor
+                    // the deadline in effect.
+                    let () = scope::check()?;
+                    let count = response
+                        .read(&mut buffer)
+                        .with_context(|| format!("failed to download {url}"))?;
+                    if count == 0 {
+                        break Ok(())
+                    }
+                    let () = file
+                        .write_all(&buffer[..count])
+                        .with_context(|| format!("failed to write {}", tmp.display()))?;
+                }
+            })
+            .and_then(|()| {
+                check_build_id(&tmp, build_id).with_context(|| format!("failed to verify {url}"))
+            })
+            .and_then(|()| {
+                rename(&tmp, dst)
+                    .with_context(|| format!("failed to move download to {}", dst.display()))
+            });
+        if result.is_err() {
+            let _result = std::fs::remove_file(&tmp);
+        }
+        let () = result?;
+        Ok(true)
+    }
+
+    /// Fetch the debug information for the binary with the provided
+    /// build ID, returning the path to the (cached) file containing
+    /// it.
+    ///
+    /// `None` is returned if none of the configured servers has debug
+    /// information for the build ID. An error of kind
+    /// [`ErrorKind::BuildIdMismatch`] is reported if servers only
+    /// provided files with a different build ID.
+    pub fn fetch_debuginfo(&self, build_id: &[u8]) -> Result<Option<PathBuf>> {
+        let hex_id = hex(build_id);
+        let dir = self.cache_dir.join(&hex_id);
+        let path = dir.join("debuginfo");
+        if path.is_file() {
+            return Ok(Some(path))
+        }
+
+        let () = create_dir_all(&dir)
+            .with_context(|| format!("failed to create directory {}", dir.display()))?;
+
+        let mut mismatch = None;
+        for url in &self.urls {
+            let url = format!("{url}/buildid/{hex_id}/debuginfo");
+            match self.download(&url, build_id, &path) {
+                Ok(true) => return Ok(Some(path)),
+                Ok(false) => (),
+                Err(err) if err.kind() == ErrorKind::BuildIdMismatch => {
+                    log::warn!("{err}");
+                    mismatch = Some(err);
+                }
+                Err(err) => {
+                    // There is no point in trying other servers once we
+                    // ran out of time (in which case the HTTP client may
+                    // report a rather generic error).
+                    let () = scope::check()?;
+                    log::warn!("{err}")
+                }
+            }
+        }
+
+        match mismatch {
+            Some(err) => Err(err),
+            None => Ok(None),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::read;
+    use std::io::BufRead as _;
+    use std::io::BufReader;
+    use std::net::TcpListener;
+    use std::thread;
+    use std::thread::sleep;
+    use std::time::Instant;
+
+    use tempfile::tempdir;
+
+
+    /// Read an ELF file with a build ID, returning its contents along
+    /// with the build ID.
+    fn elf_with_build_id() -> (&'static [u8], Vec<u8>) {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("libtest-so.so");
+        let build_id = BuildId::from_elf_path(&path).unwrap().unwrap();
+        let data = read(&path).unwrap();
+        (data.leak(), build_id.id)
+    }
+
+    /// Serve `count` requests on a local server, responding with
+    /// `content` for requests to `path` and with a 404 otherwise.
+    fn serve(count: usize, path: String, content: &'static [u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let _handle = thread::spawn(move || {
+            for stream in listener.incoming().take(count) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut request = String::new();
+                let _cnt = reader.read_line(&mut request).unwrap();
+                // Skip over the request headers.
+                loop {
+                    let mut line = String::new();
+                    let _cnt = reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break
+                    }
+                }
+
+                let requested = request.split_whitespace().nth(1).unwrap();
+                let (status, content) = if requested == path {
+                    ("200 OK", content)
+                } else {
+                    ("404 Not Found", b"".as_slice())
+                };
+                let header = format!(
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    content.len()
+                );
+                let () = stream.write_all(header.as_bytes()).unwrap();
+                let () = stream.write_all(content).unwrap();
+            }
+        });
+        url
+    }
+
+    /// Serve a single request on a local server, responding with the
+    /// provided chunks of data, sleeping for `delay` before each.
+    fn serve_slowly(chunks: &'static [&'static [u8]], delay: Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let _handle = thread::spawn(move || {
+            let (mut stream, _addr) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            loop {
+                let mut line = String::new();
+                let _cnt = reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break
+                }
+            }
+
+            let len = chunks.iter().map(|chunk| chunk.len()).sum::<usize>();
+            let header =
+                format!("HTTP/1.1 200 OK\r\nContent-Length: {len}\r\nConnection: close\r\n\r\n");
+            let () = stream.write_all(header.as_bytes()).unwrap();
+            for chunk in chunks {
+                let () = sleep(delay);
+                // The client may have given up already.
+                if stream.write_all(chunk).is_err() {
+                    break
+                }
+            }
+        });
+        url
+    }
+
+    /// Check that we format build IDs as expected.
+    #[test]
+    fn build_id_formatting() {
+        assert_eq!(hex(&[]), "");
+        assert_eq!(hex(&[0x01, 0xab, 0xff]), "01abff");
+    }
+
+    /// Check that we can fetch debug information from a server and
+    /// cache it.
+    #[test]
+    fn debuginfo_fetching() {
+        let (elf, build_id) = elf_with_build_id();
+        let url1 = serve(1, "/buildid/0102/debuginfo".to_string(), b"unused");
+        let url2 = serve(2, format!("/buildid/{}/debuginfo", hex(&build_id)), elf);
+        let dir = tempdir().unwrap();
+        let client = Client::new(
+            vec![url1, url2],
+            dir.path().to_path_buf(),
+            Duration::from_secs(5),
+        )
+        .unwrap();
+
+        // The first server does not have the file, the second one does.
+        let path = client.fetch_debuginfo(&build_id).unwrap().unwrap();
+        assert_eq!(path, dir.path().join(hex(&build_id)).join("debuginfo"));
+        assert_eq!(read(&path).unwrap(), elf);
+
+        // The file is served from the cache now.
+        let path = client.fetch_debuginfo(&build_id).unwrap().unwrap();
+        assert_eq!(read(path).unwrap(), elf);
+
+        // Neither server (well, the one left) has this one.
+        let result = client.fetch_debuginfo(&[0xef]).unwrap();
+        assert_eq!(result, None);
+    }
+
+    /// Check that we reject downloaded files not having the requested
+    /// build ID.
+    #[test]
+    fn build_id_mismatch() {
+        let (elf, _build_id) = elf_with_build_id();
+        let url = serve(1, "/buildid/abcd/debuginfo".to_string(), elf);
+        let dir = tempdir().unwrap();
+        let client =
+            Client::new(vec![url], dir.path().to_path_buf(), Duration::from_secs(5)).unwrap();
+
+        let err = client.fetch_debuginfo(&[0xab, 0xcd]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BuildIdMismatch);
+        assert!(!dir.path().join("abcd").join("debuginfo").exists());
+    }
+    /// Check that the timeout only applies to individual operations of
+    /// a download and not to the download as a whole.
+    #[test]
+    fn slow_download() {
+        let (elf, build_id) = elf_with_build_id();
+        let chunks = elf.chunks(elf.len() / 3 + 1).collect::<Vec<_>>();
+        let url = serve_slowly(chunks.leak(), Duration::from_millis(200));
+        let dir = tempdir().unwrap();
+        let client = Client::new(
+            vec![url],
+            dir.path().to_path_buf(),
+            Duration::from_millis(500),
+        )
+        .unwrap();
+
+        let path = client.fetch_debuginfo(&build_id).unwrap().unwrap();
+        assert_eq!(read(path).unwrap(), elf);
+    }
+
+    /// Check that downloads honor the deadline in effect.
+    #[test]
+    fn download_deadline() {
+        let url = serve_slowly(&[b"debug", b"info"], Duration::from_secs(10));
+        let dir = tempdir().unwrap();
+        let client =
+            Client::new(vec![url], dir.path().to_path_buf(), Duration::from_secs(60)).unwrap();
+
+        let start = Instant::now();
+        let deadline = start + Duration::from_millis(200);
+        let err = scope::with_deadline(deadline, || client.fetch_debuginfo(&[0x01])).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(!dir.path().join("01").join("debuginfo").exists());
+    }
+}
@@ -0,0 +1,168 @@
+//! Demangling of Ada symbol names as produced by GNAT.
+//!
+//! GNAT encodes fully qualified Ada names in lower case, with `__`
+//! separating the components. Various suffixes mark compiler
+//! generated entities and overloads, and operators are spelled out
+//! (e.g., `Oadd` for `"+"`).
+
+/// Names of packages from the GNAT runtime, which we use to recognize
+/// GNAT encoded names in the absence of language information.
+const RUNTIME_PREFIXES: [&str; 4] = ["ada__", "gnat__", "interfaces__", "system__"];
+
+/// The encodings of operator names.
+const OPERATORS: [(&str, &str); 19] = [
+    ("Oabs", "\"abs\""),
+    ("Oand", "\"and\""),
+    ("Omod", "\"mod\""),
+    ("Onot", "\"not\""),
+    ("Oor", "\"or\""),
+    ("Orem", "\"rem\""),
+    ("Oxor", "\"xor\""),
+    ("Oeq", "\"=\""),
+    ("One", "\"/=\""),
+    ("Olt", "\"<\""),
+    ("Ole", "\"<=\""),
+    ("Ogt", "\">\""),
+    ("Oge", "\">=\""),
+    ("Oadd", "\"+\""),
+    ("Osubtract", "\"-\""),
+    ("Oconcat", "\"&\""),
+    ("Omultiply", "\"*\""),
+    ("Odivide", "\"/\""),
+    ("Oexpon", "\"**\""),
+];
+
+
+/// Check whether `name` is likely a GNAT encoded name.
+///
+/// Because GNAT's encoding is hard to tell apart from plain C names, we
+/// only consider names of library level subprograms (prefixed with
+/// `_ada_`) and those of entities from the GNAT runtime.
+pub(crate) fn is_mangled(name: &str) -> bool {
+    name.starts_with("_ada_")
+        || RUNTIME_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+}
+
+/// Remove a trailing sequence of digits preceded by `sep` from `name`.
+fn strip_numeric_suffix<'name>(name: &'name str, sep: &str) -> &'name str {
+    let trimmed = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    if trimmed.len() != name.len() {
+        if let Some(stripped) = trimmed.strip_suffix(sep) {
+            if !stripped.is_empty() {
+                return stripped
+            }
+        }
+    }
+    name
+}
+
+/// Demangle a GNAT encoded Ada name.
+///
+/// `None` is returned if the name does not follow GNAT's encoding
+/// scheme.
+pub(crate) fn demangle(name: &str) -> Option<String> {
+    let mut name = name.strip_prefix("_ada_").unwrap_or(name);
+    if !name.starts_with(|c: char| c.is_ascii_lowercase()) {
+        return None
+    }
+
+    // Everything following `___` describes dispatching or other
+    // compiler internal details.
+    if let Some(idx) = name.find("___") {
+        name = &name[..idx];
+    }
+    // Suffixes of nested subprograms and library level elaboration.
+    name = strip_numeric_suffix(name, ".");
+    name = strip_numeric_suffix(name, "$");
+    // Task bodies.
+    name = name.strip_suffix("TKB").unwrap_or(name);
+    // Body-nested entities.
+    for suffix in ["Xbn", "Xb", "Xn", "X"] {
+        if let Some(stripped) = name.strip_suffix(suffix) {
+            name = stripped;
+            break
+        }
+    }
+    // Overloaded subprograms.
+    name = strip_numeric_suffix(name, "__");
+
+    let mut result = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("__") {
+            let () = result.push('.');
+            rest = after;
+        } else if c == 'O' {
+            let (encoded, op) = OPERATORS
+                .iter()
+                // Longer encodings go first so that, e.g., `Oxor` is not
+                // mistaken for `Oor`.
+                .filter(|(encoded, _)| rest.starts_with(encoded))
+                .max_by_key(|(encoded, _)| encoded.len())?;
+            let () = result.push_str(op);
+            rest = &rest[encoded.len()..];
+        } else if c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' {
+            let () = result.push(c);
+            rest = &rest[1..];
+        } else {
+            return None
+        }
+    }
+
+    if result.is_empty() || result.ends_with('.') {
+        return None
+    }
+    Some(result)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Check that we can demangle GNAT encoded names.
+    #[test]
+    fn demangling() {
+        let names = [
+            ("ada__calendar__clock", "ada.calendar.clock"),
+            ("_ada_main", "main"),
+            ("pkg__proc__2", "pkg.proc"),
+            ("pkg__proc.3", "pkg.proc"),
+            ("pkg__workerTKB", "pkg.worker"),
+            ("pkg__nestedX", "pkg.nested"),
+            ("pkg__typ___clean", "pkg.typ"),
+            ("pkg__Oadd", "pkg.\"+\""),
+            ("pkg__Oexpon__2", "pkg.\"**\""),
+            ("pkg__One", "pkg.\"/=\""),
+            (
+                "system__secondary_stack__ss_mark",
+                "system.secondary_stack.ss_mark",
+            ),
+        ];
+
+        for (name, expected) in names {
+            assert_eq!(demangle(name).as_deref(), Some(expected), "{name}");
+        }
+    }
+
+    /// Make sure that we reject names not following GNAT's encoding.
+    #[test]
+    fn demangling_failure() {
+        let names = ["", "Foo", "_ZN3foo3barEv", "pkg__Ofoo", "pkg__"];
+
+        for name in names {
+            assert_eq!(demangle(name), None, "{name}");
+        }
+    }
+
+    /// Check that we recognize GNAT encoded names.
+    #[test]
+    fn mangled_name_detection() {
+        assert!(is_mangled("ada__calendar__clock"));
+        assert!(is_mangled("_ada_main"));
+        assert!(!is_mangled("foo__bar"));
+    }
+}
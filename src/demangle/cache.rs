@@ -0,0 +1,201 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+
+use crate::symbolize::Alloc;
+use crate::symbolize::SrcLang;
+
+
+/// The default number of entries a [`Cache`] holds.
+const DEFAULT_CAPACITY: usize = 4096;
+
+
+#[derive(Debug, Default)]
+struct Entries {
+    /// Mapping from mangled name to the language it was demangled for
+    /// and the demangled result (`None` if the name could not be
+    /// demangled).
+    map: HashMap<Arc<str>, (SrcLang, Option<Arc<str>>)>,
+    /// Mangled names in order of insertion, used for eviction.
+    order: VecDeque<Arc<str>>,
+}
+
+
+/// A bounded cache of demangling results, keyed by mangled name.
+///
+/// Once the cache is full, the oldest entries are evicted first.
+#[derive(Debug)]
+pub(crate) struct Cache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+impl Cache {
+    /// Create a new, empty `Cache` holding at most `capacity` entries.
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// Lock the cache's entries.
+    fn entries(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Retrieve the demangled form of `name`, using `demangle` for
+    /// demangling it if no result is cached yet.
+    ///
+    /// Demangled names are allocated as per `alloc`.
+    pub(crate) fn get_or_demangle<'sym, F>(
+        &self,
+        name: Cow<'sym, str>,
+        language: SrcLang,
+        alloc: Alloc<'sym>,
+        demangle: F,
+    ) -> Cow<'sym, str>
+    where
+        F: FnOnce(&str) -> Option<String>,
+    {
+        if self.capacity == 0 {
+            return demangle(&name)
+                .map(|demangled| alloc.string(demangled))
+                .unwrap_or(name)
+        }
+
+        if let Some((lang, demangled)) = self.entries().map.get(name.as_ref()) {
+            if *lang == language {
+                return demangled
+                    .as_ref()
+                    .map(|demangled| alloc.str(demangled))
+                    .unwrap_or(name)
+            }
+        }
+
+        // Demangling happens without the lock held, so that concurrent
+        // users are not blocked on it.
+        let demangled = demangle(&name).map(Arc::<str>::from);
+        let mut entries = self.entries();
+        let key = Arc::<str>::from(name.as_ref());
+        if entries
+            .map
+            .insert(Arc::clone(&key), (language, demangled.clone()))
+            .is_none()
+        {
+            let () = entries.order.push_back(key);
+            if entries.order.len() > self.capacity {
+                if let Some(oldest) = entries.order.pop_front() {
+                    let _entry = entries.map.remove(&oldest);
+                }
+            }
+        }
+
+        demangled
+            .map(|demangled| alloc.str(&demangled))
+            .unwrap_or(name)
+    }
+
+    /// Retrieve the number of cached entries.
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.entries().map.len()
+    }
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+
+
+    /// Check that results are cached and that the cache stays bounded.
+    #[test]
+    fn caching() {
+        let cache = Cache::with_capacity(2);
+        let calls = Cell::new(0);
+        let demangle = |name: &str| {
+            let () = calls.set(calls.get() + 1);
+            (name != "raw").then(|| name.to_uppercase())
+        };
+
+        let name = cache.get_or_demangle(
+            Cow::Borrowed("foo"),
+            SrcLang::Unknown,
+            Alloc::Heap,
+            demangle,
+        );
+        assert_eq!(name, "FOO");
+        let name = cache.get_or_demangle(
+            Cow::Borrowed("foo"),
+            SrcLang::Unknown,
+            Alloc::Heap,
+            demangle,
+        );
+        assert_eq!(name, "FOO");
+        assert_eq!(calls.get(), 1);
+
+        // Failures are cached as well.
+        let name = cache.get_or_demangle(
+            Cow::Borrowed("raw"),
+            SrcLang::Unknown,
+            Alloc::Heap,
+            demangle,
+        );
+        assert!(matches!(name, Cow::Borrowed("raw")));
+        let name = cache.get_or_demangle(
+            Cow::Borrowed("raw"),
+            SrcLang::Unknown,
+            Alloc::Heap,
+            demangle,
+        );
+        assert!(matches!(name, Cow::Borrowed("raw")));
+        assert_eq!(calls.get(), 2);
+
+        // A different language requires re-demangling.
+        let name = cache.get_or_demangle(Cow::Borrowed("foo"), SrcLang::Cpp, Alloc::Heap, demangle);
+        assert_eq!(name, "FOO");
+        assert_eq!(calls.get(), 3);
+
+        let _name = cache.get_or_demangle(
+            Cow::Borrowed("bar"),
+            SrcLang::Unknown,
+            Alloc::Heap,
+            demangle,
+        );
+        assert_eq!(cache.len(), 2);
+        assert_eq!(calls.get(), 4);
+
+        // "foo" should have been evicted.
+        let _name =
+            cache.get_or_demangle(Cow::Borrowed("foo"), SrcLang::Cpp, Alloc::Heap, demangle);
+        assert_eq!(calls.get(), 5);
+    }
+
+    /// Make sure that a cache with zero capacity does not cache
+    /// anything.
+    #[test]
+    fn zero_capacity() {
+        let cache = Cache::with_capacity(0);
+        let name = cache.get_or_demangle(
+            Cow::Borrowed("foo"),
+            SrcLang::Unknown,
+            Alloc::Heap,
+            |name| Some(name.to_uppercase()),
+        );
+        assert_eq!(name, "FOO");
+        assert_eq!(cache.len(), 0);
+    }
+}
@@ -0,0 +1,578 @@
+//! Demangling support for D symbol names.
+//!
+//! The implementation follows the mangling rules laid out in the D
+//! ABI specification (<https://dlang.org/spec/abi.html#name_mangling>)
+//! and formats demangled names similar to GNU tools: function symbols
+//! are reported with their parameter types but without return type and
+//! attributes. Names using features that we do not support cause
+//! demangling to fail and callers are expected to fall back to the raw
+//! name.
+
+use crate::symbolize::DemangleOpts;
+
+/// The maximum nesting depth we support before bailing out.
+const MAX_DEPTH: usize = 64;
+
+
+/// Check whether the provided symbol name is (likely) a mangled D
+/// name.
+pub(crate) fn is_mangled(name: &str) -> bool {
+    name == "_Dmain"
+        || (name.starts_with("_D") && name[2..].starts_with(|c: char| c.is_ascii_digit()))
+}
+
+/// Demangle a D symbol name.
+///
+/// `None` is returned if the name is not a mangled D name or if it uses
+/// parts of the mangling scheme that we do not support.
+pub(crate) fn demangle(name: &str, opts: &DemangleOpts) -> Option<String> {
+    if name == "_Dmain" {
+        return Some("D main".to_string())
+    }
+
+    let mangled = name.strip_prefix("_D")?;
+    let mut demangler = Demangler {
+        text: mangled.as_bytes(),
+        opts,
+        // Back references are relative to the start of the full
+        // symbol, but that is irrelevant as long as we are consistent.
+        pos: 0,
+        depth: 0,
+    };
+    demangler.demangle()
+}
+
+
+/// Map a basic type character to the corresponding type name.
+fn basic_type(c: u8) -> Option<&'static str> {
+    let name = match c {
+        b'v' => "void",
+        b'g' => "byte",
+        b'h' => "ubyte",
+        b's' => "short",
+        b't' => "ushort",
+        b'i' => "int",
+        b'k' => "uint",
+        b'l' => "long",
+        b'm' => "ulong",
+        b'f' => "float",
+        b'd' => "double",
+        b'e' => "real",
+        b'o' => "ifloat",
+        b'p' => "idouble",
+        b'j' => "ireal",
+        b'q' => "cfloat",
+        b'r' => "cdouble",
+        b'c' => "creal",
+        b'a' => "char",
+        b'u' => "wchar",
+        b'w' => "dchar",
+        b'b' => "bool",
+        b'n' => "typeof(null)",
+        _ => return None,
+    };
+    Some(name)
+}
+
+
+/// A function's parameters, in textual form.
+struct Params {
+    params: Vec<String>,
+    variadic: Option<&'static str>,
+}
+
+impl Params {
+    fn format(&self) -> String {
+        let mut s = String::from("(");
+        let () = s.push_str(&self.params.join(", "));
+        if let Some(variadic) = self.variadic {
+            if !self.params.is_empty() {
+                let () = s.push_str(", ");
+            }
+            let () = s.push_str(variadic);
+        }
+        let () = s.push(')');
+        s
+    }
+}
+
+
+struct Demangler<'mangled> {
+    /// The mangled name, without the `_D` prefix.
+    text: &'mangled [u8],
+    /// Options controlling the output.
+    opts: &'mangled DemangleOpts,
+    /// The current parsing position inside `text`.
+    pos: usize,
+    /// The current nesting depth.
+    depth: usize,
+}
+
+impl Demangler<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.text.get(self.pos + offset).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn next_if(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn starts_with(&self, prefix: &[u8]) -> bool {
+        self.text
+            .get(self.pos..)
+            .map(|rest| rest.starts_with(prefix))
+            .unwrap_or(false)
+    }
+
+    /// Parse a decimal number.
+    fn number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        let digits = std::str::from_utf8(&self.text[start..self.pos]).ok()?;
+        digits.parse().ok()
+    }
+
+    /// Parse a back reference (the `Q` having been consumed already) and
+    /// return the position it refers to.
+    fn back_ref(&mut self) -> Option<usize> {
+        // The reference is relative to the position of the `Q`.
+        let start = self.pos - 1;
+        let mut offset = 0usize;
+        loop {
+            let c = self.next()?;
+            match c {
+                b'A'..=b'Z' => {
+                    offset = offset.checked_mul(26)?.checked_add(usize::from(c - b'A'))?;
+                }
+                b'a'..=b'z' => {
+                    offset = offset.checked_mul(26)?.checked_add(usize::from(c - b'a'))?;
+                    break
+                }
+                _ => return None,
+            }
+        }
+
+        if offset == 0 || offset > start {
+            return None
+        }
+        Some(start - offset)
+    }
+
+    /// Run `f` at the position a back reference refers to.
+    fn at_back_ref<T>(&mut self, f: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        let target = self.back_ref()?;
+        let pos = self.pos;
+        self.pos = target;
+        let result = self.nested(f);
+        self.pos = pos;
+        result
+    }
+
+    /// Run `f` while tracking the nesting depth.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        if self.depth >= MAX_DEPTH {
+            return None
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn lname(&mut self) -> Option<String> {
+        let len = self.number()?;
+        let end = self.pos.checked_add(len)?;
+        let name = self.text.get(self.pos..end)?;
+        let name = std::str::from_utf8(name).ok()?;
+        self.pos = end;
+        Some(name.to_string())
+    }
+
+    fn template_instance(&mut self) -> Option<String> {
+        if !self.starts_with(b"__T") {
+            return None
+        }
+        self.pos += 3;
+
+        let name = self.lname()?;
+        let mut args = Vec::new();
+        while !self.next_if(b'Z') {
+            let arg = match self.next()? {
+                b'T' => self.ty()?,
+                b'V' => {
+                    let _ty = self.ty()?;
+                    match self.next()? {
+                        b'i' => self.number()?.to_string(),
+                        b'N' => format!("-{}", self.number()?),
+                        _ => return None,
+                    }
+                }
+                b'S' => self.qualified_name()?,
+                _ => return None,
+            };
+            let () = args.push(arg);
+        }
+
+        if self.opts.template_args {
+            Some(format!("{name}!({})", args.join(", ")))
+        } else {
+            Some(name)
+        }
+    }
+
+    fn symbol_name(&mut self) -> Option<String> {
+        match self.peek()? {
+            b'Q' => {
+                self.pos += 1;
+                self.at_back_ref(Self::symbol_name)
+            }
+            b'_' => self.template_instance(),
+            b'0' => {
+                self.pos += 1;
+                Some("__anonymous".to_string())
+            }
+            b'1'..=b'9' => {
+                let pos = self.pos;
+                let len = self.number()?;
+                if self.starts_with(b"__T") {
+                    let start = self.pos;
+                    let name = self.template_instance()?;
+                    // The length prefix has to cover the full template
+                    // instance.
+                    if self.pos - start != len {
+                        return None
+                    }
+                    Some(name)
+                } else {
+                    self.pos = pos;
+                    self.lname()
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn is_call_convention(c: u8) -> bool {
+        matches!(c, b'F' | b'U' | b'W' | b'V' | b'R' | b'Y')
+    }
+
+    fn func_attrs(&mut self) {
+        while self.peek() == Some(b'N')
+            && matches!(self.peek_at(1), Some(b'a'..=b'f' | b'i'..=b'm'))
+        {
+            self.pos += 2;
+        }
+    }
+
+    fn param(&mut self) -> Option<String> {
+        let mut storage = String::new();
+        loop {
+            let class = match self.peek()? {
+                b'I' => "in ",
+                b'J' => "out ",
+                b'K' => "ref ",
+                b'L' => "lazy ",
+                b'M' => "scope ",
+                b'N' if self.peek_at(1) == Some(b'k') => {
+                    self.pos += 1;
+                    "return "
+                }
+                _ => break,
+            };
+            self.pos += 1;
+            let () = storage.push_str(class);
+        }
+
+        let ty = self.ty()?;
+        Some(format!("{storage}{ty}"))
+    }
+
+    /// Parse a function type without its return type.
+    fn func_type_no_return(&mut self) -> Option<Params> {
+        let _conv = self.next().filter(|c| Self::is_call_convention(*c))?;
+        let () = self.func_attrs();
+
+        let mut params = Vec::new();
+        let variadic = loop {
+            match self.peek()? {
+                b'X' => {
+                    self.pos += 1;
+                    break Some("...")
+                }
+                b'Y' => {
+                    self.pos += 1;
+                    break Some("...")
+                }
+                b'Z' => {
+                    self.pos += 1;
+                    break None
+                }
+                _ => {
+                    let () = params.push(self.param()?);
+                }
+            }
+        };
+
+        Some(Params { params, variadic })
+    }
+
+    /// Parse a full function type, including the return type.
+    fn func_type(&mut self) -> Option<(Params, String)> {
+        let params = self.func_type_no_return()?;
+        let ret = self.ty()?;
+        Some((params, ret))
+    }
+
+    fn ty(&mut self) -> Option<String> {
+        self.nested(Self::ty_impl)
+    }
+
+    fn ty_impl(&mut self) -> Option<String> {
+        let c = self.peek()?;
+        if let Some(name) = basic_type(c) {
+            self.pos += 1;
+            return Some(name.to_string())
+        }
+
+        let ty = match c {
+            b'A' => {
+                self.pos += 1;
+                format!("{}[]", self.ty()?)
+            }
+            b'G' => {
+                self.pos += 1;
+                let len = self.number()?;
+                format!("{}[{len}]", self.ty()?)
+            }
+            b'H' => {
+                self.pos += 1;
+                let key = self.ty()?;
+                let value = self.ty()?;
+                format!("{value}[{key}]")
+            }
+            b'P' => {
+                self.pos += 1;
+                if self.peek().map(Self::is_call_convention) == Some(true) {
+                    let (params, ret) = self.func_type()?;
+                    format!("{ret} function{}", params.format())
+                } else {
+                    format!("{}*", self.ty()?)
+                }
+            }
+            b'D' => {
+                self.pos += 1;
+                let (params, ret) = self.func_type()?;
+                format!("{ret} delegate{}", params.format())
+            }
+            b'x' => {
+                self.pos += 1;
+                format!("const({})", self.ty()?)
+            }
+            b'y' => {
+                self.pos += 1;
+                format!("immutable({})", self.ty()?)
+            }
+            b'O' => {
+                self.pos += 1;
+                format!("shared({})", self.ty()?)
+            }
+            b'N' if self.peek_at(1) == Some(b'g') => {
+                self.pos += 2;
+                format!("inout({})", self.ty()?)
+            }
+            b'N' if self.peek_at(1) == Some(b'n') => {
+                self.pos += 2;
+                "noreturn".to_string()
+            }
+            b'C' | b'S' | b'E' | b'T' | b'I' => {
+                self.pos += 1;
+                self.qualified_name()?
+            }
+            b'Q' => {
+                self.pos += 1;
+                self.at_back_ref(Self::ty)?
+            }
+            c if Self::is_call_convention(c) => {
+                let (params, ret) = self.func_type()?;
+                format!("{ret}{}", params.format())
+            }
+            _ => return None,
+        };
+        Some(ty)
+    }
+
+    /// Check whether the input at the current position starts a symbol
+    /// name.
+    fn at_symbol_name(&mut self) -> bool {
+        match self.peek() {
+            Some(b'0'..=b'9') => true,
+            Some(b'Q') => {
+                // A back reference may refer to a type as well, which
+                // would terminate the name.
+                let pos = self.pos;
+                self.pos += 1;
+                let target = self.back_ref();
+                self.pos = pos;
+                matches!(
+                    target.and_then(|target| self.text.get(target)),
+                    Some(b'0'..=b'9')
+                )
+            }
+            Some(b'_') => self.starts_with(b"__T"),
+            _ => false,
+        }
+    }
+
+    fn qualified_name(&mut self) -> Option<String> {
+        let mut names = Vec::new();
+        loop {
+            let mut name = self.symbol_name()?;
+
+            // Nested functions have their parent function's parameters
+            // mangled as part of the qualified name.
+            let pos = self.pos;
+            let _this = self.next_if(b'M');
+            if self.peek().map(Self::is_call_convention) == Some(true) {
+                match self.func_type_no_return() {
+                    Some(params) if self.at_symbol_name() => {
+                        if self.opts.params {
+                            let () = name.push_str(&params.format());
+                        }
+                    }
+                    _ => self.pos = pos,
+                }
+            } else {
+                self.pos = pos;
+            }
+
+            let () = names.push(name);
+            if !self.at_symbol_name() {
+                break
+            }
+        }
+        Some(names.join("."))
+    }
+
+    fn demangle(&mut self) -> Option<String> {
+        let mut name = self.qualified_name()?;
+
+        if self.pos < self.text.len() {
+            // Member functions are marked with `M` and may carry
+            // modifiers of the `this` reference.
+            let mut this = String::new();
+            if self.next_if(b'M') {
+                loop {
+                    match self.peek()? {
+                        b'x' => this.push_str(" const"),
+                        b'y' => this.push_str(" immutable"),
+                        b'O' => this.push_str(" shared"),
+                        b'N' if self.peek_at(1) == Some(b'g') => {
+                            let () = this.push_str(" inout");
+                            self.pos += 1;
+                        }
+                        _ => break,
+                    }
+                    self.pos += 1;
+                }
+            }
+
+            if self.peek().map(Self::is_call_convention) == Some(true) {
+                let (params, _ret) = self.func_type()?;
+                if self.opts.params {
+                    let () = name.push_str(&params.format());
+                    let () = name.push_str(&this);
+                }
+            } else {
+                // A variable, the type of which we do not report.
+                let _ty = self.ty()?;
+            }
+        }
+
+        if self.pos != self.text.len() {
+            return None
+        }
+        Some(name)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Check that we can demangle a set of D symbols.
+    #[test]
+    fn demangling() {
+        let symbols = [
+            ("_Dmain", "D main"),
+            ("_D4test3fooFZv", "test.foo()"),
+            ("_D4test3fooFiZv", "test.foo(int)"),
+            (
+                "_D3std5stdio7writelnFAyaZv",
+                "std.stdio.writeln(immutable(char)[])",
+            ),
+            ("_D4test3Foo3barMxFNaNbNiNfZi", "test.Foo.bar() const"),
+            (
+                "_D4test3fooFKiPxaXv",
+                "test.foo(ref int, const(char)*, ...)",
+            ),
+            ("_D4test3fooFZ3barFiZv", "test.foo().bar(int)"),
+            ("_D4test1xi", "test.x"),
+            (
+                "_D4test__T3addTiVii3Z3addFiZi",
+                "test.add!(int, 3).add(int)",
+            ),
+            ("_D4test3fooFC4test3FooQkZv", "test.foo(test.Foo, test.Foo)"),
+            ("_D4test3fooFPFiZvZv", "test.foo(void function(int))"),
+        ];
+
+        for (mangled, expected) in symbols {
+            assert!(is_mangled(mangled), "{mangled}");
+            assert_eq!(
+                demangle(mangled, &DemangleOpts::default()).as_deref(),
+                Some(expected),
+                "{mangled}"
+            );
+        }
+    }
+
+    /// Make sure that we fail gracefully on unsupported or invalid
+    /// input.
+    #[test]
+    fn demangling_failure() {
+        let symbols = [
+            "_DYNAMIC",
+            "_D",
+            "_D4tes",
+            "_D4test3fooF",
+            "_D4test3fooFiZvv",
+            "_D4test3fooFQzZv",
+        ];
+
+        for mangled in symbols {
+            assert_eq!(
+                demangle(mangled, &DemangleOpts::default()),
+                None,
+                "{mangled}"
+            );
+        }
+    }
+}
@@ -0,0 +1,342 @@
+// The Ada, D, and Swift modules are compiled in unconditionally,
+// because we rely on their mangling detection for inferring the source
+// language of symbols, irrespective of demangling support.
+#[cfg_attr(not(feature = "demangle-ada"), allow(dead_code))]
+mod ada;
+mod cache;
+#[cfg_attr(not(feature = "demangle-d"), allow(dead_code))]
+mod d;
+#[cfg_attr(not(feature = "demangle-swift"), allow(dead_code))]
+mod swift;
+
+use crate::symbolize::DemangleOpts;
+use crate::symbolize::SrcLang;
+
+pub(crate) use self::ada::is_mangled as is_ada_mangled;
+pub(crate) use self::cache::Cache;
+pub(crate) use self::d::is_mangled as is_d_mangled;
+pub(crate) use self::swift::is_mangled as is_swift_mangled;
+
+
+/// Whether support for demangling any language is compiled in.
+pub(crate) const ENABLED: bool = cfg!(any(
+    feature = "demangle-ada",
+    feature = "demangle-cpp",
+    feature = "demangle-d",
+    feature = "demangle-rust",
+    feature = "demangle-swift",
+));
+
+/// The marker used to indicate that a name got truncated.
+const ELLIPSIS: &str = "...";
+
+
+/// Remove template (or generic) arguments from a demangled name.
+///
+/// Angle brackets that do not follow an identifier (such as those
+/// surrounding qualified paths in Rust, e.g., `<T as Trait>::fn`) and
+/// those that are part of operator names are preserved.
+fn strip_template_args(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut depth = 0usize;
+    let mut prev = None;
+    let mut rest = name;
+
+    let is_ident = |c: Option<char>| c.map(|c| c.is_alphanumeric() || c == '_') == Some(true);
+
+    while let Some(c) = rest.chars().next() {
+        if depth == 0 && !is_ident(prev) {
+            if let Some(after) = rest.strip_prefix("operator") {
+                let op_len = after.len() - after.trim_start_matches(['<', '>', '=', '-']).len();
+                let len = "operator".len() + op_len;
+                let () = result.push_str(&rest[..len]);
+                rest = &rest[len..];
+                // C++ separates template arguments of operators from
+                // the operator by a space.
+                if op_len > 0 && rest.starts_with(" <") {
+                    rest = &rest[1..];
+                    prev = Some('r');
+                } else {
+                    prev = result.chars().last();
+                }
+                continue
+            }
+        }
+
+        match c {
+            '<' if depth > 0 || is_ident(prev) => depth += 1,
+            '>' if depth > 0 && prev != Some('-') => depth -= 1,
+            _ if depth == 0 => result.push(c),
+            _ => (),
+        }
+        prev = Some(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    result
+}
+
+/// Shorten a name to the provided maximum number of characters,
+/// preserving its tail, which usually is the most specific part.
+fn truncate(name: &str, max_len: usize) -> String {
+    let len = name.chars().count();
+    if len <= max_len {
+        return name.to_string()
+    }
+
+    let keep = max_len.saturating_sub(ELLIPSIS.len());
+    let tail = name.chars().skip(len - keep).collect::<String>();
+    let mut result = ELLIPSIS.chars().take(max_len).collect::<String>();
+    let () = result.push_str(&tail);
+    result
+}
+
+
+#[cfg(feature = "demangle-rust")]
+fn demangle_rust(name: &str, opts: &DemangleOpts) -> Option<String> {
+    rustc_demangle::try_demangle(name).ok().map(|name| {
+        if opts.hash {
+            format!("{name}")
+        } else {
+            format!("{name:#}")
+        }
+    })
+}
+
+/// Demangle a C++ symbol name, supporting both the Itanium and the MSVC
+/// mangling schemes.
+#[cfg(feature = "demangle-cpp")]
+fn demangle_cpp(name: &str, opts: &DemangleOpts) -> Option<String> {
+    if name.starts_with('?') {
+        let mut flags = msvc_demangler::DemangleFlags::llvm();
+        if !opts.params {
+            flags |= msvc_demangler::DemangleFlags::NAME_ONLY;
+        }
+        if !opts.return_type {
+            flags |= msvc_demangler::DemangleFlags::NO_FUNCTION_RETURNS;
+        }
+        msvc_demangler::demangle(name, flags).ok()
+    } else {
+        let mut options = cpp_demangle::DemangleOptions::new();
+        if !opts.params {
+            options = options.no_params();
+        }
+        if !opts.return_type {
+            options = options.no_return_type();
+        }
+        cpp_demangle::Symbol::new(name)
+            .ok()
+            .and_then(|sym| sym.demangle(&options).ok())
+    }
+}
+
+/// Demangle a name of unknown language, trying the Rust and then the
+/// C++ mangling schemes.
+#[cfg_attr(
+    not(any(feature = "demangle-cpp", feature = "demangle-rust")),
+    allow(unused_variables)
+)]
+fn demangle_unknown(name: &str, opts: &DemangleOpts) -> Option<String> {
+    #[cfg(feature = "demangle-rust")]
+    if let Some(demangled) = demangle_rust(name, opts) {
+        return Some(demangled)
+    }
+    #[cfg(feature = "demangle-cpp")]
+    if let Some(demangled) = demangle_cpp(name, opts) {
+        return Some(demangled)
+    }
+    None
+}
+
+fn demangle_impl(name: &str, language: SrcLang, opts: &DemangleOpts) -> Option<String> {
+    let demangled = match language {
+        #[cfg(feature = "demangle-ada")]
+        SrcLang::Ada => ada::demangle(name),
+        #[cfg(feature = "demangle-rust")]
+        SrcLang::Rust => demangle_rust(name, opts),
+        #[cfg(feature = "demangle-cpp")]
+        SrcLang::Cpp => demangle_cpp(name, opts),
+        #[cfg(feature = "demangle-d")]
+        SrcLang::D => d::demangle(name, opts),
+        #[cfg(feature = "demangle-swift")]
+        SrcLang::Swift => swift::demangle(name, opts),
+        #[cfg(feature = "demangle-swift")]
+        SrcLang::Unknown if swift::is_mangled(name) => swift::demangle(name, opts),
+        #[cfg(feature = "demangle-d")]
+        SrcLang::Unknown if d::is_mangled(name) => d::demangle(name, opts),
+        #[cfg(feature = "demangle-ada")]
+        SrcLang::Unknown if ada::is_mangled(name) => ada::demangle(name),
+        SrcLang::Unknown => demangle_unknown(name, opts),
+        // Support for demangling the language is not compiled in.
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }?;
+
+    // D template arguments are taken care of by the demangler itself,
+    // as they are not enclosed in angle brackets. Ada names do not
+    // contain any.
+    if !opts.template_args && !matches!(language, SrcLang::Ada | SrcLang::D) {
+        Some(strip_template_args(&demangled))
+    } else {
+        Some(demangled)
+    }
+}
+
+/// Demangle a symbol name using the demangling scheme for the given
+/// language, honoring the provided options.
+///
+/// `None` is returned if the name could not be demangled.
+pub(crate) fn demangle(name: &str, language: SrcLang, opts: &DemangleOpts) -> Option<String> {
+    let demangled = demangle_impl(name, language, opts)?;
+
+    let max_len = if let Some(max_len) = opts.max_len {
+        max_len
+    } else {
+        return Some(demangled)
+    };
+
+    if demangled.chars().count() <= max_len {
+        return Some(demangled)
+    }
+
+    // If the name is too long we first try removing template arguments
+    // and then parameter and return type information, before cutting
+    // it down.
+    let mut opts = opts.clone();
+    opts.template_args = false;
+    let demangled = demangle_impl(name, language, &opts)?;
+    if demangled.chars().count() <= max_len {
+        return Some(demangled)
+    }
+
+    opts.params = false;
+    opts.return_type = false;
+    let demangled = demangle_impl(name, language, &opts)?;
+    Some(truncate(&demangled, max_len))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Check that template arguments are removed as expected.
+    #[test]
+    fn template_arg_stripping() {
+        let names = [
+            ("foo", "foo"),
+            ("std::vector<int, std::allocator<int> >::size() const", "std::vector::size() const"),
+            (
+                "std::basic_ostream<char, std::char_traits<char> >& std::operator<< <std::char_traits<char> >(std::basic_ostream<char, std::char_traits<char> >&, char const*)",
+                "std::basic_ostream& std::operator<<(std::basic_ostream&, char const*)",
+            ),
+            ("foo::operator<(foo const&)", "foo::operator<(foo const&)"),
+            ("foo::operator->()", "foo::operator->()"),
+            ("<alloc::vec::Vec<u8> as core::ops::drop::Drop>::drop", "<alloc::vec::Vec as core::ops::drop::Drop>::drop"),
+            ("main.foo(Swift.Set<Swift.Int>) -> ()", "main.foo(Swift.Set) -> ()"),
+        ];
+
+        for (name, expected) in names {
+            assert_eq!(strip_template_args(name), expected);
+        }
+    }
+
+    /// Make sure that we can truncate names.
+    #[test]
+    fn name_truncation() {
+        assert_eq!(truncate("foo::bar", 8), "foo::bar");
+        assert_eq!(truncate("foo::bar", 7), "...:bar");
+        assert_eq!(truncate("foo::bar", 2), "..");
+        assert_eq!(truncate("foo::bar", 0), "");
+    }
+
+    /// Check that demangling options are honored.
+    #[cfg(feature = "demangle")]
+    #[test]
+    fn demangling_with_options() {
+        let name = "_ZNSt6vectorIiSaIiEE9push_backERKi";
+        let default = DemangleOpts::default();
+        let demangled = demangle(name, SrcLang::Cpp, &default).unwrap();
+        assert_eq!(
+            demangled,
+            "std::vector<int, std::allocator<int> >::push_back(int const&)"
+        );
+
+        let opts = DemangleOpts {
+            template_args: false,
+            ..Default::default()
+        };
+        let demangled = demangle(name, SrcLang::Cpp, &opts).unwrap();
+        assert_eq!(demangled, "std::vector::push_back(int const&)");
+
+        let opts = DemangleOpts {
+            params: false,
+            ..Default::default()
+        };
+        let demangled = demangle(name, SrcLang::Cpp, &opts).unwrap();
+        assert_eq!(
+            demangled,
+            "std::vector<int, std::allocator<int> >::push_back"
+        );
+
+        let name = "_Z3fooIiET_S0_";
+        let opts = DemangleOpts {
+            return_type: false,
+            ..Default::default()
+        };
+        let demangled = demangle(name, SrcLang::Cpp, &default).unwrap();
+        assert_eq!(demangled, "int foo<int>(int)");
+        let demangled = demangle(name, SrcLang::Cpp, &opts).unwrap();
+        assert_eq!(demangled, "foo<int>(int)");
+
+        let name = "$s4main3add1a1bS2i_SitF";
+        let demangled = demangle(name, SrcLang::Swift, &opts).unwrap();
+        assert_eq!(demangled, "main.add(a: Swift.Int, b: Swift.Int)");
+
+        let name = "_D4test__T3addTiVii3Z3addFiZi";
+        let opts = DemangleOpts {
+            template_args: false,
+            params: false,
+            ..Default::default()
+        };
+        let demangled = demangle(name, SrcLang::D, &opts).unwrap();
+        assert_eq!(demangled, "test.add.add");
+
+        let name = "_ZN4core3fmt5write17h0123456789abcdefE";
+        let demangled = demangle(name, SrcLang::Rust, &default).unwrap();
+        assert_eq!(demangled, "core::fmt::write");
+        let opts = DemangleOpts {
+            hash: true,
+            ..Default::default()
+        };
+        let demangled = demangle(name, SrcLang::Rust, &opts).unwrap();
+        assert_eq!(demangled, "core::fmt::write::h0123456789abcdef");
+    }
+
+    /// Check that overly long names are shortened as expected.
+    #[cfg(feature = "demangle")]
+    #[test]
+    fn demangling_with_max_len() {
+        let name = "_ZNSt6vectorIiSaIiEE9push_backERKi";
+        let opts = DemangleOpts {
+            max_len: Some(40),
+            ..Default::default()
+        };
+        let demangled = demangle(name, SrcLang::Cpp, &opts).unwrap();
+        assert_eq!(demangled, "std::vector::push_back(int const&)");
+
+        let opts = DemangleOpts {
+            max_len: Some(22),
+            ..Default::default()
+        };
+        let demangled = demangle(name, SrcLang::Cpp, &opts).unwrap();
+        assert_eq!(demangled, "std::vector::push_back");
+
+        let opts = DemangleOpts {
+            max_len: Some(12),
+            ..Default::default()
+        };
+        let demangled = demangle(name, SrcLang::Cpp, &opts).unwrap();
+        assert_eq!(demangled, "...push_back");
+    }
+}
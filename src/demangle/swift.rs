@@ -0,0 +1,736 @@
+//! Demangling support for Swift symbol names.
+//!
+//! Swift uses a post-fix mangling scheme: the demangler maintains a
+//! stack of nodes and each "operator" character pops its operands from
+//! and pushes its result to this stack. We only support the subset of
+//! the scheme that is commonly found in stack traces of Swift
+//! programs: plain functions, initializers & deinitializers, as well as
+//! variable accessors, defined on nominal types (classes, structs, and
+//! enums) using standard library types. For anything else we bail out
+//! and callers are expected to fall back to the raw name.
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use crate::symbolize::DemangleOpts;
+
+
+/// The prefixes Swift 5 symbols start with (with and without the
+/// leading underscore added on Darwin systems).
+const PREFIXES: [&str; 4] = ["_$s", "$s", "_$S", "$S"];
+
+/// The maximum number of words that can be referenced by word
+/// substitutions.
+const MAX_WORDS: usize = 26;
+
+/// The maximum number of times a substitution may be repeated. Counts
+/// are encoded in the (untrusted) symbol name and anything larger is
+/// rejected. This is the same limit that the Swift runtime's demangler
+/// uses.
+const MAX_REPEAT_COUNT: usize = 2048;
+
+
+/// Argument labels of a function, if any. `None` elements represent
+/// unlabeled arguments.
+type Labels = Option<Vec<Option<String>>>;
+
+
+/// Check whether the provided symbol name is (likely) a mangled Swift
+/// name.
+pub(crate) fn is_mangled(name: &str) -> bool {
+    PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+/// Demangle a Swift symbol name.
+///
+/// `None` is returned if the name is not a mangled Swift name or if it
+/// uses parts of the mangling scheme that we do not support.
+pub(crate) fn demangle(name: &str, opts: &DemangleOpts) -> Option<String> {
+    let mangled = PREFIXES
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))?;
+
+    let mut demangler = Demangler::new(mangled, opts);
+    demangler.demangle()
+}
+
+
+/// A Swift type.
+#[derive(Clone, Debug)]
+enum Ty {
+    /// A (fully qualified) nominal type.
+    Named(String),
+    /// A tuple with optionally labeled elements.
+    Tuple(Vec<(Option<String>, Ty)>),
+    /// A function type.
+    Func { params: Box<Ty>, ret: Box<Ty> },
+    /// A nominal type with generic arguments applied.
+    Generic { name: String, args: Vec<Ty> },
+}
+
+impl Ty {
+    /// Retrieve the number of parameters this type represents when
+    /// used as a function's parameter type.
+    fn param_count(&self) -> usize {
+        match self {
+            Ty::Tuple(elems) => elems.len(),
+            _ => 1,
+        }
+    }
+}
+
+impl Display for Ty {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Ty::Named(name) => f.write_str(name),
+            Ty::Tuple(elems) => {
+                f.write_str("(")?;
+                for (i, (label, ty)) in elems.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    if let Some(label) = label {
+                        write!(f, "{label}: ")?;
+                    }
+                    write!(f, "{ty}")?;
+                }
+                f.write_str(")")
+            }
+            Ty::Func { params, ret } => match params.as_ref() {
+                params @ Ty::Tuple(..) => write!(f, "{params} -> {ret}"),
+                params => write!(f, "({params}) -> {ret}"),
+            },
+            Ty::Generic { name, args } => match (name.as_str(), args.as_slice()) {
+                ("Swift.Array", [elem]) => write!(f, "[{elem}]"),
+                ("Swift.Optional", [elem]) => write!(f, "{elem}?"),
+                ("Swift.Dictionary", [key, value]) => write!(f, "[{key} : {value}]"),
+                _ => {
+                    write!(f, "{name}<")?;
+                    for (i, arg) in args.iter().enumerate() {
+                        if i > 0 {
+                            f.write_str(", ")?;
+                        }
+                        write!(f, "{arg}")?;
+                    }
+                    f.write_str(">")
+                }
+            },
+        }
+    }
+}
+
+
+/// A node on the demangler's stack.
+#[derive(Clone, Debug)]
+enum Node {
+    /// An identifier.
+    Ident(String),
+    /// A module name.
+    Module(String),
+    /// An empty list marker.
+    EmptyList,
+    /// A marker for the first element of a list.
+    FirstElem,
+    /// A type.
+    Ty(Ty),
+    /// A fully demangled entity, such as a function.
+    Entity(String),
+}
+
+
+/// Format a function signature, taking into account argument labels.
+fn format_signature(
+    params: &Ty,
+    ret: &Ty,
+    labels: Option<&[Option<String>]>,
+    opts: &DemangleOpts,
+) -> String {
+    if !opts.params {
+        return String::new()
+    }
+
+    let params = match (params, labels) {
+        (Ty::Tuple(elems), Some(labels)) => {
+            let elems = elems
+                .iter()
+                .zip(labels)
+                .map(|((_, ty), label)| {
+                    (
+                        Some(label.clone().unwrap_or_else(|| "_".into())),
+                        ty.clone(),
+                    )
+                })
+                .collect();
+            Ty::Tuple(elems)
+        }
+        (Ty::Tuple(..), None) => params.clone(),
+        (params, Some([label])) => Ty::Tuple(vec![(
+            Some(label.clone().unwrap_or_else(|| "_".into())),
+            params.clone(),
+        )]),
+        (params, _) => Ty::Tuple(vec![(None, params.clone())]),
+    };
+
+    if opts.return_type {
+        format!("{params} -> {ret}")
+    } else {
+        params.to_string()
+    }
+}
+
+
+/// Map a standard substitution character to the corresponding type.
+fn standard_type(c: u8) -> Option<&'static str> {
+    let name = match c {
+        b'a' => "Array",
+        b'b' => "Bool",
+        b'D' => "Dictionary",
+        b'd' => "Double",
+        b'f' => "Float",
+        b'h' => "Set",
+        b'i' => "Int",
+        b'J' => "Character",
+        b'N' => "ClosedRange",
+        b'n' => "Range",
+        b'O' => "ObjectIdentifier",
+        b'P' => "UnsafePointer",
+        b'p' => "UnsafeMutablePointer",
+        b'q' => "Optional",
+        b'R' => "UnsafeBufferPointer",
+        b'r' => "UnsafeMutableBufferPointer",
+        b'S' => "String",
+        b's' => "Substring",
+        b'u' => "UInt",
+        b'V' => "UnsafeRawPointer",
+        b'v' => "UnsafeMutableRawPointer",
+        b'W' => "UnsafeRawBufferPointer",
+        b'w' => "UnsafeMutableRawBufferPointer",
+        _ => return None,
+    };
+    Some(name)
+}
+
+
+struct Demangler<'mangled> {
+    /// The mangled name, without prefix.
+    text: &'mangled [u8],
+    /// Options controlling the output.
+    opts: &'mangled DemangleOpts,
+    /// The current parsing position inside `text`.
+    pos: usize,
+    /// The node stack.
+    stack: Vec<Node>,
+    /// Nodes that can be referenced by substitutions.
+    substs: Vec<Node>,
+    /// Words that can be referenced by word substitutions inside of
+    /// identifiers.
+    words: Vec<String>,
+}
+
+impl<'mangled> Demangler<'mangled> {
+    fn new(mangled: &'mangled str, opts: &'mangled DemangleOpts) -> Self {
+        Self {
+            text: mangled.as_bytes(),
+            opts,
+            pos: 0,
+            stack: Vec::new(),
+            substs: Vec::new(),
+            words: Vec::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn next_if(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Parse a decimal number.
+    fn natural(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        let digits = std::str::from_utf8(&self.text[start..self.pos]).ok()?;
+        digits.parse().ok()
+    }
+
+    fn push(&mut self, node: Node) {
+        self.stack.push(node)
+    }
+
+    fn pop_if(&mut self, pred: impl FnOnce(&Node) -> bool) -> Option<Node> {
+        if pred(self.stack.last()?) {
+            self.stack.pop()
+        } else {
+            None
+        }
+    }
+
+    fn pop_ident(&mut self) -> Option<String> {
+        match self.pop_if(|node| matches!(node, Node::Ident(..)))? {
+            Node::Ident(ident) => Some(ident),
+            _ => unreachable!(),
+        }
+    }
+
+    fn pop_ty(&mut self) -> Option<Ty> {
+        match self.pop_if(|node| matches!(node, Node::Ty(..)))? {
+            Node::Ty(ty) => Some(ty),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Pop a function's parameter or return type.
+    fn pop_params(&mut self) -> Option<Ty> {
+        if self
+            .pop_if(|node| matches!(node, Node::EmptyList))
+            .is_some()
+        {
+            Some(Ty::Tuple(Vec::new()))
+        } else {
+            self.pop_ty()
+        }
+    }
+
+    /// Pop a context (a module, a nominal type, or an entity) and
+    /// return its textual representation.
+    fn pop_context(&mut self) -> Option<String> {
+        match self.stack.pop()? {
+            Node::Ident(name) | Node::Module(name) | Node::Entity(name) => Some(name),
+            Node::Ty(Ty::Named(name)) | Node::Ty(Ty::Generic { name, .. }) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Pop the argument labels of a function with the given parameter
+    /// type.
+    fn pop_labels(&mut self, params: &Ty) -> Option<Labels> {
+        if self
+            .pop_if(|node| matches!(node, Node::EmptyList))
+            .is_some()
+        {
+            return Some(None)
+        }
+
+        let count = params.param_count();
+        if count == 0 {
+            return Some(None)
+        }
+
+        let mut labels = Vec::with_capacity(count);
+        for _ in 0..count {
+            match self.stack.pop()? {
+                Node::Ident(label) => labels.push(Some(label)),
+                Node::FirstElem => labels.push(None),
+                _ => return None,
+            }
+        }
+
+        if labels.iter().all(Option::is_none) {
+            return Some(None)
+        }
+        let () = labels.reverse();
+        Some(Some(labels))
+    }
+
+    /// Pop a function type along with its argument labels.
+    fn pop_func_ty(&mut self) -> Option<(Ty, Ty, Labels)> {
+        let (params, ret) = match self.pop_ty()? {
+            Ty::Func { params, ret } => (*params, *ret),
+            _ => return None,
+        };
+        let labels = self.pop_labels(&params)?;
+        Some((params, ret, labels))
+    }
+
+    fn demangle_identifier(&mut self) -> Option<Node> {
+        let mut word_substs = false;
+        if self.next_if(b'0') {
+            // Punycode encoded identifiers are not supported.
+            if self.peek() == Some(b'0') {
+                return None
+            }
+            word_substs = true;
+        }
+
+        let mut ident = String::new();
+        loop {
+            while word_substs && self.peek().map(|c| c.is_ascii_alphabetic()) == Some(true) {
+                let c = self.next()?;
+                let idx = if c.is_ascii_lowercase() {
+                    usize::from(c - b'a')
+                } else {
+                    word_substs = false;
+                    usize::from(c - b'A')
+                };
+                let () = ident.push_str(self.words.get(idx)?);
+            }
+
+            if self.next_if(b'0') {
+                break
+            }
+
+            let len = self.natural()?;
+            if len == 0 {
+                return None
+            }
+            let end = self.pos.checked_add(len)?;
+            let slice = self.text.get(self.pos..end)?;
+            let slice = std::str::from_utf8(slice).ok()?;
+            let () = ident.push_str(slice);
+            let () = self.extract_words(slice);
+            self.pos = end;
+
+            if !word_substs {
+                break
+            }
+        }
+
+        Some(Node::Ident(ident))
+    }
+
+    /// Remember all words in an identifier for later reference via word
+    /// substitutions.
+    fn extract_words(&mut self, slice: &str) {
+        let bytes = slice.as_bytes();
+        let mut start = None;
+        for idx in 0..=bytes.len() {
+            let c = bytes.get(idx).copied().unwrap_or(0);
+            if let Some(word_start) = start {
+                let prev = bytes[idx - 1];
+                let word_end =
+                    c == b'_' || c == 0 || (!prev.is_ascii_uppercase() && c.is_ascii_uppercase());
+                if word_end {
+                    if idx - word_start >= 2 && self.words.len() < MAX_WORDS {
+                        let () = self.words.push(slice[word_start..idx].to_string());
+                    }
+                    start = None;
+                }
+            }
+
+            if start.is_none() && !c.is_ascii_digit() && c != b'_' && c != 0 {
+                start = Some(idx);
+            }
+        }
+    }
+
+    fn substitution(&mut self, count: Option<usize>, idx: usize) -> Option<()> {
+        let count = count.unwrap_or(1);
+        if count > MAX_REPEAT_COUNT {
+            return None
+        }
+
+        let node = self.substs.get(idx)?.clone();
+        for _ in 0..count {
+            let () = self.push(node.clone());
+        }
+        Some(())
+    }
+
+    fn demangle_multi_substitutions(&mut self) -> Option<()> {
+        let mut count = None;
+        loop {
+            let c = self.next()?;
+            match c {
+                b'a'..=b'z' => {
+                    let () = self.substitution(count.take(), usize::from(c - b'a'))?;
+                }
+                b'A'..=b'Z' => return self.substitution(count, usize::from(c - b'A')),
+                b'_' => return self.substitution(None, count?.checked_add(27)?),
+                _ => {
+                    self.pos -= 1;
+                    count = Some(self.natural()?);
+                }
+            }
+        }
+    }
+
+    fn demangle_standard_substitution(&mut self) -> Option<()> {
+        let count = if matches!(self.peek(), Some(b'1'..=b'9')) {
+            self.natural()?
+        } else {
+            1
+        };
+        if count > MAX_REPEAT_COUNT {
+            return None
+        }
+
+        let c = self.next()?;
+        let node = match c {
+            b'C' => Node::Module("__C".into()),
+            b'g' => {
+                let ty = Ty::Generic {
+                    name: "Swift.Optional".into(),
+                    args: vec![self.pop_ty()?],
+                };
+                let node = Node::Ty(ty);
+                let () = self.substs.push(node.clone());
+                node
+            }
+            c => Node::Ty(Ty::Named(format!("Swift.{}", standard_type(c)?))),
+        };
+
+        for _ in 0..count {
+            let () = self.push(node.clone());
+        }
+        Some(())
+    }
+
+    fn demangle_tuple(&mut self) -> Option<Ty> {
+        let mut elems = Vec::new();
+        if self
+            .pop_if(|node| matches!(node, Node::EmptyList))
+            .is_none()
+        {
+            loop {
+                let first = self
+                    .pop_if(|node| matches!(node, Node::FirstElem))
+                    .is_some();
+                let label = self.pop_ident();
+                let ty = self.pop_ty()?;
+                let () = elems.push((label, ty));
+                if first {
+                    break
+                }
+            }
+            let () = elems.reverse();
+        }
+        Some(Ty::Tuple(elems))
+    }
+
+    fn demangle_bound_generic(&mut self) -> Option<Ty> {
+        let mut args = Vec::new();
+        while let Some(ty) = self.pop_ty() {
+            let () = args.push(ty);
+        }
+        let () = args.reverse();
+
+        // Generic arguments of parent types are not supported.
+        let _empty = self.pop_if(|node| matches!(node, Node::EmptyList))?;
+        let name = match self.pop_ty()? {
+            Ty::Named(name) => name,
+            _ => return None,
+        };
+
+        let ty = Ty::Generic { name, args };
+        let () = self.substs.push(Node::Ty(ty.clone()));
+        Some(ty)
+    }
+
+    fn demangle_function_entity(&mut self) -> Option<Node> {
+        let name = match self.next()? {
+            b'C' => "__allocating_init",
+            b'c' => "init",
+            b'D' => "__deallocating_deinit",
+            b'd' => "deinit",
+            _ => return None,
+        };
+
+        let entity = match name {
+            "__allocating_init" | "init" => {
+                let (params, ret, labels) = self.pop_func_ty()?;
+                let ctx = self.pop_context()?;
+                let sig = format_signature(&params, &ret, labels.as_deref(), self.opts);
+                format!("{ctx}.{name}{sig}")
+            }
+            _ => {
+                let ctx = self.pop_context()?;
+                format!("{ctx}.{name}")
+            }
+        };
+        Some(Node::Entity(entity))
+    }
+
+    fn demangle_variable(&mut self) -> Option<Node> {
+        let ty = self.pop_ty()?;
+        let name = self.pop_ident()?;
+        let ctx = self.pop_context()?;
+        let accessor = match self.next()? {
+            b'g' => "getter",
+            b's' => "setter",
+            b'M' => "modify",
+            b'r' => "read",
+            b'w' => "willset",
+            b'W' => "didset",
+            _ => return None,
+        };
+        let entity = if self.opts.return_type {
+            format!("{ctx}.{name}.{accessor} : {ty}")
+        } else {
+            format!("{ctx}.{name}.{accessor}")
+        };
+        Some(Node::Entity(entity))
+    }
+
+    /// Demangle a single operator and push the result onto the stack.
+    fn demangle_operator(&mut self) -> Option<()> {
+        let node = match self.peek()? {
+            b'0'..=b'9' => {
+                let node = self.demangle_identifier()?;
+                let () = self.substs.push(node.clone());
+                node
+            }
+            _ => match self.next()? {
+                b'A' => return self.demangle_multi_substitutions(),
+                b'S' => return self.demangle_standard_substitution(),
+                b's' => Node::Module("Swift".into()),
+                b'y' => Node::EmptyList,
+                b'_' => Node::FirstElem,
+                b't' => Node::Ty(self.demangle_tuple()?),
+                b'c' => {
+                    let params = self.pop_params()?;
+                    let ret = self.pop_params()?;
+                    Node::Ty(Ty::Func {
+                        params: Box::new(params),
+                        ret: Box::new(ret),
+                    })
+                }
+                b'C' | b'V' | b'O' => {
+                    let name = self.pop_ident()?;
+                    let ctx = self.pop_context()?;
+                    let node = Node::Ty(Ty::Named(format!("{ctx}.{name}")));
+                    let () = self.substs.push(node.clone());
+                    node
+                }
+                b'G' => Node::Ty(self.demangle_bound_generic()?),
+                b'F' => {
+                    let params = self.pop_params()?;
+                    let ret = self.pop_params()?;
+                    let labels = self.pop_labels(&params)?;
+                    let name = self.pop_ident()?;
+                    let ctx = self.pop_context()?;
+                    let sig = format_signature(&params, &ret, labels.as_deref(), self.opts);
+                    Node::Entity(format!("{ctx}.{name}{sig}"))
+                }
+                b'f' => self.demangle_function_entity()?,
+                b'v' => self.demangle_variable()?,
+                b'Z' => match self.stack.pop()? {
+                    Node::Entity(entity) => Node::Entity(format!("static {entity}")),
+                    _ => return None,
+                },
+                _ => return None,
+            },
+        };
+
+        let () = self.push(node);
+        Some(())
+    }
+
+    fn demangle(&mut self) -> Option<String> {
+        while self.pos < self.text.len() {
+            let () = self.demangle_operator()?;
+        }
+
+        match self.stack.as_slice() {
+            [Node::Entity(entity)] => Some(entity.clone()),
+            _ => None,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Check that we can demangle a set of supported Swift symbols.
+    #[test]
+    fn demangling() {
+        let symbols = [
+            ("$s4main3fooyyF", "main.foo() -> ()"),
+            (
+                "_$s4main3add1a1bS2i_SitF",
+                "main.add(a: Swift.Int, b: Swift.Int) -> Swift.Int",
+            ),
+            (
+                "$s4main3sumySiSaySiGF",
+                "main.sum([Swift.Int]) -> Swift.Int",
+            ),
+            (
+                "$s4main3FooCACycfC",
+                "main.Foo.__allocating_init() -> main.Foo",
+            ),
+            ("$s4main3FooCfD", "main.Foo.__deallocating_deinit"),
+            ("$s4main1xSivg", "main.x.getter : Swift.Int"),
+            ("$s4main4nameSSSgvs", "main.name.setter : Swift.String?"),
+            (
+                "$s4main12SomeLongTypeV0cB0yyF",
+                "main.SomeLongType.LongSome() -> ()",
+            ),
+            (
+                "$s4main3BarV5valueyySiFZ",
+                "static main.Bar.value(Swift.Int) -> ()",
+            ),
+        ];
+
+        for (mangled, expected) in symbols {
+            assert!(is_mangled(mangled), "{mangled}");
+            assert_eq!(
+                demangle(mangled, &DemangleOpts::default()).as_deref(),
+                Some(expected),
+                "{mangled}"
+            );
+        }
+    }
+
+    /// Make sure that we fail gracefully on unsupported or invalid
+    /// input.
+    #[test]
+    fn demangling_failure() {
+        let symbols = [
+            "main",
+            "_ZN4core9panicking9panic_fmt17h5f1a6fd39197ad62E",
+            "$s",
+            "$s4main",
+            "$s4main3fooyyFTf4n_n",
+            "$s99main3fooyyF",
+            "$s4main3fooyyF4main",
+            "$s00main3fooyyF",
+        ];
+
+        for mangled in symbols {
+            assert_eq!(
+                demangle(mangled, &DemangleOpts::default()),
+                None,
+                "{mangled}"
+            );
+        }
+    }
+
+    /// Make sure that excessive substitution repeat counts are rejected
+    /// instead of exhausting memory or overflowing.
+    #[test]
+    fn excessive_substitution_counts() {
+        let symbols = [
+            "$s4main3fooyySiA999999999999aF",
+            "$s4main3fooyySiA999999999999AF",
+            "$s4main3fooyyS999999999999iF",
+            "$s4main3fooyySiA18446744073709551615_F",
+            "$s4main3fooyySiA18446744073709551600_F",
+        ];
+
+        for mangled in symbols {
+            assert_eq!(
+                demangle(mangled, &DemangleOpts::default()),
+                None,
+                "{mangled}"
+            );
+        }
+    }
+}
@@ -0,0 +1,376 @@
+//! Support for split DWARF, i.e., debug information residing in `.dwo`
+//! files or `.dwp` packages thereof.
+
+use std::ffi::OsString;
+use std::fs;
+use std::mem;
+use std::ops::Deref as _;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::elf::ElfParser;
+use crate::log::debug;
+use crate::once::OnceCell;
+use crate::util::bytes_to_os_str;
+use crate::vfs::FileAccess;
+use crate::vfs::StdFileAccess;
+use crate::Error;
+use crate::ErrorExt as _;
+use crate::ErrorKind;
+use crate::Result;
+
+use super::reader;
+use super::reader::R;
+
+
+/// Open the ELF file at `path` using `fs`, if it exists.
+fn open_elf(fs: &dyn FileAccess, path: &Path) -> Result<Option<Arc<ElfParser>>> {
+    let data = match fs.open(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).with_context(|| format!("failed to open {}", path.display())),
+    };
+    Ok(Some(Arc::new(ElfParser::from_mmap(data.mmap().clone()))))
+}
+
+/// Retrieve the source code language of the compilation unit `unit`.
+fn unit_language<'dwarf>(unit: &gimli::Unit<R<'dwarf>>) -> Result<Option<gimli::DwLang>> {
+    let mut entries = unit.entries();
+    let lang = match entries.next_dfs()? {
+        Some((_, entry)) => match entry.attr_value(gimli::DW_AT_language)? {
+            Some(gimli::AttributeValue::Language(lang)) => Some(lang),
+            _ => None,
+        },
+        None => None,
+    };
+    Ok(lang)
+}
+
+/// Find the split compilation unit with ID `dwo_id` in `dwarf`.
+fn find_split_unit<'dwarf>(
+    dwarf: &gimli::Dwarf<R<'dwarf>>,
+    dwo_id: gimli::DwoId,
+    skeleton: &gimli::Unit<R<'dwarf>>,
+) -> Result<Option<gimli::Unit<R<'dwarf>>>> {
+    let mut headers = dwarf.units();
+    while let Some(header) = headers.next()? {
+        let mut unit = dwarf.unit(header)?;
+        if unit.dwo_id == Some(dwo_id) {
+            let () = unit.copy_relocated_attributes(skeleton);
+            return Ok(Some(unit))
+        }
+    }
+    Ok(None)
+}
+
+
+/// Information on where to find the split DWARF files belonging to a
+/// binary.
+#[derive(Debug)]
+pub(crate) struct DwoLocator {
+    /// The path under which the root directory of the mount namespace
+    /// the binary resides in is accessible, if it may differ from
+    /// ours.
+    root: Option<PathBuf>,
+    /// The path to the binary, as seen from within its mount namespace.
+    binary: Option<PathBuf>,
+    /// The file access to use for opening split DWARF files.
+    fs: Arc<dyn FileAccess>,
+}
+
+impl Default for DwoLocator {
+    fn default() -> Self {
+        Self {
+            root: None,
+            binary: None,
+            fs: Arc::new(StdFileAccess::default()),
+        }
+    }
+}
+
+impl DwoLocator {
+    /// Create a `DwoLocator` for the binary accessed via `path`.
+    ///
+    /// Binaries of other processes accessed through
+    /// `/proc/<pid>/map_files/` or `/proc/<pid>/root/` have their split
+    /// DWARF files looked up relative to said process' root directory,
+    /// which may reside in a different mount namespace.
+    pub(crate) fn new(path: &Path) -> Self {
+        let mut components = path.components();
+        let proc = match (
+            components.next(),
+            components.next(),
+            components.next(),
+            components.next(),
+        ) {
+            (
+                Some(Component::RootDir),
+                Some(Component::Normal(proc)),
+                Some(Component::Normal(pid)),
+                Some(Component::Normal(kind)),
+            ) if proc == "proc" => Some((Path::new("/proc").join(pid), kind)),
+            _ => None,
+        };
+
+        match proc {
+            Some((pid_dir, kind)) if kind == "map_files" => Self {
+                root: Some(pid_dir.join("root")),
+                binary: fs::read_link(path).ok(),
+                ..Default::default()
+            },
+            Some((pid_dir, kind)) if kind == "root" => Self {
+                root: Some(pid_dir.join("root")),
+                binary: Some(Path::new("/").join(components.as_path())),
+                ..Default::default()
+            },
+            _ => Self {
+                root: None,
+                binary: Some(path.to_path_buf()),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Open split DWARF files using `fs`.
+    pub(crate) fn with_file_access(mut self, fs: Arc<dyn FileAccess>) -> Self {
+        self.fs = fs;
+        self
+    }
+
+    /// Retrieve the list of paths under which the file with the given
+    /// path, as seen from within the binary's mount namespace, may be
+    /// accessible.
+    fn candidates(&self, path: &Path) -> Vec<PathBuf> {
+        let path = if path.is_relative() {
+            // Relative paths are interpreted relative to the directory
+            // containing the binary.
+            match self.binary.as_ref().and_then(|binary| binary.parent()) {
+                Some(dir) => dir.join(path),
+                None => path.to_path_buf(),
+            }
+        } else {
+            path.to_path_buf()
+        };
+
+        match &self.root {
+            Some(root) if path.is_absolute() => {
+                // `join` would discard `root` for absolute paths.
+                let mut rooted = root.clone().into_os_string();
+                let () = rooted.push(path.as_os_str());
+                vec![PathBuf::from(rooted), path]
+            }
+            _ => vec![path],
+        }
+    }
+
+    /// Retrieve the list of paths under which the DWARF package
+    /// belonging to the binary may be accessible.
+    fn dwp_candidates(&self) -> Vec<PathBuf> {
+        match &self.binary {
+            Some(binary) => {
+                let mut dwp = OsString::from(binary.as_os_str());
+                let () = dwp.push(".dwp");
+                self.candidates(Path::new(&dwp))
+            }
+            None => Vec::new(),
+        }
+    }
+}
+
+
+/// A DWARF package (`.dwp` file) along with the parser owning its data.
+struct Package<'dwarf> {
+    /// The DWARF package.
+    package: gimli::DwarfPackage<R<'dwarf>>,
+    /// The parser for the package file.
+    // SAFETY: This member has to be listed after `package`, which
+    //         references its data.
+    _parser: Arc<ElfParser>,
+}
+
+
+/// A split compilation unit loaded from a `.dwo` or `.dwp` file.
+pub(super) struct DwoUnit<'dwarf> {
+    /// The DWARF data of the split unit.
+    pub(super) dwarf: gimli::Dwarf<R<'dwarf>>,
+    /// The split compilation unit itself.
+    pub(super) unit: gimli::Unit<R<'dwarf>>,
+    /// The source code language of the unit.
+    pub(super) lang: Option<gimli::DwLang>,
+    /// The parser for the `.dwo` file, if the unit was not loaded from
+    /// a package.
+    // SAFETY: This member has to be listed after `dwarf` and `unit`,
+    //         which reference its data.
+    _parser: Option<Arc<ElfParser>>,
+}
+
+
+/// Lazily loaded split DWARF data belonging to a binary.
+#[derive(Default)]
+pub(super) struct SplitDwarf<'dwarf> {
+    /// Information on where to find split DWARF files.
+    locator: DwoLocator,
+    /// The DWARF package belonging to the binary, if any.
+    package: OnceCell<Option<Package<'dwarf>>>,
+}
+
+impl<'dwarf> SplitDwarf<'dwarf> {
+    pub(super) fn new(locator: DwoLocator) -> Self {
+        Self {
+            locator,
+            package: OnceCell::new(),
+        }
+    }
+
+    fn package(&self) -> Result<Option<&Package<'dwarf>>> {
+        let package = self.package.get_or_try_init(|| {
+            for path in self.locator.dwp_candidates() {
+                let parser = match open_elf(self.locator.fs.as_ref(), &path)? {
+                    Some(parser) => parser,
+                    None => continue,
+                };
+                // SAFETY: The parser is stored alongside the package
+                //         referencing its data, so it is fine to
+                //         conjure up a 'dwarf lifetime here.
+                let data =
+                    unsafe { mem::transmute::<&ElfParser, &'dwarf ElfParser>(parser.deref()) };
+                let empty = R::new(&[], reader::endianess(data)?);
+                let mut load_section = |section| match section {
+                    // We have no use for type units. Some tools emit
+                    // empty type unit indices that `gimli` considers
+                    // invalid, so don't bother loading them at all.
+                    gimli::SectionId::DebugTuIndex => Ok(empty),
+                    _ => reader::load_dwo_section(data, section),
+                };
+                let package = gimli::DwarfPackage::load(&mut load_section, empty)
+                    .with_context(|| format!("failed to load DWARF package {}", path.display()))?;
+                return Result::<_, Error>::Ok(Some(Package {
+                    package,
+                    _parser: parser,
+                }))
+            }
+            Ok(None)
+        })?;
+        Ok(package.as_ref())
+    }
+
+    /// Load the split compilation unit belonging to `skeleton`.
+    ///
+    /// The DWARF package of the binary is consulted first, before
+    /// falling back to the `.dwo` file referenced by the skeleton unit.
+    pub(super) fn load(
+        &self,
+        skeleton: &gimli::Unit<R<'dwarf>>,
+        sections: &gimli::Dwarf<R<'dwarf>>,
+    ) -> Result<Option<DwoUnit<'dwarf>>> {
+        let dwo_id = match skeleton.dwo_id {
+            Some(dwo_id) => dwo_id,
+            None => return Ok(None),
+        };
+
+        if let Some(package) = self.package()? {
+            if let Some(dwarf) = package.package.find_cu(dwo_id, sections)? {
+                if let Some(unit) = find_split_unit(&dwarf, dwo_id, skeleton)? {
+                    let dwo = DwoUnit {
+                        lang: unit_language(&unit)?,
+                        dwarf,
+                        unit,
+                        _parser: None,
+                    };
+                    return Ok(Some(dwo))
+                }
+            }
+        }
+
+        let dwo_name = match skeleton.dwo_name()? {
+            Some(dwo_name) => sections.attr_string(skeleton, dwo_name)?,
+            None => return Ok(None),
+        };
+        let dwo_name = bytes_to_os_str(dwo_name.slice()).ok_or(gimli::Error::BadUtf8)?;
+        let path = match &skeleton.comp_dir {
+            Some(comp_dir) => {
+                let comp_dir = bytes_to_os_str(comp_dir.slice()).ok_or(gimli::Error::BadUtf8)?;
+                Path::new(comp_dir).join(dwo_name)
+            }
+            None => PathBuf::from(dwo_name),
+        };
+
+        for path in self.locator.candidates(&path) {
+            let parser = match open_elf(self.locator.fs.as_ref(), &path)? {
+                Some(parser) => parser,
+                None => continue,
+            };
+            // SAFETY: The parser is stored alongside the unit
+            //         referencing its data, so it is fine to conjure
+            //         up a 'dwarf lifetime here.
+            let data = unsafe { mem::transmute::<&ElfParser, &'dwarf ElfParser>(parser.deref()) };
+            let mut load_section = |section| reader::load_dwo_section(data, section);
+            let mut dwarf = gimli::Dwarf::load(&mut load_section)?;
+            let () = dwarf.make_dwo(sections);
+
+            match find_split_unit(&dwarf, dwo_id, skeleton)? {
+                Some(unit) => {
+                    let dwo = DwoUnit {
+                        lang: unit_language(&unit)?,
+                        dwarf,
+                        unit,
+                        _parser: Some(parser),
+                    };
+                    return Ok(Some(dwo))
+                }
+                None => {
+                    debug!(
+                        "split DWARF file {} does not contain unit with DWO ID {:#x}",
+                        path.display(),
+                        dwo_id.0
+                    );
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Check that we derive the correct lookup locations for split
+    /// DWARF files.
+    #[test]
+    fn locator_candidates() {
+        let locator = DwoLocator::new(Path::new("/usr/bin/foo"));
+        assert_eq!(
+            locator.candidates(Path::new("/build/foo.dwo")),
+            vec![PathBuf::from("/build/foo.dwo")]
+        );
+        assert_eq!(
+            locator.candidates(Path::new("foo.dwo")),
+            vec![PathBuf::from("/usr/bin/foo.dwo")]
+        );
+        assert_eq!(
+            locator.dwp_candidates(),
+            vec![PathBuf::from("/usr/bin/foo.dwp")]
+        );
+
+        let locator = DwoLocator::new(Path::new("/proc/42/root/usr/bin/foo"));
+        assert_eq!(
+            locator.candidates(Path::new("/build/foo.dwo")),
+            vec![
+                PathBuf::from("/proc/42/root/build/foo.dwo"),
+                PathBuf::from("/build/foo.dwo")
+            ]
+        );
+        assert_eq!(
+            locator.dwp_candidates(),
+            vec![
+                PathBuf::from("/proc/42/root/usr/bin/foo.dwp"),
+                PathBuf::from("/usr/bin/foo.dwp")
+            ]
+        );
+    }
+}
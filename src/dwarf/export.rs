@@ -0,0 +1,56 @@
+//! Types describing the functions contained in DWARF debug information,
+//! in a form suitable for conversion into other formats.
+
+use std::ffi::OsStr;
+use std::ops::Range;
+use std::path::Path;
+
+
+/// A source file, as referenced by debug information.
+pub(crate) type SrcFile<'dwarf> = (&'dwarf Path, &'dwarf OsStr);
+
+
+/// A row of a function's line table.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct LineInfo<'dwarf> {
+    /// The first address covered by the row.
+    pub addr: u64,
+    /// The source file the code at `addr` belongs to.
+    pub file: SrcFile<'dwarf>,
+    /// The line number of the code at `addr`.
+    pub line: u32,
+}
+
+
+/// A function call inlined into another function.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct InlinedFnInfo<'dwarf> {
+    /// The name of the inlined function.
+    pub name: Option<&'dwarf str>,
+    /// The address ranges covered by the inlined code.
+    pub ranges: Vec<Range<u64>>,
+    /// The source file containing the call.
+    pub call_file: Option<SrcFile<'dwarf>>,
+    /// The line number of the call.
+    pub call_line: u32,
+    /// Functions inlined into this one in turn.
+    pub children: Vec<InlinedFnInfo<'dwarf>>,
+}
+
+
+/// A function, along with its line information and the calls inlined
+/// into it.
+///
+/// A function spanning multiple discontiguous address ranges is
+/// reported once per range.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct FnInfo<'dwarf> {
+    /// The (preferably linkage) name of the function.
+    pub name: &'dwarf str,
+    /// The address range covered by the function.
+    pub range: Range<u64>,
+    /// The rows of the function's line table, sorted by address.
+    pub lines: Vec<LineInfo<'dwarf>>,
+    /// The function calls inlined into the function.
+    pub inlined: Vec<InlinedFnInfo<'dwarf>>,
+}
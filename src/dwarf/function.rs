@@ -34,6 +34,7 @@ use std::vec;
 use gimli::Error;
 
 use crate::once::OnceCell;
+use crate::scope;
 
 use super::range::RangeAttributes;
 use super::reader::R;
@@ -120,24 +121,27 @@ pub(super) struct InlinedFunction<'dwarf> {
     pub(crate) call_file: Option<u64>,
     pub(crate) call_line: u32,
     pub(crate) call_column: u32,
+    /// The index of the inlined function containing this one in
+    /// `InlinedFunctions::inlined_functions`, if any.
+    pub(super) parent: Option<usize>,
 }
 
 
-struct InlinedFunctionAddress {
-    range: gimli::Range,
+pub(super) struct InlinedFunctionAddress {
+    pub(super) range: gimli::Range,
     call_depth: usize,
     /// An index into `Function::inlined_functions`.
-    function: usize,
+    pub(super) function: usize,
 }
 
 
 pub(super) struct InlinedFunctions<'dwarf> {
     /// List of all `DW_TAG_inlined_subroutine` details in this
     /// function.
-    inlined_functions: Box<[InlinedFunction<'dwarf>]>,
+    pub(super) inlined_functions: Box<[InlinedFunction<'dwarf>]>,
     /// List of `DW_TAG_inlined_subroutine` address ranges in this
     /// function.
-    inlined_addresses: Box<[InlinedFunctionAddress]>,
+    pub(super) inlined_addresses: Box<[InlinedFunctionAddress]>,
 }
 
 impl<'dwarf> InlinedFunctions<'dwarf> {
@@ -166,6 +170,7 @@ impl<'dwarf> InlinedFunctions<'dwarf> {
             &mut inlined_functions,
             &mut inlined_addresses,
             0,
+            None,
         )?;
 
         // Sort ranges in "breadth-first traversal order", i.e. first by
@@ -237,7 +242,7 @@ impl<'dwarf> InlinedFunctions<'dwarf> {
 /// `function` field.
 #[derive(Debug)]
 pub(crate) struct FunctionAddress {
-    range: gimli::Range,
+    pub(crate) range: gimli::Range,
     /// An index into `Functions::functions`.
     pub(crate) function: usize,
 }
@@ -418,6 +423,7 @@ impl<'dwarf> Functions<'dwarf> {
 }
 
 impl<'dwarf> Function<'dwarf> {
+    #[allow(clippy::too_many_arguments)]
     fn parse_children(
         entries: &mut gimli::EntriesRaw<'_, '_, R<'dwarf>>,
         depth: isize,
@@ -426,6 +432,7 @@ impl<'dwarf> Function<'dwarf> {
         inlined_functions: &mut Vec<InlinedFunction<'dwarf>>,
         inlined_addresses: &mut Vec<InlinedFunctionAddress>,
         inlined_depth: usize,
+        parent: Option<usize>,
     ) -> Result<(), Error> {
         loop {
             let next_depth = entries.next_depth();
@@ -437,6 +444,15 @@ impl<'dwarf> Function<'dwarf> {
                     gimli::DW_TAG_subprogram => {
                         Function::skip(entries, abbrev, next_depth)?;
                     }
+                    // Bound recursion by ignoring inlined functions
+                    // nested beyond the configured limit.
+                    gimli::DW_TAG_inlined_subroutine
+                        if scope::limits()
+                            .map(|limits| inlined_depth >= limits.max_depth)
+                            .unwrap_or(false) =>
+                    {
+                        Function::skip(entries, abbrev, next_depth)?;
+                    }
                     gimli::DW_TAG_inlined_subroutine => {
                         InlinedFunction::parse(
                             entries,
@@ -447,6 +463,7 @@ impl<'dwarf> Function<'dwarf> {
                             inlined_functions,
                             inlined_addresses,
                             inlined_depth,
+                            parent,
                         )?;
                     }
                     _ => {
@@ -494,6 +511,7 @@ impl<'dwarf> InlinedFunction<'dwarf> {
         inlined_functions: &mut Vec<InlinedFunction<'dwarf>>,
         inlined_addresses: &mut Vec<InlinedFunctionAddress>,
         inlined_depth: usize,
+        parent: Option<usize>,
     ) -> Result<(), Error> {
         let mut ranges = RangeAttributes::default();
         let mut name = None;
@@ -572,6 +590,7 @@ impl<'dwarf> InlinedFunction<'dwarf> {
             call_file,
             call_line,
             call_column,
+            parent,
         });
 
         ranges.for_each_range(sections, unit, |range| {
@@ -590,6 +609,7 @@ impl<'dwarf> InlinedFunction<'dwarf> {
             inlined_functions,
             inlined_addresses,
             inlined_depth + 1,
+            Some(function_index),
         )
     }
 }
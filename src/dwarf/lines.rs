@@ -27,11 +27,13 @@
 
 use std::borrow::Cow;
 use std::ffi::OsStr;
-use std::mem;
 use std::num::NonZeroU64;
-use std::os::unix::ffi::OsStrExt as _;
 use std::path::Path;
 
+use crate::util::bytes_to_os_str;
+use crate::util::push_u64_leb128;
+use crate::util::ReadRaw as _;
+
 use super::reader::R;
 
 
@@ -52,35 +54,197 @@ fn render_file<'dwarf>(
     sections: &gimli::Dwarf<R<'dwarf>>,
 ) -> Result<(Cow<'dwarf, Path>, &'dwarf OsStr), gimli::Error> {
     let dir = if let Some(ref comp_dir) = dw_unit.comp_dir {
-        Path::new(OsStr::from_bytes(comp_dir.slice()))
+        Path::new(bytes_to_os_str(comp_dir.slice()).ok_or(gimli::Error::BadUtf8)?)
     } else {
         Path::new("")
     };
 
-    // The directory index 0 is defined to correspond to the compilation unit directory.
-    let dir = if file.directory_index() != 0 {
+    // The directory index 0 is defined to correspond to the compilation
+    // unit directory. Prior to DWARF 5 it is implicit and we just use
+    // `DW_AT_comp_dir`. Starting with DWARF 5 it is an explicit entry
+    // in the directory table, which is what compilers such as clang
+    // reference for all files residing in the compilation directory.
+    let dir = if file.directory_index() != 0 || header.version() >= 5 {
         if let Some(directory) = file.directory(header) {
             let d = sections.attr_string(dw_unit, directory)?;
-            path_push(dir, Path::new(OsStr::from_bytes(d.slice())))
+            let d = bytes_to_os_str(d.slice()).ok_or(gimli::Error::BadUtf8)?;
+            path_push(dir, Path::new(d))
         } else {
             Cow::default()
         }
     } else {
-        Cow::default()
+        Cow::Borrowed(dir)
     };
 
     let f = sections.attr_string(dw_unit, file.path_name())?;
-    let file = OsStr::from_bytes(f.slice());
+    let file = bytes_to_os_str(f.slice()).ok_or(gimli::Error::BadUtf8)?;
     Ok((dir, file))
 }
 
 
+/// The number of rows encoded in a single block of a [`LineRows`]
+/// object.
+const BLOCK_ROWS: usize = 16;
+
+
+/// Map a signed value to an unsigned one, such that values of small
+/// magnitude map to small values.
+#[inline]
+fn zigzag_encode(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+#[inline]
+fn zigzag_decode(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+
+/// The start of a block of rows.
+#[derive(Debug)]
+struct Block {
+    /// The address of the first row in the block.
+    address: u64,
+    /// The offset of the block's first row in the encoded data.
+    offset: usize,
+}
+
+
+/// A compact representation of the rows of a line sequence.
+///
+/// Rows are delta encoded against their predecessor, with each value
+/// stored as LEB128. To allow for efficient random access, rows are
+/// grouped into blocks of [`BLOCK_ROWS`] rows each, with every block
+/// being decodable on its own. Compared to storing [`LineRow`] objects
+/// directly, this representation typically needs less than a quarter of
+/// the memory.
+#[derive(Debug)]
+pub(crate) struct LineRows {
+    /// The encoded rows.
+    data: Box<[u8]>,
+    /// The blocks the rows are grouped into.
+    blocks: Box<[Block]>,
+    /// The total number of rows.
+    len: usize,
+}
+
+impl LineRows {
+    /// Encode the provided rows, which are expected to be sorted by
+    /// address.
+    fn new(rows: &[LineRow]) -> Self {
+        let mut data = Vec::new();
+        let mut blocks = Vec::with_capacity((rows.len() + BLOCK_ROWS - 1) / BLOCK_ROWS);
+
+        for chunk in rows.chunks(BLOCK_ROWS) {
+            // SANITY: Chunks are never empty.
+            let first = &chunk[0];
+            let () = blocks.push(Block {
+                address: first.address,
+                offset: data.len(),
+            });
+
+            let mut address = first.address;
+            let mut line = 0;
+            for row in chunk {
+                // Malformed data may not be properly sorted. We still
+                // have to be able to represent it faithfully.
+                let () = push_u64_leb128(&mut data, row.address.wrapping_sub(address));
+                let () = push_u64_leb128(
+                    &mut data,
+                    zigzag_encode(i64::from(row.line) - i64::from(line)),
+                );
+                let () = push_u64_leb128(&mut data, row.file_index);
+                let () = push_u64_leb128(&mut data, u64::from(row.column));
+                address = row.address;
+                line = row.line;
+            }
+        }
+
+        Self {
+            data: data.into_boxed_slice(),
+            blocks: blocks.into_boxed_slice(),
+            len: rows.len(),
+        }
+    }
+
+    /// Retrieve the number of rows.
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Decode the rows of the block with index `block`.
+    fn block_rows(&self, block: usize) -> impl Iterator<Item = LineRow> + '_ {
+        let (mut address, mut data) = self
+            .blocks
+            .get(block)
+            .map(|block| (block.address, &self.data[block.offset..]))
+            .unwrap_or((0, &[]));
+        let count = (self.len - (block * BLOCK_ROWS).min(self.len)).min(BLOCK_ROWS);
+        let mut line = 0u32;
+
+        (0..count).map_while(move |_| {
+            // We created the data ourselves, so decoding cannot fail.
+            address = address.wrapping_add(data.read_u64_leb128()?.0);
+            let delta = zigzag_decode(data.read_u64_leb128()?.0);
+            line = (i64::from(line) + delta) as u32;
+            let file_index = data.read_u64_leb128()?.0;
+            let column = data.read_u64_leb128()?.0 as u32;
+
+            Some(LineRow {
+                address,
+                file_index,
+                line,
+                column,
+            })
+        })
+    }
+
+    /// Retrieve the row at index `idx`.
+    pub(crate) fn get(&self, idx: usize) -> Option<LineRow> {
+        if idx >= self.len {
+            return None
+        }
+        self.block_rows(idx / BLOCK_ROWS).nth(idx % BLOCK_ROWS)
+    }
+
+    /// Iterate over the rows, starting at the one with index `idx`.
+    pub(crate) fn iter_from(&self, idx: usize) -> impl Iterator<Item = LineRow> + '_ {
+        (idx / BLOCK_ROWS..self.blocks.len())
+            .flat_map(|block| self.block_rows(block))
+            .skip(idx % BLOCK_ROWS)
+    }
+
+    /// Binary search the rows for one with the given `address`.
+    ///
+    /// The semantics are equivalent to those of
+    /// [`slice::binary_search_by_key`].
+    pub(crate) fn search(&self, address: u64) -> Result<usize, usize> {
+        let block = self
+            .blocks
+            .partition_point(|block| block.address <= address)
+            .saturating_sub(1);
+        let base = block * BLOCK_ROWS;
+
+        for (idx, row) in self.block_rows(block).enumerate() {
+            if row.address == address {
+                return Ok(base + idx)
+            } else if row.address > address {
+                return Err(base + idx)
+            }
+        }
+        Err((base + BLOCK_ROWS).min(self.len))
+    }
+}
+
+
 pub(crate) struct LineSequence {
     pub(crate) start: u64,
     pub(crate) end: u64,
-    pub(crate) rows: Box<[LineRow]>,
+    pub(crate) rows: LineRows,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub(crate) struct LineRow {
     pub(crate) address: u64,
     pub(crate) file_index: u64,
@@ -106,13 +270,12 @@ impl<'dwarf> Lines<'dwarf> {
             if row.end_sequence() {
                 if let Some(start) = sequence_rows.first().map(|x| x.address) {
                     let end = row.address();
-                    let mut rows = Vec::new();
-                    mem::swap(&mut rows, &mut sequence_rows);
                     sequences.push(LineSequence {
                         start,
                         end,
-                        rows: rows.into_boxed_slice(),
+                        rows: LineRows::new(&sequence_rows),
                     });
+                    let () = sequence_rows.clear();
                 }
                 continue
             }
@@ -161,3 +324,81 @@ impl<'dwarf> Lines<'dwarf> {
         })
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Check that rows survive an encoding round trip and can be
+    /// searched for.
+    #[test]
+    fn row_encoding() {
+        for count in [
+            0,
+            1,
+            BLOCK_ROWS - 1,
+            BLOCK_ROWS,
+            BLOCK_ROWS + 1,
+            5 * BLOCK_ROWS + 3,
+        ] {
+            let rows = (0..count as u64)
+                .map(|i| LineRow {
+                    address: 0xffff_0000_0000 + i * i * 3,
+                    file_index: i % 5,
+                    line: if i % 2 == 0 {
+                        1000 + i as u32
+                    } else {
+                        i as u32
+                    },
+                    column: (i % 80) as u32,
+                })
+                .collect::<Vec<_>>();
+            let encoded = LineRows::new(&rows);
+            assert_eq!(encoded.len(), rows.len());
+
+            for (idx, row) in rows.iter().enumerate() {
+                assert_eq!(encoded.get(idx), Some(*row));
+                assert_eq!(encoded.search(row.address), Ok(idx));
+                assert_eq!(
+                    encoded.search(row.address + 1),
+                    rows.binary_search_by_key(&(row.address + 1), |row| row.address)
+                );
+            }
+            assert_eq!(encoded.get(rows.len()), None);
+            assert!(encoded.iter_from(0).eq(rows.iter().copied()));
+            assert!(encoded
+                .iter_from(rows.len() / 2)
+                .eq(rows[rows.len() / 2..].iter().copied()));
+            assert_eq!(encoded.search(0), Err(0));
+            assert_eq!(encoded.search(u64::MAX), Err(rows.len()));
+        }
+    }
+
+    /// Check that extreme values are encoded correctly.
+    #[test]
+    fn extreme_values() {
+        let rows = [
+            LineRow {
+                address: 0,
+                file_index: u64::MAX,
+                line: u32::MAX,
+                column: u32::MAX,
+            },
+            LineRow {
+                address: u64::MAX,
+                file_index: 0,
+                line: 0,
+                column: 0,
+            },
+        ];
+        let encoded = LineRows::new(&rows);
+        assert_eq!(encoded.get(0), Some(rows[0]));
+        assert_eq!(encoded.get(1), Some(rows[1]));
+
+        for value in [0, 1, -1, i64::MIN, i64::MAX] {
+            assert_eq!(zigzag_decode(zigzag_encode(value)), value);
+        }
+    }
+}
@@ -84,7 +84,7 @@ impl<'unit, 'dwarf> LocationRangeUnitIter<'unit, 'dwarf> {
             };
 
             let row_idx = if let Some(seq) = lines.sequences.get(seq_idx) {
-                let idx = seq.rows.binary_search_by(|row| row.address.cmp(&probe_low));
+                let idx = seq.rows.search(probe_low);
                 match idx {
                     Ok(x) => x,
                     Err(0) => 0, // probe below sequence, but range could overlap
@@ -122,9 +122,16 @@ impl<'unit, 'dwarf> Iterator for LocationRangeUnitIter<'unit, 'dwarf> {
                         break
                     }
 
-                    // SANITY: We always have a file present for each
-                    //         `file_index`.
-                    let (dir, file) = self.lines.files.get(row.file_index as usize).unwrap();
+                    let (dir, file) = match self.lines.files.get(row.file_index as usize) {
+                        Some(entry) => entry,
+                        None => {
+                            // Malformed debug information may reference
+                            // files that do not exist. There is nothing
+                            // meaningful we can report for such rows.
+                            self.row_idx += 1;
+                            continue
+                        }
+                    };
                     let nextaddr = seq
                         .rows
                         .get(self.row_idx + 1)
@@ -133,7 +140,7 @@ impl<'unit, 'dwarf> Iterator for LocationRangeUnitIter<'unit, 'dwarf> {
 
                     let item = (
                         row.address,
-                        nextaddr - row.address,
+                        nextaddr.saturating_sub(row.address),
                         Location {
                             dir,
                             file,
@@ -1,3 +1,5 @@
+mod dwo;
+mod export;
 mod function;
 mod lines;
 mod location;
@@ -7,4 +9,11 @@ mod resolver;
 mod unit;
 mod units;
 
+#[cfg(any(feature = "breakpad", feature = "gsym"))]
+pub(crate) use self::export::FnInfo;
+#[cfg(any(feature = "breakpad", feature = "gsym"))]
+pub(crate) use self::export::InlinedFnInfo;
+#[cfg(feature = "gsym")]
+pub(crate) use self::export::LineInfo;
+pub(crate) use self::location::Location;
 pub(crate) use self::resolver::DwarfResolver;
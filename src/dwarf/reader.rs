@@ -1,31 +1,54 @@
 use gimli::EndianSlice;
+use gimli::RunTimeEndian;
 use gimli::SectionId;
 
 use crate::elf::ElfParser;
 use crate::Result;
 
 
-#[cfg(target_endian = "little")]
-type Endianess = gimli::LittleEndian;
-#[cfg(target_endian = "big")]
-type Endianess = gimli::BigEndian;
-
 /// The gimli reader type we currently use. Could be made generic if
 /// need be, but we keep things simple while we can.
-pub(crate) type R<'dat> = EndianSlice<'dat, Endianess>;
+///
+/// The byte order is determined at run time, as we may be dealing with
+/// files created for a different architecture than that of the host.
+pub(crate) type R<'dat> = EndianSlice<'dat, RunTimeEndian>;
+
+
+/// Retrieve the byte order of DWARF data contained in the ELF file
+/// represented by `parser`.
+pub(super) fn endianess(parser: &ElfParser) -> Result<RunTimeEndian> {
+    let endian = if parser.is_big_endian()? {
+        RunTimeEndian::Big
+    } else {
+        RunTimeEndian::Little
+    };
+    Ok(endian)
+}
 
 
 pub(super) fn load_section(parser: &ElfParser, id: SectionId) -> Result<R<'_>> {
-    let result = parser.find_section(id.name())?;
+    load_section_by_name(parser, Some(id.name()))
+}
+
+/// Load a section from a split DWARF file (a `.dwo` or `.dwp` file).
+pub(super) fn load_dwo_section(parser: &ElfParser, id: SectionId) -> Result<R<'_>> {
+    load_section_by_name(parser, id.dwo_name())
+}
+
+fn load_section_by_name<'parser>(
+    parser: &'parser ElfParser,
+    name: Option<&str>,
+) -> Result<R<'parser>> {
+    let result = match name {
+        Some(name) => parser.find_section(name)?,
+        None => None,
+    };
     let data = match result {
         Some(idx) => parser.section_data(idx)?,
         // Make sure to return empty data if a section does not exist.
         None => &[],
     };
 
-    #[cfg(target_endian = "little")]
-    let reader = EndianSlice::new(data, gimli::LittleEndian);
-    #[cfg(target_endian = "big")]
-    let reader = EndianSlice::new(data, gimli::BigEndian);
+    let reader = EndianSlice::new(data, endianess(parser)?);
     Ok(reader)
 }
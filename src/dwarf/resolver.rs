@@ -7,9 +7,9 @@ use std::fmt::Result as FmtResult;
 use std::mem;
 use std::mem::swap;
 use std::ops::Deref as _;
-#[cfg(test)]
+use std::ops::Range;
 use std::path::Path;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use gimli::Dwarf;
 
@@ -20,11 +20,18 @@ use crate::inspect::SymType;
 use crate::symbolize::AddrCodeInfo;
 use crate::symbolize::CodeInfo;
 use crate::symbolize::IntSym;
+use crate::symbolize::Reason;
 use crate::symbolize::SrcLang;
+use crate::vfs::FileAccess;
+#[cfg(test)]
+use crate::vfs::StdFileAccess;
 use crate::Addr;
 use crate::Error;
 use crate::Result;
+use crate::SymResolver;
 
+use super::dwo::DwoLocator;
+use super::export::FnInfo;
 use super::location::Location;
 use super::reader;
 use super::units::Units;
@@ -33,6 +40,12 @@ use super::units::Units;
 impl From<Option<gimli::DwLang>> for SrcLang {
     fn from(other: Option<gimli::DwLang>) -> Self {
         match other {
+            Some(
+                gimli::DW_LANG_Ada83
+                | gimli::DW_LANG_Ada95
+                | gimli::DW_LANG_Ada2005
+                | gimli::DW_LANG_Ada2012,
+            ) => SrcLang::Ada,
             Some(gimli::DW_LANG_Rust) => SrcLang::Rust,
             Some(
                 gimli::DW_LANG_C_plus_plus
@@ -40,6 +53,8 @@ impl From<Option<gimli::DwLang>> for SrcLang {
                 | gimli::DW_LANG_C_plus_plus_11
                 | gimli::DW_LANG_C_plus_plus_14,
             ) => SrcLang::Cpp,
+            Some(gimli::DW_LANG_D) => SrcLang::D,
+            Some(gimli::DW_LANG_Swift) => SrcLang::Swift,
             _ => SrcLang::Unknown,
         }
     }
@@ -54,17 +69,32 @@ pub(crate) struct DwarfResolver {
     //         Furthermore, this member has to be listed before `parser`
     //         to make sure we never end up with a dangling reference.
     units: Units<'static>,
-    parser: Rc<ElfParser>,
+    parser: Arc<ElfParser>,
     line_number_info: bool,
 }
 
 impl DwarfResolver {
     /// Retrieve the resolver's underlying `ElfParser`.
-    pub fn parser(&self) -> &Rc<ElfParser> {
+    pub fn parser(&self) -> &Arc<ElfParser> {
         &self.parser
     }
 
-    pub fn from_parser(parser: Rc<ElfParser>, line_number_info: bool) -> Result<Self, Error> {
+    /// Create a `DwarfResolver` for the debug information available
+    /// via `parser`.
+    ///
+    /// `path` is the path through which the binary was accessed and is
+    /// used for finding split DWARF files it references, which are
+    /// opened using `fs`.
+    ///
+    /// If `hot_ranges` is provided, only compilation units overlapping
+    /// these address ranges are indexed.
+    pub fn from_parser(
+        path: &Path,
+        parser: Arc<ElfParser>,
+        fs: Arc<dyn FileAccess>,
+        line_number_info: bool,
+        hot_ranges: Option<&[Range<Addr>]>,
+    ) -> Result<Self, Error> {
         // SAFETY: We own the `ElfParser` and make sure that it stays
         //         around while the `Units` object uses it. As such, it
         //         is fine to conjure a 'static lifetime here.
@@ -72,7 +102,8 @@ impl DwarfResolver {
             unsafe { mem::transmute::<&ElfParser, &'static ElfParser>(parser.deref()) };
         let mut load_section = |section| reader::load_section(static_parser, section);
         let dwarf = Dwarf::load(&mut load_section)?;
-        let units = Units::parse(dwarf)?;
+        let locator = DwoLocator::new(path).with_file_access(fs);
+        let units = Units::parse(dwarf, hot_ranges)?.with_dwo_locator(locator);
         let slf = Self {
             units,
             parser,
@@ -88,7 +119,8 @@ impl DwarfResolver {
     #[cfg(test)]
     pub fn open(filename: &Path, debug_line_info: bool) -> Result<Self> {
         let parser = ElfParser::open(filename)?;
-        Self::from_parser(Rc::new(parser), debug_line_info)
+        let fs = Arc::new(StdFileAccess::default());
+        Self::from_parser(filename, Arc::new(parser), fs, debug_line_info, None)
     }
 
     /// Find source code information of an address.
@@ -116,6 +148,7 @@ impl DwarfResolver {
                     file: Cow::Borrowed(file),
                     line,
                     column: column.map(|col| col.try_into().unwrap_or(u16::MAX)),
+                    context: None,
                     _non_exhaustive: (),
                 };
 
@@ -137,6 +170,7 @@ impl DwarfResolver {
                                     file: Cow::Borrowed(file),
                                     line,
                                     column: column.map(|col| col.try_into().unwrap_or(u16::MAX)),
+                                    context: None,
                                     _non_exhaustive: (),
                                 }
                             });
@@ -200,6 +234,34 @@ impl DwarfResolver {
         }
     }
 
+    /// Invoke `f` for each function described by the DWARF data, along
+    /// with its line information and inlined function calls.
+    pub(crate) fn for_each_function<F>(&self, f: F) -> Result<()>
+    where
+        F: FnMut(FnInfo<'_>) -> Result<()>,
+    {
+        self.units.for_each_function(f)
+    }
+
+    /// Fold over all functions described by the DWARF data, providing
+    /// each one's name and address range.
+    pub(crate) fn fold_functions<F, R>(&self, r: R, f: F) -> Result<R>
+    where
+        F: FnMut(R, &str, Range<u64>) -> R,
+    {
+        self.units.fold_functions(r, f)
+    }
+
+    /// Find the address ranges of the code generated for `line` of all
+    /// source files whose path ends in `file`.
+    pub(crate) fn find_line_addrs(
+        &self,
+        file: &Path,
+        line: u32,
+    ) -> Result<Vec<(Range<Addr>, Location<'_>)>> {
+        self.units.find_line_addrs(file, line)
+    }
+
     /// Find the address of a symbol from DWARF.
     ///
     /// # Arguments
@@ -246,6 +308,7 @@ impl DwarfResolver {
                                 .transpose()?
                                 .flatten(),
                             obj_file_name: None,
+                            section: None,
                         };
                         Ok(info)
                     }
@@ -258,6 +321,21 @@ impl DwarfResolver {
     }
 }
 
+impl SymResolver for DwarfResolver {
+    fn find_sym(&self, addr: Addr) -> Result<Result<IntSym<'_>, Reason>> {
+        let sym = DwarfResolver::find_sym(self, addr)?;
+        Ok(sym.ok_or(Reason::UnknownAddr))
+    }
+
+    fn find_addr(&self, name: &str, opts: &FindAddrOpts) -> Result<Vec<SymInfo<'_>>> {
+        DwarfResolver::find_addr(self, name, opts)
+    }
+
+    fn find_code_info(&self, addr: Addr, inlined_fns: bool) -> Result<Option<AddrCodeInfo<'_>>> {
+        DwarfResolver::find_code_info(self, addr, inlined_fns)
+    }
+}
+
 impl Debug for DwarfResolver {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         f.write_str(stringify!(DwarfResolver))
@@ -25,8 +25,11 @@
 // > IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // > DEALINGS IN THE SOFTWARE.
 
+use crate::log::warn;
 use crate::once::OnceCell;
 
+use super::dwo::DwoUnit;
+use super::dwo::SplitDwarf;
 use super::function::Function;
 use super::function::Functions;
 use super::lines::Lines;
@@ -47,6 +50,9 @@ pub(super) struct Unit<'dwarf> {
     lang: Option<gimli::DwLang>,
     lines: OnceCell<Lines<'dwarf>>,
     funcs: OnceCell<Functions<'dwarf>>,
+    /// The split compilation unit belonging to this skeleton unit, if
+    /// any.
+    dwo: OnceCell<Option<Box<DwoUnit<'dwarf>>>>,
 }
 
 impl<'dwarf> Unit<'dwarf> {
@@ -60,16 +66,48 @@ impl<'dwarf> Unit<'dwarf> {
             lang,
             lines,
             funcs: OnceCell::new(),
+            dwo: OnceCell::new(),
+        }
+    }
+
+    /// Retrieve the DWARF data and unit to use for parsing functions.
+    ///
+    /// For skeleton units these are the ones of the corresponding split
+    /// compilation unit, if it can be found. Otherwise we fall back to
+    /// the skeleton unit itself.
+    pub(super) fn dwarf_and_unit<'unit>(
+        &'unit self,
+        sections: &'unit gimli::Dwarf<R<'dwarf>>,
+        split: &SplitDwarf<'dwarf>,
+    ) -> (
+        &'unit gimli::Dwarf<R<'dwarf>>,
+        &'unit gimli::Unit<R<'dwarf>>,
+    ) {
+        if self.dw_unit.dwo_id.is_none() {
+            return (sections, &self.dw_unit)
+        }
+
+        let dwo = self.dwo.get_or_init(|| {
+            split
+                .load(&self.dw_unit, sections)
+                .unwrap_or_else(|err| {
+                    warn!("failed to load split DWARF unit: {err:#}");
+                    None
+                })
+                .map(Box::new)
+        });
+        match dwo {
+            Some(dwo) => (&dwo.dwarf, &dwo.unit),
+            None => (sections, &self.dw_unit),
         }
     }
 
-    #[cfg(test)]
-    #[cfg(feature = "nightly")]
     pub(super) fn parse_functions<'unit>(
         &'unit self,
         sections: &gimli::Dwarf<R<'dwarf>>,
+        split: &SplitDwarf<'dwarf>,
     ) -> Result<&'unit Functions<'dwarf>, gimli::Error> {
-        let unit = &self.dw_unit;
+        let (sections, unit) = self.dwarf_and_unit(sections, split);
         let functions = self.parse_functions_dwarf_and_unit(unit, sections)?;
         Ok(functions)
     }
@@ -79,8 +117,9 @@ impl<'dwarf> Unit<'dwarf> {
     pub(super) fn parse_inlined_functions<'unit>(
         &'unit self,
         sections: &gimli::Dwarf<R<'dwarf>>,
+        split: &SplitDwarf<'dwarf>,
     ) -> Result<&'unit Functions<'dwarf>, gimli::Error> {
-        let unit = &self.dw_unit;
+        let (sections, unit) = self.dwarf_and_unit(sections, split);
 
         self.funcs.get_or_try_init(|| {
             let funcs = Functions::parse(unit, sections)?;
@@ -132,8 +171,9 @@ impl<'dwarf> Unit<'dwarf> {
         &self,
         probe: u64,
         sections: &gimli::Dwarf<R<'dwarf>>,
+        split: &SplitDwarf<'dwarf>,
     ) -> Result<Option<&Function<'dwarf>>, gimli::Error> {
-        let unit = &self.dw_unit;
+        let (sections, unit) = self.dwarf_and_unit(sections, split);
         let functions = self.parse_functions_dwarf_and_unit(unit, sections)?;
         let function = match functions.find_address(probe) {
             Some(address) => {
@@ -150,8 +190,9 @@ impl<'dwarf> Unit<'dwarf> {
         &'slf self,
         name: &str,
         sections: &gimli::Dwarf<R<'dwarf>>,
+        split: &SplitDwarf<'dwarf>,
     ) -> Result<Option<&'slf Function<'dwarf>>, gimli::Error> {
-        let unit = &self.dw_unit;
+        let (sections, unit) = self.dwarf_and_unit(sections, split);
         let functions = self.parse_functions_dwarf_and_unit(unit, sections)?;
         for func in functions.functions.iter() {
             let name = Some(name.as_bytes());
@@ -163,14 +204,16 @@ impl<'dwarf> Unit<'dwarf> {
     }
 
     /// Attempt to retrieve the compilation unit's source code language.
-    #[inline]
-    pub(super) fn dw_unit(&self) -> &gimli::Unit<R<'dwarf>> {
-        &self.dw_unit
-    }
-
-    /// Attempt to retrieve the compilation unit's source code language.
+    ///
+    /// For skeleton units the language is only known once the
+    /// corresponding split unit has been loaded.
     #[inline]
     pub(super) fn language(&self) -> Option<gimli::DwLang> {
-        self.lang
+        self.lang.or_else(|| {
+            self.dwo
+                .get()
+                .and_then(Option::as_ref)
+                .and_then(|dwo| dwo.lang)
+        })
     }
 }
@@ -25,12 +25,30 @@
 // > IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // > DEALINGS IN THE SOFTWARE.
 
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::mem;
+use std::ops::Range;
+use std::path::Path;
+
+use gimli::Section as _;
+
 use crate::log::warn;
 use crate::once::OnceCell;
+use crate::scope;
+use crate::Error;
 use crate::ErrorExt as _;
+use crate::Progress;
 use crate::Result;
 
+use super::dwo::DwoLocator;
+use super::dwo::SplitDwarf;
+use super::export::FnInfo;
+use super::export::InlinedFnInfo;
+use super::export::LineInfo;
+use super::export::SrcFile;
 use super::function::Function;
+use super::function::InlinedFunctions;
 use super::lines::Lines;
 use super::location::Location;
 use super::range::RangeAttributes;
@@ -51,6 +69,108 @@ fn format_offset(offset: gimli::UnitSectionOffset<usize>) -> String {
 }
 
 
+/// Retrieve the source file with index `idx` from `files`.
+fn src_file<'lines>(
+    files: &'lines [(Cow<'_, Path>, &'lines OsStr)],
+    idx: u64,
+) -> Option<SrcFile<'lines>> {
+    let (dir, file) = files.get(usize::try_from(idx).ok()?)?;
+    Some((dir.as_ref(), *file))
+}
+
+
+/// Collect the line table rows covering `range`.
+fn collect_lines<'lines>(lines: &'lines Lines<'_>, range: &Range<u64>) -> Vec<LineInfo<'lines>> {
+    let mut infos = Vec::new();
+    for sequence in lines
+        .sequences
+        .iter()
+        .filter(|sequence| sequence.start < range.end && range.start < sequence.end)
+    {
+        // Start with the row covering the beginning of the range, which
+        // may reside before it.
+        let idx = match sequence.rows.search(range.start) {
+            Ok(idx) => idx,
+            Err(idx) => idx.saturating_sub(1),
+        };
+
+        for row in sequence.rows.iter_from(idx) {
+            if row.address >= range.end {
+                break
+            }
+            let file = match src_file(&lines.files, row.file_index) {
+                Some(file) => file,
+                None => continue,
+            };
+
+            let info = LineInfo {
+                addr: row.address.max(range.start),
+                file,
+                line: row.line,
+            };
+            let () = infos.push(info);
+        }
+    }
+    let () = infos.sort_by_key(|info| info.addr);
+    // Of multiple rows for the same address, the last one is the
+    // relevant one.
+    let () = infos.dedup_by(|next, prev| {
+        if next.addr == prev.addr {
+            let () = mem::swap(next, prev);
+            true
+        } else {
+            false
+        }
+    });
+    infos
+}
+
+
+/// Convert the flat list of inlined functions into a tree.
+fn collect_inlined_fns<'lines>(
+    inlined_fns: &'lines InlinedFunctions<'_>,
+    files: &'lines [(Cow<'_, Path>, &'lines OsStr)],
+) -> Vec<InlinedFnInfo<'lines>> {
+    let count = inlined_fns.inlined_functions.len();
+    let mut ranges = vec![Vec::new(); count];
+    for address in inlined_fns.inlined_addresses.iter() {
+        let () = ranges[address.function].push(address.range.begin..address.range.end);
+    }
+
+    // Inlined functions are stored in depth-first order, meaning that
+    // children always come after their parent. Hence, by converting
+    // them in reverse order all children are available once we get to
+    // the parent.
+    let mut children = (0..count).map(|_| Vec::new()).collect::<Vec<_>>();
+    let mut roots = Vec::new();
+    for (idx, inlined_fn) in inlined_fns.inlined_functions.iter().enumerate().rev() {
+        let mut ranges = mem::take(&mut ranges[idx]);
+        let () = ranges.sort_by_key(|range| range.start);
+        let mut fn_children = mem::take(&mut children[idx]);
+        let () = fn_children.reverse();
+
+        let info = InlinedFnInfo {
+            name: inlined_fn
+                .name
+                .as_ref()
+                .and_then(|name| name.to_string().ok()),
+            ranges,
+            call_file: inlined_fn
+                .call_file
+                .and_then(|call_file| src_file(files, call_file)),
+            call_line: inlined_fn.call_line,
+            children: fn_children,
+        };
+        match inlined_fn.parent {
+            Some(parent) => children[parent].push(info),
+            None => roots.push(info),
+        }
+    }
+    let () = roots.reverse();
+    roots
+}
+
+
 pub(crate) struct Units<'dwarf> {
     /// The DWARF data.
     dwarf: gimli::Dwarf<R<'dwarf>>,
@@ -58,10 +178,19 @@ pub(crate) struct Units<'dwarf> {
     unit_ranges: Box<[UnitRange]>,
     /// All units along with meta-data.
     units: Box<[Unit<'dwarf>]>,
+    /// Split DWARF data referenced by skeleton units.
+    split: SplitDwarf<'dwarf>,
 }
 
 impl<'dwarf> Units<'dwarf> {
-    pub(crate) fn parse(sections: gimli::Dwarf<R<'dwarf>>) -> Result<Self> {
+    /// Parse the compilation units in `sections`.
+    ///
+    /// If `hot_ranges` is provided, only units overlapping at least one
+    /// of the given address ranges are retained.
+    pub(crate) fn parse(
+        sections: gimli::Dwarf<R<'dwarf>>,
+        hot_ranges: Option<&[Range<u64>]>,
+    ) -> Result<Self> {
         // Find all the references to compilation units in .debug_aranges.
         // Note that we always also iterate through all of .debug_info to
         // find compilation units, because .debug_aranges may be missing some.
@@ -72,11 +201,31 @@ impl<'dwarf> Units<'dwarf> {
         }
         aranges.sort_by_key(|i| i.0);
 
+        let total_bytes = sections.debug_info.reader().len() as u64;
+        let max_units = scope::limits().map(|limits| limits.max_units);
         let mut unit_ranges = Vec::new();
         let mut res_units = Vec::new();
         let mut units = sections.units();
         while let Some(header) = units.next()? {
+            let () = scope::check()?;
+            let () = scope::report(|| Progress::DwarfIndex {
+                units: res_units.len(),
+                bytes: header
+                    .offset()
+                    .as_debug_info_offset()
+                    .map(|offset| offset.0 as u64)
+                    .unwrap_or(0),
+                total_bytes,
+            });
+
             let unit_id = res_units.len();
+            if let Some(max_units) = max_units {
+                if unit_id >= max_units {
+                    return Err(Error::with_invalid_data(format!(
+                        "number of DWARF units exceeds limit ({max_units})"
+                    )))
+                }
+            }
             let offset = match header.offset().as_debug_info_offset() {
                 Some(offset) => offset,
                 None => continue,
@@ -94,6 +243,7 @@ impl<'dwarf> Units<'dwarf> {
                 )
             })?;
 
+            let first_range = unit_ranges.len();
             let mut lang = None;
             let mut have_unit_range = false;
             {
@@ -206,9 +356,28 @@ impl<'dwarf> Units<'dwarf> {
                 }
             }
 
+            if let Some(hot_ranges) = hot_ranges {
+                let hot = unit_ranges[first_range..].iter().any(|unit_range| {
+                    hot_ranges.iter().any(|hot_range| {
+                        hot_range.start < unit_range.range.end
+                            && unit_range.range.begin < hot_range.end
+                    })
+                });
+                if !hot {
+                    let () = unit_ranges.truncate(first_range);
+                    continue
+                }
+            }
+
             res_units.push(Unit::new(dw_unit, lang, lines))
         }
 
+        let () = scope::report(|| Progress::DwarfIndex {
+            units: res_units.len(),
+            bytes: total_bytes,
+            total_bytes,
+        });
+
         // Sort this for faster lookups.
         unit_ranges.sort_by_key(|i| i.range.begin);
 
@@ -224,10 +393,18 @@ impl<'dwarf> Units<'dwarf> {
             dwarf: sections,
             unit_ranges: unit_ranges.into_boxed_slice(),
             units: res_units.into_boxed_slice(),
+            split: SplitDwarf::default(),
         };
         Ok(slf)
     }
 
+    /// Set the [`DwoLocator`] to use for finding split DWARF files
+    /// referenced by skeleton units.
+    pub(crate) fn with_dwo_locator(mut self, locator: DwoLocator) -> Self {
+        self.split = SplitDwarf::new(locator);
+        self
+    }
+
     /// Finds the CUs for the function address given.
     ///
     /// There might be multiple CUs whose range contains this address.
@@ -300,7 +477,7 @@ impl<'dwarf> Units<'dwarf> {
         probe: u64,
     ) -> Result<Option<(&Function<'dwarf>, Option<gimli::DwLang>)>, gimli::Error> {
         for unit in self.find_units(probe) {
-            if let Some(function) = unit.find_function(probe, &self.dwarf)? {
+            if let Some(function) = unit.find_function(probe, &self.dwarf, &self.split)? {
                 return Ok(Some((function, unit.language())))
             }
         }
@@ -320,8 +497,9 @@ impl<'dwarf> Units<'dwarf> {
         gimli::Error,
     > {
         for unit in self.find_units(probe) {
-            if let Some(function) = unit.find_function(probe, &self.dwarf)? {
-                let inlined_fns = function.parse_inlined_functions(unit.dw_unit(), &self.dwarf)?;
+            if let Some(function) = unit.find_function(probe, &self.dwarf, &self.split)? {
+                let (dwarf, dw_unit) = unit.dwarf_and_unit(&self.dwarf, &self.split);
+                let inlined_fns = function.parse_inlined_functions(dw_unit, dwarf)?;
                 let iter = inlined_fns.find_inlined_functions(probe).map(|inlined_fn| {
                     let name = inlined_fn
                         .name
@@ -375,7 +553,142 @@ impl<'dwarf> Units<'dwarf> {
     ) -> impl Iterator<Item = Result<&Function<'dwarf>, gimli::Error>> + 's {
         self.units
             .iter()
-            .filter_map(move |unit| unit.find_name(name, &self.dwarf).transpose())
+            .filter_map(move |unit| unit.find_name(name, &self.dwarf, &self.split).transpose())
+    }
+
+    /// Invoke `f` for each function described by the debug information,
+    /// along with its line information and inlined function calls.
+    pub(crate) fn for_each_function<F>(&self, mut f: F) -> Result<()>
+    where
+        F: FnMut(FnInfo<'_>) -> Result<()>,
+    {
+        for unit in self.units.iter() {
+            let () = scope::check()?;
+            let functions = unit.parse_functions(&self.dwarf, &self.split)?;
+            let lines = unit.parse_lines(&self.dwarf)?;
+            let files = lines.map(|lines| &*lines.files).unwrap_or(&[]);
+
+            for address in functions.addresses.iter() {
+                let function = &functions.functions[address.function];
+                let name = match function.name.as_ref().map(|name| name.to_string()) {
+                    Some(Ok(name)) => name,
+                    _ => continue,
+                };
+                let range = address.range.begin..address.range.end;
+                if range.is_empty() {
+                    continue
+                }
+
+                let (dwarf, dw_unit) = unit.dwarf_and_unit(&self.dwarf, &self.split);
+                let inlined_fns = function.parse_inlined_functions(dw_unit, dwarf)?;
+                let info = FnInfo {
+                    name,
+                    lines: lines
+                        .map(|lines| collect_lines(lines, &range))
+                        .unwrap_or_default(),
+                    inlined: collect_inlined_fns(inlined_fns, files),
+                    range,
+                };
+                let () = f(info)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fold over all functions described by the debug information,
+    /// providing each one's name and address range.
+    ///
+    /// Contrary to [`Units::for_each_function`], neither line
+    /// information nor inlined function calls are parsed.
+    pub(crate) fn fold_functions<F, R>(&self, mut r: R, mut f: F) -> Result<R>
+    where
+        F: FnMut(R, &'dwarf str, Range<u64>) -> R,
+    {
+        for unit in self.units.iter() {
+            let () = scope::check()?;
+            let functions = unit.parse_functions(&self.dwarf, &self.split)?;
+
+            for address in functions.addresses.iter() {
+                let function = &functions.functions[address.function];
+                let name = match function.name.as_ref().map(|name| name.to_string()) {
+                    Some(Ok(name)) => name,
+                    _ => continue,
+                };
+                let range = address.range.begin..address.range.end;
+                if range.is_empty() {
+                    continue
+                }
+                r = f(r, name, range);
+            }
+        }
+        Ok(r)
+    }
+
+    /// Find the address ranges of the code generated for `line` of all
+    /// source files whose path ends in `file`.
+    ///
+    /// Consecutive line table rows attributed to the same line are
+    /// merged into a single range.
+    pub(crate) fn find_line_addrs(
+        &self,
+        file: &Path,
+        line: u32,
+    ) -> Result<Vec<(Range<u64>, Location<'_>)>> {
+        let mut addrs = Vec::new();
+        for unit in self.units.iter() {
+            let () = scope::check()?;
+            let lines = match unit.parse_lines(&self.dwarf)? {
+                Some(lines) => lines,
+                None => continue,
+            };
+
+            // Matching paths is comparably expensive, so do it once per
+            // file and not once per row.
+            let matches = lines
+                .files
+                .iter()
+                .map(|(dir, name)| dir.join(name).ends_with(file))
+                .collect::<Vec<_>>();
+            if !matches.contains(&true) {
+                continue
+            }
+
+            for sequence in lines.sequences.iter() {
+                let mut rows = sequence.rows.iter_from(0).peekable();
+                while let Some(row) = rows.next() {
+                    let matched = usize::try_from(row.file_index)
+                        .ok()
+                        .and_then(|idx| matches.get(idx))
+                        .copied()
+                        .unwrap_or(false);
+                    if row.line != line || !matched {
+                        continue
+                    }
+
+                    let mut end = sequence.end;
+                    while let Some(next) = rows.peek() {
+                        if next.line != row.line || next.file_index != row.file_index {
+                            end = next.address;
+                            break
+                        }
+                        let _next = rows.next();
+                    }
+
+                    // SANITY: We checked that the file index is valid
+                    //         above.
+                    let (dir, file) = src_file(&lines.files, row.file_index).unwrap();
+                    let location = Location {
+                        dir,
+                        file,
+                        line: Some(row.line),
+                        column: (row.column != 0).then_some(row.column),
+                    };
+                    let () = addrs.push((row.address..end, location));
+                }
+            }
+        }
+        let () = addrs.sort_by_key(|(range, _location)| range.start);
+        Ok(addrs)
     }
 
     /// Initialize all function data structures. This is used for benchmarks.
@@ -383,7 +696,7 @@ impl<'dwarf> Units<'dwarf> {
     #[cfg(feature = "nightly")]
     fn parse_functions(&self) -> Result<(), gimli::Error> {
         for unit in self.units.iter() {
-            let _functions = unit.parse_functions(&self.dwarf)?;
+            let _functions = unit.parse_functions(&self.dwarf, &self.split)?;
         }
         Ok(())
     }
@@ -394,7 +707,7 @@ impl<'dwarf> Units<'dwarf> {
     #[cfg(feature = "nightly")]
     fn parse_inlined_functions(&self) -> Result<(), gimli::Error> {
         for unit in self.units.iter() {
-            let _functions = unit.parse_inlined_functions(&self.dwarf)?;
+            let _functions = unit.parse_inlined_functions(&self.dwarf, &self.split)?;
         }
         Ok(())
     }
@@ -451,6 +764,13 @@ mod tests {
             "test-dwarf-v3.bin",
             "test-dwarf-v4.bin",
             "test-dwarf-v5.bin",
+            "test-dwarf-v4-comp-dir.bin",
+            "test-dwarf-v5-comp-dir.bin",
+            "test-dwarf-v4-split.bin",
+            "test-dwarf-v5-split.bin",
+            "test-dwarf-v4-dwp.bin",
+            "test-dwarf-v4-zlib.bin",
+            "test-dwarf-v4-zstd.bin",
         ];
 
         for binary in binaries {
@@ -461,7 +781,9 @@ mod tests {
             let parser = ElfParser::open(bin_name.as_ref()).unwrap();
             let mut load_section = |section| reader::load_section(&parser, section);
             let dwarf = Dwarf::<R>::load(&mut load_section).unwrap();
-            let units = Units::parse(dwarf).unwrap();
+            let units = Units::parse(dwarf, None)
+                .unwrap()
+                .with_dwo_locator(DwoLocator::new(&bin_name));
 
             // Double check that we actually did what we set out to do
             // by checking that we can find a function that we know
@@ -480,6 +802,141 @@ mod tests {
         }
     }
 
+    /// Check that we report the compilation directory for files
+    /// referencing directory index 0, which is implicit before DWARF 5
+    /// and an explicit entry of the directory table starting with it.
+    #[test]
+    fn comp_dir_file() {
+        let data_dir = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+
+        for binary in ["test-dwarf-v4-comp-dir.bin", "test-dwarf-v5-comp-dir.bin"] {
+            let bin_name = data_dir.join(binary);
+            let parser = ElfParser::open(bin_name.as_ref()).unwrap();
+            let mut load_section = |section| reader::load_section(&parser, section);
+            let dwarf = Dwarf::<R>::load(&mut load_section).unwrap();
+            let units = Units::parse(dwarf, None).unwrap();
+
+            let mut funcs = units.find_name("fibonacci");
+            let func = funcs.next().unwrap().unwrap();
+            let addr = func.range.as_ref().unwrap().begin;
+            let loc = units.find_location(addr).unwrap().unwrap();
+            assert_eq!(loc.dir, data_dir, "{binary}");
+            assert_eq!(loc.file, OsStr::new("test-exe.c"));
+            assert_eq!(loc.line.unwrap(), 8);
+        }
+    }
+
+    /// Check that we fall back to the skeleton unit if split DWARF
+    /// files cannot be found.
+    #[test]
+    fn split_dwarf_missing_dwo() {
+        let bin_name = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-dwarf-v4-dwp.bin");
+
+        let parser = ElfParser::open(bin_name.as_ref()).unwrap();
+        let load = || {
+            let mut load_section = |section| reader::load_section(&parser, section);
+            Dwarf::<R>::load(&mut load_section).unwrap()
+        };
+
+        let units = Units::parse(load(), None)
+            .unwrap()
+            .with_dwo_locator(DwoLocator::new(&bin_name));
+        let func = units.find_name("fibonacci").next().unwrap().unwrap();
+        let addr = func.range.as_ref().unwrap().begin;
+
+        // Without a locator we have no way of finding the DWARF
+        // package and the `.dwo` file does not exist.
+        let units = Units::parse(load(), None).unwrap();
+        assert!(units.find_name("fibonacci").next().is_none());
+        assert!(units.find_function(addr).unwrap().is_none());
+        // Line information is still available from the skeleton unit.
+        let loc = units.find_location(addr).unwrap().unwrap();
+        assert_eq!(loc.file, OsStr::new("test-exe.c"));
+    }
+
+    /// Check that we can parse function and line information from DWARF
+    /// 5 as emitted by LLVM based compilers, which reference strings and
+    /// addresses by index.
+    #[test]
+    fn dwarf5_indexed_forms() {
+        let data_dir = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+        let bin_name = data_dir.join("test-rs-dwarf-v5.bin");
+
+        let parser = ElfParser::open(bin_name.as_ref()).unwrap();
+        // Double check that the binary actually uses the sections in
+        // question.
+        for section in [".debug_str_offsets", ".debug_addr", ".debug_line_str"] {
+            assert!(parser.find_section(section).unwrap().is_some(), "{section}");
+        }
+
+        let mut load_section = |section| reader::load_section(&parser, section);
+        let dwarf = Dwarf::<R>::load(&mut load_section).unwrap();
+        let units = Units::parse(dwarf, None).unwrap();
+
+        // Functions are reported by their linkage name.
+        let name = "_RNvCs69hjMPjVIJK_4test13test_function";
+        let mut funcs = units.find_name(name);
+        let func = funcs.next().unwrap().unwrap();
+        assert!(funcs.next().is_none());
+
+        let range = func.range.unwrap();
+        let (func, lang) = units.find_function(range.begin).unwrap().unwrap();
+        assert_eq!(func.name.unwrap().to_string().unwrap(), name);
+        assert_eq!(lang, Some(gimli::DW_LANG_Rust));
+
+        let loc = units.find_location(range.begin).unwrap().unwrap();
+        assert_eq!(loc.dir, data_dir);
+        assert_eq!(loc.file, OsStr::new("test.rs"));
+        assert_eq!(loc.line, Some(26));
+
+        let inlined = (range.begin..range.end)
+            .find_map(|addr| {
+                units
+                    .find_inlined_functions(addr)
+                    .unwrap()
+                    .unwrap()
+                    .map(Result::unwrap)
+                    .find(|(name, _loc)| name.ends_with("inlined_call"))
+            })
+            .unwrap();
+        let loc = inlined.1.unwrap();
+        assert_eq!(loc.dir, data_dir);
+        assert_eq!(loc.file, OsStr::new("test.rs"));
+        assert_eq!(loc.line, Some(27));
+    }
+
+    /// Check that only units overlapping the provided hot ranges are
+    /// retained.
+    #[test]
+    fn hot_range_restriction() {
+        let bin_name = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-stable-addresses.bin");
+        let parser = ElfParser::open(bin_name.as_ref()).unwrap();
+        let load = || {
+            let mut load_section = |section| reader::load_section(&parser, section);
+            Dwarf::<R>::load(&mut load_section).unwrap()
+        };
+
+        let units = Units::parse(load(), None).unwrap();
+        assert_eq!(units.units.len(), 2);
+        assert!(units.find_location(0x2000034).unwrap().is_some());
+
+        // `factorial` resides in a different unit than
+        // `factorial_wrapper`.
+        let units =
+            Units::parse(load(), Some(&[0x2000100..0x2000101, 0x2000213..0x2000214])).unwrap();
+        assert_eq!(units.units.len(), 1);
+        assert!(units.find_location(0x2000100).unwrap().is_some());
+        assert!(units.find_location(0x2000034).unwrap().is_none());
+
+        let units = Units::parse(load(), Some(&[])).unwrap();
+        assert_eq!(units.units.len(), 0);
+        assert_eq!(units.unit_ranges.len(), 0);
+    }
+
     /// Check that we fail to find any data for an address not
     /// represented.
     #[test]
@@ -499,7 +956,7 @@ mod tests {
             let parser = ElfParser::open(bin_name.as_ref()).unwrap();
             let mut load_section = |section| reader::load_section(&parser, section);
             let dwarf = Dwarf::<R>::load(&mut load_section).unwrap();
-            let units = Units::parse(dwarf).unwrap();
+            let units = Units::parse(dwarf, None).unwrap();
 
             // Bogus address typically somewhere in kernel space but
             // unlikely to be in any of our binaries.
@@ -526,7 +983,7 @@ mod tests {
 
         let () = b.iter(|| {
             let dwarf = Dwarf::<R>::load(&mut load_section).unwrap();
-            let units = Units::parse(black_box(dwarf)).unwrap();
+            let units = Units::parse(black_box(dwarf), None).unwrap();
             let _funcs = black_box(units.parse_functions().unwrap());
         });
     }
@@ -557,7 +1014,7 @@ mod tests {
 
         let () = b.iter(|| {
             let dwarf = Dwarf::<R>::load(&mut load_section).unwrap();
-            let units = Units::parse(black_box(dwarf)).unwrap();
+            let units = Units::parse(black_box(dwarf), None).unwrap();
             let _lines = black_box(units.parse_inlined_functions().unwrap());
         });
     }
@@ -588,7 +1045,7 @@ mod tests {
 
         let () = b.iter(|| {
             let dwarf = Dwarf::<R>::load(&mut load_section).unwrap();
-            let units = Units::parse(black_box(dwarf)).unwrap();
+            let units = Units::parse(black_box(dwarf), None).unwrap();
             let _lines = black_box(units.parse_lines().unwrap());
         });
     }
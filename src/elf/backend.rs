@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 #[cfg(feature = "dwarf")]
 use crate::dwarf::DwarfResolver;
@@ -9,6 +9,6 @@ use super::ElfParser;
 #[derive(Clone, Debug)]
 pub(crate) enum ElfBackend {
     #[cfg(feature = "dwarf")]
-    Dwarf(Rc<DwarfResolver>), // ELF w/ DWARF
-    Elf(Rc<ElfParser>), // ELF w/o DWARF
+    Dwarf(Arc<DwarfResolver>), // ELF w/ DWARF
+    Elf(Arc<ElfParser>), // ELF w/o DWARF
 }
@@ -0,0 +1,231 @@
+//! Support for locating separate debug information files, as described
+//! in the "Debugging Information in Separate Files" section of the GDB
+//! manual.
+use std::ffi::OsStr;
+use std::fs::canonicalize;
+use std::fs::read as read_file;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::log;
+use crate::normalize::buildid::BuildId;
+use crate::util::bytes_to_os_str;
+use crate::util::ReadRaw as _;
+use crate::IntoError as _;
+use crate::Result;
+
+use super::ElfParser;
+
+
+/// The directory searched for debug information files by default.
+pub(crate) const DEFAULT_DEBUG_DIR: &str = "/usr/lib/debug";
+
+
+/// Calculate the CRC32 checksum of `data`, as used by the
+/// `.gnu_debuglink` section.
+fn crc32(data: &[u8]) -> u32 {
+    const fn table() -> [u32; 256] {
+        let mut table = [0; 256];
+        let mut i = 0;
+        while i < table.len() {
+            let mut crc = i as u32;
+            let mut j = 0;
+            while j < 8 {
+                crc = if crc & 1 != 0 {
+                    0xedb88320 ^ (crc >> 1)
+                } else {
+                    crc >> 1
+                };
+                j += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    }
+
+    static TABLE: [u32; 256] = table();
+
+    !data.iter().fold(!0u32, |crc, byte| {
+        TABLE[((crc ^ u32::from(*byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+
+/// Read the file name and checksum stored in the `.gnu_debuglink`
+/// section of the ELF file represented by `parser`, if present.
+fn read_debug_link(parser: &ElfParser) -> Result<Option<(&OsStr, u32)>> {
+    let idx = if let Some(idx) = parser.find_section(".gnu_debuglink")? {
+        idx
+    } else {
+        return Ok(None)
+    };
+
+    let data = parser.section_data(idx)?;
+    let mut cursor = data;
+    let name = cursor
+        .read_cstr()
+        .ok_or_invalid_data(|| "failed to read .gnu_debuglink file name")?;
+    // The checksum is aligned to four bytes, relative to the start of
+    // the section.
+    let offset = (name.to_bytes_with_nul().len() + 3) & !3;
+    let crc = data
+        .get(offset..)
+        .and_then(|mut data| data.read_u32())
+        .ok_or_invalid_data(|| "failed to read .gnu_debuglink checksum")?;
+    // The checksum is stored in the file's byte order.
+    let crc = if parser.is_big_endian()? {
+        u32::from_be(crc)
+    } else {
+        u32::from_le(crc)
+    };
+    let name = bytes_to_os_str(name.to_bytes())
+        .ok_or_invalid_data(|| "invalid .gnu_debuglink file name")?;
+    Ok(Some((name, crc)))
+}
+
+
+/// Check whether the file at `path` has the provided CRC32 checksum.
+fn check_crc(path: &Path, crc: u32) -> bool {
+    match read_file(path) {
+        Ok(data) => {
+            let actual = crc32(&data);
+            if actual != crc {
+                log::debug!(
+                    "ignoring debug file {}: checksum {actual:#x} does not match expected {crc:#x}",
+                    path.display()
+                );
+            }
+            actual == crc
+        }
+        Err(_err) => false,
+    }
+}
+
+/// Check whether the ELF file at `path` has the provided build ID.
+fn check_build_id(path: &Path, build_id: &[u8]) -> bool {
+    match BuildId::from_elf_path(&path) {
+        Ok(Some(actual)) => {
+            if actual.id != build_id {
+                log::debug!(
+                    "ignoring debug file {}: build ID does not match",
+                    path.display()
+                );
+            }
+            actual.id == build_id
+        }
+        Ok(None) => false,
+        Err(err) => {
+            log::debug!("failed to read build ID of {}: {err}", path.display());
+            false
+        }
+    }
+}
+
+
+/// Locate the separate debug information file for the ELF file at
+/// `path`, represented by `parser`.
+///
+/// Debug files are searched for
+/// - by build ID, as `<dir>/.build-id/xx/yyy.debug` in each of the
+///   provided debug directories, with `xx` being the first byte of the
+///   build ID and `yyy` the remaining ones, in hexadecimal
+/// - by the name stored in the `.gnu_debuglink` section, in the
+///   directory of the binary, its `.debug/` sub-directory, and, for
+///   each of the debug directories, in the directory of the binary
+///   relative to it
+///
+/// Files found by build ID have to have the same build ID, files found
+/// by debug link have to match its checksum.
+pub(crate) fn find_debug_file(
+    path: &Path,
+    parser: &ElfParser,
+    debug_dirs: &[PathBuf],
+) -> Result<Option<PathBuf>> {
+    if let Some(build_id) = BuildId::from_parser(parser)? {
+        let id = build_id
+            .id
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+        if id.len() > 2 {
+            let (dir, file) = id.split_at(2);
+            for debug_dir in debug_dirs {
+                let candidate = debug_dir
+                    .join(".build-id")
+                    .join(dir)
+                    .join(format!("{file}.debug"));
+                if candidate.is_file() && check_build_id(&candidate, &build_id.id) {
+                    return Ok(Some(candidate))
+                }
+            }
+        }
+    }
+
+    if let Some((name, crc)) = read_debug_link(parser)? {
+        // The provided path may not be the "canonical" one, e.g., when
+        // referring to a file via `/proc/<pid>/map_files/`.
+        let path = canonicalize(path).unwrap_or_else(|_err| path.to_path_buf());
+        let dir = path.parent().unwrap_or_else(|| Path::new("/"));
+        let mut candidates = vec![dir.join(name), dir.join(".debug").join(name)];
+        for debug_dir in debug_dirs {
+            // `join` would discard `debug_dir` for absolute paths.
+            let mut candidate = debug_dir.clone().into_os_string();
+            let () = candidate.push(dir.as_os_str());
+            let () = candidates.push(Path::new(&candidate).join(name));
+        }
+
+        for candidate in candidates {
+            // The debug link may refer to the binary itself, which we
+            // know lacks debug information.
+            if candidate != path && candidate.is_file() && check_crc(&candidate, crc) {
+                return Ok(Some(candidate))
+            }
+        }
+    }
+    Ok(None)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Check that we calculate CRC32 checksums correctly.
+    #[test]
+    fn crc32_calculation() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414fa339
+        );
+    }
+
+    /// Check that we can read the `.gnu_debuglink` section of a binary.
+    #[test]
+    fn debug_link_reading() {
+        let bin = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("libtest-so-dwarf-debuglink.so");
+        let parser = ElfParser::open(&bin).unwrap();
+        let (name, crc) = read_debug_link(&parser).unwrap().unwrap();
+        assert_eq!(name, OsStr::new("libtest-so-dwarf.debug"));
+
+        let debug = bin.with_file_name(name);
+        let data = read_file(&debug).unwrap();
+        assert_eq!(crc32(&data), crc);
+
+        let path = find_debug_file(&bin, &parser, &[]).unwrap().unwrap();
+        assert_eq!(path, canonicalize(debug).unwrap());
+
+        // A binary without debug link.
+        let bin = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("libtest-so-dwarf-stripped.so");
+        let parser = ElfParser::open(&bin).unwrap();
+        assert_eq!(read_debug_link(&parser).unwrap(), None);
+        assert_eq!(find_debug_file(&bin, &parser, &[]).unwrap(), None);
+    }
+}
@@ -0,0 +1,322 @@
+//! Support for ELF files using a byte order different from that of the
+//! host, e.g., when symbolizing addresses captured on a big-endian
+//! s390x system on an x86_64 machine.
+//!
+//! Rather than teaching every consumer of ELF structures about byte
+//! order, we create a copy of such a file in which all structures that
+//! the parser interprets (file, program, and section headers, symbol
+//! tables, relocations, notes, and compression headers) are converted
+//! to host byte order. Other section contents, such as DWARF data, are
+//! left untouched and have to be interpreted according to the file's
+//! byte order.
+
+use std::mem::size_of;
+
+use crate::util::Pod;
+use crate::util::ReadRaw as _;
+
+use super::types::Elf64_Ehdr;
+use super::types::Elf64_Nhdr;
+use super::types::Elf64_Phdr;
+use super::types::Elf64_Rela;
+use super::types::Elf64_Shdr;
+use super::types::Elf64_Sym;
+use super::types::EI_CLASS;
+use super::types::EI_DATA;
+use super::types::ELFCLASS64;
+use super::types::ELFDATA2LSB;
+use super::types::ELFDATA2MSB;
+use super::types::PN_XNUM;
+use super::types::PT_NOTE;
+use super::types::SHF_COMPRESSED;
+use super::types::SHT_DYNSYM;
+use super::types::SHT_NOBITS;
+use super::types::SHT_NOTE;
+use super::types::SHT_RELA;
+use super::types::SHT_SYMTAB;
+
+
+/// The sizes of the members of `Elf64_Ehdr` following `e_ident`.
+const EHDR_FIELDS: &[usize] = &[2, 2, 4, 8, 8, 8, 4, 2, 2, 2, 2, 2, 2];
+/// The sizes of the members of `Elf64_Phdr`.
+const PHDR_FIELDS: &[usize] = &[4, 4, 8, 8, 8, 8, 8, 8];
+/// The sizes of the members of `Elf64_Shdr`.
+const SHDR_FIELDS: &[usize] = &[4, 4, 8, 8, 8, 8, 4, 4, 8, 8];
+/// The sizes of the members of `Elf64_Sym`.
+const SYM_FIELDS: &[usize] = &[4, 1, 1, 2, 8, 8];
+/// The sizes of the members of `Elf64_Rela`.
+const RELA_FIELDS: &[usize] = &[8, 8, 8];
+/// The sizes of the members of `Elf64_Chdr`.
+const CHDR_FIELDS: &[usize] = &[4, 4, 8, 8];
+/// The sizes of the members of `Elf64_Nhdr`.
+const NHDR_FIELDS: &[usize] = &[4, 4, 4];
+
+/// The `EI_DATA` value describing the host's byte order.
+#[cfg(target_endian = "little")]
+const ELFDATA_NATIVE: u8 = ELFDATA2LSB;
+#[cfg(target_endian = "big")]
+const ELFDATA_NATIVE: u8 = ELFDATA2MSB;
+
+
+/// Check whether the provided data represent a 64 bit ELF file using a
+/// byte order different from that of the host.
+pub(super) fn is_foreign(data: &[u8]) -> bool {
+    data.starts_with(b"\x7fELF")
+        && data.get(EI_CLASS) == Some(&ELFCLASS64)
+        && matches!(
+            data.get(EI_DATA),
+            Some(&data) if data != ELFDATA_NATIVE && (data == ELFDATA2LSB || data == ELFDATA2MSB)
+        )
+}
+
+/// Create a copy of the provided foreign byte order ELF data with all
+/// ELF structures converted to host byte order.
+///
+/// Conversion happens on a best-effort basis: malformed data are left
+/// as they are and will be reported as such once parsed.
+pub(super) fn to_native(data: &[u8]) -> Vec<u8> {
+    let mut data = data.to_vec();
+    let _converted = convert(&mut data);
+    data
+}
+
+/// Reverse the byte order of consecutive fields of the given sizes,
+/// starting at `offset`.
+fn swap_fields(data: &mut [u8], offset: usize, fields: &[usize]) -> Option<()> {
+    let mut offset = offset;
+    for size in fields {
+        let end = offset.checked_add(*size)?;
+        let () = data.get_mut(offset..end)?.reverse();
+        offset = end;
+    }
+    Some(())
+}
+
+/// Convert a table of `count` objects of type `T`, with the given field
+/// sizes, starting at `offset`.
+fn swap_table<T>(data: &mut [u8], offset: usize, count: usize, fields: &[usize]) -> Option<()> {
+    debug_assert_eq!(fields.iter().sum::<usize>(), size_of::<T>());
+
+    for idx in 0..count {
+        let offset = idx
+            .checked_mul(size_of::<T>())
+            .and_then(|rel| offset.checked_add(rel))?;
+        let () = swap_fields(data, offset, fields)?;
+    }
+    Some(())
+}
+
+/// Read an object of type `T` at `offset`.
+fn read<T>(data: &[u8], offset: usize) -> Option<T>
+where
+    T: Pod,
+{
+    data.get(offset..)?.read_pod::<T>()
+}
+
+/// Convert the notes contained in `size` bytes starting at `offset`.
+fn swap_notes(data: &mut [u8], offset: usize, size: usize) -> Option<()> {
+    fn align4(value: u32) -> Option<usize> {
+        usize::try_from(value)
+            .ok()?
+            .checked_add(3)
+            .map(|value| value & !3)
+    }
+
+    let end = offset.checked_add(size)?;
+    let mut offset = offset;
+    while offset.checked_add(size_of::<Elf64_Nhdr>())? <= end {
+        let () = swap_fields(data, offset, NHDR_FIELDS)?;
+        let nhdr = read::<Elf64_Nhdr>(data, offset)?;
+        offset = offset
+            .checked_add(size_of::<Elf64_Nhdr>())?
+            .checked_add(align4(nhdr.n_namesz)?)?
+            .checked_add(align4(nhdr.n_descsz)?)?;
+    }
+    Some(())
+}
+
+/// Convert the contents of the section described by `shdr`, to the
+/// extent that we interpret them.
+fn swap_section(data: &mut [u8], shdr: &Elf64_Shdr) -> Option<()> {
+    if shdr.sh_type == SHT_NOBITS {
+        return Some(())
+    }
+
+    let offset = usize::try_from(shdr.sh_offset).ok()?;
+    let size = usize::try_from(shdr.sh_size).ok()?;
+    if shdr.sh_flags & SHF_COMPRESSED != 0 {
+        // Only the compression header is subject to conversion, the
+        // compressed data will be interpreted once decompressed.
+        return swap_fields(data, offset, CHDR_FIELDS)
+    }
+
+    match shdr.sh_type {
+        SHT_SYMTAB | SHT_DYNSYM => {
+            swap_table::<Elf64_Sym>(data, offset, size / size_of::<Elf64_Sym>(), SYM_FIELDS)
+        }
+        SHT_RELA => {
+            swap_table::<Elf64_Rela>(data, offset, size / size_of::<Elf64_Rela>(), RELA_FIELDS)
+        }
+        SHT_NOTE => swap_notes(data, offset, size),
+        _ => Some(()),
+    }
+}
+
+fn convert(data: &mut [u8]) -> Option<()> {
+    let () = swap_fields(data, 16, EHDR_FIELDS)?;
+    let ehdr = read::<Elf64_Ehdr>(data, 0)?;
+
+    let (shoff, shnum, phnum) = if ehdr.e_shoff != 0 {
+        let shoff = usize::try_from(ehdr.e_shoff).ok()?;
+        // The first section header may contain the actual number of
+        // section and program headers (see `ElfParser`).
+        let () = swap_fields(data, shoff, SHDR_FIELDS)?;
+        let shdr = read::<Elf64_Shdr>(data, shoff)?;
+        let shnum = if ehdr.e_shnum == 0 {
+            usize::try_from(shdr.sh_size).ok()?
+        } else {
+            usize::from(ehdr.e_shnum)
+        };
+        let phnum = if ehdr.e_phnum == PN_XNUM {
+            usize::try_from(shdr.sh_info).ok()?
+        } else {
+            usize::from(ehdr.e_phnum)
+        };
+        (shoff, shnum, phnum)
+    } else {
+        (0, 0, usize::from(ehdr.e_phnum))
+    };
+
+    if shnum > 1 {
+        let offset = shoff.checked_add(size_of::<Elf64_Shdr>())?;
+        let () = swap_table::<Elf64_Shdr>(data, offset, shnum - 1, SHDR_FIELDS)?;
+    }
+
+    let phoff = usize::try_from(ehdr.e_phoff).ok()?;
+    if phnum > 0 {
+        let () = swap_table::<Elf64_Phdr>(data, phoff, phnum, PHDR_FIELDS)?;
+    }
+
+    for idx in 1..shnum {
+        let offset = shoff + idx * size_of::<Elf64_Shdr>();
+        // SANITY: We converted all section headers above, so they
+        //         are known to be in bounds.
+        let shdr = read::<Elf64_Shdr>(data, offset).unwrap();
+        // A malformed section should not prevent conversion of the
+        // remaining ones.
+        let _converted = swap_section(data, &shdr);
+    }
+
+    // Note segments typically overlap with note sections, which we
+    // converted already. Only take them into account in the absence
+    // of sections, as is the case for core files.
+    if shnum == 0 {
+        for idx in 0..phnum {
+            let offset = phoff + idx * size_of::<Elf64_Phdr>();
+            // SANITY: We converted all program headers above, so they
+            //         are known to be in bounds.
+            let phdr = read::<Elf64_Phdr>(data, offset).unwrap();
+            if phdr.p_type == PT_NOTE {
+                let _converted = usize::try_from(phdr.p_offset)
+                    .ok()
+                    .zip(usize::try_from(phdr.p_filesz).ok())
+                    .and_then(|(offset, size)| swap_notes(data, offset, size));
+            }
+        }
+    }
+    Some(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::fs::read as read_file;
+    use std::path::Path;
+
+    use crate::elf::types::Elf64_Chdr;
+    use crate::elf::types::EM_AARCH64;
+    use crate::elf::types::EM_S390;
+    use crate::elf::types::ET_REL;
+
+
+    /// Read the test file with the given name.
+    fn read_data(name: &str) -> Vec<u8> {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join(name);
+        read_file(path).unwrap()
+    }
+
+    /// Check that field size tables match the corresponding types.
+    #[test]
+    fn field_sizes() {
+        assert_eq!(
+            EHDR_FIELDS.iter().sum::<usize>() + 16,
+            size_of::<Elf64_Ehdr>()
+        );
+        assert_eq!(PHDR_FIELDS.iter().sum::<usize>(), size_of::<Elf64_Phdr>());
+        assert_eq!(SHDR_FIELDS.iter().sum::<usize>(), size_of::<Elf64_Shdr>());
+        assert_eq!(SYM_FIELDS.iter().sum::<usize>(), size_of::<Elf64_Sym>());
+        assert_eq!(RELA_FIELDS.iter().sum::<usize>(), size_of::<Elf64_Rela>());
+        assert_eq!(CHDR_FIELDS.iter().sum::<usize>(), size_of::<Elf64_Chdr>());
+        assert_eq!(NHDR_FIELDS.iter().sum::<usize>(), size_of::<Elf64_Nhdr>());
+    }
+
+    /// Check that we detect foreign byte order ELF files correctly.
+    #[test]
+    fn foreign_detection() {
+        let data = read_data("test-stable-addresses.bin");
+        assert!(!is_foreign(&data));
+        assert!(!is_foreign(b"\x7fELF"));
+        assert!(!is_foreign(&[]));
+
+        let aarch64 = read_data("test-cross-arch-aarch64.o");
+        let s390x = read_data("test-cross-arch-s390x.o");
+        assert_eq!(is_foreign(&aarch64), cfg!(target_endian = "big"));
+        assert_eq!(is_foreign(&s390x), cfg!(target_endian = "little"));
+    }
+
+    /// Check that we convert ELF structures of a foreign byte order
+    /// file into host byte order.
+    #[test]
+    fn foreign_conversion() {
+        let (data, machine) = if cfg!(target_endian = "little") {
+            (read_data("test-cross-arch-s390x.o"), EM_S390)
+        } else {
+            (read_data("test-cross-arch-aarch64.o"), EM_AARCH64)
+        };
+        let data = to_native(&data);
+        let ehdr = read::<Elf64_Ehdr>(&data, 0).unwrap();
+        assert_eq!(ehdr.e_type, ET_REL);
+        assert_eq!(ehdr.e_machine, machine);
+        assert_eq!(ehdr.e_ehsize as usize, size_of::<Elf64_Ehdr>());
+        assert_eq!(ehdr.e_shentsize as usize, size_of::<Elf64_Shdr>());
+
+        let shoff = ehdr.e_shoff as usize;
+        let shdrs = (1..usize::from(ehdr.e_shnum))
+            .map(|idx| read::<Elf64_Shdr>(&data, shoff + idx * size_of::<Elf64_Shdr>()).unwrap())
+            .collect::<Vec<_>>();
+        let symtab = shdrs
+            .iter()
+            .find(|shdr| shdr.sh_type == SHT_SYMTAB)
+            .unwrap();
+        assert_eq!(symtab.sh_entsize as usize, size_of::<Elf64_Sym>());
+        assert!(shdrs
+            .iter()
+            .filter(|shdr| shdr.sh_type == SHT_RELA)
+            .all(|shdr| shdr.sh_entsize as usize == size_of::<Elf64_Rela>()));
+    }
+
+    /// Make sure that malformed data do not cause trouble.
+    #[test]
+    fn malformed_conversion() {
+        let data = read_data("test-cross-arch-s390x.o");
+        for len in [0, 16, 63, 64, 65, 256, data.len() / 2, data.len() - 1] {
+            let _data = to_native(&data[..len]);
+        }
+    }
+}
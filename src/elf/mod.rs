@@ -1,4 +1,6 @@
 mod backend;
+mod debug_link;
+mod endian;
 mod parser;
 #[cfg_attr(not(feature = "dwarf"), allow(unused_variables))]
 mod resolver;
@@ -6,6 +8,8 @@ mod resolver;
 pub(crate) mod types;
 
 pub(crate) use backend::ElfBackend;
+pub(crate) use debug_link::find_debug_file;
+pub(crate) use debug_link::DEFAULT_DEBUG_DIR;
 pub(crate) use parser::ElfParser;
 pub(crate) use resolver::ElfResolver;
 pub(crate) use resolver::ElfResolverData;
@@ -2,18 +2,28 @@ use std::borrow::Cow;
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
+#[cfg(test)]
 use std::fs::File;
+#[cfg(feature = "zstd")]
+use std::io::Read as _;
 use std::mem;
 use std::ops::Deref as _;
+#[cfg(test)]
 use std::path::Path;
 
 use crate::inspect::FindAddrOpts;
+use crate::inspect::SymBinding;
+use crate::inspect::SymFilter;
 use crate::inspect::SymInfo;
+use crate::inspect::SymTable;
 use crate::inspect::SymType;
+use crate::inspect::SymVisibility;
 use crate::mmap::Mmap;
 use crate::once::OnceCell;
+use crate::scope;
 use crate::symbolize::Reason;
 use crate::util::find_match_or_lower_bound_by_key;
+use crate::util::glob_match;
 use crate::util::ReadRaw as _;
 use crate::Addr;
 use crate::Error;
@@ -21,15 +31,53 @@ use crate::ErrorExt as _;
 use crate::IntoError as _;
 use crate::Result;
 
+use super::endian;
+use super::types::Elf64_Chdr;
 use super::types::Elf64_Ehdr;
 use super::types::Elf64_Phdr;
+use super::types::Elf64_Rela;
 use super::types::Elf64_Shdr;
 use super::types::Elf64_Sym;
+use super::types::EI_DATA;
+use super::types::ELFCOMPRESS_ZLIB;
+use super::types::ELFCOMPRESS_ZSTD;
+use super::types::ELFDATA2MSB;
+use super::types::EM_AARCH64;
+use super::types::EM_S390;
+use super::types::EM_X86_64;
+use super::types::ET_REL;
 use super::types::PN_XNUM;
 use super::types::PT_LOAD;
+use super::types::R_390_32;
+use super::types::R_390_64;
+use super::types::R_390_NONE;
+use super::types::R_AARCH64_ABS32;
+use super::types::R_AARCH64_ABS64;
+use super::types::R_AARCH64_NONE;
+use super::types::R_X86_64_32;
+use super::types::R_X86_64_32S;
+use super::types::R_X86_64_64;
+use super::types::R_X86_64_NONE;
+use super::types::SHF_ALLOC;
+use super::types::SHF_COMPRESSED;
+use super::types::SHN_LORESERVE;
 use super::types::SHN_UNDEF;
 use super::types::SHN_XINDEX;
+use super::types::SHT_RELA;
+use super::types::STB_GLOBAL;
+use super::types::STB_GNU_UNIQUE;
+use super::types::STB_LOCAL;
+use super::types::STB_WEAK;
+use super::types::STT_COMMON;
 use super::types::STT_FUNC;
+use super::types::STT_GNU_IFUNC;
+use super::types::STT_NOTYPE;
+use super::types::STT_OBJECT;
+use super::types::STT_TLS;
+use super::types::STV_DEFAULT;
+use super::types::STV_HIDDEN;
+use super::types::STV_INTERNAL;
+use super::types::STV_PROTECTED;
 
 
 fn symbol_name<'mmap>(strtab: &'mmap [u8], sym: &Elf64_Sym) -> Result<&'mmap str> {
@@ -45,6 +93,22 @@ fn symbol_name<'mmap>(strtab: &'mmap [u8], sym: &Elf64_Sym) -> Result<&'mmap str
     Ok(name)
 }
 
+/// Retrieve the number of bytes patched by a relocation of the given
+/// type on the given machine, or `None` if the relocation is not
+/// supported.
+fn relocation_size(machine: u16, type_: u32) -> Option<usize> {
+    match (machine, type_) {
+        (EM_X86_64, R_X86_64_NONE) | (EM_AARCH64, R_AARCH64_NONE) | (EM_S390, R_390_NONE) => {
+            Some(0)
+        }
+        (EM_X86_64, R_X86_64_32 | R_X86_64_32S)
+        | (EM_AARCH64, R_AARCH64_ABS32)
+        | (EM_S390, R_390_32) => Some(4),
+        (EM_X86_64, R_X86_64_64) | (EM_AARCH64, R_AARCH64_ABS64) | (EM_S390, R_390_64) => Some(8),
+        _ => None,
+    }
+}
+
 fn find_sym<'mmap>(
     symtab: &[&Elf64_Sym],
     strtab: &'mmap [u8],
@@ -93,6 +157,64 @@ struct EhdrExt<'mmap> {
     phnum: usize,
 }
 
+impl EhdrExt<'_> {
+    /// Check whether the ELF file is stored in big-endian byte order.
+    ///
+    /// ELF structures are always provided in host byte order, but
+    /// section contents are stored in this byte order.
+    fn is_big_endian(&self) -> bool {
+        self.ehdr.e_ident[EI_DATA] == ELFDATA2MSB
+    }
+}
+
+
+#[cfg(feature = "zlib")]
+fn decompress_zlib(data: &[u8], size: usize) -> Result<Vec<u8>> {
+    use miniz_oxide::inflate::decompress_to_vec_zlib_with_limit;
+
+    decompress_to_vec_zlib_with_limit(data, size)
+        .map_err(|err| Error::with_invalid_data(err.to_string()))
+        .context("failed to decompress zlib data")
+}
+
+#[cfg(not(feature = "zlib"))]
+fn decompress_zlib(_data: &[u8], _size: usize) -> Result<Vec<u8>> {
+    Err(Error::with_unsupported(
+        "zlib compressed ELF sections require the `zlib` feature",
+    ))
+}
+
+#[cfg(feature = "zstd")]
+fn decompress_zstd(mut data: &[u8], size: usize) -> Result<Vec<u8>> {
+    use ruzstd::StreamingDecoder;
+
+    // The size is read from the file and may be bogus, so we do not
+    // preallocate based on it.
+    let mut decompressed = Vec::new();
+    let _cnt = StreamingDecoder::new(&mut data)
+        .map_err(|err| Error::with_invalid_data(err.to_string()))
+        .context("failed to decompress zstd data")?
+        // Read one more byte than expected, so that we can detect
+        // size mismatches.
+        .take((size as u64).saturating_add(1))
+        .read_to_end(&mut decompressed)
+        .context("failed to decompress zstd data")?;
+    Ok(decompressed)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress_zstd(_data: &[u8], _size: usize) -> Result<Vec<u8>> {
+    Err(Error::with_unsupported(
+        "zstd compressed ELF sections require the `zstd` feature",
+    ))
+}
+
+
+/// The decompressed data of a compressed section.
+type DecompressedSection = OnceCell<Box<[u8]>>;
+/// The relocated data of a section, if it is subject to relocation.
+type RelocatedSection = OnceCell<Option<Box<[u8]>>>;
+
 
 struct Cache<'mmap> {
     /// A slice of the raw ELF data that we are about to parse.
@@ -108,6 +230,12 @@ struct Cache<'mmap> {
     /// The cached ELF string table.
     strtab: OnceCell<&'mmap [u8]>,
     str2symtab: OnceCell<Box<[(&'mmap str, usize)]>>, // strtab offset to symtab in the dictionary order
+    /// Decompressed copies of compressed sections, indexed by
+    /// section.
+    decompressed: OnceCell<Box<[DecompressedSection]>>,
+    /// Copies of non-allocated sections of a relocatable object with
+    /// relocations applied, indexed by section.
+    relocated: OnceCell<Box<[RelocatedSection]>>,
 }
 
 impl<'mmap> Cache<'mmap> {
@@ -122,6 +250,8 @@ impl<'mmap> Cache<'mmap> {
             symtab: OnceCell::new(),
             strtab: OnceCell::new(),
             str2symtab: OnceCell::new(),
+            decompressed: OnceCell::new(),
+            relocated: OnceCell::new(),
         }
     }
 
@@ -133,6 +263,15 @@ impl<'mmap> Cache<'mmap> {
             .get(idx)
             .ok_or_invalid_input(|| format!("ELF section index ({idx}) out of bounds"))?;
 
+        if let Some(limits) = scope::limits() {
+            if section.sh_size > limits.max_section_size {
+                return Err(Error::with_invalid_data(format!(
+                    "ELF section {idx} exceeds size limit ({} > {})",
+                    section.sh_size, limits.max_section_size
+                )))
+            }
+        }
+
         let data = self
             .elf_data
             .get(section.sh_offset as usize..)
@@ -168,7 +307,7 @@ impl<'mmap> Cache<'mmap> {
             && ehdr.e_ident[2] == b'L'
             && ehdr.e_ident[3] == b'F')
         {
-            return Err(Error::with_invalid_data(format!(
+            return Err(Error::with_unsupported_format(format!(
                 "encountered unexpected e_ident: {:x?}",
                 &ehdr.e_ident[0..4]
             )))
@@ -179,13 +318,19 @@ impl<'mmap> Cache<'mmap> {
         // number of entries in the section header table is held in the sh_size
         // member of the initial entry in section header table."
         let shnum = if ehdr.e_shnum == 0 {
-            let shdr = self.read_first_shdr(ehdr)?;
-            usize::try_from(shdr.sh_size).ok().ok_or_invalid_data(|| {
-                format!(
-                    "ELF file contains unsupported number of sections ({})",
-                    shdr.sh_size
-                )
-            })?
+            // Files without a section header table, such as core
+            // files, have `e_shoff` set to zero.
+            if ehdr.e_shoff == 0 {
+                0
+            } else {
+                let shdr = self.read_first_shdr(ehdr)?;
+                usize::try_from(shdr.sh_size).ok().ok_or_invalid_data(|| {
+                    format!(
+                        "ELF file contains unsupported number of sections ({})",
+                        shdr.sh_size
+                    )
+                })?
+            }
         } else {
             ehdr.e_shnum.into()
         };
@@ -406,6 +551,162 @@ impl<'mmap> Cache<'mmap> {
             .deref();
         Ok(str2symtab)
     }
+
+    /// Create a copy of the section at index `idx` with all
+    /// relocations targeting it applied, if there are any.
+    ///
+    /// Symbols values are section relative in relocatable objects and,
+    /// hence, so are the resulting addresses.
+    fn relocate_section(&self, idx: usize) -> Result<Option<Box<[u8]>>> {
+        let ehdr = self.ensure_ehdr()?;
+        let shdrs = self.ensure_shdrs()?;
+        let mut data = None;
+
+        for (rela_idx, shdr) in shdrs.iter().enumerate() {
+            if shdr.sh_type != SHT_RELA || shdr.sh_info as usize != idx {
+                continue
+            }
+
+            let data = match &mut data {
+                Some(data) => data,
+                None => data.insert(self.uncompressed_section_data(idx)?.to_vec()),
+            };
+
+            let mut syms = self.section_data(shdr.sh_link as usize)?;
+            let syms = syms
+                .read_pod_slice_ref::<Elf64_Sym>(syms.len() / mem::size_of::<Elf64_Sym>())
+                .ok_or_invalid_data(|| "failed to read symbol table contents")?;
+            let mut relas = self.section_data(rela_idx)?;
+            let relas = relas
+                .read_pod_slice_ref::<Elf64_Rela>(relas.len() / mem::size_of::<Elf64_Rela>())
+                .ok_or_invalid_data(|| "failed to read relocation section contents")?;
+
+            for rela in relas {
+                let size = relocation_size(ehdr.ehdr.e_machine, rela.type_()).ok_or_else(|| {
+                    Error::with_unsupported(format!(
+                        "ELF relocation type {} on machine {} is unsupported",
+                        rela.type_(),
+                        ehdr.ehdr.e_machine
+                    ))
+                })?;
+                if size == 0 {
+                    continue
+                }
+
+                let sym = syms.get(rela.sym() as usize).ok_or_invalid_data(|| {
+                    format!("ELF relocation symbol index ({}) out of bounds", rela.sym())
+                })?;
+                let value = sym.st_value.wrapping_add(rela.r_addend as u64);
+                // Section contents are stored in the file's byte order.
+                let (be, le) = (value.to_be_bytes(), value.to_le_bytes());
+                let bytes = if ehdr.is_big_endian() {
+                    &be[be.len() - size..]
+                } else {
+                    &le[..size]
+                };
+
+                let offset = rela.r_offset as usize;
+                let () = data
+                    .get_mut(offset..offset + size)
+                    .ok_or_invalid_data(|| {
+                        format!("ELF relocation offset ({offset:#x}) out of bounds")
+                    })?
+                    .copy_from_slice(bytes);
+            }
+        }
+        Ok(data.map(Vec::into_boxed_slice))
+    }
+
+    /// Decompress the data of the compressed section at index `idx`.
+    fn decompress_section(&self, idx: usize) -> Result<Box<[u8]>> {
+        let mut data = self.section_data(idx)?;
+        let chdr = data.read_pod::<Elf64_Chdr>().ok_or_invalid_data(|| {
+            format!("failed to read ELF section {idx} compression header")
+        })?;
+
+        if let Some(limits) = scope::limits() {
+            if chdr.ch_size > limits.max_section_size {
+                return Err(Error::with_invalid_data(format!(
+                    "decompressed ELF section {idx} exceeds size limit ({} > {})",
+                    chdr.ch_size, limits.max_section_size
+                )))
+            }
+        }
+
+        let size = usize::try_from(chdr.ch_size).ok().ok_or_invalid_data(|| {
+            format!("ELF section {idx} size ({}) is too large", chdr.ch_size)
+        })?;
+        let decompressed = match chdr.ch_type {
+            ELFCOMPRESS_ZLIB => decompress_zlib(data, size),
+            ELFCOMPRESS_ZSTD => decompress_zstd(data, size),
+            ty => Err(Error::with_unsupported(format!(
+                "ELF section compression type {ty} is unsupported"
+            ))),
+        }
+        .with_context(|| format!("failed to decompress ELF section {idx}"))?;
+
+        if decompressed.len() != size {
+            return Err(Error::with_invalid_data(format!(
+                "decompressed ELF section {idx} has unexpected size ({} != {size})",
+                decompressed.len()
+            )))
+        }
+        Ok(decompressed.into_boxed_slice())
+    }
+
+    /// Retrieve the data of the section at index `idx`, decompressing
+    /// it if necessary.
+    ///
+    /// Decompression happens lazily, on first access to a compressed
+    /// section.
+    fn uncompressed_section_data(&self, idx: usize) -> Result<&[u8]> {
+        let shdrs = self.ensure_shdrs()?;
+        let shdr = shdrs
+            .get(idx)
+            .ok_or_invalid_input(|| format!("ELF section index ({idx}) out of bounds"))?;
+        if shdr.sh_flags & SHF_COMPRESSED == 0 {
+            return self.section_data(idx)
+        }
+
+        let decompressed = self
+            .decompressed
+            .get_or_init(|| shdrs.iter().map(|_| OnceCell::new()).collect());
+        // SANITY: `decompressed` has one entry per section and we
+        //         checked `idx` above.
+        let data = decompressed[idx].get_or_try_init(|| self.decompress_section(idx))?;
+        Ok(data)
+    }
+
+    /// Retrieve the data of the section at index `idx` with relocations
+    /// applied, if the ELF file is a relocatable object and the section
+    /// is subject to relocation.
+    ///
+    /// Only sections not allocated at run time, such as those containing
+    /// debug information, are considered.
+    fn relocated_section_data(&self, idx: usize) -> Result<Option<&[u8]>> {
+        let ehdr = self.ensure_ehdr()?;
+        if ehdr.ehdr.e_type != ET_REL {
+            return Ok(None)
+        }
+
+        let shdrs = self.ensure_shdrs()?;
+        let shdr = shdrs
+            .get(idx)
+            .ok_or_invalid_input(|| format!("ELF section index ({idx}) out of bounds"))?;
+        if shdr.sh_flags & SHF_ALLOC != 0 {
+            return Ok(None)
+        }
+
+        let relocated = self
+            .relocated
+            .get_or_init(|| shdrs.iter().map(|_| OnceCell::new()).collect());
+        // SANITY: `relocated` has one entry per section and we checked
+        //         `idx` above.
+        let data = relocated[idx]
+            .get_or_try_init(|| self.relocate_section(idx))?
+            .as_deref();
+        Ok(data)
+    }
 }
 
 impl Debug for Cache<'_> {
@@ -430,6 +731,7 @@ pub(crate) struct ElfParser {
 
 impl ElfParser {
     /// Create an `ElfParser` from an open file.
+    #[cfg(test)]
     pub fn open_file(file: &File) -> Result<ElfParser> {
         Mmap::map(file)
             .map(Self::from_mmap)
@@ -437,7 +739,16 @@ impl ElfParser {
     }
 
     /// Create an `ElfParser` from mmap'ed data.
+    ///
+    /// Files using a byte order different from that of the host are
+    /// converted up front and, hence, copied into memory.
     pub fn from_mmap(mmap: Mmap) -> ElfParser {
+        let mmap = if endian::is_foreign(&mmap) {
+            Mmap::from_vec(endian::to_native(&mmap))
+        } else {
+            mmap
+        };
+
         // We transmute the mmap's lifetime to static here as that is a
         // necessity for self-referentiality.
         // SAFETY: We never hand out any 'static references to cache
@@ -452,6 +763,7 @@ impl ElfParser {
     }
 
     /// Create an `ElfParser` for a path.
+    #[cfg(test)]
     pub fn open(filename: &Path) -> Result<ElfParser> {
         let file = File::open(filename)
             .with_context(|| format!("failed to open {}", filename.display()))?;
@@ -459,8 +771,26 @@ impl ElfParser {
     }
 
     /// Retrieve the data corresponding to the ELF section at index `idx`.
+    ///
+    /// Compressed sections (`SHF_COMPRESSED`) are decompressed
+    /// transparently. For relocatable objects, relocations are applied
+    /// to sections not allocated at run time (such as those containing
+    /// debug information), with addresses being relative to the section
+    /// they refer to.
     pub fn section_data(&self, idx: usize) -> Result<&[u8]> {
-        self.cache.section_data(idx)
+        if let Some(data) = self.cache.relocated_section_data(idx)? {
+            return Ok(data)
+        }
+        self.cache.uncompressed_section_data(idx)
+    }
+
+    /// Check whether the ELF file is stored in big-endian byte order.
+    ///
+    /// Section data, as returned by [`ElfParser::section_data`], have to
+    /// be interpreted in this byte order.
+    pub fn is_big_endian(&self) -> Result<bool> {
+        let ehdr = self.cache.ensure_ehdr()?;
+        Ok(ehdr.is_big_endian())
     }
 
     /// Find the section of a given name.
@@ -539,6 +869,7 @@ impl ElfParser {
                                 .then(|| self.file_offset(shdrs, sym_ref))
                                 .transpose()?,
                             obj_file_name: None,
+                            section: None,
                         });
                     }
                 }
@@ -583,6 +914,7 @@ impl ElfParser {
                         .then(|| self.file_offset(shdrs, sym))
                         .transpose()?,
                     obj_file_name: None,
+                    section: None,
                 };
                 r = f(r, &sym_info)
             }
@@ -591,6 +923,150 @@ impl ElfParser {
         }
     }
 
+    /// Perform an operation on each defined symbol of an ELF symbol
+    /// table that matches `filter`.
+    ///
+    /// Symbols are reported in symbol table order.
+    pub(crate) fn for_each_filtered_sym<F, R>(
+        &self,
+        filter: &SymFilter,
+        offset_in_file: bool,
+        mut r: R,
+        mut f: F,
+    ) -> Result<R>
+    where
+        F: FnMut(R, &SymInfo<'_>) -> R,
+    {
+        let SymFilter {
+            sym_type,
+            binding,
+            visibility,
+            name,
+            table,
+            _non_exhaustive: (),
+        } = filter;
+
+        let idx = match table {
+            None => match self.cache.find_section(".symtab")? {
+                Some(idx) => Some(idx),
+                None => self.cache.find_section(".dynsym")?,
+            },
+            Some(SymTable::Symtab) => self.cache.find_section(".symtab")?,
+            Some(SymTable::Dynsym) => self.cache.find_section(".dynsym")?,
+            Some(SymTable::Dwarf) => {
+                return Err(Error::with_unsupported(
+                    "DWARF symbols are not contained in an ELF symbol table",
+                ))
+            }
+        };
+        let idx = match idx {
+            Some(idx) => idx,
+            None => return Ok(r),
+        };
+
+        let shdrs = self.cache.ensure_shdrs()?;
+        // SANITY: `find_section` only reports valid indexes.
+        let shdr = &shdrs[idx];
+        let strtab = self.cache.section_data(shdr.sh_link as usize)?;
+        let mut data = self.cache.section_data(idx)?;
+        if data.len() % mem::size_of::<Elf64_Sym>() != 0 {
+            return Err(Error::with_invalid_data(
+                "size of symbol table section is invalid",
+            ))
+        }
+        let count = data.len() / mem::size_of::<Elf64_Sym>();
+        let syms = data
+            .read_pod_slice_ref::<Elf64_Sym>(count)
+            .ok_or_invalid_data(|| "failed to read symbol table contents")?;
+
+        for sym in syms {
+            if sym.st_shndx == SHN_UNDEF {
+                continue
+            }
+
+            let type_ = match sym.type_() {
+                STT_FUNC | STT_GNU_IFUNC => SymType::Function,
+                STT_OBJECT | STT_COMMON | STT_TLS => SymType::Variable,
+                STT_NOTYPE => SymType::Unknown,
+                // Section and file symbols, among others, don't
+                // describe anything of interest.
+                _ => continue,
+            };
+            if *sym_type != SymType::Unknown && type_ != *sym_type {
+                continue
+            }
+
+            if let Some(binding) = binding {
+                let sym_binding = match sym.binding() {
+                    STB_LOCAL => Some(SymBinding::Local),
+                    STB_GLOBAL | STB_GNU_UNIQUE => Some(SymBinding::Global),
+                    STB_WEAK => Some(SymBinding::Weak),
+                    _ => None,
+                };
+                if sym_binding != Some(*binding) {
+                    continue
+                }
+            }
+
+            if let Some(visibility) = visibility {
+                let sym_visibility = match sym.visibility() {
+                    STV_DEFAULT => SymVisibility::Default,
+                    STV_INTERNAL => SymVisibility::Internal,
+                    STV_HIDDEN => SymVisibility::Hidden,
+                    STV_PROTECTED => SymVisibility::Protected,
+                    _ => unreachable!(),
+                };
+                if sym_visibility != *visibility {
+                    continue
+                }
+            }
+
+            let sym_name = symbol_name(strtab, sym)?;
+            if let Some(pattern) = name {
+                if !glob_match(pattern, sym_name) {
+                    continue
+                }
+            }
+
+            // Symbols with a special section index (e.g., absolute
+            // ones) are not associated with any section.
+            let section = if sym.st_shndx < SHN_LORESERVE {
+                Some(self.cache.section_name(usize::from(sym.st_shndx))?)
+            } else {
+                None
+            };
+
+            let sym_info = SymInfo {
+                name: Cow::Borrowed(sym_name),
+                addr: sym.st_value as Addr,
+                size: sym.st_size as usize,
+                sym_type: type_,
+                file_offset: (offset_in_file && section.is_some())
+                    .then(|| self.file_offset(shdrs, sym))
+                    .transpose()?,
+                obj_file_name: None,
+                section: section.map(Cow::Borrowed),
+            };
+            r = f(r, &sym_info)
+        }
+        Ok(r)
+    }
+
+    /// Find the name of the allocated section containing `addr`, if
+    /// any.
+    pub(crate) fn find_section_name_by_addr(&self, addr: Addr) -> Result<Option<&str>> {
+        let shdrs = self.cache.ensure_shdrs()?;
+        for (idx, shdr) in shdrs.iter().enumerate().skip(1) {
+            if shdr.sh_flags & SHF_ALLOC != 0
+                && (shdr.sh_addr..shdr.sh_addr.saturating_add(shdr.sh_size)).contains(&addr)
+            {
+                let name = self.cache.section_name(idx)?;
+                return Ok(Some(name))
+            }
+        }
+        Ok(None)
+    }
+
     /// Find the file offset of the symbol at address `addr`.
     // If possible, use the constant-time [`file_offset`][Self::file_offset]
     // method instead.
@@ -620,6 +1096,19 @@ impl ElfParser {
         Ok(phdrs)
     }
 
+    /// Retrieve the type of the ELF file, e.g., `ET_DYN`.
+    pub(crate) fn file_type(&self) -> Result<u16> {
+        let ehdr = self.cache.ensure_ehdr()?;
+        Ok(ehdr.ehdr.e_type)
+    }
+
+    /// Retrieve the machine architecture of the ELF file, e.g.,
+    /// `EM_X86_64`.
+    pub(crate) fn machine(&self) -> Result<u16> {
+        let ehdr = self.cache.ensure_ehdr()?;
+        Ok(ehdr.ehdr.e_machine)
+    }
+
     pub(crate) fn program_headers(&self) -> Result<&[Elf64_Phdr]> {
         let phdrs = self.cache.ensure_phdrs()?;
         Ok(phdrs)
@@ -664,6 +1153,8 @@ mod tests {
 
     use test_log::test;
 
+    use crate::ErrorKind;
+
 
     /// Exercise the `Debug` representation of various types.
     #[test]
@@ -767,7 +1258,7 @@ mod tests {
 
         let parser = ElfParser::open_file(&file).unwrap();
         let ehdr = parser.cache.ensure_ehdr().unwrap();
-        assert_eq!(ehdr.shnum, SHNUM.into());
+        assert_eq!(ehdr.shnum, usize::from(SHNUM));
         assert_eq!(ehdr.phnum, usize::try_from(PHNUM).unwrap());
     }
 
@@ -825,7 +1316,7 @@ mod tests {
         let parser = ElfParser::open_file(&file).unwrap();
         let ehdr = parser.cache.ensure_ehdr().unwrap();
         let shstrndx = parser.cache.shstrndx(ehdr.ehdr).unwrap();
-        assert_eq!(shstrndx, SHSTRNDX.into());
+        assert_eq!(shstrndx, usize::from(SHSTRNDX));
     }
 
 
@@ -893,6 +1384,96 @@ mod tests {
             .unwrap();
     }
 
+    /// Check that relocations are applied to debug sections of
+    /// relocatable object files.
+    #[test]
+    fn relocatable_object_sections() {
+        let obj = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-stable-addresses-cu2.o");
+
+        let parser = ElfParser::open(obj.as_ref()).unwrap();
+        let idx = parser.find_section(".debug_info").unwrap().unwrap();
+        let relocated = parser.section_data(idx).unwrap();
+        let raw = parser.cache.section_data(idx).unwrap();
+        assert_eq!(relocated.len(), raw.len());
+        assert_ne!(relocated, raw);
+
+        // Allocated sections are reported as-is.
+        let idx = parser.find_section(".text").unwrap().unwrap();
+        let text = parser.section_data(idx).unwrap();
+        assert_eq!(text, parser.cache.section_data(idx).unwrap());
+    }
+
+    /// Check that compressed sections are decompressed transparently.
+    #[test]
+    fn compressed_sections() {
+        let data = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+        let parser = ElfParser::open(&data.join("test-dwarf-v4.bin")).unwrap();
+        let idx = parser.find_section(".debug_info").unwrap().unwrap();
+        let expected = parser.section_data(idx).unwrap();
+
+        for file in ["test-dwarf-v4-zlib.bin", "test-dwarf-v4-zstd.bin"] {
+            let parser = ElfParser::open(&data.join(file)).unwrap();
+            let idx = parser.find_section(".debug_info").unwrap().unwrap();
+            let raw = parser.cache.section_data(idx).unwrap();
+            assert_ne!(raw, expected);
+
+            let decompressed = parser.section_data(idx).unwrap();
+            assert_eq!(decompressed, expected);
+            // Decompressed data should be cached.
+            assert_eq!(
+                parser.section_data(idx).unwrap().as_ptr(),
+                decompressed.as_ptr()
+            );
+        }
+
+        // Relocations should be applied to the decompressed data.
+        let parser = ElfParser::open(&data.join("test-stable-addresses-cu2.o")).unwrap();
+        let idx = parser.find_section(".debug_info").unwrap().unwrap();
+        let expected = parser.section_data(idx).unwrap();
+
+        let parser = ElfParser::open(&data.join("test-stable-addresses-cu2-zlib.o")).unwrap();
+        let idx = parser.find_section(".debug_info").unwrap().unwrap();
+        assert_eq!(parser.section_data(idx).unwrap(), expected);
+    }
+
+    /// Check that the decompressed size stated by a zstd compressed
+    /// section is not trusted for allocating memory.
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_section_bogus_size() {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-dwarf-v4-zstd.bin");
+        let parser = ElfParser::open(&path).unwrap();
+        let idx = parser.find_section(".debug_info").unwrap().unwrap();
+        let mut raw = parser.cache.section_data(idx).unwrap();
+        let chdr = raw.read_pod::<Elf64_Chdr>().unwrap();
+
+        let decompressed = decompress_zstd(raw, usize::MAX).unwrap();
+        assert_eq!(decompressed.len() as u64, chdr.ch_size);
+    }
+
+    /// Check that we honor the section size limit for decompressed
+    /// sections.
+    #[test]
+    fn compressed_section_size_limit() {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-dwarf-v4-zlib.bin");
+        let parser = ElfParser::open(&path).unwrap();
+        let idx = parser.find_section(".debug_info").unwrap().unwrap();
+        let raw = parser.cache.section_data(idx).unwrap();
+
+        let limits = scope::Limits {
+            max_section_size: raw.len() as u64,
+            ..Default::default()
+        };
+        let err = scope::with_limits(&limits, || parser.section_data(idx)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
     /// Make sure that we can look up a symbol in an ELF file.
     #[test]
     fn lookup_symbol() {
@@ -2,20 +2,28 @@ use std::borrow::Cow;
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
+use std::ops::Range;
 use std::path::Path;
 use std::path::PathBuf;
-use std::rc::Rc;
+use std::sync::Arc;
 
 #[cfg(feature = "dwarf")]
 use crate::dwarf::DwarfResolver;
 use crate::file_cache::FileCache;
+use crate::insert_map::InsertMap;
 use crate::inspect::FindAddrOpts;
 use crate::inspect::SymInfo;
+use crate::log;
+use crate::mmap::Advice;
+use crate::normalize::buildid::BuildId;
 use crate::once::OnceCell;
 use crate::symbolize::AddrCodeInfo;
+use crate::symbolize::IntModule;
 use crate::symbolize::IntSym;
 use crate::symbolize::Reason;
 use crate::symbolize::SrcLang;
+use crate::vfs::FileAccess;
+use crate::vfs::FileData;
 use crate::Addr;
 use crate::Error;
 use crate::Result;
@@ -27,22 +35,89 @@ use super::ElfParser;
 
 
 /// Resolver data associated with a specific source.
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub(crate) struct ElfResolverData {
     /// A bare-bones ELF resolver.
-    pub elf: OnceCell<Rc<ElfResolver>>,
+    pub elf: OnceCell<Arc<ElfResolver>>,
     /// An ELF resolver with debug information enabled.
-    pub dwarf: OnceCell<Rc<ElfResolver>>,
+    pub dwarf: OnceCell<Arc<ElfResolver>>,
+    /// ELF resolvers with debug information restricted to certain
+    /// address ranges, keyed by these (sorted and merged) ranges.
+    pub hot: InsertMap<Box<[Range<Addr>]>, ElfResolver>,
+}
+
+
+/// Sort and merge the provided address ranges, dropping empty ones.
+fn normalize_ranges(ranges: &[Range<Addr>]) -> Box<[Range<Addr>]> {
+    let mut ranges = ranges
+        .iter()
+        .filter(|range| !range.is_empty())
+        .cloned()
+        .collect::<Vec<_>>();
+    let () = ranges.sort_by_key(|range| range.start);
+    let () = ranges.dedup_by(|next, prev| {
+        if next.start <= prev.end {
+            prev.end = prev.end.max(next.end);
+            true
+        } else {
+            false
+        }
+    });
+    ranges.into_boxed_slice()
 }
 
 impl FileCache<ElfResolverData> {
-    pub(crate) fn elf_resolver<'slf>(
-        &'slf self,
+    /// Create an `ElfResolver` for the ELF file represented by `file`.
+    fn create_elf_resolver(
+        &self,
+        path: &Path,
+        file: &FileData,
+        parser: Arc<ElfParser>,
+        debug_syms: bool,
+        code_info: bool,
+        hot_ranges: Option<&[Range<Addr>]>,
+    ) -> Result<ElfResolver> {
+        // Indexing of debug information reads the corresponding
+        // sections front to back.
+        let sequential = debug_syms
+            && self
+                .advice()
+                .map(|advice| advice.sequential_indexing && advice.applies(file.mmap()))
+                .unwrap_or(false);
+        let advise = |advice| {
+            if let Err(err) = file.mmap().advise(advice) {
+                log::debug!(
+                    "failed to apply {advice:?} advice to {}: {err}",
+                    path.display()
+                );
+            }
+        };
+
+        if sequential {
+            let () = advise(Advice::Sequential);
+        }
+        let result = ElfResolver::from_parser(
+            path,
+            parser,
+            self.file_access(),
+            debug_syms,
+            code_info,
+            hot_ranges,
+        );
+        if sequential {
+            let () = advise(Advice::Normal);
+        }
+        result
+    }
+
+    pub(crate) fn elf_resolver(
+        &self,
         path: &Path,
         debug_syms: bool,
         code_info: bool,
-    ) -> Result<&'slf Rc<ElfResolver>> {
-        let (file, cell) = self.entry(path)?;
+    ) -> Result<Arc<ElfResolver>> {
+        let entry = self.entry(path)?;
+        let (file, cell) = (entry.data(), entry.value());
         let resolver = if let Some(data) = cell.get() {
             if debug_syms {
                 data.dwarf.get_or_try_init(|| {
@@ -51,8 +126,9 @@ impl FileCache<ElfResolverData> {
                     //         initializing the `dwarf` part of it, the
                     //         `elf` part *must* be present.
                     let parser = data.elf.get().unwrap().parser().clone();
-                    let resolver = ElfResolver::from_parser(path, parser, debug_syms, code_info)?;
-                    let resolver = Rc::new(resolver);
+                    let resolver =
+                        self.create_elf_resolver(path, file, parser, debug_syms, code_info, None)?;
+                    let resolver = Arc::new(resolver);
                     Result::<_, Error>::Ok(resolver)
                 })?
             } else {
@@ -62,16 +138,18 @@ impl FileCache<ElfResolverData> {
                     //         initializing the `elf` part of it, the
                     //         `dwarf` part *must* be present.
                     let parser = data.dwarf.get().unwrap().parser().clone();
-                    let resolver = ElfResolver::from_parser(path, parser, debug_syms, code_info)?;
-                    let resolver = Rc::new(resolver);
+                    let resolver =
+                        self.create_elf_resolver(path, file, parser, debug_syms, code_info, None)?;
+                    let resolver = Arc::new(resolver);
                     Result::<_, Error>::Ok(resolver)
                 })?
             }
             .clone()
         } else {
-            let parser = Rc::new(ElfParser::open_file(file)?);
-            let resolver = ElfResolver::from_parser(path, parser, debug_syms, code_info)?;
-            Rc::new(resolver)
+            let parser = Arc::new(ElfParser::from_mmap(file.mmap().clone()));
+            let resolver =
+                self.create_elf_resolver(path, file, parser, debug_syms, code_info, None)?;
+            Arc::new(resolver)
         };
 
         let data = cell.get_or_init(|| {
@@ -79,11 +157,13 @@ impl FileCache<ElfResolverData> {
                 ElfResolverData {
                     dwarf: OnceCell::from(resolver),
                     elf: OnceCell::new(),
+                    hot: InsertMap::new(),
                 }
             } else {
                 ElfResolverData {
                     dwarf: OnceCell::new(),
                     elf: OnceCell::from(resolver),
+                    hot: InsertMap::new(),
                 }
             }
         });
@@ -94,7 +174,36 @@ impl FileCache<ElfResolverData> {
             data.elf.get()
         };
         // SANITY: We made sure to create the desired resolver above.
-        Ok(resolver.unwrap())
+        Ok(resolver.unwrap().clone())
+    }
+
+    /// Retrieve the ELF resolver with debug information for the file
+    /// at `path`, if it has been created already.
+    pub(crate) fn indexed_elf_resolver(&self, path: &Path) -> Result<Option<Arc<ElfResolver>>> {
+        let entry = self.entry(path)?;
+        let resolver = entry.value().get().and_then(|data| data.dwarf.get());
+        Ok(resolver.cloned())
+    }
+
+    /// Retrieve an ELF resolver with debug information for the file at
+    /// `path`, only indexing debug information overlapping `ranges`.
+    pub(crate) fn hot_elf_resolver(
+        &self,
+        path: &Path,
+        ranges: &[Range<Addr>],
+        code_info: bool,
+    ) -> Result<Arc<ElfResolver>> {
+        // Make sure that resolver data are present. Creating a
+        // bare-bones ELF resolver is cheap.
+        let parser = self.elf_resolver(path, false, code_info)?.parser().clone();
+        let entry = self.entry(path)?;
+        // SANITY: We just made sure that the cell is initialized.
+        let data = entry.value().get().unwrap();
+        let ranges = normalize_ranges(ranges);
+
+        data.hot.get_or_try_insert(ranges.clone(), || {
+            self.create_elf_resolver(path, entry.data(), parser, true, code_info, Some(&ranges))
+        })
     }
 }
 
@@ -111,6 +220,12 @@ impl FileCache<ElfResolverData> {
 pub struct ElfResolver {
     backend: ElfBackend,
     file_name: PathBuf,
+    /// The parser for the binary that symbols are reported for, if
+    /// different from the one backing `backend` (e.g., when the
+    /// latter represents a separate debug information file).
+    binary: Option<Arc<ElfParser>>,
+    /// The lazily read build ID of the binary.
+    build_id: OnceCell<Option<Vec<u8>>>,
 }
 
 impl ElfResolver {
@@ -118,19 +233,38 @@ impl ElfResolver {
         Ok(ElfResolver {
             backend,
             file_name: file_name.to_path_buf(),
+            binary: None,
+            build_id: OnceCell::new(),
         })
     }
 
+    /// Set the parser for the binary that this resolver reports
+    /// module information for, in case it is backed by a separate
+    /// debug information file.
+    pub(crate) fn with_binary(mut self, binary: Arc<ElfParser>) -> Self {
+        self.binary = Some(binary);
+        self
+    }
+
+    /// Create an `ElfResolver` for the ELF file represented by
+    /// `parser`.
+    ///
+    /// Split DWARF files referenced by the file are opened using `fs`.
+    /// If `hot_ranges` is provided, only debug information overlapping
+    /// the given address ranges is indexed.
     pub(crate) fn from_parser(
         path: &Path,
-        parser: Rc<ElfParser>,
+        parser: Arc<ElfParser>,
+        _fs: &Arc<dyn FileAccess>,
         _debug_syms: bool,
         code_info: bool,
+        hot_ranges: Option<&[Range<Addr>]>,
     ) -> Result<Self> {
         #[cfg(feature = "dwarf")]
         let backend = if _debug_syms {
-            let dwarf = DwarfResolver::from_parser(parser, code_info)?;
-            let backend = ElfBackend::Dwarf(Rc::new(dwarf));
+            let dwarf =
+                DwarfResolver::from_parser(path, parser, _fs.clone(), code_info, hot_ranges)?;
+            let backend = ElfBackend::Dwarf(Arc::new(dwarf));
             backend
         } else {
             ElfBackend::Elf(parser)
@@ -143,7 +277,7 @@ impl ElfResolver {
         Ok(resolver)
     }
 
-    pub(crate) fn parser(&self) -> &Rc<ElfParser> {
+    pub(crate) fn parser(&self) -> &Arc<ElfParser> {
         match &self.backend {
             #[cfg(feature = "dwarf")]
             ElfBackend::Dwarf(dwarf) => dwarf.parser(),
@@ -151,6 +285,15 @@ impl ElfResolver {
         }
     }
 
+    /// Retrieve the DWARF resolver used by this object, if any.
+    #[cfg(feature = "dwarf")]
+    pub(crate) fn dwarf(&self) -> Option<&Arc<DwarfResolver>> {
+        match &self.backend {
+            ElfBackend::Dwarf(dwarf) => Some(dwarf),
+            ElfBackend::Elf(..) => None,
+        }
+    }
+
     /// Retrieve the path to the ELF file represented by this resolver.
     pub(crate) fn file_name(&self) -> &Path {
         &self.file_name
@@ -226,6 +369,19 @@ impl SymResolver for ElfResolver {
     fn find_code_info(&self, addr: Addr, inlined_fns: bool) -> Result<Option<AddrCodeInfo<'_>>> {
         Ok(None)
     }
+
+    fn module_info(&self, addr: Addr) -> Result<IntModule<'_>> {
+        let parser = self.binary.as_ref().unwrap_or_else(|| self.parser());
+        let build_id = self
+            .build_id
+            .get_or_try_init(|| BuildId::from_parser(parser).map(|id| id.map(|id| id.id)))?;
+        let module = IntModule {
+            path: Some(&self.file_name),
+            build_id: build_id.as_deref(),
+            file_offset: parser.find_file_offset(addr)?,
+        };
+        Ok(module)
+    }
 }
 
 impl Debug for ElfResolver {
@@ -247,6 +403,8 @@ mod tests {
 
     #[cfg(feature = "dwarf")]
     use crate::dwarf::DwarfResolver;
+    #[cfg(feature = "dwarf")]
+    use crate::vfs::StdFileAccess;
 
 
     /// Exercise the `Debug` representation of various types.
@@ -256,7 +414,7 @@ mod tests {
             .join("data")
             .join("test-stable-addresses.bin");
 
-        let parser = Rc::new(ElfParser::open(&path).unwrap());
+        let parser = Arc::new(ElfParser::open(&path).unwrap());
         let backend = ElfBackend::Elf(parser.clone());
         let resolver = ElfResolver::with_backend(&path, backend).unwrap();
         let dbg = format!("{resolver:?}");
@@ -265,8 +423,9 @@ mod tests {
 
         #[cfg(feature = "dwarf")]
         {
-            let dwarf = DwarfResolver::from_parser(parser, true).unwrap();
-            let backend = ElfBackend::Dwarf(Rc::new(dwarf));
+            let fs = Arc::new(StdFileAccess::default());
+            let dwarf = DwarfResolver::from_parser(&path, parser, fs, true, None).unwrap();
+            let backend = ElfBackend::Dwarf(Arc::new(dwarf));
             let resolver = ElfResolver::with_backend(&path, backend).unwrap();
             let dbg = format!("{resolver:?}");
             assert!(dbg.starts_with("DWARF"), "{dbg}");
@@ -274,6 +433,14 @@ mod tests {
         }
     }
 
+    /// Check that we sort and merge address ranges as expected.
+    #[test]
+    fn range_normalization() {
+        let ranges = normalize_ranges(&[5..8, 1..3, 4..4, 2..4, 7..10, 12..13]);
+        assert_eq!(&*ranges, &[1..4, 5..10, 12..13]);
+        assert_eq!(&*normalize_ranges(&[]), &[]);
+    }
+
     /// Check that we fail finding an offset for an address not
     /// representing a symbol in an ELF file.
     #[test]
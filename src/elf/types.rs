@@ -1,13 +1,31 @@
 const EI_NIDENT: usize = 16;
 
+pub(crate) const EI_CLASS: usize = 4;
+pub(crate) const EI_DATA: usize = 5;
+
+pub(crate) const ELFCLASS64: u8 = 2;
+
+pub(crate) const ELFDATA2LSB: u8 = 1;
+pub(crate) const ELFDATA2MSB: u8 = 2;
+
 type Elf64_Addr = u64;
 type Elf64_Half = u16;
 type Elf64_Off = u64;
+type Elf64_Sxword = i64;
 type Elf64_Word = u32;
 type Elf64_Xword = u64;
 
+pub(crate) const ET_REL: u16 = 1;
 pub(crate) const ET_EXEC: u16 = 2;
 pub(crate) const ET_DYN: u16 = 3;
+pub(crate) const ET_CORE: u16 = 4;
+
+pub(crate) const EM_MIPS: u16 = 8;
+pub(crate) const EM_PPC64: u16 = 21;
+pub(crate) const EM_S390: u16 = 22;
+pub(crate) const EM_X86_64: u16 = 62;
+pub(crate) const EM_AARCH64: u16 = 183;
+pub(crate) const EM_RISCV: u16 = 243;
 
 #[derive(Debug)]
 #[repr(C)]
@@ -32,6 +50,7 @@ pub(crate) struct Elf64_Ehdr {
 unsafe impl crate::util::Pod for Elf64_Ehdr {}
 
 pub(crate) const PT_LOAD: u32 = 1;
+pub(crate) const PT_NOTE: u32 = 4;
 
 #[derive(Debug)]
 #[repr(C)]
@@ -50,6 +69,8 @@ pub(crate) struct Elf64_Phdr {
 unsafe impl crate::util::Pod for Elf64_Phdr {}
 
 pub(crate) const PF_X: Elf64_Word = 1;
+pub(crate) const PF_W: Elf64_Word = 2;
+pub(crate) const PF_R: Elf64_Word = 4;
 
 pub(crate) const PN_XNUM: u16 = 0xffff;
 
@@ -75,9 +96,46 @@ pub(crate) const SHN_UNDEF: u16 = 0;
 pub(crate) const SHN_LORESERVE: u16 = 0xff00;
 pub(crate) const SHN_XINDEX: u16 = 0xffff;
 
+pub(crate) const SHT_SYMTAB: Elf64_Word = 2;
+pub(crate) const SHT_RELA: Elf64_Word = 4;
 pub(crate) const SHT_NOTE: Elf64_Word = 7;
+pub(crate) const SHT_NOBITS: Elf64_Word = 8;
+pub(crate) const SHT_DYNSYM: Elf64_Word = 11;
+
+pub(crate) const SHF_ALLOC: Elf64_Xword = 0x2;
+pub(crate) const SHF_COMPRESSED: Elf64_Xword = 0x800;
+
+#[derive(Debug)]
+#[repr(C)]
+pub(crate) struct Elf64_Chdr {
+    pub ch_type: Elf64_Word,       /* Compression algorithm */
+    pub ch_reserved: Elf64_Word,   /* Reserved */
+    pub ch_size: Elf64_Xword,      /* Size of uncompressed data */
+    pub ch_addralign: Elf64_Xword, /* Alignment of uncompressed data */
+}
+
+// SAFETY: `Elf64_Chdr` is valid for any bit pattern.
+unsafe impl crate::util::Pod for Elf64_Chdr {}
 
+pub(crate) const ELFCOMPRESS_ZLIB: Elf64_Word = 1;
+pub(crate) const ELFCOMPRESS_ZSTD: Elf64_Word = 2;
+
+pub(crate) const STT_NOTYPE: u8 = 0;
+pub(crate) const STT_OBJECT: u8 = 1;
 pub(crate) const STT_FUNC: u8 = 2;
+pub(crate) const STT_COMMON: u8 = 5;
+pub(crate) const STT_TLS: u8 = 6;
+pub(crate) const STT_GNU_IFUNC: u8 = 10;
+
+pub(crate) const STB_LOCAL: u8 = 0;
+pub(crate) const STB_GLOBAL: u8 = 1;
+pub(crate) const STB_WEAK: u8 = 2;
+pub(crate) const STB_GNU_UNIQUE: u8 = 10;
+
+pub(crate) const STV_DEFAULT: u8 = 0;
+pub(crate) const STV_INTERNAL: u8 = 1;
+pub(crate) const STV_HIDDEN: u8 = 2;
+pub(crate) const STV_PROTECTED: u8 = 3;
 
 #[derive(Clone, Debug)]
 #[repr(C)]
@@ -95,12 +153,61 @@ impl Elf64_Sym {
     pub fn type_(&self) -> u8 {
         self.st_info & 0xf
     }
+
+    /// Extract the symbols binding, typically represented by a STB_*
+    /// constant.
+    pub fn binding(&self) -> u8 {
+        self.st_info >> 4
+    }
+
+    /// Extract the symbols visibility, represented by a STV_* constant.
+    pub fn visibility(&self) -> u8 {
+        self.st_other & 0x3
+    }
 }
 
 // SAFETY: `Elf64_Sym` is valid for any bit pattern.
 unsafe impl crate::util::Pod for Elf64_Sym {}
 
+pub(crate) const R_X86_64_NONE: Elf64_Word = 0;
+pub(crate) const R_X86_64_64: Elf64_Word = 1;
+pub(crate) const R_X86_64_32: Elf64_Word = 10;
+pub(crate) const R_X86_64_32S: Elf64_Word = 11;
+
+pub(crate) const R_AARCH64_NONE: Elf64_Word = 0;
+pub(crate) const R_AARCH64_ABS64: Elf64_Word = 257;
+pub(crate) const R_AARCH64_ABS32: Elf64_Word = 258;
+
+pub(crate) const R_390_NONE: Elf64_Word = 0;
+pub(crate) const R_390_32: Elf64_Word = 4;
+pub(crate) const R_390_64: Elf64_Word = 22;
+
+#[derive(Debug)]
+#[repr(C)]
+pub(crate) struct Elf64_Rela {
+    pub r_offset: Elf64_Addr,   /* Location at which to apply the action */
+    pub r_info: Elf64_Xword,    /* Index and type of relocation */
+    pub r_addend: Elf64_Sxword, /* Constant addend used to compute value */
+}
+
+impl Elf64_Rela {
+    /// Extract the index of the symbol the relocation refers to.
+    pub fn sym(&self) -> Elf64_Word {
+        (self.r_info >> 32) as Elf64_Word
+    }
+
+    /// Extract the relocation type, typically represented by an R_*
+    /// constant.
+    pub fn type_(&self) -> Elf64_Word {
+        (self.r_info & 0xffffffff) as Elf64_Word
+    }
+}
+
+// SAFETY: `Elf64_Rela` is valid for any bit pattern.
+unsafe impl crate::util::Pod for Elf64_Rela {}
+
 pub(crate) const NT_GNU_BUILD_ID: Elf64_Word = 3;
+pub(crate) const NT_FILE: Elf64_Word = 0x46494c45;
 
 #[derive(Debug)]
 #[repr(C)]
@@ -182,5 +289,12 @@ mod tests {
             st_size: 0,
         };
         assert_ne!(format!("{sym:?}"), "");
+
+        let rela = Elf64_Rela {
+            r_offset: 0,
+            r_info: 0,
+            r_addend: 0,
+        };
+        assert_ne!(format!("{rela:?}"), "");
     }
 }
@@ -25,7 +25,7 @@ mod private {
     impl Sealed for Error {}
 
     impl Sealed for io::Error {}
-    #[cfg(feature = "dwarf")]
+    #[cfg(any(feature = "dwarf", feature = "unwind"))]
     impl Sealed for gimli::Error {}
 }
 
@@ -94,7 +94,7 @@ impl IntoCowStr for String {
 }
 
 enum ErrorImpl {
-    #[cfg(feature = "dwarf")]
+    #[cfg(any(feature = "dwarf", feature = "unwind"))]
     Dwarf {
         error: gimli::Error,
         #[cfg(feature = "backtrace")]
@@ -105,6 +105,14 @@ enum ErrorImpl {
         #[cfg(feature = "backtrace")]
         backtrace: Backtrace,
     },
+    /// An error of a kind specific to this crate, i.e., one that does
+    /// not have an [`io::ErrorKind`] equivalent.
+    Custom {
+        kind: ErrorKind,
+        error: Box<str>,
+        #[cfg(feature = "backtrace")]
+        backtrace: Backtrace,
+    },
     // Unfortunately, if we just had a single `Context` variant that
     // contains a `Cow`, this inner `Cow` would cause an overall enum
     // size increase by a machine word, because currently `rustc`
@@ -124,7 +132,7 @@ enum ErrorImpl {
 impl ErrorImpl {
     fn kind(&self) -> ErrorKind {
         match self {
-            #[cfg(feature = "dwarf")]
+            #[cfg(any(feature = "dwarf", feature = "unwind"))]
             Self::Dwarf { .. } => ErrorKind::InvalidDwarf,
             Self::Io { error, .. } => match error.kind() {
                 io::ErrorKind::NotFound => ErrorKind::NotFound,
@@ -134,12 +142,14 @@ impl ErrorImpl {
                 io::ErrorKind::InvalidInput => ErrorKind::InvalidInput,
                 io::ErrorKind::InvalidData => ErrorKind::InvalidData,
                 io::ErrorKind::TimedOut => ErrorKind::TimedOut,
+                io::ErrorKind::Interrupted => ErrorKind::Interrupted,
                 io::ErrorKind::WriteZero => ErrorKind::WriteZero,
                 io::ErrorKind::Unsupported => ErrorKind::Unsupported,
                 io::ErrorKind::UnexpectedEof => ErrorKind::UnexpectedEof,
                 io::ErrorKind::OutOfMemory => ErrorKind::OutOfMemory,
                 _ => ErrorKind::Other,
             },
+            Self::Custom { kind, .. } => *kind,
             Self::ContextOwned { source, .. } | Self::ContextStatic { source, .. } => {
                 source.deref().kind()
             }
@@ -150,9 +160,10 @@ impl ErrorImpl {
     #[cfg(feature = "backtrace")]
     fn backtrace(&self) -> Option<&Backtrace> {
         match self {
-            #[cfg(feature = "dwarf")]
+            #[cfg(any(feature = "dwarf", feature = "unwind"))]
             Self::Dwarf { backtrace, .. } => Some(backtrace),
             Self::Io { backtrace, .. } => Some(backtrace),
+            Self::Custom { backtrace, .. } => Some(backtrace),
             Self::ContextOwned { .. } => None,
             Self::ContextStatic { .. } => None,
         }
@@ -182,7 +193,7 @@ impl Debug for ErrorImpl {
             let mut dbg;
 
             match self {
-                #[cfg(feature = "dwarf")]
+                #[cfg(any(feature = "dwarf", feature = "unwind"))]
                 Self::Dwarf { error, .. } => {
                     dbg = f.debug_tuple(stringify!(Dwarf));
                     dbg.field(error)
@@ -191,6 +202,10 @@ impl Debug for ErrorImpl {
                     dbg = f.debug_tuple(stringify!(Io));
                     dbg.field(error)
                 }
+                Self::Custom { kind, error, .. } => {
+                    dbg = f.debug_tuple(stringify!(Custom));
+                    dbg.field(kind).field(error)
+                }
                 Self::ContextOwned { context, .. } => {
                     dbg = f.debug_tuple(stringify!(ContextOwned));
                     dbg.field(context)
@@ -203,9 +218,10 @@ impl Debug for ErrorImpl {
             .finish()
         } else {
             let () = match self {
-                #[cfg(feature = "dwarf")]
+                #[cfg(any(feature = "dwarf", feature = "unwind"))]
                 Self::Dwarf { error, .. } => write!(f, "Error: {error}")?,
                 Self::Io { error, .. } => write!(f, "Error: {error}")?,
+                Self::Custom { error, .. } => write!(f, "Error: {error}")?,
                 Self::ContextOwned { context, .. } => write!(f, "Error: {context}")?,
                 Self::ContextStatic { context, .. } => write!(f, "Error: {context}")?,
             };
@@ -234,9 +250,10 @@ impl Debug for ErrorImpl {
 impl Display for ErrorImpl {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         let () = match self {
-            #[cfg(feature = "dwarf")]
+            #[cfg(any(feature = "dwarf", feature = "unwind"))]
             Self::Dwarf { error, .. } => Display::fmt(error, f)?,
             Self::Io { error, .. } => Display::fmt(error, f)?,
+            Self::Custom { error, .. } => Display::fmt(error, f)?,
             Self::ContextOwned { context, .. } => Display::fmt(context, f)?,
             Self::ContextStatic { context, .. } => Display::fmt(context, f)?,
         };
@@ -255,9 +272,10 @@ impl Display for ErrorImpl {
 impl error::Error for ErrorImpl {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
-            #[cfg(feature = "dwarf")]
+            #[cfg(any(feature = "dwarf", feature = "unwind"))]
             Self::Dwarf { error, .. } => error.source(),
             Self::Io { error, .. } => error.source(),
+            Self::Custom { .. } => None,
             Self::ContextOwned { source, .. } | Self::ContextStatic { source, .. } => Some(source),
         }
     }
@@ -270,7 +288,12 @@ impl error::Error for ErrorImpl {
 /// [`std::io::Error`], because these are the most common sources of
 /// error that the crate concerns itself with. On top of that, however,
 /// there are additional more specific variants such as
-/// [`InvalidDwarf`][ErrorKind::InvalidDwarf].
+/// [`InvalidDwarf`][ErrorKind::InvalidDwarf] or
+/// [`UnsupportedFormat`][ErrorKind::UnsupportedFormat].
+///
+/// The kind of an [`Error`] is retained when context is added to it,
+/// meaning that it always reflects the root cause of the error and can
+/// be used for programmatic handling of failures.
 #[derive(Clone, Copy, Debug, PartialEq)]
 #[non_exhaustive]
 pub enum ErrorKind {
@@ -289,8 +312,20 @@ pub enum ErrorKind {
     InvalidData,
     /// DWARF input data was invalid.
     InvalidDwarf,
+    /// The input is not in a format supported by the operation, e.g.,
+    /// because a file is not an ELF file.
+    UnsupportedFormat,
+    /// The build ID of a file did not match the expected one, e.g.,
+    /// because a separate debug information file belongs to a
+    /// different binary.
+    BuildIdMismatch,
+    /// Entering or leaving a Linux namespace failed.
+    NamespaceFailure,
     /// The I/O operation's timeout expired, causing it to be canceled.
     TimedOut,
+    /// The operation was interrupted, typically because it got
+    /// cancelled.
+    Interrupted,
     /// An error returned when an operation could not be completed
     /// because a call to [`write`] returned [`Ok(0)`].
     WriteZero,
@@ -410,6 +445,22 @@ impl Error {
         Self::with_io_error(io::ErrorKind::InvalidData, error)
     }
 
+    #[inline]
+    pub(crate) fn with_interrupted<E>(error: E) -> Self
+    where
+        E: ToString,
+    {
+        Self::with_io_error(io::ErrorKind::Interrupted, error)
+    }
+
+    #[inline]
+    pub(crate) fn with_timed_out<E>(error: E) -> Self
+    where
+        E: ToString,
+    {
+        Self::with_io_error(io::ErrorKind::TimedOut, error)
+    }
+
     #[inline]
     pub(crate) fn with_unsupported<E>(error: E) -> Self
     where
@@ -418,6 +469,47 @@ impl Error {
         Self::with_io_error(io::ErrorKind::Unsupported, error)
     }
 
+    #[inline]
+    fn with_kind<E>(kind: ErrorKind, error: E) -> Self
+    where
+        E: ToString,
+    {
+        Self {
+            error: Box::new(ErrorImpl::Custom {
+                kind,
+                error: error.to_string().into_boxed_str(),
+                #[cfg(feature = "backtrace")]
+                backtrace: Backtrace::capture(),
+            }),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn with_unsupported_format<E>(error: E) -> Self
+    where
+        E: ToString,
+    {
+        Self::with_kind(ErrorKind::UnsupportedFormat, error)
+    }
+
+    #[cfg(feature = "debuginfod")]
+    #[inline]
+    pub(crate) fn with_build_id_mismatch<E>(error: E) -> Self
+    where
+        E: ToString,
+    {
+        Self::with_kind(ErrorKind::BuildIdMismatch, error)
+    }
+
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub(crate) fn with_namespace_failure<E>(error: E) -> Self
+    where
+        E: ToString,
+    {
+        Self::with_kind(ErrorKind::NamespaceFailure, error)
+    }
+
     /// Retrieve a rough error classification in the form of an
     /// [`ErrorKind`].
     #[inline]
@@ -466,7 +558,7 @@ impl error::Error for Error {
     }
 }
 
-#[cfg(feature = "dwarf")]
+#[cfg(any(feature = "dwarf", feature = "unwind"))]
 impl From<gimli::Error> for Error {
     fn from(other: gimli::Error) -> Self {
         Self {
@@ -579,7 +671,7 @@ impl ErrorExt for io::Error {
     }
 }
 
-#[cfg(feature = "dwarf")]
+#[cfg(any(feature = "dwarf", feature = "unwind"))]
 impl ErrorExt for gimli::Error {
     type Output = Error;
 
@@ -741,4 +833,33 @@ Caused by:
         assert_eq!(format!("{err:?}"), expected);
         assert_ne!(format!("{err:#?}"), "");
     }
+
+    /// Check that crate specific error kinds are retained when adding
+    /// context.
+    #[test]
+    fn custom_kind() {
+        let err = Error::with_unsupported_format("not an ELF file");
+        assert_eq!(err.kind(), ErrorKind::UnsupportedFormat);
+        assert!(err.source().is_none());
+        assert_eq!(format!("{err}"), "not an ELF file");
+        assert_eq!(
+            format!("{err:#?}"),
+            r#"Custom(
+    UnsupportedFormat,
+    "not an ELF file",
+)"#
+        );
+
+        let err = err.context("failed to parse /tmp/foo");
+        assert_eq!(err.kind(), ErrorKind::UnsupportedFormat);
+        assert_eq!(
+            format!("{err:#}"),
+            "failed to parse /tmp/foo: not an ELF file"
+        );
+
+        let err = Error::with_kind(ErrorKind::BuildIdMismatch, "build ID mismatch")
+            .context("inner")
+            .context("outer".to_string());
+        assert_eq!(err.kind(), ErrorKind::BuildIdMismatch);
+    }
 }
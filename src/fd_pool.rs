@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs::File;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt as _;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use crate::ErrorExt as _;
+use crate::Result;
+
+
+/// The default maximum number of file descriptors kept open by a
+/// [`FdPool`].
+pub(crate) const DEFAULT_MAX_OPEN_FILES: usize = 128;
+
+
+/// A file descriptor kept open by the pool.
+#[derive(Debug)]
+struct Entry {
+    file: Arc<File>,
+    /// The device the file resides on.
+    dev: u64,
+    /// The file's inode number.
+    inode: u64,
+    /// The "time" at which the entry was last used.
+    used: u64,
+}
+
+
+#[derive(Debug, Default)]
+struct State {
+    /// The open files, keyed by path.
+    files: HashMap<PathBuf, Entry>,
+    /// A monotonically increasing counter used for tracking recency of
+    /// use.
+    clock: u64,
+}
+
+
+/// A pool of open file descriptors.
+///
+/// The pool keeps open up to a configurable number of regular files,
+/// so that repeated accesses to the same path can reuse a descriptor.
+/// Once that number is exceeded, the least recently used descriptors
+/// are closed. Descriptors still referenced by a user are only closed
+/// once the last reference is gone.
+///
+/// On non-unix systems files cannot be reliably identified and are
+/// never kept open.
+pub(crate) struct FdPool {
+    /// The maximum number of descriptors kept open.
+    capacity: usize,
+    state: Mutex<State>,
+}
+
+impl FdPool {
+    /// Create a new `FdPool` keeping at most `capacity` file
+    /// descriptors open.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Retrieve the maximum number of file descriptors kept open.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Open the file at `path` for reading.
+    ///
+    /// If the pool already contains a descriptor for the path that
+    /// still refers to the file currently present there, it is reused.
+    /// Note that reused descriptors are shared and so users should not
+    /// rely on the file position.
+    pub fn open(&self, path: &Path) -> Result<Arc<File>> {
+        if self.capacity == 0 || !cfg!(unix) {
+            let file = File::open(path)
+                .with_context(|| format!("failed to open file {}", path.display()))?;
+            return Ok(Arc::new(file))
+        }
+
+        // We only check the file system outside of the lock, so that
+        // users don't serialize on it.
+        let current = path.metadata().ok().and_then(|meta| identify(&meta));
+        {
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            state.clock += 1;
+            let clock = state.clock;
+            if let Some(entry) = state.files.get_mut(path) {
+                if current == Some((entry.dev, entry.inode)) {
+                    entry.used = clock;
+                    return Ok(Arc::clone(&entry.file))
+                }
+            }
+        }
+
+        let file =
+            File::open(path).with_context(|| format!("failed to open file {}", path.display()))?;
+        let file = Arc::new(file);
+        let meta = file
+            .metadata()
+            .with_context(|| format!("failed to query meta data of {}", path.display()))?;
+        // Only non-empty regular files are accessed without relying on
+        // the file position, which is shared between users. Note that
+        // many pseudo files (e.g., in `/proc`) claim to be empty
+        // regular files.
+        if let Some((dev, inode)) = identify(&meta).filter(|_| meta.is_file() && meta.len() > 0) {
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            let entry = Entry {
+                file: Arc::clone(&file),
+                dev,
+                inode,
+                used: state.clock,
+            };
+            let _prev = state.files.insert(path.to_path_buf(), entry);
+            let () = self.evict(&mut state);
+        }
+        Ok(file)
+    }
+
+    /// Close the least recently used descriptors until we are within
+    /// capacity again.
+    fn evict(&self, state: &mut State) {
+        while state.files.len() > self.capacity {
+            let path = state
+                .files
+                .iter()
+                .min_by_key(|(_path, entry)| entry.used)
+                .map(|(path, _entry)| path.clone());
+            if let Some(path) = path {
+                let _entry = state.files.remove(&path);
+            } else {
+                break
+            }
+        }
+    }
+
+    /// Retrieve the number of file descriptors currently kept open.
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .files
+            .len()
+    }
+}
+
+impl Default for FdPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_OPEN_FILES)
+    }
+}
+
+impl Debug for FdPool {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct(stringify!(FdPool))
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+
+/// Retrieve the device and inode number identifying a file.
+#[cfg(unix)]
+fn identify(meta: &std::fs::Metadata) -> Option<(u64, u64)> {
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn identify(_meta: &std::fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::fs;
+    use std::io::Write as _;
+
+    use tempfile::tempdir;
+
+    use crate::ErrorKind;
+
+
+    /// Exercise the `Debug` representation of various types.
+    #[test]
+    fn debug_repr() {
+        let pool = FdPool::default();
+        assert_ne!(format!("{pool:?}"), "");
+    }
+
+    /// Check that descriptors are reused and closed in least recently
+    /// used order.
+    #[test]
+    fn lru_closing() {
+        let dir = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+        let a = dir.join("test-stable-addresses.bin");
+        let b = dir.join("test-stable-addresses-no-dwarf.bin");
+        let c = dir.join("test-stable-addresses-dwarf-only.bin");
+
+        let pool = FdPool::new(2);
+        let file_a = pool.open(&a).unwrap();
+        let file_b = pool.open(&b).unwrap();
+        assert_eq!(pool.len(), 2);
+        assert!(Arc::ptr_eq(&file_a, &pool.open(&a).unwrap()));
+
+        // `b` is least recently used and should get closed.
+        let _file_c = pool.open(&c).unwrap();
+        assert_eq!(pool.len(), 2);
+        assert!(Arc::ptr_eq(&file_a, &pool.open(&a).unwrap()));
+        assert!(!Arc::ptr_eq(&file_b, &pool.open(&b).unwrap()));
+
+        // Special files are not kept open.
+        let _file = pool.open(Path::new("/proc/self/maps")).unwrap();
+        assert_eq!(pool.len(), 2);
+
+        let err = pool.open(&dir.join("does-not-exist")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+    }
+
+    /// Check that we don't reuse descriptors of files that got
+    /// replaced.
+    #[test]
+    fn replaced_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("file");
+        let () = fs::write(&path, b"foo").unwrap();
+
+        let pool = FdPool::default();
+        let file1 = pool.open(&path).unwrap();
+
+        let tmp = dir.path().join("tmp");
+        let mut file = File::create(&tmp).unwrap();
+        let () = file.write_all(b"bar").unwrap();
+        let () = fs::rename(&tmp, &path).unwrap();
+
+        let file2 = pool.open(&path).unwrap();
+        assert!(!Arc::ptr_eq(&file1, &file2));
+        assert_eq!(pool.len(), 1);
+
+        let pool = FdPool::new(0);
+        let _file = pool.open(&path).unwrap();
+        assert_eq!(pool.len(), 0);
+    }
+}
@@ -0,0 +1,131 @@
+/// Information about the optional functionality compiled into the
+/// library.
+///
+/// An object of this type is retrieved via [`features`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Features {
+    /// Whether DWARF debug information is supported (the `dwarf`
+    /// feature).
+    pub dwarf: bool,
+    /// Whether the Gsym format is supported (the `gsym` feature).
+    pub gsym: bool,
+    /// Whether the Breakpad symbol file format is supported (the
+    /// `breakpad` feature).
+    pub breakpad: bool,
+    /// Whether kernel addresses can be symbolized and normalized (the
+    /// `kernel` feature).
+    pub kernel: bool,
+    /// Whether addresses in running processes can be symbolized and
+    /// normalized (the `process` feature).
+    pub process: bool,
+    /// Whether Ada symbol names are demangled (the `demangle-ada`
+    /// feature).
+    pub demangle_ada: bool,
+    /// Whether C++ symbol names are demangled (the `demangle-cpp`
+    /// feature).
+    pub demangle_cpp: bool,
+    /// Whether D symbol names are demangled (the `demangle-d` feature).
+    pub demangle_d: bool,
+    /// Whether Rust symbol names are demangled (the `demangle-rust`
+    /// feature).
+    pub demangle_rust: bool,
+    /// Whether Swift symbol names are demangled (the `demangle-swift`
+    /// feature).
+    pub demangle_swift: bool,
+    /// Whether errors capture backtraces (the `backtrace` feature).
+    pub backtrace: bool,
+    /// Whether debug information is fetched from debuginfod servers
+    /// (the `debuginfod` feature).
+    pub debuginfod: bool,
+    /// Whether the symbol cache daemon is supported (the
+    /// `cache-daemon` feature, on Unix systems).
+    pub cache_daemon: bool,
+    /// Whether files are read in bulk using `io_uring` (the `io-uring`
+    /// feature, on Linux).
+    pub io_uring: bool,
+    /// Whether non-blocking variants of the symbolization and
+    /// inspection APIs are available (the `nonblocking` feature).
+    pub nonblocking: bool,
+    /// Whether the `perf_event_open(2)` based stack sampler is
+    /// available (the `perf-event` feature, on Linux).
+    pub perf_event: bool,
+    /// Whether events are emitted using the `tracing` crate (the
+    /// `tracing` feature).
+    pub tracing: bool,
+    /// Whether the DWARF call frame information based stack unwinder
+    /// is available (the `unwind` feature).
+    pub unwind: bool,
+    /// Whether xz compressed kernel modules are supported (the `xz`
+    /// feature).
+    pub xz: bool,
+    /// Whether zlib compressed ELF sections are supported (the `zlib`
+    /// feature).
+    pub zlib: bool,
+    /// Whether zstd compressed kernel modules and ELF sections are
+    /// supported (the `zstd` feature).
+    pub zstd: bool,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+
+/// Query the optional functionality that this build of the library
+/// supports.
+///
+/// This function allows for detecting support for certain subsystems
+/// at runtime, without having to replicate the library's compile time
+/// configuration.
+///
+/// ```rust
+/// let features = blazesym::features();
+/// if features.gsym {
+///     // Gsym files can be used as symbolization source.
+/// }
+/// ```
+pub const fn features() -> Features {
+    Features {
+        dwarf: cfg!(feature = "dwarf"),
+        gsym: cfg!(feature = "gsym"),
+        breakpad: cfg!(feature = "breakpad"),
+        kernel: cfg!(feature = "kernel"),
+        process: cfg!(feature = "process"),
+        demangle_ada: cfg!(feature = "demangle-ada"),
+        demangle_cpp: cfg!(feature = "demangle-cpp"),
+        demangle_d: cfg!(feature = "demangle-d"),
+        demangle_rust: cfg!(feature = "demangle-rust"),
+        demangle_swift: cfg!(feature = "demangle-swift"),
+        backtrace: cfg!(feature = "backtrace"),
+        tracing: cfg!(feature = "tracing"),
+        cache_daemon: cfg!(all(unix, feature = "cache-daemon")),
+        debuginfod: cfg!(feature = "debuginfod"),
+        io_uring: cfg!(all(target_os = "linux", feature = "io-uring")),
+        nonblocking: cfg!(feature = "nonblocking"),
+        perf_event: cfg!(all(target_os = "linux", feature = "perf-event")),
+        unwind: cfg!(feature = "unwind"),
+        xz: cfg!(feature = "xz"),
+        zlib: cfg!(feature = "zlib"),
+        zstd: cfg!(feature = "zstd"),
+        _non_exhaustive: (),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Check that reported features reflect the compile time
+    /// configuration.
+    #[test]
+    fn reported_features() {
+        let features = features();
+        assert_ne!(format!("{features:?}"), "");
+        assert_eq!(features.dwarf, cfg!(feature = "dwarf"));
+        assert_eq!(features.gsym, cfg!(feature = "gsym"));
+        assert_eq!(features.breakpad, cfg!(feature = "breakpad"));
+        assert_eq!(features.kernel, cfg!(feature = "kernel"));
+        assert_eq!(features.process, cfg!(feature = "process"));
+    }
+}
@@ -1,80 +1,314 @@
-use std::fs::File;
-use std::os::unix::io::AsRawFd;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
 
 use crate::insert_map::InsertMap;
+use crate::mmap::MmapAdvice;
 use crate::once::OnceCell;
-use crate::util::fstat;
-use crate::ErrorExt as _;
+use crate::scope;
+use crate::symbolize::CacheStats;
+use crate::vfs::FileAccess;
+use crate::vfs::FileData;
+use crate::vfs::FileId;
+use crate::vfs::StdFileAccess;
 use crate::Result;
 
 
+/// A process wide clock used for tracking when cache entries were
+/// last used, allowing for comparisons across caches.
+static CLOCK: AtomicU64 = AtomicU64::new(0);
+
+/// Retrieve the next "time stamp" of our clock.
+fn tick() -> u64 {
+    CLOCK.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+
+/// The key identifying a cache entry.
 #[derive(Debug, Eq, Hash, PartialEq)]
-// `libc` has deprecated `time_t` usage on `musl`. See
-// https://github.com/rust-lang/libc/issues/1848
-#[cfg_attr(target_env = "musl", allow(deprecated))]
-struct EntryMeta {
-    path: PathBuf,
-    dev: libc::dev_t,
-    inode: libc::ino_t,
-    size: libc::off_t,
-    mtime_sec: libc::time_t,
-    mtime_nsec: i64,
+enum EntryKey {
+    /// A file identified by its device and inode (and version),
+    /// irrespective of the path through which it is accessed. That
+    /// allows for sharing of data between, say, processes running the
+    /// same binary, but in different mount namespaces.
+    File(FileId),
+    /// A file identified by its path and version, for cases in which
+    /// the version does not uniquely identify the file.
+    Path(PathBuf, FileId),
 }
 
-impl EntryMeta {
-    fn new(path: PathBuf, stat: &libc::stat) -> Self {
-        // Casts are necessary because on Android some libc types do not
-        // use proper typedefs. https://github.com/rust-lang/libc/issues/3285
-        Self {
-            path,
-            dev: stat.st_dev as _,
-            inode: stat.st_ino as _,
-            size: stat.st_size as _,
-            mtime_sec: stat.st_mtime,
-            mtime_nsec: stat.st_mtime_nsec as _,
+impl EntryKey {
+    fn new(path: &Path, id: &FileId) -> Self {
+        if id.is_unique() {
+            Self::File(id.clone())
+        } else {
+            Self::Path(path.to_path_buf(), id.clone())
         }
     }
 }
 
 
+/// A cached file along with the value associated with it.
 #[derive(Debug)]
-struct Entry<T> {
-    file: File,
+pub(crate) struct Entry<T> {
+    data: FileData,
     value: OnceCell<T>,
+    /// The "time" at which the entry was last used.
+    last_use: AtomicU64,
+    /// The paths through which the file was accessed.
+    paths: Mutex<HashSet<PathBuf>>,
 }
 
 impl<T> Entry<T> {
-    fn new(file: File) -> Self {
+    fn new(data: FileData) -> Self {
         Self {
-            file,
+            data,
             value: OnceCell::new(),
+            last_use: AtomicU64::new(tick()),
+            paths: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Retrieve the file's data.
+    #[inline]
+    pub(crate) fn data(&self) -> &FileData {
+        &self.data
+    }
+
+    /// Retrieve the value associated with the file.
+    #[inline]
+    pub(crate) fn value(&self) -> &OnceCell<T> {
+        &self.value
+    }
+
+    /// Mark the entry as used through `path`.
+    fn touch(&self, path: &Path) {
+        let () = self.last_use.store(tick(), Ordering::Relaxed);
+        let mut paths = self.paths.lock().unwrap_or_else(PoisonError::into_inner);
+        if !paths.contains(path) {
+            let _new = paths.insert(path.to_path_buf());
         }
     }
 }
 
 
-#[derive(Debug)]
+/// A type erased interface for managing the contents of a cache.
+pub(crate) trait ManagedCache {
+    /// Retrieve statistics about the cache's usage.
+    fn stats(&self) -> CacheStats;
+
+    /// Report the time of last use along with the file size of each
+    /// cache entry.
+    fn usage(&self, usage: &mut Vec<(u64, u64)>);
+
+    /// Retain only entries for which `f`, being provided a path
+    /// through which the file was accessed and the time of its last
+    /// use, returns `true`.
+    ///
+    /// Entries accessed through multiple paths are only evicted once
+    /// `f` returned `false` for all of them. The paths for which `f`
+    /// returned `false` are added to `evicted`.
+    ///
+    /// Evicted entries are released once no longer in use.
+    fn retain(&self, f: &mut dyn FnMut(&Path, u64) -> bool, evicted: &mut Vec<PathBuf>);
+}
+
+
 pub(crate) struct FileCache<T> {
-    cache: InsertMap<EntryMeta, Entry<T>>,
+    /// The provider used for accessing files.
+    fs: Arc<dyn FileAccess>,
+    /// The hints to apply to newly loaded files, if any.
+    advice: Option<MmapAdvice>,
+    cache: InsertMap<EntryKey, Entry<T>>,
+    /// The number of lookups served from the cache.
+    hits: AtomicU64,
+    /// The number of lookups that required loading a file.
+    misses: AtomicU64,
+    /// The number of entries evicted so far.
+    evictions: AtomicU64,
 }
 
 impl<T> FileCache<T> {
     pub fn new() -> Self {
+        Self::with_file_access(Arc::new(StdFileAccess::default()))
+    }
+
+    /// Create a new `FileCache` accessing files through `fs`.
+    pub fn with_file_access(fs: Arc<dyn FileAccess>) -> Self {
         Self {
+            fs,
+            advice: None,
             cache: InsertMap::new(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// Set the hints to apply to the memory mappings of newly loaded
+    /// files.
+    pub fn with_advice(mut self, advice: Option<MmapAdvice>) -> Self {
+        self.advice = advice;
+        self
+    }
+
+    /// Retrieve the hints applied to the memory mappings of loaded
+    /// files, if any.
+    #[inline]
+    pub fn advice(&self) -> Option<&MmapAdvice> {
+        self.advice.as_ref()
+    }
+
+    /// Retrieve the file access used for loading files.
+    #[inline]
+    pub fn file_access(&self) -> &Arc<dyn FileAccess> {
+        &self.fs
+    }
+
+    fn new_entry(&self, data: FileData) -> Entry<T> {
+        if let Some(advice) = &self.advice {
+            let () = advice.apply(data.mmap());
+        }
+        let _cnt = self.misses.fetch_add(1, Ordering::Relaxed);
+        Entry::new(data)
+    }
+
+    /// Look up the entry for the file at `path`, using its version as
+    /// reported by the file access, without loading it.
+    fn lookup(&self, path: &Path) -> Result<Option<Arc<Entry<T>>>> {
+        let fs = Arc::clone(&self.fs);
+        let owned = path.to_path_buf();
+        let version = scope::run_blocking(move || fs.version(&owned))?;
+        let entry = version.and_then(|version| {
+            let key = EntryKey::new(path, version.id());
+            self.cache.get(&key)
+        });
+        if let Some(entry) = &entry {
+            let _cnt = self.hits.fetch_add(1, Ordering::Relaxed);
+            let () = entry.touch(path);
         }
+        Ok(entry)
     }
 
-    pub fn entry(&self, path: &Path) -> Result<(&File, &OnceCell<T>)> {
-        let file =
-            File::open(path).with_context(|| format!("failed to open file {}", path.display()))?;
-        let stat = fstat(file.as_raw_fd())?;
-        let meta = EntryMeta::new(path.to_path_buf(), &stat);
+    fn get_or_insert(&self, path: &Path, data: FileData) -> Arc<Entry<T>> {
+        let key = EntryKey::new(path, data.id());
+        let mut inserted = false;
+        let entry = self.cache.get_or_insert(key, || {
+            inserted = true;
+            self.new_entry(data)
+        });
+        if !inserted {
+            let _cnt = self.hits.fetch_add(1, Ordering::Relaxed);
+        }
+        let () = entry.touch(path);
+        entry
+    }
+
+    /// Retrieve the cache entry for the file at `path`, loading the
+    /// file if necessary.
+    ///
+    /// The entry stays valid for as long as it is referenced, even if
+    /// it gets evicted in the meantime.
+    pub fn entry(&self, path: &Path) -> Result<Arc<Entry<T>>> {
+        // Only load the file if we don't have it cached already.
+        if let Some(entry) = self.lookup(path)? {
+            return Ok(entry)
+        }
+
+        let fs = Arc::clone(&self.fs);
+        let owned = path.to_path_buf();
+        let data = scope::run_blocking(move || fs.open(&owned))?;
+        let entry = self.get_or_insert(path, data);
+        Ok(entry)
+    }
+
+    /// Load the files at `paths` into the cache, in bulk.
+    ///
+    /// Files are loaded using [`FileAccess::open_many`], which may read
+    /// them concurrently. Exactly one result is reported for each path.
+    pub fn preload(&self, paths: &[&Path]) -> Vec<Result<FileData>> {
+        // With a deadline in effect, files are loaded one by one, with
+        // each operation being bounded by the deadline.
+        if scope::remaining().is_some() {
+            return paths
+                .iter()
+                .map(|path| self.entry(path).map(|entry| entry.data.clone()))
+                .collect()
+        }
+
+        self.fs
+            .open_many(paths)
+            .into_iter()
+            .zip(paths)
+            .map(|(result, path)| {
+                let data = result?;
+                let entry = self.get_or_insert(path, data);
+                Ok(entry.data.clone())
+            })
+            .collect()
+    }
+}
+
+impl<T> ManagedCache for FileCache<T> {
+    fn stats(&self) -> CacheStats {
+        let mut stats = CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            ..Default::default()
+        };
+        let () = self.cache.for_each(|_meta, entry| {
+            stats.entries += 1;
+            stats.bytes += entry.data.data().len() as u64;
+        });
+        stats
+    }
+
+    fn usage(&self, usage: &mut Vec<(u64, u64)>) {
+        let () = self.cache.for_each(|_meta, entry| {
+            let last_use = entry.last_use.load(Ordering::Relaxed);
+            usage.push((last_use, entry.data.data().len() as u64))
+        });
+    }
+
+    fn retain(&self, f: &mut dyn FnMut(&Path, u64) -> bool, evicted: &mut Vec<PathBuf>) {
+        let removed = self.cache.retain(|_key, entry| {
+            let last_use = entry.last_use.load(Ordering::Relaxed);
+            let mut paths = entry.paths.lock().unwrap_or_else(PoisonError::into_inner);
+            let () = paths.retain(|path| {
+                let retain = f(path, last_use);
+                if !retain {
+                    let () = evicted.push(path.clone());
+                }
+                retain
+            });
+            !paths.is_empty()
+        });
+        let _cnt = self.evictions.fetch_add(removed as u64, Ordering::Relaxed);
+    }
+}
 
-        let entry = self.cache.get_or_insert(meta, || Entry::new(file));
-        Ok((&entry.file, &entry.value))
+impl<T> Debug for FileCache<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct(stringify!(FileCache))
+            .field("fs", &self.fs)
+            .field("advice", &self.advice)
+            .field("cache", &self.cache)
+            .field("hits", &self.hits)
+            .field("misses", &self.misses)
+            .field("evictions", &self.evictions)
+            .finish()
     }
 }
 
@@ -83,7 +317,7 @@ impl<T> FileCache<T> {
 mod tests {
     use super::*;
 
-    use std::io::Read as _;
+    use std::fs::File;
     use std::io::Write as _;
     use std::thread::sleep;
     use std::time::Duration;
@@ -91,6 +325,8 @@ mod tests {
     use tempfile::tempfile;
     use tempfile::NamedTempFile;
 
+    use crate::vfs::FileVersion;
+
 
     /// Exercise the `Debug` representation of various types.
     #[test]
@@ -99,7 +335,7 @@ mod tests {
         assert_ne!(format!("{cache:?}"), "");
 
         let tmpfile = tempfile().unwrap();
-        let entry = Entry::<usize>::new(tmpfile);
+        let entry = Entry::<usize>::new(FileData::from_file(&tmpfile).unwrap());
         assert_ne!(format!("{entry:?}"), "");
     }
 
@@ -110,16 +346,168 @@ mod tests {
         let tmpfile = NamedTempFile::new().unwrap();
 
         {
-            let (_file, cell) = cache.entry(tmpfile.path()).unwrap();
-            assert_eq!(cell.get(), None);
+            let entry = cache.entry(tmpfile.path()).unwrap();
+            assert_eq!(entry.value().get(), None);
+
+            let () = entry.value().set(42).unwrap();
+        }
+
+        {
+            let entry = cache.entry(tmpfile.path()).unwrap();
+            assert_eq!(entry.value().get(), Some(&42));
+        }
+    }
+
+    /// Check that cached files are not loaded again.
+    #[test]
+    fn lookup_without_loading() {
+        #[derive(Debug, Default)]
+        struct Counting {
+            opened: AtomicU64,
+        }
+
+        impl FileAccess for Counting {
+            fn open(&self, path: &Path) -> Result<FileData> {
+                let _cnt = self.opened.fetch_add(1, Ordering::Relaxed);
+                StdFileAccess::default().open(path)
+            }
+
+            fn version(&self, path: &Path) -> Result<Option<FileVersion>> {
+                StdFileAccess::default().version(path)
+            }
+        }
+
+        let fs = Arc::new(Counting::default());
+        let cache = FileCache::<usize>::with_file_access(fs.clone());
+        let mut tmpfile = NamedTempFile::new().unwrap();
+        let () = tmpfile.write_all(b"foobar").unwrap();
+
+        for _ in 0..3 {
+            let entry = cache.entry(tmpfile.path()).unwrap();
+            assert_eq!(entry.data().data(), b"foobar");
+        }
+        assert_eq!(fs.opened.load(Ordering::Relaxed), 1);
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 1);
+    }
+
+    /// Check that preloaded files are served from the cache.
+    #[test]
+    fn preload() {
+        let cache = FileCache::<usize>::new();
+        let mut tmpfile = NamedTempFile::new().unwrap();
+        let () = tmpfile.write_all(b"foobar").unwrap();
+        let missing = tmpfile.path().with_extension("missing");
+
+        let results = cache.preload(&[tmpfile.path(), &missing]);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap().data(), b"foobar");
+        assert!(results[1].is_err());
+
+        {
+            let entry = cache.entry(tmpfile.path()).unwrap();
+            assert_eq!(entry.data().data(), b"foobar");
+            let () = entry.value().set(42).unwrap();
+        }
+
+        // Preloading again keeps the existing entry.
+        let _results = cache.preload(&[tmpfile.path()]);
+        let entry = cache.entry(tmpfile.path()).unwrap();
+        assert_eq!(entry.value().get(), Some(&42));
+    }
 
-            let () = cell.set(42).unwrap();
+    /// Check that we track cache statistics and can evict entries.
+    #[test]
+    fn stats_and_eviction() {
+        let cache = FileCache::<usize>::new();
+        let mut tmpfile1 = NamedTempFile::new().unwrap();
+        let () = tmpfile1.write_all(b"foo").unwrap();
+        let mut tmpfile2 = NamedTempFile::new().unwrap();
+        let () = tmpfile2.write_all(b"foobar").unwrap();
+
+        {
+            let _entry = cache.entry(tmpfile1.path()).unwrap();
+            let _entry = cache.entry(tmpfile2.path()).unwrap();
+            let _entry = cache.entry(tmpfile1.path()).unwrap();
         }
 
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.evictions, 0);
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.bytes, 9);
+
+        // The second file is the least recently used one.
+        let mut usage = Vec::new();
+        let () = cache.usage(&mut usage);
+        let () = usage.sort();
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].1, 6);
+        let cutoff = usage[0].0;
+
+        let mut evicted = Vec::new();
+        let () = cache.retain(&mut |_path, last_use| last_use > cutoff, &mut evicted);
+        assert_eq!(evicted, vec![tmpfile2.path().to_path_buf()]);
+
+        let stats = cache.stats();
+        assert_eq!(stats.evictions, 1);
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.bytes, 3);
+
+        // Evicted entries stay valid while in use.
+        let entry = cache.entry(tmpfile1.path()).unwrap();
+        let mut evicted = Vec::new();
+        let () = cache.retain(&mut |_path, _last_use| false, &mut evicted);
+        assert_eq!(evicted, vec![tmpfile1.path().to_path_buf()]);
+        assert_eq!(entry.data().data(), b"foo");
+        assert_eq!(Arc::strong_count(&entry), 1);
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.evictions, 2);
+        assert_eq!(stats.entries, 0);
+    }
+
+    /// Check that the same file accessed through different paths
+    /// shares a single cache entry.
+    #[cfg(unix)]
+    #[test]
+    fn shared_entries() {
+        let cache = FileCache::<usize>::new();
+        let mut tmpfile = NamedTempFile::new().unwrap();
+        let () = tmpfile.write_all(b"foobar").unwrap();
+        let link = tmpfile.path().with_extension("link");
+        let () = std::fs::hard_link(tmpfile.path(), &link).unwrap();
+
         {
-            let (_file, cell) = cache.entry(tmpfile.path()).unwrap();
-            assert_eq!(cell.get(), Some(&42));
+            let entry = cache.entry(tmpfile.path()).unwrap();
+            let () = entry.value().set(42).unwrap();
+            let entry = cache.entry(&link).unwrap();
+            assert_eq!(entry.value().get(), Some(&42));
         }
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.entries, 1);
+
+        // Evicting one of the paths keeps the entry alive for the
+        // other one.
+        let mut evicted = Vec::new();
+        let () = cache.retain(&mut |path, _last_use| path != link, &mut evicted);
+        assert_eq!(evicted, vec![link.clone()]);
+        assert_eq!(cache.stats().entries, 1);
+
+        let mut evicted = Vec::new();
+        let () = cache.retain(&mut |_path, _last_use| false, &mut evicted);
+        assert_eq!(evicted, vec![tmpfile.path().to_path_buf()]);
+        assert_eq!(cache.stats().entries, 0);
+
+        let () = std::fs::remove_file(&link).unwrap();
     }
 
     /// Make sure that a changed file purges the cache entry.
@@ -127,13 +515,12 @@ mod tests {
     fn outdated() {
         let cache = FileCache::<usize>::new();
         let tmpfile = NamedTempFile::new().unwrap();
-        let modified = {
-            let (file, cell) = cache.entry(tmpfile.path()).unwrap();
-            assert_eq!(cell.get(), None);
+        {
+            let entry = cache.entry(tmpfile.path()).unwrap();
+            assert_eq!(entry.value().get(), None);
 
-            let () = cell.set(42).unwrap();
-            file.metadata().unwrap().modified().unwrap()
-        };
+            let () = entry.value().set(42).unwrap();
+        }
 
         // Sleep briefly to make sure that file times will end up being
         // different.
@@ -143,13 +530,9 @@ mod tests {
         let () = file.write_all(b"foobar").unwrap();
 
         {
-            let (mut file, entry) = cache.entry(tmpfile.path()).unwrap();
-            assert_eq!(entry.get(), None);
-            assert_ne!(file.metadata().unwrap().modified().unwrap(), modified);
-
-            let mut content = Vec::new();
-            let _count = file.read_to_end(&mut content);
-            assert_eq!(content, b"foobar");
+            let entry = cache.entry(tmpfile.path()).unwrap();
+            assert_eq!(entry.value().get(), None);
+            assert_eq!(entry.data().data(), b"foobar");
         }
     }
 }
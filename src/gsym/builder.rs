@@ -0,0 +1,637 @@
+//! Creation of Gsym data from ELF files.
+
+use std::borrow::Cow;
+#[cfg(feature = "dwarf")]
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::mem::size_of;
+#[cfg(feature = "dwarf")]
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+#[cfg(feature = "dwarf")]
+use crate::dwarf::DwarfResolver;
+#[cfg(feature = "dwarf")]
+use crate::dwarf::FnInfo;
+#[cfg(feature = "dwarf")]
+use crate::dwarf::InlinedFnInfo;
+#[cfg(feature = "dwarf")]
+use crate::dwarf::LineInfo;
+use crate::elf::ElfParser;
+use crate::inspect::FindAddrOpts;
+use crate::mmap::Mmap;
+use crate::normalize::buildid::read_elf_build_id;
+use crate::util::os_str_to_bytes;
+#[cfg(feature = "dwarf")]
+use crate::util::push_i64_leb128;
+#[cfg(feature = "dwarf")]
+use crate::util::push_u64_leb128;
+#[cfg(feature = "dwarf")]
+use crate::vfs::StdFileAccess;
+use crate::Error;
+use crate::ErrorExt as _;
+use crate::Result;
+
+#[cfg(feature = "dwarf")]
+use super::linetab::ADVANCE_LINE;
+#[cfg(feature = "dwarf")]
+use super::linetab::ADVANCE_PC;
+#[cfg(feature = "dwarf")]
+use super::linetab::END_SEQUENCE;
+use super::linetab::FIRST_SPECIAL;
+#[cfg(feature = "dwarf")]
+use super::linetab::SET_FILE;
+use super::types::GSYM_MAGIC;
+use super::types::GSYM_VERSION;
+use super::types::INFO_TYPE_END_OF_LIST;
+use super::types::INFO_TYPE_INLINE_INFO;
+use super::types::INFO_TYPE_LINE_TABLE_INFO;
+
+
+/// The smallest line delta encodable in a special line table opcode.
+const MIN_LINE_DELTA: i64 = -4;
+/// The largest line delta encodable in a special line table opcode.
+const MAX_LINE_DELTA: i64 = 10;
+/// The size of the header of a Gsym file, in bytes.
+const HEADER_SIZE: usize = 48;
+
+
+/// Retrieve the raw bytes making up an `OsStr`, converting it lossily
+/// where necessary.
+fn os_str_bytes(s: &OsStr) -> Cow<'_, [u8]> {
+    match os_str_to_bytes(s) {
+        Some(bytes) => Cow::Borrowed(bytes),
+        None => Cow::Owned(s.to_string_lossy().into_owned().into_bytes()),
+    }
+}
+
+
+/// The string table of a Gsym file under construction.
+struct StrTab {
+    /// The NUL terminated strings, concatenated.
+    data: Vec<u8>,
+    /// The offsets of the strings added so far.
+    offsets: HashMap<Vec<u8>, u32>,
+}
+
+impl StrTab {
+    fn new() -> Self {
+        // The empty string always resides at offset zero.
+        Self {
+            data: vec![0],
+            offsets: HashMap::from([(Vec::new(), 0)]),
+        }
+    }
+
+    /// Add a string to the table, returning its offset.
+    fn insert(&mut self, s: &[u8]) -> u32 {
+        if let Some(offset) = self.offsets.get(s) {
+            return *offset
+        }
+
+        let offset = self.data.len() as u32;
+        let () = self.data.extend_from_slice(s);
+        let () = self.data.push(0);
+        let _prev = self.offsets.insert(s.to_vec(), offset);
+        offset
+    }
+}
+
+
+/// The file table of a Gsym file under construction.
+struct FileTab {
+    /// The directory and file name string table offsets of each file.
+    files: Vec<(u32, u32)>,
+    /// The indexes of the files added so far.
+    indexes: HashMap<(u32, u32), u32>,
+}
+
+impl FileTab {
+    fn new() -> Self {
+        // Index zero is reserved for "no file".
+        Self {
+            files: vec![(0, 0)],
+            indexes: HashMap::from([((0, 0), 0)]),
+        }
+    }
+
+    /// Add a file to the table, returning its index.
+    fn insert(&mut self, strtab: &mut StrTab, dir: &Path, file: &OsStr) -> u32 {
+        let dir = strtab.insert(&os_str_bytes(dir.as_os_str()));
+        let file = strtab.insert(&os_str_bytes(file));
+        let key = (dir, file);
+
+        *self.indexes.entry(key).or_insert_with(|| {
+            let idx = self.files.len() as u32;
+            let () = self.files.push(key);
+            idx
+        })
+    }
+}
+
+
+/// A function as stored in a Gsym file.
+struct Func {
+    /// The size of the function, in bytes.
+    size: u32,
+    /// The string table offset of the function's name.
+    name: u32,
+    /// The encoded line table of the function, if any.
+    line_table: Option<Vec<u8>>,
+    /// The encoded inline information of the function, if any.
+    inline_info: Option<Vec<u8>>,
+}
+
+
+/// Try encoding the provided deltas as a special line table opcode.
+fn special_op(line_delta: i64, addr_delta: u64) -> Option<u8> {
+    if !(MIN_LINE_DELTA..=MAX_LINE_DELTA).contains(&line_delta) {
+        return None
+    }
+    let range = (MAX_LINE_DELTA - MIN_LINE_DELTA + 1) as u64;
+    let op = ((line_delta - MIN_LINE_DELTA) as u64)
+        .checked_add(addr_delta.checked_mul(range)?)?
+        .checked_add(u64::from(FIRST_SPECIAL))?;
+    u8::try_from(op).ok()
+}
+
+
+/// Encode the line table of a function starting at `start`.
+#[cfg(feature = "dwarf")]
+fn encode_line_table(
+    lines: &[LineInfo<'_>],
+    start: u64,
+    strtab: &mut StrTab,
+    filetab: &mut FileTab,
+) -> Option<Vec<u8>> {
+    let first = lines.first()?;
+
+    let mut data = Vec::new();
+    let () = push_i64_leb128(&mut data, MIN_LINE_DELTA);
+    let () = push_i64_leb128(&mut data, MAX_LINE_DELTA);
+    let () = push_u64_leb128(&mut data, first.line.into());
+
+    let mut prev_addr = start;
+    let mut prev_file = 1;
+    let mut prev_line = i64::from(first.line);
+    for line in lines {
+        let (dir, file) = line.file;
+        let file = filetab.insert(strtab, dir, file);
+        if file != prev_file {
+            let () = data.push(SET_FILE);
+            let () = push_u64_leb128(&mut data, file.into());
+            prev_file = file;
+        }
+
+        let line_delta = i64::from(line.line) - prev_line;
+        let addr_delta = line.addr - prev_addr;
+        if let Some(op) = special_op(line_delta, addr_delta) {
+            let () = data.push(op);
+        } else {
+            if line_delta != 0 {
+                let () = data.push(ADVANCE_LINE);
+                let () = push_i64_leb128(&mut data, line_delta);
+            }
+            let () = data.push(ADVANCE_PC);
+            let () = push_u64_leb128(&mut data, addr_delta);
+        }
+        prev_addr = line.addr;
+        prev_line = i64::from(line.line);
+    }
+    let () = data.push(END_SEQUENCE);
+    Some(data)
+}
+
+
+/// An entry of the inline information tree of a Gsym file.
+#[cfg(feature = "dwarf")]
+struct InlineEntry<'inl> {
+    ranges: Vec<Range<u64>>,
+    name: u32,
+    call_file: u32,
+    call_line: u32,
+    children: &'inl [InlinedFnInfo<'inl>],
+}
+
+#[cfg(feature = "dwarf")]
+impl InlineEntry<'_> {
+    /// Encode the entry, with its ranges being relative to `base`.
+    ///
+    /// Ranges are clipped to `bounds`. Entries without any ranges are
+    /// omitted, along with their children.
+    fn encode(
+        &self,
+        base: u64,
+        bounds: &Range<u64>,
+        strtab: &mut StrTab,
+        filetab: &mut FileTab,
+        data: &mut Vec<u8>,
+    ) {
+        let ranges = self
+            .ranges
+            .iter()
+            .map(|range| range.start.max(bounds.start)..range.end.min(bounds.end))
+            .filter(|range| !range.is_empty() && range.start >= base)
+            .collect::<Vec<_>>();
+        let child_base = match ranges.first() {
+            Some(range) => range.start,
+            None => return,
+        };
+
+        let mut children = Vec::new();
+        for child in self.children {
+            let call_file = child
+                .call_file
+                .map(|(dir, file)| filetab.insert(strtab, dir, file))
+                .unwrap_or(0);
+            let entry = InlineEntry {
+                ranges: child.ranges.clone(),
+                name: child
+                    .name
+                    .map(|name| strtab.insert(name.as_bytes()))
+                    .unwrap_or(0),
+                call_file,
+                call_line: child.call_line,
+                children: &child.children,
+            };
+            let () = entry.encode(child_base, bounds, strtab, filetab, &mut children);
+        }
+
+        let () = push_u64_leb128(data, ranges.len() as u64);
+        for range in &ranges {
+            let () = push_u64_leb128(data, range.start - base);
+            let () = push_u64_leb128(data, range.end - range.start);
+        }
+        let has_children = !children.is_empty();
+        let () = data.push(u8::from(has_children));
+        let () = data.extend_from_slice(&self.name.to_ne_bytes());
+        let () = push_u64_leb128(data, self.call_file.into());
+        let () = push_u64_leb128(data, self.call_line.into());
+        if has_children {
+            let () = data.extend_from_slice(&children);
+            // A range count of zero terminates the list of children.
+            let () = push_u64_leb128(data, 0);
+        }
+    }
+}
+
+
+/// Align `data` to a multiple of four bytes.
+fn align4(data: &mut Vec<u8>) {
+    let () = data.resize((data.len() + 3) & !3, 0);
+}
+
+
+/// A builder for creating Gsym data from an ELF file.
+///
+/// Functions are read from the ELF file's symbol tables as well as its
+/// DWARF debug information (if the `dwarf` feature is enabled). The
+/// latter also provides source code location information, including
+/// that of inlined function calls.
+///
+/// # Examples
+/// ```
+/// # use std::path::Path;
+/// use blazesym::gsym;
+/// use blazesym::symbolize::GsymData;
+/// use blazesym::symbolize::Input;
+/// use blazesym::symbolize::Source;
+/// use blazesym::symbolize::Symbolizer;
+///
+/// # let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+/// #     .join("data")
+/// #     .join("test-stable-addresses.bin");
+/// let data = gsym::Builder::default().build(&path).unwrap();
+///
+/// let src = Source::from(GsymData::new(&data));
+/// let symbolizer = Symbolizer::new();
+/// let sym = symbolizer
+///     .symbolize_single(&src, Input::VirtOffset(0x2000100))
+///     .unwrap()
+///     .into_sym()
+///     .unwrap();
+/// assert_eq!(sym.name, "factorial");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Builder {
+    /// Whether to include source code location information.
+    code_info: bool,
+    /// Whether to include information about inlined function calls.
+    inlined_fns: bool,
+}
+
+impl Builder {
+    /// Enable/disable the inclusion of source code location information
+    /// (line numbers, file names etc.).
+    pub fn enable_code_info(mut self, enable: bool) -> Builder {
+        self.code_info = enable;
+        self
+    }
+
+    /// Enable/disable the inclusion of inlined function information.
+    pub fn enable_inlined_fns(mut self, enable: bool) -> Builder {
+        self.inlined_fns = enable;
+        self
+    }
+
+    #[cfg(feature = "dwarf")]
+    fn add_dwarf_fns(
+        &self,
+        path: &Path,
+        parser: &Arc<ElfParser>,
+        funcs: &mut BTreeMap<u64, Func>,
+        strtab: &mut StrTab,
+        filetab: &mut FileTab,
+    ) -> Result<()> {
+        let fs = Arc::new(StdFileAccess::default());
+        let resolver = DwarfResolver::from_parser(path, parser.clone(), fs, self.code_info, None)?;
+        let () = resolver.for_each_function(|info: FnInfo<'_>| {
+            let FnInfo {
+                name,
+                range,
+                lines,
+                inlined,
+            } = info;
+
+            let line_table = self
+                .code_info
+                .then(|| encode_line_table(&lines, range.start, strtab, filetab))
+                .flatten();
+            let inline_info = (self.inlined_fns && !inlined.is_empty()).then(|| {
+                let name = strtab.insert(name.as_bytes());
+                let entry = InlineEntry {
+                    ranges: vec![range.clone()],
+                    name,
+                    call_file: 0,
+                    call_line: 0,
+                    children: &inlined,
+                };
+                let mut data = Vec::new();
+                let () = entry.encode(range.start, &range, strtab, filetab, &mut data);
+                data
+            });
+            let func = Func {
+                size: u32::try_from(range.end - range.start).unwrap_or(u32::MAX),
+                name: strtab.insert(name.as_bytes()),
+                line_table,
+                inline_info,
+            };
+
+            match funcs.entry(range.start) {
+                Entry::Vacant(entry) => {
+                    let _func = entry.insert(func);
+                }
+                // The same code may be described by multiple functions
+                // (e.g., due to identical code folding). Prefer the one
+                // carrying line information.
+                Entry::Occupied(mut entry) => {
+                    if entry.get().line_table.is_none() && func.line_table.is_some() {
+                        let _prev = entry.insert(func);
+                    }
+                }
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    fn add_elf_syms(
+        &self,
+        parser: &ElfParser,
+        funcs: &mut BTreeMap<u64, Func>,
+        strtab: &mut StrTab,
+    ) -> Result<()> {
+        let syms = parser.for_each_sym(&FindAddrOpts::default(), Vec::new(), |mut syms, sym| {
+            let () = syms.push((sym.addr, sym.size, sym.name.to_string()));
+            syms
+        })?;
+
+        for (addr, size, name) in syms {
+            if addr == 0 {
+                continue
+            }
+            // Symbols falling into a function that we already know
+            // about would shadow the remainder of it.
+            if let Some((start, func)) = funcs.range(..=addr).next_back() {
+                if *start == addr || addr < start + u64::from(func.size) {
+                    continue
+                }
+            }
+
+            let func = Func {
+                size: u32::try_from(size).unwrap_or(u32::MAX),
+                name: strtab.insert(name.as_bytes()),
+                line_table: None,
+                inline_info: None,
+            };
+            let _prev = funcs.insert(addr, func);
+        }
+        Ok(())
+    }
+
+    /// Create Gsym data for the ELF file at `path`.
+    pub fn build(&self, path: &Path) -> Result<Vec<u8>> {
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        let mmap = Mmap::map(&file).with_context(|| format!("failed to map {}", path.display()))?;
+        let parser = Arc::new(ElfParser::from_mmap(mmap));
+
+        let mut funcs = BTreeMap::new();
+        let mut strtab = StrTab::new();
+        #[cfg_attr(not(feature = "dwarf"), allow(unused_mut))]
+        let mut filetab = FileTab::new();
+
+        #[cfg(feature = "dwarf")]
+        let () = self
+            .add_dwarf_fns(path, &parser, &mut funcs, &mut strtab, &mut filetab)
+            .context("failed to read DWARF debug information")?;
+        let () = self.add_elf_syms(&parser, &mut funcs, &mut strtab)?;
+
+        let uuid = read_elf_build_id(&path)?.unwrap_or_default();
+        write(&funcs, &strtab, &filetab, &uuid)
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            code_info: true,
+            inlined_fns: true,
+        }
+    }
+}
+
+
+/// Serialize the provided functions into Gsym data.
+fn write(
+    funcs: &BTreeMap<u64, Func>,
+    strtab: &StrTab,
+    filetab: &FileTab,
+    uuid: &[u8],
+) -> Result<Vec<u8>> {
+    let num_addrs = u32::try_from(funcs.len())
+        .map_err(|_| Error::with_invalid_data("too many functions for Gsym format"))?;
+    let base_addr = funcs.keys().next().copied().unwrap_or(0);
+    let max_offset = funcs.keys().next_back().copied().unwrap_or(0) - base_addr;
+    let addr_off_size = if max_offset <= u8::MAX.into() {
+        size_of::<u8>()
+    } else if max_offset <= u16::MAX.into() {
+        size_of::<u16>()
+    } else if max_offset <= u32::MAX.into() {
+        size_of::<u32>()
+    } else {
+        size_of::<u64>()
+    };
+
+    // The string table follows the various fixed size tables, which we
+    // write in a second step once all offsets are known.
+    let mut strtab_offset = HEADER_SIZE + funcs.len() * addr_off_size;
+    strtab_offset = (strtab_offset + 3) & !3;
+    strtab_offset += funcs.len() * size_of::<u32>();
+    strtab_offset += size_of::<u32>() + filetab.files.len() * 2 * size_of::<u32>();
+
+    let mut data = vec![0; strtab_offset];
+    let () = data.extend_from_slice(&strtab.data);
+
+    let mut addr_tab = Vec::with_capacity(funcs.len() * addr_off_size);
+    let mut addr_info_offsets = Vec::with_capacity(funcs.len());
+    for (addr, func) in funcs {
+        let offset = addr - base_addr;
+        match addr_off_size {
+            1 => addr_tab.push(offset as u8),
+            2 => addr_tab.extend_from_slice(&(offset as u16).to_ne_bytes()),
+            4 => addr_tab.extend_from_slice(&(offset as u32).to_ne_bytes()),
+            _ => addr_tab.extend_from_slice(&offset.to_ne_bytes()),
+        }
+
+        let () = align4(&mut data);
+        let offset = u32::try_from(data.len())
+            .map_err(|_| Error::with_invalid_data("Gsym data exceed maximum size"))?;
+        let () = addr_info_offsets.push(offset);
+
+        let () = data.extend_from_slice(&func.size.to_ne_bytes());
+        let () = data.extend_from_slice(&func.name.to_ne_bytes());
+        for (typ, info) in [
+            (INFO_TYPE_LINE_TABLE_INFO, &func.line_table),
+            (INFO_TYPE_INLINE_INFO, &func.inline_info),
+        ] {
+            if let Some(info) = info {
+                let () = data.extend_from_slice(&typ.to_ne_bytes());
+                let () = data.extend_from_slice(&(info.len() as u32).to_ne_bytes());
+                let () = data.extend_from_slice(info);
+            }
+        }
+        let () = data.extend_from_slice(&INFO_TYPE_END_OF_LIST.to_ne_bytes());
+    }
+
+    let mut tables = Vec::with_capacity(strtab_offset);
+    let () = tables.extend_from_slice(&GSYM_MAGIC.to_ne_bytes());
+    let () = tables.extend_from_slice(&GSYM_VERSION.to_ne_bytes());
+    let () = tables.push(addr_off_size as u8);
+    let uuid = &uuid[..uuid.len().min(20)];
+    let () = tables.push(uuid.len() as u8);
+    let () = tables.extend_from_slice(&base_addr.to_ne_bytes());
+    let () = tables.extend_from_slice(&num_addrs.to_ne_bytes());
+    let () = tables.extend_from_slice(&(strtab_offset as u32).to_ne_bytes());
+    let () = tables.extend_from_slice(&(strtab.data.len() as u32).to_ne_bytes());
+    let () = tables.extend_from_slice(uuid);
+    let () = tables.resize(HEADER_SIZE, 0);
+    let () = tables.extend_from_slice(&addr_tab);
+    let () = align4(&mut tables);
+    for offset in addr_info_offsets {
+        let () = tables.extend_from_slice(&offset.to_ne_bytes());
+    }
+    let () = tables.extend_from_slice(&(filetab.files.len() as u32).to_ne_bytes());
+    for (dir, file) in &filetab.files {
+        let () = tables.extend_from_slice(&dir.to_ne_bytes());
+        let () = tables.extend_from_slice(&file.to_ne_bytes());
+    }
+    debug_assert_eq!(tables.len(), strtab_offset);
+
+    let () = data[..strtab_offset].copy_from_slice(&tables);
+    Ok(data)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::gsym::GsymResolver;
+    use crate::SymResolver as _;
+
+
+    /// Check that we can convert an ELF file into Gsym data and look
+    /// up symbols and source code information in the result.
+    #[cfg(feature = "dwarf")]
+    #[test]
+    fn elf_conversion() {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-stable-addresses.bin");
+        let data = Builder::default().build(&path).unwrap();
+        let resolver = GsymResolver::with_data(&data).unwrap();
+
+        let sym = resolver.find_sym(0x2000000).unwrap().unwrap();
+        assert_eq!(sym.name, "main");
+        let info = resolver.find_code_info(0x2000000, true).unwrap().unwrap();
+        assert_eq!(info.direct.1.line, Some(50));
+        assert_eq!(info.direct.1.file, OsStr::new("test-stable-addresses.c"));
+        assert_eq!(info.inlined, Vec::new());
+
+        let sym = resolver.find_sym(0x2000100).unwrap().unwrap();
+        assert_eq!(sym.name, "factorial");
+        let info = resolver.find_code_info(0x2000100, true).unwrap().unwrap();
+        assert_eq!(info.direct.1.line, Some(8));
+        assert_eq!(info.direct.1.file, OsStr::new("test-stable-addresses.c"));
+        assert_eq!(info.inlined, Vec::new());
+
+        let addr = 0x200020a;
+        let sym = resolver.find_sym(addr).unwrap().unwrap();
+        assert_eq!(sym.name, "factorial_inline_test");
+
+        let info = resolver.find_code_info(addr, true).unwrap().unwrap();
+        assert_eq!(info.direct.1.line, Some(32));
+        assert_eq!(info.direct.1.file, OsStr::new("test-stable-addresses.c"));
+        assert_eq!(info.inlined.len(), 2);
+
+        let name = &info.inlined[0].0;
+        assert_eq!(*name, "factorial_inline_wrapper");
+        let frame = info.inlined[0].1.as_ref().unwrap();
+        assert_eq!(frame.file, OsStr::new("test-stable-addresses.c"));
+        assert_eq!(frame.line, Some(26));
+
+        let name = &info.inlined[1].0;
+        assert_eq!(*name, "factorial_2nd_layer_inline_wrapper");
+        let frame = info.inlined[1].1.as_ref().unwrap();
+        assert_eq!(frame.file, OsStr::new("test-stable-addresses.c"));
+        assert_eq!(frame.line, Some(21));
+
+        let info = resolver.find_code_info(addr, false).unwrap().unwrap();
+        assert_eq!(info.direct.1.line, Some(21));
+        assert_eq!(info.inlined, Vec::new());
+    }
+
+    /// Check that disabling source code information omits line tables
+    /// while still reporting symbols.
+    #[test]
+    fn elf_conversion_without_code_info() {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-stable-addresses.bin");
+        let data = Builder::default()
+            .enable_code_info(false)
+            .build(&path)
+            .unwrap();
+        let resolver = GsymResolver::with_data(&data).unwrap();
+
+        let sym = resolver.find_sym(0x2000100).unwrap().unwrap();
+        assert_eq!(sym.name, "factorial");
+        let info = resolver.find_code_info(0x2000100, true).unwrap();
+        assert_eq!(info, None);
+    }
+}
@@ -4,15 +4,15 @@ use crate::util::ReadRaw as _;
 use crate::Addr;
 
 /// End of the line table
-const END_SEQUENCE: u8 = 0x00;
+pub(super) const END_SEQUENCE: u8 = 0x00;
 /// Set [`LineTableRow.file_idx`], don't push a row.
-const SET_FILE: u8 = 0x01;
+pub(super) const SET_FILE: u8 = 0x01;
 /// Increment [`LineTableRow.address`], and push a row.
-const ADVANCE_PC: u8 = 0x02;
+pub(super) const ADVANCE_PC: u8 = 0x02;
 /// Set [`LineTableRow.file_line`], don't push a row.
-const ADVANCE_LINE: u8 = 0x03;
+pub(super) const ADVANCE_LINE: u8 = 0x03;
 /// All special opcodes push a row.
-const FIRST_SPECIAL: u8 = 0x04;
+pub(super) const FIRST_SPECIAL: u8 = 0x04;
 
 
 #[derive(Debug)]
@@ -113,12 +113,12 @@ pub fn run_op(
         }
         ADVANCE_PC => {
             let (adv, _bytes) = ops.read_u64_leb128()?;
-            row.addr += adv as Addr;
+            row.addr = row.addr.checked_add(adv as Addr)?;
             Some(RunResult::NewRow)
         }
         ADVANCE_LINE => {
             let (adv, _bytes) = ops.read_i64_leb128()?;
-            row.file_line = (row.file_line as i64 + adv) as u32;
+            row.file_line = (row.file_line as i64).wrapping_add(adv) as u32;
             Some(RunResult::Ok)
         }
         // Special operators.
@@ -131,16 +131,19 @@ pub fn run_op(
             let adjusted = (op - FIRST_SPECIAL) as i64;
             // The range of line number delta is from min_delta to max_delta,
             // including max_delta.
-            let range = header.max_delta - header.min_delta + 1;
-            if range == 0 {
+            let range = header
+                .max_delta
+                .checked_sub(header.min_delta)?
+                .checked_add(1)?;
+            if range <= 0 {
                 return None
             }
-            let line_delta = header.min_delta + (adjusted % range);
+            let line_delta = header.min_delta.wrapping_add(adjusted % range);
             let addr_delta = adjusted / range;
 
-            let file_line = row.file_line as i32 + line_delta as i32;
+            let file_line = (row.file_line as i32).wrapping_add(line_delta as i32);
             row.file_line = file_line as u32;
-            row.addr += addr_delta as Addr;
+            row.addr = row.addr.checked_add(addr_delta as Addr)?;
             Some(RunResult::NewRow)
         }
     }
@@ -1,7 +1,11 @@
+//! Functionality for working with Gsym files.
+
+mod builder;
 mod inline;
 mod linetab;
 mod parser;
 mod resolver;
 mod types;
 
-pub use resolver::GsymResolver;
+pub use builder::Builder;
+pub(crate) use resolver::GsymResolver;
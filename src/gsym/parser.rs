@@ -38,8 +38,9 @@
 use std::ffi::OsStr;
 use std::iter;
 use std::mem::align_of;
-use std::os::unix::ffi::OsStrExt as _;
 
+use crate::scope;
+use crate::util::bytes_to_os_str;
 use crate::util::find_match_or_lower_bound;
 use crate::util::Pod;
 use crate::util::ReadRaw as _;
@@ -83,15 +84,27 @@ impl GsymContext<'_> {
     ///
     /// Returns a GsymContext, which includes the Header and other important tables.
     pub fn parse_header(data: &[u8]) -> Result<GsymContext> {
+        if let Some(limits) = scope::limits() {
+            if data.len() as u64 > limits.max_section_size {
+                return Err(Error::with_invalid_data(format!(
+                    "GSYM data exceed size limit ({} > {})",
+                    data.len(),
+                    limits.max_section_size
+                )))
+            }
+        }
+
         fn parse_header_impl(mut data: &[u8]) -> Option<Result<GsymContext>> {
             let head = data;
             let magic = data.read_u32()?;
             if magic != GSYM_MAGIC {
-                return Some(Err(Error::with_invalid_data("invalid magic number")))
+                return Some(Err(Error::with_unsupported_format("invalid magic number")))
             }
             let version = data.read_u16()?;
             if version != GSYM_VERSION {
-                return Some(Err(Error::with_invalid_data("unknown version number")))
+                return Some(Err(Error::with_unsupported_format(
+                    "unknown version number",
+                )))
             }
 
             let addr_off_size = data.read_u8()?;
@@ -197,13 +210,19 @@ impl GsymContext<'_> {
     #[inline]
     pub fn get_str(&self, offset: usize) -> Option<&OsStr> {
         let bytes = self.str_tab.get(offset..)?.read_cstr()?.to_bytes();
-        Some(OsStr::from_bytes(bytes))
+        bytes_to_os_str(bytes)
     }
 
     #[inline]
     pub fn file_info(&self, idx: usize) -> Option<&FileInfo> {
         self.file_tab.get(idx)
     }
+    /// Get the UUID of the GSYM file, if one is present.
+    pub fn uuid(&self) -> Option<&[u8]> {
+        let size = usize::from(self.header.uuid_size).min(self.header.uuid.len());
+        let uuid = &self.header.uuid[..size];
+        (!uuid.is_empty()).then_some(uuid)
+    }
 }
 
 
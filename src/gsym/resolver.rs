@@ -3,7 +3,6 @@ use std::ffi::OsStr;
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
-use std::fs::File;
 use std::mem;
 use std::mem::swap;
 use std::path::Path;
@@ -14,6 +13,7 @@ use crate::inspect::SymInfo;
 use crate::mmap::Mmap;
 use crate::symbolize::AddrCodeInfo;
 use crate::symbolize::CodeInfo;
+use crate::symbolize::IntModule;
 use crate::symbolize::IntSym;
 use crate::symbolize::Reason;
 use crate::symbolize::SrcLang;
@@ -56,12 +56,7 @@ impl GsymResolver<'static> {
         Self::from_mmap(path, mmap)
     }
 
-    pub fn from_file(path: PathBuf, file: &File) -> Result<Self> {
-        let mmap = Mmap::map(file)?;
-        Self::from_mmap(path, mmap)
-    }
-
-    fn from_mmap(path: PathBuf, mmap: Mmap) -> Result<Self> {
+    pub(crate) fn from_mmap(path: PathBuf, mmap: Mmap) -> Result<Self> {
         let ctx = GsymContext::parse_header(&mmap)?;
         let slf = Self {
             file_name: Some(path),
@@ -112,6 +107,7 @@ impl<'dat> GsymResolver<'dat> {
             file: Cow::Borrowed(OsStr::new(file)),
             line,
             column: None,
+            context: None,
             _non_exhaustive: (),
         };
         Ok(info)
@@ -236,8 +232,8 @@ impl SymResolver for GsymResolver<'_> {
                         line_tab_info = self.parse_line_tab_info(addr_ent.data, symaddr, addr)?;
                     }
                 }
-                INFO_TYPE_INLINE_INFO if inlined_fns => {
-                    if inline_info.is_none() {
+                INFO_TYPE_INLINE_INFO => {
+                    if inlined_fns && inline_info.is_none() {
                         let mut data = addr_ent.data;
                         inline_info = InlineInfo::parse(&mut data, symaddr, Some(addr))?;
                     }
@@ -316,6 +312,17 @@ impl SymResolver for GsymResolver<'_> {
             Ok(None)
         }
     }
+
+    fn module_info(&self, _addr: Addr) -> Result<IntModule<'_>> {
+        let module = IntModule {
+            path: self.file_name.as_deref(),
+            build_id: self.ctx.uuid(),
+            // There is no notion of file offsets for addresses in
+            // Gsym files.
+            file_offset: None,
+        };
+        Ok(module)
+    }
 }
 
 impl Debug for GsymResolver<'_> {
@@ -0,0 +1,132 @@
+//! Support for accessing the original images of files mapped into a
+//! process, which may have been deleted or replaced on disk since.
+
+use std::fs;
+use std::mem::size_of;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::elf::types::Elf64_Ehdr;
+use crate::elf::types::Elf64_Phdr;
+use crate::elf::types::PT_LOAD;
+use crate::elf::types::PT_NOTE;
+use crate::maps::PathMapsEntry;
+use crate::normalize::buildid::read_build_id_from_note_data;
+use crate::util::read_file_at;
+use crate::util::ReadRaw as _;
+use crate::Pid;
+use crate::Result;
+
+
+/// The maximum size of a note segment we are willing to read from
+/// process memory.
+const MAX_NOTES_SIZE: u64 = 64 * 1024;
+
+
+/// Retrieve the path under which the kernel provides access to the
+/// main executable of process `pid`.
+pub(crate) fn exe_path(pid: Pid) -> PathBuf {
+    PathBuf::from(format!("/proc/{pid}/exe"))
+}
+
+/// Check whether `path`, as reported in `/proc/<pid>/maps`, refers to
+/// the main executable of process `pid`.
+pub(crate) fn is_exe(pid: Pid, path: &Path) -> bool {
+    match fs::read_link(exe_path(pid)) {
+        Ok(exe) => {
+            let exe = exe.to_string_lossy();
+            let exe = exe.strip_suffix(" (deleted)").unwrap_or(&exe);
+            Path::new(exe) == path
+        }
+        Err(..) => false,
+    }
+}
+
+/// Read the build ID of the ELF file mapped by `entry` from the memory
+/// of process `pid`.
+///
+/// The ELF header is expected to be mapped at the address corresponding
+/// to file offset zero, as is the case for binaries loaded by the
+/// kernel or the dynamic linker. `None` is returned if no build ID
+/// could be found.
+pub(crate) fn read_mapped_build_id(pid: Pid, entry: &PathMapsEntry) -> Result<Option<Vec<u8>>> {
+    let base = match entry.range.start.checked_sub(entry.offset) {
+        Some(base) => base,
+        None => return Ok(None),
+    };
+    let mem = format!("/proc/{pid}/mem");
+
+    let mut ehdr = [0; size_of::<Elf64_Ehdr>()];
+    let () = read_file_at(&mem, base, &mut ehdr)?;
+    let ehdr = match ehdr.as_slice().read_pod::<Elf64_Ehdr>() {
+        Some(ehdr) if ehdr.e_ident.starts_with(b"\x7fELF") => ehdr,
+        _ => return Ok(None),
+    };
+
+    let mut phdrs = vec![0; usize::from(ehdr.e_phnum) * size_of::<Elf64_Phdr>()];
+    let () = read_file_at(&mem, base + ehdr.e_phoff, &mut phdrs)?;
+    let phdrs = phdrs
+        .chunks_exact(size_of::<Elf64_Phdr>())
+        .filter_map(|mut phdr| phdr.read_pod::<Elf64_Phdr>())
+        .collect::<Vec<_>>();
+
+    // The virtual address corresponding to file offset zero, i.e.,
+    // the one at which we found the ELF header.
+    let first_vaddr = match phdrs.iter().find(|phdr| phdr.p_type == PT_LOAD) {
+        Some(phdr) => phdr.p_vaddr.wrapping_sub(phdr.p_offset),
+        None => return Ok(None),
+    };
+
+    for phdr in phdrs.iter().filter(|phdr| phdr.p_type == PT_NOTE) {
+        let addr = base.wrapping_add(phdr.p_vaddr.wrapping_sub(first_vaddr));
+        let mut notes = vec![0; phdr.p_filesz.min(MAX_NOTES_SIZE) as usize];
+        let () = read_file_at(&mem, addr, &mut notes)?;
+        // Note segments may use an alignment that we do not support
+        // parsing. Just skip those.
+        if let Ok(Some(build_id)) = read_build_id_from_note_data(&notes) {
+            return Ok(Some(build_id))
+        }
+    }
+    Ok(None)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env::current_exe;
+
+    use crate::maps;
+    use crate::maps::PathName;
+    use crate::normalize::buildid::read_elf_build_id;
+    use crate::vfs::StdFileAccess;
+
+
+    /// Check that we can read the build ID of our own executable from
+    /// memory.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn mapped_build_id_reading() {
+        let exe = current_exe().unwrap();
+        assert!(is_exe(Pid::Slf, &exe));
+        assert!(!is_exe(Pid::Slf, Path::new("/bin/true")));
+
+        let entry = maps::parse(&StdFileAccess::default(), Pid::Slf)
+            .unwrap()
+            .map(Result::unwrap)
+            .find_map(|entry| match entry.path_name {
+                Some(PathName::Path(path)) if path.symbolic_path == exe => Some(PathMapsEntry {
+                    range: entry.range,
+                    mode: entry.mode,
+                    offset: entry.offset,
+                    path,
+                }),
+                _ => None,
+            })
+            .unwrap();
+
+        let build_id = read_mapped_build_id(Pid::Slf, &entry).unwrap();
+        assert_eq!(build_id, read_elf_build_id(&exe).unwrap());
+    }
+}
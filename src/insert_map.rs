@@ -1,84 +1,118 @@
-use std::cell::RefCell;
-use std::collections::hash_map;
 use std::collections::HashMap;
 use std::hash::Hash;
+use std::mem;
+use std::sync::Arc;
+use std::sync::PoisonError;
+use std::sync::RwLock;
 
 use crate::Result;
 
 
-/// An insert-only map.
+/// A concurrent map handing out shared ownership of its values.
 ///
-/// This map allows only for insertion, but not removal of values. It
-/// does so behind an immutable interface.
+/// This map allows for insertion and removal of values behind an
+/// immutable interface and can be shared between threads. Values are
+/// handed out as [`Arc`] clones, so that removing them from the map
+/// releases them as soon as the last clone is gone.
 #[derive(Debug)]
 pub(crate) struct InsertMap<K, V> {
-    /// A proxy member used for making sure that we do not borrow `map` mutably
-    /// multiple times.
-    refcell: RefCell<()>,
-    map: RefCell<HashMap<K, V>>,
+    /// The actual map.
+    map: RwLock<HashMap<K, Arc<V>>>,
 }
 
 impl<K, V> InsertMap<K, V> {
     /// Create a new, empty `InsertMap` instance.
     pub(crate) fn new() -> Self {
         Self {
-            refcell: RefCell::new(()),
-            map: RefCell::new(HashMap::new()),
+            map: RwLock::new(HashMap::new()),
         }
     }
 
+    /// Retrieve the value mapping to a key, if present.
+    pub(crate) fn get(&self, key: &K) -> Option<Arc<V>>
+    where
+        K: Eq + Hash,
+    {
+        let map = self.map.read().unwrap_or_else(PoisonError::into_inner);
+        map.get(key).cloned()
+    }
+
     /// Retrieve a value mapping to a key, if already present, or insert
     /// it and return it then.
     ///
-    /// # Panics
-    /// The `init` function should not use functionality provided by the
-    /// object this method operates on, recursively, or a runtime panic
-    /// may be the result.
-    pub(crate) fn get_or_insert<F>(&self, key: K, init: F) -> &V
+    /// The `init` function runs without any lock held. If multiple
+    /// threads race to insert a value for the same key, the first
+    /// inserted value wins and the others are discarded.
+    pub(crate) fn get_or_insert<F>(&self, key: K, init: F) -> Arc<V>
     where
         K: Eq + Hash,
         F: FnOnce() -> V,
     {
-        let _borrow = self.map.borrow_mut();
-        // SAFETY: We are sure to not borrow mutably twice because the `_borrow`
-        //         guard protects us.
-        let map = unsafe { self.map.as_ptr().as_mut() }.unwrap();
-        match map.entry(key) {
-            hash_map::Entry::Occupied(occupied) => occupied.into_mut(),
-            hash_map::Entry::Vacant(vacancy) => vacancy.insert(init()),
+        match self.get_or_try_insert(key, || Ok(init())) {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
         }
     }
 
     /// Retrieve a value mapping to a key, if already present, or insert
     /// it and return it then.
     ///
-    /// # Panics
-    /// The `init` function should not use functionality provided by the
-    /// object this method operates on, recursively, or a runtime panic
-    /// may be the result.
-    pub(crate) fn get_or_try_insert<F>(&self, key: K, init: F) -> Result<&V>
+    /// The `init` function runs without any lock held. If multiple
+    /// threads race to insert a value for the same key, the first
+    /// inserted value wins and the others are discarded.
+    pub(crate) fn get_or_try_insert<F>(&self, key: K, init: F) -> Result<Arc<V>>
     where
         K: Eq + Hash,
         F: FnOnce() -> Result<V>,
     {
-        let _borrow = self.refcell.borrow_mut();
-        // SAFETY: We are sure to not borrow mutably twice because the `_borrow`
-        //         guard protects us.
-        let map = unsafe { self.map.as_ptr().as_mut() }.unwrap();
-        match map.entry(key) {
-            hash_map::Entry::Occupied(occupied) => {
-                let entry = occupied.into_mut();
-                Ok(entry)
-            }
-            hash_map::Entry::Vacant(vacancy) => {
-                let value = init()?;
-                let entry = vacancy.insert(value);
-                Ok(entry)
-            }
+        if let Some(value) = self.get(&key) {
+            return Ok(value)
         }
+
+        let value = init()?;
+        let mut map = self.map.write().unwrap_or_else(PoisonError::into_inner);
+        let value = map.entry(key).or_insert_with(|| Arc::new(value));
+        Ok(Arc::clone(value))
+    }
+
+    /// Invoke `f` for each key-value pair stored in the map.
+    pub(crate) fn for_each<F>(&self, mut f: F)
+    where
+        F: FnMut(&K, &V),
+    {
+        let map = self.map.read().unwrap_or_else(PoisonError::into_inner);
+        let () = map.iter().for_each(|(key, value)| f(key, value));
+    }
+
+    /// Retain only the key-value pairs for which `f` returns `true`,
+    /// reporting the number of removed ones.
+    ///
+    /// Removed values are released once no longer referenced
+    /// elsewhere.
+    pub(crate) fn retain<F>(&self, mut f: F) -> usize
+    where
+        K: Eq + Hash,
+        F: FnMut(&K, &Arc<V>) -> bool,
+    {
+        let removed = {
+            let mut map = self.map.write().unwrap_or_else(PoisonError::into_inner);
+            let mut removed = Vec::new();
+            for (key, value) in mem::take(&mut *map) {
+                if f(&key, &value) {
+                    let _prev = map.insert(key, value);
+                } else {
+                    let () = removed.push(value);
+                }
+            }
+            removed
+        };
+        // Values are dropped without the lock held, as dropping them
+        // may be costly.
+        removed.len()
     }
 }
 
+
 impl<K, V> Default for InsertMap<K, V> {
     fn default() -> Self {
         Self::new()
@@ -101,10 +135,10 @@ mod tests {
         let s = map
             .get_or_try_insert(42, || Ok("you win the price"))
             .unwrap();
-        assert_eq!(s, &"you win the price");
+        assert_eq!(*s, "you win the price");
 
         let s = map.get_or_try_insert(42, || panic!()).unwrap();
-        assert_eq!(s, &"you win the price");
+        assert_eq!(*s, "you win the price");
 
         let err = map
             .get_or_try_insert(31, || Err(Error::with_unsupported("unsupported")))
@@ -112,17 +146,72 @@ mod tests {
         assert_eq!(err.kind(), ErrorKind::Unsupported);
 
         let s = map.get_or_try_insert(31, || Ok("31 wins")).unwrap();
-        assert_eq!(s, &"31 wins");
+        assert_eq!(*s, "31 wins");
+        assert_eq!(map.get(&31).as_deref(), Some(&"31 wins"));
+        assert_eq!(map.get(&13), None);
     }
 
 
-    /// Make sure that `InsertMap` does not allow for recursive
-    /// access as part of initialization.
+    /// Make sure that `InsertMap` allows for recursive access as part
+    /// of initialization, with the first inserted value winning.
     #[test]
-    #[should_panic = "already borrowed"]
     fn recursive_access() {
         let map = InsertMap::<usize, &'static str>::new();
-        let _value =
-            map.get_or_try_insert(42, || map.get_or_try_insert(42, || Ok("foobar")).copied());
+        let value = map
+            .get_or_try_insert(42, || {
+                map.get_or_try_insert(42, || Ok("foobar")).unwrap();
+                Ok("foobaz")
+            })
+            .unwrap();
+        assert_eq!(*value, "foobar");
+    }
+
+
+    /// Check that we can iterate over and remove values.
+    #[test]
+    fn iteration_and_removal() {
+        let map = InsertMap::<usize, usize>::new();
+        for i in 0..10 {
+            let _value = map.get_or_insert(i, || i * 2);
+        }
+
+        let mut sum = 0;
+        let () = map.for_each(|_key, value| sum += value);
+        assert_eq!(sum, 90);
+
+        let value = map.get_or_insert(1, || 1337);
+        let removed = map.retain(|key, _value| key % 2 == 0);
+        assert_eq!(removed, 5);
+        // Handed out values stay valid, but are no longer shared with
+        // the map.
+        assert_eq!(*value, 2);
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        let mut keys = Vec::new();
+        let () = map.for_each(|key, _value| keys.push(*key));
+        let () = keys.sort();
+        assert_eq!(keys, vec![0, 2, 4, 6, 8]);
+
+        let value = map.get_or_insert(1, || 1337);
+        assert_eq!(*value, 1337);
+    }
+
+
+    /// Check that removed values are released once no longer
+    /// referenced.
+    #[test]
+    fn release_on_removal() {
+        let map = InsertMap::<usize, Arc<()>>::new();
+        let tracker = Arc::new(());
+        let () = drop(map.get_or_insert(0, || Arc::clone(&tracker)));
+        let value = map.get_or_insert(1, || Arc::clone(&tracker));
+        assert_eq!(Arc::strong_count(&tracker), 3);
+
+        let removed = map.retain(|_key, _value| false);
+        assert_eq!(removed, 2);
+        assert_eq!(Arc::strong_count(&tracker), 2);
+
+        let () = drop(value);
+        assert_eq!(Arc::strong_count(&tracker), 1);
     }
 }
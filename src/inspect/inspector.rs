@@ -1,15 +1,301 @@
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::ops::Deref as _;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::ar;
+use crate::demangle;
+#[cfg(feature = "dwarf")]
+use crate::dwarf::Location;
+use crate::elf::ElfParser;
+use crate::elf::ElfResolver;
 use crate::elf::ElfResolverData;
 use crate::file_cache::FileCache;
+use crate::mmap::Mmap;
+use crate::once::OnceCell;
+#[cfg(feature = "dwarf")]
+use crate::symbolize::CodeInfo;
+use crate::symbolize::DemangleOpts;
+use crate::symbolize::SrcLang;
+#[cfg(feature = "dwarf")]
+use crate::util::glob_match;
+use crate::vfs::FileAccess;
+use crate::Error;
+use crate::ErrorExt as _;
 use crate::Result;
 use crate::SymResolver;
 
+use super::source::Archive;
 use super::source::Elf;
 use super::source::Source;
 use super::FindAddrOpts;
+use super::LineAddr;
+use super::SymFilter;
 use super::SymInfo;
+use super::SymTable;
 use super::SymType;
 
 
+/// An ELF object file contained in a static archive.
+#[derive(Debug)]
+struct ArchiveMember {
+    /// The name of the member, in `archive(member)` notation.
+    name: PathBuf,
+    /// The offset of the member's data from the beginning of the
+    /// archive.
+    offset: u64,
+    /// The resolver for the member.
+    resolver: ElfResolver,
+}
+
+impl ArchiveMember {
+    /// Adjust symbol information reported by the member's resolver to
+    /// be expressed in terms of the archive.
+    fn fixup_sym<'slf>(&'slf self, sym: &'slf SymInfo<'_>) -> SymInfo<'slf> {
+        SymInfo {
+            name: Cow::Borrowed(sym.name.deref()),
+            addr: sym.addr,
+            size: sym.size,
+            sym_type: sym.sym_type,
+            file_offset: sym.file_offset.map(|offset| offset + self.offset),
+            obj_file_name: Some(Cow::Borrowed(&self.name)),
+            section: sym.section.as_deref().map(Cow::Borrowed),
+        }
+    }
+}
+
+
+/// Resolver data associated with a static archive.
+#[derive(Debug, Default)]
+struct ArchiveData {
+    /// Member resolvers not using debug information.
+    elf: OnceCell<Arc<[ArchiveMember]>>,
+    /// Member resolvers with debug information enabled.
+    dwarf: OnceCell<Arc<[ArchiveMember]>>,
+}
+
+
+/// Create resolvers for all ELF members of the provided archive.
+fn create_archive_members(
+    path: &Path,
+    archive: &ar::Archive,
+    fs: &Arc<dyn FileAccess>,
+    debug_syms: bool,
+) -> Result<Box<[ArchiveMember]>> {
+    archive
+        .entries()
+        .filter_map(|entry| {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => return Some(Err(err)),
+            };
+            if !entry.data.starts_with(b"\x7fELF") {
+                return None
+            }
+
+            let mut name = OsString::from(path.as_os_str());
+            let () = name.push("(");
+            let () = name.push(entry.path.as_os_str());
+            let () = name.push(")");
+            let name = PathBuf::from(name);
+
+            // Archive members are only two byte aligned, but the ELF
+            // parser requires natural alignment of the data it reads.
+            // Copy misaligned members.
+            let mmap = if entry.data_offset % 8 == 0 {
+                let range = entry.data_offset..entry.data_offset + entry.data.len() as u64;
+                // SANITY: The entry is guaranteed to be contained in
+                //         the archive.
+                archive.mmap().constrain(range).unwrap()
+            } else {
+                Mmap::from_vec(entry.data.to_vec())
+            };
+            let parser = Arc::new(ElfParser::from_mmap(mmap));
+            let code_info = true;
+            let result = ElfResolver::from_parser(&name, parser, fs, debug_syms, code_info, None)
+                .with_context(|| format!("failed to parse archive member {}", name.display()))
+                .map(|resolver| ArchiveMember {
+                    name,
+                    offset: entry.data_offset,
+                    resolver,
+                });
+            Some(result)
+        })
+        .collect()
+}
+
+
+/// Fold over all symbols known to `resolver` that match `filter`.
+fn fold_resolver_syms<F, R>(resolver: &ElfResolver, filter: &SymFilter, r: R, f: F) -> Result<R>
+where
+    F: FnMut(R, &SymInfo<'_>) -> R,
+{
+    let SymFilter {
+        sym_type,
+        binding,
+        visibility,
+        name,
+        table,
+        _non_exhaustive: (),
+    } = filter;
+
+    if *table != Some(SymTable::Dwarf) {
+        let offset_in_file = true;
+        return resolver
+            .parser()
+            .for_each_filtered_sym(filter, offset_in_file, r, f)
+    }
+
+    // DWARF only describes functions and carries no information about
+    // symbol binding and visibility.
+    if *sym_type == SymType::Variable || binding.is_some() || visibility.is_some() {
+        return Ok(r)
+    }
+
+    #[cfg(feature = "dwarf")]
+    if let Some(dwarf) = resolver.dwarf() {
+        let mut f = f;
+        let parser = dwarf.parser();
+        let mut result = Ok(());
+        let r = dwarf.fold_functions(r, |r, sym_name, range| {
+            if result.is_err() {
+                return r
+            }
+            if let Some(pattern) = name {
+                if !glob_match(pattern, sym_name) {
+                    return r
+                }
+            }
+
+            let section = match parser.find_section_name_by_addr(range.start) {
+                Ok(section) => section,
+                Err(err) => {
+                    result = Err(err);
+                    return r
+                }
+            };
+            let file_offset = match parser.find_file_offset(range.start) {
+                Ok(offset) => offset,
+                Err(err) => {
+                    result = Err(err);
+                    return r
+                }
+            };
+            let sym = SymInfo {
+                name: Cow::Borrowed(sym_name),
+                addr: range.start,
+                size: usize::try_from(range.end - range.start).unwrap_or(usize::MAX),
+                sym_type: SymType::Function,
+                file_offset,
+                obj_file_name: None,
+                section: section.map(Cow::Borrowed),
+            };
+            f(r, &sym)
+        })?;
+        let () = result?;
+        return Ok(r)
+    }
+    Ok(r)
+}
+
+
+/// Find the code generated for `line` of `file`, using the DWARF
+/// information available to `resolver`.
+fn resolver_line_addrs<'res>(
+    resolver: &'res ElfResolver,
+    file: &Path,
+    line: u32,
+    function: Option<&str>,
+) -> Result<Vec<LineAddr<'res>>> {
+    #[cfg(feature = "dwarf")]
+    if let Some(dwarf) = resolver.dwarf() {
+        let mut addrs = Vec::new();
+        for (range, location) in dwarf.find_line_addrs(file, line)? {
+            let name = dwarf
+                .find_sym(range.start)?
+                .map(|sym| sym.name)
+                .filter(|name| !name.is_empty());
+            if function.is_some() && name != function {
+                continue
+            }
+
+            let Location {
+                dir,
+                file,
+                line,
+                column,
+            } = location;
+            let addr = LineAddr {
+                addr: range.start,
+                size: usize::try_from(range.end - range.start).unwrap_or(usize::MAX),
+                code_info: CodeInfo {
+                    dir: Some(Cow::Borrowed(dir)),
+                    file: Cow::Borrowed(file),
+                    line,
+                    column: column.map(|col| col.try_into().unwrap_or(u16::MAX)),
+                    context: None,
+                    _non_exhaustive: (),
+                },
+                function: name.map(Cow::Borrowed),
+                obj_file_name: None,
+                _non_exhaustive: (),
+            };
+            let () = addrs.push(addr);
+        }
+        return Ok(addrs)
+    }
+    Ok(Vec::new())
+}
+
+
+/// A builder for configurable construction of [`Inspector`] objects.
+#[derive(Clone, Debug, Default)]
+pub struct Builder {
+    /// Whether or not to demangle reported symbol names.
+    demangle: bool,
+    /// Options controlling the output of demangling.
+    demangle_opts: DemangleOpts,
+}
+
+impl Builder {
+    /// Enable/disable transparent demangling of reported symbol names.
+    ///
+    /// Demangling happens on a best-effort basis and only for languages
+    /// for which support is compiled in (see the `demangle-*`
+    /// features). Names passed to [`Inspector::lookup`] are always
+    /// matched against the mangled symbol names.
+    ///
+    /// By default demangling is disabled.
+    pub fn enable_demangling(mut self, enable: bool) -> Builder {
+        self.demangle = enable;
+        self
+    }
+
+    /// Set the options controlling the level of detail of demangled
+    /// symbol names.
+    pub fn set_demangle_opts(mut self, opts: DemangleOpts) -> Builder {
+        self.demangle_opts = opts;
+        self
+    }
+
+    /// Create the [`Inspector`] object.
+    pub fn build(self) -> Inspector {
+        let Builder {
+            demangle,
+            demangle_opts,
+        } = self;
+
+        Inspector {
+            elf_cache: FileCache::new(),
+            archive_cache: FileCache::new(),
+            demangle,
+            demangle_opts,
+        }
+    }
+}
+
 /// An inspector of various "sources".
 ///
 /// Object of this type can be used to perform inspections of supported sources.
@@ -26,21 +312,73 @@ use super::SymType;
 #[derive(Debug)]
 pub struct Inspector {
     elf_cache: FileCache<ElfResolverData>,
+    archive_cache: FileCache<(ar::Archive, ArchiveData)>,
+    demangle: bool,
+    demangle_opts: DemangleOpts,
 }
 
 impl Inspector {
     /// Create a new `Inspector`.
     pub fn new() -> Self {
-        Self {
-            elf_cache: FileCache::new(),
+        Builder::default().build()
+    }
+
+    /// Retrieve a [`Builder`] object for configurable construction of
+    /// an [`Inspector`].
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    /// Demangle the provided symbol name, if enabled.
+    fn maybe_demangle(&self, name: &str) -> Option<String> {
+        if self.demangle && demangle::ENABLED {
+            // Symbol tables carry no language information.
+            demangle::demangle(name, SrcLang::Unknown, &self.demangle_opts)
+        } else {
+            None
+        }
+    }
+
+    /// Convert `sym` into a `SymInfo` with demangled name, if enabled.
+    fn demangle_sym<'sym>(&self, sym: &'sym SymInfo<'_>) -> SymInfo<'sym> {
+        let name = self
+            .maybe_demangle(&sym.name)
+            .map(Cow::Owned)
+            .unwrap_or_else(|| Cow::Borrowed(sym.name.deref()));
+        SymInfo {
+            name,
+            addr: sym.addr,
+            size: sym.size,
+            sym_type: sym.sym_type,
+            file_offset: sym.file_offset,
+            obj_file_name: sym.obj_file_name.as_deref().map(Cow::Borrowed),
+            section: sym.section.as_deref().map(Cow::Borrowed),
         }
     }
 
+    /// Retrieve resolvers for all ELF members of the archive at
+    /// `path`.
+    fn archive_members(&self, path: &Path, debug_syms: bool) -> Result<Arc<[ArchiveMember]>> {
+        let entry = self.archive_cache.entry(path)?;
+        let (archive, data) = entry.value().get_or_try_init(|| {
+            let archive = ar::Archive::with_mmap(entry.data().mmap().clone())?;
+            Result::<_, Error>::Ok((archive, ArchiveData::default()))
+        })?;
+        let members = if debug_syms { &data.dwarf } else { &data.elf };
+        let members = members.get_or_try_init(|| {
+            create_archive_members(path, archive, self.archive_cache.file_access(), debug_syms)
+                .map(Arc::from)
+        })?;
+        Ok(Arc::clone(members))
+    }
+
     /// Look up information (address etc.) about a list of symbols,
     /// given their names.
     ///
     /// # Notes
-    /// - no symbol name demangling is performed currently
+    /// - names are matched against mangled symbol names, irrespective
+    ///   of whether demangling is enabled (see
+    ///   [`Builder::enable_demangling`])
     pub fn lookup<'slf>(
         &'slf self,
         src: &Source,
@@ -67,13 +405,108 @@ impl Inspector {
                             // is very unfortunate, but it's unclear how else to
                             // make the borrow checker accept this code (modulo
                             // `transmute`).
-                            syms.into_iter().map(|sym| sym.to_owned()).collect()
+                            syms.into_iter()
+                                .map(|sym| self.demangle_sym(&sym).to_owned())
+                                .collect()
                         })
                     })
                     .collect::<Result<Vec<_>>>()?;
 
                 Ok(syms)
             }
+            Source::Archive(Archive {
+                path,
+                debug_syms,
+                _non_exhaustive: (),
+            }) => {
+                let members = self.archive_members(path, *debug_syms)?;
+                let syms = names
+                    .iter()
+                    .map(|name| {
+                        let mut syms = Vec::new();
+                        for member in members.iter() {
+                            let () =
+                                syms.extend(member.resolver.find_addr(name, &opts)?.iter().map(
+                                    |sym| self.demangle_sym(&member.fixup_sym(sym)).to_owned(),
+                                ));
+                        }
+                        Ok(syms)
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                Ok(syms)
+            }
+        }
+    }
+
+    /// Look up the addresses of the code generated for a source code
+    /// line.
+    ///
+    /// `file` is matched against the trailing components of the source
+    /// file paths recorded in the debug information, i.e., both
+    /// `main.c` and `src/main.c` match `/build/src/main.c`. If
+    /// `function` is provided, only code belonging to the function of
+    /// this (mangled) name is reported. Consecutive code for the same
+    /// line is reported as a single entry, ordered by address.
+    ///
+    /// # Notes
+    /// - lookup is based on DWARF line information and requires
+    ///   [`debug_syms`][Elf::debug_syms] to be enabled; no addresses are
+    ///   reported otherwise
+    /// - only exact line matches are reported; lines for which no code
+    ///   was generated (e.g., comments) yield no addresses
+    pub fn lookup_line<'slf>(
+        &'slf self,
+        src: &Source,
+        file: &Path,
+        line: u32,
+        function: Option<&str>,
+    ) -> Result<Vec<LineAddr<'slf>>> {
+        let demangle = |addr: LineAddr<'_>| {
+            let mut addr = addr.to_owned();
+            if let Some(name) = addr
+                .function
+                .as_deref()
+                .and_then(|name| self.maybe_demangle(name))
+            {
+                addr.function = Some(Cow::Owned(name));
+            }
+            addr
+        };
+
+        match src {
+            Source::Elf(Elf {
+                path,
+                debug_syms,
+                _non_exhaustive: (),
+            }) => {
+                let code_info = true;
+                let resolver = self.elf_cache.elf_resolver(path, *debug_syms, code_info)?;
+                let addrs = resolver_line_addrs(&resolver, file, line, function)?
+                    .into_iter()
+                    .map(demangle)
+                    .collect();
+                Ok(addrs)
+            }
+            Source::Archive(Archive {
+                path,
+                debug_syms,
+                _non_exhaustive: (),
+            }) => {
+                let members = self.archive_members(path, *debug_syms)?;
+                let mut addrs = Vec::new();
+                for member in members.iter() {
+                    let () = addrs.extend(
+                        resolver_line_addrs(&member.resolver, file, line, function)?
+                            .into_iter()
+                            .map(|mut addr| {
+                                addr.obj_file_name = Some(Cow::Borrowed(member.name.as_path()));
+                                demangle(addr)
+                            }),
+                    );
+                }
+                Ok(addrs)
+            }
         }
     }
 
@@ -83,15 +516,14 @@ impl Inspector {
     /// not be relied on.
     ///
     /// # Notes
-    /// - no symbol name demangling is performed currently
     /// - currently only function symbols (as opposed to variables) are
     ///   reported
     /// - undefined symbols (such as ones referencing a different shared
     ///   object) are not reported
-    /// - for the [`Elf`](Source::Elf) source, at present DWARF symbols are
-    ///   ignored (irrespective of the [`debug_syms`][Elf::debug_syms]
-    ///   configuration)
-    pub fn for_each<F, R>(&self, src: &Source, r: R, f: F) -> Result<R>
+    /// - for the [`Elf`](Source::Elf) and [`Archive`](Source::Archive)
+    ///   sources, at present DWARF symbols are ignored (irrespective of
+    ///   the [`debug_syms`][Elf::debug_syms] configuration)
+    pub fn for_each<F, R>(&self, src: &Source, r: R, mut f: F) -> Result<R>
     where
         F: FnMut(R, &SymInfo<'_>) -> R,
     {
@@ -108,7 +540,70 @@ impl Inspector {
                 let code_info = true;
                 let resolver = self.elf_cache.elf_resolver(path, *debug_syms, code_info)?;
                 let parser = resolver.parser();
-                parser.for_each_sym(&opts, r, f)
+                parser.for_each_sym(&opts, r, |r, sym| f(r, &self.demangle_sym(sym)))
+            }
+            Source::Archive(Archive {
+                path,
+                debug_syms,
+                _non_exhaustive: (),
+            }) => {
+                let opts = FindAddrOpts {
+                    offset_in_file: true,
+                    sym_type: SymType::Unknown,
+                };
+                let members = self.archive_members(path, *debug_syms)?;
+                members.iter().try_fold(r, |r, member| {
+                    let parser = member.resolver.parser();
+                    parser.for_each_sym(&opts, r, |r, sym| {
+                        f(r, &self.demangle_sym(&member.fixup_sym(sym)))
+                    })
+                })
+            }
+        }
+    }
+
+    /// Perform an operation on each symbol in the source that matches
+    /// `filter`.
+    ///
+    /// Contrary to [`Inspector::for_each`], this method reports symbols
+    /// of all types (unless restricted by the filter), including the
+    /// name of the section containing each symbol, and it can report
+    /// symbols from a specific symbol table or from DWARF debug
+    /// information. Symbols are reported in implementation defined
+    /// order that should not be relied on.
+    ///
+    /// # Notes
+    /// - undefined symbols (such as ones referencing a different shared
+    ///   object) as well as section and file symbols are not reported
+    /// - the name filter is matched against mangled symbol names,
+    ///   irrespective of whether demangling is enabled
+    /// - DWARF symbols (see [`SymTable::Dwarf`]) are only reported if
+    ///   [`debug_syms`][Elf::debug_syms] is enabled for the source
+    pub fn for_each_sym<F, R>(&self, src: &Source, filter: &SymFilter, r: R, mut f: F) -> Result<R>
+    where
+        F: FnMut(R, &SymInfo<'_>) -> R,
+    {
+        match src {
+            Source::Elf(Elf {
+                path,
+                debug_syms,
+                _non_exhaustive: (),
+            }) => {
+                let code_info = true;
+                let resolver = self.elf_cache.elf_resolver(path, *debug_syms, code_info)?;
+                fold_resolver_syms(&resolver, filter, r, |r, sym| f(r, &self.demangle_sym(sym)))
+            }
+            Source::Archive(Archive {
+                path,
+                debug_syms,
+                _non_exhaustive: (),
+            }) => {
+                let members = self.archive_members(path, *debug_syms)?;
+                members.iter().try_fold(r, |r, member| {
+                    fold_resolver_syms(&member.resolver, filter, r, |r, sym| {
+                        f(r, &self.demangle_sym(&member.fixup_sym(sym)))
+                    })
+                })
             }
         }
     }
@@ -126,7 +621,7 @@ mod tests {
     use super::*;
 
     use std::path::Path;
-    use std::rc::Rc;
+    use std::sync::Arc;
 
     use crate::ErrorKind;
 
@@ -170,23 +665,16 @@ mod tests {
         assert!(elf.debug_syms);
 
         let inspector = Inspector::new();
-        let data = || {
-            inspector
-                .elf_cache
-                .entry(&test_elf)
-                .unwrap()
-                .1
-                .get()
-                .unwrap()
-                .clone()
-        };
+        let entry = || inspector.elf_cache.entry(&test_elf).unwrap();
 
         let _results = inspector.lookup(&Source::Elf(elf.clone()), &["factorial"]);
-        let data1 = data();
+        let entry1 = entry();
+        let data1 = entry1.value().get().unwrap();
 
         let _results = inspector.lookup(&Source::Elf(elf.clone()), &["factorial"]);
-        let data2 = data();
-        assert!(Rc::ptr_eq(
+        let entry2 = entry();
+        let data2 = entry2.value().get().unwrap();
+        assert!(Arc::ptr_eq(
             data1.dwarf.get().unwrap(),
             data2.dwarf.get().unwrap()
         ));
@@ -196,8 +684,9 @@ mod tests {
         elf.debug_syms = false;
 
         let _results = inspector.lookup(&Source::Elf(elf.clone()), &["factorial"]);
-        let data3 = data();
-        assert!(!Rc::ptr_eq(
+        let entry3 = entry();
+        let data3 = entry3.value().get().unwrap();
+        assert!(!Arc::ptr_eq(
             data1.dwarf.get().unwrap(),
             data3.elf.get().unwrap()
         ));
@@ -21,9 +21,12 @@ mod source;
 use std::borrow::Cow;
 use std::path::Path;
 
+use crate::symbolize::CodeInfo;
 use crate::Addr;
 
+pub use inspector::Builder;
 pub use inspector::Inspector;
+pub use source::Archive;
 pub use source::Elf;
 pub use source::Source;
 
@@ -41,6 +44,82 @@ pub enum SymType {
 }
 
 
+/// The binding of a symbol, determining its linkage visibility.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SymBinding {
+    /// The symbol is not visible outside the object file containing
+    /// it.
+    Local,
+    /// The symbol is visible to all object files being combined.
+    Global,
+    /// The symbol is global, but with lower precedence.
+    Weak,
+}
+
+
+/// The visibility of a symbol once it has become part of an
+/// executable or shared object.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SymVisibility {
+    /// The visibility is specified by the symbol's binding.
+    Default,
+    /// Processor specific hidden class.
+    Internal,
+    /// The symbol is not visible to other components.
+    Hidden,
+    /// The symbol is visible to other components, but not
+    /// preemptable.
+    Protected,
+}
+
+
+/// The table from which to report symbols.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SymTable {
+    /// The regular ELF symbol table (`.symtab`).
+    Symtab,
+    /// The dynamic ELF symbol table (`.dynsym`).
+    Dynsym,
+    /// Functions described by DWARF debug information.
+    Dwarf,
+}
+
+
+/// A filter restricting the symbols reported by
+/// [`Inspector::for_each_sym`].
+///
+/// The default filter matches all symbols.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SymFilter {
+    /// Only report symbols of the given type. [`SymType::Unknown`]
+    /// means symbols of all types.
+    pub sym_type: SymType,
+    /// Only report symbols with the given binding.
+    ///
+    /// Symbols from DWARF do not carry binding information and are
+    /// never reported if set.
+    pub binding: Option<SymBinding>,
+    /// Only report symbols with the given visibility.
+    ///
+    /// Symbols from DWARF do not carry visibility information and are
+    /// never reported if set.
+    pub visibility: Option<SymVisibility>,
+    /// Only report symbols whose (mangled) name matches the given
+    /// shell style glob, e.g., `foo*`. Supported are `*`, `?`, and
+    /// bracket expressions such as `[a-z]`.
+    pub name: Option<String>,
+    /// The table to report symbols from.
+    ///
+    /// If not set, symbols are reported from the ELF symbol table
+    /// also used for symbolization, i.e., `.symtab`, falling back to
+    /// `.dynsym` if the former does not exist.
+    pub table: Option<SymTable>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+
 /// Information about a symbol.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct SymInfo<'src> {
@@ -55,7 +134,12 @@ pub struct SymInfo<'src> {
     /// The offset in the object file.
     pub file_offset: Option<u64>,
     /// The file name of the shared object.
+    ///
+    /// For symbols contained in a static archive, this is the name of
+    /// the archive along with the member, e.g., `libfoo.a(foo.o)`.
     pub obj_file_name: Option<Cow<'src, Path>>,
+    /// The name of the section containing the symbol, if known.
+    pub section: Option<Cow<'src, str>>,
 }
 
 impl SymInfo<'_> {
@@ -73,6 +157,55 @@ impl SymInfo<'_> {
                 .obj_file_name
                 .as_deref()
                 .map(|path| Cow::Owned(path.to_path_buf())),
+            section: self
+                .section
+                .as_deref()
+                .map(|section| Cow::Owned(section.to_string())),
+        }
+    }
+}
+
+
+/// Information about code generated for a source code line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineAddr<'src> {
+    /// The first address of the code attributed to the line.
+    pub addr: Addr,
+    /// The number of bytes of code attributed to the line, starting at
+    /// `addr`.
+    pub size: usize,
+    /// The source code location, as recorded in the line table.
+    pub code_info: CodeInfo<'src>,
+    /// The name of the function containing the code, if known.
+    pub function: Option<Cow<'src, str>>,
+    /// The file name of the object file containing the code.
+    ///
+    /// For code contained in a static archive, this is the name of the
+    /// archive along with the member, e.g., `libfoo.a(foo.o)`.
+    pub obj_file_name: Option<Cow<'src, Path>>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl LineAddr<'_> {
+    /// Clone the object ensuring that references are converted to owned
+    /// objects.
+    #[inline]
+    pub fn to_owned(&self) -> LineAddr<'static> {
+        LineAddr {
+            addr: self.addr,
+            size: self.size,
+            code_info: self.code_info.to_owned(),
+            function: self
+                .function
+                .as_deref()
+                .map(|function| Cow::Owned(function.to_string())),
+            obj_file_name: self
+                .obj_file_name
+                .as_deref()
+                .map(|path| Cow::Owned(path.to_path_buf())),
+            _non_exhaustive: (),
         }
     }
 }
@@ -35,12 +35,55 @@ impl From<Elf> for Source {
 }
 
 
+/// A static archive, as created by `ar`.
+///
+/// Each ELF object file contained in the archive is inspected, with
+/// addresses being relative to the section containing the respective
+/// symbol. Members that are not ELF files are ignored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Archive {
+    /// The path to the archive.
+    pub path: PathBuf,
+    /// Whether or not to consult debug symbols to satisfy the request
+    /// (if present).
+    pub debug_syms: bool,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Archive {
+    /// Create a new [`Archive`] object, referencing the provided path.
+    ///
+    /// `debug_syms` defaults to `true` when using this constructor.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            debug_syms: true,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+impl From<Archive> for Source {
+    fn from(archive: Archive) -> Self {
+        Source::Archive(archive)
+    }
+}
+
+
 /// The source to use for the inspection request.
 #[derive(Clone, Debug, PartialEq)]
 #[non_exhaustive]
 pub enum Source {
     /// The source is an ELF file.
+    ///
+    /// Relocatable object files are supported as well, in which case
+    /// addresses are relative to the section containing the respective
+    /// symbol.
     Elf(Elf),
+    /// The source is a static archive of object files.
+    Archive(Archive),
 }
 
 impl Source {
@@ -48,6 +91,7 @@ impl Source {
     pub fn path(&self) -> Option<&Path> {
         match self {
             Self::Elf(elf) => Some(&elf.path),
+            Self::Archive(archive) => Some(&archive.path),
         }
     }
 }
@@ -0,0 +1,223 @@
+use std::mem::size_of;
+
+use crate::util::ReadRaw as _;
+use crate::Error;
+use crate::IntoError as _;
+use crate::Result;
+
+use super::JitSym;
+
+
+/// The magic number identifying a jitdump file ("JiTD").
+const JITDUMP_MAGIC: u32 = 0x4A695444;
+
+/// Record describing a newly loaded piece of code.
+const JIT_CODE_LOAD: u32 = 0;
+/// Record describing code that moved to a different address.
+const JIT_CODE_MOVE: u32 = 1;
+/// Record indicating that the file got closed.
+const JIT_CODE_CLOSE: u32 = 3;
+
+
+/// A reader of jitdump data, taking care of the file's byte order.
+struct Reader<'data> {
+    data: &'data [u8],
+    swap: bool,
+}
+
+impl<'data> Reader<'data> {
+    fn read_u32(&mut self) -> Option<u32> {
+        let value = self.data.read_u32()?;
+        Some(if self.swap { value.swap_bytes() } else { value })
+    }
+
+    fn read_u64(&mut self) -> Option<u64> {
+        let value = self.data.read_u64()?;
+        Some(if self.swap { value.swap_bytes() } else { value })
+    }
+}
+
+
+/// Parse a jitdump file, as emitted by the JVM (e.g., via
+/// `perf-map-agent` or the `-XX:+DumpJitdump` family of agents) and
+/// other JIT runtimes.
+///
+/// Code move records are applied to the previously loaded code, so the
+/// result reflects the final location of each piece of code.
+pub(crate) fn parse(data: &[u8]) -> Result<Vec<JitSym>> {
+    let mut reader = Reader { data, swap: false };
+    let magic = reader
+        .read_u32()
+        .ok_or_unexpected_eof(|| "failed to read jitdump magic")?;
+    reader.swap = match magic {
+        JITDUMP_MAGIC => false,
+        magic if magic.swap_bytes() == JITDUMP_MAGIC => true,
+        _ => {
+            return Err(Error::with_invalid_data(format!(
+                "encountered invalid jitdump magic: {magic:#x}"
+            )))
+        }
+    };
+
+    let _version = reader.read_u32();
+    let header_size = reader
+        .read_u32()
+        .ok_or_unexpected_eof(|| "failed to read jitdump header size")?;
+    // Skip over the remainder of the header; its size may grow in
+    // future versions.
+    let () = reader
+        .data
+        .read_slice((header_size as usize).saturating_sub(3 * size_of::<u32>()))
+        .map(|_| ())
+        .ok_or_unexpected_eof(|| "jitdump header is truncated")?;
+
+    let mut syms = Vec::<JitSym>::new();
+    while !reader.data.is_empty() {
+        let id = reader
+            .read_u32()
+            .ok_or_unexpected_eof(|| "failed to read jitdump record ID")?;
+        let size = reader
+            .read_u32()
+            .ok_or_unexpected_eof(|| "failed to read jitdump record size")?;
+        let _timestamp = reader.read_u64();
+        let prefix_size = 2 * size_of::<u32>() + size_of::<u64>();
+        let body = reader
+            .data
+            .read_slice((size as usize).saturating_sub(prefix_size))
+            .ok_or_unexpected_eof(|| format!("jitdump record of type {id} is truncated"))?;
+        let mut body = Reader {
+            data: body,
+            swap: reader.swap,
+        };
+
+        match id {
+            JIT_CODE_LOAD => {
+                let (_pid, _tid) = (body.read_u32(), body.read_u32());
+                let _vma = body.read_u64();
+                let addr = body.read_u64();
+                let code_size = body.read_u64();
+                let _index = body.read_u64();
+                let name = body.data.read_cstr();
+                let (addr, code_size, name) = (|| Some((addr?, code_size?, name?)))()
+                    .ok_or_invalid_data(|| "encountered malformed jitdump code load record")?;
+
+                let sym = JitSym {
+                    addr,
+                    size: code_size as usize,
+                    name: name.to_string_lossy().into_owned(),
+                };
+                let () = syms.push(sym);
+            }
+            JIT_CODE_MOVE => {
+                let (_pid, _tid) = (body.read_u32(), body.read_u32());
+                let _vma = body.read_u64();
+                let old_addr = body.read_u64();
+                let new_addr = body.read_u64();
+                let (old_addr, new_addr) = (|| Some((old_addr?, new_addr?)))()
+                    .ok_or_invalid_data(|| "encountered malformed jitdump code move record")?;
+
+                if let Some(sym) = syms.iter_mut().rev().find(|sym| sym.addr == old_addr) {
+                    sym.addr = new_addr;
+                }
+            }
+            JIT_CODE_CLOSE => break,
+            // Debug information, unwinding information, and other
+            // records are of no interest to us.
+            _ => (),
+        }
+    }
+
+    Ok(syms)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Create the binary representation of a jitdump record.
+    fn record(id: u32, body: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let () = data.extend_from_slice(&id.to_ne_bytes());
+        let () = data.extend_from_slice(&(16 + body.len() as u32).to_ne_bytes());
+        let () = data.extend_from_slice(&0u64.to_ne_bytes());
+        let () = data.extend_from_slice(body);
+        data
+    }
+
+    /// Create the binary representation of a code load record.
+    fn code_load(addr: u64, size: u64, name: &str) -> Vec<u8> {
+        let mut body = Vec::new();
+        let () = body.extend_from_slice(&[0; 2 * size_of::<u32>()]);
+        let () = body.extend_from_slice(&addr.to_ne_bytes());
+        let () = body.extend_from_slice(&addr.to_ne_bytes());
+        let () = body.extend_from_slice(&size.to_ne_bytes());
+        let () = body.extend_from_slice(&0u64.to_ne_bytes());
+        let () = body.extend_from_slice(name.as_bytes());
+        let () = body.push(b'\0');
+        record(JIT_CODE_LOAD, &body)
+    }
+
+    /// Create the binary representation of a jitdump file header.
+    fn header() -> Vec<u8> {
+        let mut data = Vec::new();
+        let () = data.extend_from_slice(&JITDUMP_MAGIC.to_ne_bytes());
+        let () = data.extend_from_slice(&1u32.to_ne_bytes());
+        let () = data.extend_from_slice(&40u32.to_ne_bytes());
+        let () = data.extend_from_slice(&[0; 28]);
+        data
+    }
+
+
+    /// Check that we can parse jitdump data.
+    #[test]
+    fn jitdump_parsing() {
+        let mut data = header();
+        let () = data.extend(code_load(0x1000, 0x100, "Interpreter"));
+        let () = data.extend(code_load(0x2000, 0x20, "LFoo;::bar"));
+
+        let mut body = Vec::new();
+        let () = body.extend_from_slice(&[0; 2 * size_of::<u32>()]);
+        let () = body.extend_from_slice(&0x2000u64.to_ne_bytes());
+        let () = body.extend_from_slice(&0x2000u64.to_ne_bytes());
+        let () = body.extend_from_slice(&0x3000u64.to_ne_bytes());
+        let () = body.extend_from_slice(&0x20u64.to_ne_bytes());
+        let () = body.extend_from_slice(&0u64.to_ne_bytes());
+        let () = data.extend(record(JIT_CODE_MOVE, &body));
+        // Some record we don't care about.
+        let () = data.extend(record(2, &[1, 2, 3, 4]));
+        let () = data.extend(record(JIT_CODE_CLOSE, &[]));
+        let () = data.extend(code_load(0x4000, 0x20, "ignored"));
+
+        let syms = parse(&data).unwrap();
+        assert_eq!(
+            syms,
+            vec![
+                JitSym {
+                    addr: 0x1000,
+                    size: 0x100,
+                    name: "Interpreter".to_string(),
+                },
+                JitSym {
+                    addr: 0x3000,
+                    size: 0x20,
+                    name: "LFoo;::bar".to_string(),
+                },
+            ]
+        );
+    }
+
+    /// Make sure that we report errors on invalid jitdump data.
+    #[test]
+    fn invalid_jitdump() {
+        let err = parse(b"JUNK").unwrap_err();
+        assert!(err.to_string().contains("invalid jitdump magic"), "{err}");
+
+        let mut data = header();
+        let mut load = code_load(0x1000, 0x100, "foo");
+        let () = load.truncate(load.len() - 10);
+        let () = data.extend(load);
+        let _err = parse(&data).unwrap_err();
+    }
+}
@@ -0,0 +1,49 @@
+//! Support for symbolizing just-in-time (JIT) compiled code, based on
+//! the symbol information that runtimes such as the JVM emit in the
+//! form of perf maps and jitdump files.
+
+mod jitdump;
+mod perf_map;
+mod resolver;
+
+use std::path::PathBuf;
+
+use crate::Addr;
+use crate::Pid;
+
+pub(crate) use self::jitdump::parse as parse_jitdump;
+pub(crate) use self::perf_map::parse as parse_perf_map;
+pub(crate) use self::perf_map::remove_stale;
+pub(crate) use self::resolver::JitResolver;
+
+
+/// A symbol describing a piece of JIT compiled code.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct JitSym {
+    /// The address at which the code starts.
+    pub addr: Addr,
+    /// The size of the code, in bytes.
+    pub size: usize,
+    /// The name of the symbol.
+    pub name: String,
+}
+
+
+/// Convert a `Pid` into the numeric ID used in file names by JIT
+/// runtimes.
+fn pid_num(pid: Pid) -> u32 {
+    match pid {
+        Pid::Slf => std::process::id(),
+        Pid::Pid(pid) => pid.get(),
+    }
+}
+
+/// Retrieve the default path of the perf map file of the given process.
+pub(crate) fn perf_map_path(pid: Pid) -> PathBuf {
+    PathBuf::from(format!("/tmp/perf-{}.map", pid_num(pid)))
+}
+
+/// Retrieve the default path of the jitdump file of the given process.
+pub(crate) fn jitdump_path(pid: Pid) -> PathBuf {
+    PathBuf::from(format!("/tmp/jit-{}.dump", pid_num(pid)))
+}
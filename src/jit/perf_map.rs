@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+
+use crate::Addr;
+
+use super::JitSym;
+
+
+/// Parse a hexadecimal number, with or without `0x` prefix.
+fn parse_hex<T>(
+    s: &str,
+    from_str_radix: fn(&str, u32) -> Result<T, std::num::ParseIntError>,
+) -> Option<T> {
+    let s = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    from_str_radix(s, 16).ok()
+}
+
+/// Parse a single perf map line of the form `START SIZE NAME`.
+fn parse_line(line: &str) -> Option<JitSym> {
+    let line = line.trim_start();
+    let (addr, rest) = line.split_once(char::is_whitespace)?;
+    let (size, name) = rest.trim_start().split_once(char::is_whitespace)?;
+    let addr = parse_hex(addr, Addr::from_str_radix)?;
+    let size = parse_hex(size, usize::from_str_radix)?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None
+    }
+
+    let sym = JitSym {
+        addr,
+        size,
+        name: name.to_string(),
+    };
+    Some(sym)
+}
+
+/// Parse the contents of a perf map file (as written, for example, by
+/// `perf-map-agent` or the JVM's `-XX:+DumpPerfMapAtExit`).
+///
+/// Symbols are reported in file order. Malformed lines are skipped.
+pub(crate) fn parse(data: &str) -> Vec<JitSym> {
+    data.lines().filter_map(parse_line).collect()
+}
+
+
+/// Remove stale entries from a list of perf map symbols.
+///
+/// Runtimes such as V8 only ever append to perf maps, even when code
+/// gets moved or garbage collected. The latest entry (i.e., the one
+/// furthest towards the end of the file) covering an address is the
+/// valid one and any earlier entries overlapping with it are stale. The
+/// remaining symbols are reported in file order.
+pub(crate) fn remove_stale(syms: Vec<JitSym>) -> Vec<JitSym> {
+    // Non-overlapping ranges of the symbols kept so far, mapping start
+    // to end address.
+    let mut ranges = BTreeMap::<Addr, Addr>::new();
+    let mut valid = syms
+        .into_iter()
+        .rev()
+        .filter(|sym| {
+            let start = sym.addr;
+            let end = sym.addr.saturating_add(sym.size.max(1) as Addr);
+            // Because kept ranges never overlap, the one starting last
+            // before our end is the only candidate for overlapping us.
+            if let Some((_, prev_end)) = ranges.range(..end).next_back() {
+                if *prev_end > start {
+                    return false
+                }
+            }
+            let _prev = ranges.insert(start, end);
+            true
+        })
+        .collect::<Vec<_>>();
+    let () = valid.reverse();
+    valid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Check that we can parse perf map contents.
+    #[test]
+    fn perf_map_parsing() {
+        let data = r#"7f5c3c001000 80 Interpreter
+0x7f5c3c0010a0 0x20 LFoo;::bar(I)V
+7f5c3c001100 40 void java.lang.Object.<init>() [c1]
+garbage
+7f5c3c001200 zz invalid size
+7f5c3c001300 10
+"#;
+        let syms = parse(data);
+        assert_eq!(
+            syms,
+            vec![
+                JitSym {
+                    addr: 0x7f5c3c001000,
+                    size: 0x80,
+                    name: "Interpreter".to_string(),
+                },
+                JitSym {
+                    addr: 0x7f5c3c0010a0,
+                    size: 0x20,
+                    name: "LFoo;::bar(I)V".to_string(),
+                },
+                JitSym {
+                    addr: 0x7f5c3c001100,
+                    size: 0x40,
+                    name: "void java.lang.Object.<init>() [c1]".to_string(),
+                },
+            ]
+        );
+    }
+
+    /// Check that stale perf map entries are removed.
+    #[test]
+    fn stale_removal() {
+        let sym = |addr, size, name: &str| JitSym {
+            addr,
+            size,
+            name: name.to_string(),
+        };
+
+        let syms = vec![
+            sym(0x1000, 0x100, "LazyCompile:~foo"),
+            sym(0x2000, 0x100, "LazyCompile:~bar"),
+            // `foo` got garbage collected and the space reused.
+            sym(0x1080, 0x100, "LazyCompile:~baz"),
+            sym(0x3000, 0x0, "empty"),
+            // `bar` got moved.
+            sym(0x4000, 0x100, "LazyCompile:~bar"),
+            sym(0x20ff, 0x1, "LazyCompile:~qux"),
+        ];
+        let syms = remove_stale(syms);
+        assert_eq!(
+            syms,
+            vec![
+                sym(0x1080, 0x100, "LazyCompile:~baz"),
+                sym(0x3000, 0x0, "empty"),
+                sym(0x4000, 0x100, "LazyCompile:~bar"),
+                sym(0x20ff, 0x1, "LazyCompile:~qux"),
+            ]
+        );
+    }
+}
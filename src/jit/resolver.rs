@@ -0,0 +1,231 @@
+use std::borrow::Cow;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use crate::inspect::FindAddrOpts;
+use crate::inspect::SymInfo;
+use crate::inspect::SymType;
+use crate::symbolize::AddrCodeInfo;
+use crate::symbolize::IntSym;
+use crate::symbolize::Reason;
+use crate::symbolize::SrcLang;
+use crate::Addr;
+use crate::Result;
+use crate::SymResolver;
+
+use super::JitSym;
+
+
+/// Name prefixes of JVM code regions that are not individual methods,
+/// but rather cover large ranges of generated code, such as the template
+/// interpreter or the various stubs and adapters.
+const JVM_REGION_PREFIXES: [&str; 7] = [
+    "Interpreter",
+    "StubRoutines",
+    "BufferBlob",
+    "RuntimeStub",
+    "I2C/C2I adapters",
+    "vtable chunks",
+    "itable chunks",
+];
+
+
+/// Check whether a symbol name refers to a JVM code region as opposed
+/// to an individual method.
+fn is_region(name: &str) -> bool {
+    JVM_REGION_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+
+#[derive(Debug)]
+struct Sym {
+    addr: Addr,
+    size: usize,
+    name: String,
+    /// Whether the symbol describes a region of code (e.g., the
+    /// interpreter) rather than a single method.
+    region: bool,
+}
+
+impl Sym {
+    #[inline]
+    fn contains(&self, addr: Addr) -> bool {
+        addr == self.addr || (addr > self.addr && addr - self.addr < self.size as Addr)
+    }
+}
+
+
+/// A symbol resolver for JIT compiled code, backed by symbols from perf
+/// maps or jitdump files.
+///
+/// Symbols for individual methods take precedence over those describing
+/// larger code regions, such as the JVM's template interpreter or stub
+/// routines, which only serve as a fallback.
+pub(crate) struct JitResolver {
+    /// Symbols, sorted by address.
+    syms: Vec<Sym>,
+    /// The largest symbol size, which bounds the backwards search for
+    /// symbols covering an address.
+    max_size: usize,
+}
+
+impl JitResolver {
+    /// Create a new `JitResolver` from the provided symbols.
+    ///
+    /// If multiple symbols start at the same address, later ones are
+    /// preferred over earlier ones.
+    pub(crate) fn new(syms: impl IntoIterator<Item = JitSym>) -> Self {
+        let mut syms = syms
+            .into_iter()
+            .map(|JitSym { addr, size, name }| Sym {
+                addr,
+                size,
+                region: is_region(&name),
+                name,
+            })
+            .collect::<Vec<_>>();
+        // Note that the sort is stable, preserving the relative order
+        // of symbols at the same address.
+        let () = syms.sort_by_key(|sym| sym.addr);
+        let max_size = syms.iter().map(|sym| sym.size).max().unwrap_or(0);
+
+        Self { syms, max_size }
+    }
+
+    fn find_jit_sym(&self, addr: Addr) -> Result<&Sym, Reason> {
+        if self.syms.is_empty() {
+            return Err(Reason::MissingSyms)
+        }
+
+        let end = self.syms.partition_point(|sym| sym.addr <= addr);
+        let mut best = None::<&Sym>;
+        for sym in self.syms[..end].iter().rev() {
+            if addr - sym.addr > self.max_size as Addr {
+                break
+            }
+            if !sym.contains(addr) {
+                continue
+            }
+
+            best = match best {
+                None => Some(sym),
+                Some(prev) => {
+                    // Methods beat regions and smaller symbols beat
+                    // larger ones; on a tie the later entry, which we
+                    // visited first, wins.
+                    if (sym.region, sym.size) < (prev.region, prev.size) {
+                        Some(sym)
+                    } else {
+                        Some(prev)
+                    }
+                }
+            };
+        }
+
+        best.ok_or(Reason::UnknownAddr)
+    }
+}
+
+impl SymResolver for JitResolver {
+    fn find_sym(&self, addr: Addr) -> Result<Result<IntSym<'_>, Reason>> {
+        let sym = self.find_jit_sym(addr).map(|sym| IntSym {
+            name: &sym.name,
+            addr: sym.addr,
+            size: Some(sym.size),
+            // The language of JIT compiled code is not known to us.
+            lang: SrcLang::Unknown,
+        });
+        Ok(sym)
+    }
+
+    fn find_addr<'slf>(&'slf self, name: &str, opts: &FindAddrOpts) -> Result<Vec<SymInfo<'slf>>> {
+        if let SymType::Variable = opts.sym_type {
+            return Ok(Vec::new())
+        }
+
+        let syms = self
+            .syms
+            .iter()
+            .filter(|sym| sym.name == name)
+            .map(|sym| SymInfo {
+                name: Cow::Borrowed(&sym.name),
+                addr: sym.addr,
+                size: sym.size,
+                sym_type: SymType::Function,
+                file_offset: None,
+                obj_file_name: None,
+                section: None,
+            })
+            .collect();
+        Ok(syms)
+    }
+
+    fn find_code_info(&self, _addr: Addr, _inlined_fns: bool) -> Result<Option<AddrCodeInfo<'_>>> {
+        Ok(None)
+    }
+}
+
+impl Debug for JitResolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "JitResolver")
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn sym(addr: Addr, size: usize, name: &str) -> JitSym {
+        JitSym {
+            addr,
+            size,
+            name: name.to_string(),
+        }
+    }
+
+    fn find(resolver: &JitResolver, addr: Addr) -> Result<&str, Reason> {
+        resolver.find_jit_sym(addr).map(|sym| sym.name.as_str())
+    }
+
+
+    /// Check that methods are preferred over JVM code regions.
+    #[test]
+    fn region_fallback() {
+        let resolver = JitResolver::new([
+            sym(0x1000, 0x1000, "Interpreter"),
+            sym(0x1100, 0x10, "LFoo;::bar"),
+            sym(0x3000, 0x100, "StubRoutines (1)"),
+            sym(0x4000, 0x20, "LFoo;::baz"),
+            sym(0x4000, 0x20, "LFoo;::baz2"),
+        ]);
+
+        assert_eq!(find(&resolver, 0x1000), Ok("Interpreter"));
+        assert_eq!(find(&resolver, 0x1108), Ok("LFoo;::bar"));
+        assert_eq!(find(&resolver, 0x1110), Ok("Interpreter"));
+        assert_eq!(find(&resolver, 0x1fff), Ok("Interpreter"));
+        assert_eq!(find(&resolver, 0x2000), Err(Reason::UnknownAddr));
+        assert_eq!(find(&resolver, 0x30ff), Ok("StubRoutines (1)"));
+        assert_eq!(find(&resolver, 0x4010), Ok("LFoo;::baz2"));
+        assert_eq!(find(&resolver, 0x10), Err(Reason::UnknownAddr));
+
+        let resolver = JitResolver::new([]);
+        assert_eq!(find(&resolver, 0x1000), Err(Reason::MissingSyms));
+    }
+
+    /// Check that we can look up JIT symbols by name.
+    #[test]
+    fn name_lookup() {
+        let resolver = JitResolver::new([sym(0x1100, 0x10, "LFoo;::bar")]);
+        let syms = resolver
+            .find_addr("LFoo;::bar", &FindAddrOpts::default())
+            .unwrap();
+        assert_eq!(syms.len(), 1);
+        assert_eq!(syms[0].addr, 0x1100);
+        assert_eq!(syms[0].size, 0x10);
+    }
+}
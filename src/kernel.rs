@@ -1,51 +1,206 @@
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
+use std::ops::Range;
 use std::path::Path;
-use std::rc::Rc;
+use std::sync::Arc;
 
+#[cfg(all(unix, feature = "cache-daemon"))]
+use crate::daemon::IndexResolver;
 use crate::elf::ElfResolver;
 use crate::inspect::FindAddrOpts;
 use crate::inspect::SymInfo;
 use crate::ksym::KSymResolver;
+use crate::log::debug;
+#[cfg(target_os = "linux")]
+use crate::normalize::buildid::read_build_id_from_note_data;
 use crate::symbolize::AddrCodeInfo;
+use crate::symbolize::IntModule;
 use crate::symbolize::IntSym;
 use crate::symbolize::Reason;
+#[cfg(target_os = "linux")]
+use crate::vfs::FileAccess;
 use crate::Addr;
 use crate::Error;
+#[cfg(target_os = "linux")]
+use crate::ErrorExt as _;
+#[cfg(target_os = "linux")]
+use crate::ErrorKind;
 use crate::Result;
 use crate::SymResolver;
 
 
+/// The path to the file containing the notes of the running kernel.
+#[cfg(target_os = "linux")]
+const SYS_KERNEL_NOTES: &str = "/sys/kernel/notes";
+/// The symbols marking the start of the kernel's text, used for
+/// determining the KASLR offset.
+const TEXT_SYMS: [&str; 2] = ["_text", "_stext"];
+
+
+/// Read the build ID of the running kernel.
+#[cfg(target_os = "linux")]
+pub(crate) fn read_kernel_build_id(fs: &dyn FileAccess) -> Result<Option<Vec<u8>>> {
+    let path = Path::new(SYS_KERNEL_NOTES);
+    let data = match fs.open(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    read_build_id_from_note_data(data.data())
+        .with_context(|| format!("failed to read build ID from {}", path.display()))
+}
+
+/// Determine the offset by which the kernel described by `ksym` got
+/// relocated relative to its image `elf`, by comparing the addresses
+/// of the first of `syms` present in both.
+fn find_text_offset(ksym: &KSymSource, elf: &ElfResolver, syms: &[&str]) -> Result<Option<u64>> {
+    let opts = FindAddrOpts::default();
+    for name in syms {
+        let ksym_addr = match ksym.resolver().find_addr(name, &opts)?.first() {
+            Some(sym) => sym.addr,
+            None => continue,
+        };
+        // With `kptr_restrict` in effect kallsyms reports all
+        // addresses as zero, which tells us nothing.
+        if ksym_addr == 0 {
+            return Ok(None)
+        }
+
+        if let Some(sym) = elf.parser().find_addr(name, &opts)?.first() {
+            return Ok(Some(ksym_addr.wrapping_sub(sym.addr)))
+        }
+    }
+    Ok(None)
+}
+
+/// Determine the KASLR offset of the kernel described by `ksym`
+/// relative to its image `elf`.
+pub(crate) fn find_kaslr_offset(ksym: &KSymSource, elf: &ElfResolver) -> Result<Option<u64>> {
+    let offset = find_text_offset(ksym, elf, &TEXT_SYMS)?;
+    if offset.is_none() {
+        debug!(
+            "failed to determine KASLR offset of {}; assuming none",
+            elf.file_name().display()
+        );
+    }
+    Ok(offset)
+}
+
+
+/// The source of kernel symbols in kallsyms format.
+pub(crate) enum KSymSource {
+    /// Symbols parsed from a kallsyms file.
+    KSym(Arc<KSymResolver>),
+    /// Symbols provided by the cache daemon.
+    #[cfg(all(unix, feature = "cache-daemon"))]
+    Index(Arc<IndexResolver>),
+}
+
+impl KSymSource {
+    fn resolver(&self) -> &dyn SymResolver {
+        match self {
+            Self::KSym(resolver) => resolver.as_ref(),
+            #[cfg(all(unix, feature = "cache-daemon"))]
+            Self::Index(resolver) => resolver.as_ref(),
+        }
+    }
+
+    fn file_name(&self) -> &Path {
+        match self {
+            Self::KSym(resolver) => resolver.file_name(),
+            #[cfg(all(unix, feature = "cache-daemon"))]
+            Self::Index(resolver) => resolver.file_name(),
+        }
+    }
+}
+
+
+/// A loaded kernel module along with the resolver for its ELF file.
+pub(crate) struct ModuleResolver {
+    /// The address range at which the module is loaded.
+    pub range: Range<Addr>,
+    /// The address at which the module's `.text` section is loaded.
+    ///
+    /// Kernel modules are relocatable objects and addresses reported by
+    /// `resolver` are relative to the start of this section.
+    pub text_base: Addr,
+    /// The resolver for the module's ELF file.
+    pub resolver: Arc<ElfResolver>,
+}
+
+
 pub(crate) struct KernelResolver {
-    pub ksym_resolver: Option<Rc<KSymResolver>>,
-    pub elf_resolver: Option<Rc<ElfResolver>>,
+    pub ksym_resolver: Option<KSymSource>,
+    pub elf_resolver: Option<Arc<ElfResolver>>,
+    /// The offset by which addresses are relocated relative to those
+    /// in the image represented by `elf_resolver`.
+    pub kaslr_offset: u64,
+    /// Resolvers for kernel modules, sorted by load address.
+    pub modules: Vec<ModuleResolver>,
 }
 
 impl KernelResolver {
     pub fn new(
-        ksym_resolver: Option<Rc<KSymResolver>>,
-        elf_resolver: Option<Rc<ElfResolver>>,
+        ksym_resolver: Option<KSymSource>,
+        elf_resolver: Option<Arc<ElfResolver>>,
+        kaslr_offset: u64,
+        mut modules: Vec<ModuleResolver>,
     ) -> Result<KernelResolver> {
-        if ksym_resolver.is_none() && elf_resolver.is_none() {
+        if ksym_resolver.is_none() && elf_resolver.is_none() && modules.is_empty() {
             return Err(Error::with_not_found(
                     "failed to create kernel resolver: neither ksym resolver nor kernel image ELF resolver are present",
             ))
         }
 
+        let () = modules.sort_by_key(|module| module.range.start);
         Ok(KernelResolver {
             ksym_resolver,
             elf_resolver,
+            kaslr_offset,
+            modules,
         })
     }
+
+    /// Find the module containing `addr`, along with the address
+    /// relative to the module's `.text` section.
+    fn find_module(&self, addr: Addr) -> Option<(&ModuleResolver, Addr)> {
+        let idx = self
+            .modules
+            .partition_point(|module| module.range.start <= addr)
+            .checked_sub(1)?;
+        let module = &self.modules[idx];
+        if !module.range.contains(&addr) {
+            return None
+        }
+        let offset = addr.checked_sub(module.text_base)?;
+        Some((module, offset))
+    }
 }
 
 impl SymResolver for KernelResolver {
     fn find_sym(&self, addr: Addr) -> Result<Result<IntSym<'_>, Reason>> {
+        if let Some((module, offset)) = self.find_module(addr) {
+            if let Ok(sym) = module.resolver.find_sym(offset)? {
+                let sym = IntSym {
+                    addr: sym.addr.wrapping_add(module.text_base),
+                    ..sym
+                };
+                return Ok(Ok(sym))
+            }
+        }
+
         if let Some(ksym_resolver) = self.ksym_resolver.as_ref() {
-            ksym_resolver.find_sym(addr)
+            ksym_resolver.resolver().find_sym(addr)
+        } else if let Some(elf_resolver) = self.elf_resolver.as_ref() {
+            let result = elf_resolver.find_sym(addr.wrapping_sub(self.kaslr_offset))?;
+            let result = result.map(|sym| IntSym {
+                addr: sym.addr.wrapping_add(self.kaslr_offset),
+                ..sym
+            });
+            Ok(result)
         } else {
-            self.elf_resolver.as_ref().unwrap().find_sym(addr)
+            Ok(Err(Reason::UnknownAddr))
         }
     }
 
@@ -58,12 +213,30 @@ impl SymResolver for KernelResolver {
     }
 
     fn find_code_info(&self, addr: Addr, inlined_fns: bool) -> Result<Option<AddrCodeInfo>> {
+        if let Some((module, offset)) = self.find_module(addr) {
+            return module.resolver.find_code_info(offset, inlined_fns)
+        }
+
         if let Some(resolver) = self.elf_resolver.as_ref() {
-            resolver.find_code_info(addr, inlined_fns)
+            resolver.find_code_info(addr.wrapping_sub(self.kaslr_offset), inlined_fns)
         } else {
             Ok(None)
         }
     }
+    fn module_info(&self, addr: Addr) -> Result<IntModule<'_>> {
+        if let Some((module, offset)) = self.find_module(addr) {
+            return module.resolver.module_info(offset)
+        }
+
+        // kallsyms does not tell us anything about the kernel image,
+        // so we can only report module information if we have the
+        // latter at hand.
+        if let Some(resolver) = self.elf_resolver.as_ref() {
+            resolver.module_info(addr.wrapping_sub(self.kaslr_offset))
+        } else {
+            Ok(IntModule::default())
+        }
+    }
 }
 
 impl Debug for KernelResolver {
@@ -73,7 +246,7 @@ impl Debug for KernelResolver {
             "KernelResolver {} {}",
             self.ksym_resolver
                 .as_ref()
-                .map(|resolver| resolver.file_name())
+                .map(KSymSource::file_name)
                 .unwrap_or_else(|| Path::new(""))
                 .display(),
             self.elf_resolver
@@ -84,3 +257,64 @@ impl Debug for KernelResolver {
         )
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use crate::elf::ElfParser;
+    use crate::vfs::StdFileAccess;
+
+
+    /// Check that we can determine the KASLR offset of a kernel and
+    /// take it into account when symbolizing addresses.
+    #[test]
+    fn kaslr_offset() {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-stable-addresses.bin");
+        let parser = Arc::new(ElfParser::open(&path).unwrap());
+        let fs = Arc::new(StdFileAccess::default()) as Arc<dyn FileAccess>;
+        let elf = Arc::new(ElfResolver::from_parser(&path, parser, &fs, true, true, None).unwrap());
+
+        let kallsyms = b"ffffffff82000100 T factorial\n";
+        let ksym =
+            KSymResolver::load_from_reader(&kallsyms[..], PathBuf::from("kallsyms")).unwrap();
+        let ksym = KSymSource::KSym(Arc::new(ksym));
+        let offset = find_text_offset(&ksym, &elf, &["_text", "factorial"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(offset, 0xffffffff80000000);
+        assert_eq!(find_kaslr_offset(&ksym, &elf).unwrap(), None);
+
+        // Addresses hidden by `kptr_restrict` don't tell us anything.
+        let kallsyms = b"0000000000000000 T factorial\n";
+        let restricted =
+            KSymResolver::load_from_reader(&kallsyms[..], PathBuf::from("kallsyms")).unwrap();
+        let restricted = KSymSource::KSym(Arc::new(restricted));
+        assert_eq!(
+            find_text_offset(&restricted, &elf, &["factorial"]).unwrap(),
+            None
+        );
+
+        let resolver = KernelResolver::new(None, Some(elf), offset, Vec::new()).unwrap();
+        let sym = resolver.find_sym(0xffffffff82000102).unwrap().unwrap();
+        assert_eq!(sym.name, "factorial");
+        assert_eq!(sym.addr, 0xffffffff82000100);
+
+        #[cfg(feature = "dwarf")]
+        {
+            let info = resolver
+                .find_code_info(0xffffffff82000100, false)
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                info.direct.1.file,
+                std::ffi::OsStr::new("test-stable-addresses.c")
+            );
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! Support for locating and reading the ELF files of loaded kernel
+//! modules.
+use std::collections::HashMap;
+use std::ffi::OsStr;
+#[cfg(any(feature = "xz", feature = "zstd"))]
+use std::io::Read as _;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str;
+
+use crate::vfs::FileAccess;
+use crate::Addr;
+use crate::Error;
+use crate::ErrorExt as _;
+use crate::IntoError as _;
+use crate::Result;
+
+
+/// The path to the list of kernel modules loaded into the running
+/// kernel.
+pub(crate) const PROC_MODULES: &str = "/proc/modules";
+/// The directory containing per-module information in sysfs.
+pub(crate) const SYS_MODULE: &str = "/sys/module";
+/// The name of the file mapping module names to module files, relative
+/// to the module directory.
+pub(crate) const MODULES_DEP: &str = "modules.dep";
+
+
+/// A kernel module loaded into the kernel.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct KernelModule {
+    /// The name of the module, with dashes replaced by underscores.
+    pub name: String,
+    /// The address range at which the module is loaded.
+    pub range: Range<Addr>,
+}
+
+
+/// Normalize the name of a kernel module the way the kernel does,
+/// treating dashes and underscores as equivalent.
+fn normalize_name(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+/// Parse a single line of `/proc/modules`.
+///
+/// The format is:
+/// ```text
+/// <name> <size> <refcnt> <deps> <state> <addr> [<taint>]
+/// ```
+///
+/// Entries for which the kernel hides the load address (because of
+/// `kptr_restrict`) are reported as `None`.
+fn parse_module_line(line: &str) -> Result<Option<KernelModule>> {
+    let mut fields = line.split_ascii_whitespace();
+    let name = fields
+        .next()
+        .ok_or_invalid_data(|| format!("encountered malformed kernel module line: {line}"))?;
+    let size = fields
+        .next()
+        .and_then(|size| size.parse::<u64>().ok())
+        .ok_or_invalid_data(|| format!("failed to parse size of kernel module `{name}`"))?;
+    let addr = fields
+        .nth(3)
+        .and_then(|addr| addr.strip_prefix("0x"))
+        .and_then(|addr| Addr::from_str_radix(addr, 16).ok())
+        .ok_or_invalid_data(|| format!("failed to parse address of kernel module `{name}`"))?;
+
+    if addr == 0 {
+        return Ok(None)
+    }
+
+    let module = KernelModule {
+        name: normalize_name(name),
+        range: addr..addr.saturating_add(size),
+    };
+    Ok(Some(module))
+}
+
+/// Parse a list of loaded kernel modules in `/proc/modules` format.
+///
+/// Modules whose load address is concealed (i.e., reported as zero,
+/// as is the case for unprivileged readers) are skipped. The result is
+/// sorted by load address.
+pub(crate) fn parse_modules(data: &[u8]) -> Result<Vec<KernelModule>> {
+    let data = str::from_utf8(data)
+        .map_err(Error::with_invalid_data)
+        .context("kernel module list is not valid UTF-8")?;
+
+    let mut modules = data
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| parse_module_line(line).transpose())
+        .collect::<Result<Vec<_>>>()?;
+    let () = modules.sort_by_key(|module| module.range.start);
+    Ok(modules)
+}
+
+/// Parse a `modules.dep` file, mapping module names to the paths of
+/// the corresponding module files, relative to the module directory.
+pub(crate) fn parse_modules_dep(data: &[u8]) -> HashMap<String, PathBuf> {
+    String::from_utf8_lossy(data)
+        .lines()
+        .filter_map(|line| {
+            let (path, _deps) = line.split_once(':')?;
+            let path = Path::new(path.trim());
+            let file_name = path.file_name()?.to_str()?;
+            let (name, _ext) = file_name.split_once(".ko")?;
+            Some((normalize_name(name), path.to_path_buf()))
+        })
+        .collect()
+}
+
+/// Read the address at which the `.text` section of the kernel module
+/// `name` is loaded into the running kernel.
+///
+/// Section addresses are only exposed to privileged users and `None`
+/// is returned if it is not available.
+fn read_text_base(fs: &dyn FileAccess, name: &str) -> Option<Addr> {
+    let path = Path::new(SYS_MODULE)
+        .join(name)
+        .join("sections")
+        .join(".text");
+    let data = fs.open(&path).ok()?;
+    let addr = str::from_utf8(data.data()).ok()?;
+    let addr = Addr::from_str_radix(addr.trim().trim_start_matches("0x"), 16).ok()?;
+    (addr != 0).then_some(addr)
+}
+
+/// Determine the base address of `module`, as loaded into the running
+/// kernel, i.e., the address of its `.text` section.
+///
+/// The `.text` section is located at address zero in a module's `.ko`
+/// file, meaning that offsets relative to this base can be used for
+/// looking up symbols in it directly. If the section address is not
+/// available, the module's load address is used instead, which
+/// coincides with it for the vast majority of modules.
+pub(crate) fn text_base(fs: &dyn FileAccess, module: &KernelModule) -> Addr {
+    read_text_base(fs, &module.name).unwrap_or(module.range.start)
+}
+
+/// Check whether the kernel module at `path` is compressed, judging by
+/// its extension.
+pub(crate) fn is_compressed(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(OsStr::to_str),
+        Some("gz" | "xz" | "zst")
+    )
+}
+
+#[cfg(feature = "xz")]
+fn decompress_xz(data: &[u8]) -> Result<Vec<u8>> {
+    use xz2::read::XzDecoder;
+
+    let mut decompressed = Vec::new();
+    let _cnt = XzDecoder::new_multi_decoder(data)
+        .read_to_end(&mut decompressed)
+        .context("failed to decompress xz data")?;
+    Ok(decompressed)
+}
+
+#[cfg(not(feature = "xz"))]
+fn decompress_xz(_data: &[u8]) -> Result<Vec<u8>> {
+    Err(Error::with_unsupported(
+        "xz compressed kernel modules require the `xz` feature",
+    ))
+}
+
+#[cfg(feature = "zstd")]
+fn decompress_zstd(mut data: &[u8]) -> Result<Vec<u8>> {
+    use ruzstd::StreamingDecoder;
+
+    let mut decompressed = Vec::new();
+    let _cnt = StreamingDecoder::new(&mut data)
+        .map_err(|err| Error::with_invalid_data(err.to_string()))
+        .context("failed to decompress zstd data")?
+        .read_to_end(&mut decompressed)
+        .context("failed to decompress zstd data")?;
+    Ok(decompressed)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress_zstd(_data: &[u8]) -> Result<Vec<u8>> {
+    Err(Error::with_unsupported(
+        "zstd compressed kernel modules require the `zstd` feature",
+    ))
+}
+
+/// Decompress the contents of the compressed kernel module at `path`.
+pub(crate) fn decompress(path: &Path, data: &[u8]) -> Result<Vec<u8>> {
+    match path.extension().and_then(OsStr::to_str) {
+        Some("xz") => decompress_xz(data),
+        Some("zst") => decompress_zstd(data),
+        _ => Err(Error::with_unsupported(format!(
+            "compression format of kernel module {} is unsupported",
+            path.display()
+        ))),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::vfs::MemFileAccess;
+    use crate::ErrorKind;
+
+
+    /// Check that we can parse `/proc/modules` lines.
+    #[test]
+    fn module_line_parsing() {
+        let line = "ext4 1036288 1 - Live 0xffffffffc0a5f000";
+        let module = parse_module_line(line).unwrap().unwrap();
+        assert_eq!(module.name, "ext4");
+        assert_eq!(module.range, 0xffffffffc0a5f000..0xffffffffc0b5c000);
+
+        let line = "nvidia 56512512 1 nvidia_modeset, Live 0xffffffffc1000000 (POE)";
+        let module = parse_module_line(line).unwrap().unwrap();
+        assert_eq!(module.name, "nvidia");
+        assert_eq!(module.range.start, 0xffffffffc1000000);
+
+        // Hidden addresses are skipped.
+        let line = "ext4 1036288 1 - Live 0x0000000000000000";
+        assert_eq!(parse_module_line(line).unwrap(), None);
+
+        for line in ["ext4", "ext4 xxx 1 - Live 0x1000", "ext4 1036288 1 - Live"] {
+            let err = parse_module_line(line).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData, "{line}");
+        }
+    }
+
+    /// Check that we can parse `/proc/modules` contents.
+    #[test]
+    fn modules_parsing() {
+        let data = b"\
+nf-tables 311296 0 - Live 0xffffffffc0a00000
+crc32c_intel 24576 1 nf_tables, Live 0xffffffffc0100000 (E)
+hidden 4096 0 - Live 0x0000000000000000
+
+";
+        let modules = parse_modules(data).unwrap();
+        assert_eq!(
+            modules,
+            vec![
+                KernelModule {
+                    name: "crc32c_intel".to_string(),
+                    range: 0xffffffffc0100000..0xffffffffc0106000,
+                },
+                KernelModule {
+                    name: "nf_tables".to_string(),
+                    range: 0xffffffffc0a00000..0xffffffffc0a4c000,
+                },
+            ]
+        );
+
+        let err = parse_modules(b"garbage\n").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = parse_modules(b"\xff 4096 0 - Live 0x1000\n").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    /// Check that we determine the base address of a kernel module as
+    /// expected.
+    #[test]
+    fn module_text_base() {
+        let module = KernelModule {
+            name: "ext4".to_string(),
+            range: 0xffffffffc0a5f000..0xffffffffc0b5c000,
+        };
+        let mut fs = MemFileAccess::new();
+        assert_eq!(text_base(&fs, &module), 0xffffffffc0a5f000);
+
+        let () = fs.insert(
+            "/sys/module/ext4/sections/.text",
+            b"0xffffffffc0a60000\n".to_vec(),
+        );
+        assert_eq!(text_base(&fs, &module), 0xffffffffc0a60000);
+
+        // Concealed addresses are ignored.
+        let () = fs.insert(
+            "/sys/module/ext4/sections/.text",
+            b"0x0000000000000000\n".to_vec(),
+        );
+        assert_eq!(text_base(&fs, &module), 0xffffffffc0a5f000);
+    }
+
+    /// Check that we can parse `modules.dep` contents.
+    #[test]
+    fn modules_dep_parsing() {
+        let data = b"\
+kernel/net/netfilter/nf_tables.ko.zst: kernel/lib/libcrc32c.ko.zst
+kernel/arch/x86/crypto/crc32c-intel.ko:
+updates/dkms/zfs.ko.xz:
+";
+        let index = parse_modules_dep(data);
+        assert_eq!(index.len(), 3);
+        assert_eq!(
+            index.get("nf_tables"),
+            Some(&PathBuf::from("kernel/net/netfilter/nf_tables.ko.zst"))
+        );
+        assert_eq!(
+            index.get("crc32c_intel"),
+            Some(&PathBuf::from("kernel/arch/x86/crypto/crc32c-intel.ko"))
+        );
+        assert_eq!(
+            index.get("zfs"),
+            Some(&PathBuf::from("updates/dkms/zfs.ko.xz"))
+        );
+    }
+
+    /// Check that we detect compressed kernel modules correctly.
+    #[test]
+    fn compression_detection() {
+        assert!(is_compressed(Path::new("foo.ko.xz")));
+        assert!(is_compressed(Path::new("foo.ko.zst")));
+        assert!(is_compressed(Path::new("foo.ko.gz")));
+        assert!(!is_compressed(Path::new("foo.ko")));
+
+        let err = decompress(Path::new("foo.ko.gz"), b"").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+
+    /// Check that we can decompress zstd compressed data.
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_decompression() {
+        let data = [
+            0x28, 0xb5, 0x2f, 0xfd, 0x00, 0x58, 0x69, 0x00, 0x00, 0x6b, 0x65, 0x72, 0x6e, 0x65,
+            0x6c, 0x20, 0x6d, 0x6f, 0x64, 0x75, 0x6c, 0x65,
+        ];
+        let decompressed = decompress(Path::new("foo.ko.zst"), &data).unwrap();
+        assert_eq!(decompressed, b"kernel module");
+
+        let err = decompress(Path::new("foo.ko.zst"), b"foobar").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -2,9 +2,11 @@ use std::borrow::Cow;
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
+#[cfg(test)]
 use std::fs::File;
 use std::io::BufRead;
 use std::io::BufReader;
+use std::io::Read;
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -60,9 +62,19 @@ pub struct KSymResolver {
 }
 
 impl KSymResolver {
+    #[cfg(test)]
     pub fn load_file_name(filename: PathBuf) -> Result<Self> {
         let f = File::open(&filename)?;
-        let mut reader = BufReader::new(f);
+        Self::load_from_reader(f, filename)
+    }
+
+    /// Load kernel symbols in kallsyms format from `reader`, with
+    /// `filename` being the path they originate from.
+    pub(crate) fn load_from_reader<R>(reader: R, filename: PathBuf) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut reader = BufReader::new(reader);
         let mut line = String::new();
         let mut syms = Vec::with_capacity(DFL_KSYM_CAP);
 
@@ -113,6 +125,12 @@ impl KSymResolver {
         }
     }
 
+    /// Retrieve the kernel symbols, sorted by address.
+    #[cfg(all(unix, feature = "cache-daemon"))]
+    pub(crate) fn syms(&self) -> &[Ksym] {
+        &self.syms
+    }
+
     /// Retrieve the path to the kallsyms file used by this resolver.
     pub(crate) fn file_name(&self) -> &Path {
         &self.file_name
@@ -158,6 +176,7 @@ impl SymResolver for KSymResolver {
                     sym_type: SymType::Function,
                     file_offset: None,
                     obj_file_name: None,
+                    section: None,
                 })
                 .collect()
         } else {
@@ -15,6 +15,7 @@
 //! - [`inspect`] contains APIs for inspecting files such as ELF and Gsym to
 //!   lookup addresses to symbol names, for example
 //! - [`normalize`] exposes address normalization functionality
+//! - [`vfs`] provides abstractions for customizing file access
 //!
 //! C API bindings are defined in a cross-cutting manner as part of the
 //! `cblazesym` crate (note that Rust code should not have to consume
@@ -34,31 +35,72 @@
     rustdoc::broken_intra_doc_links
 )]
 #![cfg_attr(feature = "nightly", feature(test))]
-#![cfg_attr(not(feature = "dwarf"), allow(dead_code))]
+#![cfg_attr(
+    any(
+        not(feature = "breakpad"),
+        not(feature = "dwarf"),
+        not(feature = "gsym"),
+        not(feature = "kernel"),
+        not(feature = "process")
+    ),
+    allow(dead_code)
+)]
 
 
 #[cfg(feature = "nightly")]
 extern crate test;
 
+mod ar;
+#[cfg(feature = "breakpad")]
+pub mod breakpad;
+mod coredump;
+#[cfg(all(unix, feature = "cache-daemon"))]
+pub mod daemon;
+#[cfg(feature = "debuginfod")]
+mod debuginfod;
+mod demangle;
 #[cfg(feature = "dwarf")]
 mod dwarf;
 mod elf;
 mod error;
+mod fd_pool;
+mod features;
 mod file_cache;
-mod gsym;
+#[cfg(feature = "gsym")]
+pub mod gsym;
+mod image;
 mod insert_map;
 pub mod inspect;
+mod jit;
+#[cfg(feature = "kernel")]
 mod kernel;
+#[cfg(feature = "kernel")]
+mod kmod;
+#[cfg(feature = "kernel")]
 mod ksym;
+mod linker_map;
+mod macho;
 mod maps;
 mod mmap;
 #[cfg(target_os = "linux")]
 mod namespace;
+#[cfg(feature = "nonblocking")]
+pub mod nonblocking;
 pub mod normalize;
 mod once;
+mod pe;
+#[cfg(all(target_os = "linux", feature = "perf-event"))]
+pub mod perf;
 mod resolver;
+mod scope;
 pub mod symbolize;
+#[cfg(feature = "unwind")]
+pub mod unwind;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod uring;
 mod util;
+mod vdso;
+pub mod vfs;
 mod zip;
 
 use std::fmt::Display;
@@ -75,6 +117,11 @@ pub use crate::error::Error;
 pub use crate::error::ErrorExt;
 pub use crate::error::ErrorKind;
 pub use crate::error::IntoError;
+pub use crate::features::features;
+pub use crate::features::Features;
+pub use crate::scope::CancellationToken;
+pub use crate::scope::Limits;
+pub use crate::scope::Progress;
 
 /// A result type using our [`Error`] by default.
 pub type Result<T, E = Error> = result::Result<T, E>;
@@ -87,11 +134,21 @@ pub type Addr = u64;
 /// Utility functionality not specific to any overarching theme.
 pub mod helper {
     pub use crate::normalize::buildid::read_elf_build_id;
+    pub use crate::normalize::buildid::BuildId;
+    pub use crate::normalize::buildid::BuildIdKind;
+    pub use crate::normalize::notes::AbiOs;
+    pub use crate::normalize::notes::AbiTag;
+    pub use crate::normalize::notes::ElfNotes;
+    pub use crate::normalize::notes::GnuProperty;
+    pub use crate::normalize::notes::PackageMetadata;
+
+    #[cfg(target_os = "linux")]
+    pub use crate::namespace::ProcessView;
 }
 
 
 /// An enumeration identifying a process.
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum Pid {
     /// The current process.
     Slf,
@@ -0,0 +1,529 @@
+//! Support for symbolization using linker map files, as emitted by GNU
+//! ld (`-Map`) and LLD (`-Map`).
+//!
+//! Map files list the address of each input section and of the global
+//! symbols contained therein. They are of interest for firmware and
+//! other embedded images, where neither debug information nor a symbol
+//! table may survive into the shipped artifact.
+
+use std::borrow::Cow;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::path::Path;
+
+use crate::inspect::FindAddrOpts;
+use crate::inspect::SymInfo;
+use crate::inspect::SymType;
+use crate::symbolize::AddrCodeInfo;
+use crate::symbolize::IntSym;
+use crate::symbolize::Reason;
+use crate::symbolize::SrcLang;
+use crate::Addr;
+use crate::Error;
+use crate::Result;
+use crate::SymResolver;
+
+
+/// The line in a GNU ld map file after which address information is
+/// listed.
+const GNU_MEMORY_MAP_MARKER: &str = "Linker script and memory map";
+
+
+/// A symbol as listed in a linker map file.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct MapSym {
+    /// The address of the symbol.
+    pub addr: Addr,
+    /// The size of the symbol, in bytes.
+    ///
+    /// Map files generally do not contain symbol sizes. Unless provided,
+    /// the size is inferred as the distance to the next symbol in the
+    /// same input section or to the end of the section.
+    pub size: usize,
+    /// The name of the symbol.
+    pub name: String,
+    /// The object file that the symbol stems from, if known.
+    pub obj: Option<String>,
+}
+
+
+/// An input section along with the symbols it contains.
+struct Section<'dat> {
+    /// The start address of the section.
+    start: Addr,
+    /// The end address of the section.
+    end: Addr,
+    /// The object file that the section stems from, if known.
+    obj: Option<&'dat str>,
+    /// Symbols (address, size if known, name) inside the section.
+    syms: Vec<(Addr, Option<usize>, &'dat str)>,
+}
+
+impl<'dat> Section<'dat> {
+    fn new(start: Addr, size: Addr, obj: &'dat str) -> Self {
+        Self {
+            start,
+            end: start.saturating_add(size),
+            obj: (!obj.is_empty()).then_some(obj),
+            syms: Vec::new(),
+        }
+    }
+
+    fn add_sym(&mut self, addr: Addr, size: Option<usize>, name: &'dat str) {
+        if (self.start..self.end).contains(&addr) {
+            let () = self.syms.push((addr, size, name));
+        }
+    }
+
+    /// Convert the section's symbols into [`MapSym`] objects, inferring
+    /// sizes where necessary.
+    fn into_syms(mut self, syms: &mut Vec<MapSym>) {
+        let () = self.syms.sort_by_key(|(addr, _size, _name)| *addr);
+        for (i, (addr, size, name)) in self.syms.iter().enumerate() {
+            let size = size.unwrap_or_else(|| {
+                let end = self
+                    .syms
+                    .get(i + 1)
+                    .map(|(next, _size, _name)| *next)
+                    .unwrap_or(self.end);
+                usize::try_from(end - addr).unwrap_or(usize::MAX)
+            });
+            let sym = MapSym {
+                addr: *addr,
+                size,
+                name: name.to_string(),
+                obj: self.obj.map(str::to_string),
+            };
+            let () = syms.push(sym);
+        }
+    }
+}
+
+
+/// Parse a hexadecimal number, with or without `0x` prefix.
+fn parse_hex(s: &str) -> Option<Addr> {
+    let s = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    Addr::from_str_radix(s, 16).ok()
+}
+
+/// Split off the first whitespace separated token of `s`.
+fn split_token(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start();
+    if s.is_empty() {
+        return None
+    }
+    Some(s.split_once(char::is_whitespace).unwrap_or((s, "")))
+}
+
+/// Check whether a name reported in a symbol position actually is a
+/// linker script statement, such as an assignment.
+fn is_statement(name: &str) -> bool {
+    name.is_empty()
+        || name.contains(" = ")
+        || name.starts_with("PROVIDE")
+        || name.starts_with("ASSERT")
+}
+
+
+/// Parse the memory map of a GNU ld map file.
+///
+/// Input sections are listed with a single space of indentation,
+/// followed by their name, address, size, and the object file they
+/// belong to. Names that are too long are put on a line of their own.
+/// Symbols are listed with more indentation and only consist of an
+/// address and a name.
+fn parse_gnu<'dat>(lines: impl Iterator<Item = &'dat str>, syms: &mut Vec<MapSym>) {
+    let mut section = None::<Section<'_>>;
+    // Whether we saw an input section name without address information
+    // on the previous line.
+    let mut pending = false;
+
+    for line in lines {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            continue
+        }
+
+        let indent = line.len() - trimmed.len();
+        if indent <= 1 {
+            // Output sections and other statements start at column 0,
+            // input sections and input section descriptions at column
+            // 1. Either way the previous input section ends here.
+            if let Some(section) = section.take() {
+                let () = section.into_syms(syms);
+            }
+            pending = false;
+
+            if indent == 1 {
+                if let Some((name, rest)) = split_token(trimmed) {
+                    if let Some((start, rest)) = split_token(rest) {
+                        if let Some((size, obj)) = split_token(rest) {
+                            if let (Some(start), Some(size)) = (parse_hex(start), parse_hex(size)) {
+                                section = Some(Section::new(start, size, obj.trim()));
+                            }
+                        }
+                    } else {
+                        // Patterns such as `*(.text)` never have
+                        // address information.
+                        pending = !name.starts_with('*');
+                    }
+                }
+            }
+            continue
+        }
+
+        let (addr, rest) = match split_token(trimmed) {
+            Some((addr, rest)) => match parse_hex(addr) {
+                Some(addr) => (addr, rest),
+                None => continue,
+            },
+            None => continue,
+        };
+
+        if pending {
+            pending = false;
+            if let Some((size, obj)) = split_token(rest) {
+                if let Some(size) = parse_hex(size) {
+                    section = Some(Section::new(addr, size, obj.trim()));
+                }
+            }
+            continue
+        }
+
+        let name = rest.trim();
+        if is_statement(name) {
+            continue
+        }
+
+        if let Some(section) = &mut section {
+            let () = section.add_sym(addr, None, name);
+        }
+    }
+
+    if let Some(section) = section.take() {
+        let () = section.into_syms(syms);
+    }
+}
+
+
+/// Parse an LLD map file, given its header line.
+///
+/// Each line lists a number of columns (virtual address, load address,
+/// size, alignment) followed by the name of an output section, input
+/// section, or symbol. Which of these a line describes is indicated by
+/// the column in which the name starts, as given by the header.
+fn parse_lld<'dat>(
+    header: &'dat str,
+    lines: impl Iterator<Item = &'dat str>,
+    syms: &mut Vec<MapSym>,
+) -> Option<()> {
+    let in_col = header.find(" In ")? + 1;
+    let sym_col = header.find("Symbol")?;
+    // Older versions of LLD do not report a load address.
+    let columns = if header.contains("LMA") { 4 } else { 3 };
+    let mut section = None::<Section<'_>>;
+
+    let parse_values = |line: &'dat str| {
+        let mut rest = line;
+        let mut values = [0; 4];
+        for value in values.iter_mut().take(columns) {
+            let (token, remainder) = split_token(rest)?;
+            *value = parse_hex(token)?;
+            rest = remainder;
+        }
+        Some((values, rest))
+    };
+
+    for line in lines {
+        let (values, rest) = match parse_values(line) {
+            Some(result) => result,
+            None => continue,
+        };
+
+        let name = rest.trim_start();
+        let col = line.len() - name.len();
+        let name = name.trim_end();
+        let addr = values[0];
+        let size = values[columns - 2];
+
+        if col >= sym_col {
+            if let Some(section) = &mut section {
+                let size = (size != 0).then(|| usize::try_from(size).unwrap_or(usize::MAX));
+                let () = section.add_sym(addr, size, name);
+            }
+        } else {
+            if let Some(section) = section.take() {
+                let () = section.into_syms(syms);
+            }
+
+            if col >= in_col {
+                // Input sections are reported as `file.o:(.text)`.
+                let obj = name.rsplit_once(":(").map(|(obj, _)| obj).unwrap_or("");
+                section = Some(Section::new(addr, size, obj));
+            }
+        }
+    }
+
+    if let Some(section) = section.take() {
+        let () = section.into_syms(syms);
+    }
+    Some(())
+}
+
+
+/// Parse the contents of a GNU ld or LLD map file.
+///
+/// Symbols are reported in file order. Lines that cannot be
+/// interpreted are skipped.
+pub(crate) fn parse(data: &str) -> Result<Vec<MapSym>> {
+    let mut syms = Vec::new();
+    let mut lines = data.lines();
+
+    let header = lines.clone().find(|line| !line.trim().is_empty());
+    match header {
+        Some(header)
+            if ["VMA", "Address"]
+                .iter()
+                .any(|col| header.trim_start().starts_with(col))
+                && header.contains("Symbol") =>
+        {
+            let lines = lines.skip_while(|line| *line != header).skip(1);
+            let () = parse_lld(header, lines, &mut syms)
+                .ok_or_else(|| Error::with_invalid_data("LLD map file header is malformed"))?;
+        }
+        _ => {
+            if !lines.any(|line| line.trim_end() == GNU_MEMORY_MAP_MARKER) {
+                return Err(Error::with_unsupported_format(
+                    "file is not a recognized linker map file",
+                ))
+            }
+            let () = parse_gnu(lines, &mut syms);
+        }
+    }
+    Ok(syms)
+}
+
+
+/// A symbol resolver backed by the contents of a linker map file.
+pub(crate) struct LinkerMapResolver {
+    /// Symbols, sorted by address.
+    syms: Vec<MapSym>,
+}
+
+impl LinkerMapResolver {
+    /// Create a new `LinkerMapResolver` from the provided symbols.
+    pub(crate) fn new(mut syms: Vec<MapSym>) -> Self {
+        let () = syms.sort_by_key(|sym| sym.addr);
+        Self { syms }
+    }
+
+    fn find_map_sym(&self, addr: Addr) -> Result<&MapSym, Reason> {
+        if self.syms.is_empty() {
+            return Err(Reason::MissingSyms)
+        }
+
+        let idx = self.syms.partition_point(|sym| sym.addr <= addr);
+        self.syms[..idx]
+            .iter()
+            .rev()
+            .take_while(|sym| sym.addr == self.syms[idx.saturating_sub(1)].addr)
+            .find(|sym| addr - sym.addr < sym.size.max(1) as Addr)
+            .ok_or(Reason::UnknownAddr)
+    }
+}
+
+impl SymResolver for LinkerMapResolver {
+    fn find_sym(&self, addr: Addr) -> Result<Result<IntSym<'_>, Reason>> {
+        let sym = self.find_map_sym(addr).map(|sym| IntSym {
+            name: &sym.name,
+            addr: sym.addr,
+            size: Some(sym.size),
+            // Map files do not carry any source code language
+            // information.
+            lang: SrcLang::Unknown,
+        });
+        Ok(sym)
+    }
+
+    fn find_addr<'slf>(&'slf self, name: &str, opts: &FindAddrOpts) -> Result<Vec<SymInfo<'slf>>> {
+        if let SymType::Variable = opts.sym_type {
+            return Ok(Vec::new())
+        }
+
+        let syms = self
+            .syms
+            .iter()
+            .filter(|sym| sym.name == name)
+            .map(|sym| SymInfo {
+                name: Cow::Borrowed(&sym.name),
+                addr: sym.addr,
+                size: sym.size,
+                sym_type: SymType::Unknown,
+                file_offset: None,
+                obj_file_name: sym.obj.as_deref().map(|obj| Cow::Borrowed(Path::new(obj))),
+                section: None,
+            })
+            .collect();
+        Ok(syms)
+    }
+
+    fn find_code_info(&self, _addr: Addr, _inlined_fns: bool) -> Result<Option<AddrCodeInfo<'_>>> {
+        Ok(None)
+    }
+}
+
+impl Debug for LinkerMapResolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "LinkerMapResolver")
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ErrorKind;
+
+
+    fn sym(addr: Addr, size: usize, name: &str, obj: &str) -> MapSym {
+        MapSym {
+            addr,
+            size,
+            name: name.to_string(),
+            obj: Some(obj.to_string()),
+        }
+    }
+
+    /// Exercise the `Debug` representation of various types.
+    #[test]
+    fn debug_repr() {
+        let resolver = LinkerMapResolver::new(Vec::new());
+        assert_ne!(format!("{resolver:?}"), "");
+    }
+
+    /// Check that we can parse a map file as emitted by GNU ld.
+    #[test]
+    fn gnu_parsing() {
+        let data = r#"Discarded input sections
+
+ .note.GNU-stack
+                0x0000000000000000        0x0 /tmp/a.o
+
+Memory Configuration
+
+Name             Origin             Length             Attributes
+*default*        0x0000000000000000 0xffffffffffffffff
+
+Linker script and memory map
+
+LOAD /tmp/a.o
+LOAD /tmp/b.o
+
+.text           0x0000000002000000      0x217
+ *(.text.main)
+ .text.main     0x0000000002000000       0x34 /tmp/a.o
+                0x0000000002000000                main
+ *(.text)
+ .text          0x0000000002000034       0x11 /tmp/a.o
+ .text          0x0000000002000045       0x22 /tmp/b.o
+                0x0000000002000050                bar
+                0x0000000002000056                foo
+                0x0000000002000100                . = ABSOLUTE (0x2000100)
+ *fill*         0x0000000002000067       0x99
+ *(.text.factorial)
+ .text.factorial
+                0x0000000002000100       0x2b /tmp/a.o
+                0x0000000002000100                factorial
+                0x0000000002000200                PROVIDE (__end = .)
+
+.data           0x0000000002000217        0x0
+                0x0000000002000217                _edata = .
+OUTPUT(/tmp/t.bin elf64-x86-64)
+"#;
+        let syms = parse(data).unwrap();
+        assert_eq!(
+            syms,
+            vec![
+                sym(0x2000000, 0x34, "main", "/tmp/a.o"),
+                sym(0x2000050, 0x6, "bar", "/tmp/b.o"),
+                sym(0x2000056, 0x11, "foo", "/tmp/b.o"),
+                sym(0x2000100, 0x2b, "factorial", "/tmp/a.o"),
+            ]
+        );
+    }
+
+    /// Check that we can parse a map file as emitted by LLD.
+    #[test]
+    fn lld_parsing() {
+        let data = r#"             VMA              LMA     Size Align Out     In      Symbol
+          200158           200158       1c     1 .rodata
+          200158           200158       1c     1         <internal>:(.rodata)
+          201000           201000       60    16 .text
+          201000           201000       34    16         /tmp/a.o:(.text)
+          201000           201000        0     1                 main
+          201010           201010        8     1                 helper
+          201040           201040       20    16         /tmp/libfoo.a(b.o):(.text.foo)
+          201040           201040        0     1                 foo
+          202000           202000        0     1 .comment
+"#;
+        let syms = parse(data).unwrap();
+        assert_eq!(
+            syms,
+            vec![
+                sym(0x201000, 0x10, "main", "/tmp/a.o"),
+                sym(0x201010, 0x8, "helper", "/tmp/a.o"),
+                sym(0x201040, 0x20, "foo", "/tmp/libfoo.a(b.o)"),
+            ]
+        );
+
+        // Older versions of LLD do not report load addresses.
+        let data = r#"Address          Size             Align Out     In      Symbol
+0000000000201000 0000000000000034    16 .text
+0000000000201000 0000000000000034    16         /tmp/a.o:(.text)
+0000000000201000 0000000000000000     0                 main
+"#;
+        let syms = parse(data).unwrap();
+        assert_eq!(syms, vec![sym(0x201000, 0x34, "main", "/tmp/a.o")]);
+    }
+
+    /// Make sure that we reject data that is not a map file.
+    #[test]
+    fn invalid_map() {
+        let err = parse("7f5c3c001000 80 Interpreter\n").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedFormat);
+    }
+
+    /// Check that symbols are looked up as expected.
+    #[test]
+    fn sym_lookup() {
+        let resolver = LinkerMapResolver::new(vec![
+            sym(0x2000, 0x10, "bar", "b.o"),
+            sym(0x1000, 0x10, "foo", "a.o"),
+            sym(0x1010, 0x0, "empty", "a.o"),
+        ]);
+        let find = |addr| resolver.find_map_sym(addr).map(|sym| sym.name.as_str());
+
+        assert_eq!(find(0x1000), Ok("foo"));
+        assert_eq!(find(0x100f), Ok("foo"));
+        assert_eq!(find(0x1010), Ok("empty"));
+        assert_eq!(find(0x1011), Err(Reason::UnknownAddr));
+        assert_eq!(find(0x2008), Ok("bar"));
+        assert_eq!(find(0x0), Err(Reason::UnknownAddr));
+
+        let syms = resolver.find_addr("bar", &FindAddrOpts::default()).unwrap();
+        assert_eq!(syms.len(), 1);
+        assert_eq!(syms[0].addr, 0x2000);
+        assert_eq!(syms[0].obj_file_name.as_deref(), Some(Path::new("b.o")));
+
+        let resolver = LinkerMapResolver::new(Vec::new());
+        assert_eq!(
+            resolver.find_map_sym(0x1000).map(|sym| sym.name.as_str()),
+            Err(Reason::MissingSyms)
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! Minimal support for the Mach-O object file format.
+//!
+//! Only the parts necessary for identifying a Mach-O module are
+//! covered. The format is described in `<mach-o/loader.h>`.
+
+use crate::util::ReadRaw as _;
+use crate::IntoError as _;
+use crate::Result;
+
+const MH_MAGIC: u32 = 0xfeedface;
+const MH_CIGAM: u32 = 0xcefaedfe;
+const MH_MAGIC_64: u32 = 0xfeedfacf;
+const MH_CIGAM_64: u32 = 0xcffaedfe;
+
+/// The load command containing a module's UUID.
+const LC_UUID: u32 = 0x1b;
+
+/// The size of the `mach_header` structure, in bytes.
+const MACH_HEADER_SIZE: usize = 28;
+/// The size of the `mach_header_64` structure, in bytes.
+const MACH_HEADER_64_SIZE: usize = 32;
+
+
+/// Read the magic number at the start of `data`, if any.
+fn read_magic(data: &[u8]) -> Option<u32> {
+    let mut data = data;
+    data.read_u32()
+}
+
+/// Check whether `data` represents a (thin) Mach-O file.
+pub(crate) fn is_macho(data: &[u8]) -> bool {
+    matches!(
+        read_magic(data),
+        Some(MH_MAGIC | MH_CIGAM | MH_MAGIC_64 | MH_CIGAM_64)
+    )
+}
+
+/// Read the UUID of the Mach-O file represented by `data`, as stored in
+/// its `LC_UUID` load command.
+///
+/// Returns [`None`] if the file does not contain a UUID.
+pub(crate) fn read_uuid(data: &[u8]) -> Result<Option<[u8; 16]>> {
+    let (header_size, swap) = match read_magic(data) {
+        Some(MH_MAGIC) => (MACH_HEADER_SIZE, false),
+        Some(MH_CIGAM) => (MACH_HEADER_SIZE, true),
+        Some(MH_MAGIC_64) => (MACH_HEADER_64_SIZE, false),
+        Some(MH_CIGAM_64) => (MACH_HEADER_64_SIZE, true),
+        _ => {
+            return Err(crate::Error::with_unsupported_format(
+                "data is not a Mach-O file",
+            ))
+        }
+    };
+    let fix = |value: u32| if swap { value.swap_bytes() } else { value };
+
+    let mut header = data
+        .get(..header_size)
+        .ok_or_invalid_data(|| "failed to read Mach-O header")?;
+    // Skip `magic`, `cputype`, `cpusubtype`, and `filetype`.
+    let _skipped = header.read_slice(16).unwrap();
+    let ncmds = fix(header.read_u32().unwrap());
+
+    let mut cmds = &data[header_size..];
+    for _ in 0..ncmds {
+        let mut cmd_data = cmds;
+        let cmd = cmd_data
+            .read_u32()
+            .map(fix)
+            .ok_or_invalid_data(|| "failed to read Mach-O load command")?;
+        let cmdsize = cmd_data
+            .read_u32()
+            .map(fix)
+            .ok_or_invalid_data(|| "failed to read Mach-O load command size")?;
+        if cmdsize < 8 {
+            return Err(crate::Error::with_invalid_data(format!(
+                "encountered invalid Mach-O load command size {cmdsize}"
+            )))
+        }
+
+        if cmd == LC_UUID {
+            let uuid = cmd_data
+                .read_slice(16)
+                .ok_or_invalid_data(|| "failed to read Mach-O UUID")?;
+            // SANITY: We read exactly 16 bytes.
+            return Ok(Some(<[u8; 16]>::try_from(uuid).unwrap()))
+        }
+
+        let _cmd = cmds
+            .read_slice(cmdsize as _)
+            .ok_or_invalid_data(|| "Mach-O load command exceeds file bounds")?;
+    }
+    Ok(None)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ErrorKind;
+
+
+    /// Create a minimal Mach-O file with the given magic and load
+    /// commands, in native byte order unless `swap` is set.
+    fn macho(magic: u32, cmds: &[(u32, &[u8])], swap: bool) -> Vec<u8> {
+        let fix = |value: u32| if swap { value.swap_bytes() } else { value };
+        let mut data = Vec::new();
+        let () = data.extend_from_slice(&magic.to_ne_bytes());
+        // `cputype`, `cpusubtype`, and `filetype`.
+        let () = data.extend_from_slice(&[0; 12]);
+        let () = data.extend_from_slice(&fix(cmds.len() as u32).to_ne_bytes());
+        let sizeofcmds = cmds.iter().map(|(_, data)| 8 + data.len()).sum::<usize>();
+        let () = data.extend_from_slice(&fix(sizeofcmds as u32).to_ne_bytes());
+        // `flags`
+        let () = data.extend_from_slice(&[0; 4]);
+        if magic == MH_MAGIC_64 || magic == MH_CIGAM_64 {
+            // `reserved`
+            let () = data.extend_from_slice(&[0; 4]);
+        }
+
+        for (cmd, cmd_data) in cmds {
+            let () = data.extend_from_slice(&fix(*cmd).to_ne_bytes());
+            let () = data.extend_from_slice(&fix(8 + cmd_data.len() as u32).to_ne_bytes());
+            let () = data.extend_from_slice(cmd_data);
+        }
+        data
+    }
+
+    /// Check that we can read the UUID of Mach-O files.
+    #[test]
+    fn uuid_reading() {
+        let uuid = [
+            0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54,
+            0x32, 0x10,
+        ];
+        let cmds = [(0x19, [0; 64].as_slice()), (LC_UUID, uuid.as_slice())];
+
+        for (magic, swap) in [
+            (MH_MAGIC, false),
+            (MH_MAGIC_64, false),
+            (MH_CIGAM, true),
+            (MH_CIGAM_64, true),
+        ] {
+            let data = macho(magic, &cmds, swap);
+            assert!(is_macho(&data));
+            assert_eq!(read_uuid(&data).unwrap(), Some(uuid));
+        }
+
+        let data = macho(MH_MAGIC_64, &cmds[..1], false);
+        assert_eq!(read_uuid(&data).unwrap(), None);
+    }
+
+    /// Check that we fail gracefully on invalid Mach-O data.
+    #[test]
+    fn invalid_data() {
+        assert!(!is_macho(b"\x7fELF"));
+        let err = read_uuid(b"\x7fELF").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedFormat);
+
+        let uuid = [0; 16];
+        let data = macho(MH_MAGIC_64, &[(LC_UUID, uuid.as_slice())], false);
+        for len in [4, MACH_HEADER_64_SIZE + 4, data.len() - 1] {
+            let err = read_uuid(&data[..len]).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+
+        // A load command with a bogus size must not result in an
+        // endless loop.
+        let mut data = macho(MH_MAGIC_64, &[(0x19, [0; 8].as_slice())], false);
+        data[MACH_HEADER_64_SIZE + 4..MACH_HEADER_64_SIZE + 8].copy_from_slice(&0u32.to_ne_bytes());
+        let err = read_uuid(&data).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -1,15 +1,17 @@
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
-use std::fs::File;
 use std::io::BufRead;
 use std::io::BufReader;
+use std::io::Cursor;
 use std::io::Error;
 use std::io::ErrorKind;
 use std::io::Read;
 use std::ops::Range;
+use std::path::Path;
 use std::path::PathBuf;
 
+use crate::vfs::FileAccess;
 use crate::Addr;
 use crate::ErrorExt as _;
 use crate::IntoError as _;
@@ -17,7 +19,7 @@ use crate::Pid;
 use crate::Result;
 
 
-#[derive(Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub(crate) struct EntryPath {
     /// The path of the file backing the maps entry via a
     /// `/proc/<xxx>/map_files/` component.
@@ -31,12 +33,15 @@ pub(crate) struct EntryPath {
     /// parsed. This path has been sanitized and no longer contains any
     /// `(deleted)` suffixes.
     pub symbolic_path: PathBuf,
+    /// Whether the file was deleted (or replaced) after it got mapped,
+    /// as indicated by a `(deleted)` suffix.
+    pub deleted: bool,
 }
 
 
 /// The "pathname" component in a proc maps entry. See `proc(5)` section
 /// `/proc/[pid]/maps`.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub(crate) enum PathName {
     Path(EntryPath),
     Component(String),
@@ -61,7 +66,7 @@ impl PathName {
 }
 
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub(crate) struct MapsEntry {
     /// The virtual address range covered by this entry.
     pub range: Range<Addr>,
@@ -100,7 +105,10 @@ impl Debug for PathMapsEntry {
 
 
 /// Parse a line of a proc maps file.
-fn parse_maps_line<'line>(line: &'line str, pid: Pid) -> Result<MapsEntry> {
+///
+/// If `pid` is `None`, the maps data are not associated with a live
+/// process and files are referenced by their symbolic path only.
+fn parse_maps_line<'line>(line: &'line str, pid: Option<Pid>) -> Result<MapsEntry> {
     let full_line = line;
 
     let split_once = |line: &'line str, component| -> Result<(&'line str, &'line str)> {
@@ -159,16 +167,23 @@ fn parse_maps_line<'line>(line: &'line str, pid: Pid) -> Result<MapsEntry> {
     let path_name = match path_str.as_bytes() {
         [] => None,
         [b'/', ..] => {
-            let symbolic_path =
-                PathBuf::from(path_str.strip_suffix(" (deleted)").unwrap_or(path_str));
+            let (path_str, deleted) = match path_str.strip_suffix(" (deleted)") {
+                Some(path_str) => (path_str, true),
+                None => (path_str, false),
+            };
+            let symbolic_path = PathBuf::from(path_str);
             // TODO: May have to resolve the symbolic link in case of
             //       `Pid::Slf` here for remote symbolization use cases.
-            let maps_file = PathBuf::from(format!(
-                "/proc/{pid}/map_files/{loaded_addr:x}-{end_addr:x}"
-            ));
+            let maps_file = match pid {
+                Some(pid) => PathBuf::from(format!(
+                    "/proc/{pid}/map_files/{loaded_addr:x}-{end_addr:x}"
+                )),
+                None => symbolic_path.clone(),
+            };
             Some(PathName::Path(EntryPath {
                 maps_file,
                 symbolic_path,
+                deleted,
             }))
         }
         // This variant would typically capture components such as `[vdso]` or
@@ -192,7 +207,7 @@ fn parse_maps_line<'line>(line: &'line str, pid: Pid) -> Result<MapsEntry> {
 struct MapsEntryIter<R> {
     reader: R,
     line: String,
-    pid: Pid,
+    pid: Option<Pid>,
 }
 
 impl<R> Iterator for MapsEntryIter<R>
@@ -234,42 +249,72 @@ where
     MapsEntryIter {
         reader: BufReader::new(reader),
         line: String::new(),
-        pid,
+        pid: Some(pid),
+    }
+}
+
+/// Parse a previously captured proc maps file from the provided reader.
+///
+/// Contrary to [`parse_file`], the resulting entries do not refer to
+/// files via `/proc/<pid>/map_files/`, as the process they describe may
+/// no longer exist, but via the paths recorded in the maps data.
+pub(crate) fn parse_snapshot<R>(reader: R) -> impl Iterator<Item = Result<MapsEntry>>
+where
+    R: Read,
+{
+    MapsEntryIter {
+        reader: BufReader::new(reader),
+        line: String::new(),
+        pid: None,
     }
 }
 
-/// Parse the maps file for the process with the given PID.
-pub(crate) fn parse(pid: Pid) -> Result<impl Iterator<Item = Result<MapsEntry>>> {
+/// Parse the maps file for the process with the given PID, accessing
+/// it through `fs`.
+///
+/// Only Linux exposes process memory maps this way; elsewhere an error
+/// of kind [`Unsupported`][crate::ErrorKind::Unsupported] is reported.
+pub(crate) fn parse(
+    fs: &dyn FileAccess,
+    pid: Pid,
+) -> Result<impl Iterator<Item = Result<MapsEntry>>> {
+    if cfg!(not(target_os = "linux")) {
+        return Err(crate::Error::with_unsupported(
+            "process symbolization and normalization is only supported on Linux",
+        ))
+    }
+
     let path = format!("/proc/{pid}/maps");
-    let file =
-        File::open(&path).with_context(|| format!("failed to open proc maps file {path}"))?;
-    let iter = parse_file(file, pid);
+    let data = fs
+        .open(Path::new(&path))
+        .with_context(|| format!("failed to open proc maps file {path}"))?;
+    let iter = parse_file(Cursor::new(data), pid);
     Ok(iter)
 }
 
 /// A helper function checking whether a `MapsEntry` has relevance to
 /// symbolization efforts and converting it accordingly.
 pub(crate) fn filter_map_relevant(entry: MapsEntry) -> Option<PathMapsEntry> {
-    let MapsEntry {
-        range,
-        mode,
-        offset,
-        path_name,
-    } = entry;
+    classify_relevant(entry).ok()
+}
 
+/// Check whether a `MapsEntry` has relevance to symbolization efforts,
+/// converting it into a `PathMapsEntry` if so and handing it back
+/// otherwise.
+pub(crate) fn classify_relevant(entry: MapsEntry) -> Result<PathMapsEntry, MapsEntry> {
     // Only readable (r---) or executable (--x-) entries are of relevance.
-    if (mode & 0b1010) == 0 {
-        return None
+    if (entry.mode & 0b1010) == 0 {
+        return Err(entry)
     }
 
-    match path_name {
-        Some(PathName::Path(path)) => Some(PathMapsEntry {
-            range,
-            mode,
-            offset,
+    match entry.path_name {
+        Some(PathName::Path(path)) => Ok(PathMapsEntry {
+            range: entry.range,
+            mode: entry.mode,
+            offset: entry.offset,
             path,
         }),
-        _ => None,
+        path_name => Err(MapsEntry { path_name, ..entry }),
     }
 }
 
@@ -282,6 +327,8 @@ mod tests {
 
     use test_log::test;
 
+    use crate::vfs::StdFileAccess;
+
 
     /// Check that the `Debug` representation of [`Entry`] is as expected.
     #[test]
@@ -293,6 +340,7 @@ mod tests {
             path: EntryPath {
                 maps_file: PathBuf::from("/proc/1234/maps_files/559cf1bdf000-559cf1be0000"),
                 symbolic_path: PathBuf::from("/lib64/libc.so.6"),
+                deleted: false,
             },
         };
 
@@ -309,7 +357,7 @@ mod tests {
     #[allow(clippy::suspicious_map)]
     #[test]
     fn self_map_parsing() {
-        let maps = parse(Pid::Slf).unwrap();
+        let maps = parse(&StdFileAccess::default(), Pid::Slf).unwrap();
         assert_ne!(maps.map(|entry| entry.unwrap()).count(), 0);
     }
 
@@ -364,7 +412,7 @@ ffffffffff600000-ffffffffff601000 --xp 00000000 00:00 0                  [vsysca
         });
 
         // Parse the first (actual) line.
-        let entry = parse_maps_line(lines.lines().nth(1).unwrap(), Pid::Slf).unwrap();
+        let entry = parse_maps_line(lines.lines().nth(1).unwrap(), Some(Pid::Slf)).unwrap();
         assert_eq!(entry.range.start, 0x400000);
         assert_eq!(entry.range.end, 0x401000);
         assert_eq!(
@@ -378,7 +426,7 @@ ffffffffff600000-ffffffffff601000 --xp 00000000 00:00 0                  [vsysca
             Path::new("/proc/self/map_files/400000-401000")
         );
 
-        let entry = parse_maps_line(lines.lines().nth(7).unwrap(), Pid::Slf).unwrap();
+        let entry = parse_maps_line(lines.lines().nth(7).unwrap(), Some(Pid::Slf)).unwrap();
         assert_eq!(entry.range.start, 0x55f4a95cb000);
         assert_eq!(entry.range.end, 0x55f4a95cf000);
         assert_eq!(entry.mode, 0b1011);
@@ -394,7 +442,7 @@ ffffffffff600000-ffffffffff601000 --xp 00000000 00:00 0                  [vsysca
         );
         assert_eq!(entry.path_name.as_ref().unwrap().as_component(), None);
 
-        let entry = parse_maps_line(lines.lines().nth(11).unwrap(), Pid::Slf).unwrap();
+        let entry = parse_maps_line(lines.lines().nth(11).unwrap(), Some(Pid::Slf)).unwrap();
         assert_eq!(entry.range.start, 0x55f4aa379000);
         assert_eq!(entry.range.end, 0x55f4aa39a000);
         assert_eq!(entry.mode, 0b1101);
@@ -404,7 +452,7 @@ ffffffffff600000-ffffffffff601000 --xp 00000000 00:00 0                  [vsysca
         );
         assert_eq!(entry.path_name.as_ref().unwrap().as_path(), None);
 
-        let entry = parse_maps_line(lines.lines().nth(13).unwrap(), Pid::Slf).unwrap();
+        let entry = parse_maps_line(lines.lines().nth(13).unwrap(), Some(Pid::Slf)).unwrap();
         assert_eq!(entry.mode, 0b1001);
         assert_eq!(
             entry
@@ -418,6 +466,30 @@ ffffffffff600000-ffffffffff601000 --xp 00000000 00:00 0                  [vsysca
         );
     }
 
+    /// Check that entries of a proc maps snapshot refer to files by
+    /// their symbolic path.
+    #[test]
+    fn snapshot_parsing() {
+        let lines = r#"
+7f2321e37000-7f2321f6f000 r-xp 00037000 00:20 1808269                    /usr/lib64/libgnutls.so.30.34.1 (deleted)
+7ffd033ab000-7ffd033ad000 r-xp 00000000 00:00 0                          [vdso]
+"#;
+
+        let entries = parse_snapshot(lines.as_bytes())
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let path = entries[0].path_name.as_ref().unwrap().as_path().unwrap();
+        assert_eq!(path.maps_file, Path::new("/usr/lib64/libgnutls.so.30.34.1"));
+        assert_eq!(path.symbolic_path, path.maps_file);
+        assert!(path.deleted);
+        assert_eq!(
+            entries[1].path_name.as_ref().unwrap().as_component(),
+            Some("[vdso]")
+        );
+    }
+
     /// Check that we error out as expected on malformed proc maps lines.
     #[test]
     fn malformed_proc_maps_lines() {
@@ -436,7 +508,7 @@ ffffffffff600000-ffffffffff601000 --xp 00000000 00:00 0                  [vsysca
         ];
 
         let () = lines.iter().for_each(|line| {
-            let _err = parse_maps_line(line, Pid::Slf).unwrap_err();
+            let _err = parse_maps_line(line, Some(Pid::Slf)).unwrap_err();
         });
     }
 }
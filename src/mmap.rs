@@ -1,34 +1,124 @@
 use std::fs::File;
 use std::io;
+#[cfg(not(unix))]
+use std::io::Read as _;
 use std::ops::Deref;
 use std::ops::Range;
+#[cfg(unix)]
 use std::os::unix::io::AsRawFd;
 #[cfg(test)]
 use std::path::Path;
+#[cfg(unix)]
 use std::ptr::null_mut;
-use std::rc::Rc;
+#[cfg(unix)]
 use std::slice;
+use std::sync::Arc;
 
+use crate::log;
+#[cfg(unix)]
 use crate::Error;
+#[cfg(unix)]
 use crate::ErrorExt as _;
 use crate::Result;
 
 
+/// Hints about the expected usage of large memory mapped files, as
+/// provided to the kernel via `madvise`.
+///
+/// Hints are applied to memory mapped debug files and symbol indexes
+/// whose size reaches the configured threshold. They have no effect
+/// on systems not supporting them and failure to apply them is not
+/// considered an error.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MmapAdvice {
+    /// The size, in bytes, starting at which a memory mapping is
+    /// considered large.
+    pub threshold: u64,
+    /// Whether to request backing by transparent huge pages
+    /// (`MADV_HUGEPAGE`), reducing TLB pressure on random access.
+    ///
+    /// This hint is only supported on Linux.
+    pub huge_pages: bool,
+    /// Whether to request eager read-ahead of the entire mapping
+    /// (`MADV_WILLNEED`), reducing page fault stalls on first access.
+    pub will_need: bool,
+    /// Whether to announce sequential access (`MADV_SEQUENTIAL`) while
+    /// DWARF debug information is being indexed.
+    pub sequential_indexing: bool,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl MmapAdvice {
+    /// Apply the hints concerning random access to `mmap`, if it is
+    /// large enough.
+    pub(crate) fn apply(&self, mmap: &Mmap) {
+        if !self.applies(mmap) {
+            return
+        }
+
+        let advices = [
+            (self.huge_pages, Advice::HugePage),
+            (self.will_need, Advice::WillNeed),
+        ];
+        for (_, advice) in advices.into_iter().filter(|(enabled, _)| *enabled) {
+            if let Err(err) = mmap.advise(advice) {
+                log::debug!("failed to apply {advice:?} advice to memory mapping: {err}");
+            }
+        }
+    }
+
+    /// Check whether the hints apply to `mmap`.
+    pub(crate) fn applies(&self, mmap: &Mmap) -> bool {
+        mmap.is_mapped() && mmap.len() as u64 >= self.threshold
+    }
+}
+
+impl Default for MmapAdvice {
+    fn default() -> Self {
+        Self {
+            threshold: 64 * 1024 * 1024,
+            huge_pages: true,
+            will_need: true,
+            sequential_indexing: true,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+
+/// An access pattern hint for a memory mapping.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Advice {
+    /// No special treatment.
+    Normal,
+    /// Pages will be accessed in sequential order.
+    Sequential,
+    /// Pages will be accessed soon.
+    WillNeed,
+    /// Back the mapping with transparent huge pages.
+    HugePage,
+}
+
+
 #[derive(Debug)]
 pub(crate) struct Builder {
     /// The protection flags to use.
+    #[cfg(unix)]
     protection: libc::c_int,
 }
 
 impl Builder {
     fn new() -> Self {
         Self {
+            #[cfg(unix)]
             protection: libc::PROT_READ,
         }
     }
 
     /// Configure the mapping to be executable.
-    #[cfg(test)]
+    #[cfg(all(test, unix))]
     pub fn exec(mut self) -> Self {
         self.protection |= libc::PROT_EXEC;
         self
@@ -45,6 +135,7 @@ impl Builder {
     }
 
     /// Map the provided file into memory, in its entirety.
+    #[cfg(unix)]
     pub fn map(self, file: &File) -> Result<Mmap> {
         let len = libc::size_t::try_from(file.metadata()?.len())
             .map_err(Error::with_invalid_data)
@@ -69,20 +160,33 @@ impl Builder {
 
         let mapping = Mapping { ptr, len };
         let mmap = Mmap {
-            mapping: Rc::new(mapping),
+            data: Arc::new(Data::Mapped(mapping)),
             view: 0..len as u64,
         };
         Ok(mmap)
     }
+
+    /// Read the provided file into memory, in its entirety.
+    ///
+    /// On platforms without `mmap` support we fall back to reading the
+    /// file's contents into a heap-allocated buffer.
+    #[cfg(not(unix))]
+    pub fn map(self, mut file: &File) -> Result<Mmap> {
+        let mut data = Vec::new();
+        let _count = file.read_to_end(&mut data)?;
+        Ok(Mmap::from_vec(data))
+    }
 }
 
 
+#[cfg(unix)]
 #[derive(Debug)]
 pub(crate) struct Mapping {
     ptr: *mut libc::c_void,
     len: usize,
 }
 
+#[cfg(unix)]
 impl Deref for Mapping {
     type Target = [u8];
 
@@ -93,6 +197,16 @@ impl Deref for Mapping {
     }
 }
 
+// SAFETY: The mapping is read-only once created and merely unmapped on
+//         drop, so it can safely be sent to and accessed from other
+//         threads.
+#[cfg(unix)]
+unsafe impl Send for Mapping {}
+// SAFETY: See above.
+#[cfg(unix)]
+unsafe impl Sync for Mapping {}
+
+#[cfg(unix)]
 impl Drop for Mapping {
     fn drop(&mut self) {
         // SAFETY: The `ptr` is valid.
@@ -103,10 +217,35 @@ impl Drop for Mapping {
 }
 
 
+/// The data backing a [`Mmap`].
+#[derive(Debug)]
+enum Data {
+    /// An actual memory mapping.
+    #[cfg(unix)]
+    Mapped(Mapping),
+    /// Data read into memory, for sources that cannot be mapped.
+    Owned(Box<[u8]>),
+}
+
+impl Deref for Data {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            #[cfg(unix)]
+            Self::Mapped(mapping) => mapping.deref(),
+            Self::Owned(data) => data.deref(),
+        }
+    }
+}
+
+
+/// A memory mapped file or, for data that cannot be mapped, a
+/// heap-allocated stand-in with the same interface.
 #[derive(Clone, Debug)]
 pub(crate) struct Mmap {
-    /// The actual memory mapping.
-    mapping: Rc<Mapping>,
+    /// The backing data.
+    data: Arc<Data>,
     /// The view on the memory mapping that this object represents.
     view: Range<u64>,
 }
@@ -122,6 +261,70 @@ impl Mmap {
         Self::builder().map(file)
     }
 
+    /// Create a `Mmap` object backed by the provided data instead of
+    /// an actual memory mapping.
+    pub fn from_vec(data: Vec<u8>) -> Self {
+        let len = data.len();
+        Self {
+            data: Arc::new(Data::Owned(data.into_boxed_slice())),
+            view: 0..len as u64,
+        }
+    }
+
+    /// Check whether the object is backed by an actual memory mapping.
+    pub fn is_mapped(&self) -> bool {
+        match *self.data {
+            #[cfg(unix)]
+            Data::Mapped(..) => true,
+            Data::Owned(..) => false,
+        }
+    }
+
+    /// Provide `advice` about the usage of the memory backing the
+    /// current view.
+    ///
+    /// Advice is silently ignored for data not backed by a memory
+    /// mapping and on systems not supporting it.
+    #[cfg(unix)]
+    pub fn advise(&self, advice: Advice) -> io::Result<()> {
+        let advice = match advice {
+            Advice::Normal => libc::MADV_NORMAL,
+            Advice::Sequential => libc::MADV_SEQUENTIAL,
+            Advice::WillNeed => libc::MADV_WILLNEED,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            Advice::HugePage => libc::MADV_HUGEPAGE,
+            #[cfg(not(any(target_os = "linux", target_os = "android")))]
+            Advice::HugePage => return Ok(()),
+        };
+
+        if !self.is_mapped() || self.is_empty() {
+            return Ok(())
+        }
+
+        // `madvise` requires a page aligned start address.
+        // SAFETY: `sysconf` is always safe to call.
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let start = self.as_ptr() as usize;
+        let aligned = start - start % page_size;
+        let len = self.len() + (start - aligned);
+        // SAFETY: The range is part of our mapping, which stays valid
+        //         for the duration of the call.
+        let rc = unsafe { libc::madvise(aligned as *mut libc::c_void, len, advice) };
+        if rc < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        Ok(())
+    }
+
+    /// Provide `advice` about the usage of the memory backing the
+    /// current view.
+    ///
+    /// Advice is not supported on this platform and silently ignored.
+    #[cfg(not(unix))]
+    pub fn advise(&self, _advice: Advice) -> io::Result<()> {
+        Ok(())
+    }
+
     /// Create a new `Mmap` object (sharing the same underlying memory mapping
     /// as the current one) that restricts its view to the provided `range`.
     /// Adjustment happens relative to the current view.
@@ -141,7 +344,7 @@ impl Deref for Mmap {
     type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
-        self.mapping
+        self.data
             .deref()
             .get(self.view.start as usize..self.view.end as usize)
             .unwrap()
@@ -205,4 +408,46 @@ mod tests {
 
         assert!(mmap.constrain(1..2).is_none());
     }
+
+    /// Check that we can provide advice about the usage of a `Mmap`.
+    #[test]
+    fn advice() {
+        let mut file = tempfile().unwrap();
+        let () = file.write_all(&[0; 8192]).unwrap();
+        let () = file.sync_all().unwrap();
+
+        let mmap = Mmap::map(&file).unwrap();
+        assert!(mmap.is_mapped());
+        // Views not starting at a page boundary are supported as well.
+        let view = mmap.constrain(1..4097).unwrap();
+        for advice in [Advice::Sequential, Advice::WillNeed, Advice::Normal] {
+            let () = mmap.advise(advice).unwrap();
+            let () = view.advise(advice).unwrap();
+        }
+
+        let owned = Mmap::from_vec(vec![0; 8192]);
+        assert!(!owned.is_mapped());
+        let () = owned.advise(Advice::WillNeed).unwrap();
+
+        let advice = MmapAdvice {
+            threshold: 8192,
+            ..Default::default()
+        };
+        assert!(advice.applies(&mmap));
+        assert!(!advice.applies(&view));
+        assert!(!advice.applies(&owned));
+        // Failure to apply hints, e.g., because transparent huge pages
+        // are unsupported, is not fatal.
+        let () = advice.apply(&mmap);
+    }
+
+    /// Check that a `Mmap` can be backed by heap-allocated data.
+    #[test]
+    fn owned_data() {
+        let mmap = Mmap::from_vec(b"abcdefghijklmnopqrstuvwxyz".to_vec());
+        assert_eq!(mmap.deref(), b"abcdefghijklmnopqrstuvwxyz");
+
+        let mmap = mmap.constrain(1..3).unwrap();
+        assert_eq!(mmap.deref(), b"bc");
+    }
 }
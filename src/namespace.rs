@@ -9,6 +9,7 @@ use std::io::BufReader;
 use std::os::unix::fs::MetadataExt;
 use std::os::unix::io::AsFd as _;
 use std::os::unix::io::AsRawFd as _;
+use std::path::Path;
 use std::path::PathBuf;
 use std::str::FromStr;
 
@@ -19,6 +20,7 @@ use crate::log::warn;
 use crate::Error;
 use crate::ErrorExt;
 use crate::Pid;
+use crate::Result;
 
 fn get_nspid(pid: Pid) -> Result<(Pid, Pid), Error> {
     let fname = format!("/proc/{pid}/status");
@@ -61,7 +63,7 @@ fn get_nspid(pid: Pid) -> Result<(Pid, Pid), Error> {
 }
 
 fn enter_mntns(nsi: &NsInfo) -> Result<(), Error> {
-    if !nsi.need_setns {
+    if !nsi.entered {
         return Ok(());
     }
 
@@ -73,7 +75,10 @@ fn enter_mntns(nsi: &NsInfo) -> Result<(), Error> {
     if rc < 0 {
         let err = io::Error::last_os_error();
         warn!("setns to {:?} failed, err {}", mntns_path, err);
-        return Err(Error::from(err))
+        return Err(Error::with_namespace_failure(format!(
+            "failed to enter mount namespace `{}`: {err}",
+            mntns_path.display()
+        )))
     }
     Ok(())
 }
@@ -82,6 +87,9 @@ pub(crate) struct NsInfo {
     tgid: Pid,
     nstgid: Pid,
     need_setns: bool,
+    /// Whether we entered the process' mount namespace (and have to
+    /// switch back to ours eventually).
+    entered: bool,
     mntns_path: Option<PathBuf>,
     oldns: File,
     // From https://github.com/torvalds/linux/commit/b01c1f69c8660eaeab7d365cd570103c5c073a02, we see
@@ -91,7 +99,14 @@ pub(crate) struct NsInfo {
     oldcwd: PathBuf,
 }
 impl NsInfo {
-    pub(crate) fn new(pid: Pid) -> Result<Self, Error> {
+    /// Gather namespace information about the process identified by
+    /// `pid`, entering its mount namespace if `enter` is `true`.
+    ///
+    /// Entering a mount namespace affects the calling process as a
+    /// whole and fails if it is multi-threaded. Without entering it,
+    /// files of the process should be accessed via
+    /// [`root`][NsInfo::root].
+    pub(crate) fn new(pid: Pid, enter: bool) -> Result<Self, Error> {
         let old_stat_path = "/proc/self/ns/mnt";
         let new_stat_path = format!("/proc/{pid}/ns/mnt");
         let old_stat = fs::metadata(old_stat_path).context("failed to stat `/proc/self/ns/mnt`")?;
@@ -110,6 +125,7 @@ impl NsInfo {
             tgid,
             nstgid,
             need_setns,
+            entered: enter && need_setns,
             mntns_path,
             oldns,
             oldcwd,
@@ -121,13 +137,24 @@ impl NsInfo {
 
 
     pub(crate) fn pid(&self) -> Pid {
-        if self.need_setns {
+        if self.entered {
             self.nstgid
         } else {
             self.tgid
         }
     }
 
+    /// Retrieve the path under which the root directory of the
+    /// process' mount namespace is accessible, if the namespace differs
+    /// from ours and has not been entered.
+    pub(crate) fn root(&self) -> Option<PathBuf> {
+        if self.need_setns && !self.entered {
+            Some(PathBuf::from(format!("/proc/{}/root", self.tgid)))
+        } else {
+            None
+        }
+    }
+
     #[cfg(test)]
     pub(crate) fn need_setns(&self) -> bool {
         self.need_setns
@@ -136,7 +163,7 @@ impl NsInfo {
 
 impl Drop for NsInfo {
     fn drop(&mut self) {
-        if !self.need_setns {
+        if !self.entered {
             return;
         }
         // SAFTEY: `setns` with the legal file descriptor is always safe to call.
@@ -156,6 +183,102 @@ impl Drop for NsInfo {
     }
 }
 
+
+/// A view of the file system of a process, as seen from within its
+/// mount namespace.
+///
+/// Processes running inside containers typically live in mount and PID
+/// namespaces different from ours. Paths they refer to (e.g., files
+/// they emit or reference in configuration) are only meaningful inside
+/// their mount namespace, and file names may embed the process ID as
+/// the process sees it. A `ProcessView` takes care of translating
+/// both, without entering the process' namespace and, hence, without
+/// affecting the calling process.
+#[derive(Clone, Debug)]
+pub struct ProcessView {
+    /// The ID of the process in our PID namespace.
+    pid: Pid,
+    /// The ID of the process in its own PID namespace.
+    ns_pid: Pid,
+    /// The path under which the root directory of the process' mount
+    /// namespace is accessible, if it differs from ours.
+    root: Option<PathBuf>,
+}
+
+impl ProcessView {
+    /// Create a new `ProcessView` for the process identified by `pid`.
+    pub fn new(pid: Pid) -> Result<Self> {
+        let nsi = NsInfo::new(pid, false)?;
+        let slf = Self {
+            pid: nsi.tgid,
+            ns_pid: nsi.nstgid,
+            root: nsi.root(),
+        };
+        Ok(slf)
+    }
+
+    /// Retrieve the ID of the process as seen from our PID namespace.
+    #[inline]
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    /// Retrieve the ID of the process as seen from within its own PID
+    /// namespace.
+    ///
+    /// This is the ID the process uses when naming files after itself,
+    /// such as `/tmp/perf-<pid>.map`.
+    #[inline]
+    pub fn ns_pid(&self) -> Pid {
+        self.ns_pid
+    }
+
+    /// Check whether the process lives in a mount namespace different
+    /// from ours.
+    #[inline]
+    pub fn is_foreign_mount_ns(&self) -> bool {
+        self.root.is_some()
+    }
+
+    /// Translate a path, as seen by the process, into one that can be
+    /// used to access the same file from our mount namespace.
+    ///
+    /// Relative paths are interpreted relative to the process' current
+    /// working directory.
+    pub fn resolve_path(&self, path: &Path) -> PathBuf {
+        if path.is_relative() {
+            return Path::new(&format!("/proc/{}/cwd", self.pid)).join(path)
+        }
+
+        match &self.root {
+            Some(root) => {
+                // `join` would discard `root` for absolute paths.
+                let mut root = root.clone().into_os_string();
+                let () = root.push(path.as_os_str());
+                PathBuf::from(root)
+            }
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Open the file at `path`, as seen by the process, for reading.
+    ///
+    /// Please refer to [`resolve_path`][ProcessView::resolve_path] for
+    /// details on how `path` is interpreted.
+    pub fn open(&self, path: &Path) -> Result<File> {
+        let resolved = self.resolve_path(path);
+        let file = File::open(&resolved).with_context(|| {
+            format!(
+                "failed to open `{}` of process {} (as `{}`)",
+                path.display(),
+                self.pid,
+                resolved.display()
+            )
+        })?;
+        Ok(file)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -177,10 +300,45 @@ mod tests {
 
     #[test]
     fn access_same_mnt_ns() {
-        let nsi = NsInfo::new(Pid::Slf);
-        assert!(nsi.as_ref().is_ok());
-        let nsi = nsi.unwrap();
-        assert!(!nsi.need_setns());
-        assert!(enter_mntns(&nsi).is_ok());
+        for enter in [true, false] {
+            let nsi = NsInfo::new(Pid::Slf, enter);
+            assert!(nsi.as_ref().is_ok());
+            let nsi = nsi.unwrap();
+            assert!(!nsi.need_setns());
+            assert_eq!(nsi.root(), None);
+            assert_eq!(nsi.nstgid, Pid::from(process::id()));
+            assert!(enter_mntns(&nsi).is_ok());
+        }
+    }
+
+    /// Check that we can resolve paths of a process in our own mount
+    /// namespace.
+    #[test]
+    fn process_view_same_mnt_ns() {
+        let view = ProcessView::new(Pid::Slf).unwrap();
+        let pid = Pid::from(process::id());
+        assert_eq!(view.pid(), pid);
+        assert_eq!(view.ns_pid(), pid);
+        assert!(!view.is_foreign_mount_ns());
+
+        let path = Path::new("/proc/self/status");
+        assert_eq!(view.resolve_path(path), path);
+        let path = Path::new("Cargo.toml");
+        assert_eq!(
+            view.resolve_path(path),
+            Path::new(&format!("/proc/{pid}/cwd/Cargo.toml"))
+        );
+        let _file = view.open(path).unwrap();
+
+        let err = view.open(Path::new("/does-not-exist")).unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::NotFound);
+    }
+
+    /// Check that we report an error for a process that does not
+    /// exist.
+    #[test]
+    fn process_view_invalid_pid() {
+        let err = ProcessView::new(Pid::from(u32::MAX)).unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::NotFound);
     }
 }
@@ -0,0 +1,499 @@
+//! Non-blocking variants of the symbolization and inspection APIs.
+//!
+//! Symbolization and inspection may involve blocking operations, such
+//! as file I/O, fetching of debug information from debuginfod servers,
+//! or resolving paths in other mount namespaces. The types in this
+//! module offload these operations to a pool of worker threads and
+//! hand out futures ([`Task`]) that complete once the work is done.
+//! These futures are not tied to any specific executor and can be
+//! awaited from any `async` context without blocking it.
+//!
+//! ```no_run
+//! # async fn f() -> blazesym::Result<()> {
+//! use blazesym::nonblocking::Symbolizer;
+//! use blazesym::symbolize::Input;
+//! use blazesym::symbolize::Process;
+//! use blazesym::symbolize::Source;
+//! use blazesym::Pid;
+//!
+//! let symbolizer = Symbolizer::new();
+//! let src = Source::Process(Process::new(Pid::Slf));
+//! let syms = symbolizer
+//!     .symbolize(&src, Input::AbsAddr(&[0xffffffff81000000]))
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::future::Future;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+use std::thread;
+
+use crate::inspect;
+use crate::inspect::SymInfo;
+use crate::log;
+use crate::symbolize;
+use crate::symbolize::Input;
+use crate::symbolize::Symbolized;
+use crate::CancellationToken;
+use crate::Result;
+
+
+type Job = Box<dyn FnOnce() + Send>;
+
+
+/// A pool of worker threads executing jobs.
+struct Pool {
+    /// The sending end of the job queue. Workers shut down once it is
+    /// dropped.
+    sender: Mutex<mpsc::Sender<Job>>,
+    /// The number of workers successfully started.
+    workers: usize,
+}
+
+impl Pool {
+    fn new(threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..threads.max(1))
+            .filter(|_| {
+                let receiver = Arc::clone(&receiver);
+                let result = thread::Builder::new()
+                    .name("blazesym-worker".to_string())
+                    .spawn(move || loop {
+                        let job = receiver
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .recv();
+                        match job {
+                            Ok(job) => job(),
+                            Err(mpsc::RecvError) => break,
+                        }
+                    });
+                match result {
+                    Ok(_handle) => true,
+                    Err(err) => {
+                        log::warn!("failed to spawn worker thread: {err}");
+                        false
+                    }
+                }
+            })
+            .count();
+
+        Self {
+            sender: Mutex::new(sender),
+            workers,
+        }
+    }
+
+    /// Run `f` on one of the pool's workers.
+    ///
+    /// If the pool has no workers, `f` is run on the calling thread.
+    fn spawn<F, T>(&self, f: F, token: Option<CancellationToken>) -> Task<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let shared = Arc::new(Mutex::new(Shared {
+            result: None,
+            waker: None,
+        }));
+        let task = Task {
+            shared: Arc::clone(&shared),
+            token,
+        };
+
+        let job = Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+            let mut shared = shared.lock().unwrap_or_else(PoisonError::into_inner);
+            shared.result = Some(result);
+            if let Some(waker) = shared.waker.take() {
+                let () = waker.wake();
+            }
+        });
+
+        let job = if self.workers > 0 {
+            let sender = self.sender.lock().unwrap_or_else(PoisonError::into_inner);
+            match sender.send(job) {
+                Ok(()) => return task,
+                Err(mpsc::SendError(job)) => job,
+            }
+        } else {
+            job
+        };
+        let () = job();
+        task
+    }
+}
+
+impl Debug for Pool {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct(stringify!(Pool))
+            .field("workers", &self.workers)
+            .finish()
+    }
+}
+
+
+/// State shared between a [`Task`] and the job computing its result.
+struct Shared<T> {
+    /// The result of the job, once it finished.
+    result: Option<thread::Result<T>>,
+    /// The waker to notify once the result is available.
+    waker: Option<Waker>,
+}
+
+
+/// A future representing work performed on a worker thread.
+///
+/// Dropping a `Task` before it completed requests cancellation of the
+/// underlying operation, if it supports it.
+#[must_use = "tasks do nothing unless awaited"]
+pub struct Task<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+    /// The token to cancel once the task is dropped, if any.
+    token: Option<CancellationToken>,
+}
+
+impl<T> Future for Task<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.shared.lock().unwrap_or_else(PoisonError::into_inner);
+        match shared.result.take() {
+            Some(Ok(result)) => Poll::Ready(result),
+            Some(Err(payload)) => panic::resume_unwind(payload),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Drop for Task<T> {
+    fn drop(&mut self) {
+        if let Some(token) = &self.token {
+            let () = token.cancel();
+        }
+    }
+}
+
+impl<T> Debug for Task<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let done = self
+            .shared
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .result
+            .is_some();
+        f.debug_struct(stringify!(Task))
+            .field("done", &done)
+            .finish()
+    }
+}
+
+
+/// A non-blocking front-end to a [`symbolize::Symbolizer`].
+///
+/// Requests are served by a pool of worker threads, sized according
+/// to the parallelism configured for the wrapped symbolizer (see
+/// [`symbolize::Builder::set_parallelism`]), all of which share its
+/// caches.
+///
+/// Because results are handed across threads, they never borrow from
+/// the symbolizer's caches, i.e., all [`Symbolized`] objects reported
+/// are fully owned.
+#[derive(Debug)]
+pub struct Symbolizer {
+    symbolizer: Arc<symbolize::Symbolizer>,
+    pool: Pool,
+}
+
+impl Symbolizer {
+    /// Create a new [`Symbolizer`] wrapping a default configured
+    /// [`symbolize::Symbolizer`].
+    pub fn new() -> Self {
+        Self::from(symbolize::Symbolizer::new())
+    }
+
+    /// Symbolize a list of addresses.
+    ///
+    /// This method is the non-blocking equivalent of
+    /// [`symbolize::Symbolizer::symbolize`]. Dropping the returned
+    /// [`Task`] cancels the request.
+    pub fn symbolize(
+        &self,
+        src: &symbolize::Source<'static>,
+        input: Input<&[u64]>,
+    ) -> Task<Result<Vec<Symbolized<'static>>>> {
+        let symbolizer = Arc::clone(&self.symbolizer);
+        let src = src.clone();
+        let addrs = input.into_inner().to_vec();
+        let input = input.map(|_addrs| ());
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+
+        self.pool.spawn(
+            move || {
+                let input = input.map(|()| addrs.as_slice());
+                let syms = symbolizer.symbolize_cancellable(&src, input, &token)?;
+                let syms = syms.iter().map(Symbolized::to_owned).collect();
+                Ok(syms)
+            },
+            Some(cancel),
+        )
+    }
+
+    /// Symbolize a single address.
+    ///
+    /// This method is the non-blocking equivalent of
+    /// [`symbolize::Symbolizer::symbolize_single`].
+    pub fn symbolize_single(
+        &self,
+        src: &symbolize::Source<'static>,
+        input: Input<u64>,
+    ) -> Task<Result<Symbolized<'static>>> {
+        let symbolizer = Arc::clone(&self.symbolizer);
+        let src = src.clone();
+
+        self.pool.spawn(
+            move || {
+                let sym = symbolizer.symbolize_single(&src, input)?;
+                Ok(sym.to_owned())
+            },
+            None,
+        )
+    }
+
+    /// Retrieve the wrapped [`symbolize::Symbolizer`].
+    ///
+    /// The returned object may be used for issuing blocking requests
+    /// or for managing the shared caches.
+    pub fn inner(&self) -> &Arc<symbolize::Symbolizer> {
+        &self.symbolizer
+    }
+}
+
+impl Default for Symbolizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<symbolize::Symbolizer> for Symbolizer {
+    fn from(symbolizer: symbolize::Symbolizer) -> Self {
+        let pool = Pool::new(symbolizer.parallelism());
+        Self {
+            symbolizer: Arc::new(symbolizer),
+            pool,
+        }
+    }
+}
+
+
+/// A non-blocking front-end to an [`inspect::Inspector`].
+///
+/// Requests are served by a pool of worker threads, sized according
+/// to the available parallelism of the system, all of which share the
+/// inspector's caches.
+#[derive(Debug)]
+pub struct Inspector {
+    inspector: Arc<inspect::Inspector>,
+    pool: Pool,
+}
+
+impl Inspector {
+    /// Create a new [`Inspector`] wrapping a default configured
+    /// [`inspect::Inspector`].
+    pub fn new() -> Self {
+        Self::from(inspect::Inspector::new())
+    }
+
+    /// Look up information (address etc.) about a list of symbols,
+    /// given their names.
+    ///
+    /// This method is the non-blocking equivalent of
+    /// [`inspect::Inspector::lookup`].
+    pub fn lookup(
+        &self,
+        src: &inspect::Source,
+        names: &[&str],
+    ) -> Task<Result<Vec<Vec<SymInfo<'static>>>>> {
+        let inspector = Arc::clone(&self.inspector);
+        let src = src.clone();
+        let names = names
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+
+        self.pool.spawn(
+            move || {
+                let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+                let syms = inspector.lookup(&src, &names)?;
+                let syms = syms
+                    .iter()
+                    .map(|syms| syms.iter().map(SymInfo::to_owned).collect())
+                    .collect();
+                Ok(syms)
+            },
+            None,
+        )
+    }
+
+    /// Retrieve the wrapped [`inspect::Inspector`].
+    pub fn inner(&self) -> &Arc<inspect::Inspector> {
+        &self.inspector
+    }
+}
+
+impl Default for Inspector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<inspect::Inspector> for Inspector {
+    fn from(inspector: inspect::Inspector) -> Self {
+        let threads = thread::available_parallelism()
+            .map(usize::from)
+            .unwrap_or(1);
+        Self {
+            inspector: Arc::new(inspector),
+            pool: Pool::new(threads),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+    use std::sync::Condvar;
+    use std::task::Wake;
+
+    use crate::symbolize::Elf;
+    use crate::symbolize::Source;
+
+
+    /// A waker unblocking a thread waiting for a task to complete.
+    #[derive(Default)]
+    struct Signal {
+        woken: Mutex<bool>,
+        cond: Condvar,
+    }
+
+    impl Wake for Signal {
+        fn wake(self: Arc<Self>) {
+            *self.woken.lock().unwrap() = true;
+            let () = self.cond.notify_one();
+        }
+    }
+
+    /// Drive `future` to completion on the calling thread.
+    fn block_on<F>(future: F) -> F::Output
+    where
+        F: Future,
+    {
+        let signal = Arc::new(Signal::default());
+        let waker = Waker::from(Arc::clone(&signal));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                break output
+            }
+
+            let mut woken = signal.woken.lock().unwrap();
+            while !*woken {
+                woken = signal.cond.wait(woken).unwrap();
+            }
+            *woken = false;
+        }
+    }
+
+
+    /// Exercise the `Debug` representation of various types.
+    #[test]
+    fn debug_repr() {
+        let symbolizer = Symbolizer::new();
+        assert_ne!(format!("{symbolizer:?}"), "");
+
+        let inspector = Inspector::new();
+        assert_ne!(format!("{inspector:?}"), "");
+
+        let task = Pool::new(1).spawn(|| 42, None);
+        assert_ne!(format!("{task:?}"), "");
+    }
+
+    /// Check that tasks report the result of the work performed.
+    #[test]
+    fn task_completion() {
+        let pool = Pool::new(2);
+        let tasks = (0..16)
+            .map(|i| pool.spawn(move || i * 2, None))
+            .collect::<Vec<_>>();
+        let results = tasks.into_iter().map(block_on).collect::<Vec<_>>();
+        assert_eq!(results, (0..16).map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    /// Check that a panic on a worker thread is propagated to the
+    /// awaiting party.
+    #[test]
+    #[should_panic = "worker panic"]
+    fn task_panic() {
+        let pool = Pool::new(1);
+        let task = pool.spawn(|| panic!("worker panic"), None);
+        let () = block_on(task);
+    }
+
+    /// Check that we can symbolize addresses without blocking.
+    #[test]
+    fn symbolization() {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-stable-addresses.bin");
+        let src = Source::Elf(Elf::new(path));
+        let symbolizer = Symbolizer::new();
+
+        let task = symbolizer.symbolize(&src, Input::VirtOffset(&[0x2000100, 0x2000200]));
+        let syms = block_on(task).unwrap();
+        assert_eq!(syms.len(), 2);
+        assert_eq!(syms[0].as_sym().unwrap().name, "factorial");
+
+        let task = symbolizer.symbolize_single(&src, Input::VirtOffset(0x2000100));
+        let sym = block_on(task).unwrap();
+        assert_eq!(sym, syms[0]);
+    }
+
+    /// Check that we can look up symbols without blocking.
+    #[test]
+    fn lookup() {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-stable-addresses.bin");
+        let src = inspect::Source::Elf(inspect::Elf::new(path));
+        let inspector = Inspector::new();
+
+        let task = inspector.lookup(&src, &["factorial"]);
+        let syms = block_on(task).unwrap();
+        assert_eq!(syms.len(), 1);
+        assert_eq!(syms[0][0].addr, 0x2000100);
+    }
+}
@@ -1,16 +1,38 @@
+use std::fs::File;
 use std::path::Path;
 
 use crate::elf;
 use crate::elf::types::Elf64_Nhdr;
+use crate::elf::types::NT_GNU_BUILD_ID;
 use crate::elf::ElfParser;
 use crate::log::warn;
+use crate::macho;
+use crate::mmap::Mmap;
+use crate::normalize::PdbInfo;
+use crate::pe;
 use crate::util::ReadRaw as _;
+use crate::vfs::FileAccess;
+use crate::vfs::FileData;
+use crate::vfs::StdFileAccess;
 use crate::IntoError as _;
 use crate::Result;
 
 
-/// Typedefs for functions reading build IDs.
-pub(crate) type BuildIdFn = dyn Fn(&Path) -> Result<Option<Vec<u8>>>;
+/// Typedefs for functions reading module identification information.
+pub(crate) type ModuleInfoFn<'f> = dyn Fn(&Path) -> Result<ModuleInfo> + 'f;
+
+
+/// Identification information about a module, by object file format.
+#[derive(Debug, PartialEq)]
+pub(crate) enum ModuleInfo {
+    /// An ELF file, along with its build ID.
+    Elf(Option<Vec<u8>>),
+    /// A Mach-O file, along with its UUID.
+    MachO(Option<[u8; 16]>),
+    /// A PE file, along with information about the PDB file it
+    /// references.
+    Pe(Option<PdbInfo>),
+}
 
 
 /// Iterate over all note sections to find one of type
@@ -42,6 +64,35 @@ fn read_build_id_from_notes(parser: &ElfParser) -> Result<Option<Vec<u8>>> {
     Ok(None)
 }
 
+/// Read the GNU build ID from a set of ELF notes, such as those
+/// exported for kernel modules in `/sys/module/<name>/notes/` or the
+/// contents of a `PT_NOTE` segment.
+pub(crate) fn read_build_id_from_note_data(mut data: &[u8]) -> Result<Option<Vec<u8>>> {
+    /// Round `value` up to the next multiple of four, the alignment
+    /// of note name and descriptor data.
+    fn align4(value: u32) -> usize {
+        (value as usize + 3) & !3
+    }
+
+    while !data.is_empty() {
+        let header = data
+            .read_pod::<Elf64_Nhdr>()
+            .ok_or_invalid_data(|| "failed to read ELF note header")?;
+        let name = data
+            .read_slice(align4(header.n_namesz))
+            .ok_or_invalid_data(|| "failed to read ELF note name")?;
+        let desc = data
+            .read_slice(align4(header.n_descsz))
+            .ok_or_invalid_data(|| "failed to read ELF note descriptor")?;
+
+        if header.n_type == NT_GNU_BUILD_ID && name.starts_with(b"GNU\0") {
+            let build_id = desc[..header.n_descsz as usize].to_vec();
+            return Ok(Some(build_id))
+        }
+    }
+    Ok(None)
+}
+
 /// Attempt to read an ELF binary's build ID from the .note.gnu.build-id section.
 fn read_build_id_from_section_name(parser: &ElfParser) -> Result<Option<Vec<u8>>> {
     let build_id_section = ".note.gnu.build-id";
@@ -84,13 +135,73 @@ fn read_build_id_from_section_name(parser: &ElfParser) -> Result<Option<Vec<u8>>
     }
 }
 
-pub(super) trait BuildIdReader: 'static {
-    fn read_build_id_from_elf(path: &Path) -> Result<Option<Vec<u8>>>;
+/// The size of the build ID computed by
+/// [`compute_fallback_build_id`], in bytes.
+const FALLBACK_BUILD_ID_SIZE: usize = 16;
+
+/// The maximum number of bytes of the `.text` section hashed by
+/// [`compute_fallback_build_id`].
+const FALLBACK_BUILD_ID_TEXT_SIZE: usize = 4096;
+
+
+/// Compute a fallback identifier for an ELF binary lacking a build ID.
+///
+/// The identifier is derived from the contents of the binary's `.text`
+/// section, by XOR-ing together the 16 byte blocks of its first page.
+/// This is the same scheme that Breakpad (and tools compatible with it,
+/// such as `symbolic`) use, meaning that identifiers can be matched up
+/// with those reported by these tools.
+pub(crate) fn compute_fallback_build_id(parser: &ElfParser) -> Result<Option<Vec<u8>>> {
+    let idx = if let Some(idx) = parser.find_section(".text")? {
+        idx
+    } else {
+        return Ok(None)
+    };
+
+    let text = parser.section_data(idx)?;
+    if text.is_empty() {
+        return Ok(None)
+    }
+
+    let text = &text[..text.len().min(FALLBACK_BUILD_ID_TEXT_SIZE)];
+    let mut build_id = vec![0; FALLBACK_BUILD_ID_SIZE];
+    for chunk in text.chunks(FALLBACK_BUILD_ID_SIZE) {
+        let () = build_id
+            .iter_mut()
+            .zip(chunk)
+            .for_each(|(id, byte)| *id ^= byte);
+    }
+    Ok(Some(build_id))
+}
+
+
+pub(crate) trait BuildIdReader: 'static {
+    /// Attempt to read identification information about the module
+    /// contained in a file.
+    ///
+    /// Files are assumed to be ELF files, unless they are found to be
+    /// Mach-O ones.
+    #[cfg_attr(feature = "tracing", crate::log::instrument(skip(fs)))]
+    fn read_module_info(fs: &dyn FileAccess, path: &Path) -> Result<ModuleInfo> {
+        let data = fs.open(path)?;
+        if macho::is_macho(data.data()) {
+            let uuid = macho::read_uuid(data.data())?;
+            Ok(ModuleInfo::MachO(uuid))
+        } else if pe::is_pe(data.data()) {
+            let pdb = pe::read_pdb_info(data.data())?;
+            Ok(ModuleInfo::Pe(pdb))
+        } else {
+            let parser = ElfParser::from_mmap(data.mmap().clone());
+            let build_id = Self::read_build_id(&parser)?;
+            Ok(ModuleInfo::Elf(build_id))
+        }
+    }
+
     fn read_build_id(parser: &ElfParser) -> Result<Option<Vec<u8>>>;
 }
 
 
-pub(super) struct DefaultBuildIdReader;
+pub(crate) struct DefaultBuildIdReader;
 
 impl BuildIdReader for DefaultBuildIdReader {
     /// Attempt to read an ELF binary's build ID.
@@ -104,12 +215,21 @@ impl BuildIdReader for DefaultBuildIdReader {
             Ok(None)
         }
     }
+}
+
 
-    /// Attempt to read an ELF binary's build ID from a file.
+/// A [`BuildIdReader`] computing a fallback identifier for binaries
+/// without build ID.
+pub(super) struct FallbackBuildIdReader;
+
+impl BuildIdReader for FallbackBuildIdReader {
     #[cfg_attr(feature = "tracing", crate::log::instrument)]
-    fn read_build_id_from_elf(path: &Path) -> Result<Option<Vec<u8>>> {
-        let parser = ElfParser::open(path)?;
-        Self::read_build_id(&parser)
+    fn read_build_id(parser: &ElfParser) -> Result<Option<Vec<u8>>> {
+        if let Some(build_id) = DefaultBuildIdReader::read_build_id(parser)? {
+            Ok(Some(build_id))
+        } else {
+            compute_fallback_build_id(parser)
+        }
     }
 }
 
@@ -117,8 +237,8 @@ impl BuildIdReader for DefaultBuildIdReader {
 pub(super) struct NoBuildIdReader;
 
 impl BuildIdReader for NoBuildIdReader {
-    fn read_build_id_from_elf(_path: &Path) -> Result<Option<Vec<u8>>> {
-        Ok(None)
+    fn read_module_info(_fs: &dyn FileAccess, _path: &Path) -> Result<ModuleInfo> {
+        Ok(ModuleInfo::Elf(None))
     }
     fn read_build_id(_parser: &ElfParser) -> Result<Option<Vec<u8>>> {
         Ok(None)
@@ -168,7 +288,98 @@ pub fn read_elf_build_id<P>(path: &P) -> Result<Option<Vec<u8>>>
 where
     P: AsRef<Path>,
 {
-    DefaultBuildIdReader::read_build_id_from_elf(path.as_ref())
+    let data = StdFileAccess::default().open(path.as_ref())?;
+    let parser = ElfParser::from_mmap(data.mmap().clone());
+    DefaultBuildIdReader::read_build_id(&parser)
+}
+
+
+/// The kind of a [`BuildId`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum BuildIdKind {
+    /// A build ID as stored in an ELF note of type `NT_GNU_BUILD_ID`.
+    Gnu,
+    /// An identifier computed from the contents of an ELF file's
+    /// `.text` section, for files that lack a build ID. See
+    /// [`BuildId::compute_fallback`].
+    Fallback,
+}
+
+
+/// A build ID identifying a binary.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct BuildId {
+    /// The kind of the build ID.
+    pub kind: BuildIdKind,
+    /// The raw bytes making up the build ID.
+    pub id: Vec<u8>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl BuildId {
+    pub(crate) fn from_parser(parser: &ElfParser) -> Result<Option<Self>> {
+        let build_id = DefaultBuildIdReader::read_build_id(parser)?.map(|id| Self {
+            kind: BuildIdKind::Gnu,
+            id,
+            _non_exhaustive: (),
+        });
+        Ok(build_id)
+    }
+
+    /// Read the GNU build ID of the ELF file at `path`.
+    ///
+    /// Returns [`None`] if the file does not contain a build ID.
+    pub fn from_elf_path<P>(path: &P) -> Result<Option<Self>>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let data = StdFileAccess::default().open(path)?;
+        let parser = ElfParser::from_mmap(data.mmap().clone());
+        Self::from_parser(&parser)
+    }
+
+    /// Read the GNU build ID of the provided open ELF file.
+    ///
+    /// On Unix systems, an existing file descriptor can be used by
+    /// converting it into a [`File`] first, e.g., via
+    /// [`FromRawFd`][std::os::unix::io::FromRawFd].
+    ///
+    /// Returns [`None`] if the file does not contain a build ID.
+    pub fn from_elf_file(file: &File) -> Result<Option<Self>> {
+        let data = FileData::from_file(file)?;
+        let parser = ElfParser::from_mmap(data.mmap().clone());
+        Self::from_parser(&parser)
+    }
+
+    /// Read the GNU build ID of the ELF file contained in `data`.
+    ///
+    /// Returns [`None`] if the file does not contain a build ID.
+    pub fn from_elf_data(data: &[u8]) -> Result<Option<Self>> {
+        let parser = ElfParser::from_mmap(Mmap::from_vec(data.to_vec()));
+        Self::from_parser(&parser)
+    }
+
+    /// Compute the fallback identifier for the ELF file contained in
+    /// `data`.
+    ///
+    /// This is the identifier reported during normalization for files
+    /// without a GNU build ID, if enabled via
+    /// [`Builder::enable_build_id_fallback`][crate::normalize::Builder::enable_build_id_fallback].
+    ///
+    /// Returns [`None`] if the file does not contain a `.text` section.
+    pub fn compute_fallback(data: &[u8]) -> Result<Option<Self>> {
+        let parser = ElfParser::from_mmap(Mmap::from_vec(data.to_vec()));
+        let build_id = compute_fallback_build_id(&parser)?.map(|id| Self {
+            kind: BuildIdKind::Fallback,
+            id,
+            _non_exhaustive: (),
+        });
+        Ok(build_id)
+    }
 }
 
 
@@ -221,4 +432,79 @@ mod tests {
         let build_id = read_elf_build_id(&elf).unwrap();
         assert_eq!(build_id, None);
     }
+
+    /// Check that we compute a fallback build ID for binaries that
+    /// lack one.
+    #[test]
+    fn build_id_fallback() {
+        let elf = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-no-debug.bin");
+        let parser = ElfParser::open(&elf).unwrap();
+        let build_id = FallbackBuildIdReader::read_build_id(&parser)
+            .unwrap()
+            .unwrap();
+        assert_eq!(build_id.len(), FALLBACK_BUILD_ID_SIZE);
+        assert_ne!(build_id, vec![0; FALLBACK_BUILD_ID_SIZE]);
+        // The computation is deterministic.
+        let parser = ElfParser::open(&elf).unwrap();
+        assert_eq!(
+            compute_fallback_build_id(&parser).unwrap().unwrap(),
+            build_id
+        );
+
+        // Binaries with a build ID report it unchanged.
+        let elf = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("libtest-so.so");
+        let parser = ElfParser::open(&elf).unwrap();
+        let build_id = FallbackBuildIdReader::read_build_id(&parser)
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some(build_id), read_elf_build_id(&elf).unwrap());
+    }
+
+    /// Check that we can read build IDs from paths, files, and
+    /// in-memory data.
+    #[test]
+    fn build_id_reading_public() {
+        let elf = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("libtest-so.so");
+        let build_id = BuildId::from_elf_path(&elf).unwrap().unwrap();
+        assert_eq!(build_id.kind, BuildIdKind::Gnu);
+        assert_eq!(
+            Some(&build_id.id),
+            read_elf_build_id(&elf).unwrap().as_ref()
+        );
+
+        let file = File::open(&elf).unwrap();
+        assert_eq!(
+            BuildId::from_elf_file(&file).unwrap().as_ref(),
+            Some(&build_id)
+        );
+
+        let data = std::fs::read(&elf).unwrap();
+        assert_eq!(
+            BuildId::from_elf_data(&data).unwrap().as_ref(),
+            Some(&build_id)
+        );
+
+        let elf = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-no-debug.bin");
+        assert_eq!(BuildId::from_elf_path(&elf).unwrap(), None);
+
+        let data = std::fs::read(&elf).unwrap();
+        let fallback = BuildId::compute_fallback(&data).unwrap().unwrap();
+        assert_eq!(fallback.kind, BuildIdKind::Fallback);
+        let parser = ElfParser::open(&elf).unwrap();
+        assert_eq!(
+            Some(fallback.id),
+            FallbackBuildIdReader::read_build_id(&parser).unwrap()
+        );
+
+        let err = BuildId::from_elf_data(b"not an ELF file").unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::InvalidData);
+    }
 }
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::kmod::parse_modules;
+use crate::kmod::text_base;
+use crate::kmod::PROC_MODULES;
+use crate::kmod::SYS_MODULE;
+use crate::log::warn;
+use crate::vfs::FileAccess;
+use crate::Addr;
+use crate::ErrorExt as _;
+use crate::ErrorKind;
+use crate::Result;
+
+use super::buildid::read_build_id_from_note_data;
+use super::meta::KernelMeta;
+use super::meta::KernelModule;
+use super::meta::Unknown;
+use super::normalizer::Output;
+
+
+/// A type representing the output of kernel address normalization.
+///
+/// Contrary to the output of user space address normalization, the
+/// outputs for kernel module addresses are not file offsets, but
+/// offsets relative to the module's base address, i.e., the address
+/// of its `.text` section. The base address of the `.text` section in
+/// a kernel module's `.ko` file is zero, meaning that such an offset is
+/// directly usable for looking up symbols and line information in it.
+pub type KernelOutput = Output<KernelMeta>;
+
+
+/// Read the build ID of the kernel module with the given name.
+fn read_module_build_id(fs: &dyn FileAccess, name: &str) -> Result<Option<Vec<u8>>> {
+    let path = Path::new(SYS_MODULE)
+        .join(name)
+        .join("notes")
+        .join(".note.gnu.build-id");
+    let data = match fs.open(&path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    read_build_id_from_note_data(data.data())
+        .with_context(|| format!("failed to read build ID from {}", path.display()))
+}
+
+
+/// Normalize kernel addresses into offsets within the kernel modules
+/// containing them.
+///
+/// Addresses not belonging to any loaded kernel module (e.g., because
+/// they reside in the kernel image itself) are reported in
+/// non-normalized form along with [`Unknown`] meta data.
+pub(super) fn normalize_kernel_addrs_impl(
+    fs: &dyn FileAccess,
+    addrs: &[Addr],
+    read_build_ids: bool,
+) -> Result<KernelOutput> {
+    let data = fs.open(Path::new(PROC_MODULES))?;
+    let modules =
+        parse_modules(data.data()).with_context(|| format!("failed to parse {PROC_MODULES}"))?;
+
+    let mut normalized = KernelOutput {
+        outputs: Vec::with_capacity(addrs.len()),
+        meta: Vec::new(),
+    };
+    // Lookup table from module index to index into `normalized.meta`
+    // and the module's base address.
+    let mut meta_lookup = HashMap::<usize, (usize, Addr)>::new();
+    let mut unknown_idx = None;
+
+    for addr in addrs {
+        let idx = modules.partition_point(|module| module.range.start <= *addr);
+        let module = idx
+            .checked_sub(1)
+            .filter(|idx| modules[*idx].range.contains(addr));
+
+        let output = module.and_then(|module_idx| {
+            let module = &modules[module_idx];
+            let (meta_idx, base) = if let Some(entry) = meta_lookup.get(&module_idx) {
+                *entry
+            } else {
+                let build_id = if read_build_ids {
+                    read_module_build_id(fs, &module.name).unwrap_or_else(|err| {
+                        warn!(
+                            "failed to read build ID of kernel module `{}`: {err}",
+                            module.name
+                        );
+                        None
+                    })
+                } else {
+                    None
+                };
+                let meta = KernelMeta::Module(KernelModule {
+                    name: module.name.clone(),
+                    build_id,
+                    _non_exhaustive: (),
+                });
+                let meta_idx = normalized.meta.len();
+                let () = normalized.meta.push(meta);
+                let base = text_base(fs, module);
+                let _prev = meta_lookup.insert(module_idx, (meta_idx, base));
+                (meta_idx, base)
+            };
+            // Code placed ahead of the `.text` section can't be
+            // expressed relative to it.
+            let offset = addr.checked_sub(base)?;
+            Some((offset, meta_idx))
+        });
+
+        let output = output.unwrap_or_else(|| {
+            let meta_idx = *unknown_idx.get_or_insert_with(|| {
+                let () = normalized
+                    .meta
+                    .push(KernelMeta::Unknown(Unknown::default()));
+                normalized.meta.len() - 1
+            });
+            (*addr, meta_idx)
+        });
+        let () = normalized.outputs.push(output);
+    }
+
+    debug_assert_eq!(normalized.outputs.len(), addrs.len());
+    Ok(normalized)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    use crate::elf::types::NT_GNU_BUILD_ID;
+    use crate::vfs::FileData;
+    use crate::Error;
+
+
+    /// A [`FileAccess`] implementation serving files from memory.
+    #[derive(Debug, Default)]
+    struct MemFileAccess {
+        files: Mutex<HashMap<PathBuf, Vec<u8>>>,
+    }
+
+    impl MemFileAccess {
+        fn add(&self, path: &str, data: &[u8]) {
+            let _prev = self
+                .files
+                .lock()
+                .unwrap()
+                .insert(PathBuf::from(path), data.to_vec());
+        }
+    }
+
+    impl FileAccess for MemFileAccess {
+        fn open(&self, path: &Path) -> Result<FileData> {
+            self.files
+                .lock()
+                .unwrap()
+                .get(path)
+                .map(|data| FileData::from_vec(data.clone(), 0))
+                .ok_or_else(|| Error::with_not_found(format!("{} not found", path.display())))
+        }
+    }
+
+    /// Create the contents of a note file containing a GNU build ID.
+    fn build_id_note(build_id: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let () = data.extend_from_slice(&4u32.to_ne_bytes());
+        let () = data.extend_from_slice(&(build_id.len() as u32).to_ne_bytes());
+        let () = data.extend_from_slice(&NT_GNU_BUILD_ID.to_ne_bytes());
+        let () = data.extend_from_slice(b"GNU\0");
+        let () = data.extend_from_slice(build_id);
+        let () = data.resize((data.len() + 3) & !3, 0);
+        data
+    }
+
+
+    /// Check that we can read build IDs from note files.
+    #[test]
+    fn notes_build_id_reading() {
+        let mut data = Vec::new();
+        // A note of a different type, with a name and descriptor
+        // requiring padding.
+        let () = data.extend_from_slice(&6u32.to_ne_bytes());
+        let () = data.extend_from_slice(&3u32.to_ne_bytes());
+        let () = data.extend_from_slice(&1u32.to_ne_bytes());
+        let () = data.extend_from_slice(b"Linux\0\0\0");
+        let () = data.extend_from_slice(&[1, 2, 3, 0]);
+        let () = data.extend_from_slice(&build_id_note(&[0xab, 0xcd, 0xef]));
+
+        let build_id = read_build_id_from_note_data(&data).unwrap();
+        assert_eq!(build_id, Some(vec![0xab, 0xcd, 0xef]));
+
+        let build_id = read_build_id_from_note_data(&data[..24]).unwrap();
+        assert_eq!(build_id, None);
+
+        let err = read_build_id_from_note_data(&data[..data.len() - 4]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    /// Check that we normalize kernel addresses as expected.
+    #[test]
+    fn kernel_address_normalization() {
+        let fs = MemFileAccess::default();
+        let () = fs.add(
+            PROC_MODULES,
+            br#"xfs 2015232 1 - Live 0xffffffffc0c00000
+ext4 1036288 1 - Live 0xffffffffc0a5f000
+hidden 4096 0 - Live 0x0000000000000000
+"#,
+        );
+        let () = fs.add(
+            "/sys/module/ext4/notes/.note.gnu.build-id",
+            &build_id_note(&[0x12, 0x34]),
+        );
+        let () = fs.add("/sys/module/xfs/sections/.text", b"0xffffffffc0c00008\n");
+
+        let addrs = [
+            0xffffffffc0c00010,
+            0xffffffff81000000,
+            0xffffffffc0a5f100,
+            0xffffffffc0c00020,
+            0xffffffffc0b5c000,
+            0xffffffffc0c00004,
+        ];
+        let normalized = normalize_kernel_addrs_impl(&fs, &addrs, true).unwrap();
+        assert_eq!(normalized.outputs.len(), addrs.len());
+        assert_eq!(normalized.meta.len(), 3);
+
+        let (offset, meta_idx) = normalized.outputs[0];
+        assert_eq!(offset, 0x8);
+        let xfs = normalized.meta[meta_idx].module().unwrap();
+        assert_eq!(xfs.name, "xfs");
+        assert_eq!(xfs.build_id, None);
+
+        let (addr, meta_idx) = normalized.outputs[1];
+        assert_eq!(addr, 0xffffffff81000000);
+        assert!(normalized.meta[meta_idx].unknown().is_some());
+
+        let (offset, meta_idx) = normalized.outputs[2];
+        assert_eq!(offset, 0x100);
+        let ext4 = normalized.meta[meta_idx].module().unwrap();
+        assert_eq!(ext4.name, "ext4");
+        assert_eq!(ext4.build_id, Some(vec![0x12, 0x34]));
+
+        assert_eq!(normalized.outputs[3], (0x18, normalized.outputs[0].1));
+        // One past the end of the `ext4` module.
+        assert_eq!(
+            normalized.outputs[4],
+            (0xffffffffc0b5c000, normalized.outputs[1].1)
+        );
+        // Ahead of the `xfs` module's `.text` section.
+        assert_eq!(
+            normalized.outputs[5],
+            (0xffffffffc0c00004, normalized.outputs[1].1)
+        );
+
+        let normalized = normalize_kernel_addrs_impl(&fs, &addrs, false).unwrap();
+        let (_offset, meta_idx) = normalized.outputs[2];
+        assert_eq!(normalized.meta[meta_idx].module().unwrap().build_id, None);
+    }
+}
@@ -5,6 +5,28 @@ use std::path::PathBuf;
 type BuildId = Vec<u8>;
 
 
+/// An identifier of a module, independent of its object file format.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ModuleId {
+    /// The GNU build ID of an ELF file, as raw bytes.
+    Gnu(Vec<u8>),
+    /// The UUID of a Mach-O file, as stored in its `LC_UUID` load
+    /// command.
+    MachOUuid([u8; 16]),
+    /// The GUID and age of the PDB file referenced by a PE file.
+    ///
+    /// Together, these two values identify the PDB file on a symbol
+    /// server.
+    PeGuidAge {
+        /// The GUID of the PDB file.
+        guid: [u8; 16],
+        /// The age of the PDB file.
+        age: u32,
+    },
+}
+
+
 /// Meta information about an APK.
 ///
 /// This type is used in the [`UserMeta::Apk`] variant.
@@ -42,6 +64,10 @@ type BuildId = Vec<u8>;
 pub struct Apk {
     /// The canonical absolute path to the APK, including its name.
     pub path: PathBuf,
+    /// The path of the (uncompressed) ELF file inside the APK that
+    /// the normalized offset belongs to, relative to the root of the
+    /// archive (e.g., `lib/arm64-v8a/libfoo.so`), if known.
+    pub elf_path: Option<PathBuf>,
     /// The struct is non-exhaustive and open to extension.
     #[doc(hidden)]
     pub _non_exhaustive: (),
@@ -56,6 +82,73 @@ pub struct Elf {
     /// The canonical absolute path to the ELF file, including its name.
     pub path: PathBuf,
     /// The ELF file's build ID, if available.
+    ///
+    /// If enabled via
+    /// [`Builder::enable_build_id_fallback`][crate::normalize::Builder::enable_build_id_fallback],
+    /// this is a fallback identifier for files without build ID.
+    pub build_id: Option<BuildId>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+
+/// Meta information about a Mach-O file.
+///
+/// This type is used in the [`UserMeta::MachO`] variant.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MachO {
+    /// The canonical absolute path to the Mach-O file, including its
+    /// name.
+    pub path: PathBuf,
+    /// The Mach-O file's UUID, if available.
+    pub uuid: Option<[u8; 16]>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+
+/// Information about the PDB file referenced by a PE file, as stored
+/// in the CodeView record of the PE file's debug directory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PdbInfo {
+    /// The GUID of the PDB file.
+    pub guid: [u8; 16],
+    /// The age of the PDB file.
+    pub age: u32,
+    /// The path to the PDB file, as recorded by the linker.
+    pub path: PathBuf,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+
+/// Meta information about a PE file (Windows executable or DLL).
+///
+/// This type is used in the [`UserMeta::Pe`] variant.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pe {
+    /// The canonical absolute path to the PE file, including its name.
+    pub path: PathBuf,
+    /// Information about the PDB file referenced by the PE file, if
+    /// available.
+    pub pdb: Option<PdbInfo>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+
+/// Meta information about a kernel module.
+///
+/// This type is used in the [`KernelMeta::Module`] variant.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KernelModule {
+    /// The name of the module, as reported in `/proc/modules`.
+    pub name: String,
+    /// The module's build ID, if available.
     pub build_id: Option<BuildId>,
     /// The struct is non-exhaustive and open to extension.
     #[doc(hidden)]
@@ -83,6 +176,12 @@ impl From<Unknown> for UserMeta {
     }
 }
 
+impl From<Unknown> for KernelMeta {
+    fn from(unknown: Unknown) -> Self {
+        Self::Unknown(unknown)
+    }
+}
+
 
 /// Meta information for an address.
 #[derive(Clone, Debug, PartialEq)]
@@ -92,6 +191,16 @@ pub enum UserMeta {
     Apk(Apk),
     /// The address belongs to an ELF file.
     Elf(Elf),
+    /// The address belongs to a Mach-O file.
+    ///
+    /// Mach-O files are only recognized as such if the reading of build
+    /// IDs is enabled. Otherwise they are reported as [`UserMeta::Elf`].
+    MachO(MachO),
+    /// The address belongs to a PE file, e.g., one mapped by Wine.
+    ///
+    /// PE files are only recognized as such if the reading of build
+    /// IDs is enabled. Otherwise they are reported as [`UserMeta::Elf`].
+    Pe(Pe),
     /// The address' origin is unknown.
     Unknown(Unknown),
 }
@@ -113,6 +222,22 @@ impl UserMeta {
         }
     }
 
+    /// Retrieve the [`MachO`] of this enum, if this variant is active.
+    pub fn macho(&self) -> Option<&MachO> {
+        match self {
+            Self::MachO(macho) => Some(macho),
+            _ => None,
+        }
+    }
+
+    /// Retrieve the [`Pe`] of this enum, if this variant is active.
+    pub fn pe(&self) -> Option<&Pe> {
+        match self {
+            Self::Pe(pe) => Some(pe),
+            _ => None,
+        }
+    }
+
     /// Retrieve the [`Unknown`] of this enum, if this variant is active.
     pub fn unknown(&self) -> Option<&Unknown> {
         match self {
@@ -120,6 +245,62 @@ impl UserMeta {
             _ => None,
         }
     }
+
+    /// Retrieve the identifier of the module described by this meta
+    /// information, if available.
+    ///
+    /// This is the build ID for ELF files, the UUID for Mach-O files,
+    /// and the PDB GUID and age for PE files.
+    pub fn module_id(&self) -> Option<ModuleId> {
+        match self {
+            Self::Elf(elf) => elf.build_id.clone().map(ModuleId::Gnu),
+            Self::MachO(macho) => macho.uuid.map(ModuleId::MachOUuid),
+            Self::Pe(pe) => pe.pdb.as_ref().map(|pdb| ModuleId::PeGuidAge {
+                guid: pdb.guid,
+                age: pdb.age,
+            }),
+            Self::Apk(..) | Self::Unknown(..) => None,
+        }
+    }
+}
+
+
+/// Meta information for a kernel address.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum KernelMeta {
+    /// The address belongs to a kernel module.
+    Module(KernelModule),
+    /// The address does not belong to a kernel module.
+    Unknown(Unknown),
+}
+
+impl KernelMeta {
+    /// Retrieve the [`KernelModule`] of this enum, if this variant is
+    /// active.
+    pub fn module(&self) -> Option<&KernelModule> {
+        match self {
+            Self::Module(module) => Some(module),
+            _ => None,
+        }
+    }
+
+    /// Retrieve the [`Unknown`] of this enum, if this variant is active.
+    pub fn unknown(&self) -> Option<&Unknown> {
+        match self {
+            Self::Unknown(unknown) => Some(unknown),
+            _ => None,
+        }
+    }
+
+    /// Retrieve the identifier of the module described by this meta
+    /// information, if available.
+    pub fn module_id(&self) -> Option<ModuleId> {
+        match self {
+            Self::Module(module) => module.build_id.clone().map(ModuleId::Gnu),
+            Self::Unknown(..) => None,
+        }
+    }
 }
 
 
@@ -134,26 +315,89 @@ mod tests {
     fn user_addr_meta_accessors() {
         let meta = UserMeta::Apk(Apk {
             path: PathBuf::from("/tmp/archive.apk"),
+            elf_path: None,
             _non_exhaustive: (),
         });
         assert!(meta.apk().is_some());
         assert!(meta.elf().is_none());
+        assert!(meta.macho().is_none());
+        assert!(meta.pe().is_none());
         assert!(meta.unknown().is_none());
+        assert_eq!(meta.module_id(), None);
 
         let meta = UserMeta::Elf(Elf {
             path: PathBuf::from("/tmp/executable.bin"),
-            build_id: None,
+            build_id: Some(vec![0x12, 0x34]),
             _non_exhaustive: (),
         });
         assert!(meta.apk().is_none());
         assert!(meta.elf().is_some());
+        assert!(meta.macho().is_none());
+        assert!(meta.pe().is_none());
         assert!(meta.unknown().is_none());
+        assert_eq!(meta.module_id(), Some(ModuleId::Gnu(vec![0x12, 0x34])));
+
+        let meta = UserMeta::MachO(MachO {
+            path: PathBuf::from("/tmp/executable.dylib"),
+            uuid: Some([0x42; 16]),
+            _non_exhaustive: (),
+        });
+        assert!(meta.apk().is_none());
+        assert!(meta.elf().is_none());
+        assert!(meta.macho().is_some());
+        assert!(meta.pe().is_none());
+        assert!(meta.unknown().is_none());
+        assert_eq!(meta.module_id(), Some(ModuleId::MachOUuid([0x42; 16])));
+
+        let meta = UserMeta::Pe(Pe {
+            path: PathBuf::from("/tmp/library.dll"),
+            pdb: Some(PdbInfo {
+                guid: [0x42; 16],
+                age: 3,
+                path: PathBuf::from("library.pdb"),
+                _non_exhaustive: (),
+            }),
+            _non_exhaustive: (),
+        });
+        assert!(meta.apk().is_none());
+        assert!(meta.elf().is_none());
+        assert!(meta.macho().is_none());
+        assert!(meta.pe().is_some());
+        assert!(meta.unknown().is_none());
+        assert_eq!(
+            meta.module_id(),
+            Some(ModuleId::PeGuidAge {
+                guid: [0x42; 16],
+                age: 3
+            })
+        );
 
         let meta = UserMeta::Unknown(Unknown {
             _non_exhaustive: (),
         });
         assert!(meta.apk().is_none());
         assert!(meta.elf().is_none());
+        assert!(meta.macho().is_none());
+        assert!(meta.pe().is_none());
+        assert!(meta.unknown().is_some());
+    }
+
+    /// Check that we can access individual variants of a
+    /// [`KernelMeta`] via the accessor functions.
+    #[test]
+    fn kernel_addr_meta_accessors() {
+        let meta = KernelMeta::Module(KernelModule {
+            name: "ext4".to_string(),
+            build_id: Some(vec![0x12, 0x34]),
+            _non_exhaustive: (),
+        });
+        assert!(meta.module().is_some());
+        assert!(meta.unknown().is_none());
+        assert_eq!(meta.module_id(), Some(ModuleId::Gnu(vec![0x12, 0x34])));
+
+        let meta = KernelMeta::from(Unknown::default());
+        assert!(meta.module().is_none());
         assert!(meta.unknown().is_some());
+        assert_eq!(meta.module_id(), None);
     }
 }
@@ -39,12 +39,24 @@
 //! ```
 
 pub(crate) mod buildid;
+#[cfg(feature = "kernel")]
+mod kernel;
 mod meta;
 mod normalizer;
+pub(crate) mod notes;
+mod tracker;
 mod user;
 
+#[cfg(feature = "kernel")]
+pub use kernel::KernelOutput;
 pub use meta::Apk;
 pub use meta::Elf;
+pub use meta::KernelMeta;
+pub use meta::KernelModule;
+pub use meta::MachO;
+pub use meta::ModuleId;
+pub use meta::PdbInfo;
+pub use meta::Pe;
 pub use meta::Unknown;
 pub use meta::UserMeta;
 pub use normalizer::Builder;
@@ -53,7 +65,11 @@ pub use normalizer::Normalizer;
 // will not resolve links. See https://github.com/rust-lang/rust/issues/116854
 #[doc(hidden)]
 pub use normalizer::Output;
+pub use normalizer::TaggedOutput;
+pub use tracker::AddressSpaceTracker;
+pub use user::ModuleSummary;
 pub use user::UserOutput;
+pub use user::UserOutputIter;
 
 pub(crate) use user::normalize_sorted_user_addrs_with_entries;
 pub(crate) use user::Handler;
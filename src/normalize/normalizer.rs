@@ -1,10 +1,26 @@
+use std::sync::Arc;
+
 use crate::util;
+use crate::vfs::FileAccess;
+use crate::vfs::StdFileAccess;
 use crate::Addr;
+#[cfg(feature = "process")]
 use crate::Pid;
 use crate::Result;
 
+#[cfg(feature = "kernel")]
+use super::kernel::normalize_kernel_addrs_impl;
+#[cfg(feature = "kernel")]
+use super::kernel::KernelOutput;
+use super::tracker::AddressSpaceTracker;
+#[cfg(feature = "process")]
 use super::user::normalize_user_addrs_sorted_impl;
+use super::user::normalize_user_addrs_sorted_with_entries_impl;
 use super::user::UserOutput;
+#[cfg(feature = "process")]
+use super::user::UserOutputIter;
+#[cfg(feature = "process")]
+use super::UserMeta;
 
 
 /// A type capturing normalized outputs along with captured meta data.
@@ -31,6 +47,42 @@ pub struct Output<M> {
 }
 
 
+/// A type capturing normalized outputs, each carrying a caller provided
+/// value, along with captured meta data.
+///
+/// This type is the result of "tagged" normalization, e.g., via
+/// [`Normalizer::normalize_user_addrs_tagged`]. Other than the
+/// additional value in each output, it is equivalent to [`Output`].
+#[derive(Clone, Debug)]
+pub struct TaggedOutput<M, T> {
+    /// Outputs along with an index into `meta` for retrieval of the
+    /// corresponding meta information and the value provided for the
+    /// input.
+    ///
+    /// See [`Output::outputs`] for the semantics of the first two
+    /// members.
+    pub outputs: Vec<(u64, usize, T)>,
+    /// Meta information about the normalized outputs.
+    pub meta: Vec<M>,
+}
+
+impl<M, T> TaggedOutput<M, T> {
+    /// Combine an [`Output`] with the values provided for its inputs.
+    fn new<I>(output: Output<M>, tags: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let Output { outputs, meta } = output;
+        let outputs = outputs
+            .into_iter()
+            .zip(tags)
+            .map(|((output, meta_idx), tag)| (output, meta_idx, tag))
+            .collect();
+        Self { outputs, meta }
+    }
+}
+
+
 /// A builder for configurable construction of [`Normalizer`] objects.
 ///
 /// By default all features are enabled.
@@ -39,6 +91,11 @@ pub struct Builder {
     /// Whether to read and report build IDs as part of the
     /// normalization process.
     build_ids: bool,
+    /// Whether to compute a fallback identifier for binaries without
+    /// build ID.
+    build_id_fallback: bool,
+    /// The provider used for accessing files.
+    fs: Arc<dyn FileAccess>,
 }
 
 impl Builder {
@@ -48,17 +105,57 @@ impl Builder {
         self
     }
 
+    /// Enable/disable the computation of a fallback identifier for ELF
+    /// binaries that lack a GNU build ID.
+    ///
+    /// If enabled, binaries without build ID are reported with a 16
+    /// byte identifier derived from the contents of their `.text`
+    /// section instead, using the same scheme as Breakpad. Such
+    /// identifiers can be used for matching up binaries with
+    /// separately stored artifacts, but they are not guaranteed to be
+    /// unique in the way that proper build IDs are.
+    ///
+    /// This setting only has an effect if the reading of build IDs is
+    /// enabled. It is disabled by default.
+    pub fn enable_build_id_fallback(mut self, enable: bool) -> Builder {
+        self.build_id_fallback = enable;
+        self
+    }
+
+    /// Set the [`FileAccess`] implementation used for accessing files.
+    ///
+    /// By default, the local file system is used.
+    pub fn set_file_access<F>(mut self, fs: F) -> Builder
+    where
+        F: FileAccess + 'static,
+    {
+        self.fs = Arc::new(fs);
+        self
+    }
+
     /// Create the [`Normalizer`] object.
     pub fn build(self) -> Normalizer {
-        let Builder { build_ids } = self;
-
-        Normalizer { build_ids }
+        let Builder {
+            build_ids,
+            build_id_fallback,
+            fs,
+        } = self;
+
+        Normalizer {
+            build_ids,
+            build_id_fallback,
+            fs,
+        }
     }
 }
 
 impl Default for Builder {
     fn default() -> Self {
-        Self { build_ids: true }
+        Self {
+            build_ids: true,
+            build_id_fallback: false,
+            fs: Arc::new(StdFileAccess::default()),
+        }
     }
 }
 
@@ -71,11 +168,26 @@ impl Default for Builder {
 /// things) and converting them to "normalized" virtual addresses as
 /// they are present in, say, an ELF binary or a DWARF debug info file,
 /// and one would be able to see them using tools such as readelf(1).
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Normalizer {
     /// Flag indicating whether or not to read build IDs as part of the
     /// normalization process.
     build_ids: bool,
+    /// Flag indicating whether or not to compute a fallback identifier
+    /// for binaries without build ID.
+    build_id_fallback: bool,
+    /// The provider used for accessing files.
+    fs: Arc<dyn FileAccess>,
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self {
+            build_ids: false,
+            build_id_fallback: false,
+            fs: Arc::new(StdFileAccess::default()),
+        }
+    }
 }
 
 impl Normalizer {
@@ -112,9 +224,16 @@ impl Normalizer {
     ///
     /// Normalized addresses are reported in the exact same order in which the
     /// non-normalized ones were provided.
+    #[cfg(feature = "process")]
     #[cfg_attr(feature = "tracing", crate::log::instrument(skip(self)))]
     pub fn normalize_user_addrs_sorted(&self, pid: Pid, addrs: &[Addr]) -> Result<UserOutput> {
-        normalize_user_addrs_sorted_impl(addrs.iter().copied(), pid, self.build_ids)
+        normalize_user_addrs_sorted_impl(
+            &*self.fs,
+            addrs.iter().copied(),
+            pid,
+            self.build_ids,
+            self.build_id_fallback,
+        )
     }
 
 
@@ -126,14 +245,168 @@ impl Normalizer {
     /// identically. If you do happen to know that `addrs` is sorted, using
     /// [`Normalizer::normalize_user_addrs_sorted`] instead will result in
     /// slightly faster normalization.
+    #[cfg(feature = "process")]
     #[cfg_attr(feature = "tracing", crate::log::instrument(skip(self)))]
     pub fn normalize_user_addrs(&self, pid: Pid, addrs: &[Addr]) -> Result<UserOutput> {
         util::with_ordered_elems(
             addrs,
             |normalized: &mut UserOutput| normalized.outputs.as_mut_slice(),
-            |sorted_addrs| normalize_user_addrs_sorted_impl(sorted_addrs, pid, self.build_ids),
+            |sorted_addrs| {
+                normalize_user_addrs_sorted_impl(
+                    &*self.fs,
+                    sorted_addrs,
+                    pid,
+                    self.build_ids,
+                    self.build_id_fallback,
+                )
+            },
+        )
+    }
+
+    /// Lazily normalize addresses belonging to a process.
+    ///
+    /// This method returns an iterator that normalizes one address at a
+    /// time, instead of materializing all outputs at once. It is meant
+    /// for batch jobs processing a large number of addresses. Per
+    /// component summaries are available once iteration is complete,
+    /// via [`UserOutputIter::finish`].
+    ///
+    /// `addrs` have to be sorted in ascending order. An error is
+    /// reported by the iterator for the first address violating this
+    /// constraint, after which it is exhausted.
+    ///
+    /// ```no_run
+    /// use blazesym::normalize::Normalizer;
+    /// use blazesym::Addr;
+    /// use blazesym::Pid;
+    ///
+    /// # let addrs = Vec::<Addr>::new();
+    /// let normalizer = Normalizer::new();
+    /// let mut iter = normalizer
+    ///     .normalize_user_addrs_iter(Pid::Slf, addrs)
+    ///     .unwrap();
+    /// for result in &mut iter {
+    ///     let (output, meta_idx) = result.unwrap();
+    ///     // Process the output right away...
+    /// }
+    /// for summary in iter.finish() {
+    ///     println!("{} addresses in {:?}", summary.addr_count, summary.meta);
+    /// }
+    /// ```
+    #[cfg(feature = "process")]
+    #[cfg_attr(feature = "tracing", crate::log::instrument(skip(self, addrs)))]
+    pub fn normalize_user_addrs_iter<A>(
+        &self,
+        pid: Pid,
+        addrs: A,
+    ) -> Result<UserOutputIter<'_, A::IntoIter>>
+    where
+        A: IntoIterator<Item = Addr>,
+    {
+        UserOutputIter::new(
+            &*self.fs,
+            addrs.into_iter(),
+            pid,
+            self.build_ids,
+            self.build_id_fallback,
+        )
+    }
+
+    /// Normalize addresses belonging to a process, each carrying a
+    /// caller provided value.
+    ///
+    /// This method behaves like [`Normalizer::normalize_user_addrs`],
+    /// but passes each input's value through to the corresponding
+    /// output. It allows for correlating normalization results with,
+    /// say, the samples they originate from without maintaining
+    /// parallel arrays.
+    #[cfg(feature = "process")]
+    #[cfg_attr(feature = "tracing", crate::log::instrument(skip_all, fields(pid = ?pid)))]
+    pub fn normalize_user_addrs_tagged<I, T>(
+        &self,
+        pid: Pid,
+        addrs: I,
+    ) -> Result<TaggedOutput<UserMeta, T>>
+    where
+        I: IntoIterator<Item = (Addr, T)>,
+    {
+        let (addrs, tags) = addrs.into_iter().unzip::<_, _, Vec<_>, Vec<_>>();
+        let output = self.normalize_user_addrs(pid, &addrs)?;
+        debug_assert_eq!(output.outputs.len(), tags.len());
+        Ok(TaggedOutput::new(output, tags))
+    }
+
+    /// Normalize addresses belonging to a process whose address space
+    /// is tracked by the provided [`AddressSpaceTracker`].
+    ///
+    /// This method behaves like [`Normalizer::normalize_user_addrs_sorted`],
+    /// except that the mappings of the process are not read from its
+    /// `/proc/<pid>/maps` file, but retrieved from `tracker`.
+    #[cfg_attr(feature = "tracing", crate::log::instrument(skip(self, tracker)))]
+    pub fn normalize_tracked_addrs_sorted(
+        &self,
+        tracker: &AddressSpaceTracker,
+        addrs: &[Addr],
+    ) -> Result<UserOutput> {
+        normalize_user_addrs_sorted_with_entries_impl(
+            &*self.fs,
+            addrs.iter().copied(),
+            tracker.entries(),
+            self.build_ids,
+            self.build_id_fallback,
+        )
+    }
+
+    /// Normalize addresses belonging to a process whose address space
+    /// is tracked by the provided [`AddressSpaceTracker`].
+    ///
+    /// Contrary to [`Normalizer::normalize_tracked_addrs_sorted`], the
+    /// provided `addrs` array does not have to be sorted, but otherwise
+    /// the functions behave identically.
+    #[cfg_attr(feature = "tracing", crate::log::instrument(skip(self, tracker)))]
+    pub fn normalize_tracked_addrs(
+        &self,
+        tracker: &AddressSpaceTracker,
+        addrs: &[Addr],
+    ) -> Result<UserOutput> {
+        util::with_ordered_elems(
+            addrs,
+            |normalized: &mut UserOutput| normalized.outputs.as_mut_slice(),
+            |sorted_addrs| {
+                normalize_user_addrs_sorted_with_entries_impl(
+                    &*self.fs,
+                    sorted_addrs,
+                    tracker.entries(),
+                    self.build_ids,
+                    self.build_id_fallback,
+                )
+            },
         )
     }
+
+    /// Normalize kernel addresses.
+    ///
+    /// Addresses belonging to a loaded kernel module are normalized into
+    /// an offset relative to the module's base address and reported
+    /// along with the module's name and (if enabled) build ID, as
+    /// [`KernelModule`][crate::normalize::KernelModule] meta data. This
+    /// information is sufficient for symbolizing them using the
+    /// module's `.ko` file on a different system.
+    ///
+    /// Addresses not belonging to any kernel module, e.g., because they
+    /// reside in the kernel image itself, are not normalized. They are
+    /// reported as [`Unknown`][crate::normalize::Unknown] meta entries.
+    ///
+    /// Module load addresses are read from `/proc/modules` and the
+    /// addresses of their `.text` sections from `/sys/module`, both of
+    /// which typically require elevated privileges. `addrs` do not have
+    /// to be sorted and normalized addresses are reported in the order
+    /// in which they were provided.
+    #[cfg(feature = "kernel")]
+    #[cfg_attr(feature = "tracing", crate::log::instrument(skip(self)))]
+    pub fn normalize_kernel_addrs(&self, addrs: &[Addr]) -> Result<KernelOutput> {
+        normalize_kernel_addrs_impl(&*self.fs, addrs, self.build_ids)
+    }
 }
 
 
@@ -143,6 +416,7 @@ mod tests {
 
     use std::mem::transmute;
     use std::path::Path;
+    use std::path::PathBuf;
 
     use test_log::test;
 
@@ -153,6 +427,7 @@ mod tests {
     use crate::normalize::buildid::read_elf_build_id;
     use crate::normalize::Apk;
     use crate::normalize::Elf;
+    use crate::normalize::ModuleId;
     use crate::normalize::Unknown;
     use crate::normalize::UserMeta;
     use crate::symbolize;
@@ -284,6 +559,160 @@ mod tests {
         assert_eq!(meta, &UserMeta::Elf(expected_elf));
     }
 
+    /// Check that we report Mach-O modules along with their UUID.
+    #[test]
+    fn user_address_normalization_macho() {
+        let uuid = [0x42; 16];
+        let mut data = Vec::new();
+        // `mach_header_64` with a single load command.
+        let () = data.extend_from_slice(&0xfeedfacfu32.to_ne_bytes());
+        let () = data.extend_from_slice(&[0; 12]);
+        let () = data.extend_from_slice(&1u32.to_ne_bytes());
+        let () = data.extend_from_slice(&24u32.to_ne_bytes());
+        let () = data.extend_from_slice(&[0; 8]);
+        // `LC_UUID`
+        let () = data.extend_from_slice(&0x1bu32.to_ne_bytes());
+        let () = data.extend_from_slice(&24u32.to_ne_bytes());
+        let () = data.extend_from_slice(&uuid);
+        let () = data.resize(4096, 0);
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let () = std::io::Write::write_all(&mut file, &data).unwrap();
+        let mmap = Mmap::builder().open(file.path()).unwrap();
+        let addr = unsafe { mmap.as_ptr().add(0x10) } as Addr;
+
+        let normalizer = Normalizer::new();
+        let normalized = normalizer
+            .normalize_user_addrs_sorted(Pid::Slf, [addr].as_slice())
+            .unwrap();
+        assert_eq!(normalized.outputs.len(), 1);
+        let output = normalized.outputs[0];
+        assert_eq!(output.0, 0x10);
+        let meta = &normalized.meta[output.1];
+        let macho = meta.macho().unwrap();
+        assert_eq!(macho.path, file.path());
+        assert_eq!(macho.uuid, Some(uuid));
+        assert_eq!(meta.module_id(), Some(ModuleId::MachOUuid(uuid)));
+    }
+
+    /// Check that iterator based normalization produces the same
+    /// results as the batch variant.
+    #[test]
+    fn user_address_normalization_iter() {
+        let mut addrs = [
+            libc::__errno_location as *const () as Addr,
+            libc::dlopen as *const () as Addr,
+            libc::fopen as *const () as Addr,
+            0x0,
+            user_address_normalization_iter as *const () as Addr,
+        ];
+        let () = addrs.sort();
+
+        let normalizer = Normalizer::new();
+        let expected = normalizer
+            .normalize_user_addrs_sorted(Pid::Slf, addrs.as_slice())
+            .unwrap();
+
+        let mut iter = normalizer
+            .normalize_user_addrs_iter(Pid::Slf, addrs)
+            .unwrap();
+        let outputs = (&mut iter).collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(outputs, expected.outputs);
+        assert_eq!(iter.meta(), expected.meta.as_slice());
+
+        let summaries = iter.finish();
+        assert_eq!(summaries.len(), expected.meta.len());
+        for (idx, summary) in summaries.iter().enumerate() {
+            assert_eq!(summary.meta, expected.meta[idx]);
+            let count = outputs
+                .iter()
+                .filter(|(_, meta_idx)| *meta_idx == idx)
+                .count();
+            assert_eq!(summary.addr_count, count);
+        }
+        assert_eq!(
+            summaries
+                .iter()
+                .map(|summary| summary.addr_count)
+                .sum::<usize>(),
+            addrs.len()
+        );
+
+        // Unsorted input is reported as an error, after which the
+        // iterator is exhausted.
+        let mut iter = normalizer
+            .normalize_user_addrs_iter(Pid::Slf, [addrs[1], addrs[0], addrs[2]])
+            .unwrap();
+        assert!(iter.next().unwrap().is_ok());
+        let err = iter.next().unwrap().unwrap_err();
+        assert!(err.to_string().contains("are not sorted"), "{err}");
+        assert!(iter.next().is_none());
+    }
+
+    /// Check that caller provided values are passed through tagged
+    /// normalization.
+    #[test]
+    fn user_address_normalization_tagged() {
+        let addrs = [
+            (libc::fopen as *const () as Addr, "fopen"),
+            (0x0, "null"),
+            (libc::__errno_location as *const () as Addr, "errno"),
+        ];
+
+        let normalizer = Normalizer::new();
+        let tagged = normalizer
+            .normalize_user_addrs_tagged(Pid::Slf, addrs)
+            .unwrap();
+        let untagged = normalizer
+            .normalize_user_addrs(Pid::Slf, &addrs.map(|(addr, _tag)| addr))
+            .unwrap();
+        assert_eq!(tagged.meta, untagged.meta);
+        assert_eq!(tagged.outputs.len(), addrs.len());
+
+        for ((output, meta_idx, tag), (expected, (_addr, expected_tag))) in tagged
+            .outputs
+            .iter()
+            .zip(untagged.outputs.iter().zip(addrs))
+        {
+            assert_eq!((*output, *meta_idx), *expected);
+            assert_eq!(*tag, expected_tag);
+        }
+    }
+
+    /// Check that we can normalize addresses using the mappings
+    /// reported to an [`AddressSpaceTracker`].
+    #[test]
+    fn tracked_address_normalization() {
+        let so_path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("libtest-so.so");
+
+        let mut tracker = AddressSpaceTracker::new(Pid::Slf);
+        let () = tracker.add_mapping(0x10000..0x20000, 0x1000, &so_path);
+        let () = tracker.add_mapping(0x20000..0x30000, 0x0, "/does/not/exist");
+        let () = tracker.remove_mapping(0x20000..0x30000);
+
+        let normalizer = Normalizer::new();
+        let normalized = normalizer
+            .normalize_tracked_addrs(&tracker, [0x20100, 0x10100].as_slice())
+            .unwrap();
+        assert_eq!(normalized.outputs.len(), 2);
+        assert_eq!(normalized.meta.len(), 2);
+
+        let (offset, meta_idx) = normalized.outputs[1];
+        assert_eq!(offset, 0x1100);
+        let expected_elf = Elf {
+            build_id: Some(read_elf_build_id(&so_path).unwrap().unwrap()),
+            path: so_path,
+            _non_exhaustive: (),
+        };
+        assert_eq!(normalized.meta[meta_idx], UserMeta::Elf(expected_elf));
+
+        let (addr, meta_idx) = normalized.outputs[0];
+        assert_eq!(addr, 0x20100);
+        assert_eq!(normalized.meta[meta_idx], Unknown::default().into());
+    }
+
     /// Check that we can normalize addresses in our own shared object inside a
     /// zip archive.
     #[test]
@@ -341,6 +770,7 @@ mod tests {
             let meta = &normalized.meta[output.1];
             let expected = Apk {
                 path: test_zip.clone(),
+                elf_path: Some(PathBuf::from(so_name)),
                 _non_exhaustive: (),
             };
             assert_eq!(meta, &UserMeta::Apk(expected));
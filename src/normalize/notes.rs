@@ -0,0 +1,515 @@
+use std::fs::File;
+use std::mem::size_of;
+use std::path::Path;
+use std::str;
+
+use crate::elf;
+use crate::elf::types::Elf64_Nhdr;
+use crate::elf::ElfParser;
+use crate::log::debug;
+use crate::mmap::Mmap;
+use crate::util::ReadRaw as _;
+use crate::vfs::FileAccess as _;
+use crate::vfs::FileData;
+use crate::vfs::StdFileAccess;
+use crate::IntoError as _;
+use crate::Result;
+
+use super::buildid::BuildId;
+use super::buildid::BuildIdKind;
+
+
+/// The note type of the ABI tag in a "GNU" note.
+const NT_GNU_ABI_TAG: u32 = 1;
+/// The note type of a GNU property list in a "GNU" note.
+const NT_GNU_PROPERTY_TYPE_0: u32 = 5;
+/// The note type of package metadata in an "FDO" note.
+///
+/// See <https://systemd.io/ELF_PACKAGE_METADATA/>.
+const NT_FDO_PACKAGING_METADATA: u32 = 0xcafe1a7e;
+
+/// The GNU property describing AArch64 hardware features in use.
+const GNU_PROPERTY_AARCH64_FEATURE_1_AND: u32 = 0xc0000000;
+/// The GNU property describing x86 hardware features in use.
+const GNU_PROPERTY_X86_FEATURE_1_AND: u32 = 0xc0000002;
+
+const GNU_PROPERTY_AARCH64_FEATURE_1_BTI: u32 = 1 << 0;
+const GNU_PROPERTY_AARCH64_FEATURE_1_PAC: u32 = 1 << 1;
+const GNU_PROPERTY_X86_FEATURE_1_IBT: u32 = 1 << 0;
+const GNU_PROPERTY_X86_FEATURE_1_SHSTK: u32 = 1 << 1;
+
+
+/// The operating system an ELF file targets, as reported by its ABI
+/// tag.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum AbiOs {
+    /// Linux.
+    Linux,
+    /// GNU Hurd.
+    Hurd,
+    /// Solaris.
+    Solaris,
+    /// FreeBSD.
+    FreeBsd,
+    /// An operating system not known to the library, identified by its
+    /// raw value.
+    Unknown(u32),
+}
+
+impl From<u32> for AbiOs {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => Self::Linux,
+            1 => Self::Hurd,
+            2 => Self::Solaris,
+            3 => Self::FreeBsd,
+            _ => Self::Unknown(value),
+        }
+    }
+}
+
+
+/// The contents of an ELF file's `NT_GNU_ABI_TAG` note.
+///
+/// The tag describes the operating system and the minimum version of
+/// its ABI that the file requires.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct AbiTag {
+    /// The operating system targeted.
+    pub os: AbiOs,
+    /// The minimum required ABI version, as `(major, minor,
+    /// subminor)` triple.
+    pub version: (u32, u32, u32),
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+
+/// Hardware security features an ELF file is marked as compatible
+/// with, as reported by its `NT_GNU_PROPERTY_TYPE_0` note.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct GnuProperty {
+    /// Whether the file is compatible with x86 Indirect Branch Tracking
+    /// (IBT).
+    pub x86_ibt: bool,
+    /// Whether the file is compatible with x86 Shadow Stacks (SHSTK).
+    pub x86_shstk: bool,
+    /// Whether the file is compatible with AArch64 Branch Target
+    /// Identification (BTI).
+    pub aarch64_bti: bool,
+    /// Whether the file is compatible with AArch64 Pointer
+    /// Authentication (PAC).
+    pub aarch64_pac: bool,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+
+/// Package metadata as embedded by distributions in an ELF file's
+/// `.note.package` section.
+///
+/// See <https://systemd.io/ELF_PACKAGE_METADATA/> for details on the
+/// format. Commonly used fields are extracted, while the full JSON
+/// document is available via [`json`][PackageMetadata::json].
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct PackageMetadata {
+    /// The type of the package (e.g., `rpm` or `deb`).
+    pub ty: Option<String>,
+    /// The name of the package.
+    pub name: Option<String>,
+    /// The version of the package.
+    pub version: Option<String>,
+    /// The architecture the package was built for.
+    pub architecture: Option<String>,
+    /// The distribution the package was built for (e.g., `fedora`).
+    pub os: Option<String>,
+    /// The version of the distribution.
+    pub os_version: Option<String>,
+    /// The raw JSON document containing the metadata.
+    pub json: String,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl PackageMetadata {
+    fn from_json(json: &str) -> Self {
+        let mut metadata = Self {
+            json: json.to_string(),
+            ..Default::default()
+        };
+
+        if let Some(fields) = parse_flat_json(json) {
+            for (key, value) in fields {
+                let field = match key.as_str() {
+                    "type" => &mut metadata.ty,
+                    "name" => &mut metadata.name,
+                    "version" => &mut metadata.version,
+                    "architecture" => &mut metadata.architecture,
+                    "os" => &mut metadata.os,
+                    "osVersion" => &mut metadata.os_version,
+                    _ => continue,
+                };
+                *field = Some(value);
+            }
+        } else {
+            debug!("failed to parse package metadata note: {json}");
+        }
+        metadata
+    }
+}
+
+
+/// Parse a JSON string literal, with `s` pointing past the opening
+/// quote.
+fn parse_json_str(s: &mut str::Chars<'_>) -> Option<String> {
+    let mut string = String::new();
+    loop {
+        match s.next()? {
+            '"' => break Some(string),
+            '\\' => {
+                let c = match s.next()? {
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'u' => {
+                        let hex = s.by_ref().take(4).collect::<String>();
+                        let code = u32::from_str_radix(&hex, 16).ok()?;
+                        // Surrogate pairs are not supported and replaced.
+                        char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                    }
+                    c => c,
+                };
+                let () = string.push(c);
+            }
+            c => string.push(c),
+        }
+    }
+}
+
+/// Parse a JSON document consisting of a single object without nested
+/// objects or arrays, retrieving all members with string values.
+///
+/// That is the format the package metadata note is required to adhere
+/// to.
+fn parse_flat_json(json: &str) -> Option<Vec<(String, String)>> {
+    let mut s = json.trim().strip_prefix('{')?.chars();
+    let mut fields = Vec::new();
+
+    loop {
+        let key = match s.find(|c: &char| !c.is_whitespace())? {
+            '"' => parse_json_str(&mut s)?,
+            '}' if fields.is_empty() => break,
+            _ => return None,
+        };
+        if s.find(|c: &char| !c.is_whitespace())? != ':' {
+            return None
+        }
+
+        match s.find(|c: &char| !c.is_whitespace())? {
+            '"' => {
+                let value = parse_json_str(&mut s)?;
+                let () = fields.push((key, value));
+                match s.find(|c: &char| !c.is_whitespace())? {
+                    ',' => continue,
+                    '}' => break,
+                    _ => return None,
+                }
+            }
+            '{' | '[' => return None,
+            _ => {
+                // Some other scalar value; skip it.
+                match s.find(|c| *c == ',' || *c == '}')? {
+                    ',' => continue,
+                    _ => break,
+                }
+            }
+        }
+    }
+    Some(fields)
+}
+
+
+/// Information contained in an ELF file's notes.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ElfNotes {
+    /// The file's GNU build ID, if any.
+    pub build_id: Option<BuildId>,
+    /// The file's ABI tag, if any.
+    pub abi_tag: Option<AbiTag>,
+    /// The hardware features the file is marked as compatible with, if
+    /// a GNU property note is present.
+    pub gnu_property: Option<GnuProperty>,
+    /// Package metadata, if any.
+    pub package: Option<PackageMetadata>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl ElfNotes {
+    fn from_parser(parser: &ElfParser) -> Result<Self> {
+        let mut notes = Self::default();
+        let shdrs = parser.section_headers()?;
+        for (idx, shdr) in shdrs.iter().enumerate() {
+            if shdr.sh_type == elf::types::SHT_NOTE {
+                let data = parser.section_data(idx)?;
+                // Notes are padded to four bytes, unless the section
+                // requires eight byte alignment.
+                let align = if shdr.sh_addralign == 8 { 8 } else { 4 };
+                let () = notes.parse_section(data, align)?;
+            }
+        }
+        Ok(notes)
+    }
+
+    /// Parse the notes contained in a single note section.
+    fn parse_section(&mut self, data: &[u8], align: usize) -> Result<()> {
+        let padded = |offset: usize| (offset + align - 1) & !(align - 1);
+        let mut offset = 0;
+
+        // Note that name and descriptor are aligned relative to the
+        // start of the section, not to their respective lengths.
+        while offset < data.len() {
+            let mut bytes = &data[offset..];
+            let header = bytes
+                .read_pod::<Elf64_Nhdr>()
+                .ok_or_invalid_data(|| "failed to read ELF note header")?;
+            let name = bytes
+                .read_slice(header.n_namesz as _)
+                .ok_or_invalid_data(|| "failed to read ELF note name")?;
+            let name = name.strip_suffix(b"\0").unwrap_or(name);
+            let desc_offset = padded(offset + size_of::<Elf64_Nhdr>() + header.n_namesz as usize);
+            let desc = data
+                .get(desc_offset..)
+                .and_then(|mut bytes| bytes.read_slice(header.n_descsz as _))
+                .ok_or_invalid_data(|| "failed to read ELF note descriptor")?;
+            offset = padded(desc_offset + header.n_descsz as usize);
+
+            match (name, header.n_type) {
+                (b"GNU", elf::types::NT_GNU_BUILD_ID) if self.build_id.is_none() => {
+                    self.build_id = Some(BuildId {
+                        kind: BuildIdKind::Gnu,
+                        id: desc.to_vec(),
+                        _non_exhaustive: (),
+                    });
+                }
+                (b"GNU", NT_GNU_ABI_TAG) => {
+                    let mut desc = desc;
+                    let mut read = || {
+                        desc.read_u32()
+                            .ok_or_invalid_data(|| "failed to read ELF ABI tag")
+                    };
+                    self.abi_tag = Some(AbiTag {
+                        os: AbiOs::from(read()?),
+                        version: (read()?, read()?, read()?),
+                        _non_exhaustive: (),
+                    });
+                }
+                (b"GNU", NT_GNU_PROPERTY_TYPE_0) => {
+                    let property = self.gnu_property.get_or_insert_with(GnuProperty::default);
+                    let () = parse_gnu_properties(desc, property)?;
+                }
+                (b"FDO", NT_FDO_PACKAGING_METADATA) => {
+                    let json = desc.strip_suffix(b"\0").unwrap_or(desc);
+                    let json = str::from_utf8(json)
+                        .ok()
+                        .ok_or_invalid_data(|| "package metadata note is not valid UTF-8")?;
+                    self.package = Some(PackageMetadata::from_json(json));
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the notes of the ELF file at `path`.
+    pub fn from_elf_path<P>(path: &P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let data = StdFileAccess::default().open(path.as_ref())?;
+        let parser = ElfParser::from_mmap(data.mmap().clone());
+        Self::from_parser(&parser)
+    }
+
+    /// Read the notes of the provided open ELF file.
+    pub fn from_elf_file(file: &File) -> Result<Self> {
+        let data = FileData::from_file(file)?;
+        let parser = ElfParser::from_mmap(data.mmap().clone());
+        Self::from_parser(&parser)
+    }
+
+    /// Read the notes of the ELF file contained in `data`.
+    pub fn from_elf_data(data: &[u8]) -> Result<Self> {
+        let parser = ElfParser::from_mmap(Mmap::from_vec(data.to_vec()));
+        Self::from_parser(&parser)
+    }
+}
+
+
+/// Parse the property array of an `NT_GNU_PROPERTY_TYPE_0` note.
+///
+/// Property entries are padded to eight bytes in 64 bit ELF files.
+fn parse_gnu_properties(mut desc: &[u8], property: &mut GnuProperty) -> Result<()> {
+    while !desc.is_empty() {
+        let ty = desc
+            .read_u32()
+            .ok_or_invalid_data(|| "failed to read GNU property type")?;
+        let size = desc
+            .read_u32()
+            .ok_or_invalid_data(|| "failed to read GNU property size")?;
+        let mut data = desc
+            .read_slice(size as usize)
+            .ok_or_invalid_data(|| "failed to read GNU property data")?;
+        let padding = (8 - size as usize % 8) % 8;
+        let _padding = desc.read_slice(padding.min(desc.len()));
+
+        match ty {
+            GNU_PROPERTY_X86_FEATURE_1_AND => {
+                let features = data
+                    .read_u32()
+                    .ok_or_invalid_data(|| "failed to read x86 feature property")?;
+                property.x86_ibt = features & GNU_PROPERTY_X86_FEATURE_1_IBT != 0;
+                property.x86_shstk = features & GNU_PROPERTY_X86_FEATURE_1_SHSTK != 0;
+            }
+            GNU_PROPERTY_AARCH64_FEATURE_1_AND => {
+                let features = data
+                    .read_u32()
+                    .ok_or_invalid_data(|| "failed to read AArch64 feature property")?;
+                property.aarch64_bti = features & GNU_PROPERTY_AARCH64_FEATURE_1_BTI != 0;
+                property.aarch64_pac = features & GNU_PROPERTY_AARCH64_FEATURE_1_PAC != 0;
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::normalize::buildid::read_elf_build_id;
+
+
+    /// Create the binary representation of an ELF note.
+    fn note(name: &[u8], ty: u32, desc: &[u8], align: usize) -> Vec<u8> {
+        let padded = |len: usize| (len + align - 1) & !(align - 1);
+        let mut note = Vec::new();
+        let () = note.extend_from_slice(&(name.len() as u32 + 1).to_ne_bytes());
+        let () = note.extend_from_slice(&(desc.len() as u32).to_ne_bytes());
+        let () = note.extend_from_slice(&ty.to_ne_bytes());
+        let () = note.extend_from_slice(name);
+        let () = note.push(b'\0');
+        let () = note.resize(padded(note.len()), 0);
+        let () = note.extend_from_slice(desc);
+        let () = note.resize(padded(note.len()), 0);
+        note
+    }
+
+
+    /// Exercise the `Debug` representation of various types.
+    #[test]
+    fn debug_repr() {
+        let notes = ElfNotes::default();
+        assert_ne!(format!("{notes:?}"), "");
+    }
+
+    /// Check that we can parse the notes of some of our test binaries.
+    #[test]
+    fn notes_reading() {
+        let elf = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-no-debug.bin");
+        let notes = ElfNotes::from_elf_path(&elf).unwrap();
+        assert_eq!(notes.build_id, None);
+        assert_eq!(notes.package, None);
+        let abi_tag = notes.abi_tag.unwrap();
+        assert_eq!(abi_tag.os, AbiOs::Linux);
+        assert_eq!(abi_tag.version, (3, 2, 0));
+        let property = notes.gnu_property.unwrap();
+        assert!(!property.aarch64_bti);
+
+        let elf = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("libtest-so.so");
+        let notes = ElfNotes::from_elf_path(&elf).unwrap();
+        assert_eq!(
+            notes.build_id.map(|build_id| build_id.id),
+            read_elf_build_id(&elf).unwrap()
+        );
+
+        let data = std::fs::read(&elf).unwrap();
+        let file = File::open(&elf).unwrap();
+        assert_eq!(
+            ElfNotes::from_elf_data(&data).unwrap(),
+            ElfNotes::from_elf_file(&file).unwrap()
+        );
+    }
+
+    /// Check that we can parse GNU property and package metadata notes.
+    #[test]
+    fn note_parsing() {
+        let mut properties = Vec::new();
+        // Some unrelated property, which needs padding.
+        let () = properties.extend_from_slice(&0xc0008002u32.to_ne_bytes());
+        let () = properties.extend_from_slice(&4u32.to_ne_bytes());
+        let () = properties.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        let () = properties.extend_from_slice(&GNU_PROPERTY_X86_FEATURE_1_AND.to_ne_bytes());
+        let () = properties.extend_from_slice(&4u32.to_ne_bytes());
+        let () = properties.extend_from_slice(&[0b11, 0, 0, 0, 0, 0, 0, 0]);
+
+        let json =
+            br#"{"type":"rpm","name":"foo\"bar","version":"1.2-3.fc40","osVersion":"40","n":1}"#;
+        let mut desc = json.to_vec();
+        let () = desc.push(b'\0');
+
+        let mut data = note(b"GNU", NT_GNU_PROPERTY_TYPE_0, &properties, 8);
+        let () = data.extend(note(b"FDO", NT_FDO_PACKAGING_METADATA, &desc, 8));
+
+        let mut notes = ElfNotes::default();
+        let () = notes.parse_section(&data, 8).unwrap();
+
+        let property = notes.gnu_property.unwrap();
+        assert!(property.x86_ibt);
+        assert!(property.x86_shstk);
+        assert!(!property.aarch64_bti);
+        assert!(!property.aarch64_pac);
+
+        let package = notes.package.unwrap();
+        assert_eq!(package.ty.as_deref(), Some("rpm"));
+        assert_eq!(package.name.as_deref(), Some("foo\"bar"));
+        assert_eq!(package.version.as_deref(), Some("1.2-3.fc40"));
+        assert_eq!(package.os_version.as_deref(), Some("40"));
+        assert_eq!(package.os, None);
+        assert_eq!(package.json.as_bytes(), json);
+
+        // Truncated notes are reported as errors.
+        let mut notes = ElfNotes::default();
+        let err = notes.parse_section(&data[..10], 8).unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::InvalidData);
+    }
+
+    /// Check that we can parse "flat" JSON documents.
+    #[test]
+    fn flat_json_parsing() {
+        assert_eq!(parse_flat_json("{}"), Some(Vec::new()));
+        assert_eq!(
+            parse_flat_json(r#" { "a" : "b\u0041\n", "c": true, "d":"e" } "#),
+            Some(vec![
+                ("a".to_string(), "bA\n".to_string()),
+                ("d".to_string(), "e".to_string()),
+            ])
+        );
+        assert_eq!(parse_flat_json(r#"{"a": {"b": "c"}}"#), None);
+        assert_eq!(parse_flat_json(r#"{"a": "b""#), None);
+        assert_eq!(parse_flat_json("[]"), None);
+    }
+}
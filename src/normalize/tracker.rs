@@ -0,0 +1,277 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+
+#[cfg(feature = "process")]
+use crate::maps;
+use crate::maps::EntryPath;
+use crate::maps::MapsEntry;
+use crate::maps::PathMapsEntry;
+use crate::maps::PathName;
+#[cfg(feature = "process")]
+use crate::vfs::StdFileAccess;
+use crate::Addr;
+use crate::Pid;
+use crate::Result;
+
+
+/// The mode we assume for mappings reported via
+/// [`AddressSpaceTracker::add_mapping`] (`r-x`).
+const MAPPING_MODE: u8 = 0b1010;
+
+
+/// A tracker for the address space of a process, kept up to date by
+/// means of `mmap` and `munmap` events.
+///
+/// Continuous profilers typically learn about changes to the address
+/// space of the processes they sample (e.g., through
+/// `PERF_RECORD_MMAP2` events or BPF programs). Instead of re-reading
+/// `/proc/<pid>/maps` for every batch of addresses to normalize, such
+/// events can be fed into an `AddressSpaceTracker`, which then serves
+/// as the view of the process' address space for normalization via
+/// [`Normalizer::normalize_tracked_addrs`][crate::normalize::Normalizer::normalize_tracked_addrs].
+///
+/// ```no_run
+/// use blazesym::normalize::AddressSpaceTracker;
+/// use blazesym::normalize::Normalizer;
+/// use blazesym::Pid;
+///
+/// let pid = Pid::from(1234);
+/// let mut tracker = AddressSpaceTracker::from_proc_maps(pid).unwrap();
+/// // A new shared object got mapped...
+/// let () = tracker.add_mapping(0x7f0000000000..0x7f0000010000, 0x1000, "/usr/lib/libfoo.so");
+/// // ...and some other mapping disappeared.
+/// let () = tracker.remove_mapping(0x7f0000020000..0x7f0000030000);
+///
+/// let normalizer = Normalizer::new();
+/// let normalized = normalizer
+///     .normalize_tracked_addrs(&tracker, [0x7f0000000100].as_slice())
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct AddressSpaceTracker {
+    /// The process whose address space is being tracked.
+    pid: Pid,
+    /// The tracked (file backed) mappings, keyed by start address.
+    mappings: BTreeMap<Addr, PathMapsEntry>,
+}
+
+impl AddressSpaceTracker {
+    /// Create a new `AddressSpaceTracker` for the process with the
+    /// given ID, without any mappings.
+    pub fn new(pid: Pid) -> Self {
+        Self {
+            pid,
+            mappings: BTreeMap::new(),
+        }
+    }
+
+    /// Create a new `AddressSpaceTracker` for the process with the
+    /// given ID, seeded with the mappings currently reported in its
+    /// `/proc/<pid>/maps` file.
+    #[cfg(feature = "process")]
+    pub fn from_proc_maps(pid: Pid) -> Result<Self> {
+        let mut slf = Self::new(pid);
+        for entry in maps::parse(&StdFileAccess::default(), pid)? {
+            if let Some(entry) = maps::filter_map_relevant(entry?) {
+                let _prev = slf.mappings.insert(entry.range.start, entry);
+            }
+        }
+        Ok(slf)
+    }
+
+    /// Retrieve the path under which the file at `path`, as seen by the
+    /// tracked process, can be accessed.
+    fn accessible_path(&self, path: &Path) -> PathBuf {
+        let root = PathBuf::from(format!("/proc/{}/root", self.pid));
+        // `join` would discard `root` for absolute paths.
+        let mut accessible = root.into_os_string();
+        let () = accessible.push(path.as_os_str());
+        PathBuf::from(accessible)
+    }
+
+    /// Report a new mapping of the file at `path` at address range
+    /// `range`, starting at file offset `offset`.
+    ///
+    /// `path` is the path to the file as seen by the tracked process.
+    /// Any existing mappings overlapping `range` are replaced, mirroring
+    /// the semantics of `mmap` with `MAP_FIXED`.
+    pub fn add_mapping(&mut self, range: Range<Addr>, offset: u64, path: impl Into<PathBuf>) {
+        let symbolic_path = path.into();
+        let () = self.remove_mapping(range.clone());
+        if range.is_empty() {
+            return
+        }
+
+        let entry = PathMapsEntry {
+            range: range.clone(),
+            mode: MAPPING_MODE,
+            offset,
+            path: EntryPath {
+                maps_file: self.accessible_path(&symbolic_path),
+                symbolic_path,
+                deleted: false,
+            },
+        };
+        let _prev = self.mappings.insert(range.start, entry);
+    }
+
+    /// Report the unmapping of address range `range`.
+    ///
+    /// Mappings partially covered by `range` are trimmed (or split)
+    /// accordingly.
+    pub fn remove_mapping(&mut self, range: Range<Addr>) {
+        if range.is_empty() {
+            return
+        }
+
+        // Mappings may start before `range` and still overlap it, so
+        // we have to start the search at the mapping preceding it.
+        let first = self
+            .mappings
+            .range(..range.start)
+            .next_back()
+            .map(|(start, _entry)| *start)
+            .unwrap_or(range.start);
+        let overlapping = self
+            .mappings
+            .range(first..range.end)
+            .filter(|(_start, entry)| entry.range.end > range.start)
+            .map(|(start, _entry)| *start)
+            .collect::<Vec<_>>();
+
+        for start in overlapping {
+            // SANITY: We just found the key in the map.
+            let entry = self.mappings.remove(&start).unwrap();
+
+            if entry.range.start < range.start {
+                let head = PathMapsEntry {
+                    range: entry.range.start..range.start,
+                    mode: entry.mode,
+                    offset: entry.offset,
+                    path: self.reaccessible(&entry.path),
+                };
+                let _prev = self.mappings.insert(head.range.start, head);
+            }
+
+            if entry.range.end > range.end {
+                let tail = PathMapsEntry {
+                    range: range.end..entry.range.end,
+                    mode: entry.mode,
+                    offset: entry.offset + (range.end - entry.range.start),
+                    path: self.reaccessible(&entry.path),
+                };
+                let _prev = self.mappings.insert(tail.range.start, tail);
+            }
+        }
+    }
+
+    /// Create a copy of `path` usable for an adjusted mapping.
+    ///
+    /// Paths of mappings seeded from `/proc/<pid>/maps` refer to
+    /// `/proc/<pid>/map_files/`, which is keyed by the exact address
+    /// range and would no longer be valid.
+    fn reaccessible(&self, path: &EntryPath) -> EntryPath {
+        EntryPath {
+            maps_file: self.accessible_path(&path.symbolic_path),
+            symbolic_path: path.symbolic_path.clone(),
+            deleted: path.deleted,
+        }
+    }
+
+    /// Retrieve the process whose address space is being tracked.
+    #[inline]
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    /// Retrieve an iterator over the tracked mappings, sorted by start
+    /// address.
+    pub(crate) fn entries(&self) -> impl Iterator<Item = Result<MapsEntry>> + '_ {
+        self.mappings.values().map(|entry| {
+            Ok(MapsEntry {
+                range: entry.range.clone(),
+                mode: entry.mode,
+                offset: entry.offset,
+                path_name: Some(PathName::Path(entry.path.clone())),
+            })
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Retrieve the tracked mappings in a form suitable for comparison.
+    fn mappings(tracker: &AddressSpaceTracker) -> Vec<(Range<Addr>, u64, PathBuf)> {
+        tracker
+            .entries()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let path = match entry.path_name {
+                    Some(PathName::Path(path)) => path.symbolic_path,
+                    _ => unreachable!(),
+                };
+                (entry.range, entry.offset, path)
+            })
+            .collect()
+    }
+
+    /// Check that we track mappings and unmappings as expected.
+    #[test]
+    fn mapping_tracking() {
+        let mut tracker = AddressSpaceTracker::new(Pid::from(42));
+        let () = tracker.add_mapping(0x1000..0x5000, 0x0, "/usr/bin/foo");
+        let () = tracker.add_mapping(0x8000..0x9000, 0x2000, "/usr/lib/libbar.so");
+        assert_eq!(
+            mappings(&tracker),
+            vec![
+                (0x1000..0x5000, 0x0, PathBuf::from("/usr/bin/foo")),
+                (0x8000..0x9000, 0x2000, PathBuf::from("/usr/lib/libbar.so")),
+            ]
+        );
+
+        let entry = tracker.entries().next().unwrap().unwrap();
+        let path = entry.path_name.as_ref().unwrap().as_path().unwrap();
+        assert_eq!(path.maps_file, Path::new("/proc/42/root/usr/bin/foo"));
+
+        // Punch a hole into the first mapping.
+        let () = tracker.remove_mapping(0x2000..0x3000);
+        assert_eq!(
+            mappings(&tracker),
+            vec![
+                (0x1000..0x2000, 0x0, PathBuf::from("/usr/bin/foo")),
+                (0x3000..0x5000, 0x2000, PathBuf::from("/usr/bin/foo")),
+                (0x8000..0x9000, 0x2000, PathBuf::from("/usr/lib/libbar.so")),
+            ]
+        );
+
+        // Map over parts of two existing mappings.
+        let () = tracker.add_mapping(0x4000..0x8800, 0x1000, "/usr/lib/libbaz.so");
+        assert_eq!(
+            mappings(&tracker),
+            vec![
+                (0x1000..0x2000, 0x0, PathBuf::from("/usr/bin/foo")),
+                (0x3000..0x4000, 0x2000, PathBuf::from("/usr/bin/foo")),
+                (0x4000..0x8800, 0x1000, PathBuf::from("/usr/lib/libbaz.so")),
+                (0x8800..0x9000, 0x2800, PathBuf::from("/usr/lib/libbar.so")),
+            ]
+        );
+
+        // Unmap everything.
+        let () = tracker.remove_mapping(0x0..0x10000);
+        assert_eq!(mappings(&tracker), Vec::new());
+    }
+
+    /// Check that we can seed a tracker with the mappings of a process.
+    #[test]
+    fn proc_maps_seeding() {
+        let tracker = AddressSpaceTracker::from_proc_maps(Pid::Slf).unwrap();
+        assert_eq!(tracker.pid(), Pid::Slf);
+        assert_ne!(mappings(&tracker), Vec::new());
+    }
+}
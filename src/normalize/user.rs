@@ -1,44 +1,72 @@
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::ffi::OsStr;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
 use std::io::Error;
 use std::io::ErrorKind;
-use std::marker::PhantomData;
 use std::path::Path;
 use std::path::PathBuf;
 
 use crate::maps;
+use crate::maps::MapsEntry;
 use crate::maps::PathMapsEntry;
+use crate::vfs::FileAccess;
+use crate::zip;
+use crate::zip::create_apk_elf_path;
+use crate::zip::split_apk_elf_path;
 use crate::Addr;
+use crate::ErrorExt as _;
 use crate::Pid;
 use crate::Result;
 
-use super::buildid::BuildIdFn;
 use super::buildid::BuildIdReader;
 use super::buildid::DefaultBuildIdReader;
+use super::buildid::FallbackBuildIdReader;
+use super::buildid::ModuleInfo;
+use super::buildid::ModuleInfoFn;
 use super::buildid::NoBuildIdReader;
 use super::meta::Apk;
 use super::meta::Elf;
+use super::meta::MachO;
+use super::meta::Pe;
 use super::meta::Unknown;
 use super::meta::UserMeta;
 use super::normalizer::Output;
 
 
-/// Make a [`UserMeta::Elf`] variant.
-fn make_elf_meta(entry: &PathMapsEntry, get_build_id: &BuildIdFn) -> Result<UserMeta> {
-    let elf = Elf {
-        path: entry.path.symbolic_path.to_path_buf(),
-        build_id: get_build_id(&entry.path.maps_file)?,
-        _non_exhaustive: (),
+/// Make a [`UserMeta::Elf`], [`UserMeta::MachO`], or [`UserMeta::Pe`]
+/// variant, depending on the type of module mapped.
+fn make_module_meta(entry: &PathMapsEntry, get_info: &ModuleInfoFn<'_>) -> Result<UserMeta> {
+    let path = entry.path.symbolic_path.to_path_buf();
+    let meta = match get_info(&entry.path.maps_file)? {
+        ModuleInfo::Elf(build_id) => UserMeta::Elf(Elf {
+            path,
+            build_id,
+            _non_exhaustive: (),
+        }),
+        ModuleInfo::MachO(uuid) => UserMeta::MachO(MachO {
+            path,
+            uuid,
+            _non_exhaustive: (),
+        }),
+        ModuleInfo::Pe(pdb) => UserMeta::Pe(Pe {
+            path,
+            pdb,
+            _non_exhaustive: (),
+        }),
     };
-    let meta = UserMeta::Elf(elf);
     Ok(meta)
 }
 
 
 /// Make a [`UserMeta::Apk`] variant.
-fn make_apk_meta(entry: &PathMapsEntry) -> Result<UserMeta> {
+fn make_apk_meta(path: PathBuf, elf_path: Option<PathBuf>) -> Result<UserMeta> {
     let apk = Apk {
-        path: entry.path.symbolic_path.to_path_buf(),
+        path,
+        elf_path,
         _non_exhaustive: (),
     };
     let meta = UserMeta::Apk(apk);
@@ -107,10 +135,36 @@ pub(crate) trait Handler<D = ()> {
 
     /// Handle an address residing in the provided [`PathMapsEntry`].
     fn handle_entry_addr(&mut self, addr: Addr, entry: &PathMapsEntry) -> Result<()>;
+
+    /// Handle an address residing in a proc maps entry that is not
+    /// relevant to normalization, such as the stack or a guard page.
+    ///
+    /// By default such addresses are treated as unknown.
+    fn handle_other_entry_addr(&mut self, addr: Addr, _entry: &MapsEntry, data: D) -> Result<()> {
+        self.handle_unknown_addr(addr, data)
+    }
+}
+
+
+/// The signature of [`BuildIdReader::read_module_info`].
+type ReadModuleInfoFn = fn(&dyn FileAccess, &Path) -> Result<ModuleInfo>;
+
+/// Retrieve the function to use for reading module information, based
+/// on the build ID related configuration.
+fn module_info_reader(read_build_ids: bool, build_id_fallback: bool) -> ReadModuleInfoFn {
+    match (read_build_ids, build_id_fallback) {
+        (true, false) => DefaultBuildIdReader::read_module_info,
+        (true, true) => FallbackBuildIdReader::read_module_info,
+        (false, _) => NoBuildIdReader::read_module_info,
+    }
 }
 
 
-struct NormalizationHandler<R> {
+struct NormalizationHandler<'fs> {
+    /// The provider used for accessing files.
+    fs: &'fs dyn FileAccess,
+    /// The function used for reading module information.
+    read_module_info: ReadModuleInfoFn,
     /// The user output we are building up.
     normalized: UserOutput,
     /// Lookup table from path (as used in each proc maps entry) to index into
@@ -119,29 +173,52 @@ struct NormalizationHandler<R> {
     /// The index of the `Unknown` entry in `meta_lookup`, used for all unknown
     /// addresses.
     unknown_idx: Option<usize>,
-    #[doc(hidden)]
-    _phanton: PhantomData<R>,
+    /// Lookup table from APK path (as used in each proc maps entry) to
+    /// the parsed archive.
+    apks: HashMap<PathBuf, zip::Archive>,
 }
 
-impl<R> NormalizationHandler<R> {
+impl<'fs> NormalizationHandler<'fs> {
     /// Instantiate a new `NormalizationHandler` object.
-    fn new(addr_cnt: usize) -> Self {
+    fn new(fs: &'fs dyn FileAccess, read_module_info: ReadModuleInfoFn, addr_cnt: usize) -> Self {
         Self {
+            fs,
+            read_module_info,
             normalized: UserOutput {
                 outputs: Vec::with_capacity(addr_cnt),
                 meta: Vec::new(),
             },
             meta_lookup: HashMap::<PathBuf, usize>::new(),
             unknown_idx: None,
-            _phanton: PhantomData,
+            apks: HashMap::new(),
         }
     }
+
+    /// Find the path of the uncompressed ELF file inside the APK mapped
+    /// by `entry` that contains the provided file offset.
+    fn find_apk_elf(&mut self, entry: &PathMapsEntry, file_off: u64) -> Result<Option<PathBuf>> {
+        let apk = match self.apks.entry(entry.path.symbolic_path.clone()) {
+            Entry::Occupied(occupied) => occupied.into_mut(),
+            Entry::Vacant(vacant) => {
+                let data = self.fs.open(&entry.path.maps_file).with_context(|| {
+                    format!("failed to open APK {}", entry.path.maps_file.display())
+                })?;
+                let apk = zip::Archive::with_mmap(data.mmap().clone()).with_context(|| {
+                    format!("failed to open APK {}", entry.path.symbolic_path.display())
+                })?;
+                vacant.insert(apk)
+            }
+        };
+
+        let elf_path = apk
+            .entry_at_offset(file_off)?
+            .filter(|apk_entry| apk_entry.compression == 0)
+            .map(|apk_entry| apk_entry.path.to_path_buf());
+        Ok(elf_path)
+    }
 }
 
-impl<R> Handler<()> for NormalizationHandler<R>
-where
-    R: BuildIdReader,
-{
+impl Handler<()> for NormalizationHandler<'_> {
     #[cfg_attr(feature = "tracing", crate::log::instrument(skip_all, fields(addr = format_args!("{addr:#x}"))))]
     fn handle_unknown_addr(&mut self, addr: Addr, (): ()) -> Result<()> {
         self.unknown_idx = self.normalized.add_unknown_addr(addr, self.unknown_idx);
@@ -150,23 +227,43 @@ where
 
     fn handle_entry_addr(&mut self, addr: Addr, entry: &PathMapsEntry) -> Result<()> {
         let file_off = addr - entry.range.start + entry.offset;
+        let fs = self.fs;
+        let read_module_info = self.read_module_info;
         let ext = entry
             .path
             .symbolic_path
             .extension()
             .unwrap_or_else(|| OsStr::new(""));
-        match ext.to_str() {
-            Some("apk") | Some("zip") => self.normalized.add_normalized_offset(
+        // Libraries loaded directly from an APK may be reported
+        // using an Android-style `base.apk!/lib/libfoo.so` path,
+        // with the offset being relative to the APK.
+        if let Some((apk_path, elf_path)) = split_apk_elf_path(&entry.path.symbolic_path) {
+            return self.normalized.add_normalized_offset(
                 file_off,
                 &entry.path.symbolic_path,
                 &mut self.meta_lookup,
-                || make_apk_meta(entry),
-            ),
+                || make_apk_meta(apk_path, Some(elf_path)),
+            )
+        }
+
+        match ext.to_str() {
+            Some("apk") | Some("zip") => {
+                let apk_path = &entry.path.symbolic_path;
+                let elf_path = self.find_apk_elf(entry, file_off)?;
+                let key = match &elf_path {
+                    Some(elf_path) => Cow::Owned(create_apk_elf_path(apk_path, elf_path)?),
+                    None => Cow::Borrowed(apk_path.as_path()),
+                };
+                self.normalized
+                    .add_normalized_offset(file_off, &key, &mut self.meta_lookup, || {
+                        make_apk_meta(apk_path.clone(), elf_path)
+                    })
+            }
             _ => self.normalized.add_normalized_offset(
                 file_off,
                 &entry.path.symbolic_path,
                 &mut self.meta_lookup,
-                || make_elf_meta(entry, &R::read_build_id_from_elf),
+                || make_module_meta(entry, &|path| read_module_info(fs, path)),
             ),
         }
     }
@@ -185,15 +282,18 @@ where
     H: Handler<D>,
     D: Clone,
 {
-    let mut entries = entries.filter_map(|result| match result {
-        Ok(entry) => maps::filter_map_relevant(entry).map(Ok),
-        Err(err) => Some(Err(err)),
-    });
+    // We consider all entries, so that we can report addresses in
+    // irrelevant ones (e.g., the stack) accordingly.
+    let mut entries = entries.map(|result| result.map(maps::classify_relevant));
+    let range = |entry: &Result<PathMapsEntry, MapsEntry>| match entry {
+        Ok(entry) => entry.range.clone(),
+        Err(entry) => entry.range.clone(),
+    };
 
     let mut entry = entries.next().ok_or_else(|| {
         Error::new(
             ErrorKind::UnexpectedEof,
-            "proc maps does not contain relevant entries",
+            "proc maps does not contain any entries",
         )
     })??;
 
@@ -211,7 +311,7 @@ where
         }
         prev_addr = addr;
 
-        while addr >= entry.range.end {
+        while addr >= range(&entry).end {
             entry = if let Some(entry) = entries.next() {
                 entry?
             } else {
@@ -229,12 +329,15 @@ where
         // that means that we cannot find a suitable entry. This could
         // happen, for example, if an ELF object was unmapped between
         // address capture and normalization.
-        if addr < entry.range.start {
+        if addr < range(&entry).start {
             let () = handler.handle_unknown_addr(addr, data.clone())?;
             continue 'main
         }
 
-        let () = handler.handle_entry_addr(addr, &entry)?;
+        let () = match &entry {
+            Ok(entry) => handler.handle_entry_addr(addr, entry)?,
+            Err(entry) => handler.handle_other_entry_addr(addr, entry, data.clone())?,
+        };
     }
 
     Ok(handler)
@@ -258,26 +361,205 @@ where
 /// File offsets are reported in the exact same order in which the
 /// non-normalized addresses ones were provided.
 pub(super) fn normalize_user_addrs_sorted_impl<A>(
+    fs: &dyn FileAccess,
     addrs: A,
     pid: Pid,
     read_build_ids: bool,
+    build_id_fallback: bool,
+) -> Result<UserOutput>
+where
+    A: ExactSizeIterator<Item = Addr> + Clone,
+{
+    let entries = maps::parse(fs, pid)?;
+    normalize_user_addrs_sorted_with_entries_impl(
+        fs,
+        addrs,
+        entries,
+        read_build_ids,
+        build_id_fallback,
+    )
+}
+
+/// Normalize all `addrs` using the provided proc maps entries, which
+/// need to be sorted by start address.
+///
+/// Apart from the source of the entries, this function behaves like
+/// [`normalize_user_addrs_sorted_impl`].
+pub(super) fn normalize_user_addrs_sorted_with_entries_impl<A, E>(
+    fs: &dyn FileAccess,
+    addrs: A,
+    entries: E,
+    read_build_ids: bool,
+    build_id_fallback: bool,
 ) -> Result<UserOutput>
 where
     A: ExactSizeIterator<Item = Addr> + Clone,
+    E: Iterator<Item = Result<maps::MapsEntry>>,
 {
     let addrs_cnt = addrs.len();
-    let entries = maps::parse(pid)?;
-
-    if read_build_ids {
-        let handler = NormalizationHandler::<DefaultBuildIdReader>::new(addrs_cnt);
-        let handler = normalize_sorted_user_addrs_with_entries(addrs, entries, handler, ())?;
-        debug_assert_eq!(handler.normalized.outputs.len(), addrs_cnt);
-        Ok(handler.normalized)
-    } else {
-        let handler = NormalizationHandler::<NoBuildIdReader>::new(addrs_cnt);
-        let handler = normalize_sorted_user_addrs_with_entries(addrs, entries, handler, ())?;
-        debug_assert_eq!(handler.normalized.outputs.len(), addrs_cnt);
-        Ok(handler.normalized)
+    let read_module_info = module_info_reader(read_build_ids, build_id_fallback);
+    let handler = NormalizationHandler::new(fs, read_module_info, addrs_cnt);
+    let handler = normalize_sorted_user_addrs_with_entries(addrs, entries, handler, ())?;
+    debug_assert_eq!(handler.normalized.outputs.len(), addrs_cnt);
+    Ok(handler.normalized)
+}
+
+
+/// A summary of the addresses normalized to a single component, as
+/// reported by [`UserOutputIter::finish`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModuleSummary {
+    /// Meta information about the component.
+    pub meta: UserMeta,
+    /// The number of addresses normalized to the component.
+    pub addr_count: usize,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+
+/// An iterator normalizing addresses belonging to a process one at a
+/// time.
+///
+/// Objects of this type are created by
+/// [`Normalizer::normalize_user_addrs_iter`][crate::normalize::Normalizer::normalize_user_addrs_iter].
+/// The iterator yields the normalized output and the index of the
+/// corresponding meta information for each input address, in order.
+/// Meta information is accumulated as components are encountered and
+/// can be accessed at any time via [`UserOutputIter::meta`].
+pub struct UserOutputIter<'fs, A> {
+    /// The addresses to normalize.
+    addrs: A,
+    /// The relevant proc maps entries of the process.
+    entries: Box<dyn Iterator<Item = Result<PathMapsEntry>> + Send>,
+    /// The proc maps entry currently being considered, if any.
+    entry: Option<PathMapsEntry>,
+    /// The previously normalized address.
+    prev_addr: Addr,
+    /// The handler taking care of the actual normalization.
+    handler: NormalizationHandler<'fs>,
+    /// The number of addresses normalized, by meta data index.
+    counts: Vec<usize>,
+    /// Whether an error was encountered, fusing the iterator.
+    failed: bool,
+}
+
+impl<'fs, A> UserOutputIter<'fs, A>
+where
+    A: Iterator<Item = Addr>,
+{
+    pub(super) fn new(
+        fs: &'fs dyn FileAccess,
+        addrs: A,
+        pid: Pid,
+        read_build_ids: bool,
+        build_id_fallback: bool,
+    ) -> Result<Self> {
+        let mut entries = maps::parse(fs, pid)?.filter_map(|result| match result {
+            Ok(entry) => maps::filter_map_relevant(entry).map(Ok),
+            Err(err) => Some(Err(err)),
+        });
+        let entry = entries.next().transpose()?;
+        let read_module_info = module_info_reader(read_build_ids, build_id_fallback);
+
+        let slf = Self {
+            addrs,
+            entries: Box::new(entries),
+            entry,
+            prev_addr: 0,
+            handler: NormalizationHandler::new(fs, read_module_info, 1),
+            counts: Vec::new(),
+            failed: false,
+        };
+        Ok(slf)
+    }
+
+    /// Retrieve the meta information gathered so far.
+    ///
+    /// The meta data index reported for each output is guaranteed to
+    /// be valid for the returned slice.
+    #[inline]
+    pub fn meta(&self) -> &[UserMeta] {
+        &self.handler.normalized.meta
+    }
+
+    /// Finish normalization, retrieving a summary of all the
+    /// components encountered.
+    ///
+    /// Summaries are reported in the order of meta data indices. All
+    /// addresses that could not be normalized are attributed to a
+    /// single summary with [`UserMeta::Unknown`] meta information.
+    pub fn finish(self) -> Vec<ModuleSummary> {
+        self.handler
+            .normalized
+            .meta
+            .into_iter()
+            .zip(self.counts)
+            .map(|(meta, addr_count)| ModuleSummary {
+                meta,
+                addr_count,
+                _non_exhaustive: (),
+            })
+            .collect()
+    }
+
+    fn normalize(&mut self, addr: Addr) -> Result<(u64, usize)> {
+        if addr < self.prev_addr {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "addresses to normalize are not sorted",
+            )
+            .into())
+        }
+        self.prev_addr = addr;
+
+        while let Some(entry) = &self.entry {
+            if addr < entry.range.end {
+                break
+            }
+            self.entry = self.entries.next().transpose()?;
+        }
+
+        match &self.entry {
+            Some(entry) if addr >= entry.range.start => {
+                self.handler.handle_entry_addr(addr, entry)?
+            }
+            _ => self.handler.handle_unknown_addr(addr, ())?,
+        }
+
+        // SANITY: The handler reported exactly one output above.
+        let output = self.handler.normalized.outputs.pop().unwrap();
+        let () = self.counts.resize(self.handler.normalized.meta.len(), 0);
+        self.counts[output.1] += 1;
+        Ok(output)
+    }
+}
+
+impl<A> Iterator for UserOutputIter<'_, A>
+where
+    A: Iterator<Item = Addr>,
+{
+    type Item = Result<(u64, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None
+        }
+
+        let addr = self.addrs.next()?;
+        let result = self.normalize(addr);
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
+
+impl<A> Debug for UserOutputIter<'_, A> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct(stringify!(UserOutputIter))
+            .field("entry", &self.entry)
+            .field("meta", &self.handler.normalized.meta)
+            .finish()
     }
 }
 
@@ -288,6 +570,8 @@ mod tests {
 
     use test_log::test;
 
+    use crate::vfs::StdFileAccess;
+
 
     /// Check that we correctly handle normalization of an address not
     /// in any executable segment.
@@ -324,8 +608,10 @@ mod tests {
             let pid = Pid::Slf;
             let entries = maps::parse_file(maps.as_bytes(), pid);
             let addrs = [unknown_addr as Addr];
+            let fs = StdFileAccess::default();
 
-            let handler = NormalizationHandler::<NoBuildIdReader>::new(addrs.len());
+            let handler =
+                NormalizationHandler::new(&fs, NoBuildIdReader::read_module_info, addrs.len());
             let normalized = normalize_sorted_user_addrs_with_entries(
                 addrs.as_slice().iter().copied(),
                 entries,
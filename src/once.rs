@@ -1,35 +1,58 @@
-//! A copy of std::once::OnceCell.
+//! A thread-safe cell that can be written to only once, modeled after
+//! std::sync::OnceLock.
 // TODO: Remove this module once our minimum supported Rust version is greater
-//       1.70 and/or `OnceCell::get_or_try_init` is stable.
+//       1.70 and/or `OnceLock::get_or_try_init` is stable.
 
 use std::cell::UnsafeCell;
 use std::convert::Infallible;
 use std::fmt;
 use std::hint::unreachable_unchecked;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::thread;
+use std::thread::ThreadId;
 
 /// A cell which can be written to only once.
 ///
-/// This allows obtaining a shared `&T` reference to its inner value without copying or replacing
-/// it (unlike [`Cell`]), and without runtime borrow checks (unlike [`RefCell`]). However,
-/// only immutable references can be obtained unless one has a mutable reference to the cell
-/// itself.
-///
-/// For a thread-safe version of this struct, see [`std::sync::OnceLock`].
-///
-/// [`RefCell`]: crate::cell::RefCell
-/// [`Cell`]: crate::cell::Cell
-/// [`std::sync::OnceLock`]: ../../std/sync/struct.OnceLock.html
+/// This allows obtaining a shared `&T` reference to its inner value
+/// without copying or replacing it. Contrary to the
+/// [`std::cell::OnceCell`] it is named after, the cell can be shared
+/// between threads: initialization is serialized through a lock, while
+/// reading an already initialized value is lock-free.
 pub struct OnceCell<T> {
-    // Invariant: written to at most once.
+    /// Lock serializing initialization of `inner`.
+    lock: Mutex<()>,
+    /// The thread currently running an initializer, if any, used for
+    /// detecting reentrant initialization.
+    initializer: Mutex<Option<ThreadId>>,
+    /// Whether `inner` has been initialized.
+    init: AtomicBool,
+    // Invariant: written to at most once, while holding `lock` and
+    //            before `init` is set.
     inner: UnsafeCell<Option<T>>,
 }
 
+// SAFETY: Values are only ever written while holding the lock and
+//         before publishing them via `init` with release semantics.
+//         Readers only access the value after observing `init` with
+//         acquire semantics. Because a `&T` may be handed out to
+//         multiple threads and the value may have been created on
+//         another thread, `T` has to be both `Sync` and `Send`.
+unsafe impl<T: Sync + Send> Sync for OnceCell<T> {}
+// SAFETY: Sending the cell sends the contained `T`.
+unsafe impl<T: Send> Send for OnceCell<T> {}
+
 impl<T> OnceCell<T> {
     /// Creates a new empty cell.
     #[inline]
     #[must_use]
     pub const fn new() -> OnceCell<T> {
         OnceCell {
+            lock: Mutex::new(()),
+            initializer: Mutex::new(None),
+            init: AtomicBool::new(false),
             inner: UnsafeCell::new(None),
         }
     }
@@ -39,8 +62,13 @@ impl<T> OnceCell<T> {
     /// Returns `None` if the cell is empty.
     #[inline]
     pub fn get(&self) -> Option<&T> {
-        // SAFETY: Safe due to `inner`'s invariant
-        unsafe { &*self.inner.get() }.as_ref()
+        if self.init.load(Ordering::Acquire) {
+            // SAFETY: Safe due to `inner`'s invariant; once `init` is
+            //         set the value is never written again.
+            unsafe { &*self.inner.get() }.as_ref()
+        } else {
+            None
+        }
     }
 
     /// Sets the contents of the cell to `value`.
@@ -66,16 +94,40 @@ impl<T> OnceCell<T> {
     /// `Err(&current_value, value)` if it was full.
     #[inline]
     pub fn try_insert(&self, value: T) -> Result<&T, (&T, T)> {
+        let () = self.check_reentrancy();
+        let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
         if let Some(old) = self.get() {
             return Err((old, value))
         }
 
-        // SAFETY: This is the only place where we set the slot, no races
-        // due to reentrancy/concurrency are possible, and we've
-        // checked that slot is currently `None`, so this write
-        // maintains the `inner`'s invariant.
+        Ok(self.insert_locked(value))
+    }
+
+    /// Panic if the calling thread is currently initializing the cell,
+    /// as it would otherwise deadlock trying to acquire `lock`.
+    fn check_reentrancy(&self) {
+        let initializer = self
+            .initializer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if *initializer == Some(thread::current().id()) {
+            panic!("reentrant init")
+        }
+    }
+
+    /// Store `value` in the cell and publish it.
+    ///
+    /// The caller has to hold `lock` and have checked that the cell is
+    /// still empty.
+    fn insert_locked(&self, value: T) -> &T {
+        // SAFETY: We hold the lock and have checked that slot is
+        //         currently `None`. No reader accesses the slot before
+        //         `init` is set, so this write maintains the
+        //         `inner`'s invariant.
         let slot = unsafe { &mut *self.inner.get() };
-        Ok(slot.insert(value))
+        let value = slot.insert(value);
+        let () = self.init.store(true, Ordering::Release);
+        value
     }
 
     /// Gets the contents of the cell, initializing it with `f`
@@ -103,6 +155,9 @@ impl<T> OnceCell<T> {
     /// the cell was empty. If the cell was empty and `f` failed, an
     /// error is returned.
     ///
+    /// If multiple threads race to initialize the cell, only one of
+    /// them runs `f`, while the others block until it has finished.
+    ///
     /// # Panics
     ///
     /// If `f` panics, the panic is propagated to the caller, and the cell
@@ -126,16 +181,33 @@ impl<T> OnceCell<T> {
         {
             f()
         }
-        let val = outlined_call(f)?;
-        // Note that *some* forms of reentrant initialization might lead to
-        // UB (see `reentrant_init` test). I believe that just removing this
-        // `panic`, while keeping `try_insert` would be sound, but it seems
-        // better to panic, rather than to silently use an old value.
-        if let Ok(val) = self.try_insert(val) {
-            Ok(val)
-        } else {
-            panic!("reentrant init")
+
+        /// A guard resetting the initializing thread when dropped,
+        /// including when unwinding.
+        struct Initializer<'cell>(&'cell Mutex<Option<ThreadId>>);
+
+        impl Drop for Initializer<'_> {
+            fn drop(&mut self) {
+                *self.0.lock().unwrap_or_else(PoisonError::into_inner) = None;
+            }
         }
+
+        let () = self.check_reentrancy();
+        let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        // Another thread may have initialized the cell while we were
+        // waiting for the lock.
+        if let Some(val) = self.get() {
+            return Ok(val)
+        }
+
+        *self
+            .initializer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(thread::current().id());
+        let initializer = Initializer(&self.initializer);
+        let val = outlined_call(f);
+        let () = drop(initializer);
+        Ok(self.insert_locked(val?))
     }
 }
 
@@ -185,10 +257,59 @@ impl<T> From<T> for OnceCell<T> {
     #[inline]
     fn from(value: T) -> Self {
         OnceCell {
+            lock: Mutex::new(()),
+            initializer: Mutex::new(None),
+            init: AtomicBool::new(true),
             inner: UnsafeCell::new(Some(value)),
         }
     }
 }
 
-// Just like for `Cell<T>` this isn't needed, but results in nicer error messages.
-//impl<T> !Sync for OnceCell<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::panic::catch_unwind;
+    use std::panic::AssertUnwindSafe;
+    use std::sync::atomic::AtomicUsize;
+
+
+    /// Check that concurrent initialization runs the initializer only
+    /// once and that all threads observe the same value.
+    #[test]
+    fn concurrent_init() {
+        let cell = OnceCell::<usize>::new();
+        let calls = AtomicUsize::new(0);
+
+        let () = thread::scope(|s| {
+            for _ in 0..8 {
+                let _handle = s.spawn(|| {
+                    let value = cell.get_or_init(|| {
+                        let _count = calls.fetch_add(1, Ordering::Relaxed);
+                        42
+                    });
+                    assert_eq!(*value, 42);
+                });
+            }
+        });
+
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert_eq!(cell.get(), Some(&42));
+    }
+
+    /// Check that reentrant initialization panics instead of
+    /// deadlocking, while leaving the cell usable.
+    #[test]
+    fn reentrant_init() {
+        let cell = OnceCell::<usize>::new();
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            cell.get_or_init(|| *cell.get_or_init(|| 1) + 1)
+        }));
+        let err = result.unwrap_err();
+        assert_eq!(err.downcast_ref::<&str>(), Some(&"reentrant init"));
+
+        assert_eq!(cell.get(), None);
+        assert_eq!(*cell.get_or_init(|| 42), 42);
+    }
+}
@@ -0,0 +1,293 @@
+//! Minimal support for the PE (Portable Executable) file format.
+//!
+//! Only the parts necessary for identifying a PE module are covered,
+//! namely the CodeView record in the debug directory, which references
+//! the module's PDB file. The format is described at
+//! <https://learn.microsoft.com/en-us/windows/win32/debug/pe-format>.
+
+use std::path::PathBuf;
+
+use crate::normalize::PdbInfo;
+use crate::util::bytes_to_os_str;
+use crate::util::ReadRaw as _;
+use crate::Error;
+use crate::IntoError as _;
+use crate::Result;
+
+/// The offset of the `e_lfanew` member in the DOS header.
+const E_LFANEW_OFFSET: usize = 0x3c;
+/// The size of the COFF file header, in bytes.
+const COFF_HEADER_SIZE: usize = 20;
+/// The size of a section header, in bytes.
+const SECTION_HEADER_SIZE: usize = 40;
+/// The size of a debug directory entry, in bytes.
+const DEBUG_DIRECTORY_SIZE: usize = 28;
+
+const IMAGE_NT_OPTIONAL_HDR32_MAGIC: u16 = 0x10b;
+const IMAGE_NT_OPTIONAL_HDR64_MAGIC: u16 = 0x20b;
+/// The index of the debug directory in the optional header's data
+/// directories.
+const IMAGE_DIRECTORY_ENTRY_DEBUG: u32 = 6;
+const IMAGE_DEBUG_TYPE_CODEVIEW: u32 = 2;
+/// The signature of a PDB 7.0 CodeView record.
+const CV_SIGNATURE_RSDS: &[u8; 4] = b"RSDS";
+
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let mut data = data.get(offset..)?;
+    data.read_u16().map(u16::from_le)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let mut data = data.get(offset..)?;
+    data.read_u32().map(u32::from_le)
+}
+
+/// Read the offset of the PE signature, if `data` starts with a DOS
+/// header.
+fn pe_offset(data: &[u8]) -> Option<usize> {
+    if !data.starts_with(b"MZ") {
+        return None
+    }
+    read_u32(data, E_LFANEW_OFFSET).map(|offset| offset as usize)
+}
+
+/// Check whether `data` represents a PE file.
+pub(crate) fn is_pe(data: &[u8]) -> bool {
+    pe_offset(data)
+        .and_then(|offset| data.get(offset..offset.checked_add(4)?))
+        .map(|signature| signature == b"PE\0\0")
+        .unwrap_or(false)
+}
+
+/// Convert a relative virtual address into a file offset, using the
+/// provided section headers.
+fn rva_to_offset(sections: &[u8], rva: u32) -> Option<usize> {
+    sections
+        .chunks_exact(SECTION_HEADER_SIZE)
+        .find_map(|section| {
+            let virtual_size = read_u32(section, 8)?;
+            let virtual_addr = read_u32(section, 12)?;
+            let raw_size = read_u32(section, 16)?;
+            let raw_offset = read_u32(section, 20)?;
+            let size = virtual_size.max(raw_size);
+            let offset = rva.checked_sub(virtual_addr)?;
+            (offset < size && offset < raw_size).then_some(raw_offset as usize + offset as usize)
+        })
+}
+
+/// Read the PDB information of the PE file represented by `data`, as
+/// stored in the CodeView record of its debug directory.
+///
+/// Returns [`None`] if the file does not contain such a record.
+pub(crate) fn read_pdb_info(data: &[u8]) -> Result<Option<PdbInfo>> {
+    if !is_pe(data) {
+        return Err(Error::with_unsupported_format("data is not a PE file"))
+    }
+    // SANITY: `is_pe` checked that there is a PE offset.
+    let coff = pe_offset(data).unwrap() + 4;
+    let section_cnt =
+        read_u16(data, coff + 2).ok_or_invalid_data(|| "failed to read PE COFF file header")?;
+    let opt_hdr_size =
+        read_u16(data, coff + 16).ok_or_invalid_data(|| "failed to read PE COFF file header")?;
+    let opt_hdr = coff + COFF_HEADER_SIZE;
+
+    let (dir_cnt_offset, dirs_offset) =
+        match read_u16(data, opt_hdr).ok_or_invalid_data(|| "failed to read PE optional header")? {
+            IMAGE_NT_OPTIONAL_HDR32_MAGIC => (92, 96),
+            IMAGE_NT_OPTIONAL_HDR64_MAGIC => (108, 112),
+            magic => {
+                return Err(Error::with_invalid_data(format!(
+                    "encountered unsupported PE optional header magic {magic:#x}"
+                )))
+            }
+        };
+
+    let dir_cnt = read_u32(data, opt_hdr + dir_cnt_offset)
+        .ok_or_invalid_data(|| "failed to read PE optional header")?;
+    if dir_cnt <= IMAGE_DIRECTORY_ENTRY_DEBUG {
+        return Ok(None)
+    }
+    let debug_dir = opt_hdr + dirs_offset + IMAGE_DIRECTORY_ENTRY_DEBUG as usize * 8;
+    let debug_rva = read_u32(data, debug_dir)
+        .ok_or_invalid_data(|| "failed to read PE debug data directory")?;
+    let debug_size = read_u32(data, debug_dir + 4)
+        .ok_or_invalid_data(|| "failed to read PE debug data directory")?;
+    if debug_rva == 0 || debug_size == 0 {
+        return Ok(None)
+    }
+
+    let sections = opt_hdr + usize::from(opt_hdr_size);
+    let sections = data
+        .get(sections..sections + usize::from(section_cnt) * SECTION_HEADER_SIZE)
+        .ok_or_invalid_data(|| "failed to read PE section headers")?;
+    let debug_offset = rva_to_offset(sections, debug_rva)
+        .ok_or_invalid_data(|| "PE debug directory is not backed by file data")?;
+    let entries = data
+        .get(debug_offset..debug_offset.saturating_add(debug_size as usize))
+        .ok_or_invalid_data(|| "PE debug directory exceeds file bounds")?;
+
+    for entry in entries.chunks_exact(DEBUG_DIRECTORY_SIZE) {
+        // SANITY: `chunks_exact` guarantees sufficient data.
+        let type_ = read_u32(entry, 12).unwrap();
+        if type_ != IMAGE_DEBUG_TYPE_CODEVIEW {
+            continue
+        }
+        let size = read_u32(entry, 16).unwrap() as usize;
+        let offset = read_u32(entry, 24).unwrap() as usize;
+        let mut record = data
+            .get(offset..offset.saturating_add(size))
+            .ok_or_invalid_data(|| "PE CodeView record exceeds file bounds")?;
+
+        if record.read_slice(4) != Some(CV_SIGNATURE_RSDS) {
+            // Older formats (e.g., PDB 2.0 `NB10` records) are not
+            // supported.
+            continue
+        }
+        let guid = record
+            .read_slice(16)
+            .ok_or_invalid_data(|| "failed to read PDB GUID")?;
+        let age = record
+            .read_u32()
+            .map(u32::from_le)
+            .ok_or_invalid_data(|| "failed to read PDB age")?;
+        let path = record
+            .read_cstr()
+            .and_then(|path| bytes_to_os_str(path.to_bytes()))
+            .map(PathBuf::from)
+            .unwrap_or_default();
+
+        let info = PdbInfo {
+            // SANITY: We read exactly 16 bytes.
+            guid: <[u8; 16]>::try_from(guid).unwrap(),
+            age,
+            path,
+            _non_exhaustive: (),
+        };
+        return Ok(Some(info))
+    }
+    Ok(None)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write as _;
+
+    use tempfile::NamedTempFile;
+
+    use crate::mmap::Mmap;
+    use crate::normalize::ModuleId;
+    use crate::normalize::Normalizer;
+    use crate::Addr;
+    use crate::ErrorKind;
+    use crate::Pid;
+
+
+    /// Create a minimal PE32+ file with a debug directory containing a
+    /// single CodeView record for the provided PDB information.
+    fn pe(guid: [u8; 16], age: u32, pdb: &str) -> Vec<u8> {
+        let mut data = vec![0; 0x200];
+        // DOS header.
+        data[..2].copy_from_slice(b"MZ");
+        data[E_LFANEW_OFFSET..E_LFANEW_OFFSET + 4].copy_from_slice(&0x40u32.to_le_bytes());
+        // PE signature and COFF file header.
+        data[0x40..0x44].copy_from_slice(b"PE\0\0");
+        let coff = 0x44;
+        data[coff + 2..coff + 4].copy_from_slice(&1u16.to_le_bytes());
+        let opt_hdr_size = 112 + 16 * 8;
+        data[coff + 16..coff + 18].copy_from_slice(&(opt_hdr_size as u16).to_le_bytes());
+        // Optional header.
+        let opt_hdr = coff + COFF_HEADER_SIZE;
+        data[opt_hdr..opt_hdr + 2].copy_from_slice(&IMAGE_NT_OPTIONAL_HDR64_MAGIC.to_le_bytes());
+        data[opt_hdr + 108..opt_hdr + 112].copy_from_slice(&16u32.to_le_bytes());
+        let debug_dir = opt_hdr + 112 + 6 * 8;
+        data[debug_dir..debug_dir + 4].copy_from_slice(&0x1000u32.to_le_bytes());
+        data[debug_dir + 4..debug_dir + 8]
+            .copy_from_slice(&(DEBUG_DIRECTORY_SIZE as u32).to_le_bytes());
+        // A single section mapping RVA 0x1000 to file offset 0x200.
+        let section = opt_hdr + opt_hdr_size;
+        data[section..section + 8].copy_from_slice(b".rdata\0\0");
+        data[section + 8..section + 12].copy_from_slice(&0x100u32.to_le_bytes());
+        data[section + 12..section + 16].copy_from_slice(&0x1000u32.to_le_bytes());
+        data[section + 16..section + 20].copy_from_slice(&0x100u32.to_le_bytes());
+        data[section + 20..section + 24].copy_from_slice(&0x200u32.to_le_bytes());
+
+        // The debug directory, followed by the CodeView record.
+        let mut rdata = vec![0; DEBUG_DIRECTORY_SIZE];
+        let record_offset = 0x200 + DEBUG_DIRECTORY_SIZE;
+        let record_size = 4 + 16 + 4 + pdb.len() + 1;
+        rdata[12..16].copy_from_slice(&IMAGE_DEBUG_TYPE_CODEVIEW.to_le_bytes());
+        rdata[16..20].copy_from_slice(&(record_size as u32).to_le_bytes());
+        rdata[24..28].copy_from_slice(&(record_offset as u32).to_le_bytes());
+        let () = rdata.extend_from_slice(CV_SIGNATURE_RSDS);
+        let () = rdata.extend_from_slice(&guid);
+        let () = rdata.extend_from_slice(&age.to_le_bytes());
+        let () = rdata.extend_from_slice(pdb.as_bytes());
+        let () = rdata.push(0);
+        let () = rdata.resize(0x100, 0);
+        let () = data.extend_from_slice(&rdata);
+        data
+    }
+
+    /// Check that we can read PDB information from a PE file.
+    #[test]
+    fn pdb_info_reading() {
+        let guid = [
+            0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54,
+            0x32, 0x10,
+        ];
+        let data = pe(guid, 3, "kernel32.pdb");
+        assert!(is_pe(&data));
+
+        let info = read_pdb_info(&data).unwrap().unwrap();
+        assert_eq!(info.guid, guid);
+        assert_eq!(info.age, 3);
+        assert_eq!(info.path, PathBuf::from("kernel32.pdb"));
+    }
+
+    /// Check that we fail gracefully on invalid PE data.
+    #[test]
+    fn invalid_data() {
+        assert!(!is_pe(b"MZ"));
+        assert!(!is_pe(b"\x7fELF"));
+        let err = read_pdb_info(b"\x7fELF").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedFormat);
+
+        let data = pe([0; 16], 1, "test.pdb");
+        for len in [0x100, 0x1ff, 0x220] {
+            let err = read_pdb_info(&data[..len]).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData, "{len}");
+        }
+    }
+
+    /// Check that we report PE modules along with their PDB
+    /// information during normalization.
+    #[test]
+    fn normalization() {
+        let guid = [0x42; 16];
+        let mut data = pe(guid, 7, "test.pdb");
+        let () = data.resize(4096, 0);
+
+        let mut file = NamedTempFile::new().unwrap();
+        let () = file.write_all(&data).unwrap();
+        let mmap = Mmap::builder().open(file.path()).unwrap();
+        let addr = unsafe { mmap.as_ptr().add(0x10) } as Addr;
+
+        let normalizer = Normalizer::new();
+        let normalized = normalizer
+            .normalize_user_addrs_sorted(Pid::Slf, [addr].as_slice())
+            .unwrap();
+        assert_eq!(normalized.outputs.len(), 1);
+        let output = normalized.outputs[0];
+        assert_eq!(output.0, 0x10);
+        let meta = &normalized.meta[output.1];
+        let pe = meta.pe().unwrap();
+        assert_eq!(pe.path, file.path());
+        let pdb = pe.pdb.as_ref().unwrap();
+        assert_eq!(pdb.path, PathBuf::from("test.pdb"));
+        assert_eq!(meta.module_id(), Some(ModuleId::PeGuidAge { guid, age: 7 }));
+    }
+}
@@ -0,0 +1,858 @@
+//! Sampling of stacks using `perf_event_open(2)`.
+//!
+//! This module provides the plumbing necessary for building a sampling
+//! profiler on top of this crate: a [`Sampler`] configures a
+//! `cpu-clock` based perf event capturing call chains (and,
+//! optionally, user space registers and stack contents), reads samples
+//! from the kernel's ring buffer, and decodes them into [`Sample`]
+//! objects. The addresses contained in a sample can then be normalized
+//! or symbolized directly.
+//!
+//! ```no_run
+//! use std::thread::sleep;
+//! use std::time::Duration;
+//!
+//! use blazesym::perf::Config;
+//! use blazesym::perf::Sampler;
+//! use blazesym::symbolize::Symbolizer;
+//! use blazesym::Pid;
+//!
+//! let config = Config::default();
+//! let mut sampler = Sampler::open(&config, Some(Pid::Slf), None).unwrap();
+//! let () = sampler.enable().unwrap();
+//! let () = sleep(Duration::from_secs(1));
+//! let () = sampler.disable().unwrap();
+//!
+//! let symbolizer = Symbolizer::new();
+//! for sample in sampler.read().unwrap() {
+//!     let syms = sample.symbolize_user(&symbolizer).unwrap();
+//!     println!("{}: {syms:?}", sample.tid);
+//! }
+//! ```
+
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::FromRawFd as _;
+use std::os::unix::io::OwnedFd;
+use std::os::unix::io::RawFd;
+use std::ptr;
+use std::slice;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use crate::normalize::Normalizer;
+use crate::normalize::UserOutput;
+use crate::symbolize::Input;
+use crate::symbolize::Kernel;
+use crate::symbolize::Process;
+use crate::symbolize::Source;
+use crate::symbolize::Symbolized;
+use crate::symbolize::Symbolizer;
+#[cfg(feature = "unwind")]
+use crate::unwind::Registers;
+#[cfg(feature = "unwind")]
+use crate::unwind::StackMemory;
+use crate::util::ReadRaw as _;
+use crate::Addr;
+use crate::ErrorExt as _;
+use crate::IntoError as _;
+use crate::Pid;
+use crate::Result;
+
+
+const PERF_TYPE_SOFTWARE: u32 = 1;
+const PERF_COUNT_SW_CPU_CLOCK: u64 = 0;
+
+const PERF_SAMPLE_IP: u64 = 1 << 0;
+const PERF_SAMPLE_TID: u64 = 1 << 1;
+const PERF_SAMPLE_TIME: u64 = 1 << 2;
+const PERF_SAMPLE_CALLCHAIN: u64 = 1 << 5;
+const PERF_SAMPLE_CPU: u64 = 1 << 7;
+const PERF_SAMPLE_REGS_USER: u64 = 1 << 12;
+const PERF_SAMPLE_STACK_USER: u64 = 1 << 13;
+
+const ATTR_FLAG_DISABLED: u64 = 1 << 0;
+const ATTR_FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+const ATTR_FLAG_EXCLUDE_HV: u64 = 1 << 6;
+const ATTR_FLAG_FREQ: u64 = 1 << 10;
+const ATTR_FLAG_EXCLUDE_CALLCHAIN_KERNEL: u64 = 1 << 21;
+
+/// The size of `perf_event_attr` as of `PERF_ATTR_SIZE_VER5`.
+const PERF_ATTR_SIZE_VER5: u32 = 112;
+const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 1 << 3;
+
+const PERF_EVENT_IOC_ENABLE: libc::c_ulong = 0x2400;
+const PERF_EVENT_IOC_DISABLE: libc::c_ulong = 0x2401;
+
+const PERF_RECORD_LOST: u32 = 2;
+const PERF_RECORD_SAMPLE: u32 = 9;
+
+/// Call chain entries at or above this value are context markers.
+const PERF_CONTEXT_MAX: u64 = -4095i64 as u64;
+const PERF_CONTEXT_KERNEL: u64 = -128i64 as u64;
+const PERF_CONTEXT_USER: u64 = -512i64 as u64;
+
+/// The offset of the `data_head` member of `perf_event_mmap_page`.
+const DATA_HEAD_OFFSET: usize = 1024;
+/// The offset of the `data_tail` member of `perf_event_mmap_page`.
+const DATA_TAIL_OFFSET: usize = 1032;
+
+
+/// The attributes of a perf event, as understood by the kernel.
+#[repr(C)]
+#[derive(Debug, Default)]
+struct PerfEventAttr {
+    type_: u32,
+    size: u32,
+    config: u64,
+    sample_freq: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+    config2: u64,
+    branch_sample_type: u64,
+    sample_regs_user: u64,
+    sample_stack_user: u32,
+    clockid: i32,
+    sample_regs_intr: u64,
+    aux_watermark: u32,
+    sample_max_stack: u16,
+    _reserved: u16,
+}
+
+
+/// The `perf_regs.h` indexes of the registers relevant for unwinding.
+#[cfg(feature = "unwind")]
+struct UnwindRegs {
+    ip: u32,
+    sp: u32,
+    fp: u32,
+    lr: Option<u32>,
+}
+
+#[cfg(all(feature = "unwind", target_arch = "x86_64"))]
+const UNWIND_REGS: Option<UnwindRegs> = Some(UnwindRegs {
+    ip: 8,
+    sp: 7,
+    fp: 6,
+    lr: None,
+});
+#[cfg(all(feature = "unwind", target_arch = "aarch64"))]
+const UNWIND_REGS: Option<UnwindRegs> = Some(UnwindRegs {
+    ip: 32,
+    sp: 31,
+    fp: 29,
+    lr: Some(30),
+});
+#[cfg(all(
+    feature = "unwind",
+    not(any(target_arch = "x86_64", target_arch = "aarch64"))
+))]
+const UNWIND_REGS: Option<UnwindRegs> = None;
+
+/// The mask of user space registers (see [`Config::user_regs`]) to
+/// capture for [`Sample::registers`] to be able to provide the
+/// register state required for unwinding.
+///
+/// Zero on architectures for which unwinding is not supported.
+#[cfg(feature = "unwind")]
+pub const UNWIND_USER_REGS: u64 = match UNWIND_REGS {
+    Some(UnwindRegs { ip, sp, fp, lr }) => {
+        let lr = match lr {
+            Some(lr) => 1 << lr,
+            None => 0,
+        };
+        (1 << ip) | (1 << sp) | (1 << fp) | lr
+    }
+    None => 0,
+};
+
+
+/// Configuration of a [`Sampler`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// The sampling frequency, in Hz.
+    pub frequency: u64,
+    /// Whether to sample kernel stacks as well.
+    ///
+    /// Depending on the system's `perf_event_paranoid` setting,
+    /// sampling the kernel may require additional privileges.
+    pub kernel: bool,
+    /// The mask of user space registers to capture with each sample.
+    ///
+    /// Bits correspond to the architecture specific register indexes
+    /// of `perf_regs.h` (e.g., bit 8 being the instruction pointer on
+    /// x86-64). Captured registers are reported in ascending order of
+    /// their index in [`Sample::user_regs`].
+    pub user_regs: u64,
+    /// The number of bytes of the user space stack to capture with each
+    /// sample, rounded up to a multiple of eight.
+    ///
+    /// Captured stack contents are reported in
+    /// [`Sample::user_stack_data`] and, together with
+    /// [`Config::user_regs`], allow for unwinding stacks of programs
+    /// built without frame pointers.
+    pub user_stack: u32,
+    /// The number of pages making up the ring buffer shared with the
+    /// kernel. Has to be a power of two.
+    pub buffer_pages: usize,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            frequency: 99,
+            kernel: false,
+            user_regs: 0,
+            user_stack: 0,
+            buffer_pages: 64,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+
+/// A decoded stack sample.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sample {
+    /// The ID of the process that was sampled.
+    pub pid: u32,
+    /// The ID of the thread that was sampled.
+    pub tid: u32,
+    /// The CPU the thread was running on.
+    pub cpu: u32,
+    /// The time stamp of the sample, in nanoseconds.
+    pub time: u64,
+    /// The instruction pointer at the time of the sample.
+    pub ip: Addr,
+    /// The kernel part of the sampled call chain, innermost frame
+    /// first.
+    pub kernel_stack: Vec<Addr>,
+    /// The user space part of the sampled call chain, innermost frame
+    /// first.
+    pub user_stack: Vec<Addr>,
+    /// The captured user space registers, if requested via
+    /// [`Config::user_regs`].
+    pub user_regs: Vec<u64>,
+    /// The mask of the registers contained in [`Sample::user_regs`],
+    /// as per [`Config::user_regs`].
+    pub user_regs_mask: u64,
+    /// The captured user space stack contents, if requested via
+    /// [`Config::user_stack`].
+    pub user_stack_data: Vec<u8>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Sample {
+    /// Symbolize the kernel part of the sample's call chain, using the
+    /// running kernel's symbols.
+    pub fn symbolize_kernel<'sym>(
+        &self,
+        symbolizer: &'sym Symbolizer,
+    ) -> Result<Vec<Symbolized<'sym>>> {
+        let src = Source::Kernel(Kernel::default());
+        symbolizer.symbolize(&src, Input::AbsAddr(&self.kernel_stack))
+    }
+
+    /// Symbolize the user space part of the sample's call chain.
+    ///
+    /// The sampled process has to still be alive.
+    pub fn symbolize_user<'sym>(
+        &self,
+        symbolizer: &'sym Symbolizer,
+    ) -> Result<Vec<Symbolized<'sym>>> {
+        let src = Source::Process(Process::new(Pid::from(self.pid)));
+        symbolizer.symbolize(&src, Input::AbsAddr(&self.user_stack))
+    }
+
+    /// Normalize the user space part of the sample's call chain, e.g.,
+    /// for symbolization on another system.
+    ///
+    /// The sampled process has to still be alive.
+    pub fn normalize_user(&self, normalizer: &Normalizer) -> Result<UserOutput> {
+        normalizer.normalize_user_addrs(Pid::from(self.pid), &self.user_stack)
+    }
+
+    /// Retrieve the captured value of the user space register with
+    /// `perf_regs.h` index `idx`, if any.
+    #[cfg(feature = "unwind")]
+    fn user_reg(&self, idx: u32) -> Option<u64> {
+        let bit = 1u64.checked_shl(idx)?;
+        if self.user_regs_mask & bit == 0 {
+            return None
+        }
+        let pos = (self.user_regs_mask & (bit - 1)).count_ones() as usize;
+        self.user_regs.get(pos).copied()
+    }
+
+    /// Retrieve the captured user space register state, in a form
+    /// suitable for unwinding the sampled stack.
+    ///
+    /// `None` is returned if the instruction or stack pointer were not
+    /// captured. See [`UNWIND_USER_REGS`] for the registers to request
+    /// via [`Config::user_regs`].
+    #[cfg(feature = "unwind")]
+    pub fn registers(&self) -> Option<Registers> {
+        let UnwindRegs { ip, sp, fp, lr } = UNWIND_REGS?;
+        let mut regs = Registers::new(self.user_reg(ip)?, self.user_reg(sp)?);
+        regs.fp = self.user_reg(fp);
+        regs.lr = lr.and_then(|lr| self.user_reg(lr));
+        Some(regs)
+    }
+
+    /// Retrieve a [`Memory`][crate::unwind::Memory] implementation
+    /// reading from the captured user space stack.
+    ///
+    /// The stack is captured starting at the stack pointer, which
+    /// consequently has to be available (see [`Sample::registers`]).
+    #[cfg(feature = "unwind")]
+    pub fn stack_memory(&self) -> Option<StackMemory<'_>> {
+        let sp = self.user_reg(UNWIND_REGS?.sp)?;
+        Some(StackMemory::new(sp, &self.user_stack_data))
+    }
+}
+
+
+/// Decode a `PERF_RECORD_SAMPLE` record, excluding its header.
+fn parse_sample(mut data: &[u8], sample_type: u64, user_regs: u64) -> Option<Sample> {
+    let mut sample = Sample::default();
+    if sample_type & PERF_SAMPLE_IP != 0 {
+        sample.ip = data.read_u64()?;
+    }
+    if sample_type & PERF_SAMPLE_TID != 0 {
+        sample.pid = data.read_u32()?;
+        sample.tid = data.read_u32()?;
+    }
+    if sample_type & PERF_SAMPLE_TIME != 0 {
+        sample.time = data.read_u64()?;
+    }
+    if sample_type & PERF_SAMPLE_CPU != 0 {
+        sample.cpu = data.read_u32()?;
+        let _reserved = data.read_u32()?;
+    }
+    if sample_type & PERF_SAMPLE_CALLCHAIN != 0 {
+        let count = data.read_u64()?;
+        let mut context = PERF_CONTEXT_USER;
+        for _ in 0..count {
+            let addr = data.read_u64()?;
+            if addr >= PERF_CONTEXT_MAX {
+                context = addr;
+            } else if context == PERF_CONTEXT_KERNEL {
+                let () = sample.kernel_stack.push(addr);
+            } else if context == PERF_CONTEXT_USER {
+                let () = sample.user_stack.push(addr);
+            }
+        }
+    }
+    if sample_type & PERF_SAMPLE_REGS_USER != 0 {
+        let abi = data.read_u64()?;
+        // An ABI of zero indicates that no registers were captured,
+        // e.g., because a kernel thread was sampled.
+        if abi != 0 {
+            sample.user_regs_mask = user_regs;
+            for _ in 0..user_regs.count_ones() {
+                let () = sample.user_regs.push(data.read_u64()?);
+            }
+        }
+    }
+    if sample_type & PERF_SAMPLE_STACK_USER != 0 {
+        let size = data.read_u64()?;
+        if size != 0 {
+            let stack = data.read_slice(size as usize)?;
+            let dyn_size = data.read_u64()?;
+            sample.user_stack_data = stack[..(dyn_size as usize).min(stack.len())].to_vec();
+        }
+    }
+    Some(sample)
+}
+
+
+/// Invoke `f` for each record in the ring buffer `data` between
+/// positions `tail` and `head`, returning the new tail position.
+///
+/// Records wrapping around the end of the buffer are copied into a
+/// contiguous buffer first.
+fn for_each_record<F>(data: &[u8], mut tail: u64, head: u64, mut f: F) -> u64
+where
+    F: FnMut(u32, &[u8]),
+{
+    let size = data.len() as u64;
+    let mut buffer = Vec::new();
+
+    while tail < head {
+        let start = (tail % size) as usize;
+        // Record headers are eight byte aligned and never wrap.
+        let mut header = &data[start..];
+        let (ty, record_size) = match (header.read_u32(), header.read_u16(), header.read_u16()) {
+            (Some(ty), Some(_misc), Some(record_size)) if record_size >= 8 => {
+                (ty, record_size as usize)
+            }
+            _ => return head,
+        };
+
+        let end = start + record_size;
+        let record = if end <= data.len() {
+            &data[start..end]
+        } else {
+            let () = buffer.clear();
+            let () = buffer.extend_from_slice(&data[start..]);
+            let () = buffer.extend_from_slice(&data[..end - data.len()]);
+            &buffer
+        };
+        let () = f(ty, &record[8..]);
+        tail += record_size as u64;
+    }
+    tail
+}
+
+
+/// The ring buffer shared with the kernel.
+struct RingBuffer {
+    ptr: *mut u8,
+    len: usize,
+    page_size: usize,
+}
+
+// SAFETY: The ring buffer is only accessed through `&mut self` methods
+//         of `Sampler`.
+unsafe impl Send for RingBuffer {}
+
+impl RingBuffer {
+    fn map(fd: RawFd, pages: usize) -> io::Result<Self> {
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let len = (pages + 1) * page_size;
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error())
+        }
+        Ok(Self {
+            ptr: ptr.cast(),
+            len,
+            page_size,
+        })
+    }
+
+    /// # Safety
+    /// `offset` has to be the offset of a `u64` member of the
+    /// `perf_event_mmap_page` structure.
+    unsafe fn atomic(&self, offset: usize) -> &AtomicU64 {
+        unsafe { &*self.ptr.add(offset).cast::<AtomicU64>() }
+    }
+
+    /// Invoke `f` for all records currently contained in the buffer
+    /// and release them afterwards.
+    fn consume<F>(&mut self, f: F)
+    where
+        F: FnMut(u32, &[u8]),
+    {
+        // SAFETY: The offsets are those of `data_head` and `data_tail`.
+        let head = unsafe { self.atomic(DATA_HEAD_OFFSET) }.load(Ordering::Acquire);
+        let tail = unsafe { self.atomic(DATA_TAIL_OFFSET) }.load(Ordering::Relaxed);
+        // SAFETY: The data area spans all but the first page of the
+        //         mapping.
+        let data = unsafe {
+            slice::from_raw_parts(self.ptr.add(self.page_size), self.len - self.page_size)
+        };
+        let tail = for_each_record(data, tail, head, f);
+        let () = unsafe { self.atomic(DATA_TAIL_OFFSET) }.store(tail, Ordering::Release);
+    }
+}
+
+impl Drop for RingBuffer {
+    fn drop(&mut self) {
+        let _rc = unsafe { libc::munmap(self.ptr.cast(), self.len) };
+    }
+}
+
+
+/// A perf event sampling stacks of a process (or all processes) at a
+/// fixed frequency.
+///
+/// The sampler is created in disabled state and has to be enabled
+/// explicitly using [`Sampler::enable`]. Samples accumulate in a ring
+/// buffer and should be [`read`][Sampler::read] periodically, e.g.,
+/// whenever the sampler's file descriptor (see
+/// [`AsRawFd::as_raw_fd`]) becomes readable. Samples that did not fit
+/// into the ring buffer are [counted][Sampler::lost].
+pub struct Sampler {
+    /// The ring buffer shared with the kernel.
+    ring: RingBuffer,
+    /// The perf event's file descriptor.
+    fd: OwnedFd,
+    /// The `PERF_SAMPLE_*` flags describing the sample layout.
+    sample_type: u64,
+    /// The mask of user space registers captured.
+    user_regs: u64,
+    /// The number of samples lost so far.
+    lost: u64,
+}
+
+impl Sampler {
+    /// Open a sampler for the process identified by `pid` (all
+    /// processes if `None`) on CPU `cpu` (all CPUs if `None`).
+    ///
+    /// Note that sampling all processes requires a specific CPU to be
+    /// provided, meaning that system wide profiling requires one
+    /// sampler per CPU. A `pid` of [`Pid::Slf`] samples the calling
+    /// thread.
+    pub fn open(config: &Config, pid: Option<Pid>, cpu: Option<u32>) -> Result<Self> {
+        let pages = Some(config.buffer_pages)
+            .filter(|pages| pages.is_power_of_two())
+            .ok_or_invalid_input(|| {
+                format!(
+                    "perf ring buffer size of {} pages is not a power of two",
+                    config.buffer_pages
+                )
+            })?;
+
+        let mut sample_type = PERF_SAMPLE_IP
+            | PERF_SAMPLE_TID
+            | PERF_SAMPLE_TIME
+            | PERF_SAMPLE_CPU
+            | PERF_SAMPLE_CALLCHAIN;
+        if config.user_regs != 0 {
+            sample_type |= PERF_SAMPLE_REGS_USER;
+        }
+        if config.user_stack != 0 {
+            sample_type |= PERF_SAMPLE_STACK_USER;
+        }
+
+        let mut flags = ATTR_FLAG_DISABLED | ATTR_FLAG_FREQ | ATTR_FLAG_EXCLUDE_HV;
+        if !config.kernel {
+            flags |= ATTR_FLAG_EXCLUDE_KERNEL | ATTR_FLAG_EXCLUDE_CALLCHAIN_KERNEL;
+        }
+
+        let attr = PerfEventAttr {
+            type_: PERF_TYPE_SOFTWARE,
+            size: PERF_ATTR_SIZE_VER5,
+            config: PERF_COUNT_SW_CPU_CLOCK,
+            sample_freq: config.frequency,
+            sample_type,
+            flags,
+            sample_regs_user: config.user_regs,
+            sample_stack_user: (config.user_stack + 7) & !7,
+            ..Default::default()
+        };
+
+        let pid = match pid {
+            Some(Pid::Slf) => 0,
+            Some(Pid::Pid(pid)) => pid.get() as libc::pid_t,
+            None => -1,
+        };
+        let cpu = cpu.map(|cpu| cpu as libc::c_int).unwrap_or(-1);
+        let rc = unsafe {
+            libc::syscall(
+                libc::SYS_perf_event_open,
+                &attr as *const PerfEventAttr,
+                pid,
+                cpu,
+                -1 as libc::c_int,
+                PERF_FLAG_FD_CLOEXEC,
+            )
+        };
+        if rc < 0 {
+            return Err(io::Error::last_os_error()).context("failed to open perf event")
+        }
+        // SAFETY: The kernel returned a valid file descriptor that we
+        //         now own.
+        let fd = unsafe { OwnedFd::from_raw_fd(rc as RawFd) };
+        let ring = RingBuffer::map(fd.as_raw_fd(), pages)
+            .context("failed to map perf event ring buffer")?;
+
+        let slf = Self {
+            ring,
+            fd,
+            sample_type,
+            user_regs: config.user_regs,
+            lost: 0,
+        };
+        Ok(slf)
+    }
+
+    fn ioctl(&self, request: libc::c_ulong) -> Result<()> {
+        let rc = unsafe { libc::ioctl(self.fd.as_raw_fd(), request as _, 0) };
+        if rc < 0 {
+            return Err(io::Error::last_os_error()).context("failed to control perf event")
+        }
+        Ok(())
+    }
+
+    /// Start sampling.
+    pub fn enable(&self) -> Result<()> {
+        self.ioctl(PERF_EVENT_IOC_ENABLE)
+    }
+
+    /// Stop sampling.
+    pub fn disable(&self) -> Result<()> {
+        self.ioctl(PERF_EVENT_IOC_DISABLE)
+    }
+
+    /// Read and decode all samples currently available.
+    pub fn read(&mut self) -> Result<Vec<Sample>> {
+        let mut samples = Vec::new();
+        let mut lost = 0;
+        let mut invalid = false;
+        let sample_type = self.sample_type;
+        let user_regs = self.user_regs;
+
+        let () = self.ring.consume(|ty, mut record| match ty {
+            PERF_RECORD_SAMPLE => match parse_sample(record, sample_type, user_regs) {
+                Some(sample) => samples.push(sample),
+                None => invalid = true,
+            },
+            PERF_RECORD_LOST => {
+                let _id = record.read_u64();
+                lost += record.read_u64().unwrap_or(0);
+            }
+            _ => (),
+        });
+        self.lost += lost;
+
+        if invalid {
+            return Err(crate::Error::with_invalid_data(
+                "encountered malformed perf sample record",
+            ))
+        }
+        Ok(samples)
+    }
+
+    /// Retrieve the number of samples lost so far, because the ring
+    /// buffer was full.
+    #[inline]
+    pub fn lost(&self) -> u64 {
+        self.lost
+    }
+}
+
+impl AsRawFd for Sampler {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl Debug for Sampler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct(stringify!(Sampler))
+            .field("fd", &self.fd)
+            .field("lost", &self.lost)
+            .finish()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::hint::black_box;
+    use std::mem::size_of;
+    use std::time::Duration;
+    use std::time::Instant;
+
+
+    /// Check that our `perf_event_attr` definition has the expected
+    /// size.
+    #[test]
+    fn attr_size() {
+        assert_eq!(size_of::<PerfEventAttr>(), PERF_ATTR_SIZE_VER5 as usize);
+    }
+
+    /// Check that we can decode sample records.
+    #[test]
+    fn sample_parsing() {
+        let mut record = Vec::new();
+        let mut push = |value: u64| record.extend_from_slice(&value.to_ne_bytes());
+        // IP
+        let () = push(0x1000);
+        // PID & TID
+        let () = push(42 | (43 << 32));
+        // TIME
+        let () = push(1337);
+        // CPU
+        let () = push(3);
+        // CALLCHAIN
+        let () = push(6);
+        let () = push(PERF_CONTEXT_KERNEL);
+        let () = push(0xffffffff81000000);
+        let () = push(PERF_CONTEXT_USER);
+        let () = push(0x1000);
+        let () = push(0x2000);
+        let () = push(-2048i64 as u64);
+        // REGS_USER
+        let () = push(2);
+        let () = push(0xa);
+        let () = push(0xb);
+        // STACK_USER
+        let () = push(16);
+        let () = push(0x1122334455667788);
+        let () = push(0);
+        let () = push(12);
+
+        let sample_type = PERF_SAMPLE_IP
+            | PERF_SAMPLE_TID
+            | PERF_SAMPLE_TIME
+            | PERF_SAMPLE_CPU
+            | PERF_SAMPLE_CALLCHAIN
+            | PERF_SAMPLE_REGS_USER
+            | PERF_SAMPLE_STACK_USER;
+        let sample = parse_sample(&record, sample_type, 0b101).unwrap();
+        assert_eq!(sample.ip, 0x1000);
+        assert_eq!(sample.pid, 42);
+        assert_eq!(sample.tid, 43);
+        assert_eq!(sample.time, 1337);
+        assert_eq!(sample.cpu, 3);
+        assert_eq!(sample.kernel_stack, vec![0xffffffff81000000]);
+        assert_eq!(sample.user_stack, vec![0x1000, 0x2000]);
+        assert_eq!(sample.user_regs, vec![0xa, 0xb]);
+        assert_eq!(sample.user_regs_mask, 0b101);
+        assert_eq!(sample.user_stack_data.len(), 12);
+        assert_eq!(
+            sample.user_stack_data[..8],
+            0x1122334455667788u64.to_ne_bytes()
+        );
+
+        // Truncated records are rejected.
+        assert_eq!(
+            parse_sample(&record[..record.len() - 8], sample_type, 0b101),
+            None
+        );
+    }
+
+    /// Check that we can retrieve the register state and stack contents
+    /// required for unwinding from a sample.
+    #[cfg(all(feature = "unwind", target_arch = "x86_64"))]
+    #[test]
+    fn sample_unwind_state() {
+        use crate::unwind::Memory as _;
+
+        let mut sample = Sample {
+            // BP, SP, IP, in ascending order of their index.
+            user_regs: vec![0x7ff0, 0x7fe0, 0x1000],
+            user_regs_mask: UNWIND_USER_REGS,
+            user_stack_data: (0..16).collect(),
+            ..Default::default()
+        };
+        let regs = sample.registers().unwrap();
+        assert_eq!(regs.ip, 0x1000);
+        assert_eq!(regs.sp, 0x7fe0);
+        assert_eq!(regs.fp, Some(0x7ff0));
+        assert_eq!(regs.lr, None);
+
+        let memory = sample.stack_memory().unwrap();
+        let mut buf = [0; 2];
+        let () = memory.read(0x7fe2, &mut buf).unwrap();
+        assert_eq!(buf, [2, 3]);
+
+        // Without the instruction pointer we can't unwind.
+        sample.user_regs_mask = 0b1100_0000;
+        sample.user_regs.truncate(2);
+        assert_eq!(sample.registers(), None);
+        assert!(sample.stack_memory().is_some());
+    }
+
+    /// Check that we correctly iterate over records in the ring buffer,
+    /// including ones wrapping around its end.
+    #[test]
+    fn record_iteration() {
+        let record = |ty: u32, payload: u64| {
+            let mut record = Vec::new();
+            let () = record.extend_from_slice(&ty.to_ne_bytes());
+            let () = record.extend_from_slice(&0u16.to_ne_bytes());
+            let () = record.extend_from_slice(&16u16.to_ne_bytes());
+            let () = record.extend_from_slice(&payload.to_ne_bytes());
+            record
+        };
+
+        // A buffer of 40 bytes, containing a record at offset 16 and
+        // one at offset 32, which wraps around.
+        let mut data = vec![0; 40];
+        let () = data[16..32].copy_from_slice(&record(1, 0x42));
+        let second = record(2, 0x43);
+        let () = data[32..].copy_from_slice(&second[..8]);
+        let () = data[..8].copy_from_slice(&second[8..]);
+
+        let mut records = Vec::new();
+        let tail = for_each_record(&data, 56, 88, |ty, record| {
+            records.push((ty, record.to_vec()))
+        });
+        assert_eq!(tail, 88);
+        assert_eq!(
+            records,
+            vec![
+                (1, 0x42u64.to_ne_bytes().to_vec()),
+                (2, 0x43u64.to_ne_bytes().to_vec()),
+            ]
+        );
+    }
+
+    /// Check that we reject invalid configurations.
+    #[test]
+    fn invalid_config() {
+        let config = Config {
+            buffer_pages: 3,
+            ..Default::default()
+        };
+        let err = Sampler::open(&config, Some(Pid::Slf), None).unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::InvalidInput);
+    }
+
+    /// End-to-end test sampling the current thread.
+    #[test]
+    fn self_sampling() {
+        let config = Config {
+            frequency: 1000,
+            ..Default::default()
+        };
+        let mut sampler = match Sampler::open(&config, Some(Pid::Slf), None) {
+            Ok(sampler) => sampler,
+            // perf events may not be available in all environments
+            // (e.g., in containers).
+            Err(err) => {
+                eprintln!("skipping test: {err}");
+                return
+            }
+        };
+        assert_ne!(format!("{sampler:?}"), "");
+
+        let () = sampler.enable().unwrap();
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(100) {
+            let _x = black_box((0..1000u64).sum::<u64>());
+        }
+        let () = sampler.disable().unwrap();
+
+        let samples = sampler.read().unwrap();
+        assert!(!samples.is_empty());
+        let sample = &samples[0];
+        assert_eq!(sample.pid, std::process::id());
+        assert!(sample.kernel_stack.is_empty());
+        assert!(!sample.user_stack.is_empty());
+
+        let symbolizer = Symbolizer::new();
+        let syms = sample.symbolize_user(&symbolizer).unwrap();
+        assert_eq!(syms.len(), sample.user_stack.len());
+        // Nothing new should have been sampled.
+        assert_eq!(sampler.read().unwrap(), Vec::new());
+    }
+}
@@ -3,6 +3,7 @@ use std::fmt::Debug;
 use crate::inspect::FindAddrOpts;
 use crate::inspect::SymInfo;
 use crate::symbolize::AddrCodeInfo;
+use crate::symbolize::IntModule;
 use crate::symbolize::IntSym;
 use crate::symbolize::Reason;
 use crate::Addr;
@@ -29,4 +30,12 @@ where
     /// address will also be looked up and reported as the optional
     /// [`AddrCodeInfo::inlined`] attribute.
     fn find_code_info(&self, addr: Addr, inlined_fns: bool) -> Result<Option<AddrCodeInfo<'_>>>;
+    /// Retrieve information about the module containing the given
+    /// address, such as its path, build ID, and the address' file
+    /// offset.
+    ///
+    /// The default implementation reports no module information.
+    fn module_info(&self, _addr: Addr) -> Result<IntModule<'_>> {
+        Ok(IntModule::default())
+    }
 }
@@ -0,0 +1,584 @@
+//! Per-thread constraints and hooks for long running operations.
+//!
+//! Operations can be cancelled, either explicitly or once a time budget
+//! is exhausted, may report their progress, and can be constrained in
+//! the amount of (potentially untrusted) data they process.
+
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::panic::catch_unwind;
+use std::panic::resume_unwind;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::sync_channel;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::Error;
+use crate::ErrorExt as _;
+use crate::Result;
+
+
+/// The constraints in effect for operations on a thread.
+#[derive(Clone, Debug, Default)]
+struct Scope {
+    /// The cancellation token in effect, if any.
+    token: Option<CancellationToken>,
+    /// The point in time by which operations have to be done, if any.
+    deadline: Option<Instant>,
+    /// The callback to report progress to, if any.
+    progress: Option<ProgressCallback>,
+    /// The parsing limits in effect, if any.
+    limits: Option<Limits>,
+    /// Whether work that could be deferred (such as the indexing of
+    /// debug information) has to be performed right away.
+    blocking: bool,
+}
+
+thread_local! {
+    /// The constraints in effect for operations on the current thread.
+    static SCOPE: RefCell<Scope> = RefCell::new(Scope::default());
+}
+
+
+/// A token for cancelling a long running operation.
+///
+/// A token is handed to an operation supporting cancellation (such as
+/// [`Symbolizer::symbolize_cancellable`][crate::symbolize::Symbolizer::symbolize_cancellable]).
+/// Cancelling it, potentially from a different thread, causes the
+/// operation to abort at the next opportunity, reporting an error of
+/// kind [`ErrorKind::Interrupted`][crate::ErrorKind::Interrupted].
+///
+/// Clones of a token share the same cancellation state.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    /// Whether cancellation was requested.
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a new, not yet cancelled, `CancellationToken`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation of all operations using this token (or a
+    /// clone of it).
+    #[inline]
+    pub fn cancel(&self) {
+        let () = self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Check whether cancellation was requested.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+
+/// Progress information about a long running operation.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Progress {
+    /// DWARF debug information is being indexed.
+    ///
+    /// Indexing happens once per file, when it is first used for
+    /// symbolization.
+    DwarfIndex {
+        /// The number of compilation units indexed so far.
+        units: usize,
+        /// The number of bytes of `.debug_info` parsed so far.
+        bytes: u64,
+        /// The total size of `.debug_info`, in bytes.
+        total_bytes: u64,
+    },
+}
+
+impl Progress {
+    /// Retrieve the progress made, in percent.
+    pub fn percent(&self) -> f64 {
+        match self {
+            Self::DwarfIndex {
+                bytes, total_bytes, ..
+            } => {
+                if *total_bytes == 0 {
+                    100.0
+                } else {
+                    (*bytes as f64 / *total_bytes as f64 * 100.0).min(100.0)
+                }
+            }
+        }
+    }
+}
+
+
+/// Limits imposed on the parsing of potentially untrusted input files.
+///
+/// Limits are put in effect via
+/// [`symbolize::Builder::set_limits`][crate::symbolize::Builder::set_limits].
+/// Input exceeding them is rejected with an error of kind
+/// [`ErrorKind::InvalidData`][crate::ErrorKind::InvalidData].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum size of an ELF section or of Gsym data, in bytes.
+    pub max_section_size: u64,
+    /// The maximum number of DWARF compilation units in a file.
+    pub max_units: usize,
+    /// The maximum nesting depth of DWARF inlined function
+    /// information. Inlined functions nested more deeply are ignored.
+    pub max_depth: usize,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_section_size: 1 << 30,
+            max_units: 1 << 20,
+            max_depth: 128,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+
+/// A callback invoked with [`Progress`] information.
+#[derive(Clone)]
+pub(crate) struct ProgressCallback(Arc<dyn Fn(&Progress) + Send + Sync>);
+
+impl ProgressCallback {
+    /// Create a new `ProgressCallback` wrapping `f`.
+    pub(crate) fn new<F>(f: F) -> Self
+    where
+        F: Fn(&Progress) + Send + Sync + 'static,
+    {
+        Self(Arc::new(f))
+    }
+}
+
+impl Debug for ProgressCallback {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_tuple(stringify!(ProgressCallback))
+            .field(&Arc::as_ptr(&self.0))
+            .finish()
+    }
+}
+
+
+/// Restores the previously active scope on drop.
+struct Restore(Option<Scope>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        if let Some(prev) = self.0.take() {
+            let () = SCOPE.with(|scope| *scope.borrow_mut() = prev);
+        }
+    }
+}
+
+
+/// Run `f` with the scope adjusted by `adjust` being in effect for the
+/// current thread.
+fn with_scope<A, F, T>(adjust: A, f: F) -> T
+where
+    A: FnOnce(&mut Scope),
+    F: FnOnce() -> T,
+{
+    let prev = SCOPE.with(|scope| {
+        let mut scope = scope.borrow_mut();
+        let prev = scope.clone();
+        let () = adjust(&mut scope);
+        prev
+    });
+    let _restore = Restore(Some(prev));
+    f()
+}
+
+
+/// A snapshot of the scope in effect for a thread, for putting it into
+/// effect on another one.
+#[derive(Clone, Debug)]
+pub(crate) struct Snapshot(Scope);
+
+impl Snapshot {
+    /// Run `f` with the captured scope being in effect for the current
+    /// thread.
+    pub(crate) fn enter<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        with_scope(|current| *current = self.0.clone(), f)
+    }
+}
+
+/// Capture the scope in effect for the current thread.
+pub(crate) fn snapshot() -> Snapshot {
+    Snapshot(SCOPE.with(|scope| scope.borrow().clone()))
+}
+
+
+/// Run `f` with `token` being in effect for the current thread.
+pub(crate) fn with_token<F, T>(token: &CancellationToken, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    with_scope(|scope| scope.token = Some(token.clone()), f)
+}
+
+
+/// Run `f` with `deadline` being in effect for the current thread.
+///
+/// If an earlier deadline is already in effect, it is kept.
+pub(crate) fn with_deadline<F, T>(deadline: Instant, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    with_scope(
+        |scope| {
+            scope.deadline = Some(
+                scope
+                    .deadline
+                    .map(|prev| prev.min(deadline))
+                    .unwrap_or(deadline),
+            )
+        },
+        f,
+    )
+}
+
+
+/// Run `f` with `progress` being in effect for the current thread.
+pub(crate) fn with_progress<F, T>(progress: &ProgressCallback, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    with_scope(|scope| scope.progress = Some(progress.clone()), f)
+}
+
+
+/// Run `f` with `limits` being in effect for the current thread.
+///
+/// Should `f` panic, the panic is caught and reported as an error, if
+/// unwinding is enabled.
+pub(crate) fn with_limits<F, T>(limits: &Limits, f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T>,
+{
+    with_scope(
+        |scope| scope.limits = Some(*limits),
+        || {
+            catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+                let msg = panic
+                    .downcast_ref::<&str>()
+                    .copied()
+                    .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                    .unwrap_or("unknown cause");
+                Err(Error::with_invalid_data(format!(
+                    "panicked while processing input: {msg}"
+                )))
+            })
+        },
+    )
+}
+
+
+/// Run `f` with work that could be deferred having to be performed
+/// right away.
+pub(crate) fn with_blocking<F, T>(f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    with_scope(|scope| scope.blocking = true, f)
+}
+
+
+/// Check whether work that could be deferred has to be performed
+/// right away on the current thread.
+#[inline]
+pub(crate) fn blocking() -> bool {
+    SCOPE.with(|scope| scope.borrow().blocking)
+}
+
+
+/// Retrieve the parsing limits in effect for the current thread, if
+/// any.
+#[inline]
+pub(crate) fn limits() -> Option<Limits> {
+    SCOPE.with(|scope| scope.borrow().limits)
+}
+
+
+/// Report the progress created by `progress` to the callback in effect
+/// for the current thread, if any.
+#[inline]
+pub(crate) fn report<F>(progress: F)
+where
+    F: FnOnce() -> Progress,
+{
+    // Clone the callback so that it can use the scope itself.
+    let callback = SCOPE.with(|scope| scope.borrow().progress.clone());
+    if let Some(ProgressCallback(callback)) = callback {
+        let () = callback(&progress());
+    }
+}
+
+
+/// Check whether the deadline in effect for the current thread, if
+/// any, has passed.
+#[inline]
+pub(crate) fn timed_out() -> bool {
+    SCOPE.with(|scope| {
+        scope
+            .borrow()
+            .deadline
+            .map(|deadline| Instant::now() >= deadline)
+            .unwrap_or(false)
+    })
+}
+
+
+/// Retrieve the time remaining until the deadline in effect for the
+/// current thread, if any.
+#[inline]
+pub(crate) fn remaining() -> Option<Duration> {
+    SCOPE.with(|scope| {
+        scope
+            .borrow()
+            .deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    })
+}
+
+
+/// Check whether the operation in progress on the current thread got
+/// cancelled or ran out of time and report an error if that is the
+/// case.
+#[inline]
+pub(crate) fn check() -> Result<()> {
+    let cancelled = SCOPE.with(|scope| {
+        scope
+            .borrow()
+            .token
+            .as_ref()
+            .map(CancellationToken::is_cancelled)
+            .unwrap_or(false)
+    });
+
+    if cancelled {
+        Err(Error::with_interrupted("operation was cancelled"))
+    } else if timed_out() {
+        Err(Error::with_timed_out("operation exceeded its time budget"))
+    } else {
+        Ok(())
+    }
+}
+
+
+/// Run the potentially blocking operation `f` (such as the opening of
+/// a file) without overrunning the deadline in effect for the current
+/// thread, if any.
+///
+/// With a deadline in effect, `f` is run on a separate thread, inside
+/// the current scope. Should the deadline pass before `f` finished,
+/// that thread is abandoned and an error of kind
+/// [`ErrorKind::TimedOut`][crate::ErrorKind::TimedOut] is reported. The
+/// thread keeps running in the background until `f` returns.
+pub(crate) fn run_blocking<F, T>(f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let remaining = match remaining() {
+        Some(remaining) => remaining,
+        None => return f(),
+    };
+    let () = check()?;
+
+    let scope = snapshot();
+    let (sender, receiver) = sync_channel(1);
+    let _handle = thread::Builder::new()
+        .name("blazesym-io".to_string())
+        .spawn(move || {
+            let result = scope.enter(|| catch_unwind(AssertUnwindSafe(f)));
+            // The receiving side may have given up already.
+            let _result = sender.send(result);
+        })
+        .context("failed to spawn thread for blocking operation")?;
+
+    match receiver.recv_timeout(remaining) {
+        Ok(Ok(result)) => result,
+        Ok(Err(panic)) => resume_unwind(panic),
+        Err(RecvTimeoutError::Timeout) => Err(Error::with_timed_out(
+            "blocking operation exceeded its time budget",
+        )),
+        Err(RecvTimeoutError::Disconnected) => {
+            unreachable!("thread for blocking operation exited without result")
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread::sleep;
+
+    use crate::ErrorKind;
+
+
+    /// Check that cancellation is only reported within the scope of a
+    /// token.
+    #[test]
+    fn scoped_cancellation() {
+        let token = CancellationToken::new();
+        let () = with_token(&token, check).unwrap();
+
+        let () = token.clone().cancel();
+        assert!(token.is_cancelled());
+
+        let err = with_token(&token, check).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Interrupted);
+
+        // Outside of the scope, no token is in effect.
+        let () = check().unwrap();
+
+        // Nested scopes restore the outer token.
+        let outer = CancellationToken::new();
+        let () = with_token(&outer, || {
+            let _err = with_token(&token, check).unwrap_err();
+            check()
+        })
+        .unwrap();
+    }
+
+    /// Check that a snapshot of a scope can be put into effect on
+    /// another thread.
+    #[test]
+    fn snapshot_transfer() {
+        let token = CancellationToken::new();
+        let () = token.cancel();
+        let deadline = Instant::now() + Duration::from_secs(3600);
+
+        let snapshot = with_token(&token, || with_deadline(deadline, snapshot));
+        let () = thread::scope(|s| {
+            s.spawn(|| {
+                let () = check().unwrap();
+                let err = snapshot.enter(|| {
+                    assert!(remaining().unwrap() > Duration::from_secs(3000));
+                    check()
+                });
+                assert_eq!(err.unwrap_err().kind(), ErrorKind::Interrupted);
+                assert_eq!(remaining(), None);
+            });
+        });
+    }
+
+    /// Check that deadlines are honored.
+    #[test]
+    fn deadline() {
+        let now = Instant::now();
+        assert_eq!(remaining(), None);
+        let () = with_deadline(now + Duration::from_secs(3600), || {
+            assert!(!timed_out());
+            assert!(remaining().unwrap() > Duration::from_secs(3000));
+            check()
+        })
+        .unwrap();
+
+        let err = with_deadline(now, || {
+            assert!(timed_out());
+            assert_eq!(remaining(), Some(Duration::ZERO));
+            // The earlier deadline stays in effect.
+            let () = with_deadline(now + Duration::from_secs(3600), || assert!(timed_out()));
+            check()
+        })
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert!(!timed_out());
+    }
+
+    /// Check that progress is reported to the callback in effect.
+    #[test]
+    fn progress_reporting() {
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reports_ = Arc::clone(&reports);
+        let callback =
+            ProgressCallback::new(move |progress| reports_.lock().unwrap().push(progress.clone()));
+        assert_ne!(format!("{callback:?}"), "");
+
+        let progress = Progress::DwarfIndex {
+            units: 1,
+            bytes: 25,
+            total_bytes: 100,
+        };
+        assert_eq!(progress.percent(), 25.0);
+
+        // Nothing should be reported without a callback.
+        let () = report(|| panic!());
+        let () = with_progress(&callback, || report(|| progress.clone()));
+        assert_eq!(*reports.lock().unwrap(), vec![progress]);
+    }
+
+    /// Check that limits are only in effect within their scope and
+    /// that panics are reported as errors.
+    #[test]
+    fn scoped_limits() {
+        let strict = Limits {
+            max_units: 42,
+            ..Default::default()
+        };
+        assert_eq!(limits(), None);
+
+        let max_units = with_limits(&strict, || Ok(limits().unwrap().max_units)).unwrap();
+        assert_eq!(max_units, 42);
+        assert_eq!(limits(), None);
+
+        let err = with_limits(&strict, || -> Result<()> { panic!("oh no") }).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("oh no"), "{err}");
+        assert_eq!(limits(), None);
+    }
+    /// Check that blocking operations do not overrun the deadline in
+    /// effect.
+    #[test]
+    fn blocking_operation() {
+        // Without a deadline the operation runs to completion on the
+        // current thread.
+        let id = thread::current().id();
+        let result = run_blocking(move || Ok(thread::current().id() == id)).unwrap();
+        assert!(result);
+
+        let deadline = Instant::now() + Duration::from_secs(3600);
+        let value = with_deadline(deadline, || {
+            run_blocking(|| {
+                // The scope is inherited.
+                assert!(remaining().is_some());
+                Ok(42)
+            })
+        })
+        .unwrap();
+        assert_eq!(value, 42);
+
+        let start = Instant::now();
+        let deadline = start + Duration::from_millis(100);
+        let err = with_deadline(deadline, || {
+            run_blocking(|| {
+                let () = sleep(Duration::from_secs(10));
+                Ok(())
+            })
+        })
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,276 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::ffi::OsStr;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::mem::replace;
+use std::mem::take;
+use std::path::Path;
+use std::slice;
+use std::str;
+
+use crate::util::bytes_to_os_str;
+use crate::util::os_str_to_bytes;
+
+use super::CodeInfo;
+
+
+/// The size of the first chunk allocated by an arena, in bytes.
+const MIN_CHUNK_SIZE: usize = 4096;
+
+
+/// The memory chunks making up an [`Arena`].
+#[derive(Default)]
+struct Chunks {
+    /// The chunk currently being allocated from.
+    ///
+    /// Allocations never exceed the chunk's capacity, meaning that its
+    /// buffer never gets reallocated and references to data in it stay
+    /// valid.
+    current: Vec<u8>,
+    /// Chunks that are full.
+    full: Vec<Vec<u8>>,
+}
+
+
+/// An arena for allocating the data of symbolization results.
+///
+/// When symbolizing via
+/// [`Symbolizer::symbolize_in`][crate::symbolize::Symbolizer::symbolize_in],
+/// symbol names and source code paths that would otherwise be heap
+/// allocated individually for each result are placed in the arena
+/// instead. Memory allocated this way is only released once the arena
+/// is [reset][Arena::reset] or dropped, all at once, which makes arenas
+/// a good fit for high-throughput pipelines symbolizing batches of
+/// addresses in a loop.
+#[derive(Default)]
+pub struct Arena {
+    chunks: RefCell<Chunks>,
+}
+
+impl Arena {
+    /// Create a new, empty `Arena`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new `Arena` with room for `capacity` bytes before
+    /// further memory has to be allocated.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let chunks = Chunks {
+            current: Vec::with_capacity(capacity),
+            full: Vec::new(),
+        };
+
+        Self {
+            chunks: RefCell::new(chunks),
+        }
+    }
+
+    /// Release all data allocated in the arena.
+    ///
+    /// The largest chunk of memory is retained and reused for
+    /// subsequent allocations.
+    pub fn reset(&mut self) {
+        let chunks = self.chunks.get_mut();
+        let largest = chunks
+            .full
+            .drain(..)
+            .chain(Some(take(&mut chunks.current)))
+            .max_by_key(Vec::capacity);
+
+        if let Some(mut largest) = largest {
+            let () = largest.clear();
+            chunks.current = largest;
+        }
+    }
+
+    /// Retrieve the number of bytes allocated in the arena.
+    pub fn allocated_bytes(&self) -> usize {
+        let chunks = self.chunks.borrow();
+        chunks.current.len() + chunks.full.iter().map(Vec::len).sum::<usize>()
+    }
+
+    /// Copy `bytes` into the arena.
+    fn alloc_bytes(&self, bytes: &[u8]) -> &[u8] {
+        let mut chunks = self.chunks.borrow_mut();
+        let chunks = &mut *chunks;
+
+        if chunks.current.capacity() - chunks.current.len() < bytes.len() {
+            let size = (chunks.current.capacity() * 2)
+                .max(MIN_CHUNK_SIZE)
+                .max(bytes.len());
+            let full = replace(&mut chunks.current, Vec::with_capacity(size));
+            if full.capacity() > 0 {
+                let () = chunks.full.push(full);
+            }
+        }
+
+        let start = chunks.current.len();
+        let () = chunks.current.extend_from_slice(bytes);
+        // SAFETY: We just copied `bytes.len()` bytes to `start`. The
+        //         chunk's buffer is never reallocated, because we never
+        //         exceed its capacity, and it is only released when the
+        //         arena is reset (which requires an exclusive
+        //         reference) or dropped.
+        unsafe { slice::from_raw_parts(chunks.current.as_ptr().add(start), bytes.len()) }
+    }
+
+    /// Copy `s` into the arena.
+    pub(crate) fn alloc_str(&self, s: &str) -> &str {
+        let bytes = self.alloc_bytes(s.as_bytes());
+        // SAFETY: The bytes were copied from a valid `str`.
+        unsafe { str::from_utf8_unchecked(bytes) }
+    }
+
+    /// Copy `s` into the arena, if it can be represented as raw bytes
+    /// on the current platform.
+    pub(crate) fn alloc_os_str(&self, s: &OsStr) -> Option<&OsStr> {
+        let bytes = os_str_to_bytes(s)?;
+        bytes_to_os_str(self.alloc_bytes(bytes))
+    }
+}
+
+impl Debug for Arena {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct(stringify!(Arena))
+            .field("allocated_bytes", &self.allocated_bytes())
+            .finish()
+    }
+}
+
+
+/// The location where data of symbolization results are allocated.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Alloc<'a> {
+    /// Data are allocated on the heap, individually.
+    Heap,
+    /// Data are allocated in the provided arena.
+    Arena(&'a Arena),
+}
+
+impl<'a> Alloc<'a> {
+    /// Create an owned copy of `s`.
+    pub(crate) fn str(self, s: &str) -> Cow<'a, str> {
+        match self {
+            Self::Heap => Cow::Owned(s.to_string()),
+            Self::Arena(arena) => Cow::Borrowed(arena.alloc_str(s)),
+        }
+    }
+
+    /// Convert `s` into an owned string, allocated as per `self`.
+    pub(crate) fn string(self, s: String) -> Cow<'a, str> {
+        match self {
+            Self::Heap => Cow::Owned(s),
+            Self::Arena(arena) => Cow::Borrowed(arena.alloc_str(&s)),
+        }
+    }
+
+    /// Create an owned copy of `s`.
+    pub(crate) fn os_str(self, s: &OsStr) -> Cow<'a, OsStr> {
+        match self {
+            Self::Heap => Cow::Owned(s.to_os_string()),
+            Self::Arena(arena) => arena
+                .alloc_os_str(s)
+                .map(Cow::Borrowed)
+                .unwrap_or_else(|| Cow::Owned(s.to_os_string())),
+        }
+    }
+
+    /// Create an owned copy of `bytes`.
+    pub(crate) fn bytes(self, bytes: &[u8]) -> Cow<'a, [u8]> {
+        match self {
+            Self::Heap => Cow::Owned(bytes.to_vec()),
+            Self::Arena(arena) => Cow::Borrowed(arena.alloc_bytes(bytes)),
+        }
+    }
+
+    /// Create an owned copy of `path`.
+    pub(crate) fn path(self, path: &Path) -> Cow<'a, Path> {
+        match self.os_str(path.as_os_str()) {
+            Cow::Borrowed(path) => Cow::Borrowed(Path::new(path)),
+            Cow::Owned(path) => Cow::Owned(path.into()),
+        }
+    }
+
+    /// Create an owned copy of `info`.
+    pub(crate) fn code_info(self, info: &CodeInfo<'_>) -> CodeInfo<'a> {
+        CodeInfo {
+            dir: info.dir.as_ref().map(|dir| self.path(dir)),
+            file: self.os_str(&info.file),
+            line: info.line,
+            column: info.column,
+            context: info.context.clone(),
+            _non_exhaustive: (),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Check that data allocated in an arena stay valid as the arena
+    /// grows.
+    #[test]
+    fn allocation() {
+        let arena = Arena::with_capacity(8);
+        assert_ne!(format!("{arena:?}"), "");
+
+        let strs = (0..1000)
+            .map(|i| arena.alloc_str(&format!("string-{i}")))
+            .collect::<Vec<_>>();
+        for (i, s) in strs.iter().enumerate() {
+            assert_eq!(*s, format!("string-{i}"));
+        }
+        assert_eq!(
+            arena.allocated_bytes(),
+            strs.iter().map(|s| s.len()).sum::<usize>()
+        );
+
+        let s = arena.alloc_os_str(OsStr::new("/usr/lib")).unwrap();
+        assert_eq!(s, OsStr::new("/usr/lib"));
+    }
+
+    /// Check that resetting an arena releases all data.
+    #[test]
+    fn reset() {
+        let mut arena = Arena::new();
+        let _s = arena.alloc_str(&"x".repeat(3 * MIN_CHUNK_SIZE));
+        let _s = arena.alloc_str("foobar");
+        assert_ne!(arena.allocated_bytes(), 0);
+
+        let () = arena.reset();
+        assert_eq!(arena.allocated_bytes(), 0);
+        assert!(arena.chunks.borrow().current.capacity() >= 3 * MIN_CHUNK_SIZE);
+
+        let s = arena.alloc_str("foobar");
+        assert_eq!(s, "foobar");
+    }
+
+    /// Check that `Alloc` creates copies as expected.
+    #[test]
+    fn alloc() {
+        let arena = Arena::new();
+        let s = Alloc::Arena(&arena).str("foo");
+        assert!(matches!(s, Cow::Borrowed("foo")));
+        let s = Alloc::Heap.str("foo");
+        assert!(matches!(s, Cow::Owned(..)));
+
+        let info = CodeInfo {
+            dir: Some(Cow::Borrowed(Path::new("/tmp"))),
+            file: Cow::Borrowed(OsStr::new("foo.c")),
+            line: Some(42),
+            column: None,
+            context: None,
+            _non_exhaustive: (),
+        };
+        let copy = Alloc::Arena(&arena).code_info(&info);
+        assert_eq!(copy, info);
+        assert!(matches!(copy.file, Cow::Borrowed(..)));
+        assert!(matches!(copy.dir, Some(Cow::Borrowed(..))));
+    }
+}
@@ -0,0 +1,124 @@
+use std::ops::AddAssign;
+
+
+/// Limits on the data cached by a [`Symbolizer`][crate::symbolize::Symbolizer].
+///
+/// Limits are enforced by evicting the least recently used files (and
+/// the data parsed from them) after each symbolization request and when
+/// calling
+/// [`Symbolizer::trim_cache`][crate::symbolize::Symbolizer::trim_cache].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CacheLimits {
+    /// The maximum combined size of the cached files, in bytes.
+    pub max_bytes: Option<u64>,
+    /// The maximum number of cached files.
+    pub max_entries: Option<usize>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl CacheLimits {
+    /// Check whether a cache with `entries` entries of `bytes` combined
+    /// size exceeds the limits.
+    pub(crate) fn exceeded(&self, entries: usize, bytes: u64) -> bool {
+        let Self {
+            max_bytes,
+            max_entries,
+            _non_exhaustive: (),
+        } = self;
+
+        max_bytes.map(|max| bytes > max).unwrap_or(false)
+            || max_entries.map(|max| entries > max).unwrap_or(false)
+    }
+}
+
+
+/// Statistics about the data cached by a
+/// [`Symbolizer`][crate::symbolize::Symbolizer].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    /// The number of times a file was found in the cache.
+    pub hits: u64,
+    /// The number of times a file was not found in the cache and had
+    /// to be loaded.
+    pub misses: u64,
+    /// The number of files evicted from the cache.
+    pub evictions: u64,
+    /// The number of files currently cached.
+    pub entries: usize,
+    /// The combined size of the files currently cached, in bytes.
+    pub bytes: u64,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl AddAssign<&CacheStats> for CacheStats {
+    fn add_assign(&mut self, other: &CacheStats) {
+        let CacheStats {
+            hits,
+            misses,
+            evictions,
+            entries,
+            bytes,
+            _non_exhaustive: (),
+        } = other;
+
+        self.hits += hits;
+        self.misses += misses;
+        self.evictions += evictions;
+        self.entries += entries;
+        self.bytes += bytes;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Check that we detect exceeded limits correctly.
+    #[test]
+    fn limit_checking() {
+        let limits = CacheLimits::default();
+        assert!(!limits.exceeded(usize::MAX, u64::MAX));
+
+        let limits = CacheLimits {
+            max_bytes: Some(100),
+            max_entries: Some(2),
+            ..Default::default()
+        };
+        assert!(!limits.exceeded(2, 100));
+        assert!(limits.exceeded(3, 100));
+        assert!(limits.exceeded(2, 101));
+    }
+
+    /// Check that we can accumulate statistics.
+    #[test]
+    fn stats_accumulation() {
+        let mut stats = CacheStats::default();
+        let other = CacheStats {
+            hits: 1,
+            misses: 2,
+            evictions: 3,
+            entries: 4,
+            bytes: 5,
+            ..Default::default()
+        };
+        stats += &other;
+        stats += &other;
+        assert_eq!(
+            stats,
+            CacheStats {
+                hits: 2,
+                misses: 4,
+                evictions: 6,
+                entries: 8,
+                bytes: 10,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+
+/// Configuration of the source code context reported alongside source
+/// code location information.
+///
+/// When enabled (see
+/// [`Builder::set_source_context`][crate::symbolize::Builder::set_source_context]),
+/// the [`CodeInfo`][crate::symbolize::CodeInfo] objects reported for
+/// symbols and inlined functions carry the lines of source code
+/// surrounding the symbolized line, provided the source file can be
+/// found.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceContext {
+    /// The number of lines to report before and after the symbolized
+    /// line.
+    pub lines: u32,
+    /// Prefixes of source file paths to replace, along with their
+    /// replacements.
+    ///
+    /// Debug information references source files by the paths they
+    /// had at build time. If sources reside elsewhere on the system
+    /// symbolizing addresses (e.g., in a checkout of the project), the
+    /// build directory can be remapped to said location. The first
+    /// matching prefix is used. Paths of source files not covered by
+    /// any remapping are used as-is, but resolved relative to the root
+    /// directory of the mount namespace of the process being
+    /// symbolized, if any.
+    pub path_remaps: Vec<(PathBuf, PathBuf)>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl SourceContext {
+    /// Map the source file `path` to the path under which it is
+    /// expected to be accessible, if any.
+    pub(crate) fn resolve(&self, path: &Path, root: Option<&Path>) -> Option<PathBuf> {
+        let remapped = self
+            .path_remaps
+            .iter()
+            .find_map(|(from, to)| path.strip_prefix(from).ok().map(|suffix| to.join(suffix)));
+
+        let path = match (remapped, root) {
+            (Some(path), _) => path,
+            (None, Some(root)) if path.is_absolute() => {
+                // `join` would discard `root` for absolute paths.
+                let mut rooted = root.to_path_buf().into_os_string();
+                let () = rooted.push(path.as_os_str());
+                PathBuf::from(rooted)
+            }
+            (None, _) => path.to_path_buf(),
+        };
+
+        // Relative paths would be interpreted relative to our working
+        // directory, which is unlikely to be what the debug
+        // information refers to.
+        path.is_absolute().then_some(path)
+    }
+
+    /// Extract the lines surrounding line `line` (1-based) from the
+    /// source file contents `data`.
+    pub(crate) fn extract(&self, data: &[u8], line: u32) -> Option<SourceLines> {
+        let line = usize::try_from(line).ok()?.checked_sub(1)?;
+        let count = usize::try_from(self.lines).unwrap_or(usize::MAX);
+        let start = line.saturating_sub(count);
+        let end = line.saturating_add(count).saturating_add(1);
+
+        // A trailing newline does not start another line.
+        let data = data.strip_suffix(b"\n").unwrap_or(data);
+        let lines = data
+            .split(|b| *b == b'\n')
+            .skip(start)
+            .take(end - start)
+            .map(|line| {
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                String::from_utf8_lossy(line).into_owned()
+            })
+            .collect::<Vec<_>>();
+
+        if start + lines.len() <= line {
+            // The line is not part of the file, which likely has
+            // changed since it got compiled.
+            return None
+        }
+
+        let lines = SourceLines {
+            // SANITY: `start` is derived from a `u32` line number.
+            start_line: u32::try_from(start + 1).unwrap(),
+            lines,
+            _non_exhaustive: (),
+        };
+        Some(lines)
+    }
+}
+
+impl Default for SourceContext {
+    fn default() -> Self {
+        Self {
+            lines: 3,
+            path_remaps: Vec::new(),
+            _non_exhaustive: (),
+        }
+    }
+}
+
+
+/// Lines of source code surrounding a symbolized source code location.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceLines {
+    /// The line number of the first line in [`lines`][Self::lines].
+    pub start_line: u32,
+    /// The lines of source code, without line terminators.
+    ///
+    /// Invalid UTF-8 sequences are replaced with
+    /// `U+FFFD REPLACEMENT CHARACTER`.
+    pub lines: Vec<String>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Check that we resolve source file paths as expected.
+    #[test]
+    fn path_resolution() {
+        let context = SourceContext {
+            path_remaps: vec![
+                (PathBuf::from("/build"), PathBuf::from("/checkout")),
+                (PathBuf::from("src"), PathBuf::from("/checkout/src")),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            context.resolve(Path::new("/build/src/main.c"), None),
+            Some(PathBuf::from("/checkout/src/main.c"))
+        );
+        assert_eq!(
+            context.resolve(Path::new("src/main.c"), None),
+            Some(PathBuf::from("/checkout/src/main.c"))
+        );
+        // Remapped paths are not relative to the root directory.
+        assert_eq!(
+            context.resolve(Path::new("/build/main.c"), Some(Path::new("/proc/42/root"))),
+            Some(PathBuf::from("/checkout/main.c"))
+        );
+        assert_eq!(
+            context.resolve(
+                Path::new("/usr/include/stdio.h"),
+                Some(Path::new("/proc/42/root"))
+            ),
+            Some(PathBuf::from("/proc/42/root/usr/include/stdio.h"))
+        );
+        assert_eq!(context.resolve(Path::new("main.c"), None), None);
+    }
+
+    /// Check that we extract the correct lines from a source file.
+    #[test]
+    fn line_extraction() {
+        let data = b"1\n2\r\n3\n4\n5\n";
+        let context = SourceContext {
+            lines: 1,
+            ..Default::default()
+        };
+
+        let lines = context.extract(data, 3).unwrap();
+        assert_eq!(lines.start_line, 2);
+        assert_eq!(lines.lines, ["2", "3", "4"]);
+
+        let lines = context.extract(data, 1).unwrap();
+        assert_eq!(lines.start_line, 1);
+        assert_eq!(lines.lines, ["1", "2"]);
+
+        let lines = context.extract(data, 5).unwrap();
+        assert_eq!(lines.start_line, 4);
+        assert_eq!(lines.lines, ["4", "5"]);
+
+        assert_eq!(context.extract(data, 6), None);
+        assert_eq!(context.extract(data, 0), None);
+
+        let context = SourceContext {
+            lines: 0,
+            ..Default::default()
+        };
+        let lines = context.extract(b"\xff\xfe", 1).unwrap();
+        assert_eq!(lines.lines, ["\u{fffd}\u{fffd}"]);
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::VecDeque;
+use std::fmt::Debug;
+
+use crate::Addr;
+
+use super::Sym;
+
+
+/// An extension point for substituting higher level frames (for
+/// example, those of an interpreted language) for native frames.
+///
+/// An enricher claims native frames based on their address and the
+/// symbol they got symbolized to, e.g., the interpreter's evaluation
+/// loop. Claimed frames are then replaced with frames supplied by the
+/// enricher, which typically got retrieved by the caller by reading the
+/// interpreter's state from the target process' memory.
+///
+/// Enrichers are used by [`Symbolizer::symbolize_enriched`].
+///
+/// [`Symbolizer::symbolize_enriched`]: super::Symbolizer::symbolize_enriched
+pub trait FrameEnricher: Debug {
+    /// Check whether the enricher claims the native frame at `addr`,
+    /// which got symbolized to `sym`.
+    fn claims(&self, addr: Addr, sym: &Sym<'_>) -> bool;
+
+    /// Retrieve the frames to substitute for a claimed native frame.
+    ///
+    /// This method is invoked once for each claimed frame, in stack
+    /// order (i.e., innermost frame first). The returned frames are
+    /// expected in the same order. An empty list removes the native
+    /// frame from the stack, while `None` keeps it as is.
+    fn substitute(&mut self, addr: Addr, sym: &Sym<'_>) -> Option<Vec<Sym<'static>>>;
+}
+
+
+/// The names of CPython functions evaluating Python frames.
+const CPYTHON_EVAL_FNS: [&str; 3] = [
+    "_PyEval_EvalFrameDefault",
+    "_PyEval_EvalFrame",
+    "PyEval_EvalFrameEx",
+];
+
+
+/// A [`FrameEnricher`] merging Python frames into a symbolized native
+/// stack of a CPython interpreter.
+///
+/// The Python frames are provided by the caller (e.g., as read from
+/// the interpreter's memory, in the manner of `py-spy`), grouped by the
+/// invocation of the interpreter's evaluation loop that executes them
+/// and ordered innermost first. Each native evaluation loop frame (such
+/// as `_PyEval_EvalFrameDefault`) is then replaced with the next group
+/// of Python frames.
+#[derive(Clone, Debug, Default)]
+pub struct PythonFrames {
+    /// The Python frames that remain to be substituted.
+    frames: VecDeque<Vec<Sym<'static>>>,
+}
+
+impl PythonFrames {
+    /// Create a new [`PythonFrames`] object from groups of Python
+    /// frames, one per native evaluation loop frame, innermost first.
+    pub fn new<I>(frames: I) -> Self
+    where
+        I: IntoIterator<Item = Vec<Sym<'static>>>,
+    {
+        Self {
+            frames: frames.into_iter().collect(),
+        }
+    }
+}
+
+impl FrameEnricher for PythonFrames {
+    fn claims(&self, _addr: Addr, sym: &Sym<'_>) -> bool {
+        CPYTHON_EVAL_FNS.contains(&sym.name.as_ref())
+    }
+
+    fn substitute(&mut self, _addr: Addr, _sym: &Sym<'_>) -> Option<Vec<Sym<'static>>> {
+        // If we run out of Python frames we keep reporting the native
+        // ones.
+        self.frames.pop_front()
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::Error;
+
+use super::Breakpad;
+use super::Gsym;
+use super::Reason;
+use super::Symbolized;
+
+#[cfg(doc)]
+use super::Symbolizer;
+
+
+/// A single tier of a [`Fallback`] chain.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Tier<'dat> {
+    /// Gsym data or a Gsym file.
+    Gsym(Gsym<'dat>),
+    /// The DWARF debug information contained in the ELF file at the
+    /// given path.
+    ///
+    /// Using this tier requires the `dwarf` feature.
+    Dwarf(PathBuf),
+    /// The symbol table of the ELF file at the given path.
+    Symtab(PathBuf),
+    /// The perf map at the given path, as emitted by JIT compilers.
+    PerfMap(PathBuf),
+    /// The linker map file at the given path.
+    LinkerMap(PathBuf),
+    /// Breakpad symbol file data or a Breakpad symbol file.
+    ///
+    /// Using this tier requires the `breakpad` feature.
+    Breakpad(Breakpad<'dat>),
+}
+
+
+/// An ordered list of symbolization sources, to be consulted one after
+/// the other until an address could be symbolized.
+///
+/// A fallback chain is used with [`Symbolizer::symbolize_fallback`].
+/// Contrary to the implicit ordering used for a
+/// [`Source`][super::Source] (e.g., DWARF before the ELF symbol table),
+/// the chain makes the sources as well as their order explicit. For
+/// example, a chain could consult a Gsym file first, then fall back to
+/// DWARF, then to the ELF symbol table, and finally to a perf map.
+///
+/// Inputs are treated as virtual offsets
+/// ([`Input::VirtOffset`][super::Input::VirtOffset]) by all tiers.
+#[derive(Clone, Debug)]
+pub struct Fallback<'dat> {
+    /// The tiers of the chain, in the order in which they should be
+    /// consulted.
+    pub tiers: Vec<Tier<'dat>>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl<'dat> Fallback<'dat> {
+    /// Create a new [`Fallback`] chain consulting the provided tiers
+    /// in order.
+    #[inline]
+    pub fn new(tiers: Vec<Tier<'dat>>) -> Self {
+        Self {
+            tiers,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+
+/// The reason why a tier of a [`Fallback`] chain was skipped.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Skipped {
+    /// The tier was consulted but could not symbolize the address.
+    Unknown(Reason),
+    /// The tier could not be used, e.g., because the file backing it
+    /// does not exist.
+    Failed(Arc<Error>),
+}
+
+
+/// The result of symbolizing an address using a [`Fallback`] chain.
+#[derive(Clone, Debug)]
+pub struct FallbackSymbolized<'src> {
+    /// The symbolization result.
+    ///
+    /// If no tier could symbolize the address, the reason reported by
+    /// the last tier consulted is used, if any.
+    pub symbolized: Symbolized<'src>,
+    /// The index of the tier that produced
+    /// [`symbolized`][Self::symbolized], if any.
+    pub tier: Option<usize>,
+    /// The indices of the tiers skipped before `tier`, along with the
+    /// reason for skipping each.
+    pub skipped: Vec<(usize, Skipped)>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::symbolize::BreakpadFile;
+    use crate::symbolize::GsymFile;
+
+
+    /// Exercise the `Debug` representation of various types.
+    #[test]
+    fn debug_repr() {
+        let fallback = Fallback::new(vec![
+            Tier::Gsym(Gsym::File(GsymFile::new("/tmp/test.gsym"))),
+            Tier::Dwarf(PathBuf::from("/tmp/test.bin")),
+            Tier::Symtab(PathBuf::from("/tmp/test.bin")),
+            Tier::PerfMap(PathBuf::from("/tmp/perf-1.map")),
+            Tier::LinkerMap(PathBuf::from("/tmp/test.map")),
+            Tier::Breakpad(Breakpad::File(BreakpadFile::new("/tmp/test.sym"))),
+        ]);
+        assert_ne!(format!("{fallback:?}"), "");
+
+        let result = FallbackSymbolized {
+            symbolized: Symbolized::Unknown(Reason::UnknownAddr),
+            tier: None,
+            skipped: vec![(0, Skipped::Unknown(Reason::UnknownAddr))],
+            _non_exhaustive: (),
+        };
+        assert_ne!(format!("{result:?}"), "");
+    }
+}
@@ -0,0 +1,504 @@
+use std::fs::File;
+use std::path::Path;
+
+use crate::inspect::FindAddrOpts;
+use crate::ksym::KSymResolver;
+use crate::ksym::KALLSYMS;
+use crate::Addr;
+use crate::ErrorExt as _;
+use crate::Result;
+use crate::SymResolver as _;
+
+
+/// A single frame of a textual kernel stack trace.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct KernelFrame {
+    /// The address of the frame, if known.
+    ///
+    /// Addresses are only contained in some trace formats (or in
+    /// hashed form, in which case they are not reported). See
+    /// [`KernelStack::resolve_addrs`] for a way to infer them from the
+    /// symbol information.
+    pub addr: Option<Addr>,
+    /// The name of the function the frame belongs to, if known.
+    pub name: Option<String>,
+    /// The offset of the frame's address from the start of the
+    /// function.
+    pub offset: u64,
+    /// The size of the function, if known.
+    pub size: Option<u64>,
+    /// The name of the kernel module containing the function, if it
+    /// does not belong to the kernel image proper.
+    pub module: Option<String>,
+    /// Whether the kernel considers the frame reliable.
+    ///
+    /// Frames that the kernel's unwinder merely guessed are marked
+    /// with a `?` in stack traces.
+    pub reliable: bool,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+
+/// A kernel stack trace, as parsed by [`parse_kernel_stacks`].
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct KernelStack {
+    /// The frames of the stack, innermost first.
+    pub frames: Vec<KernelFrame>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl KernelStack {
+    /// Infer the addresses of frames that only carry symbol
+    /// information, using the kernel symbols in the kallsyms file at
+    /// `kallsyms` (`/proc/kallsyms` if `None`).
+    ///
+    /// Frames whose function is not found are left untouched.
+    pub fn resolve_addrs(&mut self, kallsyms: Option<&Path>) -> Result<()> {
+        if self
+            .frames
+            .iter()
+            .all(|frame| frame.addr.is_some() || frame.name.is_none())
+        {
+            return Ok(())
+        }
+
+        let path = kallsyms.unwrap_or_else(|| Path::new(KALLSYMS));
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        let resolver = KSymResolver::load_from_reader(file, path.to_path_buf())?;
+        let opts = FindAddrOpts::default();
+
+        for frame in self.frames.iter_mut().filter(|frame| frame.addr.is_none()) {
+            if let Some(name) = &frame.name {
+                let addr = resolver
+                    .find_addr(name, &opts)?
+                    .into_iter()
+                    .find(|sym| sym.name == name.as_str())
+                    .map(|sym| sym.addr);
+                frame.addr = addr.map(|addr| addr + frame.offset);
+            }
+        }
+        Ok(())
+    }
+
+    /// Retrieve the addresses of all frames that have one, for
+    /// symbolization with a [`Kernel`][crate::symbolize::Kernel]
+    /// source.
+    pub fn addrs(&self) -> Vec<Addr> {
+        self.frames.iter().filter_map(|frame| frame.addr).collect()
+    }
+}
+
+
+/// Parse a hexadecimal number, with optional `0x` prefix.
+fn parse_hex(s: &str) -> Option<u64> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    u64::from_str_radix(s, 16).ok()
+}
+
+/// Parse a `name+0xoffset/0xsize` symbol reference, with offset and
+/// size being optional if `bare` is set.
+fn parse_sym(s: &str, bare: bool, frame: &mut KernelFrame) -> Option<()> {
+    let (name, offset, size) = match s.split_once('+') {
+        Some((name, rest)) => match rest.split_once('/') {
+            Some((offset, size)) => (name, parse_hex(offset)?, Some(parse_hex(size)?)),
+            None if bare => (name, parse_hex(rest)?, None),
+            None => return None,
+        },
+        None if bare => (s, 0, None),
+        None => return None,
+    };
+
+    if name.is_empty() {
+        return None
+    }
+    frame.name = Some(name.to_string());
+    frame.offset = offset;
+    frame.size = size;
+    Some(())
+}
+
+/// Parse the body of a frame line, i.e., what follows any line
+/// prefixes.
+///
+/// If `bare` is set, the line is known to describe a frame and a bare
+/// address or symbol name is accepted.
+fn parse_frame(line: &str, bare: bool) -> Option<KernelFrame> {
+    let mut frame = KernelFrame {
+        reliable: true,
+        ..Default::default()
+    };
+
+    let mut s = line.trim();
+    if let Some(rest) = s.strip_prefix("? ") {
+        frame.reliable = false;
+        s = rest.trim_start();
+    }
+
+    let mut bare = bare;
+    // Older kernels print the address before the symbol, e.g.:
+    // [<ffffffff81234567>] foo+0x10/0x20
+    // If address hashing is in effect, the "address" is something
+    // along the lines of `(____ptrval____)`.
+    if let Some(rest) = s.strip_prefix("[<") {
+        let (addr, rest) = rest.split_once(">]")?;
+        frame.addr = parse_hex(addr);
+        s = rest.trim_start();
+        if let Some(rest) = s.strip_prefix("? ") {
+            frame.reliable = false;
+            s = rest.trim_start();
+        }
+        bare = true;
+    }
+
+    let (sym, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+    if sym.is_empty() {
+        return frame.addr.map(|_| frame)
+    }
+
+    if sym.starts_with("0x") {
+        if !bare {
+            return None
+        }
+        frame.addr = Some(parse_hex(sym)?);
+    } else {
+        let () = parse_sym(sym, bare, &mut frame)?;
+    }
+
+    let rest = rest.trim();
+    if let Some(module) = rest
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        // The module name may be followed by the module's build ID.
+        frame.module = module.split_whitespace().next().map(str::to_string);
+    } else if !rest.is_empty() && !bare {
+        return None
+    }
+    Some(frame)
+}
+
+
+/// The kind of a line in a textual stack trace.
+#[derive(Debug, PartialEq)]
+enum Line {
+    /// A header introducing a new stack trace.
+    Header,
+    /// A frame derived from register contents, such as the faulting
+    /// instruction pointer in a `WARN` splat.
+    Register(KernelFrame),
+    /// A regular stack frame.
+    Frame(KernelFrame),
+    /// Any other line.
+    Other,
+}
+
+/// Strip a kernel log line's prefixes, such as time stamps and caller
+/// IDs (`[   12.345678] [  T123] ...`).
+fn strip_log_prefix(mut line: &str) -> &str {
+    loop {
+        line = line.trim_start();
+        match line.strip_prefix('[') {
+            Some(rest) if !rest.starts_with('<') => match rest.split_once(']') {
+                Some((_prefix, rest)) => line = rest,
+                None => break line,
+            },
+            _ => break line,
+        }
+    }
+}
+
+fn classify(line: &str) -> Line {
+    let line = strip_log_prefix(line);
+
+    if line.contains("<stack trace>") || line.starts_with("Call Trace:") {
+        return Line::Header
+    }
+
+    // ftrace's `stacktrace` option, e.g.:
+    //  => foo+0x10/0x20
+    if let Some(rest) = line.strip_prefix("=>") {
+        return parse_frame(rest, true).map_or(Line::Other, Line::Frame)
+    }
+
+    // x86 prints the instruction pointer as:
+    // RIP: 0010:foo+0x12/0x30 [mod]
+    // arm64 as:
+    // pc : foo+0x12/0x30 [mod]
+    if let Some(rest) = line
+        .strip_prefix("RIP:")
+        .or_else(|| line.strip_prefix("pc :"))
+    {
+        let rest = rest.trim_start();
+        // Skip the code segment selector, if any.
+        let rest = match rest.split_once(':') {
+            Some((segment, rest)) if segment.chars().all(|c| c.is_ascii_hexdigit()) => rest,
+            _ => rest,
+        };
+        return parse_frame(rest, true).map_or(Line::Other, Line::Register)
+    }
+
+    parse_frame(line, false).map_or(Line::Other, Line::Frame)
+}
+
+
+/// Parse textual kernel stack traces.
+///
+/// The following formats are understood:
+/// - the output of ftrace's `stacktrace` option (as found in
+///   `/sys/kernel/debug/tracing/trace`)
+/// - stack traces as part of kernel log messages, e.g., as printed by
+///   `WARN` or `BUG` and contained in `dmesg` output
+///
+/// Lines not describing stack frames are ignored. A new stack is
+/// started for each header line introducing one (e.g., `Call Trace:`).
+/// The instruction pointer reported in a register dump is considered
+/// the innermost frame of the stack trace that follows.
+///
+/// The resulting addresses can be symbolized using a
+/// [`Kernel`][crate::symbolize::Kernel] source. Frames without address
+/// may be resolved using [`KernelStack::resolve_addrs`] first.
+///
+/// ```
+/// use blazesym::symbolize::parse_kernel_stacks;
+///
+/// let trace = r#"
+///            <...>-1234  [001] .....  123.456789: <stack trace>
+///  => do_sys_openat2+0x1e/0x30
+///  => __x64_sys_openat+0x20/0x30
+///  => 0xffffffffc0123456
+/// "#;
+/// let stacks = parse_kernel_stacks(trace);
+/// assert_eq!(stacks.len(), 1);
+/// let frames = &stacks[0].frames;
+/// assert_eq!(frames[0].name.as_deref(), Some("do_sys_openat2"));
+/// assert_eq!(frames[0].offset, 0x1e);
+/// assert_eq!(frames[2].addr, Some(0xffffffffc0123456));
+/// ```
+pub fn parse_kernel_stacks(text: &str) -> Vec<KernelStack> {
+    let mut stacks = Vec::new();
+    let mut stack = KernelStack::default();
+    // Whether the current stack only contains frames derived from
+    // register contents.
+    let mut registers_only = false;
+
+    for line in text.lines() {
+        match classify(line) {
+            Line::Header => {
+                if !registers_only && !stack.frames.is_empty() {
+                    let () = stacks.push(stack);
+                    stack = KernelStack::default();
+                }
+                registers_only = false;
+            }
+            Line::Register(frame) => {
+                if !registers_only && !stack.frames.is_empty() {
+                    let () = stacks.push(stack);
+                    stack = KernelStack::default();
+                }
+                let () = stack.frames.push(frame);
+                registers_only = true;
+            }
+            Line::Frame(frame) => {
+                let () = stack.frames.push(frame);
+                registers_only = false;
+            }
+            Line::Other => (),
+        }
+    }
+
+    if !stack.frames.is_empty() {
+        let () = stacks.push(stack);
+    }
+    stacks
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write as _;
+
+    use tempfile::NamedTempFile;
+
+
+    fn frame(name: &str, offset: u64, size: u64) -> KernelFrame {
+        KernelFrame {
+            name: Some(name.to_string()),
+            offset,
+            size: Some(size),
+            reliable: true,
+            ..Default::default()
+        }
+    }
+
+
+    /// Exercise the `Debug` representation of various types.
+    #[test]
+    fn debug_repr() {
+        let stack = KernelStack {
+            frames: vec![frame("foo", 0x10, 0x20)],
+            ..Default::default()
+        };
+        assert_ne!(format!("{stack:?}"), "");
+    }
+
+    /// Check that we can parse the output of ftrace's `stacktrace`
+    /// option.
+    #[test]
+    fn ftrace_parsing() {
+        let trace = r#"
+# tracer: nop
+#
+           <...>-1234  [001] .....  123.456789: <stack trace>
+ => do_sys_openat2+0x1e/0x30
+ => __x64_sys_openat
+ => 0xffffffffc0123456
+ => foo_mod_fn+0x8/0x40 [foo_mod]
+            bash-42    [000] .....  124.000000: <stack trace>
+ => entry_SYSCALL_64_after_hwframe+0x6e/0xd8
+"#;
+        let stacks = parse_kernel_stacks(trace);
+        assert_eq!(stacks.len(), 2);
+        assert_eq!(
+            stacks[0].frames,
+            vec![
+                frame("do_sys_openat2", 0x1e, 0x30),
+                KernelFrame {
+                    name: Some("__x64_sys_openat".to_string()),
+                    reliable: true,
+                    ..Default::default()
+                },
+                KernelFrame {
+                    addr: Some(0xffffffffc0123456),
+                    reliable: true,
+                    ..Default::default()
+                },
+                KernelFrame {
+                    module: Some("foo_mod".to_string()),
+                    ..frame("foo_mod_fn", 0x8, 0x40)
+                },
+            ]
+        );
+        assert_eq!(
+            stacks[1].frames,
+            vec![frame("entry_SYSCALL_64_after_hwframe", 0x6e, 0xd8)]
+        );
+        assert_eq!(stacks[0].addrs(), vec![0xffffffffc0123456]);
+    }
+
+    /// Check that we can parse a stack trace as part of a `WARN`
+    /// splat.
+    #[test]
+    fn warn_parsing() {
+        let trace = r#"
+[   12.345678] ------------[ cut here ]------------
+[   12.345679] WARNING: CPU: 1 PID: 42 at drivers/foo/foo.c:123 foo_probe+0x12/0x30 [foo]
+[   12.345680] Modules linked in: foo(+) bar
+[   12.345681] RIP: 0010:foo_probe+0x12/0x30 [foo 0123456789abcdef]
+[   12.345682] Code: 48 89 e5 0f 0b 5d c3 cc cc cc cc
+[   12.345683] RSP: 0018:ffffb2e5c0b1fb70 EFLAGS: 00010246
+[   12.345684] Call Trace:
+[   12.345685]  <TASK>
+[   12.345686]  ? __warn+0x81/0x130
+[   12.345687]  do_one_initcall+0x41/0x200
+[   12.345688]  </TASK>
+[   12.345689] ---[ end trace 0000000000000000 ]---
+"#;
+        let stacks = parse_kernel_stacks(trace);
+        assert_eq!(stacks.len(), 1);
+        assert_eq!(
+            stacks[0].frames,
+            vec![
+                KernelFrame {
+                    module: Some("foo".to_string()),
+                    ..frame("foo_probe", 0x12, 0x30)
+                },
+                KernelFrame {
+                    reliable: false,
+                    ..frame("__warn", 0x81, 0x130)
+                },
+                frame("do_one_initcall", 0x41, 0x200),
+            ]
+        );
+    }
+
+    /// Check that we can parse stack traces in the format used by older
+    /// kernels, which includes addresses.
+    #[test]
+    fn legacy_parsing() {
+        let trace = r#"
+Call Trace:
+ [<ffffffff8100a1b2>] dump_stack+0x19/0x1b
+ [<ffffffff8100a1c3>] ? warn_slowpath_common+0x61/0x80
+ [<(____ptrval____)>] do_one_initcall+0x41/0x200
+"#;
+        let stacks = parse_kernel_stacks(trace);
+        assert_eq!(stacks.len(), 1);
+        assert_eq!(
+            stacks[0].frames,
+            vec![
+                KernelFrame {
+                    addr: Some(0xffffffff8100a1b2),
+                    ..frame("dump_stack", 0x19, 0x1b)
+                },
+                KernelFrame {
+                    addr: Some(0xffffffff8100a1c3),
+                    reliable: false,
+                    ..frame("warn_slowpath_common", 0x61, 0x80)
+                },
+                frame("do_one_initcall", 0x41, 0x200),
+            ]
+        );
+        assert_eq!(
+            stacks[0].addrs(),
+            vec![0xffffffff8100a1b2, 0xffffffff8100a1c3]
+        );
+    }
+
+    /// Make sure that we do not mistake arbitrary lines for frames.
+    #[test]
+    fn non_frame_lines() {
+        let text = r#"
+Hello world
+Modules linked in: foo bar
+0xffffffff81000000
+foo+0x10
+foo+0x10/0x20 trailing garbage
++0x10/0x20
+"#;
+        assert_eq!(parse_kernel_stacks(text), Vec::new());
+    }
+
+    /// Check that we can infer addresses of frames using kallsyms.
+    #[test]
+    fn addr_resolution() {
+        let mut kallsyms = NamedTempFile::new().unwrap();
+        let () = writeln!(
+            kallsyms,
+            "ffffffff81000000 T do_one_initcall\nffffffff81000200 T do_one_initcall_other"
+        )
+        .unwrap();
+
+        let mut stack = KernelStack {
+            frames: vec![
+                frame("do_one_initcall", 0x41, 0x200),
+                frame("unknown_fn", 0x1, 0x2),
+                KernelFrame {
+                    addr: Some(0x42),
+                    ..frame("do_one_initcall_other", 0x1, 0x2)
+                },
+            ],
+            ..Default::default()
+        };
+        let () = stack.resolve_addrs(Some(kallsyms.path())).unwrap();
+        assert_eq!(stack.frames[0].addr, Some(0xffffffff81000041));
+        assert_eq!(stack.frames[1].addr, None);
+        assert_eq!(stack.frames[2].addr, Some(0x42));
+    }
+}
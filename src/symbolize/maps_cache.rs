@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::maps::MapsEntry;
+use crate::Addr;
+use crate::Pid;
+
+
+/// The parsed proc maps entries of a process.
+#[derive(Debug)]
+struct Cached {
+    /// The entries, in address order.
+    entries: Arc<[MapsEntry]>,
+    /// The time at which the entries were parsed.
+    time: Instant,
+}
+
+
+/// Check whether `addr` is covered by any of the (sorted) `entries`.
+fn covers(entries: &[MapsEntry], addr: Addr) -> bool {
+    let idx = entries.partition_point(|entry| entry.range.start <= addr);
+    idx.checked_sub(1)
+        .map(|idx| entries[idx].range.contains(&addr))
+        .unwrap_or(false)
+}
+
+
+/// A cache of parsed proc maps files, keyed by process.
+///
+/// Entries are refreshed once they got older than the configured time
+/// to live, once they got invalidated explicitly, or once they fail
+/// to cover an address of interest (e.g., because the process mapped
+/// a shared object in the meantime).
+#[derive(Debug)]
+pub(crate) struct MapsCache {
+    /// The time after which cached entries are considered stale.
+    ttl: Duration,
+    entries: Mutex<HashMap<Pid, Cached>>,
+}
+
+impl MapsCache {
+    /// Create a new, empty `MapsCache` with entries that stay valid for
+    /// `ttl`.
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Lock the cache's entries.
+    fn entries(&self) -> MutexGuard<'_, HashMap<Pid, Cached>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Retrieve the cached proc maps entries of process `pid`, provided
+    /// they are still valid and cover all of `addrs`.
+    pub(crate) fn get(&self, pid: Pid, addrs: &[Addr]) -> Option<Arc<[MapsEntry]>> {
+        let entries = self.entries();
+        let cached = entries.get(&pid)?;
+        if cached.time.elapsed() >= self.ttl {
+            return None
+        }
+        if !addrs.iter().all(|addr| covers(&cached.entries, *addr)) {
+            return None
+        }
+        Some(Arc::clone(&cached.entries))
+    }
+
+    /// Cache the proc maps entries of process `pid`.
+    ///
+    /// `entries` are expected to be sorted by address, as they are in
+    /// proc maps files.
+    pub(crate) fn insert(&self, pid: Pid, entries: Vec<MapsEntry>) -> Arc<[MapsEntry]> {
+        let entries = Arc::<[MapsEntry]>::from(entries);
+        let cached = Cached {
+            entries: Arc::clone(&entries),
+            time: Instant::now(),
+        };
+
+        let mut map = self.entries();
+        // Get rid of stale entries, so that we do not accumulate those
+        // of processes that exited long ago.
+        let () = map.retain(|_pid, cached| cached.time.elapsed() < self.ttl);
+        let _prev = map.insert(pid, cached);
+        entries
+    }
+
+    /// Invalidate the cached proc maps entries of process `pid`, if any.
+    pub(crate) fn invalidate(&self, pid: Pid) {
+        let _cached = self.entries().remove(&pid);
+    }
+
+    /// Remove all cached entries.
+    pub(crate) fn clear(&self) {
+        let () = self.entries().clear();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread::sleep;
+
+
+    fn entry(start: Addr, end: Addr) -> MapsEntry {
+        MapsEntry {
+            range: start..end,
+            mode: 0b1010,
+            offset: 0,
+            path_name: None,
+        }
+    }
+
+    /// Check that cached entries are reported as long as they are
+    /// valid and cover the addresses of interest.
+    #[test]
+    fn retrieval() {
+        let cache = MapsCache::new(Duration::MAX);
+        let pid = Pid::from(42);
+        assert!(cache.get(pid, &[]).is_none());
+
+        let entries = cache.insert(pid, vec![entry(0x1000, 0x2000), entry(0x3000, 0x4000)]);
+        assert_eq!(entries.len(), 2);
+
+        let cached = cache.get(pid, &[]).unwrap();
+        assert!(Arc::ptr_eq(&cached, &entries));
+        let cached = cache.get(pid, &[0x1000, 0x1fff, 0x3800]).unwrap();
+        assert!(Arc::ptr_eq(&cached, &entries));
+        // Entries are per process.
+        assert!(cache.get(Pid::from(43), &[]).is_none());
+
+        // Addresses not covered by any entry cause a miss.
+        for addr in [0x0, 0xfff, 0x2000, 0x2800, 0x4000] {
+            assert!(cache.get(pid, &[0x1000, addr]).is_none(), "{addr:#x}");
+        }
+    }
+
+    /// Check that entries are invalidated properly.
+    #[test]
+    fn invalidation() {
+        let cache = MapsCache::new(Duration::MAX);
+        let pid1 = Pid::from(42);
+        let pid2 = Pid::from(43);
+        let _entries = cache.insert(pid1, vec![entry(0x1000, 0x2000)]);
+        let _entries = cache.insert(pid2, vec![entry(0x1000, 0x2000)]);
+
+        let () = cache.invalidate(pid1);
+        assert!(cache.get(pid1, &[]).is_none());
+        assert!(cache.get(pid2, &[]).is_some());
+
+        let () = cache.clear();
+        assert!(cache.get(pid2, &[]).is_none());
+    }
+
+    /// Check that entries expire after the configured time to live.
+    #[test]
+    fn expiry() {
+        let cache = MapsCache::new(Duration::from_millis(10));
+        let pid = Pid::from(42);
+        let _entries = cache.insert(pid, vec![entry(0x1000, 0x2000)]);
+        let () = sleep(Duration::from_millis(20));
+        assert!(cache.get(pid, &[]).is_none());
+
+        let cache = MapsCache::new(Duration::ZERO);
+        let _entries = cache.insert(pid, vec![entry(0x1000, 0x2000)]);
+        assert!(cache.get(pid, &[]).is_none());
+    }
+}
@@ -96,8 +96,20 @@
 //! }
 //! ```
 
+mod arena;
+mod cache;
+mod context;
+mod enrich;
+mod fallback;
+#[cfg(feature = "kernel")]
+mod kstack;
+mod maps_cache;
+mod normalized;
+mod prefetch;
+mod result_cache;
 mod source;
 mod symbolizer;
+mod symdir;
 
 use std::borrow::Cow;
 use std::ffi::OsStr;
@@ -106,17 +118,52 @@ use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
 use std::path::Path;
 
+pub use crate::mmap::MmapAdvice;
+pub use arena::Arena;
+pub use cache::CacheLimits;
+pub use cache::CacheStats;
+pub use context::SourceContext;
+pub use context::SourceLines;
+pub use enrich::FrameEnricher;
+pub use enrich::PythonFrames;
+pub use fallback::Fallback;
+pub use fallback::FallbackSymbolized;
+pub use fallback::Skipped;
+pub use fallback::Tier;
+#[cfg(feature = "kernel")]
+pub use kstack::parse_kernel_stacks;
+#[cfg(feature = "kernel")]
+pub use kstack::KernelFrame;
+#[cfg(feature = "kernel")]
+pub use kstack::KernelStack;
+pub use normalized::Batch;
+pub use normalized::Locator;
+pub use prefetch::Prefetch;
 pub use source::Apk;
+pub use source::Breakpad;
+pub use source::BreakpadData;
+pub use source::BreakpadFile;
+pub use source::Coredump;
 pub use source::Elf;
 pub use source::Gsym;
 pub use source::GsymData;
 pub use source::GsymFile;
+pub use source::Jvm;
 pub use source::Kernel;
+pub use source::LinkerMap;
+pub use source::Node;
 pub use source::Process;
+pub use source::Snapshot;
 pub use source::Source;
 pub use symbolizer::Builder;
 pub use symbolizer::Symbolizer;
+pub use symdir::SymbolDir;
 
+pub(crate) use arena::Alloc;
+#[cfg(feature = "unwind")]
+pub(crate) use symbolizer::elf_offset_to_address;
+
+use crate::demangle;
 use crate::Addr;
 
 
@@ -155,6 +202,19 @@ impl<T> Input<T> {
             Self::AbsAddr(x) | Self::VirtOffset(x) | Self::FileOffset(x) => x,
         }
     }
+
+    /// Map the inner payload, retaining the variant.
+    #[inline]
+    pub(crate) fn map<U, F>(self, f: F) -> Input<U>
+    where
+        F: FnOnce(T) -> U,
+    {
+        match self {
+            Self::AbsAddr(x) => Input::AbsAddr(f(x)),
+            Self::VirtOffset(x) => Input::VirtOffset(f(x)),
+            Self::FileOffset(x) => Input::FileOffset(f(x)),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -204,6 +264,12 @@ pub struct CodeInfo<'src> {
     /// The column number of the symbolized instruction in the source
     /// code.
     pub column: Option<u16>,
+    /// The source code surrounding [`line`][Self::line].
+    ///
+    /// This information is only reported if enabled via
+    /// [`Builder::set_source_context`] and if the source file could be
+    /// found.
+    pub context: Option<SourceLines>,
     /// The struct is non-exhaustive and open to extension.
     #[doc(hidden)]
     pub _non_exhaustive: (),
@@ -231,6 +297,7 @@ impl CodeInfo<'_> {
             file: Cow::Owned(self.file.to_os_string()),
             line: self.line,
             column: self.column,
+            context: self.context.clone(),
             _non_exhaustive: (),
         }
     }
@@ -249,17 +316,140 @@ pub struct InlinedFn<'src> {
     pub _non_exhaustive: (),
 }
 
+impl InlinedFn<'_> {
+    /// Convert this object into one with all references converted into
+    /// guaranteed owned (i.e., heap allocated) members.
+    pub fn to_owned(&self) -> InlinedFn<'static> {
+        InlinedFn {
+            name: Cow::Owned(self.name.to_string()),
+            code_info: self.code_info.as_ref().map(CodeInfo::to_owned),
+            _non_exhaustive: (),
+        }
+    }
+}
+
+
+/// Options controlling the level of detail of demangled symbol names.
+///
+/// These options only have an effect if demangling is enabled (see
+/// [`Builder::enable_demangling`]) and are set via
+/// [`Builder::set_demangle_opts`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DemangleOpts {
+    /// Whether to include template (or generic) arguments.
+    pub template_args: bool,
+    /// Whether to include function parameters.
+    pub params: bool,
+    /// Whether to include function return types.
+    pub return_type: bool,
+    /// Whether to include the hash suffix of Rust symbol names (e.g.,
+    /// `::h0123456789abcdef` for the legacy mangling scheme).
+    pub hash: bool,
+    /// The maximum length of a demangled name, in characters.
+    ///
+    /// Names exceeding this length are first stripped of template
+    /// arguments and then of parameters and return types. If the name
+    /// is still too long after that, its leading part is cut off and
+    /// replaced with `...`.
+    pub max_len: Option<usize>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Default for DemangleOpts {
+    fn default() -> Self {
+        Self {
+            template_args: true,
+            params: true,
+            return_type: true,
+            hash: false,
+            max_len: None,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+
+/// The way in which files of a process residing in a different mount
+/// namespace are accessed.
+///
+/// This setting is configured via [`Builder::set_mount_ns_mode`] and
+/// only relevant for [`Source::Process`] based symbolization on Linux.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MountNsMode {
+    /// Access files via `/proc/<pid>/root/`, leaving the mount
+    /// namespace of the calling process untouched.
+    ///
+    /// This mode is safe to use from multi-threaded programs, such as
+    /// profilers symbolizing containerized processes. Note, however,
+    /// that absolute symbolic links encountered underneath
+    /// `/proc/<pid>/root/` are resolved against the root directory of
+    /// the calling process, not that of the target process. Files
+    /// referenced through such links may hence not be found or, worse,
+    /// a file with the same path on the host may be used instead.
+    #[default]
+    ProcRoot,
+    /// Temporarily enter the mount namespace of the process using
+    /// `setns(2)`.
+    ///
+    /// Doing so affects the calling process as a whole (including its
+    /// current working directory, which is restored afterwards) and
+    /// fails if the process is multi-threaded. In return, paths are
+    /// resolved exactly as the target process sees them.
+    Enter,
+}
+
 
 /// The source code language from which a symbol originates.
-#[derive(Clone, Copy, Default, Debug)]
-pub(crate) enum SrcLang {
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SrcLang {
     /// The language is unknown.
     #[default]
     Unknown,
+    /// The language is Ada.
+    Ada,
     /// The language is C++.
     Cpp,
+    /// The language is D.
+    D,
     /// The language is Rust.
     Rust,
+    /// The language is Swift.
+    Swift,
+}
+
+impl SrcLang {
+    /// Infer the source code language from a symbol's mangling scheme,
+    /// if possible.
+    pub(crate) fn from_mangled_name(name: &str) -> Self {
+        /// Check whether `name` is a "legacy" mangled Rust name, which
+        /// uses Itanium style mangling with a trailing hash component.
+        fn is_legacy_rust(name: &str) -> bool {
+            name.strip_suffix('E')
+                .and_then(|name| name.get(name.len().saturating_sub(19)..))
+                .and_then(|hash| hash.strip_prefix("17h"))
+                .map(|hash| hash.bytes().all(|b| b.is_ascii_hexdigit()))
+                == Some(true)
+        }
+
+        let name = name.split('.').next().unwrap_or(name);
+        if name.starts_with("_R") || (name.starts_with("_ZN") && is_legacy_rust(name)) {
+            Self::Rust
+        } else if name.starts_with("_Z") || name.starts_with("__Z") || name.starts_with('?') {
+            Self::Cpp
+        } else if demangle::is_d_mangled(name) {
+            Self::D
+        } else if demangle::is_ada_mangled(name) {
+            Self::Ada
+        } else if demangle::is_swift_mangled(name) {
+            Self::Swift
+        } else {
+            Self::Unknown
+        }
+    }
 }
 
 
@@ -276,6 +466,19 @@ pub(crate) struct IntSym<'src> {
 }
 
 
+/// Our internal representation of the module an address belongs to.
+#[derive(Debug, Default)]
+pub(crate) struct IntModule<'src> {
+    /// The path to the binary or symbolization source.
+    pub(crate) path: Option<&'src Path>,
+    /// The build ID of the binary, if available.
+    pub(crate) build_id: Option<&'src [u8]>,
+    /// The file offset corresponding to the address, if it could be
+    /// determined.
+    pub(crate) file_offset: Option<u64>,
+}
+
+
 /// The result of address symbolization by [`Symbolizer`].
 #[derive(Clone, Debug, PartialEq)]
 pub struct Sym<'src> {
@@ -299,6 +502,12 @@ pub struct Sym<'src> {
     pub offset: usize,
     /// The symbol's size, if available.
     pub size: Option<usize>,
+    /// The source code language from which the symbol originates.
+    ///
+    /// The language is reported based on debug information, if
+    /// available, and inferred from the symbol's mangling scheme
+    /// otherwise.
+    pub lang: SrcLang,
     /// Source code location information for the symbol.
     pub code_info: Option<CodeInfo<'src>>,
     /// Inlined function information, if requested and available.
@@ -313,11 +522,60 @@ pub struct Sym<'src> {
     /// contains an inlined call to `h`, the symbols will be reported in the
     /// order `f`, `g`, `h`.
     pub inlined: Box<[InlinedFn<'src>]>,
+    /// The path to the module (binary, debug information file, Gsym
+    /// file, ...) in which the symbol was found, if known.
+    ///
+    /// When symbolizing addresses in a process, this is the path to
+    /// the binary as it is mapped into the process.
+    pub module: Option<Cow<'src, Path>>,
+    /// The build ID of the module, if available.
+    ///
+    /// For Gsym files this is the file's UUID.
+    pub build_id: Option<Cow<'src, [u8]>>,
+    /// The offset of the symbolized address inside the module's file,
+    /// if it could be determined.
+    pub file_offset: Option<u64>,
     /// The struct is non-exhaustive and open to extension.
     #[doc(hidden)]
     pub _non_exhaustive: (),
 }
 
+impl Sym<'_> {
+    /// Convert this object into one with all references converted into
+    /// guaranteed owned (i.e., heap allocated) members.
+    pub fn to_owned(&self) -> Sym<'static> {
+        let Sym {
+            name,
+            addr,
+            offset,
+            size,
+            lang,
+            code_info,
+            inlined,
+            module,
+            build_id,
+            file_offset,
+            _non_exhaustive: (),
+        } = self;
+
+        Sym {
+            name: Cow::Owned(name.to_string()),
+            addr: *addr,
+            offset: *offset,
+            size: *size,
+            lang: *lang,
+            code_info: code_info.as_ref().map(CodeInfo::to_owned),
+            inlined: inlined.iter().map(InlinedFn::to_owned).collect(),
+            module: module
+                .as_ref()
+                .map(|module| Cow::Owned(module.to_path_buf())),
+            build_id: build_id.as_ref().map(|id| Cow::Owned(id.to_vec())),
+            file_offset: *file_offset,
+            _non_exhaustive: (),
+        }
+    }
+}
+
 
 /// The reason why symbolization failed.
 ///
@@ -339,15 +597,64 @@ pub enum Reason {
     MissingSyms,
     /// The address could not be found in the symbolization source.
     UnknownAddr,
+    /// The time budget for symbolization was exhausted before the
+    /// address could be symbolized.
+    TimedOut,
+    /// The absolute address belongs to a special region of the
+    /// process' address space, which does not contain symbolizable
+    /// code.
+    SpecialRegion(SpecialRegion),
+    /// The file backing the address could only be found on disk, but
+    /// its build ID does not match that of the file mapped into the
+    /// process, meaning that it got replaced in the meantime.
+    BuildIdMismatch,
 }
 
 impl Display for Reason {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         let s = match self {
+            Self::SpecialRegion(region) => {
+                return write!(f, "absolute address belongs to {region} region")
+            }
             Self::Unmapped => "absolute address not found in virtual memory map of process",
             Self::InvalidFileOffset => "file offset does not map to a valid piece of code/data",
             Self::MissingSyms => "symbolization source has no or no relevant symbols",
             Self::UnknownAddr => "address not found in symbolization source",
+            Self::TimedOut => "time budget exhausted before address could be symbolized",
+            Self::BuildIdMismatch => "build ID of file on disk does not match that of mapped file",
+        };
+
+        f.write_str(s)
+    }
+}
+
+
+/// A special region of a process' address space.
+///
+/// This type is used in the [`Reason::SpecialRegion`] variant.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum SpecialRegion {
+    /// The stack of the process or one of its threads.
+    Stack,
+    /// The heap of the process.
+    Heap,
+    /// A guard page (or an otherwise inaccessible mapping).
+    Guard,
+    /// The legacy `vsyscall` page.
+    Vsyscall,
+    /// The vDSO, in case its symbols could not be loaded.
+    Vdso,
+}
+
+impl Display for SpecialRegion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let s = match self {
+            Self::Stack => "stack",
+            Self::Heap => "heap",
+            Self::Guard => "guard",
+            Self::Vsyscall => "vsyscall",
+            Self::Vdso => "vDSO",
         };
 
         f.write_str(s)
@@ -358,6 +665,9 @@ impl Display for Reason {
 /// An enumeration used as reporting vehicle for address symbolization.
 // We keep this enum as exhaustive because additions to it, should they occur,
 // are expected to be backwards-compatibility breaking.
+// The vast majority of results are expected to be symbols, so there is
+// little point in boxing the large variant.
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug, PartialEq)]
 pub enum Symbolized<'src> {
     /// The input address was symbolized as the provided symbol.
@@ -389,6 +699,15 @@ impl<'src> Symbolized<'src> {
             Self::Unknown(..) => None,
         }
     }
+
+    /// Convert this object into one with all references converted into
+    /// guaranteed owned (i.e., heap allocated) members.
+    pub fn to_owned(&self) -> Symbolized<'static> {
+        match self {
+            Self::Sym(sym) => Symbolized::Sym(sym.to_owned()),
+            Self::Unknown(reason) => Symbolized::Unknown(reason.clone()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -410,6 +729,7 @@ mod tests {
             file: Cow::Borrowed(OsStr::new("test.c")),
             line: Some(1337),
             column: None,
+            context: None,
             _non_exhaustive: (),
         };
 
@@ -418,12 +738,16 @@ mod tests {
             addr: 1337,
             offset: 42,
             size: None,
+            lang: SrcLang::Unknown,
             code_info: None,
             inlined: Box::new([InlinedFn {
                 name: Cow::Borrowed("inlined_test"),
                 code_info: Some(code_info.clone()),
                 _non_exhaustive: (),
             }]),
+            module: Some(Cow::Borrowed(Path::new("/tmp/test.bin"))),
+            build_id: Some(Cow::Borrowed(&[0xde, 0xad, 0xbe, 0xef])),
+            file_offset: Some(0x1337),
             _non_exhaustive: (),
         };
         assert_ne!(format!("{sym:?}"), "");
@@ -455,4 +779,31 @@ mod tests {
         assert_eq!(symbolized.as_sym(), None);
         assert_eq!(symbolized.into_sym(), None);
     }
+
+    /// Check that we infer languages from mangled names correctly.
+    #[test]
+    fn lang_inference() {
+        let names = [
+            ("_RNvCs1234_7mycrate3foo", SrcLang::Rust),
+            ("_ZN4core3fmt5write17h9f3c7e1d2b8a0c4eE", SrcLang::Rust),
+            (
+                "_ZN4core3fmt5write17h9f3c7e1d2b8a0c4eE.llvm.1234",
+                SrcLang::Rust,
+            ),
+            ("_ZN3foo3barEv", SrcLang::Cpp),
+            ("_Z3fooi", SrcLang::Cpp),
+            ("?foo@@YAXXZ", SrcLang::Cpp),
+            ("_D4test3fooFZv", SrcLang::D),
+            ("_Dmain", SrcLang::D),
+            ("$s4main3fooyyF", SrcLang::Swift),
+            ("ada__calendar__clock", SrcLang::Ada),
+            ("_ada_main", SrcLang::Ada),
+            ("main", SrcLang::Unknown),
+            ("_Data", SrcLang::Unknown),
+        ];
+
+        for (name, lang) in names {
+            assert_eq!(SrcLang::from_mangled_name(name), lang, "{name}");
+        }
+    }
 }
@@ -0,0 +1,195 @@
+use std::path::PathBuf;
+
+use crate::normalize::UserMeta;
+use crate::normalize::UserOutput;
+use crate::Result;
+
+use super::source::Apk;
+use super::source::Elf;
+use super::source::Source;
+use super::Input;
+
+
+/// An extension point for locating the artifacts corresponding to meta
+/// data reported by address normalization.
+///
+/// Normalization typically happens on a different system than
+/// symbolization. A locator maps the meta data reported by the former
+/// (e.g., the path and build ID of an ELF file) to the path of the
+/// corresponding artifact (e.g., the binary or a debug file) on the
+/// latter.
+///
+/// This trait is implemented for closures of the appropriate
+/// signature.
+pub trait Locator {
+    /// Locate the artifact described by `meta`.
+    ///
+    /// [`UserMeta::Elf`] meta data should be mapped to an ELF file,
+    /// [`UserMeta::Apk`] meta data to an APK. Locators are not invoked
+    /// for other meta data.
+    ///
+    /// `None` indicates that no artifact is available.
+    fn locate(&self, meta: &UserMeta) -> Result<Option<PathBuf>>;
+}
+
+impl<F> Locator for F
+where
+    F: Fn(&UserMeta) -> Result<Option<PathBuf>>,
+{
+    fn locate(&self, meta: &UserMeta) -> Result<Option<PathBuf>> {
+        (self)(meta)
+    }
+}
+
+
+/// A batch of normalized addresses sharing the same symbolization
+/// source.
+#[derive(Clone, Debug)]
+pub struct Batch {
+    /// The symbolization source to use.
+    pub src: Source<'static>,
+    /// The file offsets to symbolize.
+    pub offsets: Vec<u64>,
+    /// The indices of the file offsets in the
+    /// [`outputs`][crate::normalize::Output::outputs] of the
+    /// normalization result they originate from.
+    pub indices: Vec<usize>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Batch {
+    /// Group the outputs of address normalization into batches ready
+    /// for symbolization.
+    ///
+    /// `locator` is used for locating the artifacts corresponding to
+    /// each normalized component. Outputs without meta data (i.e., with
+    /// [`UserMeta::Unknown`]), for components that cannot be symbolized
+    /// (such as Mach-O or PE files), or for which no artifact could be
+    /// located are not part of any batch.
+    pub fn from_output(output: &UserOutput, locator: &dyn Locator) -> Result<Vec<Self>> {
+        // Indices into `batches`, by meta data index. `None` indicates
+        // that we have not yet consulted the locator, `Some(None)` that
+        // no artifact is available.
+        let mut lookup = vec![None; output.meta.len()];
+        let mut batches = Vec::<Self>::new();
+
+        for (idx, (offset, meta_idx)) in output.outputs.iter().enumerate() {
+            let batch_idx = match lookup[*meta_idx] {
+                Some(batch_idx) => batch_idx,
+                None => {
+                    let meta = &output.meta[*meta_idx];
+                    let src = match meta {
+                        UserMeta::Elf(..) => locator
+                            .locate(meta)?
+                            .map(|path| Source::from(Elf::new(path))),
+                        UserMeta::Apk(..) => locator
+                            .locate(meta)?
+                            .map(|path| Source::from(Apk::new(path))),
+                        // Symbolization of Mach-O and PE files is not
+                        // supported.
+                        UserMeta::MachO(..) | UserMeta::Pe(..) | UserMeta::Unknown(..) => None,
+                    };
+                    let batch_idx = src.map(|src| {
+                        let () = batches.push(Self {
+                            src,
+                            offsets: Vec::new(),
+                            indices: Vec::new(),
+                            _non_exhaustive: (),
+                        });
+                        batches.len() - 1
+                    });
+                    *lookup[*meta_idx].insert(batch_idx)
+                }
+            };
+
+            if let Some(batch_idx) = batch_idx {
+                let batch = &mut batches[batch_idx];
+                let () = batch.offsets.push(*offset);
+                let () = batch.indices.push(idx);
+            }
+        }
+        Ok(batches)
+    }
+
+    /// Retrieve the [`Input`] to symbolize for this batch.
+    #[inline]
+    pub fn input(&self) -> Input<&[u64]> {
+        Input::FileOffset(&self.offsets)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    use crate::normalize::Unknown;
+    use crate::ErrorKind;
+
+
+    fn elf_meta(path: &str) -> UserMeta {
+        UserMeta::Elf(crate::normalize::Elf {
+            path: PathBuf::from(path),
+            build_id: Some(path.as_bytes().to_vec()),
+            _non_exhaustive: (),
+        })
+    }
+
+    /// Check that we group normalized outputs into batches as
+    /// expected.
+    #[test]
+    fn batching() {
+        let output = UserOutput {
+            outputs: vec![(0x10, 0), (0x20, 1), (0xdead, 2), (0x30, 0), (0x40, 3)],
+            meta: vec![
+                elf_meta("/usr/lib/libc.so"),
+                UserMeta::Apk(crate::normalize::Apk {
+                    path: PathBuf::from("/system/app.apk"),
+                    elf_path: None,
+                    _non_exhaustive: (),
+                }),
+                UserMeta::Unknown(Unknown::default()),
+                elf_meta("/usr/lib/missing.so"),
+            ],
+        };
+
+        let locator = |meta: &UserMeta| -> Result<Option<PathBuf>> {
+            match meta {
+                UserMeta::Elf(elf) if elf.path.ends_with("missing.so") => Ok(None),
+                UserMeta::Elf(elf) => Ok(Some(
+                    Path::new("/artifacts").join(elf.path.file_name().unwrap()),
+                )),
+                UserMeta::Apk(apk) => Ok(Some(apk.path.clone())),
+                _ => panic!("locator invoked for unexpected meta data"),
+            }
+        };
+
+        let batches = Batch::from_output(&output, &locator).unwrap();
+        assert_eq!(batches.len(), 2);
+        assert!(
+            matches!(&batches[0].src, Source::Elf(elf) if elf.path == Path::new("/artifacts/libc.so"))
+        );
+        assert_eq!(batches[0].offsets, vec![0x10, 0x30]);
+        assert_eq!(batches[0].indices, vec![0, 3]);
+        assert!(matches!(
+            batches[0].input(),
+            Input::FileOffset([0x10, 0x30])
+        ));
+        assert!(
+            matches!(&batches[1].src, Source::Apk(apk) if apk.path == Path::new("/system/app.apk"))
+        );
+        assert_eq!(batches[1].offsets, vec![0x20]);
+        assert_eq!(batches[1].indices, vec![1]);
+
+        // Errors reported by the locator are propagated.
+        let locator = |_meta: &UserMeta| -> Result<Option<PathBuf>> {
+            Err(crate::Error::with_unsupported("no artifacts"))
+        };
+        let err = Batch::from_output(&output, &locator).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+}
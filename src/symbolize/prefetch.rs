@@ -0,0 +1,239 @@
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+
+use crate::elf::ElfResolverData;
+use crate::file_cache::FileCache;
+use crate::log;
+use crate::maps;
+use crate::maps::MapsEntry;
+use crate::scope;
+use crate::Limits;
+use crate::Result;
+use crate::SymResolver as _;
+
+
+/// The number of modules to load from disk at once.
+const PRELOAD_BATCH: usize = 16;
+
+/// Configuration of the background prefetching of process modules.
+///
+/// When prefetching is enabled (see
+/// [`Builder::set_prefetch`][crate::symbolize::Builder::set_prefetch]),
+/// the first symbolization request for a process kicks off the
+/// indexing of all ELF files mapped into it on a background thread.
+/// Subsequent requests for the same process are then likely to find
+/// the required data already cached.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prefetch {
+    /// The maximum combined size of the module files prefetched for a
+    /// single process, in bytes.
+    ///
+    /// Modules not fitting into the remaining budget are skipped.
+    pub memory_budget: u64,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Default for Prefetch {
+    fn default() -> Self {
+        Self {
+            memory_budget: 512 * 1024 * 1024,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+
+/// The work to perform for prefetching the modules of a process.
+#[derive(Debug)]
+pub(crate) struct PrefetchJob {
+    /// The cache to populate.
+    pub elf_cache: Arc<FileCache<ElfResolverData>>,
+    /// The paths of the ELF files to index, in order of preference.
+    pub paths: Vec<PathBuf>,
+    /// The memory budget to adhere to.
+    pub memory_budget: u64,
+    /// Whether to index debug symbols.
+    pub debug_syms: bool,
+    /// Whether to index source code location information.
+    pub code_info: bool,
+    /// The parsing limits to impose, if any.
+    pub limits: Option<Limits>,
+}
+
+impl PrefetchJob {
+    /// Retrieve the paths of the (unique) ELF files referenced by the
+    /// provided proc maps entries.
+    pub fn elf_paths(entries: &[MapsEntry]) -> Vec<PathBuf> {
+        let mut seen = HashSet::new();
+        entries
+            .iter()
+            .cloned()
+            .filter_map(maps::filter_map_relevant)
+            .filter(|entry| {
+                let ext = entry
+                    .path
+                    .symbolic_path
+                    .extension()
+                    .unwrap_or_else(|| OsStr::new(""));
+                // APKs are indexed lazily, on a per-entry basis.
+                !matches!(ext.to_str(), Some("apk") | Some("zip"))
+            })
+            .filter(|entry| seen.insert(entry.path.symbolic_path.clone()))
+            .map(|entry| entry.path.maps_file)
+            .collect()
+    }
+
+    /// Index the module at `path`, if it fits into the remaining
+    /// `budget`.
+    fn prefetch_one(&self, path: &Path, size: u64, budget: &mut u64) -> Result<()> {
+        if size > *budget {
+            log::debug!(
+                "skipping prefetch of {}: exceeds remaining memory budget",
+                path.display()
+            );
+            return Ok(())
+        }
+        *budget -= size;
+
+        let resolver = self
+            .elf_cache
+            .elf_resolver(path, self.debug_syms, self.code_info)?;
+        // Looking up a symbol forces the symbol table to be sorted and
+        // cached.
+        let _result = resolver.find_sym(0)?;
+        Ok(())
+    }
+
+    /// Run the job, indexing modules for as long as the budget allows.
+    ///
+    /// Modules are loaded in batches, allowing for them to be read from
+    /// disk concurrently.
+    fn run(self) {
+        let mut budget = self.memory_budget;
+        for batch in self.paths.chunks(PRELOAD_BATCH) {
+            if budget == 0 {
+                break
+            }
+
+            let paths = batch.iter().map(PathBuf::as_path).collect::<Vec<_>>();
+            let sizes = self
+                .elf_cache
+                .preload(&paths)
+                .into_iter()
+                .map(|result| result.map(|data| data.data().len() as u64))
+                .collect::<Vec<_>>();
+
+            for (path, size) in paths.into_iter().zip(sizes) {
+                let result = size.and_then(|size| {
+                    if let Some(limits) = &self.limits {
+                        scope::with_limits(limits, || self.prefetch_one(path, size, &mut budget))
+                    } else {
+                        self.prefetch_one(path, size, &mut budget)
+                    }
+                });
+
+                if let Err(err) = result {
+                    log::debug!("failed to prefetch {}: {err}", path.display());
+                }
+            }
+        }
+    }
+
+    /// Run the job on a background thread.
+    pub fn spawn(self) {
+        let result = thread::Builder::new()
+            .name("blazesym-prefetch".to_string())
+            .spawn(move || self.run());
+        if let Err(err) = result {
+            log::warn!("failed to spawn prefetch thread: {err}");
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::maps::EntryPath;
+    use crate::maps::PathName;
+
+
+    /// Exercise the `Debug` representation of various types.
+    #[test]
+    fn debug_repr() {
+        let prefetch = Prefetch::default();
+        assert_ne!(format!("{prefetch:?}"), "");
+    }
+
+    /// Check that we extract the relevant ELF paths from proc maps
+    /// entries.
+    #[test]
+    fn elf_path_extraction() {
+        let entry = |mode, path: &str| MapsEntry {
+            range: 0x1000..0x2000,
+            mode,
+            offset: 0,
+            path_name: Some(PathName::Path(EntryPath {
+                maps_file: PathBuf::from(format!("/proc/self/map_files/{path}")),
+                symbolic_path: PathBuf::from(path),
+                deleted: false,
+            })),
+        };
+        let entries = [
+            entry(0b1000, "libc.so"),
+            entry(0b0010, "libc.so"),
+            entry(0b0100, "writable.so"),
+            entry(0b1010, "app.apk"),
+            entry(0b1010, "libfoo.so"),
+            MapsEntry {
+                range: 0x3000..0x4000,
+                mode: 0b1010,
+                offset: 0,
+                path_name: Some(PathName::Component("vdso".to_string())),
+            },
+        ];
+        let paths = PrefetchJob::elf_paths(&entries);
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/proc/self/map_files/libc.so"),
+                PathBuf::from("/proc/self/map_files/libfoo.so"),
+            ]
+        );
+    }
+
+    /// Check that prefetching populates the cache while adhering to
+    /// the memory budget.
+    #[test]
+    fn budget_adherence() {
+        let dir = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+        let small = dir.join("test-stable-addresses-no-dwarf.bin");
+        let large = dir.join("test-stable-addresses-dwarf-only.bin");
+        let small_size = small.metadata().unwrap().len();
+        let large_size = large.metadata().unwrap().len();
+        assert!(small_size < large_size);
+
+        let elf_cache = Arc::new(FileCache::new());
+        let job = PrefetchJob {
+            elf_cache: Arc::clone(&elf_cache),
+            paths: vec![large.clone(), small.clone()],
+            memory_budget: small_size,
+            debug_syms: false,
+            code_info: false,
+            limits: None,
+        };
+        let () = job.run();
+
+        let entry = elf_cache.entry(&small).unwrap();
+        assert!(entry.value().get().is_some());
+        let entry = elf_cache.entry(&large).unwrap();
+        assert!(entry.value().get().is_none());
+    }
+}
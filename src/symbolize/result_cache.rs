@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+
+use crate::Addr;
+
+use super::Reason;
+use super::Symbolized;
+
+
+/// The key identifying a cached result: the identity of the resolver
+/// used and the address symbolized.
+///
+/// Resolvers are only ever created anew (and never modified) when the
+/// underlying file changes, so their identity also captures the
+/// "generation" of the symbolization source.
+type Key = (usize, Addr);
+
+
+#[derive(Debug, Default)]
+struct Entries {
+    map: HashMap<Key, Symbolized<'static>>,
+    /// Keys in order of insertion, used for eviction.
+    order: VecDeque<Key>,
+}
+
+
+/// A bounded cache of final symbolization results, keyed by resolver
+/// and address.
+///
+/// Once the cache is full, the oldest entries are evicted first.
+#[derive(Debug)]
+pub(crate) struct ResultCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+impl ResultCache {
+    /// Create a new, empty `ResultCache` holding at most `capacity`
+    /// entries.
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// Lock the cache's entries.
+    fn entries(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Retrieve the cached result of symbolizing `addr` using the
+    /// resolver identified by `resolver`, if any.
+    pub(crate) fn get(&self, resolver: usize, addr: Addr) -> Option<Symbolized<'static>> {
+        self.entries().map.get(&(resolver, addr)).cloned()
+    }
+
+    /// Cache the result of symbolizing `addr` using the resolver
+    /// identified by `resolver`.
+    pub(crate) fn insert(&self, resolver: usize, addr: Addr, symbolized: &Symbolized<'_>) {
+        if self.capacity == 0 {
+            return
+        }
+
+        // Results depending on the circumstances of the request are not
+        // worth remembering.
+        if let Symbolized::Unknown(Reason::TimedOut) = symbolized {
+            return
+        }
+
+        let key = (resolver, addr);
+        let mut entries = self.entries();
+        if entries.map.insert(key, symbolized.to_owned()).is_none() {
+            let () = entries.order.push_back(key);
+            if entries.order.len() > self.capacity {
+                if let Some(oldest) = entries.order.pop_front() {
+                    let _entry = entries.map.remove(&oldest);
+                }
+            }
+        }
+    }
+
+    /// Remove all cached results.
+    pub(crate) fn clear(&self) {
+        let mut entries = self.entries();
+        let () = entries.map.clear();
+        let () = entries.order.clear();
+    }
+
+    /// Retrieve the number of cached entries.
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.entries().map.len()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::borrow::Cow;
+    use std::ffi::OsStr;
+    use std::path::Path;
+
+    use crate::symbolize::CodeInfo;
+    use crate::symbolize::InlinedFn;
+    use crate::symbolize::SrcLang;
+    use crate::symbolize::Sym;
+
+
+    /// Check that results are cached and that the cache stays bounded.
+    #[test]
+    fn caching() {
+        let name = String::from("foo");
+        let sym = Symbolized::Sym(Sym {
+            name: Cow::Borrowed(&name),
+            addr: 0x1000,
+            offset: 0x10,
+            size: Some(0x20),
+            lang: SrcLang::Rust,
+            code_info: Some(CodeInfo {
+                dir: None,
+                file: Cow::Borrowed(OsStr::new("foo.rs")),
+                line: Some(42),
+                column: None,
+                context: None,
+                _non_exhaustive: (),
+            }),
+            inlined: Box::new([InlinedFn {
+                name: Cow::Borrowed(&name),
+                code_info: None,
+                _non_exhaustive: (),
+            }]),
+            module: Some(Cow::Borrowed(Path::new("/tmp/foo"))),
+            build_id: Some(Cow::Borrowed(&[0x01, 0x02])),
+            file_offset: Some(0x1010),
+            _non_exhaustive: (),
+        });
+
+        let cache = ResultCache::with_capacity(2);
+        assert_eq!(cache.get(1, 0x1010), None);
+        let () = cache.insert(1, 0x1010, &sym);
+        assert_eq!(cache.get(1, 0x1010), Some(sym.clone()));
+        // Results are specific to the resolver.
+        assert_eq!(cache.get(2, 0x1010), None);
+
+        let unknown = Symbolized::Unknown(Reason::UnknownAddr);
+        let () = cache.insert(2, 0x1010, &unknown);
+        assert_eq!(cache.get(2, 0x1010), Some(unknown));
+        assert_eq!(cache.len(), 2);
+
+        // Time outs are not cached.
+        let () = cache.insert(3, 0x1010, &Symbolized::Unknown(Reason::TimedOut));
+        assert_eq!(cache.get(3, 0x1010), None);
+
+        // The oldest entry should get evicted.
+        let () = cache.insert(4, 0x1010, &sym);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(1, 0x1010), None);
+        assert_eq!(cache.get(4, 0x1010), Some(sym));
+
+        let () = cache.clear();
+        assert_eq!(cache.len(), 0);
+        assert_eq!(cache.get(4, 0x1010), None);
+    }
+
+    /// Make sure that a cache with zero capacity does not cache
+    /// anything.
+    #[test]
+    fn zero_capacity() {
+        let cache = ResultCache::with_capacity(0);
+        let () = cache.insert(1, 0x1010, &Symbolized::Unknown(Reason::UnknownAddr));
+        assert_eq!(cache.len(), 0);
+        assert_eq!(cache.get(1, 0x1010), None);
+    }
+}
@@ -2,8 +2,10 @@ use std::cmp::min;
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
+use std::ops::Range;
 use std::path::PathBuf;
 
+use crate::Addr;
 use crate::Pid;
 
 #[cfg(doc)]
@@ -77,6 +79,14 @@ pub struct Elf {
     /// built with the `dwarf` feature to actually consult debug
     /// symbols. If neither is satisfied, ELF symbols will be used.
     pub debug_syms: bool,
+    /// The ranges of (virtual) addresses of interest, if known.
+    ///
+    /// When set, only debug information overlapping these ranges is
+    /// indexed, which can considerably speed up symbolization of large
+    /// binaries of which only small parts are ever of relevance.
+    /// Addresses outside of these ranges may still be symbolized, but
+    /// only using ELF symbols.
+    pub hot_ranges: Option<Vec<Range<Addr>>>,
     /// The struct is non-exhaustive and open to extension.
     #[doc(hidden)]
     pub _non_exhaustive: (),
@@ -91,6 +101,7 @@ impl Elf {
         Self {
             path: path.into(),
             debug_syms: true,
+            hot_ranges: None,
             _non_exhaustive: (),
         }
     }
@@ -108,6 +119,7 @@ impl Debug for Elf {
         let Self {
             path,
             debug_syms: _,
+            hot_ranges: _,
             _non_exhaustive: (),
         } = self;
 
@@ -133,9 +145,20 @@ pub struct Kernel {
     ///
     /// This should be the path of a kernel image.  For example,
     /// `"/boot/vmlinux-xxxx"`.  A `None` value will find the
-    /// kernel image of the running kernel in `"/boot/"` or
-    /// `"/usr/lib/debug/boot/"`.
+    /// kernel image of the running kernel in `"/usr/lib/debug/boot/"`
+    /// or `"/boot/"`. If the running kernel's build ID is available,
+    /// only images with a matching build ID are considered.
     pub kernel_image: Option<PathBuf>,
+    /// The offset by which the kernel got relocated at boot time, as
+    /// is the case with kernel address space layout randomization
+    /// (KASLR) enabled.
+    ///
+    /// The offset is subtracted from addresses before looking them up
+    /// in [`kernel_image`][Self::kernel_image]. A `None` value will
+    /// derive it by comparing the address of the `_text` (or `_stext`)
+    /// symbol in kallsyms and the kernel image, if both are available,
+    /// and assume no relocation otherwise.
+    pub kaslr_offset: Option<u64>,
     /// Whether or not to consult debug symbols from `kernel_image`
     /// to satisfy the request (if present).
     ///
@@ -143,6 +166,25 @@ pub struct Kernel {
     /// built with the `dwarf` feature to actually consult debug
     /// symbols. If neither is satisfied, ELF symbols will be used.
     pub debug_syms: bool,
+    /// The path of a copy of `/proc/modules`, listing the kernel
+    /// modules loaded and their load addresses.
+    ///
+    /// Addresses inside of a listed module are symbolized using the
+    /// module's ELF file (see [`module_dir`][Self::module_dir]), if it
+    /// can be found, and using kallsyms otherwise. Compressed modules
+    /// (`.ko.xz` and `.ko.zst`) require the `xz` and `zstd` features,
+    /// respectively.
+    ///
+    /// A `None` value will use `"/proc/modules"` if the running
+    /// kernel is symbolized, i.e., if [`kallsyms`][Self::kallsyms] is
+    /// `None` as well, and ignore kernel modules otherwise.
+    pub modules: Option<PathBuf>,
+    /// The path of the directory containing the kernel module files
+    /// along with a `modules.dep` file mapping module names to them.
+    ///
+    /// A `None` value will use the module directory of the running
+    /// kernel, `"/lib/modules/<release>/"`.
+    pub module_dir: Option<PathBuf>,
     /// The struct is non-exhaustive and open to extension.
     #[doc(hidden)]
     pub _non_exhaustive: (),
@@ -153,7 +195,10 @@ impl Default for Kernel {
         Self {
             kallsyms: None,
             kernel_image: None,
+            kaslr_offset: None,
             debug_syms: true,
+            modules: None,
+            module_dir: None,
             _non_exhaustive: (),
         }
     }
@@ -228,6 +273,314 @@ impl From<Process> for Source<'static> {
 }
 
 
+/// Configuration for symbolization of addresses in a Java virtual
+/// machine (JVM) process.
+///
+/// This type is used in the [`Source::Jvm`] variant.
+///
+/// Addresses are first symbolized as they would be for a
+/// [`Process`] source. Addresses not belonging to any file backed
+/// mapping (such as those in the JIT code cache) are then looked up in
+/// the symbol information that the JVM (or an agent such as
+/// `perf-map-agent`) emits in the form of a perf map and/or a jitdump
+/// file. Methods take precedence over larger code regions such as the
+/// template interpreter (reported as `Interpreter`) or stub routines,
+/// which serve as a fallback. Both files are re-read whenever they
+/// changed since they were last used.
+///
+/// The corresponding addresses supplied to [`Symbolizer::symbolize`] are
+/// expected to be absolute addresses
+/// ([`Input::AbsAddr`][crate::symbolize::Input::AbsAddr]) as valid within the
+/// process identified by the [`pid`][Jvm::pid] member.
+#[derive(Clone)]
+pub struct Jvm {
+    /// The referenced process' ID.
+    pub pid: Pid,
+    /// The path to the perf map file of the process.
+    ///
+    /// Passing `None`, by default, will be `/tmp/perf-<pid>.map` in the
+    /// process' mount namespace, with `<pid>` being the ID of the
+    /// process as seen from within its PID namespace. A missing file
+    /// is not considered an error.
+    pub perf_map: Option<PathBuf>,
+    /// The path to the jitdump file of the process.
+    ///
+    /// Passing `None`, by default, will be `/tmp/jit-<pid>.dump` in the
+    /// process' mount namespace, with `<pid>` being the ID of the
+    /// process as seen from within its PID namespace. A missing file
+    /// is not considered an error.
+    pub jitdump: Option<PathBuf>,
+    /// Whether or not to consult debug symbols to satisfy the request
+    /// (if present).
+    ///
+    /// On top of this runtime configuration, the crate needs to be
+    /// built with the `dwarf` feature to actually consult debug
+    /// symbols. If neither is satisfied, ELF symbols will be used.
+    pub debug_syms: bool,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Jvm {
+    /// Create a new [`Jvm`] object using the provided `pid`.
+    ///
+    /// `debug_syms` defaults to `true` when using this constructor.
+    #[inline]
+    pub fn new(pid: Pid) -> Self {
+        Self {
+            pid,
+            perf_map: None,
+            jitdump: None,
+            debug_syms: true,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+impl Debug for Jvm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let Self {
+            pid,
+            perf_map: _,
+            jitdump: _,
+            debug_syms: _,
+            _non_exhaustive: (),
+        } = self;
+
+        f.debug_tuple(stringify!(Jvm))
+            // We use the `Display` representation here.
+            .field(&format_args!("{pid}"))
+            .finish()
+    }
+}
+
+impl From<Jvm> for Source<'static> {
+    #[inline]
+    fn from(jvm: Jvm) -> Self {
+        Source::Jvm(jvm)
+    }
+}
+
+
+/// Configuration for symbolization of addresses in a Node.js process.
+///
+/// This type is used in the [`Source::Node`] variant.
+///
+/// Addresses are first symbolized as they would be for a
+/// [`Process`] source. Addresses not belonging to any file backed
+/// mapping are then looked up in the perf map that V8 emits when Node.js
+/// is started with `--perf-basic-prof`.
+///
+/// V8 only ever appends to this perf map, even when code gets moved or
+/// garbage collected. Hence, if multiple entries overlap, only the one
+/// appearing last in the file is considered. The perf map is re-read
+/// whenever it changed since it was last used.
+///
+/// The corresponding addresses supplied to [`Symbolizer::symbolize`] are
+/// expected to be absolute addresses
+/// ([`Input::AbsAddr`][crate::symbolize::Input::AbsAddr]) as valid within the
+/// process identified by the [`pid`][Node::pid] member.
+#[derive(Clone)]
+pub struct Node {
+    /// The referenced process' ID.
+    pub pid: Pid,
+    /// The path to the perf map file of the process.
+    ///
+    /// Passing `None`, by default, will be `/tmp/perf-<pid>.map` in the
+    /// process' mount namespace, with `<pid>` being the ID of the
+    /// process as seen from within its PID namespace. A missing file
+    /// is not considered an error.
+    pub perf_map: Option<PathBuf>,
+    /// Whether or not to consult debug symbols to satisfy the request
+    /// (if present).
+    ///
+    /// On top of this runtime configuration, the crate needs to be
+    /// built with the `dwarf` feature to actually consult debug
+    /// symbols. If neither is satisfied, ELF symbols will be used.
+    pub debug_syms: bool,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Node {
+    /// Create a new [`Node`] object using the provided `pid`.
+    ///
+    /// `debug_syms` defaults to `true` when using this constructor.
+    #[inline]
+    pub fn new(pid: Pid) -> Self {
+        Self {
+            pid,
+            perf_map: None,
+            debug_syms: true,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+impl Debug for Node {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let Self {
+            pid,
+            perf_map: _,
+            debug_syms: _,
+            _non_exhaustive: (),
+        } = self;
+
+        f.debug_tuple(stringify!(Node))
+            // We use the `Display` representation here.
+            .field(&format_args!("{pid}"))
+            .finish()
+    }
+}
+
+impl From<Node> for Source<'static> {
+    #[inline]
+    fn from(node: Node) -> Self {
+        Source::Node(node)
+    }
+}
+
+
+/// A previously captured snapshot of a process' memory mappings.
+///
+/// This type is used in the [`Source::Snapshot`] variant.
+///
+/// Addresses are symbolized as they would be for a [`Process`] source,
+/// but based on the provided contents of a `/proc/<pid>/maps` file
+/// instead of those of a live process. That makes it possible to
+/// symbolize addresses of processes that have since exited or that ran
+/// on a different system.
+///
+/// Files are accessed via the paths recorded in the maps data, using
+/// the [`FileAccess`][crate::vfs::FileAccess] implementation the
+/// [`Symbolizer`] was configured with. To serve binaries retrieved
+/// from elsewhere, use a custom implementation or
+/// [`MemFileAccess`][crate::vfs::MemFileAccess].
+///
+/// The corresponding addresses supplied to [`Symbolizer::symbolize`] are
+/// expected to be absolute addresses
+/// ([`Input::AbsAddr`][crate::symbolize::Input::AbsAddr]) as valid within the
+/// process the snapshot was captured from.
+#[derive(Clone)]
+pub struct Snapshot<'dat> {
+    /// The contents of the process' `/proc/<pid>/maps` file.
+    pub maps: &'dat str,
+    /// Whether or not to consult debug symbols to satisfy the request
+    /// (if present).
+    ///
+    /// On top of this runtime configuration, the crate needs to be
+    /// built with the `dwarf` feature to actually consult debug
+    /// symbols. If neither is satisfied, ELF symbols will be used.
+    pub debug_syms: bool,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl<'dat> Snapshot<'dat> {
+    /// Create a new [`Snapshot`] object using the provided proc maps
+    /// data.
+    ///
+    /// `debug_syms` defaults to `true` when using this constructor.
+    #[inline]
+    pub fn new(maps: &'dat str) -> Self {
+        Self {
+            maps,
+            debug_syms: true,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+impl Debug for Snapshot<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let Self {
+            maps,
+            debug_syms: _,
+            _non_exhaustive: (),
+        } = self;
+
+        f.debug_tuple(stringify!(Snapshot))
+            .field(&format_args!("{} entries", maps.lines().count()))
+            .finish()
+    }
+}
+
+impl<'dat> From<Snapshot<'dat>> for Source<'dat> {
+    #[inline]
+    fn from(snapshot: Snapshot<'dat>) -> Self {
+        Source::Snapshot(snapshot)
+    }
+}
+
+
+/// An ELF core file of a process.
+///
+/// This type is used in the [`Source::Coredump`] variant.
+///
+/// The memory map of the process is reconstructed from the `NT_FILE`
+/// note and the program headers of the core file. Addresses are then
+/// symbolized as they would be for a [`Process`] source, with the
+/// binaries involved being accessed via the paths recorded in the core
+/// file. Those paths are as seen from within the process' mount
+/// namespace.
+///
+/// The corresponding addresses supplied to [`Symbolizer::symbolize`] are
+/// expected to be absolute addresses
+/// ([`Input::AbsAddr`][crate::symbolize::Input::AbsAddr]) as valid within the
+/// process the core file was created for.
+#[derive(Clone)]
+pub struct Coredump {
+    /// The path to the core file.
+    pub path: PathBuf,
+    /// Whether or not to consult debug symbols to satisfy the request
+    /// (if present).
+    ///
+    /// On top of this runtime configuration, the crate needs to be
+    /// built with the `dwarf` feature to actually consult debug
+    /// symbols. If neither is satisfied, ELF symbols will be used.
+    pub debug_syms: bool,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Coredump {
+    /// Create a new [`Coredump`] object, referencing the provided path.
+    ///
+    /// `debug_syms` defaults to `true` when using this constructor.
+    #[inline]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            debug_syms: true,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+impl Debug for Coredump {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let Self {
+            path,
+            debug_syms: _,
+            _non_exhaustive: (),
+        } = self;
+
+        f.debug_tuple(stringify!(Coredump)).field(path).finish()
+    }
+}
+
+impl From<Coredump> for Source<'static> {
+    #[inline]
+    fn from(coredump: Coredump) -> Self {
+        Source::Coredump(coredump)
+    }
+}
+
+
 /// Enumeration of supported Gsym sources.
 ///
 /// This type is used in the [`Source::Gsym`] variant.
@@ -338,6 +691,179 @@ impl From<GsymFile> for Source<'static> {
 }
 
 
+/// Enumeration of supported Breakpad sources.
+///
+/// This type is used in the [`Source::Breakpad`] variant.
+///
+/// Addresses in Breakpad files are relative to the load address of the
+/// module they describe (i.e., the virtual address of its first
+/// loadable segment, for ELF files). The corresponding addresses
+/// supplied to [`Symbolizer::symbolize`] are expected to be such
+/// module relative addresses and provided as virtual offsets
+/// ([`Input::VirtOffset`][crate::symbolize::Input::VirtOffset]).
+#[derive(Clone)]
+pub enum Breakpad<'dat> {
+    /// "Raw" Breakpad symbol file data.
+    Data(BreakpadData<'dat>),
+    /// A Breakpad symbol file.
+    File(BreakpadFile),
+}
+
+impl Debug for Breakpad<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Data(data) => Debug::fmt(data, f),
+            Self::File(file) => Debug::fmt(file, f),
+        }
+    }
+}
+
+impl<'dat> From<Breakpad<'dat>> for Source<'dat> {
+    #[inline]
+    fn from(breakpad: Breakpad<'dat>) -> Self {
+        Source::Breakpad(breakpad)
+    }
+}
+
+
+/// Breakpad symbol file data.
+#[derive(Clone)]
+pub struct BreakpadData<'dat> {
+    /// The "raw" Breakpad symbol file data.
+    pub data: &'dat [u8],
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl<'dat> BreakpadData<'dat> {
+    /// Create a new [`BreakpadData`] object, referencing the provided
+    /// data.
+    #[inline]
+    pub fn new(data: &'dat [u8]) -> Self {
+        Self {
+            data,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+impl Debug for BreakpadData<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let Self {
+            data,
+            _non_exhaustive: (),
+        } = self;
+
+        f.debug_tuple(stringify!(BreakpadData))
+            .field(&data.get(0..(min(data.len(), 32))).unwrap_or_default())
+            .finish()
+    }
+}
+
+impl<'dat> From<BreakpadData<'dat>> for Source<'dat> {
+    #[inline]
+    fn from(breakpad: BreakpadData<'dat>) -> Self {
+        Source::Breakpad(Breakpad::Data(breakpad))
+    }
+}
+
+
+/// A Breakpad symbol file, commonly carrying a `.sym` extension.
+#[derive(Clone)]
+pub struct BreakpadFile {
+    /// The path to the Breakpad symbol file.
+    pub path: PathBuf,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl BreakpadFile {
+    /// Create a new [`BreakpadFile`] object, referencing the provided
+    /// path.
+    #[inline]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            _non_exhaustive: (),
+        }
+    }
+}
+
+impl Debug for BreakpadFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let Self {
+            path,
+            _non_exhaustive: (),
+        } = self;
+
+        f.debug_tuple(stringify!(BreakpadFile)).field(path).finish()
+    }
+}
+
+impl From<BreakpadFile> for Source<'static> {
+    #[inline]
+    fn from(breakpad: BreakpadFile) -> Self {
+        Source::Breakpad(Breakpad::File(breakpad))
+    }
+}
+
+
+/// A linker map file, as emitted by GNU ld or LLD when using `-Map`.
+///
+/// This type is used in the [`Source::LinkerMap`] variant.
+///
+/// Map files only contain global symbols and generally do not provide
+/// symbol sizes, which are inferred from the addresses of neighboring
+/// symbols and the sizes of the containing input sections. They are
+/// of use for firmware and other embedded images, where neither debug
+/// information nor a symbol table may survive into the shipped
+/// artifact.
+///
+/// The corresponding addresses supplied to [`Symbolizer::symbolize`] are
+/// expected to be virtual offsets
+/// ([`Input::VirtOffset`][crate::symbolize::Input::VirtOffset]), i.e.,
+/// addresses as assigned by the linker.
+#[derive(Clone)]
+pub struct LinkerMap {
+    /// The path to the map file.
+    pub path: PathBuf,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl LinkerMap {
+    /// Create a new [`LinkerMap`] object, referencing the provided path.
+    #[inline]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            _non_exhaustive: (),
+        }
+    }
+}
+
+impl Debug for LinkerMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let Self {
+            path,
+            _non_exhaustive: (),
+        } = self;
+
+        f.debug_tuple(stringify!(LinkerMap)).field(path).finish()
+    }
+}
+
+impl From<LinkerMap> for Source<'static> {
+    #[inline]
+    fn from(map: LinkerMap) -> Self {
+        Source::LinkerMap(map)
+    }
+}
+
+
 /// The description of a source of symbols and debug information.
 ///
 /// The source of symbols and debug information can be an ELF file, kernel
@@ -353,8 +879,20 @@ pub enum Source<'dat> {
     Kernel(Kernel),
     /// Information about a process.
     Process(Process),
+    /// Information about a Java virtual machine process.
+    Jvm(Jvm),
+    /// Information about a Node.js process.
+    Node(Node),
+    /// A snapshot of a process' memory mappings.
+    Snapshot(Snapshot<'dat>),
+    /// An ELF core file of a process.
+    Coredump(Coredump),
     /// A Gsym file.
     Gsym(Gsym<'dat>),
+    /// A linker map file.
+    LinkerMap(LinkerMap),
+    /// A Breakpad symbol file.
+    Breakpad(Breakpad<'dat>),
 }
 
 impl Debug for Source<'_> {
@@ -364,7 +902,13 @@ impl Debug for Source<'_> {
             Self::Elf(elf) => Debug::fmt(elf, f),
             Self::Kernel(kernel) => Debug::fmt(kernel, f),
             Self::Process(process) => Debug::fmt(process, f),
+            Self::Jvm(jvm) => Debug::fmt(jvm, f),
+            Self::Node(node) => Debug::fmt(node, f),
+            Self::Snapshot(snapshot) => Debug::fmt(snapshot, f),
+            Self::Coredump(coredump) => Debug::fmt(coredump, f),
             Self::Gsym(gsym) => Debug::fmt(gsym, f),
+            Self::LinkerMap(map) => Debug::fmt(map, f),
+            Self::Breakpad(breakpad) => Debug::fmt(breakpad, f),
         }
     }
 }
@@ -399,9 +943,29 @@ mod tests {
         assert_eq!(format!("{gsym:?}"), "GsymFile(\"/a-path/gsym\")");
         let src = Source::from(gsym);
         assert_eq!(format!("{src:?}"), "GsymFile(\"/a-path/gsym\")");
+
+        let map = LinkerMap::new("/a-path/firmware.map");
+        assert_eq!(format!("{map:?}"), "LinkerMap(\"/a-path/firmware.map\")");
+        let src = Source::from(map);
+        assert_eq!(format!("{src:?}"), "LinkerMap(\"/a-path/firmware.map\")");
         let src = Source::from(Gsym::Data(gsym_data));
         assert_eq!(format!("{src:?}"), "GsymData([49, 50, 51, 52, 53])");
 
+        let breakpad_data = BreakpadData::new(b"MODULE");
+        assert_eq!(
+            format!("{breakpad_data:?}"),
+            "BreakpadData([77, 79, 68, 85, 76, 69])"
+        );
+        let src = Source::from(breakpad_data);
+        assert_eq!(format!("{src:?}"), "BreakpadData([77, 79, 68, 85, 76, 69])");
+        let breakpad_file = BreakpadFile::new("/a-path/test.sym");
+        assert_eq!(
+            format!("{breakpad_file:?}"),
+            "BreakpadFile(\"/a-path/test.sym\")"
+        );
+        let src = Source::from(Breakpad::File(breakpad_file));
+        assert_eq!(format!("{src:?}"), "BreakpadFile(\"/a-path/test.sym\")");
+
         let kernel = Kernel::default();
         assert_ne!(format!("{kernel:?}"), "");
         let src = Source::from(kernel);
@@ -413,5 +977,26 @@ mod tests {
         assert_eq!(format!("{process:?}"), "Process(1234)");
         let src = Source::from(process);
         assert_eq!(format!("{src:?}"), "Process(1234)");
+
+        let jvm = Jvm::new(Pid::from(1234));
+        assert_eq!(format!("{jvm:?}"), "Jvm(1234)");
+        let src = Source::from(jvm);
+        assert_eq!(format!("{src:?}"), "Jvm(1234)");
+
+        let node = Node::new(Pid::from(1234));
+        assert_eq!(format!("{node:?}"), "Node(1234)");
+        let src = Source::from(node);
+        assert_eq!(format!("{src:?}"), "Node(1234)");
+
+        let maps = "55f4a95c9000-55f4a95cb000 r--p 00000000 00:20 41445 /usr/bin/cat\n";
+        let snapshot = Snapshot::new(maps);
+        assert_eq!(format!("{snapshot:?}"), "Snapshot(1 entries)");
+        let src = Source::from(snapshot);
+        assert_eq!(format!("{src:?}"), "Snapshot(1 entries)");
+
+        let coredump = Coredump::new("/tmp/core.1234");
+        assert_eq!(format!("{coredump:?}"), "Coredump(\"/tmp/core.1234\")");
+        let src = Source::from(coredump);
+        assert_eq!(format!("{src:?}"), "Coredump(\"/tmp/core.1234\")");
     }
 }
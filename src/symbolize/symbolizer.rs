@@ -1,114 +1,187 @@
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::ffi::OsStr;
 use std::fmt::Debug;
-use std::fs::File;
+#[cfg(all(feature = "kernel", target_os = "linux"))]
+use std::fs;
+use std::num::NonZeroUsize;
 use std::ops::Deref as _;
 use std::ops::Range;
+use std::panic;
 use std::path::Path;
 use std::path::PathBuf;
-use std::rc::Rc;
+use std::process;
+use std::slice;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
 
+#[cfg(feature = "breakpad")]
+use crate::breakpad::BreakpadResolver;
+use crate::coredump;
+#[cfg(all(unix, feature = "cache-daemon"))]
+use crate::daemon;
+#[cfg(all(unix, feature = "cache-daemon"))]
+use crate::daemon::IndexResolver;
+#[cfg(feature = "debuginfod")]
+use crate::debuginfod;
+use crate::demangle;
 use crate::elf;
 use crate::elf::ElfParser;
 use crate::elf::ElfResolver;
 use crate::elf::ElfResolverData;
 use crate::file_cache::FileCache;
+use crate::file_cache::ManagedCache;
+#[cfg(feature = "gsym")]
 use crate::gsym::GsymResolver;
+use crate::image;
 use crate::insert_map::InsertMap;
+use crate::jit;
+use crate::jit::JitResolver;
+#[cfg(feature = "kernel")]
+use crate::kernel;
+#[cfg(feature = "kernel")]
+use crate::kernel::KSymSource;
+#[cfg(feature = "kernel")]
 use crate::kernel::KernelResolver;
+#[cfg(feature = "kernel")]
+use crate::kernel::ModuleResolver;
+#[cfg(feature = "kernel")]
+use crate::kmod;
+#[cfg(feature = "kernel")]
 use crate::ksym::KSymResolver;
+#[cfg(feature = "kernel")]
 use crate::ksym::KALLSYMS;
+use crate::linker_map;
+use crate::linker_map::LinkerMapResolver;
 use crate::log;
 use crate::maps;
+use crate::maps::MapsEntry;
 use crate::maps::PathMapsEntry;
+use crate::maps::PathName;
+#[cfg(feature = "kernel")]
 use crate::mmap::Mmap;
+use crate::mmap::MmapAdvice;
 #[cfg(target_os = "linux")]
 use crate::namespace::NsInfo;
+#[cfg(target_os = "linux")]
+use crate::namespace::ProcessView;
 use crate::normalize;
+use crate::normalize::buildid::BuildId;
+use crate::normalize::buildid::BuildIdReader as _;
+use crate::normalize::buildid::DefaultBuildIdReader;
 use crate::normalize::normalize_sorted_user_addrs_with_entries;
 use crate::normalize::Handler as _;
+use crate::normalize::UserMeta;
+use crate::normalize::UserOutput;
+use crate::scope;
+use crate::scope::ProgressCallback;
 use crate::util;
+#[cfg(all(feature = "kernel", target_os = "linux"))]
 use crate::util::uname_release;
+use crate::vdso;
+use crate::vfs::FileAccess;
+use crate::vfs::FileData;
+use crate::vfs::StdFileAccess;
 use crate::zip;
+use crate::zip::create_apk_elf_path;
+use crate::zip::split_apk_elf_path;
 use crate::Addr;
+use crate::CancellationToken;
 use crate::Error;
 use crate::ErrorExt as _;
+use crate::ErrorKind;
 use crate::IntoError as _;
+use crate::Limits;
 use crate::Pid;
+use crate::Progress;
 use crate::Result;
 use crate::SymResolver;
 
+use super::maps_cache::MapsCache;
+use super::prefetch::PrefetchJob;
+use super::result_cache::ResultCache;
 use super::source::Apk;
+#[cfg(feature = "breakpad")]
+use super::source::Breakpad;
+#[cfg(feature = "breakpad")]
+use super::source::BreakpadData;
+#[cfg(feature = "breakpad")]
+use super::source::BreakpadFile;
+use super::source::Coredump;
 use super::source::Elf;
+#[cfg(feature = "gsym")]
 use super::source::Gsym;
+#[cfg(feature = "gsym")]
 use super::source::GsymData;
+#[cfg(feature = "gsym")]
 use super::source::GsymFile;
+use super::source::Jvm;
+#[cfg(feature = "kernel")]
 use super::source::Kernel;
+use super::source::LinkerMap;
+use super::source::Node;
+#[cfg(feature = "process")]
 use super::source::Process;
+use super::source::Snapshot;
 use super::source::Source;
 use super::AddrCodeInfo;
+use super::Alloc;
+use super::Arena;
+use super::Batch;
+use super::CacheLimits;
+use super::CacheStats;
+use super::DemangleOpts;
+use super::Fallback;
+use super::FallbackSymbolized;
+use super::FrameEnricher;
 use super::InlinedFn;
 use super::Input;
+use super::IntModule;
 use super::IntSym;
+use super::Locator;
+use super::MountNsMode;
+use super::Prefetch;
 use super::Reason;
+use super::Skipped;
+use super::SourceContext;
+use super::SpecialRegion;
 use super::SrcLang;
 use super::Sym;
 use super::Symbolized;
+use super::Tier;
 
 
-fn create_apk_elf_path(apk: &Path, elf: &Path) -> Result<PathBuf> {
-    let mut extension = apk
-        .extension()
-        .unwrap_or_else(|| OsStr::new("apk"))
-        .to_os_string();
-    // Append '!' to indicate separation from archive internal contents
-    // that follow. This is an Android convention.
-    let () = extension.push("!");
-
-    let mut apk = apk.to_path_buf();
-    if !apk.set_extension(extension) {
-        return Err(Error::with_invalid_data(format!(
-            "path {} is not valid",
-            apk.display()
-        )))
+/// Classify a proc maps entry not backed by a file as one of the
+/// special regions of a process' address space, if possible.
+fn classify_special_region(entry: &MapsEntry) -> Option<SpecialRegion> {
+    match &entry.path_name {
+        Some(PathName::Component(component)) => match component.as_str() {
+            // Thread stacks used to be reported as `[stack:<tid>]`.
+            "[stack]" => return Some(SpecialRegion::Stack),
+            c if c.starts_with("[stack:") => return Some(SpecialRegion::Stack),
+            "[heap]" => return Some(SpecialRegion::Heap),
+            "[vsyscall]" => return Some(SpecialRegion::Vsyscall),
+            vdso::VDSO_MAPS_COMPONENT => return Some(SpecialRegion::Vdso),
+            _ => (),
+        },
+        Some(PathName::Path(..)) | None => (),
     }
 
-    let path = apk.join(elf);
-    Ok(path)
-}
-
-
-/// Demangle a symbol name using the demangling scheme for the given language.
-#[cfg(feature = "demangle")]
-fn maybe_demangle(name: Cow<'_, str>, language: SrcLang) -> Cow<'_, str> {
-    match language {
-        SrcLang::Rust => rustc_demangle::try_demangle(name.as_ref())
-            .ok()
-            .as_ref()
-            .map(|x| Cow::Owned(format!("{x:#}"))),
-        SrcLang::Cpp => cpp_demangle::Symbol::new(name.as_ref())
-            .ok()
-            .and_then(|x| x.demangle(&Default::default()).ok().map(Cow::Owned)),
-        SrcLang::Unknown => rustc_demangle::try_demangle(name.as_ref())
-            .map(|x| Cow::Owned(format!("{x:#}")))
-            .ok()
-            .or_else(|| {
-                cpp_demangle::Symbol::new(name.as_ref())
-                    .ok()
-                    .and_then(|sym| sym.demangle(&Default::default()).ok().map(Cow::Owned))
-            }),
-    }
-    .unwrap_or(name)
-}
-
-#[cfg(not(feature = "demangle"))]
-fn maybe_demangle(name: Cow<'_, str>, _language: SrcLang) -> Cow<'_, str> {
-    // Demangling is disabled.
-    name
+    // Neither readable, writable, nor executable (---p).
+    if (entry.mode & 0b1110) == 0 {
+        Some(SpecialRegion::Guard)
+    } else {
+        None
+    }
 }
 
 
-fn elf_offset_to_address(offset: u64, parser: &ElfParser) -> Result<Option<Addr>> {
+pub(crate) fn elf_offset_to_address(offset: u64, parser: &ElfParser) -> Result<Option<Addr>> {
     let phdrs = parser.program_headers()?;
     let addr = phdrs.iter().find_map(|phdr| {
         if phdr.p_type == elf::types::PT_LOAD {
@@ -123,6 +196,63 @@ fn elf_offset_to_address(offset: u64, parser: &ElfParser) -> Result<Option<Addr>
 }
 
 
+/// Check whether to consult JIT symbol information for `symbol`, as
+/// reported by regular process symbolization.
+///
+/// JIT compiled code typically resides in anonymous mappings, which
+/// are reported as unmapped, but runtimes may just as well place it on
+/// the heap or elsewhere. Hence, any address we failed to symbolize is
+/// a candidate, unless we ran out of time.
+fn needs_jit_fallback(symbol: &Symbolized<'_>) -> bool {
+    match symbol {
+        Symbolized::Sym(..) | Symbolized::Unknown(Reason::TimedOut) => false,
+        Symbolized::Unknown(..) => true,
+    }
+}
+
+
+/// Retrieve the path under which the root directory of the mount
+/// namespace of the process symbolized using `src` is accessible, if
+/// any.
+fn source_root(src: &Source<'_>) -> Option<PathBuf> {
+    match src {
+        #[cfg(target_os = "linux")]
+        Source::Process(process) => Some(PathBuf::from(format!("/proc/{}/root", process.pid))),
+        _ => None,
+    }
+}
+
+
+/// Create an iterator over those of the (sorted) proc maps `entries`
+/// that contain any of `addrs`, as used for symbolization.
+///
+/// Entries not containing any of the addresses play no role in
+/// symbolization and are skipped, so that we don't have to copy them.
+fn relevant_entries(
+    entries: &Arc<[MapsEntry]>,
+    addrs: &[Addr],
+) -> impl Iterator<Item = Result<MapsEntry>> {
+    let mut indices = addrs
+        .iter()
+        .filter_map(|addr| {
+            let idx = entries.partition_point(|entry| entry.range.start <= *addr);
+            let idx = idx.checked_sub(1)?;
+            entries[idx].range.contains(addr).then_some(idx)
+        })
+        .collect::<Vec<_>>();
+    let () = indices.sort_unstable();
+    let () = indices.dedup();
+    // Normalization expects at least one entry to be present, but any
+    // will do for reporting all addresses as unmapped.
+    if indices.is_empty() && !entries.is_empty() {
+        let () = indices.push(0);
+    }
+
+    let entries = Arc::clone(entries);
+    indices.into_iter().map(move |idx| Ok(entries[idx].clone()))
+}
+
+
 /// A builder for configurable construction of [`Symbolizer`] objects.
 ///
 /// By default all features are enabled.
@@ -135,12 +265,54 @@ pub struct Builder {
     code_info: bool,
     /// Whether to report inlined functions as part of symbolization.
     inlined_fns: bool,
+    /// The configuration of the source code context to report, if
+    /// enabled.
+    source_context: Option<SourceContext>,
     /// Whether or not to transparently demangle symbols.
     ///
     /// Demangling happens on a best-effort basis. Currently supported
-    /// languages are Rust and C++ and the flag will have no effect if
-    /// the underlying language does not mangle symbols (such as C).
+    /// languages are Rust, C++, D, Swift, and Ada and the flag will have
+    /// no effect if the underlying language does not mangle symbols
+    /// (such as C).
     demangle: bool,
+    /// Options controlling the output of demangling.
+    demangle_opts: DemangleOpts,
+    /// The time budget for a single symbolization request.
+    time_budget: Option<Duration>,
+    /// The callback to report progress of long running operations to.
+    progress: Option<ProgressCallback>,
+    /// The limits imposed on the parsing of input files, if any.
+    limits: Option<Limits>,
+    /// The provider used for accessing files.
+    fs: Arc<dyn FileAccess>,
+    /// The socket of the cache daemon to attach to, if any.
+    #[cfg(all(unix, feature = "cache-daemon"))]
+    cache_daemon: Option<PathBuf>,
+    /// The configuration of background prefetching, if enabled.
+    prefetch: Option<Prefetch>,
+    /// The hints to apply to large memory mappings, if any.
+    mmap_advice: Option<MmapAdvice>,
+    /// The capacity of the cache of symbolization results, if enabled.
+    result_cache: Option<usize>,
+    /// The time to live of cached proc maps entries, if caching is
+    /// enabled.
+    maps_cache: Option<Duration>,
+    /// The limits on cached data, if any.
+    cache_limits: Option<CacheLimits>,
+    /// The maximum number of threads to use for parallel
+    /// symbolization, if set.
+    parallelism: Option<NonZeroUsize>,
+    /// Whether to defer the indexing of debug information to a
+    /// background thread.
+    deferred_indexing: bool,
+    /// How to access files of processes in other mount namespaces.
+    mount_ns_mode: MountNsMode,
+    /// The directories to search for separate debug information files.
+    debug_dirs: Vec<PathBuf>,
+    /// Whether to fetch missing debug information from debuginfod
+    /// servers.
+    #[cfg(feature = "debuginfod")]
+    debuginfod: bool,
 }
 
 impl Builder {
@@ -157,32 +329,397 @@ impl Builder {
         self
     }
 
+    /// Set the configuration of the source code context to report
+    /// alongside source code location information.
+    ///
+    /// Source files are looked up by the paths recorded in the debug
+    /// information (i.e., based on the compilation directory and file
+    /// tables in the case of DWARF), adjusted according to
+    /// [`SourceContext::path_remaps`]. They are read using the
+    /// configured [`FileAccess`] implementation. For
+    /// [`Source::Process`] sources, files are accessed through the
+    /// process' root directory (`/proc/<pid>/root/`), so that they are
+    /// found even if the process runs in a different mount namespace
+    /// (e.g., inside of a container).
+    ///
+    /// The setting only has an effect if source code location
+    /// information is enabled (see
+    /// [`enable_code_info`][Self::enable_code_info]).
+    ///
+    /// By default no source code context is reported.
+    pub fn set_source_context(mut self, context: Option<SourceContext>) -> Builder {
+        self.source_context = context;
+        self
+    }
+
     /// Enable/disable transparent demangling of symbol names.
     ///
     /// Demangling happens on a best-effort basis. Currently supported languages
-    /// are Rust and C++ and the flag will have no effect if the underlying
-    /// language does not mangle symbols (such as C).
+    /// are Rust, C++, D, Swift, and Ada and the flag will have no effect if the
+    /// underlying language does not mangle symbols (such as C).
     pub fn enable_demangling(mut self, enable: bool) -> Builder {
         self.demangle = enable;
         self
     }
 
+    /// Set the options controlling the level of detail of demangled
+    /// symbol names.
+    pub fn set_demangle_opts(mut self, opts: DemangleOpts) -> Builder {
+        self.demangle_opts = opts;
+        self
+    }
+
+    /// Set the time budget for a single symbolization request.
+    ///
+    /// Once the budget is exhausted, symbolization is wound down and
+    /// addresses not yet symbolized are reported as
+    /// [`Symbolized::Unknown`] with [`Reason::TimedOut`], while results
+    /// already gathered are preserved. The budget is checked in between
+    /// operations. Potentially blocking operations, such as the opening
+    /// of files via the configured [`FileAccess`] implementation, are
+    /// performed on a helper thread that is abandoned once the budget
+    /// is exhausted. Downloads of debug information from debuginfod
+    /// servers are bounded by the remaining budget as well.
+    ///
+    /// By default no budget is set.
+    pub fn set_time_budget(mut self, budget: Option<Duration>) -> Builder {
+        self.time_budget = budget;
+        self
+    }
+
+    /// Set a callback to report progress of long running operations,
+    /// such as the indexing of DWARF debug information, to.
+    ///
+    /// The callback is invoked on the thread performing the operation.
+    pub fn set_progress_callback<F>(mut self, callback: F) -> Builder
+    where
+        F: Fn(&Progress) + Send + Sync + 'static,
+    {
+        self.progress = Some(ProgressCallback::new(callback));
+        self
+    }
+
+    /// Set the limits imposed on the parsing of input files, enabling
+    /// hardened parsing mode.
+    ///
+    /// Hardened parsing mode is meant for symbolizing untrusted inputs,
+    /// such as user provided binaries. In it, input exceeding the
+    /// provided [`Limits`] is rejected and, if the crate is compiled
+    /// with unwinding enabled (`panic = "unwind"`), a panic caused by
+    /// malformed input is reported as an error of kind
+    /// [`ErrorKind::InvalidData`] instead of being propagated. Note
+    /// that the panic is still reported through the panic hook and that
+    /// aborts, e.g., due to allocation failures, cannot be intercepted.
+    ///
+    /// By default no limits are in effect and parsing is unbounded.
+    pub fn set_limits(mut self, limits: Option<Limits>) -> Builder {
+        self.limits = limits;
+        self
+    }
+
+    /// Set the [`FileAccess`] implementation used for accessing files.
+    ///
+    /// By default, the local file system is used.
+    pub fn set_file_access<F>(mut self, fs: F) -> Builder
+    where
+        F: FileAccess + 'static,
+    {
+        self.fs = Arc::new(fs);
+        self
+    }
+
+    /// Set the socket of a [cache daemon][crate::daemon::Server] to
+    /// attach to.
+    ///
+    /// When set, symbols of ELF files symbolized without debug symbols
+    /// as well as kernel symbols in kallsyms format are retrieved from
+    /// the indexes maintained by the daemon, instead of being parsed by
+    /// the `Symbolizer` itself. Files are then accessed by the daemon,
+    /// bypassing the configured [`FileAccess`]. Indexes are cached for
+    /// the lifetime of the `Symbolizer`. Should the daemon fail to
+    /// provide an index, files are parsed locally.
+    ///
+    /// By default no daemon is used.
+    #[cfg(all(unix, feature = "cache-daemon"))]
+    pub fn set_cache_daemon(mut self, socket: Option<PathBuf>) -> Builder {
+        self.cache_daemon = socket;
+        self
+    }
+
+    /// Enable/disable background prefetching of process modules.
+    ///
+    /// When enabled, the first symbolization request for a
+    /// [`Process`] triggers the indexing of all ELF files mapped into
+    /// the process on a background thread, subject to the
+    /// [`Prefetch::memory_budget`]. Subsequent requests for the same
+    /// process are then likely to be served from warm caches.
+    ///
+    /// By default prefetching is disabled.
+    pub fn set_prefetch(mut self, prefetch: Option<Prefetch>) -> Builder {
+        self.prefetch = prefetch;
+        self
+    }
+
+    /// Set the hints provided to the kernel about the usage of large
+    /// memory mapped debug files and symbol indexes.
+    ///
+    /// See [`MmapAdvice`] for details.
+    ///
+    /// By default no hints are provided.
+    pub fn set_mmap_advice(mut self, advice: Option<MmapAdvice>) -> Builder {
+        self.mmap_advice = advice;
+        self
+    }
+
+    /// Enable/disable caching of symbolization results, holding at
+    /// most `capacity` results.
+    ///
+    /// When enabled, the final result of symbolizing an address is
+    /// remembered and reused when the same address is symbolized again
+    /// using the same version of the symbolization source. That is
+    /// meant for use cases such as continuous profiling, in which the
+    /// same addresses are symbolized over and over again. Once the
+    /// cache is full, the oldest results are evicted first. Results for
+    /// sources that are not cached themselves, such as
+    /// [`GsymData`][crate::symbolize::GsymData] or the kernel, are
+    /// never cached.
+    ///
+    /// By default results are not cached.
+    pub fn set_result_cache(mut self, capacity: Option<usize>) -> Builder {
+        self.result_cache = capacity;
+        self
+    }
+
+    /// Enable/disable caching of the parsed memory maps of processes,
+    /// with cached entries being valid for at most `ttl`.
+    ///
+    /// By default, the `/proc/<pid>/maps` file of a process is read and
+    /// parsed for every request symbolizing addresses in it. For long
+    /// running processes with many mappings that are symbolized at
+    /// high frequency, doing so may dominate the cost of
+    /// symbolization. When caching is enabled, the parsed entries are
+    /// reused by subsequent requests for the same process and only
+    /// refreshed when
+    /// - they are older than `ttl`
+    /// - they got invalidated using [`Symbolizer::notify_mmap`] or
+    ///   [`Symbolizer::notify_exec`] (or any of the cache eviction
+    ///   methods)
+    ///
+    /// - they do not cover all addresses of a request (e.g., because a
+    ///   shared object got loaded in the meantime), in which case the
+    ///   proc maps file is parsed once more and addresses still not
+    ///   covered are reported as [`Reason::Unmapped`]
+    ///
+    /// Until then, cached entries are reused even if mappings they
+    /// cover changed in the meantime (e.g., because a shared object
+    /// got unloaded).
+    ///
+    /// Users tracking memory mappings of processes (e.g., using BPF)
+    /// may want to pass in [`Duration::MAX`] and rely solely on
+    /// notifications.
+    ///
+    /// By default proc maps entries are not cached.
+    pub fn set_maps_cache(mut self, ttl: Option<Duration>) -> Builder {
+        self.maps_cache = ttl;
+        self
+    }
+
+    /// Set the limits on the files (and data parsed from them) cached
+    /// by the [`Symbolizer`].
+    ///
+    /// Limits are enforced by evicting the least recently used files
+    /// after each symbolization request (and when
+    /// [`Symbolizer::trim_cache`] is called). Evicted data is released
+    /// once no longer used by requests in flight.
+    ///
+    /// By default no limits are in effect.
+    pub fn set_cache_limits(mut self, limits: Option<CacheLimits>) -> Builder {
+        self.cache_limits = limits;
+        self
+    }
+
+    /// Set the maximum number of threads to use for
+    /// [`Symbolizer::symbolize_par`] (and for serving requests made
+    /// through the non-blocking front-end, if enabled).
+    ///
+    /// By default, the available parallelism of the system (as
+    /// reported by [`std::thread::available_parallelism`]) is used.
+    pub fn set_parallelism(mut self, threads: Option<NonZeroUsize>) -> Builder {
+        self.parallelism = threads;
+        self
+    }
+
+    /// Enable/disable deferred indexing of debug information.
+    ///
+    /// Indexing the debug information of a large binary can take a
+    /// considerable amount of time. When deferred indexing is enabled,
+    /// requests for ELF files (including those mapped into a
+    /// [`Process`]) whose debug information has not been indexed yet
+    /// are served based on the symbol table only, i.e., without source
+    /// code location and inlined function information. Indexing is
+    /// kicked off on a background thread instead and subsequent
+    /// requests report full results once it is done. Use
+    /// [`Symbolizer::refine`] to wait for indexing to complete.
+    ///
+    /// By default indexing is not deferred.
+    pub fn enable_deferred_indexing(mut self, enable: bool) -> Builder {
+        self.deferred_indexing = enable;
+        self
+    }
+
+    /// Set the way in which files of processes residing in a different
+    /// mount namespace (e.g., processes running inside of containers)
+    /// are accessed.
+    ///
+    /// See [`MountNsMode`] for details. [`MountNsMode::Enter`] must
+    /// only be used by single-threaded programs.
+    ///
+    /// By default [`MountNsMode::ProcRoot`] is used.
+    pub fn set_mount_ns_mode(mut self, mode: MountNsMode) -> Builder {
+        self.mount_ns_mode = mode;
+        self
+    }
+
+    /// Set the directories to search for separate debug information
+    /// files.
+    ///
+    /// For ELF files lacking debug information, a separate file
+    /// containing it is searched for
+    /// - by build ID, as `<dir>/.build-id/xx/yyy.debug` in each of the
+    ///   provided directories
+    /// - by the file name stored in the `.gnu_debuglink` section, in
+    ///   the directory of the ELF file, its `.debug/` sub-directory,
+    ///   and in the directory of the ELF file relative to each of the
+    ///   provided directories (e.g., `<dir>/usr/bin/`)
+    ///
+    /// Files found by build ID have to have a matching build ID and
+    /// files found via `.gnu_debuglink` have to match the checksum
+    /// stored alongside the name.
+    ///
+    /// Debug information files are only searched for when debug
+    /// symbols are to be used for a symbolization source.
+    ///
+    /// By default `/usr/lib/debug` is searched.
+    pub fn set_debug_dirs<I, P>(mut self, dirs: I) -> Builder
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.debug_dirs = dirs.into_iter().map(P::into).collect();
+        self
+    }
+
+    /// Enable/disable fetching of debug information from debuginfod
+    /// servers.
+    ///
+    /// When enabled, debug information for ELF files that lack it but
+    /// have a build ID is requested from the servers listed in the
+    /// `DEBUGINFOD_URLS` environment variable and cached on disk (in
+    /// `DEBUGINFOD_CACHE_PATH`, if set, and
+    /// `$XDG_CACHE_HOME/debuginfod_client` otherwise). The timeout for
+    /// connecting to a server and for receiving data from it can be
+    /// adjusted via `DEBUGINFOD_TIMEOUT`, in seconds. Downloads are
+    /// not limited in total duration, except by the time budget (see
+    /// [`Builder::set_time_budget`]). The environment is evaluated when
+    /// the [`Symbolizer`] is created.
+    ///
+    /// Debug information is only fetched when debug symbols are to be
+    /// used for a symbolization source.
+    ///
+    /// By default fetching is enabled, but it only takes effect if at
+    /// least one server is configured.
+    #[cfg(feature = "debuginfod")]
+    pub fn enable_debuginfod(mut self, enable: bool) -> Builder {
+        self.debuginfod = enable;
+        self
+    }
+
     /// Create the [`Symbolizer`] object.
     pub fn build(self) -> Symbolizer {
         let Builder {
             code_info,
             inlined_fns,
+            source_context,
             demangle,
+            demangle_opts,
+            time_budget,
+            progress,
+            limits,
+            fs,
+            #[cfg(all(unix, feature = "cache-daemon"))]
+            cache_daemon,
+            prefetch,
+            mmap_advice,
+            result_cache,
+            maps_cache,
+            cache_limits,
+            parallelism,
+            deferred_indexing,
+            mount_ns_mode,
+            debug_dirs,
+            #[cfg(feature = "debuginfod")]
+            debuginfod,
         } = self;
 
+        #[cfg(feature = "debuginfod")]
+        let debuginfod = if debuginfod {
+            debuginfod::Client::from_env().unwrap_or_else(|err| {
+                log::warn!("failed to create debuginfod client: {err}");
+                None
+            })
+        } else {
+            None
+        };
+
         Symbolizer {
-            apk_cache: FileCache::new(),
-            elf_cache: FileCache::new(),
-            gsym_cache: FileCache::new(),
-            ksym_cache: FileCache::new(),
+            demangle_cache: demangle::Cache::default(),
+            apk_cache: FileCache::with_file_access(fs.clone()).with_advice(mmap_advice.clone()),
+            elf_cache: Arc::new(
+                FileCache::with_file_access(fs.clone()).with_advice(mmap_advice.clone()),
+            ),
+            #[cfg(feature = "gsym")]
+            gsym_cache: FileCache::with_file_access(fs.clone()).with_advice(mmap_advice.clone()),
+            #[cfg(feature = "kernel")]
+            ksym_cache: FileCache::with_file_access(fs.clone()),
+            perf_map_cache: FileCache::with_file_access(fs.clone()),
+            jvm_perf_map_cache: FileCache::with_file_access(fs.clone()),
+            jitdump_cache: FileCache::with_file_access(fs.clone()),
+            linker_map_cache: FileCache::with_file_access(fs.clone()),
+            #[cfg(feature = "breakpad")]
+            breakpad_cache: FileCache::with_file_access(fs.clone()),
+            #[cfg(feature = "kernel")]
+            kmod_cache: InsertMap::new(),
+            #[cfg(feature = "kernel")]
+            kmod_index_cache: InsertMap::new(),
+            #[cfg(all(unix, feature = "cache-daemon"))]
+            daemon: cache_daemon.map(daemon::Client::new),
+            #[cfg(all(unix, feature = "cache-daemon"))]
+            index_cache: InsertMap::new(),
+            prefetch,
+            prefetched: Mutex::new(HashSet::new()),
+            #[cfg(all(unix, feature = "cache-daemon"))]
+            mmap_advice,
+            result_cache: result_cache.map(ResultCache::with_capacity),
+            maps_cache: maps_cache.map(MapsCache::new),
+            cache_limits,
+            parallelism,
+            deferred_indexing,
+            indexing: Mutex::new(HashSet::new()),
+            mount_ns_mode,
+            debug_dirs,
+            #[cfg(feature = "debuginfod")]
+            debuginfod,
+            debug_file_resolvers: InsertMap::new(),
+            debug_files: InsertMap::new(),
+            fs,
             code_info,
             inlined_fns,
+            source_context,
             demangle,
+            demangle_opts,
+            time_budget,
+            progress,
+            limits,
         }
     }
 }
@@ -192,7 +729,26 @@ impl Default for Builder {
         Self {
             code_info: true,
             inlined_fns: true,
+            source_context: None,
             demangle: true,
+            demangle_opts: DemangleOpts::default(),
+            time_budget: None,
+            progress: None,
+            limits: None,
+            fs: Arc::new(StdFileAccess::default()),
+            #[cfg(all(unix, feature = "cache-daemon"))]
+            cache_daemon: None,
+            prefetch: None,
+            mmap_advice: None,
+            result_cache: None,
+            maps_cache: None,
+            cache_limits: None,
+            parallelism: None,
+            deferred_indexing: false,
+            mount_ns_mode: MountNsMode::default(),
+            debug_dirs: vec![PathBuf::from(elf::DEFAULT_DEBUG_DIR)],
+            #[cfg(feature = "debuginfod")]
+            debuginfod: true,
         }
     }
 }
@@ -204,17 +760,51 @@ impl Default for Builder {
 /// An "uncached" resolver is one that is created on the spot. We do so for
 /// cases when we keep the input data, for example (e.g., when we have no
 /// control over its lifetime).
-/// A "cached" resolver is one that ultimately lives in one of our internal
-/// caches. These caches have the same lifetime as the `Symbolizer` object
-/// itself (represented here as `'slf`).
+/// A "cached" resolver is one that lives in one of our internal
+/// caches. Results produced by it may be stored in the result cache.
 ///
-/// Object of this type are at the core of our logic determining whether to
-/// heap allocate certain data such as paths or symbol names or whether to just
-/// hand out references to mmap'ed data.
+/// Irrespective of the kind, symbolization results never borrow from the
+/// resolver itself, as cached resolvers may be evicted at any point.
 #[derive(Debug)]
-enum Resolver<'tmp, 'slf> {
+enum Resolver<'tmp> {
     Uncached(&'tmp (dyn SymResolver + 'tmp)),
-    Cached(&'slf dyn SymResolver),
+    Cached(&'tmp (dyn SymResolver + 'tmp)),
+}
+
+impl<'tmp> Resolver<'tmp> {
+    /// Retrieve the wrapped [`SymResolver`].
+    fn inner(&self) -> &'tmp (dyn SymResolver + 'tmp) {
+        match self {
+            Self::Uncached(resolver) | Self::Cached(resolver) => *resolver,
+        }
+    }
+}
+
+
+/// The resolver backing a [`Tier`] of a [`Fallback`] chain.
+enum TierResolver<'dat> {
+    /// A resolver created for the duration of the request.
+    Uncached(Box<dyn SymResolver + 'dat>),
+    /// A resolver living in one of our caches.
+    Cached(Arc<dyn SymResolver + 'dat>),
+    /// The tier currently has no symbols to offer, for the provided
+    /// reason.
+    Unavailable(Reason),
+}
+
+
+/// The proc maps entries of a process, along with the means for
+/// accessing its files, as shared between the threads symbolizing
+/// addresses in it.
+#[derive(Debug)]
+struct SharedMaps {
+    /// The parsed proc maps entries.
+    entries: Arc<[MapsEntry]>,
+    /// The ID of the process, as usable for accessing its proc files.
+    maps_pid: Pid,
+    /// The path under which the root directory of the process' mount
+    /// namespace is accessible, if it has to be used.
+    root: Option<PathBuf>,
 }
 
 
@@ -229,16 +819,94 @@ enum Resolver<'tmp, 'slf> {
 /// [`symbolize_single`][Symbolizer::symbolize_single] calls) for the same
 /// symbolization source, you may want to consider creating a new `Symbolizer`
 /// instance regularly.
+///
+/// A `Symbolizer` is `Send` and `Sync` and a single instance can be
+/// shared between and used from multiple threads concurrently, without
+/// the need for external synchronization.
 #[derive(Debug)]
 pub struct Symbolizer {
     #[allow(clippy::type_complexity)]
-    apk_cache: FileCache<(zip::Archive, InsertMap<Range<u64>, Rc<ElfResolver>>)>,
-    elf_cache: FileCache<ElfResolverData>,
-    gsym_cache: FileCache<Rc<GsymResolver<'static>>>,
-    ksym_cache: FileCache<Rc<KSymResolver>>,
+    apk_cache: FileCache<Arc<(zip::Archive, InsertMap<Range<u64>, ElfResolver>)>>,
+    /// The cache of ELF resolvers; shared with prefetch threads.
+    elf_cache: Arc<FileCache<ElfResolverData>>,
+    #[cfg(feature = "gsym")]
+    gsym_cache: FileCache<Arc<GsymResolver<'static>>>,
+    #[cfg(feature = "kernel")]
+    ksym_cache: FileCache<Arc<KSymResolver>>,
+    /// The cache of Node.js style perf maps, with stale entries
+    /// removed.
+    perf_map_cache: FileCache<Arc<JitResolver>>,
+    /// The cache of JVM perf maps.
+    jvm_perf_map_cache: FileCache<Arc<JitResolver>>,
+    jitdump_cache: FileCache<Arc<JitResolver>>,
+    linker_map_cache: FileCache<Arc<LinkerMapResolver>>,
+    #[cfg(feature = "breakpad")]
+    breakpad_cache: FileCache<Arc<BreakpadResolver>>,
+    /// Resolvers for compressed kernel modules, keyed by path and
+    /// whether debug symbols are used.
+    #[cfg(feature = "kernel")]
+    kmod_cache: InsertMap<(PathBuf, bool), ElfResolver>,
+    /// Kernel module files by module name, keyed by module directory.
+    #[cfg(feature = "kernel")]
+    kmod_index_cache: InsertMap<PathBuf, HashMap<String, PathBuf>>,
+    /// The client of the cache daemon, if one is used.
+    #[cfg(all(unix, feature = "cache-daemon"))]
+    daemon: Option<daemon::Client>,
+    /// Indexes retrieved from the cache daemon; `None` if the daemon
+    /// failed to provide one.
+    #[cfg(all(unix, feature = "cache-daemon"))]
+    index_cache: InsertMap<daemon::Request, Option<Arc<IndexResolver>>>,
+    /// The configuration of background prefetching, if enabled.
+    prefetch: Option<Prefetch>,
+    /// The processes for which prefetching has been triggered already.
+    prefetched: Mutex<HashSet<Pid>>,
+    /// The hints to apply to memory mapped symbol indexes, if any.
+    #[cfg(all(unix, feature = "cache-daemon"))]
+    mmap_advice: Option<MmapAdvice>,
+    /// The cache of symbolization results, if enabled.
+    result_cache: Option<ResultCache>,
+    /// The cache of parsed proc maps entries, if enabled.
+    maps_cache: Option<MapsCache>,
+    /// The limits on cached data, if any.
+    cache_limits: Option<CacheLimits>,
+    /// The maximum number of threads to use for parallel
+    /// symbolization, if set.
+    parallelism: Option<NonZeroUsize>,
+    /// Whether to defer the indexing of debug information.
+    deferred_indexing: bool,
+    /// The files for which deferred indexing has been triggered
+    /// already.
+    indexing: Mutex<HashSet<PathBuf>>,
+    /// How to access files of processes in other mount namespaces.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    mount_ns_mode: MountNsMode,
+    /// The directories to search for separate debug information files.
+    debug_dirs: Vec<PathBuf>,
+    /// The client used for fetching debug information, if enabled and
+    /// configured.
+    #[cfg(feature = "debuginfod")]
+    debuginfod: Option<debuginfod::Client>,
+    /// ELF resolvers using separate debug information files, keyed by
+    /// the path of the ELF file lacking debug information. `None`
+    /// indicates that none is available.
+    debug_file_resolvers: InsertMap<PathBuf, Option<Arc<ElfResolver>>>,
+    /// ELF resolvers using separate debug information files, keyed by
+    /// the build ID of the ELF file lacking debug information. These
+    /// are shared between all paths referring to binaries with the same
+    /// build ID, e.g., as used by different processes.
+    debug_files: InsertMap<Vec<u8>, ElfResolver>,
     code_info: bool,
     inlined_fns: bool,
+    source_context: Option<SourceContext>,
     demangle: bool,
+    demangle_opts: DemangleOpts,
+    time_budget: Option<Duration>,
+    progress: Option<ProgressCallback>,
+    limits: Option<Limits>,
+    /// The provider used for accessing files.
+    fs: Arc<dyn FileAccess>,
+    /// A cache of demangled symbol names.
+    demangle_cache: demangle::Cache,
 }
 
 impl Symbolizer {
@@ -254,271 +922,800 @@ impl Symbolizer {
     }
 
     /// Demangle the provided symbol if asked for and possible.
-    fn maybe_demangle<'sym>(&self, symbol: Cow<'sym, str>, language: SrcLang) -> Cow<'sym, str> {
-        if self.demangle {
-            maybe_demangle(symbol, language)
+    fn maybe_demangle<'sym>(
+        &self,
+        symbol: Cow<'sym, str>,
+        language: SrcLang,
+        alloc: Alloc<'sym>,
+    ) -> Cow<'sym, str> {
+        if self.demangle && demangle::ENABLED {
+            self.demangle_cache
+                .get_or_demangle(symbol, language, alloc, |name| {
+                    demangle::demangle(name, language, &self.demangle_opts)
+                })
         } else {
             symbol
         }
     }
 
     /// Symbolize an address using the provided [`SymResolver`].
+    ///
+    /// Results from cached resolvers are looked up in and added to the
+    /// result cache, if enabled.
     #[cfg_attr(feature = "tracing", crate::log::instrument(skip_all, fields(addr = format_args!("{addr:#x}"), resolver = ?resolver)))]
     fn symbolize_with_resolver<'slf>(
         &'slf self,
         addr: Addr,
-        resolver: &Resolver<'_, 'slf>,
+        resolver: &Resolver<'_>,
+        alloc: Alloc<'slf>,
     ) -> Result<Symbolized<'slf>> {
-        let (sym_name, sym_addr, sym_size, lang) = match resolver {
-            Resolver::Uncached(resolver) => match resolver.find_sym(addr)? {
-                Ok(sym) => {
-                    let IntSym {
-                        name: sym_name,
-                        addr: sym_addr,
-                        size: sym_size,
-                        lang,
-                    } = sym;
-
-                    (Cow::Owned(sym_name.to_string()), sym_addr, sym_size, lang)
-                }
-                Err(reason) => return Ok(Symbolized::Unknown(reason)),
-            },
-            Resolver::Cached(resolver) => match resolver.find_sym(addr)? {
-                Ok(sym) => {
-                    let IntSym {
-                        name: sym_name,
-                        addr: sym_addr,
-                        size: sym_size,
-                        lang,
-                    } = sym;
-
-                    (Cow::Borrowed(sym_name), sym_addr, sym_size, lang)
-                }
-                Err(reason) => return Ok(Symbolized::Unknown(reason)),
-            },
+        let (cache, id) = match (&self.result_cache, resolver) {
+            (Some(cache), Resolver::Cached(resolver)) => {
+                // Results are dropped from the cache whenever cached
+                // resolvers get evicted, making their address a suitable
+                // identity.
+                (
+                    cache,
+                    *resolver as *const dyn SymResolver as *const () as usize,
+                )
+            }
+            _ => return self.symbolize_uncached(addr, resolver, alloc),
+        };
+
+        if let Some(symbolized) = cache.get(id, addr) {
+            return Ok(symbolized)
+        }
+
+        let symbolized = self.symbolize_uncached(addr, resolver, alloc)?;
+        let () = cache.insert(id, addr, &symbolized);
+        Ok(symbolized)
+    }
+
+    /// Symbolize an address using the provided [`SymResolver`],
+    /// bypassing the result cache.
+    fn symbolize_uncached<'slf>(
+        &'slf self,
+        addr: Addr,
+        resolver: &Resolver<'_>,
+        alloc: Alloc<'slf>,
+    ) -> Result<Symbolized<'slf>> {
+        if scope::timed_out() {
+            return Ok(Symbolized::Unknown(Reason::TimedOut))
+        }
+        let () = scope::check()?;
+
+        let resolver = resolver.inner();
+        let (sym_name, sym_addr, sym_size, lang) = match resolver.find_sym(addr)? {
+            Ok(sym) => {
+                let IntSym {
+                    name: sym_name,
+                    addr: sym_addr,
+                    size: sym_size,
+                    lang,
+                } = sym;
+
+                (alloc.str(sym_name), sym_addr, sym_size, lang)
+            }
+            Err(reason) => return Ok(Symbolized::Unknown(reason)),
         };
 
         let (name, code_info, inlined) = if self.code_info {
-            match resolver {
-                Resolver::Uncached(resolver) => {
-                    let addr_code_info = resolver.find_code_info(addr, self.inlined_fns)?;
-                    if let Some(AddrCodeInfo {
-                        direct: (direct_name, direct_code_info),
-                        inlined,
-                    }) = addr_code_info
-                    {
-                        let direct_name = direct_name.map(|name| Cow::Owned(name.to_string()));
-                        let direct_code_info = direct_code_info.to_owned();
-                        let inlined = inlined
-                            .into_iter()
-                            .map(|(name, info)| {
-                                let name = self.maybe_demangle(Cow::Owned(name.to_string()), lang);
-                                InlinedFn {
-                                    name,
-                                    code_info: info.map(|info| info.to_owned()),
-                                    _non_exhaustive: (),
-                                }
-                            })
-                            .collect();
-                        (direct_name, Some(direct_code_info), inlined)
-                    } else {
-                        (None, None, Vec::new())
-                    }
-                }
-                Resolver::Cached(resolver) => {
-                    let addr_code_info = resolver.find_code_info(addr, self.inlined_fns)?;
-                    if let Some(AddrCodeInfo {
-                        direct: (direct_name, direct_code_info),
-                        inlined,
-                    }) = addr_code_info
-                    {
-                        let direct_name = direct_name.map(Cow::Borrowed);
-                        let inlined = inlined
-                            .into_iter()
-                            .map(|(name, info)| {
-                                let name = self.maybe_demangle(Cow::Borrowed(name), lang);
-                                InlinedFn {
-                                    name,
-                                    code_info: info,
-                                    _non_exhaustive: (),
-                                }
-                            })
-                            .collect();
-                        (direct_name, Some(direct_code_info), inlined)
-                    } else {
-                        (None, None, Vec::new())
-                    }
-                }
+            let addr_code_info = resolver.find_code_info(addr, self.inlined_fns)?;
+            if let Some(AddrCodeInfo {
+                direct: (direct_name, direct_code_info),
+                inlined,
+            }) = addr_code_info
+            {
+                let direct_name = direct_name.map(|name| alloc.str(name));
+                let direct_code_info = alloc.code_info(&direct_code_info);
+                let inlined = inlined
+                    .into_iter()
+                    .map(|(name, info)| {
+                        let name = self.maybe_demangle(alloc.str(name), lang, alloc);
+                        InlinedFn {
+                            name,
+                            code_info: info.map(|info| alloc.code_info(&info)),
+                            _non_exhaustive: (),
+                        }
+                    })
+                    .collect();
+                (direct_name, Some(direct_code_info), inlined)
+            } else {
+                (None, None, Vec::new())
             }
         } else {
             (None, None, Vec::new())
         };
 
+        // If the symbolization source does not tell us the language, try
+        // inferring it from the symbol's mangling scheme.
+        let detected_lang = match lang {
+            SrcLang::Unknown => SrcLang::from_mangled_name(&sym_name),
+            lang => lang,
+        };
+
+        let IntModule {
+            path,
+            build_id,
+            file_offset,
+        } = resolver.module_info(addr)?;
+        let module = path.map(|path| alloc.path(path));
+        let build_id = build_id.map(|id| alloc.bytes(id));
+
         let sym = Sym {
-            name: self.maybe_demangle(name.unwrap_or(sym_name), lang),
+            name: self.maybe_demangle(name.unwrap_or(sym_name), lang, alloc),
             addr: sym_addr,
             offset: (addr - sym_addr) as usize,
             size: sym_size,
+            lang: detected_lang,
             code_info,
             inlined: inlined.into_boxed_slice(),
+            module,
+            build_id,
+            file_offset,
             _non_exhaustive: (),
         };
         Ok(Symbolized::Sym(sym))
     }
 
+    /// Attach source code context to the source code location
+    /// information of the symbols in `symbolized`, if enabled.
+    ///
+    /// `root` is the path under which the root directory of the mount
+    /// namespace the source files reside in is accessible, if it may
+    /// differ from ours.
+    fn add_source_context<'sym, 'slf: 'sym, I>(&self, symbolized: I, root: Option<&Path>)
+    where
+        I: IntoIterator<Item = &'sym mut Symbolized<'slf>>,
+    {
+        let context = match &self.source_context {
+            Some(context) if self.code_info => context,
+            _ => return,
+        };
+
+        // Source files are commonly referenced by many frames, so make
+        // sure to only open each once.
+        let mut files = HashMap::<PathBuf, Option<FileData>>::new();
+        let code_infos = symbolized
+            .into_iter()
+            .filter_map(|symbolized| match symbolized {
+                Symbolized::Sym(sym) => Some(sym),
+                Symbolized::Unknown(..) => None,
+            })
+            .flat_map(|sym| {
+                sym.code_info.iter_mut().chain(
+                    sym.inlined
+                        .iter_mut()
+                        .filter_map(|inlined| inlined.code_info.as_mut()),
+                )
+            });
+
+        for code_info in code_infos {
+            let line = match code_info.line {
+                Some(line) => line,
+                None => continue,
+            };
+            let path = match context.resolve(&code_info.to_path(), root) {
+                Some(path) => path,
+                None => continue,
+            };
+            let data = files
+                .entry(path)
+                .or_insert_with_key(|path| match self.fs.open(path) {
+                    Ok(data) => Some(data),
+                    Err(err) => {
+                        log::debug!("failed to open source file {}: {err}", path.display());
+                        None
+                    }
+                });
+            if let Some(data) = data {
+                code_info.context = context.extract(data.data(), line);
+            }
+        }
+    }
+
     /// Symbolize a list of addresses using the provided [`SymResolver`].
     fn symbolize_addrs<'slf>(
         &'slf self,
         addrs: &[Addr],
-        resolver: &Resolver<'_, 'slf>,
-    ) -> Result<Vec<Symbolized>> {
+        resolver: &Resolver<'_>,
+        alloc: Alloc<'slf>,
+    ) -> Result<Vec<Symbolized<'slf>>> {
         addrs
             .iter()
-            .map(|addr| self.symbolize_with_resolver(*addr, resolver))
+            .map(|addr| self.symbolize_with_resolver(*addr, resolver, alloc))
             .collect()
     }
 
-    fn create_gsym_resolver(&self, path: &Path, file: &File) -> Result<Rc<GsymResolver<'static>>> {
-        let resolver = GsymResolver::from_file(path.to_path_buf(), file)?;
-        Ok(Rc::new(resolver))
+    #[cfg(feature = "gsym")]
+    fn create_gsym_resolver(
+        &self,
+        path: &Path,
+        data: &FileData,
+    ) -> Result<Arc<GsymResolver<'static>>> {
+        let resolver = GsymResolver::from_mmap(path.to_path_buf(), data.mmap().clone())?;
+        Ok(Arc::new(resolver))
     }
 
-    fn gsym_resolver<'slf>(&'slf self, path: &Path) -> Result<&'slf Rc<GsymResolver<'static>>> {
-        let (file, cell) = self.gsym_cache.entry(path)?;
-        let resolver = cell.get_or_try_init(|| self.create_gsym_resolver(path, file))?;
-        Ok(resolver)
+    #[cfg(feature = "gsym")]
+    fn gsym_resolver(&self, path: &Path) -> Result<Arc<GsymResolver<'static>>> {
+        let entry = self.gsym_cache.entry(path)?;
+        let resolver = entry
+            .value()
+            .get_or_try_init(|| self.create_gsym_resolver(path, entry.data()))?;
+        Ok(Arc::clone(resolver))
     }
 
-    fn create_apk_resolver<'slf>(
-        &'slf self,
+    fn create_apk_resolver(
+        &self,
         apk: &zip::Archive,
         apk_path: &Path,
         file_off: u64,
         debug_syms: bool,
-        resolver_map: &'slf InsertMap<Range<u64>, Rc<ElfResolver>>,
-    ) -> Result<Option<(&'slf Rc<ElfResolver>, Addr)>> {
+        resolver_map: &InsertMap<Range<u64>, ElfResolver>,
+    ) -> Result<Option<(Arc<ElfResolver>, Addr)>> {
         // Find the APK entry covering the calculated file offset.
-        for apk_entry in apk.entries() {
-            let apk_entry = apk_entry?;
-            let bounds = apk_entry.data_offset..apk_entry.data_offset + apk_entry.data.len() as u64;
-
-            if bounds.contains(&file_off) {
-                let resolver = resolver_map.get_or_try_insert(bounds.clone(), || {
-                    let mmap = apk
-                        .mmap()
-                        .constrain(bounds.clone())
-                        .ok_or_invalid_input(|| {
-                            format!(
-                                "invalid APK entry data bounds ({bounds:?}) in {}",
-                                apk_path.display()
-                            )
-                        })?;
-                    // Create an Android-style binary-in-APK path for
-                    // reporting purposes.
-                    let apk_elf_path = create_apk_elf_path(apk_path, apk_entry.path)?;
-                    let parser = Rc::new(ElfParser::from_mmap(mmap));
-                    let resolver = ElfResolver::from_parser(
-                        &apk_elf_path,
-                        parser,
-                        debug_syms,
-                        self.code_info,
-                    )?;
-                    let resolver = Rc::new(resolver);
-                    Ok(resolver)
-                })?;
-
-                let elf_off = file_off - apk_entry.data_offset;
-                if let Some(addr) = elf_offset_to_address(elf_off, resolver.parser())? {
-                    return Ok(Some((resolver, addr)))
-                }
-                break
+        if let Some(apk_entry) = apk.entry_at_offset(file_off)? {
+            let resolver =
+                self.apk_entry_resolver(apk, apk_path, &apk_entry, debug_syms, resolver_map)?;
+            let elf_off = file_off - apk_entry.data_offset;
+            if let Some(addr) = elf_offset_to_address(elf_off, resolver.parser())? {
+                return Ok(Some((resolver, addr)))
             }
         }
 
         Ok(None)
     }
 
-    fn apk_resolver<'slf>(
-        &'slf self,
-        path: &Path,
-        file_off: u64,
+    /// Retrieve the resolver for the ELF file stored in the provided
+    /// APK entry, creating it if necessary.
+    fn apk_entry_resolver(
+        &self,
+        apk: &zip::Archive,
+        apk_path: &Path,
+        apk_entry: &zip::Entry<'_>,
         debug_syms: bool,
-    ) -> Result<Option<(&'slf Rc<ElfResolver>, Addr)>> {
-        let (file, cell) = self.apk_cache.entry(path)?;
-        let (apk, resolvers) = cell.get_or_try_init(|| {
-            let apk = zip::Archive::with_mmap(Mmap::builder().map(file)?)?;
+        resolver_map: &InsertMap<Range<u64>, ElfResolver>,
+    ) -> Result<Arc<ElfResolver>> {
+        let bounds = apk_entry.data_offset..apk_entry.data_offset + apk_entry.data.len() as u64;
+        resolver_map.get_or_try_insert(bounds.clone(), || {
+            let mmap = apk
+                .mmap()
+                .constrain(bounds.clone())
+                .ok_or_invalid_input(|| {
+                    format!(
+                        "invalid APK entry data bounds ({bounds:?}) in {}",
+                        apk_path.display()
+                    )
+                })?;
+            // Create an Android-style binary-in-APK path for
+            // reporting purposes.
+            let apk_elf_path = create_apk_elf_path(apk_path, apk_entry.path)?;
+            let parser = Arc::new(ElfParser::from_mmap(mmap));
+            let resolver = ElfResolver::from_parser(
+                &apk_elf_path,
+                parser,
+                &self.fs,
+                debug_syms,
+                self.code_info,
+                None,
+            )?;
+            Ok(resolver)
+        })
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn apk(&self, path: &Path) -> Result<Arc<(zip::Archive, InsertMap<Range<u64>, ElfResolver>)>> {
+        let entry = self.apk_cache.entry(path)?;
+        let apk = entry.value().get_or_try_init(|| {
+            let apk = zip::Archive::with_mmap(entry.data().mmap().clone())?;
             let resolvers = InsertMap::new();
-            Result::<_, Error>::Ok((apk, resolvers))
+            Result::<_, Error>::Ok(Arc::new((apk, resolvers)))
         })?;
+        Ok(Arc::clone(apk))
+    }
 
+    fn apk_resolver(
+        &self,
+        path: &Path,
+        file_off: u64,
+        debug_syms: bool,
+    ) -> Result<Option<(Arc<ElfResolver>, Addr)>> {
+        let apk = self.apk(path)?;
+        let (apk, resolvers) = apk.deref();
         let result = self.create_apk_resolver(apk, path, file_off, debug_syms, resolvers);
         result
     }
 
-    fn resolve_addr_in_elf(&self, addr: Addr, path: &Path, debug_syms: bool) -> Result<Symbolized> {
-        let resolver = self
-            .elf_cache
-            .elf_resolver(path, debug_syms, self.code_info)?;
-        let symbolized = self.symbolize_with_resolver(addr, &Resolver::Cached(resolver.deref()))?;
-        Ok(symbolized)
-    }
-
-    /// Symbolize the given list of user space addresses in the provided
-    /// process.
-    fn symbolize_user_addrs(
+    /// Retrieve the resolver for the ELF file at `elf_path` inside the
+    /// APK at `apk_path`.
+    ///
+    /// The ELF file has to be stored uncompressed, which is the case
+    /// for libraries that Android loads directly from the APK.
+    fn apk_elf_resolver(
         &self,
-        addrs: &[Addr],
-        pid: Pid,
+        apk_path: &Path,
+        elf_path: &Path,
         debug_syms: bool,
-    ) -> Result<Vec<Symbolized>> {
-        struct SymbolizeHandler<'sym> {
-            /// The "outer" `Symbolizer` instance.
-            symbolizer: &'sym Symbolizer,
-            /// Whether or not to consult debug symbols to satisfy the request
-            /// (if present).
-            debug_syms: bool,
-            /// Symbols representing the symbolized addresses.
-            all_symbols: Vec<Symbolized<'sym>>,
-        }
+    ) -> Result<Arc<ElfResolver>> {
+        let apk = self.apk(apk_path)?;
+        let (apk, resolvers) = apk.deref();
+        for apk_entry in apk.entries() {
+            let apk_entry = apk_entry?;
+            if apk_entry.path != elf_path {
+                continue
+            }
 
-        impl SymbolizeHandler<'_> {
-            fn handle_apk_addr(&mut self, addr: Addr, entry: &PathMapsEntry) -> Result<()> {
-                let file_off = addr - entry.range.start + entry.offset;
-                let apk_path = &entry.path.symbolic_path;
-                match self
-                    .symbolizer
-                    .apk_resolver(apk_path, file_off, self.debug_syms)?
-                {
-                    Some((elf_resolver, elf_addr)) => {
-                        let symbol = self.symbolizer.symbolize_with_resolver(
-                            elf_addr,
-                            &Resolver::Cached(elf_resolver.deref()),
-                        )?;
-                        let () = self.all_symbols.push(symbol);
-                        Ok(())
-                    }
-                    None => self.handle_unknown_addr(addr, Reason::InvalidFileOffset),
-                }
+            if apk_entry.compression != 0 {
+                return Err(Error::with_unsupported(format!(
+                    "{} in {} is compressed",
+                    elf_path.display(),
+                    apk_path.display()
+                )))
             }
+            return self.apk_entry_resolver(apk, apk_path, &apk_entry, debug_syms, resolvers)
+        }
 
-            fn handle_elf_addr(&mut self, addr: Addr, entry: &PathMapsEntry) -> Result<()> {
-                let path = &entry.path.maps_file;
-                let file_off = addr - entry.range.start + entry.offset;
-                let parser = ElfParser::open(&entry.path.maps_file).with_context(|| {
-                    format!("failed to open map file {}", entry.path.maps_file.display())
-                })?;
+        Err(Error::with_not_found(format!(
+            "{} not found in {}",
+            elf_path.display(),
+            apk_path.display()
+        )))
+    }
+
+    /// Retrieve the index for the file referenced by `request` from the
+    /// cache daemon, if one is used and able to provide it.
+    #[cfg(all(unix, feature = "cache-daemon"))]
+    fn index_resolver(&self, request: daemon::Request) -> Result<Option<Arc<IndexResolver>>> {
+        let client = match &self.daemon {
+            Some(client) => client,
+            None => return Ok(None),
+        };
+
+        let resolver = self.index_cache.get_or_try_insert(request.clone(), || {
+            let result = client.index(&request);
+            match result {
+                Ok(resolver) => {
+                    if let Some(advice) = &self.mmap_advice {
+                        let () = advice.apply(resolver.mmap());
+                    }
+                    Ok(Some(Arc::new(resolver)))
+                }
+                Err(err) => {
+                    log::warn!(
+                        "failed to retrieve index for {} from cache daemon: {err}; parsing locally...",
+                        request.path().display()
+                    );
+                    Ok(None)
+                }
+            }
+        })?;
+        Ok(resolver.as_ref().clone())
+    }
+
+    /// Retrieve the resolver for the ELF file at `path`.
+    ///
+    /// If deferred indexing is enabled and debug information has not
+    /// been indexed yet, indexing is kicked off in the background and a
+    /// resolver working on the symbol table only is returned.
+    fn elf_resolver(&self, path: &Path, debug_syms: bool) -> Result<Arc<ElfResolver>> {
+        if debug_syms && self.deferred_indexing && !scope::blocking() {
+            if let Some(resolver) = self.elf_cache.indexed_elf_resolver(path)? {
+                return Ok(resolver)
+            }
+
+            let first = self
+                .indexing
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(path.to_path_buf());
+            if first {
+                let job = PrefetchJob {
+                    elf_cache: Arc::clone(&self.elf_cache),
+                    paths: vec![path.to_path_buf()],
+                    memory_budget: u64::MAX,
+                    debug_syms: true,
+                    code_info: self.code_info,
+                    limits: self.limits,
+                };
+                let () = job.spawn();
+            }
+            return self.elf_cache.elf_resolver(path, false, self.code_info)
+        }
+
+        let resolver = self
+            .elf_cache
+            .elf_resolver(path, debug_syms, self.code_info)?;
+        if debug_syms {
+            if let Some(resolver) = self.debug_file_resolver(path, &resolver)? {
+                return Ok(resolver)
+            }
+        }
+        Ok(resolver)
+    }
+
+    /// Locate the separate debug information file for the ELF file at
+    /// `path`, either on the local system or, if enabled, by fetching
+    /// it via debuginfod.
+    fn find_debug_file(&self, path: &Path, parser: &ElfParser) -> Result<Option<PathBuf>> {
+        if let Some(debug_path) = elf::find_debug_file(path, parser, &self.debug_dirs)? {
+            return Ok(Some(debug_path))
+        }
+
+        #[cfg(feature = "debuginfod")]
+        if let Some(client) = &self.debuginfod {
+            if let Some(build_id) = BuildId::from_parser(parser)? {
+                return client.fetch_debuginfo(&build_id.id)
+            }
+        }
+        Ok(None)
+    }
+
+    /// Retrieve a resolver using a separate debug information file for
+    /// the ELF file at `path`, if it lacks debug information itself.
+    fn debug_file_resolver(
+        &self,
+        path: &Path,
+        resolver: &ElfResolver,
+    ) -> Result<Option<Arc<ElfResolver>>> {
+        let parser = resolver.parser();
+        if parser.find_section(".debug_info")?.is_some() {
+            return Ok(None)
+        }
+
+        let resolver = self
+            .debug_file_resolvers
+            .get_or_try_insert(path.to_path_buf(), || {
+                let debug_path = if let Some(debug_path) = self.find_debug_file(path, parser)? {
+                    debug_path
+                } else {
+                    return Ok(None)
+                };
+                let create = || {
+                    // Debug information files live on the local system,
+                    // irrespective of the configured `FileAccess`.
+                    let owned = debug_path.clone();
+                    let data = scope::run_blocking(move || StdFileAccess::default().open(&owned))?;
+                    let debug_parser = Arc::new(ElfParser::from_mmap(data.mmap().clone()));
+                    // Debug information files contain the same symbols at
+                    // the same addresses as the original binary, so we can
+                    // use them as a drop-in replacement.
+                    let resolver = ElfResolver::from_parser(
+                        path,
+                        debug_parser,
+                        &self.fs,
+                        true,
+                        self.code_info,
+                        None,
+                    )
+                    .with_context(|| {
+                        format!(
+                            "failed to create resolver for debug information file {}",
+                            debug_path.display()
+                        )
+                    })?
+                    // Module information, however, should still
+                    // refer to the original binary.
+                    .with_binary(parser.clone());
+                    Result::<_, Error>::Ok(resolver)
+                };
+
+                // Binaries with the same build ID share the parsed debug
+                // information, irrespective of the path (and, hence,
+                // process or mount namespace) they are accessed through.
+                let resolver = match BuildId::from_parser(parser)? {
+                    Some(build_id) => self.debug_files.get_or_try_insert(build_id.id, create)?,
+                    None => Arc::new(create()?),
+                };
+                Ok(Some(resolver))
+            })?;
+        Ok(resolver.as_ref().clone())
+    }
+
+    /// Retrieve the resolver for an ELF file provided as
+    /// [`Source::Elf`].
+    fn elf_src_resolver(
+        &self,
+        path: &Path,
+        debug_syms: bool,
+        hot_ranges: Option<&[Range<Addr>]>,
+    ) -> Result<Arc<ElfResolver>> {
+        if let Some((apk_path, elf_path)) = split_apk_elf_path(path) {
+            return self.apk_elf_resolver(&apk_path, &elf_path, debug_syms)
+        }
+
+        match hot_ranges {
+            Some(hot_ranges) if debug_syms => {
+                self.elf_cache
+                    .hot_elf_resolver(path, hot_ranges, self.code_info)
+            }
+            _ => self.elf_resolver(path, debug_syms),
+        }
+    }
+
+    /// Retrieve the resolver to use for looking up symbols in the ELF
+    /// file at `path`, with `resolver` being the one created locally.
+    #[cfg_attr(not(all(unix, feature = "cache-daemon")), allow(unused_variables))]
+    fn elf_sym_resolver(
+        &self,
+        path: &Path,
+        debug_syms: bool,
+        resolver: &Arc<ElfResolver>,
+    ) -> Result<Arc<dyn SymResolver>> {
+        #[cfg(all(unix, feature = "cache-daemon"))]
+        if !debug_syms {
+            let request = daemon::Request::Elf(path.to_path_buf());
+            if let Some(index) = self.index_resolver(request)? {
+                return Ok(index)
+            }
+        }
+        Ok(resolver.clone())
+    }
+
+    fn resolve_addr_in_elf<'slf>(
+        &'slf self,
+        addr: Addr,
+        path: &Path,
+        debug_syms: bool,
+        alloc: Alloc<'slf>,
+    ) -> Result<Symbolized<'slf>> {
+        let resolver = self.elf_resolver(path, debug_syms)?;
+        let resolver = self.elf_sym_resolver(path, debug_syms, &resolver)?;
+        let symbolized =
+            self.symbolize_with_resolver(addr, &Resolver::Cached(resolver.deref()), alloc)?;
+        Ok(symbolized)
+    }
+
+    /// Symbolize an address in an ELF file provided as
+    /// [`Source::Elf`].
+    fn symbolize_elf_src_addr<'slf>(
+        &'slf self,
+        addr: Addr,
+        path: &Path,
+        resolver: &dyn SymResolver,
+        alloc: Alloc<'slf>,
+    ) -> Result<Symbolized<'slf>> {
+        let mut symbolized =
+            self.symbolize_with_resolver(addr, &Resolver::Cached(resolver), alloc)?;
+        if let Symbolized::Sym(sym) = &mut symbolized {
+            // The resolver may be shared with other paths referring to
+            // the same file, so report the one provided by the user.
+            sym.module = Some(alloc.path(path));
+        }
+        Ok(symbolized)
+    }
+
+    /// Symbolize the given list of user space addresses using the
+    /// provided proc maps entries.
+    ///
+    /// `root` is the path under which the root directory of the mount
+    /// namespace the entries belong to is accessible, if it has to be
+    /// used.
+    fn symbolize_entries_addrs<'slf, E>(
+        &'slf self,
+        addrs: &[Addr],
+        entries: E,
+        pid: Option<Pid>,
+        root: Option<PathBuf>,
+        debug_syms: bool,
+        alloc: Alloc<'slf>,
+    ) -> Result<Vec<Symbolized<'slf>>>
+    where
+        E: Iterator<Item = Result<MapsEntry>>,
+    {
+        struct SymbolizeHandler<'sym> {
+            /// The "outer" `Symbolizer` instance.
+            symbolizer: &'sym Symbolizer,
+            /// Whether or not to consult debug symbols to satisfy the request
+            /// (if present).
+            debug_syms: bool,
+            /// Where to allocate symbolization result data.
+            alloc: Alloc<'sym>,
+            /// Symbols representing the symbolized addresses.
+            all_symbols: Vec<Symbolized<'sym>>,
+            /// The process whose memory the addresses belong to, if it
+            /// is still running.
+            pid: Option<Pid>,
+            /// The path under which the root directory of the process'
+            /// mount namespace is accessible, if it has to be used.
+            root: Option<PathBuf>,
+            /// The resolver for the process' vDSO, along with the
+            /// address range it is mapped at, once loaded.
+            vdso: Option<(Range<Addr>, Option<ElfResolver>)>,
+        }
+
+        impl SymbolizeHandler<'_> {
+            fn handle_apk_addr(
+                &mut self,
+                addr: Addr,
+                entry: &PathMapsEntry,
+                apk_path: &Path,
+            ) -> Result<()> {
+                let file_off = addr - entry.range.start + entry.offset;
+                let apk_path = match &self.root {
+                    Some(root) => {
+                        // `join` would discard `root` for absolute paths.
+                        let mut path = root.clone().into_os_string();
+                        let () = path.push(apk_path.as_os_str());
+                        Cow::Owned(PathBuf::from(path))
+                    }
+                    None => Cow::Borrowed(apk_path),
+                };
+                match self
+                    .symbolizer
+                    .apk_resolver(&apk_path, file_off, self.debug_syms)?
+                {
+                    Some((elf_resolver, elf_addr)) => {
+                        let symbol = self.symbolizer.symbolize_with_resolver(
+                            elf_addr,
+                            &Resolver::Cached(elf_resolver.deref()),
+                            self.alloc,
+                        )?;
+                        let () = self.all_symbols.push(symbol);
+                        Ok(())
+                    }
+                    None => self.handle_unknown_addr(addr, Reason::InvalidFileOffset),
+                }
+            }
+
+            fn handle_vdso_addr(&mut self, addr: Addr, entry: &MapsEntry, pid: Pid) -> Result<()> {
+                let vdso = match &self.vdso {
+                    Some((range, resolver)) if *range == entry.range => resolver,
+                    _ => {
+                        let result =
+                            vdso::read_image(pid, &entry.range).and_then(vdso::create_resolver);
+                        let resolver = match result {
+                            Ok(resolver) => Some(resolver),
+                            Err(err) => {
+                                log::warn!("failed to load vDSO of process {pid}: {err:#}");
+                                None
+                            }
+                        };
+                        &self.vdso.insert((entry.range.clone(), resolver)).1
+                    }
+                };
+
+                let resolver = match vdso {
+                    Some(resolver) => resolver,
+                    None => {
+                        let () = self
+                            .all_symbols
+                            .push(Symbolized::Unknown(Reason::SpecialRegion(
+                                SpecialRegion::Vdso,
+                            )));
+                        return Ok(())
+                    }
+                };
+
+                // The vDSO is mapped in its entirety, starting at the
+                // beginning of the image.
+                let file_off = addr - entry.range.start;
+                match elf_offset_to_address(file_off, resolver.parser())? {
+                    Some(norm_addr) => {
+                        let symbol = self.symbolizer.symbolize_with_resolver(
+                            norm_addr,
+                            &Resolver::Uncached(resolver),
+                            self.alloc,
+                        )?;
+                        let () = self.all_symbols.push(symbol);
+                    }
+                    None => {
+                        let () = self
+                            .all_symbols
+                            .push(Symbolized::Unknown(Reason::InvalidFileOffset));
+                    }
+                }
+                Ok(())
+            }
+
+            /// Open the ELF file backing `entry`.
+            ///
+            /// If the file cannot be accessed via `/proc/<pid>/map_files/`,
+            /// we fall back to `/proc/<pid>/exe` for the main executable
+            /// and, lastly, to the file on disk, provided it was not
+            /// deleted and its build ID matches that of the mapped file.
+            fn open_elf<'entry>(
+                &self,
+                entry: &'entry PathMapsEntry,
+            ) -> Result<Result<(Cow<'entry, Path>, FileData), Reason>> {
+                let maps_file = &entry.path.maps_file;
+                let err = match self.symbolizer.fs.open(maps_file) {
+                    Ok(data) => return Ok(Ok((Cow::Borrowed(maps_file), data))),
+                    Err(err) => err,
+                };
+
+                let pid = match self.pid {
+                    Some(pid)
+                        if matches!(
+                            err.kind(),
+                            ErrorKind::NotFound | ErrorKind::PermissionDenied
+                        ) =>
+                    {
+                        pid
+                    }
+                    _ => {
+                        return Err(err).with_context(|| {
+                            format!("failed to open map file {}", maps_file.display())
+                        })
+                    }
+                };
+
+                let symbolic_path = &entry.path.symbolic_path;
+                // The kernel keeps the original image of the main
+                // executable accessible, even if it was deleted.
+                if image::is_exe(pid, symbolic_path) {
+                    let exe = image::exe_path(pid);
+                    if let Ok(data) = self.symbolizer.fs.open(&exe) {
+                        return Ok(Ok((Cow::Owned(exe), data)))
+                    }
+                }
+
+                if !entry.path.deleted {
+                    let path = match &self.root {
+                        Some(root) => {
+                            // `join` would discard `root` for absolute paths.
+                            let mut path = root.clone().into_os_string();
+                            let () = path.push(symbolic_path.as_os_str());
+                            PathBuf::from(path)
+                        }
+                        None => symbolic_path.clone(),
+                    };
+
+                    if let Ok(data) = self.symbolizer.fs.open(&path) {
+                        // The file may have been replaced since it got
+                        // mapped. Make sure that we don't symbolize
+                        // using a different one.
+                        match image::read_mapped_build_id(pid, entry) {
+                            Ok(Some(expected)) => {
+                                let parser = ElfParser::from_mmap(data.mmap().clone());
+                                let build_id = DefaultBuildIdReader::read_build_id(&parser)?;
+                                if build_id.as_deref() != Some(expected.as_slice()) {
+                                    return Ok(Err(Reason::BuildIdMismatch))
+                                }
+                            }
+                            Ok(None) => (),
+                            Err(err) => {
+                                log::debug!(
+                                    "failed to read build ID of {} from memory: {err:#}",
+                                    symbolic_path.display()
+                                );
+                            }
+                        }
+                        return Ok(Ok((Cow::Owned(path), data)))
+                    }
+                }
+
+                Err(err).with_context(|| format!("failed to open map file {}", maps_file.display()))
+            }
+
+            fn handle_elf_addr(&mut self, addr: Addr, entry: &PathMapsEntry) -> Result<()> {
+                let file_off = addr - entry.range.start + entry.offset;
+                let (path, data) = match self.open_elf(entry)? {
+                    Ok(opened) => opened,
+                    Err(reason) => return self.handle_unknown_addr(addr, reason),
+                };
+                let path = path.as_ref();
+                let parser = ElfParser::from_mmap(data.mmap().clone());
 
                 match elf_offset_to_address(file_off, &parser)? {
                     Some(norm_addr) => {
-                        let symbol = self
+                        let mut symbol = self
                             .symbolizer
-                            .resolve_addr_in_elf(norm_addr, path, self.debug_syms)
+                            .resolve_addr_in_elf(norm_addr, path, self.debug_syms, self.alloc)
                             .with_context(|| {
                                 format!(
                                     "failed to symbolize normalized address {norm_addr:#x} in ELF file {}",
                                     path.display()
                                 )
                             })?;
+                        if let Symbolized::Sym(sym) = &mut symbol {
+                            // Report the module by the path under which
+                            // it is mapped, not the (potentially
+                            // process specific) one we used for
+                            // accessing it.
+                            sym.module = Some(self.alloc.path(&entry.path.symbolic_path));
+                            sym.file_offset = Some(file_off);
+                        }
                         let () = self.all_symbols.push(symbol);
                         Ok(())
                     }
@@ -534,29 +1731,65 @@ impl Symbolizer {
                 Ok(())
             }
 
+            fn handle_other_entry_addr(
+                &mut self,
+                addr: Addr,
+                entry: &MapsEntry,
+                reason: Reason,
+            ) -> Result<()> {
+                if let Some(pid) = self.pid {
+                    if vdso::is_vdso(entry) {
+                        return self.handle_vdso_addr(addr, entry, pid)
+                    }
+                }
+
+                let reason = classify_special_region(entry)
+                    .map(Reason::SpecialRegion)
+                    .unwrap_or(reason);
+                self.handle_unknown_addr(addr, reason)
+            }
+
             fn handle_entry_addr(&mut self, addr: Addr, entry: &PathMapsEntry) -> Result<()> {
                 let ext = entry
                     .path
                     .symbolic_path
                     .extension()
                     .unwrap_or_else(|| OsStr::new(""));
-                match ext.to_str() {
-                    Some("apk") | Some("zip") => self.handle_apk_addr(addr, entry),
-                    _ => self.handle_elf_addr(addr, entry),
+                // Libraries loaded directly from an APK may be reported
+                // using an Android-style `base.apk!/lib/libfoo.so` path,
+                // with the offset being relative to the APK.
+                let result = if let Some((apk_path, _elf_path)) =
+                    split_apk_elf_path(&entry.path.symbolic_path)
+                {
+                    self.handle_apk_addr(addr, entry, &apk_path)
+                } else {
+                    match ext.to_str() {
+                        Some("apk") | Some("zip") => {
+                            self.handle_apk_addr(addr, entry, &entry.path.symbolic_path)
+                        }
+                        _ => self.handle_elf_addr(addr, entry),
+                    }
+                };
+
+                match result {
+                    // Preserve the results gathered so far if we ran out
+                    // of time.
+                    Err(err) if err.kind() == ErrorKind::TimedOut && scope::timed_out() => {
+                        self.handle_unknown_addr(addr, Reason::TimedOut)
+                    }
+                    result => result,
                 }
             }
         }
 
-        #[cfg(target_os = "linux")]
-        let nsi = NsInfo::new(pid)?;
-        #[cfg(target_os = "linux")]
-        let entries = maps::parse(nsi.pid())?;
-        #[cfg(not(target_os = "linux"))]
-        let entries = maps::parse(pid)?;
         let handler = SymbolizeHandler {
             symbolizer: self,
             debug_syms,
+            alloc,
             all_symbols: Vec::with_capacity(addrs.len()),
+            pid,
+            root,
+            vdso: None,
         };
 
         let handler = util::with_ordered_elems(
@@ -574,31 +1807,547 @@ impl Symbolizer {
         Ok(handler.all_symbols)
     }
 
-    fn create_ksym_resolver(&self, path: &Path, _file: &File) -> Result<Rc<KSymResolver>> {
-        // TODO: Should really use `file` and not `path` for the instantiation.
-        let resolver = KSymResolver::load_file_name(path.to_path_buf())?;
-        let resolver = Rc::new(resolver);
+    /// Symbolize the given list of user space addresses in the provided
+    /// process.
+    ///
+    /// If provided, `maps` are used as the process' proc maps entries,
+    /// and for accessing its files, instead of retrieving them.
+    fn symbolize_user_addrs<'slf>(
+        &'slf self,
+        addrs: &[Addr],
+        pid: Pid,
+        maps: Option<&SharedMaps>,
+        debug_syms: bool,
+        alloc: Alloc<'slf>,
+    ) -> Result<Vec<Symbolized<'slf>>> {
+        // If we enter the process' mount namespace, we have to stay in
+        // it until all files have been accessed.
+        #[cfg(target_os = "linux")]
+        let _nsi;
+        let (entries, maps_pid, root) = match maps {
+            Some(maps) => (Arc::clone(&maps.entries), maps.maps_pid, maps.root.clone()),
+            None => {
+                #[cfg(target_os = "linux")]
+                let (maps_pid, root) = {
+                    let nsi = NsInfo::new(pid, self.mount_ns_mode == MountNsMode::Enter)?;
+                    let ids = (nsi.pid(), nsi.root());
+                    _nsi = nsi;
+                    ids
+                };
+                #[cfg(not(target_os = "linux"))]
+                let (maps_pid, root) = (pid, None);
+                (self.maps_entries(pid, maps_pid, addrs)?, maps_pid, root)
+            }
+        };
+        let () = self.maybe_prefetch(pid, &entries, debug_syms);
+        let entries = relevant_entries(&entries, addrs);
+        self.symbolize_entries_addrs(addrs, entries, Some(maps_pid), root, debug_syms, alloc)
+    }
+
+    /// Retrieve the proc maps entries of the process `pid`, accessible
+    /// as `maps_pid`, consulting the maps cache if enabled.
+    ///
+    /// Cached entries are used as long as they are valid and cover all
+    /// of `addrs`. Otherwise the proc maps file is parsed anew and the
+    /// result replaces the cached entries.
+    fn maps_entries(&self, pid: Pid, maps_pid: Pid, addrs: &[Addr]) -> Result<Arc<[MapsEntry]>> {
+        if let Some(entries) = self
+            .maps_cache
+            .as_ref()
+            .and_then(|cache| cache.get(pid, addrs))
+        {
+            return Ok(entries)
+        }
+
+        let entries = maps::parse(&*self.fs, maps_pid)?.collect::<Result<Vec<_>>>()?;
+        match &self.maps_cache {
+            Some(cache) => Ok(cache.insert(pid, entries)),
+            None => Ok(Arc::from(entries)),
+        }
+    }
+
+    /// Retrieve the proc maps entries of the process `pid` covering
+    /// `addrs`, for sharing between the threads symbolizing addresses
+    /// in it.
+    ///
+    /// Entering a mount namespace is not possible from a multi-threaded
+    /// process, so files of the process are always accessed via
+    /// `/proc/<pid>/root/`, irrespective of the configured
+    /// [`MountNsMode`].
+    fn shared_maps(&self, pid: Pid, addrs: &[Addr]) -> Result<SharedMaps> {
+        #[cfg(target_os = "linux")]
+        let nsi = NsInfo::new(pid, false)?;
+        #[cfg(target_os = "linux")]
+        let (maps_pid, root) = (nsi.pid(), nsi.root());
+        #[cfg(not(target_os = "linux"))]
+        let (maps_pid, root) = (pid, None);
+        let maps = SharedMaps {
+            entries: self.maps_entries(pid, maps_pid, addrs)?,
+            maps_pid,
+            root,
+        };
+        Ok(maps)
+    }
+
+    /// Symbolize the given list of user space addresses in the process
+    /// captured by `snapshot`.
+    fn symbolize_snapshot_addrs<'slf>(
+        &'slf self,
+        addrs: &[Addr],
+        snapshot: &Snapshot<'_>,
+        alloc: Alloc<'slf>,
+    ) -> Result<Vec<Symbolized<'slf>>> {
+        let Snapshot {
+            maps,
+            debug_syms,
+            _non_exhaustive: (),
+        } = snapshot;
+
+        let entries = maps::parse_snapshot(maps.as_bytes());
+        self.symbolize_entries_addrs(addrs, entries, None, None, *debug_syms, alloc)
+    }
+
+    /// Symbolize the given list of user space addresses in the process
+    /// captured by the core file referenced by `coredump`.
+    fn symbolize_coredump_addrs<'slf>(
+        &'slf self,
+        addrs: &[Addr],
+        coredump: &Coredump,
+        alloc: Alloc<'slf>,
+    ) -> Result<Vec<Symbolized<'slf>>> {
+        let Coredump {
+            path,
+            debug_syms,
+            _non_exhaustive: (),
+        } = coredump;
+
+        let data = self
+            .fs
+            .open(path)
+            .with_context(|| format!("failed to open core file {}", path.display()))?;
+        let entries = coredump::parse_maps(data.mmap())
+            .with_context(|| format!("failed to parse core file {}", path.display()))?;
+        let entries = entries.into_iter().map(Ok);
+        self.symbolize_entries_addrs(addrs, entries, None, None, *debug_syms, alloc)
+    }
+
+    /// Kick off prefetching of the modules described by `entries`, if
+    /// enabled and not yet done for `pid`.
+    fn maybe_prefetch(&self, pid: Pid, entries: &[MapsEntry], debug_syms: bool) {
+        let prefetch = if let Some(prefetch) = &self.prefetch {
+            prefetch
+        } else {
+            return
+        };
+
+        let first = self
+            .prefetched
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(pid);
+        if !first {
+            return
+        }
+
+        let job = PrefetchJob {
+            elf_cache: Arc::clone(&self.elf_cache),
+            paths: PrefetchJob::elf_paths(entries),
+            memory_budget: prefetch.memory_budget,
+            debug_syms,
+            code_info: self.code_info,
+            limits: self.limits,
+        };
+        let () = job.spawn();
+    }
+
+    /// Resolve the default path of a file emitted by the JIT runtime of
+    /// process `pid`, as created by `make_path`, such that it can be
+    /// accessed from our mount namespace.
+    ///
+    /// Runtimes name their files after the process ID as they see it
+    /// and create them in their own mount namespace, which may both
+    /// differ from ours if the process runs inside a container.
+    fn jit_file_path(&self, pid: Pid, make_path: fn(Pid) -> PathBuf) -> Result<PathBuf> {
+        #[cfg(target_os = "linux")]
+        {
+            let view = ProcessView::new(pid)?;
+            let path = view.resolve_path(&make_path(view.ns_pid()));
+            Ok(path)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Ok(make_path(pid))
+        }
+    }
+
+    fn create_jit_resolver(&self, syms: Vec<jit::JitSym>) -> Result<Arc<JitResolver>> {
+        let resolver = JitResolver::new(syms);
+        Ok(Arc::new(resolver))
+    }
+
+    /// Retrieve a resolver for the JVM perf map or jitdump file at
+    /// `path`, if it exists.
+    ///
+    /// The file is re-read if it changed since it was last used.
+    fn jvm_resolver(&self, path: &Path, jitdump: bool) -> Result<Option<Arc<JitResolver>>> {
+        let cache = if jitdump {
+            &self.jitdump_cache
+        } else {
+            &self.jvm_perf_map_cache
+        };
+        let entry = match cache.entry(path) {
+            Ok(entry) => entry,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let data = entry.data();
+        let resolver = entry.value().get_or_try_init(|| {
+            let syms = if jitdump {
+                jit::parse_jitdump(data.data())
+                    .with_context(|| format!("failed to parse jitdump file {}", path.display()))?
+            } else {
+                jit::parse_perf_map(&String::from_utf8_lossy(data.data()))
+            };
+            self.create_jit_resolver(syms)
+        })?;
+        Ok(Some(Arc::clone(resolver)))
+    }
+
+    /// Symbolize the given list of addresses in the provided JVM process.
+    fn symbolize_jvm_addrs<'slf>(
+        &'slf self,
+        addrs: &[Addr],
+        jvm: &Jvm,
+        maps: Option<&SharedMaps>,
+        alloc: Alloc<'slf>,
+    ) -> Result<Vec<Symbolized<'slf>>> {
+        let Jvm {
+            pid,
+            perf_map,
+            jitdump,
+            debug_syms,
+            _non_exhaustive: (),
+        } = jvm;
+
+        let mut symbols = self.symbolize_user_addrs(addrs, *pid, maps, *debug_syms, alloc)?;
+        if !symbols.iter().any(needs_jit_fallback) {
+            return Ok(symbols)
+        }
+
+        let perf_map = match perf_map {
+            Some(path) => path.clone(),
+            None => self.jit_file_path(*pid, jit::perf_map_path)?,
+        };
+        let jitdump = match jitdump {
+            Some(path) => path.clone(),
+            None => self.jit_file_path(*pid, jit::jitdump_path)?,
+        };
+
+        // Both the perf map and the jitdump file are optional, as a
+        // runtime may emit only one of them (or none at all).
+        let resolvers = [
+            self.jvm_resolver(&perf_map, false)?,
+            self.jvm_resolver(&jitdump, true)?,
+        ];
+
+        for (addr, symbol) in addrs.iter().zip(symbols.iter_mut()) {
+            if needs_jit_fallback(symbol) {
+                for resolver in resolvers.iter().flatten() {
+                    let result = self.symbolize_with_resolver(
+                        *addr,
+                        &Resolver::Cached(resolver.deref()),
+                        alloc,
+                    )?;
+                    // Methods take precedence over larger code regions
+                    // containing them, no matter which file they were
+                    // reported in.
+                    let replace = match (&*symbol, &result) {
+                        (_, Symbolized::Unknown(..)) => false,
+                        (Symbolized::Unknown(..), Symbolized::Sym(..)) => true,
+                        (Symbolized::Sym(prev), Symbolized::Sym(sym)) => {
+                            sym.size.unwrap_or(usize::MAX) < prev.size.unwrap_or(usize::MAX)
+                        }
+                    };
+                    if replace {
+                        *symbol = result;
+                    }
+                }
+            }
+        }
+        Ok(symbols)
+    }
+
+    fn create_perf_map_resolver(&self, data: &FileData) -> Result<Arc<JitResolver>> {
+        let syms = jit::parse_perf_map(&String::from_utf8_lossy(data.data()));
+        let resolver = JitResolver::new(jit::remove_stale(syms));
+        Ok(Arc::new(resolver))
+    }
+
+    /// Retrieve a resolver for the perf map at `path`, if it exists.
+    ///
+    /// The perf map is re-read if it changed since it was last used.
+    fn perf_map_resolver(&self, path: &Path) -> Result<Option<Arc<JitResolver>>> {
+        let entry = match self.perf_map_cache.entry(path) {
+            Ok(entry) => entry,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let resolver = entry
+            .value()
+            .get_or_try_init(|| self.create_perf_map_resolver(entry.data()))?;
+        Ok(Some(Arc::clone(resolver)))
+    }
+
+    fn create_linker_map_resolver(&self, data: &FileData) -> Result<Arc<LinkerMapResolver>> {
+        let syms = linker_map::parse(&String::from_utf8_lossy(data.data()))?;
+        let resolver = LinkerMapResolver::new(syms);
+        Ok(Arc::new(resolver))
+    }
+
+    fn linker_map_resolver(&self, path: &Path) -> Result<Arc<LinkerMapResolver>> {
+        let entry = self.linker_map_cache.entry(path)?;
+        let resolver = entry
+            .value()
+            .get_or_try_init(|| self.create_linker_map_resolver(entry.data()))?;
+        Ok(Arc::clone(resolver))
+    }
+
+    #[cfg(feature = "breakpad")]
+    fn breakpad_resolver(&self, path: &Path) -> Result<Arc<BreakpadResolver>> {
+        let entry = self.breakpad_cache.entry(path)?;
+        let resolver = entry
+            .value()
+            .get_or_try_init(|| BreakpadResolver::from_data(entry.data().data()).map(Arc::new))?;
+        Ok(Arc::clone(resolver))
+    }
+
+    /// Symbolize the given list of addresses in the provided Node.js
+    /// process.
+    fn symbolize_node_addrs<'slf>(
+        &'slf self,
+        addrs: &[Addr],
+        node: &Node,
+        maps: Option<&SharedMaps>,
+        alloc: Alloc<'slf>,
+    ) -> Result<Vec<Symbolized<'slf>>> {
+        let Node {
+            pid,
+            perf_map,
+            debug_syms,
+            _non_exhaustive: (),
+        } = node;
+
+        let mut symbols = self.symbolize_user_addrs(addrs, *pid, maps, *debug_syms, alloc)?;
+        if !symbols.iter().any(needs_jit_fallback) {
+            return Ok(symbols)
+        }
+
+        let perf_map = match perf_map {
+            Some(path) => path.clone(),
+            None => self.jit_file_path(*pid, jit::perf_map_path)?,
+        };
+        if let Some(resolver) = self.perf_map_resolver(&perf_map)? {
+            for (addr, symbol) in addrs.iter().zip(symbols.iter_mut()) {
+                if needs_jit_fallback(symbol) {
+                    let result = self.symbolize_with_resolver(
+                        *addr,
+                        &Resolver::Cached(resolver.deref()),
+                        alloc,
+                    )?;
+                    // Retain the original reason if the perf map does
+                    // not know the address either.
+                    if let Symbolized::Sym(..) = result {
+                        *symbol = result;
+                    }
+                }
+            }
+        }
+        Ok(symbols)
+    }
+
+    #[cfg(feature = "kernel")]
+    fn create_ksym_resolver(&self, path: &Path, data: &FileData) -> Result<Arc<KSymResolver>> {
+        let resolver = KSymResolver::load_from_reader(data.data(), path.to_path_buf())?;
+        let resolver = Arc::new(resolver);
         Ok(resolver)
     }
 
-    fn ksym_resolver<'slf>(&'slf self, path: &Path) -> Result<&'slf Rc<KSymResolver>> {
-        let (file, cell) = self.ksym_cache.entry(path)?;
-        let resolver = cell.get_or_try_init(|| self.create_ksym_resolver(path, file))?;
+    #[cfg(feature = "kernel")]
+    fn ksym_resolver(&self, path: &Path) -> Result<KSymSource> {
+        #[cfg(all(unix, feature = "cache-daemon"))]
+        if let Some(index) = self.index_resolver(daemon::Request::Kallsyms(path.to_path_buf()))? {
+            return Ok(KSymSource::Index(index))
+        }
+
+        let entry = self.ksym_cache.entry(path)?;
+        let resolver = entry
+            .value()
+            .get_or_try_init(|| self.create_ksym_resolver(path, entry.data()))?;
+        Ok(KSymSource::KSym(Arc::clone(resolver)))
+    }
+
+    /// Retrieve the resolver for the kernel module file at `path`.
+    #[cfg(feature = "kernel")]
+    fn kmod_resolver(&self, path: &Path, debug_syms: bool) -> Result<Arc<ElfResolver>> {
+        if !kmod::is_compressed(path) {
+            return self.elf_resolver(path, debug_syms)
+        }
+
+        let resolver =
+            self.kmod_cache
+                .get_or_try_insert((path.to_path_buf(), debug_syms), || {
+                    let file = self.fs.open(path)?;
+                    let data = kmod::decompress(path, file.data())?;
+                    let parser = Arc::new(ElfParser::from_mmap(Mmap::from_vec(data)));
+                    let resolver = ElfResolver::from_parser(
+                        path,
+                        parser,
+                        &self.fs,
+                        debug_syms,
+                        self.code_info,
+                        None,
+                    )?;
+                    Ok(resolver)
+                })?;
+
+        if debug_syms {
+            if let Some(resolver) = self.debug_file_resolver(path, &resolver)? {
+                return Ok(resolver)
+            }
+        }
         Ok(resolver)
     }
 
-    fn create_kernel_resolver(&self, src: &Kernel) -> Result<KernelResolver> {
+    /// Retrieve the kernel module files by module name, as listed in
+    /// the `modules.dep` file in `module_dir`.
+    #[cfg(feature = "kernel")]
+    fn kmod_index(&self, module_dir: &Path) -> Result<Arc<HashMap<String, PathBuf>>> {
+        self.kmod_index_cache
+            .get_or_try_insert(module_dir.to_path_buf(), || {
+                let path = module_dir.join(kmod::MODULES_DEP);
+                let file = self.fs.open(&path)?;
+                let index = kmod::parse_modules_dep(file.data())
+                    .into_iter()
+                    .map(|(name, file)| (name, module_dir.join(file)))
+                    .collect();
+                Ok(index)
+            })
+    }
+
+    /// Create resolvers for the kernel modules containing any of the
+    /// addresses in `addrs`.
+    #[cfg(feature = "kernel")]
+    fn create_kmod_resolvers(&self, src: &Kernel, addrs: &[Addr]) -> Result<Vec<ModuleResolver>> {
+        let Kernel {
+            kallsyms,
+            kernel_image: _,
+            kaslr_offset: _,
+            debug_syms,
+            modules,
+            module_dir,
+            _non_exhaustive: (),
+        } = src;
+
+        // Only consult the modules of the running kernel if we are
+        // symbolizing it.
+        let running = modules.is_none() && kallsyms.is_none();
+        let modules = match modules {
+            Some(modules) => self.fs.open(modules)?,
+            None if running && cfg!(target_os = "linux") => {
+                match self.fs.open(Path::new(kmod::PROC_MODULES)) {
+                    Ok(modules) => modules,
+                    Err(err) => {
+                        log::warn!(
+                            "failed to read kernel modules from {}: {err}; ignoring...",
+                            kmod::PROC_MODULES
+                        );
+                        return Ok(Vec::new())
+                    }
+                }
+            }
+            None => return Ok(Vec::new()),
+        };
+
+        let modules = kmod::parse_modules(modules.data())
+            .context("failed to parse kernel module list")?
+            .into_iter()
+            .filter(|module| addrs.iter().any(|addr| module.range.contains(addr)))
+            .collect::<Vec<_>>();
+        if modules.is_empty() {
+            return Ok(Vec::new())
+        }
+
+        let module_dir = match module_dir {
+            Some(module_dir) => module_dir.clone(),
+            None => match self.find_module_dir()? {
+                Some(module_dir) => module_dir,
+                None => return Ok(Vec::new()),
+            },
+        };
+        let index = match self.kmod_index(&module_dir) {
+            Ok(index) => index,
+            Err(err) => {
+                log::warn!(
+                    "failed to read kernel module index in {}: {err}; ignoring...",
+                    module_dir.display()
+                );
+                return Ok(Vec::new())
+            }
+        };
+
+        let resolvers = modules
+            .into_iter()
+            .filter_map(|module| {
+                let path = if let Some(path) = index.get(&module.name) {
+                    path
+                } else {
+                    log::debug!("failed to find file of kernel module {}", module.name);
+                    return None
+                };
+
+                let resolver = match self.kmod_resolver(path, *debug_syms) {
+                    Ok(resolver) => resolver,
+                    Err(err) => {
+                        log::warn!(
+                            "failed to load kernel module {}: {err}; ignoring...",
+                            path.display()
+                        );
+                        return None
+                    }
+                };
+
+                let text_base = if running {
+                    kmod::text_base(self.fs.as_ref(), &module)
+                } else {
+                    module.range.start
+                };
+                let resolver = ModuleResolver {
+                    range: module.range,
+                    text_base,
+                    resolver,
+                };
+                Some(resolver)
+            })
+            .collect();
+        Ok(resolvers)
+    }
+
+    #[cfg(feature = "kernel")]
+    fn create_kernel_resolver(&self, src: &Kernel, addrs: &[Addr]) -> Result<KernelResolver> {
         let Kernel {
             kallsyms,
             kernel_image,
+            kaslr_offset,
             debug_syms,
+            modules: _,
+            module_dir: _,
             _non_exhaustive: (),
         } = src;
 
         let ksym_resolver = if let Some(kallsyms) = kallsyms {
             let ksym_resolver = self.ksym_resolver(kallsyms)?;
             Some(ksym_resolver)
-        } else {
+        } else if cfg!(target_os = "linux") {
             let kallsyms = Path::new(KALLSYMS);
             let result = self.ksym_resolver(kallsyms);
             match result {
@@ -611,6 +2360,8 @@ impl Symbolizer {
                     None
                 }
             }
+        } else {
+            None
         };
 
         let elf_resolver = if let Some(image) = kernel_image {
@@ -618,35 +2369,209 @@ impl Symbolizer {
                 .elf_cache
                 .elf_resolver(image, *debug_syms, self.code_info)?;
             Some(resolver)
+        } else if let Some(image) = self.find_kernel_image()? {
+            let result = self
+                .elf_cache
+                .elf_resolver(&image, *debug_syms, self.code_info);
+            match result {
+                Ok(resolver) => Some(resolver),
+                Err(err) => {
+                    log::warn!(
+                        "failed to load kernel image {}: {err}; ignoring...",
+                        image.display()
+                    );
+                    None
+                }
+            }
         } else {
-            let release = uname_release()?.to_str().unwrap().to_string();
-            let basename = "vmlinux-";
-            let dirs = [Path::new("/boot/"), Path::new("/usr/lib/debug/boot/")];
-            let kernel_image = dirs.iter().find_map(|dir| {
-                let path = dir.join(format!("{basename}{release}"));
-                path.exists().then_some(path)
-            });
+            None
+        };
 
-            if let Some(image) = kernel_image {
-                let result = self
-                    .elf_cache
-                    .elf_resolver(&image, *debug_syms, self.code_info);
-                match result {
-                    Ok(resolver) => Some(resolver),
+        let kaslr_offset = match (kaslr_offset, &ksym_resolver, &elf_resolver) {
+            (Some(offset), _, _) => *offset,
+            (None, Some(ksym_resolver), Some(elf_resolver)) => {
+                match kernel::find_kaslr_offset(ksym_resolver, elf_resolver) {
+                    Ok(offset) => offset.unwrap_or(0),
                     Err(err) => {
-                        log::warn!(
-                            "failed to load kernel image {}: {err}; ignoring...",
-                            image.display()
-                        );
-                        None
+                        log::warn!("failed to determine KASLR offset: {err}; ignoring...");
+                        0
                     }
                 }
-            } else {
-                None
             }
+            (None, _, _) => 0,
+        };
+
+        let modules = self.create_kmod_resolvers(src, addrs)?;
+        KernelResolver::new(ksym_resolver, elf_resolver, kaslr_offset, modules)
+    }
+
+    /// Find the image of the running kernel in one of the well-known
+    /// locations.
+    ///
+    /// If the build ID of the running kernel is known, images named
+    /// after its release are checked first, followed by any other
+    /// `vmlinux*` files, and the first one with a matching build ID is
+    /// reported. Otherwise the first image named after the release is
+    /// used.
+    #[cfg(all(feature = "kernel", target_os = "linux"))]
+    fn find_kernel_image(&self) -> Result<Option<PathBuf>> {
+        let release = uname_release()?.to_str().unwrap().to_string();
+        let basename = "vmlinux";
+        let dirs = [Path::new("/usr/lib/debug/boot/"), Path::new("/boot/")];
+        let build_id = kernel::read_kernel_build_id(self.fs.as_ref()).unwrap_or_else(|err| {
+            log::debug!("failed to read build ID of running kernel: {err}");
+            None
+        });
+
+        let named = dirs
+            .iter()
+            .map(|dir| dir.join(format!("{basename}-{release}")))
+            .collect::<Vec<_>>();
+        let build_id = match build_id {
+            Some(build_id) => build_id,
+            None => {
+                let kernel_image = named.into_iter().find(|path| {
+                    !matches!(self.fs.open(path), Err(err) if err.kind() == ErrorKind::NotFound)
+                });
+                return Ok(kernel_image)
+            }
+        };
+
+        let others = dirs
+            .iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flat_map(|entries| {
+                let mut paths = entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| {
+                        entry
+                            .file_name()
+                            .to_str()
+                            .map(|name| name.starts_with(basename))
+                            .unwrap_or(false)
+                    })
+                    .map(|entry| entry.path())
+                    .collect::<Vec<_>>();
+                let () = paths.sort();
+                paths
+            })
+            .filter(|path| !named.contains(path))
+            .collect::<Vec<_>>();
+
+        let kernel_image = named.into_iter().chain(others).find(|path| {
+            let data = match self.fs.open(path) {
+                Ok(data) => data,
+                Err(err) => {
+                    if err.kind() != ErrorKind::NotFound {
+                        log::debug!("failed to open kernel image {}: {err}", path.display());
+                    }
+                    return false
+                }
+            };
+            let parser = ElfParser::from_mmap(data.mmap().clone());
+            match DefaultBuildIdReader::read_build_id(&parser) {
+                Ok(Some(image_build_id)) if image_build_id == build_id => true,
+                Ok(..) => {
+                    log::debug!(
+                        "build ID of kernel image {} does not match running kernel",
+                        path.display()
+                    );
+                    false
+                }
+                Err(err) => {
+                    log::debug!(
+                        "failed to read build ID of kernel image {}: {err}",
+                        path.display()
+                    );
+                    false
+                }
+            }
+        });
+        Ok(kernel_image)
+    }
+
+    /// Find the image of the running kernel.
+    ///
+    /// Only Linux kernels are supported, so on other platforms there is
+    /// nothing to be found.
+    #[cfg(all(feature = "kernel", not(target_os = "linux")))]
+    fn find_kernel_image(&self) -> Result<Option<PathBuf>> {
+        Ok(None)
+    }
+
+    /// Find the module directory of the running kernel.
+    #[cfg(all(feature = "kernel", target_os = "linux"))]
+    fn find_module_dir(&self) -> Result<Option<PathBuf>> {
+        let release = uname_release()?;
+        let release = release.to_str().unwrap();
+        let module_dir = Path::new("/lib/modules").join(release);
+        Ok(Some(module_dir))
+    }
+
+    /// Find the module directory of the running kernel.
+    ///
+    /// Only Linux kernels are supported, so on other platforms there is
+    /// nothing to be found.
+    #[cfg(all(feature = "kernel", not(target_os = "linux")))]
+    fn find_module_dir(&self) -> Result<Option<PathBuf>> {
+        Ok(None)
+    }
+
+    /// Run `f` within the configured time budget and parsing limits,
+    /// if any, and with progress being reported to the configured
+    /// callback.
+    ///
+    /// Should `f` fail because the budget got exhausted, the result of
+    /// `timed_out` is reported instead.
+    ///
+    /// Afterwards, the configured cache limits, if any, are enforced.
+    fn with_scope<F, G, T>(&self, f: F, timed_out: G) -> Result<T>
+    where
+        F: FnOnce() -> Result<T>,
+        G: FnOnce() -> T,
+    {
+        let result = if let Some(limits) = &self.limits {
+            scope::with_limits(limits, || self.with_progress(f, timed_out))
+        } else {
+            self.with_progress(f, timed_out)
+        };
+        // Files loaded as part of the request may have pushed the cache
+        // beyond its limits.
+        let () = self.trim_cache();
+        result
+    }
+
+    /// Run `f` with progress being reported to the configured
+    /// callback, if any.
+    fn with_progress<F, G, T>(&self, f: F, timed_out: G) -> Result<T>
+    where
+        F: FnOnce() -> Result<T>,
+        G: FnOnce() -> T,
+    {
+        if let Some(progress) = &self.progress {
+            return scope::with_progress(progress, || self.with_time_budget(f, timed_out))
+        }
+        self.with_time_budget(f, timed_out)
+    }
+
+    /// Run `f` within the configured time budget, if any.
+    fn with_time_budget<F, G, T>(&self, f: F, timed_out: G) -> Result<T>
+    where
+        F: FnOnce() -> Result<T>,
+        G: FnOnce() -> T,
+    {
+        let deadline = match self
+            .time_budget
+            .and_then(|budget| Instant::now().checked_add(budget))
+        {
+            Some(deadline) => deadline,
+            None => return f(),
         };
 
-        KernelResolver::new(ksym_resolver.cloned(), elf_resolver.cloned())
+        scope::with_deadline(deadline, || match f() {
+            Err(err) if err.kind() == ErrorKind::TimedOut && scope::timed_out() => Ok(timed_out()),
+            result => result,
+        })
     }
 
     /// Symbolize a list of addresses.
@@ -676,12 +2601,208 @@ impl Symbolizer {
     /// | Ksym   | symbol size                      | no                   | N/A                    |
     /// |        | source code location information | no                   | N/A                    |
     /// |        | inlined function information     | no                   | N/A                    |
+    ///
+    /// The kind of [`Input`] accepted depends on the [`Source`][Source]
+    /// in use. Sources describing a single binary or symbol file work
+    /// on the addresses as they appear in said file (and, where a
+    /// mapping to file offsets is known, on the latter), while sources
+    /// describing the address space of a running or captured system
+    /// work on absolute addresses.
+    ///
+    /// | Source                                                     | [`AbsAddr`][Input::AbsAddr] | [`VirtOffset`][Input::VirtOffset] | [`FileOffset`][Input::FileOffset] |
+    /// |------------------------------------------------------------|:---------------------------:|:---------------------------------:|:---------------------------------:|
+    /// | `Apk`                                                      | no                          | no                                | yes                               |
+    /// | `Elf`                                                      | no                          | yes                               | yes                               |
+    /// | `Gsym`, `Breakpad`, `LinkerMap`                            | no                          | yes                               | no                                |
+    /// | `Kernel`, `Process`, `Snapshot`, `Coredump`, `Jvm`, `Node` | yes                         | no                                | no                                |
+    ///
+    /// Using an unsupported combination results in an error of kind
+    /// [`Unsupported`][ErrorKind::Unsupported].
     #[cfg_attr(feature = "tracing", crate::log::instrument(skip_all, fields(src = ?src, addrs = format_args!("{input:#x?}"))))]
     pub fn symbolize<'slf>(
         &'slf self,
         src: &Source,
         input: Input<&[u64]>,
     ) -> Result<Vec<Symbolized<'slf>>> {
+        self.symbolize_with_maps(src, input, None)
+    }
+
+    /// Symbolize a list of addresses, using `maps` as the proc maps
+    /// entries of the process referenced by `src`, if provided.
+    fn symbolize_with_maps<'slf>(
+        &'slf self,
+        src: &Source,
+        input: Input<&[u64]>,
+        maps: Option<&SharedMaps>,
+    ) -> Result<Vec<Symbolized<'slf>>> {
+        let count = input.into_inner().len();
+        self.with_scope(
+            || {
+                let mut symbolized = self.symbolize_impl(src, input, maps, Alloc::Heap)?;
+                let () = self.add_source_context(&mut symbolized, source_root(src).as_deref());
+                Ok(symbolized)
+            },
+            || vec![Symbolized::Unknown(Reason::TimedOut); count],
+        )
+    }
+
+    /// Symbolize a list of addresses, waiting for deferred indexing of
+    /// debug information to complete.
+    ///
+    /// This method behaves just like [`symbolize`][Self::symbolize],
+    /// except that indexing of debug information is never deferred (see
+    /// [`Builder::enable_deferred_indexing`]). If indexing is already
+    /// in progress in the background, the call blocks until it is
+    /// done. Results hence include source code location and inlined
+    /// function information, if available and enabled.
+    pub fn refine<'slf>(
+        &'slf self,
+        src: &Source,
+        input: Input<&[u64]>,
+    ) -> Result<Vec<Symbolized<'slf>>> {
+        scope::with_blocking(|| self.symbolize(src, input))
+    }
+
+    /// Symbolize a list of addresses, allocating result data in the
+    /// provided [`Arena`].
+    ///
+    /// This method behaves just like [`symbolize`][Self::symbolize],
+    /// except that symbol names and source code information that
+    /// otherwise would be heap allocated for each result are placed in
+    /// `arena` instead. Results borrow from the arena and the data are
+    /// released once it is [reset][Arena::reset] or dropped.
+    pub fn symbolize_in<'slf>(
+        &'slf self,
+        arena: &'slf Arena,
+        src: &Source,
+        input: Input<&[u64]>,
+    ) -> Result<Vec<Symbolized<'slf>>> {
+        let count = input.into_inner().len();
+        self.with_scope(
+            || {
+                let mut symbolized = self.symbolize_impl(src, input, None, Alloc::Arena(arena))?;
+                let () = self.add_source_context(&mut symbolized, source_root(src).as_deref());
+                Ok(symbolized)
+            },
+            || vec![Symbolized::Unknown(Reason::TimedOut); count],
+        )
+    }
+
+    /// Retrieve the maximum number of threads to use for parallel
+    /// symbolization.
+    pub(crate) fn parallelism(&self) -> usize {
+        self.parallelism
+            .or_else(|| thread::available_parallelism().ok())
+            .map(NonZeroUsize::get)
+            .unwrap_or(1)
+    }
+
+    /// Symbolize a list of addresses, using multiple threads.
+    ///
+    /// This method behaves just like [`symbolize`][Self::symbolize],
+    /// except that large batches of addresses are split up and
+    /// symbolized concurrently, using up to the number of threads
+    /// configured via [`Builder::set_parallelism`]. Addresses are
+    /// partitioned by their value, meaning that each thread typically
+    /// works with only a few of the resolvers involved, while all of
+    /// them share the same caches and, for process sources, the
+    /// process' memory maps, which are parsed only once. The
+    /// cancellation token and deadline in effect for the calling
+    /// thread (e.g., as part of
+    /// [`symbolize_cancellable`][Self::symbolize_cancellable]) are
+    /// honored by all threads. The time budget and parsing limits, if
+    /// any, apply to each thread individually.
+    ///
+    /// Files of processes residing in a different mount namespace are
+    /// always accessed as per [`MountNsMode::ProcRoot`], as entering
+    /// the namespace is impossible once multiple threads are involved.
+    pub fn symbolize_par<'slf>(
+        &'slf self,
+        src: &Source,
+        input: Input<&[u64]>,
+    ) -> Result<Vec<Symbolized<'slf>>> {
+        /// The minimum number of addresses worth handing to a separate
+        /// thread.
+        const MIN_CHUNK_SIZE: usize = 64;
+
+        let addrs = input.into_inner();
+        let threads = self.parallelism();
+        let chunk_size = ((addrs.len() + threads - 1) / threads).max(MIN_CHUNK_SIZE);
+        if addrs.len() <= chunk_size {
+            return self.symbolize(src, input)
+        }
+
+        // Sort addresses, so that each chunk covers a contiguous range
+        // and, hence, is likely to be served by only a few resolvers.
+        let mut sorted = addrs
+            .iter()
+            .copied()
+            .enumerate()
+            .map(|(idx, addr)| (addr, idx))
+            .collect::<Vec<_>>();
+        let () = sorted.sort_unstable();
+        let (sorted, indices) = sorted.into_iter().unzip::<_, _, Vec<_>, Vec<_>>();
+
+        // Parse the memory maps of the process once, instead of having
+        // each thread do so.
+        let maps = match (src, input) {
+            #[cfg(feature = "process")]
+            (Source::Process(Process { pid, .. }), Input::AbsAddr(..))
+            | (Source::Jvm(Jvm { pid, .. }), Input::AbsAddr(..))
+            | (Source::Node(Node { pid, .. }), Input::AbsAddr(..)) => {
+                Some(self.shared_maps(*pid, addrs)?)
+            }
+            _ => None,
+        };
+        // Worker threads have to honor the cancellation token, deadline,
+        // and limits in effect for us.
+        let scope = scope::snapshot();
+
+        let chunks = thread::scope(|s| {
+            let handles = sorted
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let scope = &scope;
+                    let maps = maps.as_ref();
+                    s.spawn(move || {
+                        scope.enter(|| self.symbolize_with_maps(src, input.map(|_| chunk), maps))
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|payload| panic::resume_unwind(payload))
+                })
+                .collect::<Result<Vec<_>>>()
+        })?;
+
+        let mut results = Vec::with_capacity(addrs.len());
+        let () = results.resize_with(addrs.len(), || None);
+        for (idx, symbolized) in indices.into_iter().zip(chunks.into_iter().flatten()) {
+            results[idx] = Some(symbolized);
+        }
+        // SANITY: Each chunk reports exactly one result per address,
+        //         so all slots have been filled.
+        let results = results
+            .into_iter()
+            .map(|symbolized| symbolized.unwrap())
+            .collect();
+        Ok(results)
+    }
+
+    fn symbolize_impl<'slf>(
+        &'slf self,
+        src: &Source,
+        input: Input<&[u64]>,
+        maps: Option<&SharedMaps>,
+        alloc: Alloc<'slf>,
+    ) -> Result<Vec<Symbolized<'slf>>> {
+        #[cfg(not(feature = "process"))]
+        let _maps = maps;
         match src {
             Source::Apk(Apk {
                 path,
@@ -690,12 +2811,12 @@ impl Symbolizer {
             }) => match input {
                 Input::VirtOffset(..) => {
                     return Err(Error::with_unsupported(
-                        "ELF symbolization does not support virtual offset inputs",
+                        "APK symbolization does not support virtual offset inputs",
                     ))
                 }
                 Input::AbsAddr(..) => {
                     return Err(Error::with_unsupported(
-                        "ELF symbolization does not support absolute address inputs",
+                        "APK symbolization does not support absolute address inputs",
                     ))
                 }
                 Input::FileOffset(offsets) => offsets
@@ -705,6 +2826,7 @@ impl Symbolizer {
                             Some((elf_resolver, elf_addr)) => self.symbolize_with_resolver(
                                 elf_addr,
                                 &Resolver::Cached(elf_resolver.deref()),
+                                alloc,
                             ),
                             None => Ok(Symbolized::Unknown(Reason::InvalidFileOffset)),
                         },
@@ -714,17 +2836,15 @@ impl Symbolizer {
             Source::Elf(Elf {
                 path,
                 debug_syms,
+                hot_ranges,
                 _non_exhaustive: (),
             }) => {
-                let resolver = self
-                    .elf_cache
-                    .elf_resolver(path, *debug_syms, self.code_info)?;
+                let resolver = self.elf_src_resolver(path, *debug_syms, hot_ranges.as_deref())?;
+                let sym_resolver = self.elf_sym_resolver(path, *debug_syms, &resolver)?;
                 match input {
                     Input::VirtOffset(addrs) => addrs
                         .iter()
-                        .map(|addr| {
-                            self.symbolize_with_resolver(*addr, &Resolver::Cached(resolver.deref()))
-                        })
+                        .map(|addr| self.symbolize_elf_src_addr(*addr, path, &*sym_resolver, alloc))
                         .collect(),
                     Input::AbsAddr(..) => {
                         return Err(Error::with_unsupported(
@@ -735,16 +2855,20 @@ impl Symbolizer {
                         .iter()
                         .map(
                             |offset| match elf_offset_to_address(*offset, resolver.parser())? {
-                                Some(addr) => self.symbolize_with_resolver(
-                                    addr,
-                                    &Resolver::Cached(resolver.deref()),
-                                ),
+                                Some(addr) => {
+                                    self.symbolize_elf_src_addr(addr, path, &*sym_resolver, alloc)
+                                }
                                 None => Ok(Symbolized::Unknown(Reason::InvalidFileOffset)),
                             },
                         )
                         .collect(),
                 }
             }
+            #[cfg(not(feature = "kernel"))]
+            Source::Kernel(..) => Err(Error::with_unsupported(
+                "kernel symbolization requires the `kernel` feature",
+            )),
+            #[cfg(feature = "kernel")]
             Source::Kernel(kernel) => {
                 let addrs = match input {
                     Input::AbsAddr(addrs) => addrs,
@@ -760,10 +2884,16 @@ impl Symbolizer {
                     }
                 };
 
-                let resolver = Rc::new(self.create_kernel_resolver(kernel)?);
-                let symbols = self.symbolize_addrs(addrs, &Resolver::Uncached(resolver.deref()))?;
+                let resolver = Arc::new(self.create_kernel_resolver(kernel, addrs)?);
+                let symbols =
+                    self.symbolize_addrs(addrs, &Resolver::Uncached(resolver.deref()), alloc)?;
                 Ok(symbols)
             }
+            #[cfg(not(feature = "process"))]
+            Source::Process(..) => Err(Error::with_unsupported(
+                "process symbolization requires the `process` feature",
+            )),
+            #[cfg(feature = "process")]
             Source::Process(Process {
                 pid,
                 debug_syms,
@@ -783,8 +2913,91 @@ impl Symbolizer {
                     }
                 };
 
-                self.symbolize_user_addrs(addrs, *pid, *debug_syms)
+                self.symbolize_user_addrs(addrs, *pid, maps, *debug_syms, alloc)
+            }
+            #[cfg(not(feature = "process"))]
+            Source::Jvm(..) => Err(Error::with_unsupported(
+                "JVM symbolization requires the `process` feature",
+            )),
+            #[cfg(feature = "process")]
+            Source::Jvm(jvm) => {
+                let addrs = match input {
+                    Input::AbsAddr(addrs) => addrs,
+                    Input::VirtOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "JVM symbolization does not support virtual offset inputs",
+                        ))
+                    }
+                    Input::FileOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "JVM symbolization does not support file offset inputs",
+                        ))
+                    }
+                };
+
+                self.symbolize_jvm_addrs(addrs, jvm, maps, alloc)
+            }
+            #[cfg(not(feature = "process"))]
+            Source::Node(..) => Err(Error::with_unsupported(
+                "Node.js symbolization requires the `process` feature",
+            )),
+            #[cfg(feature = "process")]
+            Source::Node(node) => {
+                let addrs = match input {
+                    Input::AbsAddr(addrs) => addrs,
+                    Input::VirtOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "Node.js symbolization does not support virtual offset inputs",
+                        ))
+                    }
+                    Input::FileOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "Node.js symbolization does not support file offset inputs",
+                        ))
+                    }
+                };
+
+                self.symbolize_node_addrs(addrs, node, maps, alloc)
+            }
+            Source::Snapshot(snapshot) => {
+                let addrs = match input {
+                    Input::AbsAddr(addrs) => addrs,
+                    Input::VirtOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "snapshot symbolization does not support virtual offset inputs",
+                        ))
+                    }
+                    Input::FileOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "snapshot symbolization does not support file offset inputs",
+                        ))
+                    }
+                };
+
+                self.symbolize_snapshot_addrs(addrs, snapshot, alloc)
+            }
+            Source::Coredump(coredump) => {
+                let addrs = match input {
+                    Input::AbsAddr(addrs) => addrs,
+                    Input::VirtOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "core file symbolization does not support virtual offset inputs",
+                        ))
+                    }
+                    Input::FileOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "core file symbolization does not support file offset inputs",
+                        ))
+                    }
+                };
+
+                self.symbolize_coredump_addrs(addrs, coredump, alloc)
             }
+            #[cfg(not(feature = "gsym"))]
+            Source::Gsym(..) => Err(Error::with_unsupported(
+                "Gsym symbolization requires the `gsym` feature",
+            )),
+            #[cfg(feature = "gsym")]
             Source::Gsym(Gsym::Data(GsymData {
                 data,
                 _non_exhaustive: (),
@@ -803,10 +3016,12 @@ impl Symbolizer {
                     }
                 };
 
-                let resolver = Rc::new(GsymResolver::with_data(data)?);
-                let symbols = self.symbolize_addrs(addrs, &Resolver::Uncached(resolver.deref()))?;
+                let resolver = Arc::new(GsymResolver::with_data(data)?);
+                let symbols =
+                    self.symbolize_addrs(addrs, &Resolver::Uncached(resolver.deref()), alloc)?;
                 Ok(symbols)
             }
+            #[cfg(feature = "gsym")]
             Source::Gsym(Gsym::File(GsymFile {
                 path,
                 _non_exhaustive: (),
@@ -825,11 +3040,471 @@ impl Symbolizer {
                     }
                 };
 
-                let resolver = self.gsym_resolver(path)?;
-                let symbols = self.symbolize_addrs(addrs, &Resolver::Cached(resolver.deref()))?;
-                Ok(symbols)
+                let resolver = self.gsym_resolver(path)?;
+                let symbols =
+                    self.symbolize_addrs(addrs, &Resolver::Cached(resolver.deref()), alloc)?;
+                Ok(symbols)
+            }
+            Source::LinkerMap(LinkerMap {
+                path,
+                _non_exhaustive: (),
+            }) => {
+                let addrs = match input {
+                    Input::VirtOffset(addrs) => addrs,
+                    Input::AbsAddr(..) => {
+                        return Err(Error::with_unsupported(
+                            "linker map symbolization does not support absolute address inputs",
+                        ))
+                    }
+                    Input::FileOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "linker map symbolization does not support file offset inputs",
+                        ))
+                    }
+                };
+
+                let resolver = self.linker_map_resolver(path)?;
+                let symbols =
+                    self.symbolize_addrs(addrs, &Resolver::Cached(resolver.deref()), alloc)?;
+                Ok(symbols)
+            }
+            #[cfg(not(feature = "breakpad"))]
+            Source::Breakpad(..) => Err(Error::with_unsupported(
+                "Breakpad symbolization requires the `breakpad` feature",
+            )),
+            #[cfg(feature = "breakpad")]
+            Source::Breakpad(Breakpad::Data(BreakpadData {
+                data,
+                _non_exhaustive: (),
+            })) => {
+                let addrs = match input {
+                    Input::VirtOffset(addrs) => addrs,
+                    Input::AbsAddr(..) => {
+                        return Err(Error::with_unsupported(
+                            "Breakpad symbolization does not support absolute address inputs",
+                        ))
+                    }
+                    Input::FileOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "Breakpad symbolization does not support file offset inputs",
+                        ))
+                    }
+                };
+
+                let resolver = BreakpadResolver::from_data(data)?;
+                let symbols = self.symbolize_addrs(addrs, &Resolver::Uncached(&resolver), alloc)?;
+                Ok(symbols)
+            }
+            #[cfg(feature = "breakpad")]
+            Source::Breakpad(Breakpad::File(BreakpadFile {
+                path,
+                _non_exhaustive: (),
+            })) => {
+                let addrs = match input {
+                    Input::VirtOffset(addrs) => addrs,
+                    Input::AbsAddr(..) => {
+                        return Err(Error::with_unsupported(
+                            "Breakpad symbolization does not support absolute address inputs",
+                        ))
+                    }
+                    Input::FileOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "Breakpad symbolization does not support file offset inputs",
+                        ))
+                    }
+                };
+
+                let resolver = self.breakpad_resolver(path)?;
+                let symbols =
+                    self.symbolize_addrs(addrs, &Resolver::Cached(resolver.deref()), alloc)?;
+                Ok(symbols)
+            }
+        }
+    }
+
+    /// Symbolize a list of addresses, allowing for cancellation of the
+    /// operation.
+    ///
+    /// This method behaves just like [`symbolize`][Self::symbolize],
+    /// except that the operation is aborted with an error of kind
+    /// [`ErrorKind::Interrupted`][crate::ErrorKind::Interrupted] once
+    /// `token` got cancelled. That includes potentially long running
+    /// one-time work, such as the indexing of DWARF debug information.
+    /// Data cached before cancellation remains valid and will be used
+    /// by subsequent requests.
+    pub fn symbolize_cancellable<'slf>(
+        &'slf self,
+        src: &Source,
+        input: Input<&[u64]>,
+        token: &CancellationToken,
+    ) -> Result<Vec<Symbolized<'slf>>> {
+        scope::with_token(token, || self.symbolize(src, input))
+    }
+
+    /// Symbolize a stack of addresses, substituting higher level frames
+    /// for native ones as directed by the provided [`FrameEnricher`].
+    ///
+    /// Addresses are expected to be ordered innermost frame first, as is
+    /// the case for a stack trace. They are symbolized as per
+    /// [`symbolize`][Self::symbolize]. Afterwards, each successfully
+    /// symbolized frame claimed by `enricher` is replaced with the
+    /// frames it substitutes.
+    ///
+    /// Contrary to [`symbolize`][Self::symbolize], this function does
+    /// not necessarily return exactly one [`Symbolized`] object for
+    /// each input address.
+    pub fn symbolize_enriched<'slf>(
+        &'slf self,
+        src: &Source,
+        input: Input<&[u64]>,
+        enricher: &mut dyn FrameEnricher,
+    ) -> Result<Vec<Symbolized<'slf>>> {
+        let addrs = input.into_inner();
+        let symbols = self.symbolize(src, input)?;
+        let mut enriched = Vec::with_capacity(symbols.len());
+
+        for (addr, symbol) in addrs.iter().zip(symbols) {
+            if let Symbolized::Sym(sym) = &symbol {
+                if enricher.claims(*addr, sym) {
+                    if let Some(frames) = enricher.substitute(*addr, sym) {
+                        let () = enriched.extend(frames.into_iter().map(Symbolized::Sym));
+                        continue
+                    }
+                }
+            }
+            let () = enriched.push(symbol);
+        }
+        Ok(enriched)
+    }
+
+    /// Symbolize a list of addresses, each carrying a caller provided
+    /// value.
+    ///
+    /// This method behaves like [`symbolize`][Self::symbolize], but
+    /// passes each input's value through to the corresponding output.
+    /// It allows for correlating symbolization results with, say, the
+    /// samples they originate from without maintaining parallel
+    /// arrays.
+    ///
+    /// ```no_run
+    /// # use blazesym::symbolize;
+    /// # use blazesym::Pid;
+    /// let src = symbolize::Source::Process(symbolize::Process::new(Pid::Slf));
+    /// // Addresses along with the IDs of the samples they belong to.
+    /// let samples = [(0x7f0000001234, 1), (0x7f0000005678, 2)];
+    /// let symbolizer = symbolize::Symbolizer::new();
+    /// let symbolized = symbolizer
+    ///     .symbolize_tagged(&src, symbolize::Input::AbsAddr(samples))
+    ///     .unwrap();
+    /// for (sym, sample_id) in symbolized {
+    ///     println!("sample {sample_id}: {sym:?}");
+    /// }
+    /// ```
+    pub fn symbolize_tagged<'slf, I, T>(
+        &'slf self,
+        src: &Source,
+        input: Input<I>,
+    ) -> Result<Vec<(Symbolized<'slf>, T)>>
+    where
+        I: IntoIterator<Item = (u64, T)>,
+    {
+        let mut addrs = Vec::new();
+        let mut tags = Vec::new();
+        let input = input.map(|items| {
+            (addrs, tags) = items.into_iter().unzip();
+        });
+        let symbols = self.symbolize(src, input.map(|()| addrs.as_slice()))?;
+        debug_assert_eq!(symbols.len(), tags.len());
+        Ok(symbols.into_iter().zip(tags).collect())
+    }
+
+    /// Symbolize the outputs of address normalization.
+    ///
+    /// Normalized outputs are grouped by the component they belong to,
+    /// as per [`Batch::from_output`], with `locator` mapping each
+    /// component to the corresponding artifact on the local system.
+    /// Each batch is then symbolized using
+    /// [`symbolize`][Self::symbolize].
+    ///
+    /// Exactly one [`Symbolized`] object is reported for each output,
+    /// in the same order. Outputs that could not be normalized are
+    /// reported as [`Reason::Unmapped`], while those for which no
+    /// artifact could be located (or the located artifact does not
+    /// exist) are reported as [`Reason::MissingSyms`].
+    pub fn symbolize_normalized<'slf>(
+        &'slf self,
+        output: &UserOutput,
+        locator: &dyn Locator,
+    ) -> Result<Vec<Symbolized<'slf>>> {
+        let mut symbols = output
+            .outputs
+            .iter()
+            .map(|(_output, meta_idx)| match output.meta.get(*meta_idx) {
+                Some(UserMeta::Unknown(..)) | None => Symbolized::Unknown(Reason::Unmapped),
+                Some(..) => Symbolized::Unknown(Reason::MissingSyms),
+            })
+            .collect::<Vec<_>>();
+
+        let batches = Batch::from_output(output, locator)?;
+        // Load all ELF files upfront, allowing for them to be read
+        // concurrently. Errors are reported as part of symbolization.
+        let paths = batches
+            .iter()
+            .filter_map(|batch| match &batch.src {
+                Source::Elf(elf) => Some(elf.path.as_path()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if paths.len() > 1 {
+            let _results = self.elf_cache.preload(&paths);
+        }
+
+        for batch in batches {
+            let batch_symbols = match self.symbolize(&batch.src, batch.input()) {
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                result => result?,
+            };
+            for (idx, symbol) in batch.indices.into_iter().zip(batch_symbols) {
+                symbols[idx] = symbol;
+            }
+        }
+        Ok(symbols)
+    }
+
+    /// Retrieve the resolver for the provided [`Tier`].
+    fn tier_resolver<'dat>(&self, tier: &Tier<'dat>) -> Result<TierResolver<'dat>> {
+        match tier {
+            #[cfg(feature = "gsym")]
+            Tier::Gsym(Gsym::Data(GsymData {
+                data,
+                _non_exhaustive: (),
+            })) => {
+                let resolver = GsymResolver::with_data(data)?;
+                Ok(TierResolver::Uncached(Box::new(resolver)))
+            }
+            #[cfg(feature = "gsym")]
+            Tier::Gsym(Gsym::File(GsymFile {
+                path,
+                _non_exhaustive: (),
+            })) => {
+                let resolver = self.gsym_resolver(path)?;
+                Ok(TierResolver::Cached(resolver))
+            }
+            #[cfg(not(feature = "gsym"))]
+            Tier::Gsym(..) => Err(Error::with_unsupported(
+                "Gsym symbolization requires the `gsym` feature",
+            )),
+            #[cfg(feature = "dwarf")]
+            Tier::Dwarf(path) => {
+                let resolver = self.elf_resolver(path, true)?;
+                match resolver.dwarf() {
+                    Some(dwarf) => Ok(TierResolver::Cached(dwarf.clone())),
+                    // With deferred indexing, debug information may not
+                    // have been indexed yet.
+                    None => Ok(TierResolver::Unavailable(Reason::MissingSyms)),
+                }
+            }
+            #[cfg(not(feature = "dwarf"))]
+            Tier::Dwarf(..) => Err(Error::with_unsupported(
+                "DWARF symbolization requires the `dwarf` feature",
+            )),
+            Tier::Symtab(path) => {
+                let resolver = self.elf_resolver(path, false)?;
+                let resolver = self.elf_sym_resolver(path, false, &resolver)?;
+                Ok(TierResolver::Cached(resolver))
+            }
+            Tier::PerfMap(path) => {
+                let resolver = self.perf_map_resolver(path)?.ok_or_else(|| {
+                    Error::with_not_found(format!("perf map `{}` not found", path.display()))
+                })?;
+                Ok(TierResolver::Cached(resolver))
+            }
+            Tier::LinkerMap(path) => {
+                let resolver = self.linker_map_resolver(path)?;
+                Ok(TierResolver::Cached(resolver))
+            }
+            #[cfg(feature = "breakpad")]
+            Tier::Breakpad(Breakpad::Data(BreakpadData {
+                data,
+                _non_exhaustive: (),
+            })) => {
+                let resolver = BreakpadResolver::from_data(data)?;
+                Ok(TierResolver::Uncached(Box::new(resolver)))
+            }
+            #[cfg(feature = "breakpad")]
+            Tier::Breakpad(Breakpad::File(BreakpadFile {
+                path,
+                _non_exhaustive: (),
+            })) => {
+                let resolver = self.breakpad_resolver(path)?;
+                Ok(TierResolver::Cached(resolver))
+            }
+            #[cfg(not(feature = "breakpad"))]
+            Tier::Breakpad(..) => Err(Error::with_unsupported(
+                "Breakpad symbolization requires the `breakpad` feature",
+            )),
+        }
+    }
+
+    /// Symbolize a list of addresses using a [`Fallback`] chain.
+    ///
+    /// For each address, the tiers of `fallback` are consulted in order
+    /// until one of them is able to symbolize it. The index of this
+    /// tier is reported as part of the result, as are the reasons for
+    /// skipping any of the tiers before it. Tiers that cannot be used
+    /// at all, for example because the file backing them does not
+    /// exist, are skipped instead of failing the operation.
+    ///
+    /// This function returns exactly one [`FallbackSymbolized`] object
+    /// for each input address, in the order of input addresses.
+    ///
+    /// ```no_run
+    /// # use blazesym::symbolize;
+    /// use blazesym::symbolize::Fallback;
+    /// use blazesym::symbolize::Tier;
+    ///
+    /// let fallback = Fallback::new(vec![
+    ///     Tier::Gsym(symbolize::Gsym::File(symbolize::GsymFile::new("/tmp/app.gsym"))),
+    ///     Tier::Dwarf("/tmp/app".into()),
+    ///     Tier::Symtab("/tmp/app".into()),
+    ///     Tier::PerfMap("/tmp/perf-1234.map".into()),
+    /// ]);
+    /// let symbolizer = symbolize::Symbolizer::new();
+    /// let results = symbolizer
+    ///     .symbolize_fallback(&fallback, &[0x2000100])
+    ///     .unwrap();
+    /// for result in results {
+    ///     println!("{:?} (tier {:?})", result.symbolized, result.tier);
+    /// }
+    /// ```
+    pub fn symbolize_fallback<'slf>(
+        &'slf self,
+        fallback: &Fallback<'_>,
+        addrs: &[Addr],
+    ) -> Result<Vec<FallbackSymbolized<'slf>>> {
+        self.with_scope(
+            || {
+                let mut results = self.symbolize_fallback_impl(fallback, addrs)?;
+                let () = self.add_source_context(
+                    results.iter_mut().map(|result| &mut result.symbolized),
+                    None,
+                );
+                Ok(results)
+            },
+            || {
+                addrs
+                    .iter()
+                    .map(|_addr| FallbackSymbolized {
+                        symbolized: Symbolized::Unknown(Reason::TimedOut),
+                        tier: None,
+                        skipped: Vec::new(),
+                        _non_exhaustive: (),
+                    })
+                    .collect()
+            },
+        )
+    }
+
+    fn symbolize_fallback_impl<'slf>(
+        &'slf self,
+        fallback: &Fallback<'_>,
+        addrs: &[Addr],
+    ) -> Result<Vec<FallbackSymbolized<'slf>>> {
+        /// Check whether an error should abort the operation as a
+        /// whole, instead of just causing a tier to be skipped.
+        fn aborts(err: &Error) -> bool {
+            matches!(err.kind(), ErrorKind::Interrupted | ErrorKind::TimedOut)
+        }
+
+        let Fallback {
+            tiers,
+            _non_exhaustive: (),
+        } = fallback;
+
+        // Resolvers are created lazily, once a tier is first consulted.
+        let mut resolvers = Vec::new();
+        let () = resolvers.resize_with(tiers.len(), || None);
+        let mut results = Vec::with_capacity(addrs.len());
+
+        'addrs: for addr in addrs {
+            let mut skipped = Vec::new();
+
+            for (idx, tier) in tiers.iter().enumerate() {
+                let resolver = match &mut resolvers[idx] {
+                    Some(resolver) => resolver,
+                    slot @ None => match self.tier_resolver(tier) {
+                        Err(err) if aborts(&err) => return Err(err),
+                        result => slot.insert(result.map_err(Arc::new)),
+                    },
+                };
+
+                let result = match resolver {
+                    Ok(TierResolver::Uncached(resolver)) => self.symbolize_with_resolver(
+                        *addr,
+                        &Resolver::Uncached(resolver.as_ref()),
+                        Alloc::Heap,
+                    ),
+                    Ok(TierResolver::Cached(resolver)) => self.symbolize_with_resolver(
+                        *addr,
+                        &Resolver::Cached(resolver.as_ref()),
+                        Alloc::Heap,
+                    ),
+                    Ok(TierResolver::Unavailable(reason)) => {
+                        Ok(Symbolized::Unknown(reason.clone()))
+                    }
+                    Err(err) => {
+                        let () = skipped.push((idx, Skipped::Failed(Arc::clone(err))));
+                        continue
+                    }
+                };
+
+                match result {
+                    Ok(Symbolized::Sym(sym)) => {
+                        let () = results.push(FallbackSymbolized {
+                            symbolized: Symbolized::Sym(sym),
+                            tier: Some(idx),
+                            skipped,
+                            _non_exhaustive: (),
+                        });
+                        continue 'addrs
+                    }
+                    // There is no point in consulting further tiers
+                    // once the time budget is exhausted.
+                    Ok(Symbolized::Unknown(Reason::TimedOut)) => {
+                        let () = results.push(FallbackSymbolized {
+                            symbolized: Symbolized::Unknown(Reason::TimedOut),
+                            tier: None,
+                            skipped,
+                            _non_exhaustive: (),
+                        });
+                        continue 'addrs
+                    }
+                    Ok(Symbolized::Unknown(reason)) => {
+                        let () = skipped.push((idx, Skipped::Unknown(reason)));
+                    }
+                    Err(err) if aborts(&err) => return Err(err),
+                    Err(err) => {
+                        let () = skipped.push((idx, Skipped::Failed(Arc::new(err))));
+                    }
+                }
             }
+
+            let reason = skipped
+                .iter()
+                .rev()
+                .find_map(|(_idx, skipped)| match skipped {
+                    Skipped::Unknown(reason) => Some(reason.clone()),
+                    Skipped::Failed(..) => None,
+                })
+                .unwrap_or(Reason::MissingSyms);
+            let () = results.push(FallbackSymbolized {
+                symbolized: Symbolized::Unknown(reason),
+                tier: None,
+                skipped,
+                _non_exhaustive: (),
+            });
         }
+        Ok(results)
     }
 
     /// Symbolize a single input address/offset.
@@ -842,6 +3517,234 @@ impl Symbolizer {
         &'slf self,
         src: &Source,
         input: Input<u64>,
+    ) -> Result<Symbolized<'slf>> {
+        self.with_scope(
+            || {
+                let mut symbolized = self.symbolize_single_impl(src, input, Alloc::Heap)?;
+                let () = self.add_source_context(
+                    slice::from_mut(&mut symbolized),
+                    source_root(src).as_deref(),
+                );
+                Ok(symbolized)
+            },
+            || Symbolized::Unknown(Reason::TimedOut),
+        )
+    }
+
+    /// Retrieve the file caches that can be managed without further
+    /// considerations.
+    ///
+    /// The ELF cache is not included, as it may be shared with
+    /// background threads.
+    fn file_caches(&self) -> Vec<&dyn ManagedCache> {
+        vec![
+            &self.apk_cache,
+            #[cfg(feature = "gsym")]
+            &self.gsym_cache,
+            #[cfg(feature = "kernel")]
+            &self.ksym_cache,
+            &self.perf_map_cache,
+            &self.jvm_perf_map_cache,
+            &self.jitdump_cache,
+            &self.linker_map_cache,
+            #[cfg(feature = "breakpad")]
+            &self.breakpad_cache,
+        ]
+    }
+
+    /// Evict cached files (and data derived from them) for which `f`,
+    /// being provided the path of a file and the time of its last use,
+    /// returns `false`.
+    ///
+    /// If `force` is `false`, ELF files in use by background indexing
+    /// are left untouched.
+    fn retain_cached<F>(&self, mut f: F, force: bool)
+    where
+        F: FnMut(&Path, u64) -> bool,
+    {
+        let mut evicted = Vec::new();
+        for cache in self.file_caches() {
+            let () = cache.retain(&mut f, &mut evicted);
+        }
+
+        {
+            let indexing = self.indexing.lock().unwrap_or_else(PoisonError::into_inner);
+            let () = self.elf_cache.retain(
+                &mut |path, last_use| f(path, last_use) || (!force && indexing.contains(path)),
+                &mut evicted,
+            );
+        }
+
+        let evicted = evicted.into_iter().collect::<HashSet<_>>();
+        let mut removed = evicted.len();
+        removed += self
+            .debug_file_resolvers
+            .retain(|path, _resolver| !evicted.contains(path));
+        // Shared debug information is dropped once no longer referenced
+        // by any path.
+        removed += self
+            .debug_files
+            .retain(|_build_id, resolver| Arc::strong_count(resolver) > 1);
+        // Kernel modules are not tracked in terms of usage. They are
+        // only ever evicted explicitly.
+        #[cfg(feature = "kernel")]
+        let removed = removed
+            + self
+                .kmod_cache
+                .retain(|(path, _debug_syms), _resolver| f(path, u64::MAX));
+        let () = self
+            .indexing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|path| !evicted.contains(path));
+
+        if removed > 0 {
+            // Cached results are keyed by resolver identity, which may
+            // get reused once a resolver is gone.
+            if let Some(cache) = &self.result_cache {
+                let () = cache.clear();
+            }
+        }
+    }
+
+    /// Retrieve statistics about the files (and data parsed from
+    /// them) cached by this `Symbolizer`.
+    pub fn cache_stats(&self) -> CacheStats {
+        let mut stats = self.elf_cache.stats();
+        for cache in self.file_caches() {
+            stats += &cache.stats();
+        }
+        stats
+    }
+
+    /// Evict the least recently used cached files (and data parsed from
+    /// them) until the configured [`CacheLimits`] (see
+    /// [`Builder::set_cache_limits`]) are met.
+    ///
+    /// Limits are enforced after each symbolization request
+    /// automatically. ELF files that are currently being indexed on a
+    /// background thread (as part of deferred indexing) are not
+    /// evicted and the limits may hence not be met after the call.
+    ///
+    /// Evicted data is released once no longer used by requests in
+    /// flight.
+    pub fn trim_cache(&self) {
+        let limits = if let Some(limits) = &self.cache_limits {
+            limits
+        } else {
+            return
+        };
+
+        let mut usage = Vec::new();
+        let () = self.elf_cache.usage(&mut usage);
+        for cache in self.file_caches() {
+            let () = cache.usage(&mut usage);
+        }
+        // Sort by time of last use, most recently used first.
+        let () = usage.sort_unstable_by(|(use1, _), (use2, _)| use2.cmp(use1));
+
+        let mut bytes = 0;
+        let cutoff = usage
+            .iter()
+            .enumerate()
+            .find_map(|(idx, (last_use, size))| {
+                bytes += size;
+                limits.exceeded(idx + 1, bytes).then_some(*last_use)
+            });
+
+        if let Some(cutoff) = cutoff {
+            let () = self.retain_cached(|_path, last_use| last_use > cutoff, false);
+        }
+    }
+
+    /// Evict all cached data pertaining to the file at `path`.
+    ///
+    /// Files that changed are detected and reloaded automatically, but
+    /// data of their previous versions is only released once evicted.
+    pub fn invalidate_file(&self, path: &Path) {
+        let () = self.retain_cached(|cached, _last_use| cached != path, true);
+    }
+
+    /// Evict all cached data pertaining to the process with the given
+    /// ID, e.g., because it exited or `exec`'ed.
+    ///
+    /// This includes data of files accessed through the process'
+    /// `/proc/<pid>/` directory as well as of its JIT symbol files.
+    pub fn invalidate_process(&self, pid: Pid) {
+        let proc_dirs = match pid {
+            // Files of our own process may be accessed using either
+            // form.
+            Pid::Slf => vec![
+                PathBuf::from("/proc/self"),
+                PathBuf::from(format!("/proc/{}", process::id())),
+            ],
+            Pid::Pid(..) => vec![PathBuf::from(format!("/proc/{pid}"))],
+        };
+        let perf_map = jit::perf_map_path(pid);
+        let jitdump = jit::jitdump_path(pid);
+        let () = self.retain_cached(
+            |path, _last_use| {
+                !proc_dirs.iter().any(|dir| path.starts_with(dir))
+                    && path != perf_map
+                    && path != jitdump
+            },
+            true,
+        );
+        let _removed = self
+            .prefetched
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&pid);
+        if let Some(cache) = &self.maps_cache {
+            let () = cache.invalidate(pid);
+        }
+    }
+
+    /// Evict all cached data.
+    pub fn clear_cache(&self) {
+        let () = self.retain_cached(|_path, _last_use| false, true);
+        if let Some(cache) = &self.maps_cache {
+            let () = cache.clear();
+        }
+    }
+
+    /// Notify the `Symbolizer` that the process with the given ID
+    /// changed its memory mappings, e.g., because it mapped or unmapped
+    /// a file (as happens on `dlopen` and `dlclose`).
+    ///
+    /// Cached proc maps entries of the process (see
+    /// [`Builder::set_maps_cache`]) are refreshed on the next request
+    /// involving it. The method is meant to be fed by events about the
+    /// process, such as those gathered by BPF programs, and can be
+    /// called while other requests are in flight.
+    pub fn notify_mmap(&self, pid: Pid) {
+        if let Some(cache) = &self.maps_cache {
+            let () = cache.invalidate(pid);
+        }
+    }
+
+    /// Notify the `Symbolizer` that the process with the given ID
+    /// `exec`'ed (or exited, with its ID being up for reuse).
+    ///
+    /// On top of what [`Symbolizer::notify_mmap`] does, background
+    /// prefetching (see [`Builder::set_prefetch`]) is triggered again
+    /// for the next request involving the process. Use
+    /// [`Symbolizer::invalidate_process`] to also evict cached data of
+    /// files accessed through the process.
+    pub fn notify_exec(&self, pid: Pid) {
+        let () = self.notify_mmap(pid);
+        let _removed = self
+            .prefetched
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&pid);
+    }
+
+    fn symbolize_single_impl<'slf>(
+        &'slf self,
+        src: &Source,
+        input: Input<u64>,
+        alloc: Alloc<'slf>,
     ) -> Result<Symbolized<'slf>> {
         match src {
             Source::Apk(Apk {
@@ -860,19 +3763,21 @@ impl Symbolizer {
                     ))
                 }
                 Input::FileOffset(offset) => match self.apk_resolver(path, offset, *debug_syms)? {
-                    Some((elf_resolver, elf_addr)) => self
-                        .symbolize_with_resolver(elf_addr, &Resolver::Cached(elf_resolver.deref())),
+                    Some((elf_resolver, elf_addr)) => self.symbolize_with_resolver(
+                        elf_addr,
+                        &Resolver::Cached(elf_resolver.deref()),
+                        alloc,
+                    ),
                     None => return Ok(Symbolized::Unknown(Reason::InvalidFileOffset)),
                 },
             },
             Source::Elf(Elf {
                 path,
                 debug_syms,
+                hot_ranges,
                 _non_exhaustive: (),
             }) => {
-                let resolver = self
-                    .elf_cache
-                    .elf_resolver(path, *debug_syms, self.code_info)?;
+                let resolver = self.elf_src_resolver(path, *debug_syms, hot_ranges.as_deref())?;
                 let addr = match input {
                     Input::VirtOffset(addr) => addr,
                     Input::AbsAddr(..) => {
@@ -888,8 +3793,14 @@ impl Symbolizer {
                     }
                 };
 
-                self.symbolize_with_resolver(addr, &Resolver::Cached(resolver.deref()))
+                let resolver = self.elf_sym_resolver(path, *debug_syms, &resolver)?;
+                self.symbolize_elf_src_addr(addr, path, &*resolver, alloc)
             }
+            #[cfg(not(feature = "kernel"))]
+            Source::Kernel(..) => Err(Error::with_unsupported(
+                "kernel symbolization requires the `kernel` feature",
+            )),
+            #[cfg(feature = "kernel")]
             Source::Kernel(kernel) => {
                 let addr = match input {
                     Input::AbsAddr(addr) => addr,
@@ -905,9 +3816,14 @@ impl Symbolizer {
                     }
                 };
 
-                let resolver = Rc::new(self.create_kernel_resolver(kernel)?);
-                self.symbolize_with_resolver(addr, &Resolver::Uncached(resolver.deref()))
+                let resolver = Arc::new(self.create_kernel_resolver(kernel, &[addr])?);
+                self.symbolize_with_resolver(addr, &Resolver::Uncached(resolver.deref()), alloc)
             }
+            #[cfg(not(feature = "process"))]
+            Source::Process(..) => Err(Error::with_unsupported(
+                "process symbolization requires the `process` feature",
+            )),
+            #[cfg(feature = "process")]
             Source::Process(Process {
                 pid,
                 debug_syms,
@@ -927,13 +3843,117 @@ impl Symbolizer {
                     }
                 };
 
-                let mut symbols = self.symbolize_user_addrs(&[addr], *pid, *debug_syms)?;
+                let mut symbols =
+                    self.symbolize_user_addrs(&[addr], *pid, None, *debug_syms, alloc)?;
                 debug_assert!(symbols.len() == 1, "{symbols:#?}");
                 // SANITY: `symbolize_user_addrs` should *always* return
                 //         one result for one input (except on error
                 //         paths, of course).
                 Ok(symbols.pop().unwrap())
             }
+            #[cfg(not(feature = "process"))]
+            Source::Jvm(..) => Err(Error::with_unsupported(
+                "JVM symbolization requires the `process` feature",
+            )),
+            #[cfg(feature = "process")]
+            Source::Jvm(jvm) => {
+                let addr = match input {
+                    Input::AbsAddr(addr) => addr,
+                    Input::VirtOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "JVM symbolization does not support virtual offset inputs",
+                        ))
+                    }
+                    Input::FileOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "JVM symbolization does not support file offset inputs",
+                        ))
+                    }
+                };
+
+                let mut symbols = self.symbolize_jvm_addrs(&[addr], jvm, None, alloc)?;
+                debug_assert!(symbols.len() == 1, "{symbols:#?}");
+                // SANITY: `symbolize_jvm_addrs` should *always* return
+                //         one result for one input (except on error
+                //         paths, of course).
+                Ok(symbols.pop().unwrap())
+            }
+            #[cfg(not(feature = "process"))]
+            Source::Node(..) => Err(Error::with_unsupported(
+                "Node.js symbolization requires the `process` feature",
+            )),
+            #[cfg(feature = "process")]
+            Source::Node(node) => {
+                let addr = match input {
+                    Input::AbsAddr(addr) => addr,
+                    Input::VirtOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "Node.js symbolization does not support virtual offset inputs",
+                        ))
+                    }
+                    Input::FileOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "Node.js symbolization does not support file offset inputs",
+                        ))
+                    }
+                };
+
+                let mut symbols = self.symbolize_node_addrs(&[addr], node, None, alloc)?;
+                debug_assert!(symbols.len() == 1, "{symbols:#?}");
+                // SANITY: `symbolize_node_addrs` should *always* return
+                //         one result for one input (except on error
+                //         paths, of course).
+                Ok(symbols.pop().unwrap())
+            }
+            Source::Snapshot(snapshot) => {
+                let addr = match input {
+                    Input::AbsAddr(addr) => addr,
+                    Input::VirtOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "snapshot symbolization does not support virtual offset inputs",
+                        ))
+                    }
+                    Input::FileOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "snapshot symbolization does not support file offset inputs",
+                        ))
+                    }
+                };
+
+                let mut symbols = self.symbolize_snapshot_addrs(&[addr], snapshot, alloc)?;
+                debug_assert!(symbols.len() == 1, "{symbols:#?}");
+                // SANITY: `symbolize_snapshot_addrs` should *always*
+                //         return one result for one input (except on
+                //         error paths, of course).
+                Ok(symbols.pop().unwrap())
+            }
+            Source::Coredump(coredump) => {
+                let addr = match input {
+                    Input::AbsAddr(addr) => addr,
+                    Input::VirtOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "core file symbolization does not support virtual offset inputs",
+                        ))
+                    }
+                    Input::FileOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "core file symbolization does not support file offset inputs",
+                        ))
+                    }
+                };
+
+                let mut symbols = self.symbolize_coredump_addrs(&[addr], coredump, alloc)?;
+                debug_assert!(symbols.len() == 1, "{symbols:#?}");
+                // SANITY: `symbolize_coredump_addrs` should *always*
+                //         return one result for one input (except on
+                //         error paths, of course).
+                Ok(symbols.pop().unwrap())
+            }
+            #[cfg(not(feature = "gsym"))]
+            Source::Gsym(..) => Err(Error::with_unsupported(
+                "Gsym symbolization requires the `gsym` feature",
+            )),
+            #[cfg(feature = "gsym")]
             Source::Gsym(Gsym::Data(GsymData {
                 data,
                 _non_exhaustive: (),
@@ -952,9 +3972,10 @@ impl Symbolizer {
                     }
                 };
 
-                let resolver = Rc::new(GsymResolver::with_data(data)?);
-                self.symbolize_with_resolver(addr, &Resolver::Uncached(resolver.deref()))
+                let resolver = Arc::new(GsymResolver::with_data(data)?);
+                self.symbolize_with_resolver(addr, &Resolver::Uncached(resolver.deref()), alloc)
             }
+            #[cfg(feature = "gsym")]
             Source::Gsym(Gsym::File(GsymFile {
                 path,
                 _non_exhaustive: (),
@@ -974,7 +3995,76 @@ impl Symbolizer {
                 };
 
                 let resolver = self.gsym_resolver(path)?;
-                self.symbolize_with_resolver(addr, &Resolver::Cached(resolver.deref()))
+                self.symbolize_with_resolver(addr, &Resolver::Cached(resolver.deref()), alloc)
+            }
+            Source::LinkerMap(LinkerMap {
+                path,
+                _non_exhaustive: (),
+            }) => {
+                let addr = match input {
+                    Input::VirtOffset(addr) => addr,
+                    Input::AbsAddr(..) => {
+                        return Err(Error::with_unsupported(
+                            "linker map symbolization does not support absolute address inputs",
+                        ))
+                    }
+                    Input::FileOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "linker map symbolization does not support file offset inputs",
+                        ))
+                    }
+                };
+
+                let resolver = self.linker_map_resolver(path)?;
+                self.symbolize_with_resolver(addr, &Resolver::Cached(resolver.deref()), alloc)
+            }
+            #[cfg(not(feature = "breakpad"))]
+            Source::Breakpad(..) => Err(Error::with_unsupported(
+                "Breakpad symbolization requires the `breakpad` feature",
+            )),
+            #[cfg(feature = "breakpad")]
+            Source::Breakpad(Breakpad::Data(BreakpadData {
+                data,
+                _non_exhaustive: (),
+            })) => {
+                let addr = match input {
+                    Input::VirtOffset(addr) => addr,
+                    Input::AbsAddr(..) => {
+                        return Err(Error::with_unsupported(
+                            "Breakpad symbolization does not support absolute address inputs",
+                        ))
+                    }
+                    Input::FileOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "Breakpad symbolization does not support file offset inputs",
+                        ))
+                    }
+                };
+
+                let resolver = BreakpadResolver::from_data(data)?;
+                self.symbolize_with_resolver(addr, &Resolver::Uncached(&resolver), alloc)
+            }
+            #[cfg(feature = "breakpad")]
+            Source::Breakpad(Breakpad::File(BreakpadFile {
+                path,
+                _non_exhaustive: (),
+            })) => {
+                let addr = match input {
+                    Input::VirtOffset(addr) => addr,
+                    Input::AbsAddr(..) => {
+                        return Err(Error::with_unsupported(
+                            "Breakpad symbolization does not support absolute address inputs",
+                        ))
+                    }
+                    Input::FileOffset(..) => {
+                        return Err(Error::with_unsupported(
+                            "Breakpad symbolization does not support file offset inputs",
+                        ))
+                    }
+                };
+
+                let resolver = self.breakpad_resolver(path)?;
+                self.symbolize_with_resolver(addr, &Resolver::Cached(resolver.deref()), alloc)
             }
         }
     }
@@ -992,6 +4082,7 @@ mod tests {
     use super::*;
 
     use std::mem::transmute;
+    use std::ptr;
 
     use crate::elf::ElfParser;
     use crate::inspect::FindAddrOpts;
@@ -1016,15 +4107,6 @@ mod tests {
         assert_ne!(format!("{symbolizer:?}"), "");
     }
 
-    /// Check that we can create a path to an ELF inside an APK as expected.
-    #[test]
-    fn elf_apk_path_creation() {
-        let apk = Path::new("/root/test.apk");
-        let elf = Path::new("subdir/libc.so");
-        let path = create_apk_elf_path(apk, elf).unwrap();
-        assert_eq!(path, Path::new("/root/test.apk!/subdir/libc.so"));
-    }
-
     /// Check that we can correctly construct the source code path to a symbol.
     #[test]
     fn symbol_source_code_path() {
@@ -1033,6 +4115,7 @@ mod tests {
             file: Cow::Borrowed(OsStr::new("source.c")),
             line: Some(1),
             column: Some(2),
+            context: None,
             _non_exhaustive: (),
         };
         assert_eq!(info.to_path(), Path::new("source.c"));
@@ -1048,16 +4131,51 @@ mod tests {
             return
         }
 
+        let symbolizer = Symbolizer::new();
         let symbol = Cow::Borrowed("_ZN4core9panicking9panic_fmt17h5f1a6fd39197ad62E");
-        let name = maybe_demangle(symbol, SrcLang::Rust);
+        let name = symbolizer.maybe_demangle(symbol, SrcLang::Rust, Alloc::Heap);
         assert_eq!(name, "core::panicking::panic_fmt");
 
         let symbol = Cow::Borrowed("_ZStlsISt11char_traitsIcEERSt13basic_ostreamIcT_ES5_PKc");
-        let name = maybe_demangle(symbol, SrcLang::Cpp);
+        let name = symbolizer.maybe_demangle(symbol, SrcLang::Cpp, Alloc::Heap);
         assert_eq!(
             name,
             "std::basic_ostream<char, std::char_traits<char> >& std::operator<< <std::char_traits<char> >(std::basic_ostream<char, std::char_traits<char> >&, char const*)"
         );
+
+        let symbol = Cow::Borrowed("$s4main3add1a1bS2i_SitF");
+        let name = symbolizer.maybe_demangle(symbol, SrcLang::Unknown, Alloc::Heap);
+        assert_eq!(name, "main.add(a: Swift.Int, b: Swift.Int) -> Swift.Int");
+
+        let symbol = Cow::Borrowed("?foo@@YAXXZ");
+        let name = symbolizer.maybe_demangle(symbol, SrcLang::Unknown, Alloc::Heap);
+        assert_eq!(name, "void __cdecl foo(void)");
+
+        let symbol = Cow::Borrowed("?bar@Foo@@QEAAHH@Z");
+        let name = symbolizer.maybe_demangle(symbol, SrcLang::Cpp, Alloc::Heap);
+        assert_eq!(name, "public: int __cdecl Foo::bar(int)");
+
+        let symbol = Cow::Borrowed("_D3std5stdio7writelnFAyaZv");
+        let name = symbolizer.maybe_demangle(symbol, SrcLang::Unknown, Alloc::Heap);
+        assert_eq!(name, "std.stdio.writeln(immutable(char)[])");
+
+        let symbol = Cow::Borrowed("ada__calendar__clock");
+        let name = symbolizer.maybe_demangle(symbol, SrcLang::Unknown, Alloc::Heap);
+        assert_eq!(name, "ada.calendar.clock");
+
+        let symbol = Cow::Borrowed("pkg__proc__2");
+        let name = symbolizer.maybe_demangle(symbol, SrcLang::Ada, Alloc::Heap);
+        assert_eq!(name, "pkg.proc");
+
+        // Unsupported Swift symbols should be reported as-is.
+        let symbol = Cow::Borrowed("$s4main3fooyyFTf4n_n");
+        let name = symbolizer.maybe_demangle(symbol, SrcLang::Swift, Alloc::Heap);
+        assert_eq!(name, "$s4main3fooyyFTf4n_n");
+
+        // Cached results should be reported just the same.
+        let symbol = Cow::Borrowed("_ZN4core9panicking9panic_fmt17h5f1a6fd39197ad62E");
+        let name = symbolizer.maybe_demangle(symbol, SrcLang::Rust, Alloc::Heap);
+        assert_eq!(name, "core::panicking::panic_fmt");
     }
 
     /// Make sure that we error out as expected on certain input
@@ -1167,4 +4285,186 @@ mod tests {
         assert_eq!(result.name, "the_answer");
         assert_eq!(result.addr, sym.addr);
     }
+
+    /// Check that parallel symbolization of process addresses reports
+    /// the same results as the sequential variant and honors the scope
+    /// of the calling thread.
+    #[cfg(feature = "process")]
+    #[test]
+    fn symbolize_par_process() {
+        let addrs = (0..1024)
+            .map(|i| symbolize_par_process as *const () as Addr + i)
+            .collect::<Vec<_>>();
+        let src = symbolize::Source::Process(symbolize::Process::new(Pid::Slf));
+        let symbolizer = Symbolizer::builder()
+            .set_parallelism(NonZeroUsize::new(4))
+            .build();
+        let expected = symbolizer.symbolize(&src, Input::AbsAddr(&addrs)).unwrap();
+        assert!(expected[0].as_sym().is_some());
+        let results = symbolizer
+            .symbolize_par(&src, Input::AbsAddr(&addrs))
+            .unwrap();
+        assert_eq!(results, expected);
+
+        let token = CancellationToken::new();
+        let () = token.cancel();
+        let err = scope::with_token(&token, || {
+            symbolizer.symbolize_par(&src, Input::AbsAddr(&addrs))
+        })
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Interrupted);
+    }
+
+    /// Check that we classify special regions as expected.
+    #[test]
+    fn special_region_classification() {
+        let maps = r#"
+55d3195b7000-55d3195b9000 r--p 00000000 00:12 2015701                    /bin/cat
+55d3195b9000-55d3195ba000 ---p 00002000 00:12 2015701                    /bin/cat
+55d31b4dc000-55d31b4fd000 rw-p 00000000 00:00 0                          [heap]
+7fd5b9c3d000-7fd5b9c5f000 rw-p 00000000 00:00 0
+7fd5b9c5f000-7fd5b9c60000 ---p 00000000 00:00 0
+7fd5b9c60000-7fd5b9c61000 rw-p 00000000 00:00 0                          [stack:1234]
+7ffe102a2000-7ffe102c4000 rw-p 00000000 00:00 0                          [stack]
+7ffe103f6000-7ffe103fa000 r--p 00000000 00:00 0                          [vvar]
+7ffe103fa000-7ffe103fc000 r-xp 00000000 00:00 0                          [vdso]
+ffffffffff600000-ffffffffff601000 --xp 00000000 00:00 0                  [vsyscall]
+"#;
+        let regions = maps::parse_file(maps.as_bytes(), Pid::Slf)
+            .map(|entry| classify_special_region(&entry.unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            regions,
+            vec![
+                None,
+                Some(SpecialRegion::Guard),
+                Some(SpecialRegion::Heap),
+                None,
+                Some(SpecialRegion::Guard),
+                Some(SpecialRegion::Stack),
+                Some(SpecialRegion::Stack),
+                None,
+                Some(SpecialRegion::Vdso),
+                Some(SpecialRegion::Vsyscall),
+            ]
+        );
+    }
+
+    /// Check that addresses in special regions of our own process are
+    /// reported as such.
+    #[test]
+    fn symbolize_special_region() {
+        // The file name of the executable is stored on the main
+        // thread's stack.
+        let stack_addr = unsafe { libc::getauxval(libc::AT_EXECFN) } as Addr;
+        assert_ne!(stack_addr, 0);
+
+        let guard = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                4096,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        assert_ne!(guard, libc::MAP_FAILED);
+
+        let src = symbolize::Source::Process(symbolize::Process::new(Pid::Slf));
+        let symbolizer = Symbolizer::new();
+        let result = symbolizer.symbolize(
+            &src,
+            symbolize::Input::AbsAddr(&[stack_addr, guard as Addr + 16]),
+        );
+        let _rc = unsafe { libc::munmap(guard, 4096) };
+
+        let symbolized = result.unwrap();
+        assert_eq!(
+            symbolized,
+            vec![
+                Symbolized::Unknown(Reason::SpecialRegion(SpecialRegion::Stack)),
+                Symbolized::Unknown(Reason::SpecialRegion(SpecialRegion::Guard)),
+            ]
+        );
+        assert_eq!(
+            Reason::SpecialRegion(SpecialRegion::Stack).to_string(),
+            "absolute address belongs to stack region"
+        );
+    }
+
+    /// Check that we can symbolize addresses in the vDSO of our own
+    /// process.
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn symbolize_vdso() {
+        let entry = maps::parse(&StdFileAccess::default(), Pid::Slf)
+            .unwrap()
+            .map(Result::unwrap)
+            .find(vdso::is_vdso);
+        // Not all systems map a vDSO.
+        let entry = match entry {
+            Some(entry) => entry,
+            None => return,
+        };
+
+        let image = vdso::read_image(Pid::Slf, &entry.range).unwrap();
+        let parser = ElfParser::from_mmap(Mmap::from_vec(image));
+        let opts = FindAddrOpts {
+            offset_in_file: true,
+            ..Default::default()
+        };
+        let syms = parser.find_addr("__vdso_clock_gettime", &opts).unwrap();
+        let sym = syms.first().unwrap();
+        let addr = entry.range.start + sym.file_offset.unwrap() + 2;
+
+        let src = symbolize::Source::Process(symbolize::Process::new(Pid::Slf));
+        let symbolizer = Symbolizer::new();
+        let symbolized = symbolizer
+            .symbolize_single(&src, symbolize::Input::AbsAddr(addr))
+            .unwrap();
+        let sym = symbolized.into_sym().unwrap();
+        assert!(sym.name.ends_with("clock_gettime"), "{}", sym.name);
+        assert_eq!(sym.offset, 2);
+        assert_eq!(
+            sym.module.as_deref(),
+            Some(Path::new(vdso::VDSO_MAPS_COMPONENT))
+        );
+
+        // Without access to the process' memory we report the address
+        // as belonging to the vDSO.
+        let maps = format!(
+            "{:x}-{:x} r-xp 00000000 00:00 0 [vdso]\n",
+            entry.range.start, entry.range.end
+        );
+        let src = symbolize::Source::Snapshot(symbolize::Snapshot::new(&maps));
+        let symbolized = symbolizer
+            .symbolize_single(&src, symbolize::Input::AbsAddr(addr))
+            .unwrap();
+        assert_eq!(
+            symbolized,
+            Symbolized::Unknown(Reason::SpecialRegion(SpecialRegion::Vdso))
+        );
+    }
+
+    /// Check that the default paths of JIT runtime files of our own
+    /// process are resolved correctly.
+    #[test]
+    fn jit_file_path_resolution() {
+        let symbolizer = Symbolizer::new();
+        let path = symbolizer
+            .jit_file_path(Pid::Slf, jit::perf_map_path)
+            .unwrap();
+        assert_eq!(
+            path,
+            PathBuf::from(format!("/tmp/perf-{}.map", std::process::id()))
+        );
+        let path = symbolizer
+            .jit_file_path(Pid::Slf, jit::jitdump_path)
+            .unwrap();
+        assert_eq!(
+            path,
+            PathBuf::from(format!("/tmp/jit-{}.dump", std::process::id()))
+        );
+    }
 }
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs::read_dir;
+use std::fs::File;
+use std::io::Read as _;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::log;
+use crate::normalize::buildid::read_elf_build_id;
+use crate::normalize::UserMeta;
+use crate::once::OnceCell;
+use crate::ErrorExt as _;
+use crate::Result;
+
+use super::Locator;
+
+
+/// The magic bytes at the start of every ELF file.
+const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+
+
+/// Check whether the file at `path` starts with the ELF magic.
+fn is_elf(path: &Path) -> bool {
+    let mut magic = [0; ELF_MAGIC.len()];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map(|()| &magic == ELF_MAGIC)
+        .unwrap_or(false)
+}
+
+/// Retrieve the build ID of the ELF file at `path`, if any.
+fn build_id(path: &Path) -> Option<Vec<u8>> {
+    match read_elf_build_id(&path) {
+        Ok(build_id) => build_id,
+        Err(err) => {
+            log::debug!("failed to read build ID of {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+
+/// A directory of (unstripped) binaries used for symbolizing addresses
+/// normalized on a different system, such as an Android device.
+///
+/// Binaries are located in two ways:
+/// - by path, in a directory hierarchy mirroring the one of the system
+///   the addresses were normalized on (e.g., the `symbols/` directory
+///   of an Android build or the directory passed to `simpleperf
+///   --symfs`). For example, addresses in `/system/lib64/libc.so` are
+///   symbolized using `<dir>/system/lib64/libc.so`
+/// - by build ID, in which case all ELF files in the directory and its
+///   sub-directories are considered (similar to `simpleperf
+///   --symdir`), irrespective of their location
+///
+/// If the meta data reported by normalization contains a build ID, a
+/// binary found by path is only used if its build ID matches.
+///
+/// A `SymbolDir` is a [`Locator`] and meant to be used with
+/// [`Symbolizer::symbolize_normalized`][super::Symbolizer::symbolize_normalized].
+pub struct SymbolDir {
+    /// The root of the directory hierarchy.
+    dir: PathBuf,
+    /// The ELF files in `dir`, by build ID; populated lazily.
+    by_build_id: OnceCell<HashMap<Vec<u8>, PathBuf>>,
+}
+
+impl SymbolDir {
+    /// Create a new [`SymbolDir`] for the directory at `dir`.
+    #[inline]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            by_build_id: OnceCell::new(),
+        }
+    }
+
+    /// Retrieve the path to the file mirroring `path` in our directory.
+    fn mirrored_path(&self, path: &Path) -> PathBuf {
+        let relative = path
+            .components()
+            .filter(|component| matches!(component, Component::Normal(..)))
+            .collect::<PathBuf>();
+        self.dir.join(relative)
+    }
+
+    /// Recursively index all ELF files in `dir` by their build ID.
+    fn index_dir(dir: &Path, index: &mut HashMap<Vec<u8>, PathBuf>) -> Result<()> {
+        let entries =
+            read_dir(dir).with_context(|| format!("failed to read directory {}", dir.display()))?;
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    log::debug!("failed to read entry of {}: {err}", dir.display());
+                    continue
+                }
+            };
+            let path = entry.path();
+            // Use the type of the entry itself (as opposed to that of
+            // the symbolic link target), so that we never follow
+            // symbolic links to directories and can't end up in a
+            // loop.
+            let is_dir = entry
+                .file_type()
+                .map(|file_type| file_type.is_dir())
+                .unwrap_or(false);
+            if is_dir {
+                if let Err(err) = Self::index_dir(&path, index) {
+                    log::debug!("{err}");
+                }
+            } else if is_elf(&path) {
+                if let Some(build_id) = build_id(&path) {
+                    let _path = index.entry(build_id).or_insert(path);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Retrieve the index of ELF files by build ID, creating it if
+    /// necessary.
+    fn by_build_id(&self) -> Result<&HashMap<Vec<u8>, PathBuf>> {
+        self.by_build_id.get_or_try_init(|| {
+            let mut index = HashMap::new();
+            let () = Self::index_dir(&self.dir, &mut index)?;
+            Ok(index)
+        })
+    }
+
+    fn locate_elf(&self, path: &Path, build_id: Option<&[u8]>) -> Result<Option<PathBuf>> {
+        let mirrored = self.mirrored_path(path);
+        if mirrored.is_file() {
+            match build_id {
+                None => return Ok(Some(mirrored)),
+                Some(build_id) if self::build_id(&mirrored).as_deref() == Some(build_id) => {
+                    return Ok(Some(mirrored))
+                }
+                Some(..) => (),
+            }
+        }
+
+        if let Some(build_id) = build_id {
+            let path = self.by_build_id()?.get(build_id).cloned();
+            Ok(path)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl Locator for SymbolDir {
+    fn locate(&self, meta: &UserMeta) -> Result<Option<PathBuf>> {
+        match meta {
+            UserMeta::Elf(elf) => self.locate_elf(&elf.path, elf.build_id.as_deref()),
+            UserMeta::Apk(apk) => {
+                let mirrored = self.mirrored_path(&apk.path);
+                Ok(mirrored.is_file().then_some(mirrored))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Debug for SymbolDir {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let Self {
+            dir,
+            by_build_id: _,
+        } = self;
+
+        f.debug_tuple(stringify!(SymbolDir)).field(dir).finish()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::copy;
+    use std::fs::create_dir_all;
+
+    use tempfile::tempdir;
+
+    use crate::normalize::Apk;
+    use crate::normalize::Elf;
+    use crate::normalize::Unknown;
+
+
+    fn elf_meta(path: &str, build_id: Option<&[u8]>) -> UserMeta {
+        UserMeta::Elf(Elf {
+            path: PathBuf::from(path),
+            build_id: build_id.map(<[u8]>::to_vec),
+            _non_exhaustive: (),
+        })
+    }
+
+    /// Exercise the `Debug` representation of various types.
+    #[test]
+    fn debug_repr() {
+        let dir = SymbolDir::new("/tmp/symbols");
+        assert_eq!(format!("{dir:?}"), r#"SymbolDir("/tmp/symbols")"#);
+    }
+
+    /// Check that we can locate binaries by path and by build ID.
+    #[test]
+    fn elf_locating() {
+        let so = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("libtest-so.so");
+        let build_id = read_elf_build_id(&so).unwrap().unwrap();
+
+        let tmpdir = tempdir().unwrap();
+        let lib_dir = tmpdir.path().join("system").join("lib64");
+        let () = create_dir_all(&lib_dir).unwrap();
+        let mirrored = lib_dir.join("libtest.so");
+        let _cnt = copy(&so, &mirrored).unwrap();
+        let other_dir = tmpdir.path().join("out").join("obj");
+        let () = create_dir_all(&other_dir).unwrap();
+        let () = std::fs::write(other_dir.join("not-an-elf.txt"), b"foobar").unwrap();
+        let other = other_dir.join("libtest-unstripped.so");
+        let _cnt = copy(&so, &other).unwrap();
+
+        let dir = SymbolDir::new(tmpdir.path());
+
+        // Lookup by path only.
+        let meta = elf_meta("/system/lib64/libtest.so", None);
+        assert_eq!(dir.locate(&meta).unwrap(), Some(mirrored.clone()));
+
+        // Lookup by path with matching build ID.
+        let meta = elf_meta("/system/lib64/libtest.so", Some(&build_id));
+        assert_eq!(dir.locate(&meta).unwrap(), Some(mirrored));
+
+        // Lookup of a binary not present by path, but by build ID.
+        let meta = elf_meta("/vendor/lib64/libfoo.so", Some(&build_id));
+        let path = dir.locate(&meta).unwrap().unwrap();
+        assert!(path.ends_with("libtest.so") || path == other, "{path:?}");
+
+        // A file with mismatching build ID is not reported.
+        let meta = elf_meta("/system/lib64/libtest.so", Some(b"\x01\x02\x03\x04"));
+        assert_eq!(dir.locate(&meta).unwrap(), None);
+
+        let meta = elf_meta("/vendor/lib64/libfoo.so", None);
+        assert_eq!(dir.locate(&meta).unwrap(), None);
+
+        let meta = UserMeta::Apk(Apk {
+            path: PathBuf::from("/data/app/base.apk"),
+            elf_path: None,
+            _non_exhaustive: (),
+        });
+        assert_eq!(dir.locate(&meta).unwrap(), None);
+
+        let meta = UserMeta::Unknown(Unknown::default());
+        assert_eq!(dir.locate(&meta).unwrap(), None);
+    }
+}
@@ -0,0 +1,863 @@
+//! Unwinding of user space stacks using DWARF call frame information.
+//!
+//! Given a snapshot of a thread's registers and a way of reading its
+//! memory, an [`Unwinder`] walks the thread's stack with the help of
+//! the call frame information (CFI) contained in the `.eh_frame` and
+//! `.debug_frame` sections of the binaries mapped into the process,
+//! falling back to frame pointers where no such information is
+//! available. The result is a list of addresses that can be
+//! symbolized or normalized directly.
+//!
+//! ```no_run
+//! use blazesym::symbolize::Input;
+//! use blazesym::symbolize::Process;
+//! use blazesym::symbolize::Source;
+//! use blazesym::symbolize::Symbolizer;
+//! use blazesym::unwind::ProcessMemory;
+//! use blazesym::unwind::Registers;
+//! use blazesym::unwind::Unwinder;
+//! use blazesym::Pid;
+//!
+//! # let (ip, sp) = (0, 0);
+//! let pid = Pid::from(1234);
+//! let src = Source::Process(Process::new(pid));
+//! // The registers of a stopped thread, e.g., as retrieved using
+//! // `ptrace(2)`.
+//! let regs = Registers::new(ip, sp);
+//! let unwinder = Unwinder::new();
+//! let addrs = unwinder.unwind(&src, &regs, &ProcessMemory::new(pid)).unwrap();
+//!
+//! let symbolizer = Symbolizer::new();
+//! let syms = symbolizer.symbolize(&src, Input::AbsAddr(&addrs)).unwrap();
+//! ```
+
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs::File;
+#[cfg(target_os = "linux")]
+use std::io;
+use std::mem;
+use std::mem::size_of;
+use std::ops::Deref as _;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use gimli::BaseAddresses;
+use gimli::CfaRule;
+use gimli::DebugFrame;
+use gimli::EhFrame;
+use gimli::EhFrameHdr;
+use gimli::EndianSlice;
+use gimli::NativeEndian;
+use gimli::ParsedEhFrameHdr;
+use gimli::Register;
+use gimli::RegisterRule;
+use gimli::UnwindContext;
+use gimli::UnwindSection as _;
+use gimli::UnwindTableRow;
+
+use crate::coredump;
+use crate::elf::types::EM_AARCH64;
+use crate::elf::types::EM_X86_64;
+use crate::elf::ElfParser;
+use crate::insert_map::InsertMap;
+use crate::log::debug;
+use crate::maps;
+use crate::maps::MapsEntry;
+use crate::maps::PathName;
+use crate::mmap::Mmap;
+use crate::symbolize::elf_offset_to_address;
+use crate::symbolize::Coredump;
+use crate::symbolize::Process;
+use crate::symbolize::Snapshot;
+use crate::symbolize::Source;
+use crate::vdso;
+use crate::vfs::StdFileAccess;
+use crate::Addr;
+use crate::Error;
+use crate::ErrorExt as _;
+use crate::Pid;
+use crate::Result;
+
+
+/// The gimli reader type we use for call frame information.
+type R<'dat> = EndianSlice<'dat, NativeEndian>;
+
+/// The maximum number of frames we unwind by default.
+const DEFAULT_MAX_FRAMES: usize = 256;
+
+
+/// The register state of a thread, as required for unwinding its stack.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Registers {
+    /// The instruction pointer.
+    pub ip: u64,
+    /// The stack pointer.
+    pub sp: u64,
+    /// The frame pointer (`rbp` on x86-64, `x29` on AArch64), if
+    /// known.
+    pub fp: Option<u64>,
+    /// The link register (`x30` on AArch64), if known. Unused on
+    /// architectures without a link register.
+    pub lr: Option<u64>,
+    /// The struct is non-exhaustive and open to extension.
+    #[doc(hidden)]
+    pub _non_exhaustive: (),
+}
+
+impl Registers {
+    /// Create a new [`Registers`] object using the provided instruction
+    /// and stack pointers.
+    #[inline]
+    pub fn new(ip: u64, sp: u64) -> Self {
+        Self {
+            ip,
+            sp,
+            fp: None,
+            lr: None,
+            _non_exhaustive: (),
+        }
+    }
+}
+
+
+/// A trait providing access to the memory of the thread whose stack is
+/// being unwound.
+///
+/// The trait is implemented for closures of the form
+/// `Fn(Addr, &mut [u8]) -> Result<()>`, allowing for reading memory
+/// from arbitrary sources, such as a core file or a copy of the stack
+/// captured earlier.
+pub trait Memory {
+    /// Fill `buf` with the memory contents starting at address `addr`.
+    fn read(&self, addr: Addr, buf: &mut [u8]) -> Result<()>;
+}
+
+impl<F> Memory for F
+where
+    F: Fn(Addr, &mut [u8]) -> Result<()>,
+{
+    #[inline]
+    fn read(&self, addr: Addr, buf: &mut [u8]) -> Result<()> {
+        self(addr, buf)
+    }
+}
+
+
+/// A [`Memory`] implementation reading the memory of a live process
+/// using `process_vm_readv(2)`.
+#[cfg(target_os = "linux")]
+#[derive(Clone, Debug)]
+pub struct ProcessMemory {
+    /// The process whose memory to read.
+    pid: Pid,
+}
+
+#[cfg(target_os = "linux")]
+impl ProcessMemory {
+    /// Create a new [`ProcessMemory`] object for the process `pid`.
+    #[inline]
+    pub fn new(pid: Pid) -> Self {
+        Self { pid }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Memory for ProcessMemory {
+    fn read(&self, addr: Addr, buf: &mut [u8]) -> Result<()> {
+        let pid = match self.pid {
+            Pid::Slf => unsafe { libc::getpid() },
+            Pid::Pid(pid) => pid.get() as libc::pid_t,
+        };
+        let local = libc::iovec {
+            iov_base: buf.as_mut_ptr().cast(),
+            iov_len: buf.len(),
+        };
+        let remote = libc::iovec {
+            iov_base: addr as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        // SAFETY: `local` describes the writable buffer `buf`; the
+        //         remote memory is accessed by the kernel on our
+        //         behalf and validated by it.
+        let rc = unsafe { libc::process_vm_readv(pid, &local, 1, &remote, 1, 0) };
+        if rc < 0 {
+            return Err(io::Error::last_os_error()).with_context(|| {
+                format!("failed to read memory of process {} at {addr:#x}", self.pid)
+            })
+        }
+        if rc as usize != buf.len() {
+            return Err(Error::from(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "read only {rc} of {} bytes of process {} memory at {addr:#x}",
+                    buf.len(),
+                    self.pid
+                ),
+            )))
+        }
+        Ok(())
+    }
+}
+
+
+/// A [`Memory`] implementation reading from a copy of a thread's
+/// stack, e.g., as captured along with a `perf` sample.
+///
+/// Reads of memory not contained in the copy fail.
+#[derive(Clone, Debug)]
+pub struct StackMemory<'dat> {
+    /// The address at which the copy starts.
+    addr: Addr,
+    /// The copied stack contents.
+    data: &'dat [u8],
+}
+
+impl<'dat> StackMemory<'dat> {
+    /// Create a new [`StackMemory`] object for the stack contents
+    /// `data`, copied starting at address `addr` (typically the stack
+    /// pointer).
+    #[inline]
+    pub fn new(addr: Addr, data: &'dat [u8]) -> Self {
+        Self { addr, data }
+    }
+}
+
+impl Memory for StackMemory<'_> {
+    fn read(&self, addr: Addr, buf: &mut [u8]) -> Result<()> {
+        let data = addr
+            .checked_sub(self.addr)
+            .and_then(|offset| usize::try_from(offset).ok())
+            .and_then(|offset| self.data.get(offset..)?.get(..buf.len()))
+            .ok_or_else(|| {
+                Error::with_not_found(format!(
+                    "{} bytes of stack memory at {addr:#x} were not captured",
+                    buf.len()
+                ))
+            })?;
+        let () = buf.copy_from_slice(data);
+        Ok(())
+    }
+}
+
+
+/// Read a pointer sized value from `memory` at `addr`.
+fn read_u64(memory: &dyn Memory, addr: Addr) -> Result<u64> {
+    let mut buf = [0; size_of::<u64>()];
+    let () = memory.read(addr, &mut buf)?;
+    Ok(u64::from_ne_bytes(buf))
+}
+
+
+/// The architecture specific DWARF register numbers we work with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Arch {
+    /// The stack pointer register.
+    sp: Register,
+    /// The frame pointer register.
+    fp: Register,
+    /// The link register, if any.
+    lr: Option<Register>,
+}
+
+impl Arch {
+    const X86_64: Self = Self {
+        sp: Register(7),
+        fp: Register(6),
+        lr: None,
+    };
+    const AARCH64: Self = Self {
+        sp: Register(31),
+        fp: Register(29),
+        lr: Some(Register(30)),
+    };
+
+    /// Determine the architecture from an ELF `e_machine` value.
+    fn from_machine(machine: u16) -> Option<Self> {
+        match machine {
+            EM_X86_64 => Some(Self::X86_64),
+            EM_AARCH64 => Some(Self::AARCH64),
+            _ => None,
+        }
+    }
+
+    /// The architecture we are running on, if supported.
+    fn host() -> Option<Self> {
+        if cfg!(target_arch = "x86_64") {
+            Some(Self::X86_64)
+        } else if cfg!(target_arch = "aarch64") {
+            Some(Self::AARCH64)
+        } else {
+            None
+        }
+    }
+
+    /// Retrieve the value of register `reg` from `regs`, if known.
+    fn get(&self, regs: &Registers, reg: Register) -> Option<u64> {
+        if reg == self.sp {
+            Some(regs.sp)
+        } else if reg == self.fp {
+            regs.fp
+        } else if Some(reg) == self.lr {
+            regs.lr
+        } else {
+            None
+        }
+    }
+}
+
+
+/// The `.eh_frame` section of a binary, along with the parsed
+/// `.eh_frame_hdr` lookup table, if any.
+#[derive(Debug)]
+struct EhFrameInfo {
+    /// The `.eh_frame` section.
+    eh_frame: EhFrame<R<'static>>,
+    /// The parsed `.eh_frame_hdr` section, if present.
+    hdr: Option<ParsedEhFrameHdr<R<'static>>>,
+    /// The base addresses to use for looking up entries.
+    bases: BaseAddresses,
+}
+
+
+/// The call frame information of a binary.
+#[derive(Debug)]
+struct Cfi {
+    /// The `.eh_frame` section and lookup table, if present.
+    // SAFETY: This member has to be listed before `parser`, which it
+    //         references.
+    eh_frame: Option<EhFrameInfo>,
+    /// The `.debug_frame` section, if present.
+    // SAFETY: This member has to be listed before `parser`, which it
+    //         references.
+    debug_frame: Option<DebugFrame<R<'static>>>,
+    /// The address of the `.text` section, if present.
+    text: u64,
+    /// The architecture the binary was built for, if supported.
+    arch: Option<Arch>,
+    /// The parser of the binary.
+    parser: Arc<ElfParser>,
+}
+
+impl Cfi {
+    fn new(parser: ElfParser) -> Result<Self> {
+        let parser = Arc::new(parser);
+        // SAFETY: We own the `ElfParser` and make sure that it stays
+        //         around while the parsed sections reference its data.
+        //         As such, it is fine to conjure a 'static lifetime
+        //         here.
+        let static_parser =
+            unsafe { mem::transmute::<&ElfParser, &'static ElfParser>(parser.deref()) };
+        let shdrs = static_parser.section_headers()?;
+        let section = |name| -> Result<Option<(&'static [u8], u64)>> {
+            let idx = match static_parser.find_section(name)? {
+                Some(idx) => idx,
+                None => return Ok(None),
+            };
+            let addr = shdrs.get(idx).map(|shdr| shdr.sh_addr).unwrap_or(0);
+            Ok(Some((static_parser.section_data(idx)?, addr)))
+        };
+        let text = section(".text")?.map(|(_, addr)| addr).unwrap_or(0);
+
+        let eh_frame = match section(".eh_frame")? {
+            Some((data, addr)) => {
+                let eh_frame = EhFrame::new(data, NativeEndian);
+                let mut bases = BaseAddresses::default().set_eh_frame(addr).set_text(text);
+                let hdr = match section(".eh_frame_hdr")? {
+                    Some((hdr_data, hdr_addr)) => {
+                        bases = bases.set_eh_frame_hdr(hdr_addr);
+                        let hdr = EhFrameHdr::new(hdr_data, NativeEndian)
+                            .parse(&bases, size_of::<u64>() as u8)?;
+                        Some(hdr)
+                    }
+                    None => None,
+                };
+                Some(EhFrameInfo {
+                    eh_frame,
+                    hdr,
+                    bases,
+                })
+            }
+            None => None,
+        };
+
+        let debug_frame = section(".debug_frame")?.map(|(data, _)| {
+            let mut debug_frame = DebugFrame::new(data, NativeEndian);
+            let () = debug_frame.set_address_size(size_of::<u64>() as u8);
+            debug_frame
+        });
+        let arch = Arch::from_machine(parser.machine()?);
+
+        Ok(Self {
+            eh_frame,
+            debug_frame,
+            text,
+            arch,
+            parser,
+        })
+    }
+
+    /// Find the unwind table row for the (unbiased) address `addr`,
+    /// and report it to `f`.
+    fn with_row<F, T>(&self, addr: u64, f: F) -> Result<Option<T>>
+    where
+        F: FnOnce(&UnwindTableRow<R<'_>>, Register) -> T,
+    {
+        let mut ctx = UnwindContext::new();
+
+        if let Some(EhFrameInfo {
+            eh_frame,
+            hdr,
+            bases,
+        }) = &self.eh_frame
+        {
+            let hdr_fde = hdr.as_ref().and_then(|hdr| hdr.table()).map(|table| {
+                table.fde_for_address(eh_frame, bases, addr, EhFrame::cie_from_offset)
+            });
+            let fde = match hdr_fde {
+                Some(fde) => fde,
+                None => eh_frame.fde_for_address(bases, addr, EhFrame::cie_from_offset),
+            };
+            match fde {
+                Ok(fde) => {
+                    let row = fde.unwind_info_for_address(eh_frame, bases, &mut ctx, addr)?;
+                    return Ok(Some(f(row, fde.cie().return_address_register())))
+                }
+                Err(gimli::Error::NoUnwindInfoForAddress) => (),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        if let Some(debug_frame) = &self.debug_frame {
+            let bases = BaseAddresses::default().set_text(self.text);
+            match debug_frame.fde_for_address(&bases, addr, DebugFrame::cie_from_offset) {
+                Ok(fde) => {
+                    let row = fde.unwind_info_for_address(debug_frame, &bases, &mut ctx, addr)?;
+                    return Ok(Some(f(row, fde.cie().return_address_register())))
+                }
+                Err(gimli::Error::NoUnwindInfoForAddress) => (),
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(None)
+    }
+}
+
+
+/// The outcome of unwinding a single frame.
+enum Step {
+    /// The caller's registers.
+    Caller(Registers),
+    /// The end of the stack was reached.
+    End,
+}
+
+
+/// An unwinder for user space stacks.
+///
+/// The call frame information of binaries is cached across
+/// [`unwind`][Unwinder::unwind] invocations.
+pub struct Unwinder {
+    /// The maximum number of frames to unwind.
+    max_frames: usize,
+    /// Call frame information of binaries, keyed by path.
+    cache: InsertMap<PathBuf, Option<Arc<Cfi>>>,
+}
+
+impl Unwinder {
+    /// Create a new [`Unwinder`], unwinding at most 256 frames.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_max_frames(DEFAULT_MAX_FRAMES)
+    }
+
+    /// Create a new [`Unwinder`], unwinding at most `max_frames`
+    /// frames.
+    pub fn with_max_frames(max_frames: usize) -> Self {
+        Self {
+            max_frames,
+            cache: InsertMap::new(),
+        }
+    }
+
+    /// Open the binary at `path` and retrieve its call frame
+    /// information.
+    fn load_cfi(&self, path: &Path) -> Option<Arc<Cfi>> {
+        let result = self.cache.get_or_insert(path.to_path_buf(), || {
+            let result = File::open(path)
+                .map_err(Error::from)
+                .and_then(|file| Mmap::map(&file))
+                .map(ElfParser::from_mmap)
+                .and_then(Cfi::new)
+                .with_context(|| {
+                    format!(
+                        "failed to load call frame information of {}",
+                        path.display()
+                    )
+                });
+            match result {
+                Ok(cfi) => Some(Arc::new(cfi)),
+                Err(err) => {
+                    debug!("{err:#}");
+                    None
+                }
+            }
+        });
+        result.as_ref().clone()
+    }
+
+    /// Retrieve the call frame information for the mapping `entry`.
+    fn entry_cfi(&self, entry: &MapsEntry, pid: Option<Pid>) -> Option<Arc<Cfi>> {
+        match (&entry.path_name, pid) {
+            (Some(PathName::Path(path)), _) => self
+                .load_cfi(&path.maps_file)
+                .or_else(|| self.load_cfi(&path.symbolic_path)),
+            // The vDSO image may differ between processes, so we don't
+            // cache its call frame information.
+            (Some(PathName::Component(..)), Some(pid)) if vdso::is_vdso(entry) => {
+                let result = vdso::read_image(pid, &entry.range)
+                    .map(|image| ElfParser::from_mmap(Mmap::from_vec(image)))
+                    .and_then(Cfi::new);
+                match result {
+                    Ok(cfi) => Some(Arc::new(cfi)),
+                    Err(err) => {
+                        debug!("failed to load call frame information of vDSO: {err:#}");
+                        None
+                    }
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Unwind a single frame with the help of the call frame
+    /// information `cfi`, with `addr` being the unbiased address to
+    /// look up.
+    fn step_cfi(
+        &self,
+        cfi: &Cfi,
+        arch: &Arch,
+        addr: u64,
+        regs: &Registers,
+        first: bool,
+        memory: &dyn Memory,
+    ) -> Result<Option<Step>> {
+        let result = cfi.with_row(addr, |row, ra_reg| {
+            let cfa = match row.cfa() {
+                CfaRule::RegisterAndOffset { register, offset } => {
+                    match arch.get(regs, *register) {
+                        Some(value) => value.wrapping_add(*offset as u64),
+                        None => return Ok(None),
+                    }
+                }
+                CfaRule::Expression(..) => return Ok(None),
+            };
+
+            let restore = |reg: Register| -> Result<Option<u64>> {
+                let value = match row.register(reg) {
+                    // Registers without a rule are callee-saved ones
+                    // left untouched by the function.
+                    RegisterRule::Undefined | RegisterRule::SameValue => arch.get(regs, reg),
+                    RegisterRule::Offset(offset) => {
+                        Some(read_u64(memory, cfa.wrapping_add(offset as u64))?)
+                    }
+                    RegisterRule::ValOffset(offset) => Some(cfa.wrapping_add(offset as u64)),
+                    RegisterRule::Register(other) => arch.get(regs, other),
+                    RegisterRule::Constant(value) => Some(value),
+                    // DWARF expressions and architecture specific
+                    // rules are not supported.
+                    _ => None,
+                };
+                Ok(value)
+            };
+
+            let ra = match row.register(ra_reg) {
+                // The return address is only ever implicitly defined
+                // by the link register of the innermost frame. In any
+                // other case an undefined return address marks the
+                // outermost frame.
+                RegisterRule::Undefined if !(first && Some(ra_reg) == arch.lr) => {
+                    return Ok(Some(Step::End))
+                }
+                _ => match restore(ra_reg)? {
+                    Some(ra) => ra,
+                    None => return Ok(None),
+                },
+            };
+
+            let caller = Registers {
+                ip: ra,
+                sp: cfa,
+                fp: restore(arch.fp)?,
+                lr: match arch.lr {
+                    Some(lr) => restore(lr)?,
+                    None => None,
+                },
+                _non_exhaustive: (),
+            };
+            Result::<_, Error>::Ok(Some(Step::Caller(caller)))
+        })?;
+        result.transpose().map(Option::flatten)
+    }
+
+    /// Unwind a single frame by following the frame pointer.
+    fn step_fp(&self, regs: &Registers, memory: &dyn Memory) -> Result<Step> {
+        let fp = match regs.fp {
+            Some(fp) if fp != 0 => fp,
+            _ => return Ok(Step::End),
+        };
+        // Both on x86-64 and AArch64 the frame pointer points to the
+        // saved frame pointer of the caller, followed by the return
+        // address.
+        let caller = Registers {
+            ip: read_u64(memory, fp.wrapping_add(8))?,
+            sp: fp.wrapping_add(16),
+            fp: Some(read_u64(memory, fp)?),
+            lr: None,
+            _non_exhaustive: (),
+        };
+        Ok(Step::Caller(caller))
+    }
+
+    /// Unwind the stack described by `regs`, belonging to a thread of
+    /// the process that `src` refers to.
+    ///
+    /// Supported sources are [`Source::Process`], [`Source::Snapshot`],
+    /// and [`Source::Coredump`], which provide the memory mappings of
+    /// the process. Memory is accessed through `memory`.
+    ///
+    /// The first reported address is the instruction pointer contained
+    /// in `regs`, followed by the return addresses of the callers,
+    /// innermost first. The result can be symbolized or normalized
+    /// using absolute address input. Unwinding stops once the end of
+    /// the stack is reached or no further frame can be recovered.
+    pub fn unwind(
+        &self,
+        src: &Source<'_>,
+        regs: &Registers,
+        memory: &dyn Memory,
+    ) -> Result<Vec<Addr>> {
+        let (entries, pid) = match src {
+            Source::Process(Process { pid, .. }) => {
+                let entries =
+                    maps::parse(&StdFileAccess::default(), *pid)?.collect::<Result<Vec<_>>>()?;
+                (entries, Some(*pid))
+            }
+            Source::Snapshot(Snapshot { maps, .. }) => {
+                let entries = maps::parse_snapshot(maps.as_bytes()).collect::<Result<Vec<_>>>()?;
+                (entries, None)
+            }
+            Source::Coredump(Coredump { path, .. }) => {
+                let file = File::open(path)
+                    .with_context(|| format!("failed to open core file {}", path.display()))?;
+                let mmap = Mmap::map(&file)
+                    .with_context(|| format!("failed to map core file {}", path.display()))?;
+                let entries = coredump::parse_maps(&mmap)
+                    .with_context(|| format!("failed to parse core file {}", path.display()))?;
+                (entries, None)
+            }
+            _ => {
+                return Err(Error::with_unsupported(format!(
+                    "unwinding is not supported for source {src:?}"
+                )))
+            }
+        };
+
+        let mut arch = Arch::host();
+        let mut regs = regs.clone();
+        let mut addrs = Vec::new();
+
+        while addrs.len() < self.max_frames && regs.ip != 0 {
+            let () = addrs.push(regs.ip);
+            let first = addrs.len() == 1;
+            // Return addresses point after the call instruction, which
+            // may already belong to a different function or unwind
+            // table row.
+            let lookup = if first { regs.ip } else { regs.ip - 1 };
+
+            let entry = entries.iter().find(|entry| entry.range.contains(&lookup));
+            let cfi = entry.and_then(|entry| self.entry_cfi(entry, pid));
+            if let Some(cfi_arch) = cfi.as_ref().and_then(|cfi| cfi.arch) {
+                arch = Some(cfi_arch);
+            }
+
+            let step = match (&cfi, entry, &arch) {
+                (Some(cfi), Some(entry), Some(arch)) => {
+                    let offset = lookup - entry.range.start + entry.offset;
+                    match elf_offset_to_address(offset, &cfi.parser)? {
+                        Some(addr) => self.step_cfi(cfi, arch, addr, &regs, first, memory)?,
+                        None => None,
+                    }
+                }
+                _ => None,
+            };
+            let step = match step {
+                Some(step) => step,
+                None => match self.step_fp(&regs, memory) {
+                    Ok(step) => step,
+                    Err(err) => {
+                        debug!("failed to unwind frame at {:#x}: {err:#}", regs.ip);
+                        break
+                    }
+                },
+            };
+
+            match step {
+                // The stack grows downwards, so callers' frames have to
+                // reside at higher addresses. Anything else indicates
+                // a corrupted stack or bogus unwind information.
+                Step::Caller(caller) if caller.sp > regs.sp => regs = caller,
+                Step::Caller(..) | Step::End => break,
+            }
+        }
+        Ok(addrs)
+    }
+}
+
+impl Default for Unwinder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for Unwinder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let Self {
+            max_frames,
+            cache: _,
+        } = self;
+
+        f.debug_struct(stringify!(Unwinder))
+            .field("max_frames", max_frames)
+            .finish()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use crate::symbolize::Input;
+    use crate::symbolize::Symbolizer;
+
+
+    /// Check that we can unwind a stack made up of frame records, in the
+    /// absence of any call frame information.
+    #[test]
+    fn frame_pointer_unwinding() {
+        let stack = HashMap::from([
+            (0x10000, 0x10020),
+            (0x10008, 0x2000),
+            (0x10020, 0x10040),
+            (0x10028, 0x3000),
+            (0x10040, 0),
+            (0x10048, 0),
+        ]);
+        let memory = |addr: Addr, buf: &mut [u8]| {
+            let value = stack
+                .get(&addr)
+                .ok_or_else(|| Error::with_not_found(format!("no memory at {addr:#x}")))?;
+            let () = buf.copy_from_slice(&u64::to_ne_bytes(*value));
+            Ok(())
+        };
+
+        let regs = Registers {
+            fp: Some(0x10000),
+            ..Registers::new(0x1000, 0xfff0)
+        };
+        let src = Source::Snapshot(Snapshot::new(""));
+        let unwinder = Unwinder::new();
+        let addrs = unwinder.unwind(&src, &regs, &memory).unwrap();
+        assert_eq!(addrs, vec![0x1000, 0x2000, 0x3000]);
+
+        let unwinder = Unwinder::with_max_frames(2);
+        let addrs = unwinder.unwind(&src, &regs, &memory).unwrap();
+        assert_eq!(addrs, vec![0x1000, 0x2000]);
+    }
+
+    /// Check that we can read from a copy of a stack.
+    #[test]
+    fn stack_memory_reading() {
+        let data = (0..16).collect::<Vec<u8>>();
+        let memory = StackMemory::new(0x1000, &data);
+
+        let mut buf = [0; 4];
+        let () = memory.read(0x1000, &mut buf).unwrap();
+        assert_eq!(buf, [0, 1, 2, 3]);
+        let () = memory.read(0x100c, &mut buf).unwrap();
+        assert_eq!(buf, [12, 13, 14, 15]);
+
+        for addr in [0xfff, 0x100d, 0x1010, u64::MAX] {
+            let err = memory.read(addr, &mut buf).unwrap_err();
+            assert_eq!(err.kind(), crate::ErrorKind::NotFound, "{addr:#x}");
+        }
+    }
+
+    /// Capture the current register state and unwind our own stack.
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    #[inline(never)]
+    fn unwind_here(unwinder: &Unwinder) -> Vec<Addr> {
+        let (ip, sp, fp): (u64, u64, u64);
+        // SAFETY: The assembly only reads registers.
+        unsafe {
+            std::arch::asm!(
+                "lea {ip}, [rip]",
+                "mov {sp}, rsp",
+                "mov {fp}, rbp",
+                ip = out(reg) ip,
+                sp = out(reg) sp,
+                fp = out(reg) fp,
+                options(nomem, nostack, preserves_flags),
+            )
+        };
+        let regs = Registers {
+            fp: Some(fp),
+            ..Registers::new(ip, sp)
+        };
+        let src = Source::Process(Process::new(Pid::Slf));
+        unwinder
+            .unwind(&src, &regs, &ProcessMemory::new(Pid::Slf))
+            .unwrap()
+    }
+
+    /// Check that we can unwind our own stack using call frame
+    /// information.
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    #[test]
+    fn self_unwinding() {
+        let unwinder = Unwinder::new();
+        let addrs = unwind_here(&unwinder);
+        assert!(addrs.len() > 2, "{addrs:#x?}");
+
+        let src = Source::Process(Process::new(Pid::Slf));
+        let symbolizer = Symbolizer::new();
+        let syms = symbolizer
+            .symbolize(&src, Input::AbsAddr(&addrs[..2]))
+            .unwrap();
+        let names = syms
+            .iter()
+            .map(|sym| sym.as_sym().map(|sym| sym.name.to_string()))
+            .collect::<Vec<_>>();
+        assert!(
+            names[0].as_deref().unwrap_or("").ends_with("unwind_here"),
+            "{names:?}"
+        );
+        assert!(
+            names[1]
+                .as_deref()
+                .unwrap_or("")
+                .ends_with("self_unwinding"),
+            "{names:?}"
+        );
+
+        // Unwinding again should make use of cached information and
+        // yield the same result.
+        let addrs2 = unwind_here(&unwinder);
+        assert_eq!(addrs2.len(), addrs.len());
+    }
+}
@@ -0,0 +1,535 @@
+//! Bulk reading of files using `io_uring`.
+//!
+//! Reading many (large) files sequentially, e.g., when loading debug
+//! information for all modules of a process, leaves most of the
+//! available I/O bandwidth of modern storage devices unused. The
+//! functionality provided here submits reads for many files at once,
+//! letting the kernel process them concurrently.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io;
+use std::mem;
+use std::os::unix::io::AsRawFd as _;
+use std::os::unix::io::FromRawFd as _;
+use std::os::unix::io::OwnedFd;
+use std::os::unix::io::RawFd;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::fd_pool::FdPool;
+use crate::log;
+use crate::util::fstat;
+use crate::vfs::FileData;
+use crate::Error;
+use crate::ErrorExt as _;
+use crate::Result;
+
+
+/// The number of submission queue entries to request.
+const QUEUE_DEPTH: u32 = 64;
+/// The maximum number of bytes to read with a single request.
+const MAX_READ_LEN: usize = 1 << 30;
+
+const IORING_OFF_SQ_RING: libc::off_t = 0;
+const IORING_OFF_CQ_RING: libc::off_t = 0x8000000;
+const IORING_OFF_SQES: libc::off_t = 0x10000000;
+const IORING_ENTER_GETEVENTS: u32 = 1 << 0;
+const IORING_OP_READ: u8 = 22;
+
+
+#[repr(C)]
+#[derive(Debug, Default)]
+struct SqringOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: u32,
+    dropped: u32,
+    array: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Default)]
+struct CqringOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: u32,
+    cqes: u32,
+    flags: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Default)]
+struct Params {
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: SqringOffsets,
+    cq_off: CqringOffsets,
+}
+
+/// A submission queue entry, as understood by the kernel.
+#[repr(C)]
+#[derive(Debug, Default)]
+struct Sqe {
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    off: u64,
+    addr: u64,
+    len: u32,
+    rw_flags: u32,
+    user_data: u64,
+    buf_index: u16,
+    personality: u16,
+    splice_fd_in: i32,
+    addr3: u64,
+    _pad: u64,
+}
+
+/// A completion queue entry, as understood by the kernel.
+#[repr(C)]
+#[derive(Debug)]
+struct Cqe {
+    user_data: u64,
+    res: i32,
+    flags: u32,
+}
+
+
+/// A memory mapped region shared with the kernel.
+#[derive(Debug)]
+struct RingMap {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl RingMap {
+    fn map(fd: RawFd, len: usize, offset: libc::off_t) -> io::Result<Self> {
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_POPULATE,
+                fd,
+                offset,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error())
+        }
+        Ok(Self {
+            ptr: ptr.cast(),
+            len,
+        })
+    }
+
+    /// # Safety
+    /// `offset` has to be a kernel provided offset of a `u32` value
+    /// within the mapping.
+    unsafe fn atomic(&self, offset: u32) -> &AtomicU32 {
+        unsafe { &*self.ptr.add(offset as usize).cast::<AtomicU32>() }
+    }
+
+    /// # Safety
+    /// `offset` has to be a kernel provided offset of a `u32` value
+    /// within the mapping.
+    unsafe fn read(&self, offset: u32) -> u32 {
+        unsafe { self.ptr.add(offset as usize).cast::<u32>().read() }
+    }
+}
+
+impl Drop for RingMap {
+    fn drop(&mut self) {
+        let _rc = unsafe { libc::munmap(self.ptr.cast(), self.len) };
+    }
+}
+
+
+/// A minimal `io_uring` instance, capable of performing reads.
+#[derive(Debug)]
+struct Ring {
+    /// The submission queue ring.
+    sq: RingMap,
+    /// The completion queue ring.
+    cq: RingMap,
+    /// The array of submission queue entries.
+    sqes: RingMap,
+    params: Params,
+    /// The file descriptor representing the ring.
+    fd: OwnedFd,
+}
+
+impl Ring {
+    fn new(entries: u32) -> io::Result<Self> {
+        let mut params = Params::default();
+        let rc = unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_setup,
+                entries,
+                &mut params as *mut Params,
+            )
+        };
+        if rc < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        // SAFETY: The kernel returned a valid file descriptor that we
+        //         now own.
+        let fd = unsafe { OwnedFd::from_raw_fd(rc as RawFd) };
+
+        let sq_len = params.sq_off.array as usize + params.sq_entries as usize * 4;
+        let cq_len =
+            params.cq_off.cqes as usize + params.cq_entries as usize * mem::size_of::<Cqe>();
+        let sqes_len = params.sq_entries as usize * mem::size_of::<Sqe>();
+
+        let slf = Self {
+            sq: RingMap::map(fd.as_raw_fd(), sq_len, IORING_OFF_SQ_RING)?,
+            cq: RingMap::map(fd.as_raw_fd(), cq_len, IORING_OFF_CQ_RING)?,
+            sqes: RingMap::map(fd.as_raw_fd(), sqes_len, IORING_OFF_SQES)?,
+            params,
+            fd,
+        };
+        Ok(slf)
+    }
+
+    /// Queue a read of `buf.len()` bytes at `offset` of `fd` into
+    /// `buf`.
+    ///
+    /// # Safety
+    /// `buf` has to stay valid until the corresponding completion has
+    /// been reaped. The number of queued (but not yet completed) reads
+    /// must not exceed the submission queue's size.
+    unsafe fn push_read(&mut self, fd: RawFd, buf: &mut [u8], offset: u64, user_data: u64) {
+        let off = &self.params.sq_off;
+        // SAFETY: All offsets are provided by the kernel.
+        let tail = unsafe { self.sq.atomic(off.tail) };
+        let mask = unsafe { self.sq.read(off.ring_mask) };
+        let idx = tail.load(Ordering::Relaxed) & mask;
+
+        let sqe = Sqe {
+            opcode: IORING_OP_READ,
+            fd,
+            off: offset,
+            addr: buf.as_mut_ptr() as u64,
+            len: buf.len() as u32,
+            user_data,
+            ..Default::default()
+        };
+        unsafe {
+            let () = self.sqes.ptr.cast::<Sqe>().add(idx as usize).write(sqe);
+            let () = self
+                .sq
+                .ptr
+                .add(off.array as usize)
+                .cast::<u32>()
+                .add(idx as usize)
+                .write(idx);
+        }
+        let () = tail.store(
+            tail.load(Ordering::Relaxed).wrapping_add(1),
+            Ordering::Release,
+        );
+    }
+
+    /// Submit `count` queued requests and wait for at least one
+    /// completion.
+    fn submit_and_wait(&self, count: u32) -> io::Result<()> {
+        loop {
+            let rc = unsafe {
+                libc::syscall(
+                    libc::SYS_io_uring_enter,
+                    self.fd.as_raw_fd(),
+                    count,
+                    1u32,
+                    IORING_ENTER_GETEVENTS,
+                    ptr::null::<libc::c_void>(),
+                    0usize,
+                )
+            };
+            if rc >= 0 {
+                break Ok(())
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                break Err(err)
+            }
+        }
+    }
+
+    /// Reap all available completions, reporting the user data and
+    /// result of each to `f`.
+    fn reap<F>(&self, mut f: F)
+    where
+        F: FnMut(u64, i32),
+    {
+        let off = &self.params.cq_off;
+        // SAFETY: All offsets are provided by the kernel.
+        let head = unsafe { self.cq.atomic(off.head) };
+        let tail = unsafe { self.cq.atomic(off.tail) };
+        let mask = unsafe { self.cq.read(off.ring_mask) };
+        let cqes = unsafe {
+            slice::from_raw_parts(
+                self.cq.ptr.add(off.cqes as usize).cast::<Cqe>(),
+                self.params.cq_entries as usize,
+            )
+        };
+
+        let mut cur = head.load(Ordering::Relaxed);
+        let end = tail.load(Ordering::Acquire);
+        while cur != end {
+            let cqe = &cqes[(cur & mask) as usize];
+            let () = f(cqe.user_data, cqe.res);
+            cur = cur.wrapping_add(1);
+        }
+        let () = head.store(cur, Ordering::Release);
+    }
+}
+
+
+/// A file being read.
+#[derive(Debug)]
+struct Pending {
+    /// The index of the file in the caller provided list.
+    idx: usize,
+    file: Arc<File>,
+    stat: libc::stat,
+    /// The size of the file.
+    size: usize,
+    /// The buffer the file's contents are read into.
+    buf: Vec<u8>,
+    /// The number of bytes read so far.
+    read: usize,
+    /// The error encountered while reading, if any.
+    err: Option<io::Error>,
+}
+
+impl Pending {
+    fn is_done(&self) -> bool {
+        self.err.is_some() || self.read == self.size
+    }
+}
+
+
+/// Read all `pending` files using `ring`.
+///
+/// If an error is reported, reads may still be in flight.
+fn read_pending(ring: &mut Ring, pending: &mut [Pending]) -> io::Result<()> {
+    let depth = ring.params.sq_entries as usize;
+    let mut queue = (0..pending.len()).collect::<VecDeque<_>>();
+    let mut in_flight = 0;
+
+    while !queue.is_empty() || in_flight > 0 {
+        let mut count = 0;
+        while in_flight < depth {
+            let idx = if let Some(idx) = queue.pop_front() {
+                idx
+            } else {
+                break
+            };
+            let entry = &mut pending[idx];
+            let end = entry.size.min(entry.read + MAX_READ_LEN);
+            let fd = entry.file.as_raw_fd();
+            let offset = entry.read as u64;
+            // SAFETY: Buffers are only accessed once all reads have
+            //         completed and we never exceed the queue depth.
+            let () =
+                unsafe { ring.push_read(fd, &mut entry.buf[entry.read..end], offset, idx as u64) };
+            in_flight += 1;
+            count += 1;
+        }
+
+        let () = ring.submit_and_wait(count)?;
+        let () = ring.reap(|user_data, res| {
+            in_flight -= 1;
+            let idx = user_data as usize;
+            let entry = &mut pending[idx];
+            match res {
+                res if res < 0 => entry.err = Some(io::Error::from_raw_os_error(-res)),
+                // The file shrunk since we checked its size.
+                0 => {
+                    entry.size = entry.read;
+                    let () = entry.buf.truncate(entry.read);
+                }
+                res => entry.read += res as usize,
+            }
+            if !entry.is_done() {
+                let () = queue.push_back(idx);
+            }
+        });
+    }
+    Ok(())
+}
+
+
+/// Open the files at `paths` using `pool` and read them, concurrently.
+///
+/// Regular files are read into memory in their entirety, using
+/// `io_uring`. All other files (as well as all files, should `io_uring`
+/// not be available) are loaded as per [`FileData::from_file`].
+pub(crate) fn read_files(pool: &FdPool, paths: &[&Path]) -> Vec<Result<FileData>> {
+    let mut results = paths.iter().map(|_| None).collect::<Vec<_>>();
+    let mut pending = Vec::new();
+
+    for (idx, path) in paths.iter().enumerate() {
+        let result = pool.open(path).and_then(|file| {
+            let stat = fstat(file.as_raw_fd())
+                .with_context(|| format!("failed to stat {}", path.display()))?;
+            Ok((file, stat))
+        });
+        match result {
+            Ok((file, stat))
+                if (stat.st_mode & libc::S_IFMT) == libc::S_IFREG && stat.st_size > 0 =>
+            {
+                let () = pending.push(Pending {
+                    idx,
+                    file,
+                    stat,
+                    size: stat.st_size as usize,
+                    buf: Vec::new(),
+                    read: 0,
+                    err: None,
+                });
+            }
+            Ok((file, _stat)) => {
+                results[idx] = Some(
+                    FileData::from_file(&file)
+                        .with_context(|| format!("failed to load {}", path.display())),
+                )
+            }
+            Err(err) => results[idx] = Some(Err(err)),
+        }
+    }
+
+    if !pending.is_empty() {
+        match Ring::new(QUEUE_DEPTH) {
+            Ok(mut ring) => {
+                let () = pending
+                    .iter_mut()
+                    .for_each(|entry| entry.buf = vec![0; entry.size]);
+                if let Err(err) = read_pending(&mut ring, &mut pending) {
+                    log::debug!("failed to read files using io_uring: {err}");
+                    // Reads may still be in flight and we have no way
+                    // of waiting for them at this point. Leak the
+                    // buffers, as the kernel may still write to them.
+                    let () = pending.iter_mut().for_each(|entry| {
+                        let () = mem::forget(mem::take(&mut entry.buf));
+                        entry.read = 0;
+                    });
+                }
+            }
+            Err(err) => log::debug!("failed to set up io_uring: {err}"),
+        }
+    }
+
+    for entry in pending {
+        let path = paths[entry.idx];
+        let result = match entry.err {
+            Some(err) => {
+                Err(Error::from(err)).with_context(|| format!("failed to read {}", path.display()))
+            }
+            None if entry.read == entry.size && entry.buf.len() == entry.size => {
+                Ok(FileData::from_stat_vec(&entry.stat, entry.buf))
+            }
+            None => FileData::from_file(&entry.file)
+                .with_context(|| format!("failed to load {}", path.display())),
+        };
+        results[entry.idx] = Some(result);
+    }
+
+    results
+        .into_iter()
+        // SANITY: Each file has a result at this point.
+        .map(Option::unwrap)
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::fs;
+    use std::io::Write as _;
+
+    use tempfile::NamedTempFile;
+
+    use crate::ErrorKind;
+
+
+    /// Check that we can read files in bulk.
+    #[test]
+    fn bulk_read() {
+        let dir = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+        let elf = dir.join("test-stable-addresses.bin");
+        let dwarf = dir.join("test-stable-addresses-dwarf-only.bin");
+        let mut tmpfile = NamedTempFile::new().unwrap();
+        let () = tmpfile.write_all(b"foobar").unwrap();
+        let empty = NamedTempFile::new().unwrap();
+        let missing = dir.join("does-not-exist");
+
+        let paths = [
+            elf.as_path(),
+            Path::new("/proc/self/maps"),
+            tmpfile.path(),
+            missing.as_path(),
+            dwarf.as_path(),
+            empty.path(),
+            elf.as_path(),
+        ];
+        let results = read_files(&FdPool::default(), &paths);
+        assert_eq!(results.len(), paths.len());
+
+        let mut results = results.into_iter();
+        let data = results.next().unwrap().unwrap();
+        assert_eq!(data.data(), fs::read(&elf).unwrap());
+        let data = results.next().unwrap().unwrap();
+        assert!(!data.data().is_empty());
+        let data = results.next().unwrap().unwrap();
+        assert_eq!(data.data(), b"foobar");
+        let err = results.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        let data = results.next().unwrap().unwrap();
+        assert_eq!(data.data(), fs::read(&dwarf).unwrap());
+        let data = results.next().unwrap().unwrap();
+        assert_eq!(data.data(), b"");
+        let data = results.next().unwrap().unwrap();
+        assert_eq!(data.data(), fs::read(&elf).unwrap());
+    }
+
+    /// Check that we can read more files than fit into the ring at
+    /// once.
+    #[test]
+    fn bulk_read_many() {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-stable-addresses.bin");
+        let expected = fs::read(&path).unwrap();
+        let paths = vec![path.as_path(); QUEUE_DEPTH as usize * 2 + 1];
+
+        let results = read_files(&FdPool::default(), &paths);
+        assert_eq!(results.len(), paths.len());
+        for result in results {
+            assert_eq!(result.unwrap().data(), expected);
+        }
+    }
+}
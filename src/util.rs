@@ -1,13 +1,32 @@
 use std::cmp::Ordering;
 use std::ffi::CStr;
+#[cfg(target_os = "linux")]
 use std::ffi::CString;
+use std::ffi::OsStr;
+#[cfg(unix)]
+use std::fs::File;
+#[cfg(unix)]
 use std::io;
 use std::iter;
 use std::mem::align_of;
 use std::mem::size_of;
+#[cfg(unix)]
 use std::mem::MaybeUninit;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt as _;
+#[cfg(unix)]
+use std::os::unix::fs::FileExt as _;
+#[cfg(unix)]
 use std::os::unix::io::RawFd;
 use std::slice;
+#[cfg(not(unix))]
+use std::str;
+
+#[cfg(not(unix))]
+use crate::Error;
+#[cfg(unix)]
+use crate::ErrorExt as _;
+use crate::Result;
 
 
 /// Reorder elements of `array` based on index information in `indices`.
@@ -54,6 +73,41 @@ where
 }
 
 
+/// Interpret the provided bytes as an `OsStr`.
+///
+/// On Unix systems this conversion always succeeds. Elsewhere, the
+/// bytes are required to be valid UTF-8.
+#[cfg(unix)]
+#[inline]
+pub(crate) fn bytes_to_os_str(bytes: &[u8]) -> Option<&OsStr> {
+    Some(OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+#[inline]
+pub(crate) fn bytes_to_os_str(bytes: &[u8]) -> Option<&OsStr> {
+    str::from_utf8(bytes).ok().map(OsStr::new)
+}
+
+
+/// Retrieve the raw bytes making up an `OsStr`.
+///
+/// On Unix systems this conversion always succeeds. Elsewhere, only
+/// strings that are valid UTF-8 can be converted.
+#[cfg(unix)]
+#[inline]
+pub(crate) fn os_str_to_bytes(s: &OsStr) -> Option<&[u8]> {
+    Some(s.as_bytes())
+}
+
+#[cfg(not(unix))]
+#[inline]
+pub(crate) fn os_str_to_bytes(s: &OsStr) -> Option<&[u8]> {
+    s.to_str().map(str::as_bytes)
+}
+
+
+#[cfg(unix)]
 pub(crate) fn fstat(fd: RawFd) -> io::Result<libc::stat> {
     let mut dst = MaybeUninit::uninit();
     let rc = unsafe { libc::fstat(fd, dst.as_mut_ptr()) };
@@ -65,6 +119,24 @@ pub(crate) fn fstat(fd: RawFd) -> io::Result<libc::stat> {
     Ok(unsafe { dst.assume_init() })
 }
 
+/// Read `buf.len()` bytes at `offset` from the file at `path`.
+#[cfg(unix)]
+pub(crate) fn read_file_at(path: &str, offset: u64, buf: &mut [u8]) -> Result<()> {
+    let file = File::open(path).with_context(|| format!("failed to open {path}"))?;
+    let () = file
+        .read_exact_at(buf, offset)
+        .with_context(|| format!("failed to read {path}"))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn read_file_at(_path: &str, _offset: u64, _buf: &mut [u8]) -> Result<()> {
+    Err(Error::with_unsupported(
+        "reading files at an offset is unsupported on this platform",
+    ))
+}
+
+#[cfg(target_os = "linux")]
 pub(crate) fn uname_release() -> io::Result<CString> {
     let mut dst = MaybeUninit::uninit();
     let rc = unsafe { libc::uname(dst.as_mut_ptr()) };
@@ -181,6 +253,109 @@ unsafe impl Pod for u64 {}
 unsafe impl Pod for i128 {}
 unsafe impl Pod for u128 {}
 
+/// Encode `value` as unsigned LEB128 and append it to `buf`.
+pub(crate) fn push_u64_leb128(buf: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0b0111_1111) as u8;
+        value >>= 7;
+        if value == 0 {
+            let () = buf.push(byte);
+            break
+        }
+        let () = buf.push(byte | 0b1000_0000);
+    }
+}
+
+/// Encode `value` as signed LEB128 and append it to `buf`.
+pub(crate) fn push_i64_leb128(buf: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0b0111_1111) as u8;
+        value >>= 7;
+        let sign = byte & 0b0100_0000 != 0;
+        if (value == 0 && !sign) || (value == -1 && sign) {
+            let () = buf.push(byte);
+            break
+        }
+        let () = buf.push(byte | 0b1000_0000);
+    }
+}
+
+/// Check whether `name` matches the shell style glob `pattern`.
+///
+/// Supported are `*` (any sequence of characters), `?` (any single
+/// character), and bracket expressions such as `[abc]`, `[a-z]`, or
+/// `[!0-9]`. A backslash escapes the following character.
+pub(crate) fn glob_match(pattern: &str, name: &str) -> bool {
+    /// Match the bracket expression at the start of `pattern` (past
+    /// the opening `[`) against `c`, returning whether it matched and
+    /// the remainder of the pattern.
+    fn match_class(pattern: &[char], c: char) -> Option<(bool, &[char])> {
+        let (negate, mut pattern) = match pattern {
+            ['!' | '^', rest @ ..] => (true, rest),
+            _ => (false, pattern),
+        };
+        let mut matched = false;
+        let mut first = true;
+        loop {
+            match pattern {
+                [']', rest @ ..] if !first => return Some((matched != negate, rest)),
+                [lo, '-', hi, rest @ ..] if *hi != ']' => {
+                    matched |= (*lo..=*hi).contains(&c);
+                    pattern = rest;
+                }
+                [x, rest @ ..] => {
+                    matched |= *x == c;
+                    pattern = rest;
+                }
+                // An unterminated bracket expression never matches.
+                [] => return None,
+            }
+            first = false;
+        }
+    }
+
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let mut p = pattern.as_slice();
+    let mut n = name.as_slice();
+    // The position to backtrack to after the most recent `*`.
+    let mut backtrack = None::<(&[char], &[char])>;
+
+    loop {
+        let advanced = match (p, n) {
+            (['*', p_rest @ ..], _) => {
+                backtrack = Some((p_rest, n));
+                p = p_rest;
+                continue
+            }
+            (['?', p_rest @ ..], [_, n_rest @ ..]) => Some((p_rest, n_rest)),
+            (['[', class @ ..], [c, n_rest @ ..]) => match match_class(class, *c) {
+                Some((true, p_rest)) => Some((p_rest, n_rest)),
+                _ => None,
+            },
+            (['\\', x, p_rest @ ..], [c, n_rest @ ..]) => (x == c).then_some((p_rest, n_rest)),
+            ([x, p_rest @ ..], [c, n_rest @ ..]) if x == c => Some((p_rest, n_rest)),
+            ([], []) => return true,
+            _ => None,
+        };
+
+        match advanced {
+            Some((p_rest, n_rest)) => {
+                p = p_rest;
+                n = n_rest;
+            }
+            None => match backtrack {
+                Some((p_star, [_, n_rest @ ..])) => {
+                    backtrack = Some((p_star, n_rest));
+                    p = p_star;
+                    n = n_rest;
+                }
+                _ => return false,
+            },
+        }
+    }
+}
+
 /// An trait providing utility functions for reading data from a byte buffer.
 pub(crate) trait ReadRaw<'data> {
     /// Ensure that `len` bytes are available for consumption.
@@ -590,6 +765,26 @@ mod tests {
         assert_eq!(s, 3);
     }
 
+    /// Check that leb128 encoded values survive a round trip.
+    #[test]
+    fn leb128_writing() {
+        for value in [0, 1, 63, 64, 127, 128, 0x1d79f4, u32::MAX.into(), u64::MAX] {
+            let mut data = Vec::new();
+            let () = push_u64_leb128(&mut data, value);
+            let (v, s) = data.as_slice().read_u64_leb128().unwrap();
+            assert_eq!(v, value);
+            assert_eq!(usize::from(s), data.len());
+        }
+
+        for value in [0, 1, -1, 63, -64, 64, -65, -165388, i32::MIN.into()] {
+            let mut data = Vec::new();
+            let () = push_i64_leb128(&mut data, value);
+            let (v, s) = data.as_slice().read_i64_leb128().unwrap();
+            assert_eq!(v, value);
+            assert_eq!(usize::from(s), data.len());
+        }
+    }
+
     /// Check that we can read a NUL terminated string from a slice.
     #[test]
     fn cstr_reading() {
@@ -681,4 +876,32 @@ mod tests {
         assert_eq!(find_match_or_lower_bound(&data, 100), Some(4));
         assert_eq!(find_match_or_lower_bound(&data, 1337), Some(4));
     }
+
+    /// Check that glob patterns are matched as expected.
+    #[test]
+    fn glob_matching() {
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "a"));
+        assert!(glob_match("main", "main"));
+        assert!(!glob_match("main", "main2"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("fac*", "factorial"));
+        assert!(glob_match("*rial", "factorial"));
+        assert!(glob_match("*cto*", "factorial"));
+        assert!(glob_match("f*t*l", "factorial"));
+        assert!(!glob_match("f*t*x", "factorial"));
+        assert!(glob_match("?ain", "main"));
+        assert!(!glob_match("?ain", "ain"));
+        assert!(glob_match("fn[0-9]", "fn7"));
+        assert!(!glob_match("fn[0-9]", "fnx"));
+        assert!(glob_match("fn[!0-9]", "fnx"));
+        assert!(glob_match("fn[^0-9]", "fnx"));
+        assert!(glob_match("[]a]", "]"));
+        assert!(glob_match("[a-]", "-"));
+        assert!(!glob_match("fn[0-9", "fn1"));
+        assert!(glob_match("a\\*b", "a*b"));
+        assert!(!glob_match("a\\*b", "axb"));
+        assert!(glob_match("_ZN*std*", "_ZN3std2io5stdio6_print17h"));
+    }
 }
@@ -0,0 +1,98 @@
+//! Support for symbolizing addresses in the virtual dynamic shared
+//! object (vDSO) the kernel maps into every process.
+
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::elf::ElfBackend;
+use crate::elf::ElfParser;
+use crate::elf::ElfResolver;
+use crate::log::debug;
+use crate::maps::MapsEntry;
+use crate::maps::PathName;
+use crate::mmap::Mmap;
+use crate::util::read_file_at;
+use crate::Addr;
+use crate::IntoError as _;
+use crate::Pid;
+use crate::Result;
+
+
+/// The name under which the vDSO is listed in `/proc/<pid>/maps`.
+pub(crate) const VDSO_MAPS_COMPONENT: &str = "[vdso]";
+
+
+/// Check whether the proc maps entry `entry` represents the vDSO.
+pub(crate) fn is_vdso(entry: &MapsEntry) -> bool {
+    matches!(
+        &entry.path_name,
+        Some(PathName::Component(component)) if component == VDSO_MAPS_COMPONENT
+    )
+}
+
+/// Read the vDSO image of process `pid`, mapped at `range`.
+///
+/// The image is read from the process' memory, falling back to
+/// `/proc/<pid>/map_files/` on systems that expose the vDSO there.
+pub(crate) fn read_image(pid: Pid, range: &Range<Addr>) -> Result<Vec<u8>> {
+    let len = usize::try_from(range.end - range.start)
+        .ok()
+        .ok_or_invalid_data(|| format!("vDSO mapping {range:#x?} is too large"))?;
+    let mut image = vec![0; len];
+
+    let path = format!("/proc/{pid}/mem");
+    match read_file_at(&path, range.start, &mut image) {
+        Ok(()) => return Ok(image),
+        Err(err) => debug!("{err:#}; trying map_files"),
+    }
+
+    let path = format!("/proc/{pid}/map_files/{:x}-{:x}", range.start, range.end);
+    let () = read_file_at(&path, 0, &mut image)?;
+    Ok(image)
+}
+
+/// Create an [`ElfResolver`] for the vDSO image `image`.
+pub(crate) fn create_resolver(image: Vec<u8>) -> Result<ElfResolver> {
+    let parser = Arc::new(ElfParser::from_mmap(Mmap::from_vec(image)));
+    ElfResolver::with_backend(Path::new(VDSO_MAPS_COMPONENT), ElfBackend::Elf(parser))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::maps;
+    use crate::vfs::StdFileAccess;
+    use crate::SymResolver as _;
+
+
+    /// Check that we can read and symbolize our own vDSO.
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    #[test]
+    fn self_vdso() {
+        let entry = maps::parse(&StdFileAccess::default(), Pid::Slf)
+            .unwrap()
+            .map(Result::unwrap)
+            .find(is_vdso);
+        // Not all systems map a vDSO.
+        let entry = match entry {
+            Some(entry) => entry,
+            None => return,
+        };
+
+        let image = read_image(Pid::Slf, &entry.range).unwrap();
+        assert_eq!(&image[..4], b"\x7fELF");
+
+        let resolver = create_resolver(image).unwrap();
+        let parser = resolver.parser();
+        let syms = parser
+            .find_addr("__vdso_clock_gettime", &Default::default())
+            .unwrap();
+        let sym = syms.first().unwrap();
+        let found = resolver.find_sym(sym.addr).unwrap().unwrap();
+        assert_eq!(found.addr, sym.addr);
+        assert!(found.name.ends_with("clock_gettime"), "{}", found.name);
+    }
+}
@@ -0,0 +1,446 @@
+//! Abstractions for accessing files.
+//!
+//! All file system accesses performed as part of symbolization are
+//! routed through a [`FileAccess`] implementation. By default, that is
+//! [`StdFileAccess`], which works on the local file system. Embedders
+//! can provide their own implementation, e.g., for serving files from
+//! memory, a remote location, or a re-rooted directory hierarchy.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs::File;
+use std::fs::Metadata;
+use std::io::Read as _;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt as _;
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd as _;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+#[cfg(not(unix))]
+use std::time::SystemTime;
+
+use crate::fd_pool::FdPool;
+use crate::mmap::Mmap;
+#[cfg(unix)]
+use crate::util::fstat;
+use crate::Error;
+use crate::ErrorExt as _;
+use crate::Result;
+
+
+/// Meta data identifying a specific version of a file.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+// `libc` has deprecated `time_t` usage on `musl`. See
+// https://github.com/rust-lang/libc/issues/1848
+#[cfg_attr(target_env = "musl", allow(deprecated))]
+pub(crate) enum FileId {
+    /// A file identified by its `stat` information.
+    #[cfg(unix)]
+    Stat {
+        dev: libc::dev_t,
+        inode: libc::ino_t,
+        size: libc::off_t,
+        mtime_sec: libc::time_t,
+        mtime_nsec: i64,
+    },
+    /// A file identified by its size and modification time, on
+    /// platforms without `stat` support.
+    #[cfg(not(unix))]
+    Meta {
+        size: u64,
+        mtime: Option<SystemTime>,
+    },
+    /// A file identified by a version provided by the user.
+    Version(u64),
+}
+
+impl FileId {
+    #[cfg(unix)]
+    fn from_stat(stat: &libc::stat) -> Self {
+        // Casts are necessary because on Android some libc types do not
+        // use proper typedefs. https://github.com/rust-lang/libc/issues/3285
+        Self::Stat {
+            dev: stat.st_dev as _,
+            inode: stat.st_ino as _,
+            size: stat.st_size as _,
+            mtime_sec: stat.st_mtime,
+            mtime_nsec: stat.st_mtime_nsec as _,
+        }
+    }
+
+    #[cfg(unix)]
+    fn from_metadata(meta: &Metadata) -> Self {
+        Self::Stat {
+            dev: meta.dev() as _,
+            inode: meta.ino() as _,
+            size: meta.size() as _,
+            mtime_sec: meta.mtime() as _,
+            mtime_nsec: meta.mtime_nsec(),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn from_metadata(meta: &Metadata) -> Self {
+        Self::Meta {
+            size: meta.len(),
+            mtime: meta.modified().ok(),
+        }
+    }
+
+    /// Check whether the ID uniquely identifies a file, irrespective of
+    /// the path through which it was opened.
+    pub(crate) fn is_unique(&self) -> bool {
+        match self {
+            #[cfg(unix)]
+            Self::Stat { .. } => true,
+            #[cfg(not(unix))]
+            Self::Meta { .. } => false,
+            Self::Version(..) => false,
+        }
+    }
+}
+
+
+/// The contents of a file, as provided by a [`FileAccess`]
+/// implementation.
+///
+/// Besides the actual data, a `FileData` object carries information
+/// identifying the version of the file it represents. Cached data
+/// derived from a file is only reused as long as the version stays the
+/// same.
+#[derive(Clone, Debug)]
+pub struct FileData {
+    /// The version of the file.
+    id: FileId,
+    /// The file's contents.
+    mmap: Mmap,
+}
+
+impl FileData {
+    /// Create a `FileData` object from an open file.
+    ///
+    /// The file is memory mapped if possible and read into memory
+    /// otherwise (as is the case, for example, for most files in
+    /// `/proc`). Its version is derived from the file system's meta
+    /// data.
+    #[cfg(unix)]
+    pub fn from_file(file: &File) -> Result<Self> {
+        let stat = fstat(file.as_raw_fd())?;
+        let is_reg = (stat.st_mode & libc::S_IFMT) == libc::S_IFREG;
+        let mmap = if is_reg && stat.st_size > 0 {
+            Mmap::map(file).context("failed to memory map file")?
+        } else {
+            let mut data = Vec::new();
+            let _count = (&*file)
+                .read_to_end(&mut data)
+                .context("failed to read file")?;
+            Mmap::from_vec(data)
+        };
+
+        let slf = Self {
+            id: FileId::from_stat(&stat),
+            mmap,
+        };
+        Ok(slf)
+    }
+
+    /// Create a `FileData` object from an open file.
+    ///
+    /// The file is read into memory. Its version is derived from the
+    /// file system's meta data.
+    #[cfg(not(unix))]
+    pub fn from_file(mut file: &File) -> Result<Self> {
+        let meta = file.metadata().context("failed to query file meta data")?;
+        let mut data = Vec::new();
+        let _count = file.read_to_end(&mut data).context("failed to read file")?;
+
+        let slf = Self {
+            id: FileId::from_metadata(&meta),
+            mmap: Mmap::from_vec(data),
+        };
+        Ok(slf)
+    }
+
+    /// Create a `FileData` object from in-memory data.
+    ///
+    /// `version` identifies the revision of the data. Implementations
+    /// should report a different version whenever the data for a path
+    /// change, so that no stale cached information is used.
+    pub fn from_vec(data: Vec<u8>, version: u64) -> Self {
+        Self {
+            id: FileId::Version(version),
+            mmap: Mmap::from_vec(data),
+        }
+    }
+
+    /// Create a `FileData` object from the contents of a file read
+    /// into memory, with its version derived from `stat`.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub(crate) fn from_stat_vec(stat: &libc::stat, data: Vec<u8>) -> Self {
+        Self {
+            id: FileId::from_stat(stat),
+            mmap: Mmap::from_vec(data),
+        }
+    }
+
+    /// Retrieve the file's contents.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.mmap
+    }
+
+    /// Retrieve the version of the file.
+    #[inline]
+    pub(crate) fn id(&self) -> &FileId {
+        &self.id
+    }
+
+    /// Retrieve the `Mmap` representing the file's contents.
+    #[inline]
+    pub(crate) fn mmap(&self) -> &Mmap {
+        &self.mmap
+    }
+}
+
+impl AsRef<[u8]> for FileData {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.data()
+    }
+}
+
+
+/// The version of a file, as reported by [`FileAccess::version`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FileVersion(FileId);
+
+impl FileVersion {
+    /// Create a `FileVersion` object for a file whose contents are
+    /// provided via [`FileData::from_vec`] with the same `version`.
+    pub fn new(version: u64) -> Self {
+        Self(FileId::Version(version))
+    }
+
+    /// Retrieve the version of the file.
+    #[inline]
+    pub(crate) fn id(&self) -> &FileId {
+        &self.0
+    }
+}
+
+
+/// A trait providing access to files.
+///
+/// Errors indicating that a file does not exist should be reported with
+/// kind [`ErrorKind::NotFound`][crate::ErrorKind::NotFound], as some
+/// files are optional.
+pub trait FileAccess: Debug + Send + Sync {
+    /// Open the file at `path` and retrieve its contents.
+    fn open(&self, path: &Path) -> Result<FileData>;
+
+    /// Retrieve the version of the file at `path`, without loading its
+    /// contents.
+    ///
+    /// The version has to match that of the [`FileData`] reported by
+    /// [`open`][FileAccess::open] for the same file, as long as it did
+    /// not change. Versions are used for looking up cached data, so
+    /// that files are only loaded if nothing is cached for them. The
+    /// default implementation reports `None`, in which case files are
+    /// always opened.
+    fn version(&self, path: &Path) -> Result<Option<FileVersion>> {
+        let _path = path;
+        Ok(None)
+    }
+
+    /// Open the files at `paths` and retrieve their contents.
+    ///
+    /// Exactly one result is reported for each path, in the same order.
+    /// Implementations may load the files concurrently. The default
+    /// implementation opens one file after the other, using
+    /// [`open`][FileAccess::open].
+    fn open_many(&self, paths: &[&Path]) -> Vec<Result<FileData>> {
+        paths.iter().map(|path| self.open(path)).collect()
+    }
+}
+
+
+/// A [`FileAccess`] implementation using the local file system.
+///
+/// Regular files are accessed through a pool of file descriptors that
+/// are kept open for reuse. The pool is bounded (see
+/// [`with_max_open_files`][StdFileAccess::with_max_open_files]) and
+/// closes the least recently used descriptors when full, so that
+/// symbolization of addresses in a large number of files does not run
+/// into the process' limit of open files.
+///
+/// With the `io-uring` feature enabled, files loaded via
+/// [`open_many`][FileAccess::open_many] are read into memory
+/// concurrently using `io_uring` (on Linux), instead of being memory
+/// mapped.
+#[derive(Clone, Debug, Default)]
+pub struct StdFileAccess {
+    /// The pool of open file descriptors.
+    pool: Arc<FdPool>,
+}
+
+impl StdFileAccess {
+    /// Create a new `StdFileAccess` object with the default limit on
+    /// the number of open files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new `StdFileAccess` object keeping at most
+    /// `max_open_files` files open.
+    ///
+    /// Files currently being loaded are not subject to this limit. A
+    /// value of `0` causes files to be closed as soon as they have been
+    /// loaded.
+    pub fn with_max_open_files(max_open_files: usize) -> Self {
+        Self {
+            pool: Arc::new(FdPool::new(max_open_files)),
+        }
+    }
+}
+
+impl FileAccess for StdFileAccess {
+    fn open(&self, path: &Path) -> Result<FileData> {
+        let file = self.pool.open(path)?;
+        FileData::from_file(&file).with_context(|| format!("failed to load {}", path.display()))
+    }
+
+    fn version(&self, path: &Path) -> Result<Option<FileVersion>> {
+        // Errors are reported by `open`, which is used if we can't
+        // determine the version.
+        let version = path
+            .metadata()
+            .ok()
+            .map(|meta| FileVersion(FileId::from_metadata(&meta)));
+        Ok(version)
+    }
+
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    fn open_many(&self, paths: &[&Path]) -> Vec<Result<FileData>> {
+        // We read files in chunks, so that we never have more files
+        // open than permitted.
+        paths
+            .chunks(self.pool.capacity().max(1))
+            .flat_map(|paths| crate::uring::read_files(&self.pool, paths))
+            .collect()
+    }
+}
+
+
+/// A [`FileAccess`] implementation serving files from memory.
+///
+/// Files are looked up by the exact path they were registered with.
+/// Requests for any other path are reported as not found. This type is
+/// of use, for example, for symbolizing addresses using a
+/// [`Snapshot`][crate::symbolize::Snapshot] together with binaries
+/// retrieved from the system the snapshot was captured on.
+#[derive(Debug, Default)]
+pub struct MemFileAccess {
+    /// The registered files.
+    files: HashMap<PathBuf, FileData>,
+    /// The version to assign to the next registered file.
+    version: u64,
+}
+
+impl MemFileAccess {
+    /// Create a new, empty `MemFileAccess` object.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `data` as the contents of the file at `path`.
+    ///
+    /// A previously registered file at the same path is replaced.
+    pub fn insert(&mut self, path: impl Into<PathBuf>, data: Vec<u8>) {
+        let version = self.version;
+        self.version += 1;
+        let _prev = self
+            .files
+            .insert(path.into(), FileData::from_vec(data, version));
+    }
+}
+
+impl FileAccess for MemFileAccess {
+    fn open(&self, path: &Path) -> Result<FileData> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| Error::with_not_found(format!("file {} not found", path.display())))
+    }
+
+    fn version(&self, path: &Path) -> Result<Option<FileVersion>> {
+        let version = self
+            .files
+            .get(path)
+            .map(|data| FileVersion(data.id().clone()));
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write as _;
+
+    use tempfile::NamedTempFile;
+
+    use crate::ErrorKind;
+
+
+    /// Check that we can load regular files as well as special ones
+    /// that cannot be memory mapped.
+    #[test]
+    fn std_file_access() {
+        let fs = StdFileAccess::new();
+        let mut tmpfile = NamedTempFile::new().unwrap();
+        let () = tmpfile.write_all(b"foobar").unwrap();
+
+        let data = fs.open(tmpfile.path()).unwrap();
+        assert_eq!(data.data(), b"foobar");
+        assert!(matches!(data.id(), FileId::Stat { .. }));
+        let version = fs.version(tmpfile.path()).unwrap().unwrap();
+        assert_eq!(version.id(), data.id());
+
+        let data = fs.open(Path::new("/proc/kallsyms")).unwrap();
+        let version = fs.version(Path::new("/proc/kallsyms")).unwrap().unwrap();
+        assert_eq!(version.id(), data.id());
+
+        let data = fs.open(Path::new("/proc/self/maps")).unwrap();
+        assert!(!data.data().is_empty());
+
+        let err = fs.open(Path::new("/does-not-exist")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+    }
+
+    /// Check that `MemFileAccess` serves registered files and versions
+    /// them as expected.
+    #[test]
+    fn mem_file_access() {
+        let mut fs = MemFileAccess::new();
+        let () = fs.insert("/foo", b"foo".to_vec());
+        let () = fs.insert("/bar", b"bar".to_vec());
+
+        let foo = fs.open(Path::new("/foo")).unwrap();
+        assert_eq!(foo.data(), b"foo");
+        let version = fs.version(Path::new("/foo")).unwrap().unwrap();
+        assert_eq!(version.id(), foo.id());
+        let bar = fs.open(Path::new("/bar")).unwrap();
+        assert_eq!(bar.data(), b"bar");
+        assert_ne!(foo.id(), bar.id());
+
+        let () = fs.insert("/foo", b"foobar".to_vec());
+        let foobar = fs.open(Path::new("/foo")).unwrap();
+        assert_eq!(foobar.data(), b"foobar");
+        assert_ne!(foo.id(), foobar.id());
+
+        let err = fs.open(Path::new("/baz")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+    }
+}
@@ -13,10 +13,11 @@ use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
 use std::mem::size_of;
-use std::os::unix::ffi::OsStrExt as _;
 use std::path::Path;
+use std::path::PathBuf;
 
 use crate::mmap::Mmap;
+use crate::util::bytes_to_os_str;
 use crate::util::Pod;
 use crate::util::ReadRaw as _;
 use crate::Error;
@@ -179,7 +180,14 @@ impl<'archive> EntryIter<'archive> {
             }
 
             let path = data.read_slice(lfh.file_name_length.into())?;
-            let path = Path::new(OsStr::from_bytes(path));
+            let path = match bytes_to_os_str(path) {
+                Some(path) => Path::new(path),
+                None => {
+                    return Some(Err(Error::with_invalid_data(
+                        "entry path is not valid on this platform",
+                    )))
+                }
+            };
 
             let _extra = data.read_slice(lfh.extra_field_length.into())?;
             // SAFETY: Both pointers point into the same underlying byte array.
@@ -357,6 +365,19 @@ impl Archive {
         iter
     }
 
+    /// Find the entry whose data cover the provided offset into the
+    /// archive.
+    pub fn entry_at_offset(&self, offset: u64) -> Result<Option<Entry<'_>>> {
+        for entry in self.entries() {
+            let entry = entry?;
+            let bounds = entry.data_offset..entry.data_offset + entry.data.len() as u64;
+            if bounds.contains(&offset) {
+                return Ok(Some(entry))
+            }
+        }
+        Ok(None)
+    }
+
     /// Retrieve the [`Mmap`] object used by this `Archive`.
     #[inline]
     pub fn mmap(&self) -> &Mmap {
@@ -365,6 +386,59 @@ impl Archive {
 }
 
 
+/// Create an Android-style binary-in-APK path (e.g.,
+/// `/data/app/base.apk!/lib/arm64/libfoo.so`) from the path to an APK
+/// and the path of a binary inside of it.
+pub(crate) fn create_apk_elf_path(apk: &Path, elf: &Path) -> Result<PathBuf> {
+    let mut extension = apk
+        .extension()
+        .unwrap_or_else(|| OsStr::new("apk"))
+        .to_os_string();
+    // Append '!' to indicate separation from archive internal contents
+    // that follow. This is an Android convention.
+    let () = extension.push("!");
+
+    let mut apk = apk.to_path_buf();
+    if !apk.set_extension(extension) {
+        return Err(Error::with_invalid_data(format!(
+            "path {} is not valid",
+            apk.display()
+        )))
+    }
+
+    let path = apk.join(elf);
+    Ok(path)
+}
+
+/// Split an Android-style binary-in-APK path (e.g.,
+/// `/data/app/base.apk!/lib/arm64/libfoo.so`) into the path to the APK
+/// and the path of the binary inside of it.
+///
+/// `None` is returned if the path does not follow this convention.
+pub(crate) fn split_apk_elf_path(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    let mut components = path.components();
+    let mut apk = PathBuf::new();
+
+    while let Some(component) = components.next() {
+        let name = component.as_os_str();
+        match name.to_str().and_then(|name| name.strip_suffix('!')) {
+            Some(name) if !name.is_empty() => {
+                let elf = components.as_path();
+                if elf.as_os_str().is_empty() {
+                    return None
+                }
+                let () = apk.push(name);
+                return Some((apk, elf.to_path_buf()))
+            }
+            _ => {
+                let () = apk.push(name);
+            }
+        }
+    }
+    None
+}
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -382,6 +456,32 @@ mod tests {
     use crate::ErrorKind;
 
 
+    /// Check that we can create a path to an ELF inside an APK as expected.
+    #[test]
+    fn elf_apk_path_splitting() {
+        let path = Path::new("/data/app/base.apk!/lib/arm64/libfoo.so");
+        let (apk, elf) = split_apk_elf_path(path).unwrap();
+        assert_eq!(apk, Path::new("/data/app/base.apk"));
+        assert_eq!(elf, Path::new("lib/arm64/libfoo.so"));
+
+        let apk = Path::new("/root/test.apk");
+        let elf = Path::new("subdir/libc.so");
+        let path = create_apk_elf_path(apk, elf).unwrap();
+        assert_eq!(split_apk_elf_path(&path), Some((apk.into(), elf.into())));
+
+        assert_eq!(split_apk_elf_path(Path::new("/usr/lib/libc.so")), None);
+        assert_eq!(split_apk_elf_path(Path::new("/data/app/base.apk!")), None);
+        assert_eq!(split_apk_elf_path(Path::new("/data/!/libfoo.so")), None);
+    }
+
+    #[test]
+    fn elf_apk_path_creation() {
+        let apk = Path::new("/root/test.apk");
+        let elf = Path::new("subdir/libc.so");
+        let path = create_apk_elf_path(apk, elf).unwrap();
+        assert_eq!(path, Path::new("/root/test.apk!/subdir/libc.so"));
+    }
+
     /// Exercise the `Debug` representation of various types.
     #[test]
     fn debug_repr() {
@@ -4,25 +4,59 @@
     clippy::let_unit_value
 )]
 
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::ffi::CString;
 use std::ffi::OsStr;
+use std::fs::copy;
+use std::fs::create_dir_all;
 use std::fs::read as read_file;
+use std::fs::write as write_file;
+use std::io::BufRead as _;
+use std::io::BufReader;
 use std::io::Error;
+use std::io::Write as _;
+use std::net::TcpListener;
+use std::num::NonZeroUsize;
 use std::os::unix::ffi::OsStringExt as _;
+use std::os::unix::io::AsRawFd as _;
 use std::path::Path;
-
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread::sleep;
+use std::time::Duration;
+use std::time::Instant;
+
+use blazesym::breakpad;
 use blazesym::helper::read_elf_build_id;
 use blazesym::inspect;
 use blazesym::inspect::Inspector;
+use blazesym::inspect::SymBinding;
+use blazesym::inspect::SymFilter;
+use blazesym::inspect::SymTable;
+use blazesym::inspect::SymType;
+use blazesym::inspect::SymVisibility;
 use blazesym::normalize::Normalizer;
+use blazesym::normalize::UserMeta;
 use blazesym::symbolize;
+use blazesym::symbolize::Arena;
 use blazesym::symbolize::Reason;
+use blazesym::symbolize::SymbolDir;
 use blazesym::symbolize::Symbolized;
 use blazesym::symbolize::Symbolizer;
+use blazesym::vfs::FileAccess;
+use blazesym::vfs::FileData;
+use blazesym::vfs::MemFileAccess;
+use blazesym::vfs::StdFileAccess;
 use blazesym::Addr;
+use blazesym::CancellationToken;
 use blazesym::ErrorKind;
+use blazesym::Limits;
 use blazesym::Pid;
+use blazesym::Progress;
 
 use test_log::test;
 
@@ -45,6 +79,162 @@ fn error_on_non_existent_source() {
     }
 }
 
+/// Check that we can symbolize addresses using a fallback chain.
+#[test]
+fn symbolize_fallback() {
+    let data = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+    let no_dwarf = data.join("test-stable-addresses-no-dwarf.bin");
+    let fallback = symbolize::Fallback::new(vec![
+        symbolize::Tier::Gsym(symbolize::Gsym::File(symbolize::GsymFile::new(
+            data.join("does-not-exist.gsym"),
+        ))),
+        symbolize::Tier::Dwarf(no_dwarf.clone()),
+        symbolize::Tier::Symtab(no_dwarf),
+    ]);
+
+    let symbolizer = Symbolizer::new();
+    let results = symbolizer
+        .symbolize_fallback(&fallback, &[0x2000100, 0x0])
+        .unwrap();
+    assert_eq!(results.len(), 2);
+
+    let result = &results[0];
+    let sym = result.symbolized.as_sym().unwrap();
+    assert_eq!(sym.name, "factorial");
+    assert_eq!(result.tier, Some(2));
+    assert_eq!(result.skipped.len(), 2);
+    assert_eq!(result.skipped[0].0, 0);
+    match &result.skipped[0].1 {
+        symbolize::Skipped::Failed(err) => assert_eq!(err.kind(), ErrorKind::NotFound),
+        skipped => panic!("unexpected skip reason: {skipped:?}"),
+    }
+    assert_eq!(result.skipped[1].0, 1);
+    assert!(
+        matches!(result.skipped[1].1, symbolize::Skipped::Unknown(..)),
+        "{:?}",
+        result.skipped[1].1
+    );
+
+    let result = &results[1];
+    assert_eq!(result.tier, None);
+    assert_eq!(
+        result.symbolized,
+        Symbolized::Unknown(Reason::UnknownAddr),
+        "{result:?}"
+    );
+    assert_eq!(result.skipped.len(), 3);
+
+    // With Gsym data available, it should be used first.
+    let fallback = symbolize::Fallback::new(vec![
+        symbolize::Tier::Gsym(symbolize::Gsym::File(symbolize::GsymFile::new(
+            data.join("test-stable-addresses.gsym"),
+        ))),
+        symbolize::Tier::Symtab(data.join("test-stable-addresses-no-dwarf.bin")),
+    ]);
+    let results = symbolizer
+        .symbolize_fallback(&fallback, &[0x2000100])
+        .unwrap();
+    let result = &results[0];
+    let sym = result.symbolized.as_sym().unwrap();
+    assert_eq!(sym.name, "factorial");
+    assert_eq!(result.tier, Some(0));
+    assert!(result.skipped.is_empty());
+    assert!(sym.code_info.is_some());
+}
+
+/// Make sure that we report an unsupported format when providing a
+/// source of the wrong type.
+#[test]
+fn error_on_unsupported_format() {
+    let not_binary = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses.c");
+    let srcs = vec![
+        symbolize::Source::from(symbolize::GsymFile::new(&not_binary)),
+        symbolize::Source::Elf(symbolize::Elf::new(&not_binary)),
+        symbolize::Source::from(symbolize::LinkerMap::new(&not_binary)),
+        symbolize::Source::from(symbolize::BreakpadFile::new(&not_binary)),
+    ];
+    let symbolizer = Symbolizer::default();
+
+    for src in srcs {
+        let err = symbolizer
+            .symbolize_single(&src, symbolize::Input::VirtOffset(0x2000100))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedFormat, "{err:#}");
+    }
+}
+
+/// Check that we can symbolize addresses using a linker map file.
+#[test]
+fn symbolize_linker_map() {
+    let map = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses.map");
+    let src = symbolize::Source::from(symbolize::LinkerMap::new(map));
+    let symbolizer = Symbolizer::new();
+    let results = symbolizer
+        .symbolize(
+            &src,
+            symbolize::Input::VirtOffset(&[0x2000100, 0x200010a, 0x0]),
+        )
+        .unwrap();
+    assert_eq!(results.len(), 3);
+
+    for result in &results[0..2] {
+        let result = result.as_sym().unwrap();
+        assert_eq!(result.name, "factorial");
+        assert_eq!(result.addr, 0x2000100);
+        assert_ne!(result.size, None);
+        assert_eq!(result.code_info, None);
+    }
+    assert_eq!(results[2], Symbolized::Unknown(Reason::UnknownAddr));
+}
+
+/// Check that we can symbolize addresses using a Breakpad symbol file
+/// created from an ELF file.
+#[test]
+fn symbolize_breakpad() {
+    let bin = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses.bin");
+    let dir = tempfile::tempdir().unwrap();
+    let sym = dir.path().join("test-stable-addresses.sym");
+    let data = breakpad::Builder::default().build(&bin).unwrap();
+    let () = write_file(&sym, data).unwrap();
+
+    let src = symbolize::Source::from(symbolize::BreakpadFile::new(sym));
+    let symbolizer = Symbolizer::new();
+    // Addresses are relative to the ELF file's first loadable segment,
+    // which starts at 0x2000000.
+    let results = symbolizer
+        .symbolize(&src, symbolize::Input::VirtOffset(&[0x100, 0x20a]))
+        .unwrap();
+    assert_eq!(results.len(), 2);
+
+    let result = results[0].as_sym().unwrap();
+    assert_eq!(result.name, "factorial");
+    assert_eq!(result.addr, 0x100);
+    assert_eq!(result.offset, 0);
+    let code_info = result.code_info.as_ref().unwrap();
+    assert_eq!(code_info.file, OsStr::new("test-stable-addresses.c"));
+    assert_eq!(code_info.line, Some(8));
+
+    let result = results[1].as_sym().unwrap();
+    assert_eq!(result.name, "factorial_inline_test");
+    assert_eq!(result.offset, 0xa);
+    assert_eq!(result.code_info.as_ref().unwrap().line, Some(32));
+    assert_eq!(result.inlined.len(), 2);
+    assert_eq!(result.inlined[0].name, "factorial_inline_wrapper");
+    assert_eq!(result.inlined[1].name, "factorial_2nd_layer_inline_wrapper");
+    assert_eq!(result.inlined[1].code_info.as_ref().unwrap().line, Some(21));
+
+    let err = symbolizer
+        .symbolize_single(&src, symbolize::Input::AbsAddr(0x100))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+}
+
 /// Check that we can symbolize an address using ELF, DWARF, and GSYM.
 #[test]
 fn symbolize_elf_dwarf_gsym() {
@@ -126,6 +316,67 @@ fn symbolize_elf_dwarf_gsym() {
     test(src, true);
 }
 
+/// Check that ELF and DWARF based symbolization yield the same results
+/// for file offsets as for the corresponding virtual offsets, and that
+/// unsupported inputs are rejected.
+#[test]
+fn symbolize_elf_dwarf_gsym_inputs() {
+    // All test binaries map file offset 0x1000 to virtual address
+    // 0x2000000.
+    let virt_offsets = [0x2000100, 0x2000101, 0x2000200];
+    let file_offsets = [0x1100, 0x1101, 0x1200];
+
+    for file in [
+        "test-stable-addresses.bin",
+        "test-stable-addresses-no-dwarf.bin",
+        "test-stable-addresses-dwarf-only.bin",
+    ] {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join(file);
+        let src = symbolize::Source::Elf(symbolize::Elf::new(path));
+        let symbolizer = Symbolizer::new();
+        let expected = symbolizer
+            .symbolize(&src, symbolize::Input::VirtOffset(&virt_offsets))
+            .unwrap();
+        let symbolized = symbolizer
+            .symbolize(&src, symbolize::Input::FileOffset(&file_offsets))
+            .unwrap();
+        assert_eq!(symbolized, expected, "{file}");
+        assert_eq!(
+            expected[0].as_sym().map(|sym| sym.name.as_ref()),
+            Some("factorial")
+        );
+
+        let symbolized = symbolizer
+            .symbolize_single(&src, symbolize::Input::FileOffset(file_offsets[2]))
+            .unwrap();
+        assert_eq!(symbolized, expected[2], "{file}");
+
+        // File offsets not covered by any loadable segment cannot be
+        // mapped.
+        let symbolized = symbolizer
+            .symbolize_single(&src, symbolize::Input::FileOffset(0x10))
+            .unwrap();
+        assert_eq!(symbolized, Symbolized::Unknown(Reason::InvalidFileOffset));
+
+        let err = symbolizer
+            .symbolize(&src, symbolize::Input::AbsAddr(&virt_offsets))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses.gsym");
+    let src = symbolize::Source::from(symbolize::GsymFile::new(&path));
+    let symbolizer = Symbolizer::new();
+    let err = symbolizer
+        .symbolize(&src, symbolize::Input::FileOffset(&file_offsets))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+}
+
 /// Check that we "fail" symbolization as expected on a stripped ELF
 /// binary.
 #[test]
@@ -142,210 +393,2273 @@ fn symbolize_elf_stripped() {
     assert_eq!(result, Symbolized::Unknown(Reason::MissingSyms));
 }
 
-/// Make sure that we report (enabled) or don't report (disabled) inlined
-/// functions with DWARF and Gsym sources.
+/// Check that we can symbolize an address in an ELF file stored inside
+/// an APK, referenced using an Android-style `base.apk!/lib.so` path.
 #[test]
-fn symbolize_dwarf_gsym_inlined() {
-    fn test(src: symbolize::Source, inlined_fns: bool) {
-        let symbolizer = Symbolizer::builder()
-            .enable_inlined_fns(inlined_fns)
-            .build();
-        let result = symbolizer
-            .symbolize_single(&src, symbolize::Input::VirtOffset(0x200020a))
-            .unwrap()
-            .into_sym()
-            .unwrap();
-
-        let code_info = result.code_info.as_ref().unwrap();
-        assert_ne!(code_info.dir, None);
-        assert_eq!(code_info.file, OsStr::new("test-stable-addresses.c"));
-        // The Gsym format uses inline information to "refine" the
-        // line information associated with an address. As a result,
-        // when we ignore inline information we may end up with a
-        // slightly misleading location, namely that of the deepest
-        // inlined caller.
-        assert_eq!(code_info.line, Some(if inlined_fns { 32 } else { 21 }));
-
-        if inlined_fns {
-            assert_eq!(result.inlined.len(), 2);
-
-            let name = &result.inlined[0].name;
-            assert_eq!(*name, "factorial_inline_wrapper");
-            let frame = result.inlined[0].code_info.as_ref().unwrap();
-            assert_eq!(frame.file, OsStr::new("test-stable-addresses.c"));
-            assert_eq!(frame.line, Some(26));
-
-            let name = &result.inlined[1].name;
-            assert_eq!(*name, "factorial_2nd_layer_inline_wrapper");
-            let frame = result.inlined[1].code_info.as_ref().unwrap();
-            assert_eq!(frame.file, OsStr::new("test-stable-addresses.c"));
-            assert_eq!(frame.line, Some(21));
-        } else {
-            assert!(result.inlined.is_empty(), "{:#?}", result.inlined);
-        }
-    }
-
+fn symbolize_elf_in_apk() {
     let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
         .join("data")
-        .join("test-stable-addresses.gsym");
-    let src = symbolize::Source::from(symbolize::GsymFile::new(path));
-    test(src.clone(), true);
-    test(src, false);
+        .join("test.zip!")
+        .join("test-stable-addresses-dwarf-only.bin");
+    let src = symbolize::Source::Elf(symbolize::Elf::new(path));
+    let symbolizer = Symbolizer::new();
+    let sym = symbolizer
+        .symbolize_single(&src, symbolize::Input::VirtOffset(0x2000100))
+        .unwrap()
+        .into_sym()
+        .unwrap();
+    assert_eq!(sym.name, "factorial");
+    assert!(sym.code_info.is_some());
 
     let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
         .join("data")
-        .join("test-stable-addresses-dwarf-only.bin");
-    let src = symbolize::Source::from(symbolize::Elf::new(path));
-    test(src.clone(), true);
-    test(src, false);
+        .join("test.zip!")
+        .join("does-not-exist.so");
+    let src = symbolize::Source::Elf(symbolize::Elf::new(path));
+    let err = symbolizer
+        .symbolize_single(&src, symbolize::Input::VirtOffset(0x2000100))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
 }
 
-/// Check that we can symbolize the `abort_creds` function inside a
-/// kernel image properly. Inside of
-/// vmlinux-5.17.12-100.fc34.x86_64.dwarf, this function's address range
-/// and name are in separate attributes:
-/// ```text
-/// 0x01273e11: DW_TAG_subprogram
-///               DW_AT_abstract_origin     (0x0126ff5d "abort_creds")
-///               DW_AT_low_pc      (0xffffffff8110ecb0)
-///               DW_AT_high_pc     (0xffffffff8110ecce)
-///               DW_AT_frame_base  (DW_OP_call_frame_cfa)
-///               DW_AT_call_all_calls      (true)
-///               DW_AT_sibling     (0x01273f66)
-///
-/// 0x0110932c: DW_TAG_subprogram
-///               DW_AT_external    (true)
-///               DW_AT_name        ("abort_creds")
-///               DW_AT_decl_file   ("<...>/include/linux/cred.h")
-///               DW_AT_decl_line   (163)
-///               DW_AT_decl_column (0x0d)
-///               DW_AT_prototyped  (true)
-///               DW_AT_declaration (true)
-///               DW_AT_sibling     (0x0110933e)
-/// ```
-/// In the past we were unable to handle this case properly.
+/// Check that we can fetch debug information for an ELF file lacking
+/// it from a debuginfod server.
 #[test]
-#[cfg_attr(not(feature = "generate-large-test-files"), ignore)]
-fn symbolize_dwarf_complex() {
-    let test_dwarf = Path::new(&env!("CARGO_MANIFEST_DIR"))
+fn symbolize_elf_debuginfod() {
+    let stripped = Path::new(&env!("CARGO_MANIFEST_DIR"))
         .join("data")
-        .join("vmlinux-5.17.12-100.fc34.x86_64.dwarf");
-    let src = symbolize::Source::Elf(symbolize::Elf::new(test_dwarf));
+        .join("libtest-so-dwarf-stripped.so");
+    let debuginfo = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("libtest-so-dwarf.so");
+    let build_id = read_elf_build_id(&stripped).unwrap().unwrap();
+    let build_id = build_id
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+    let debuginfo_path = format!("/buildid/{build_id}/debuginfo");
+    let content = read_file(debuginfo).unwrap();
+
+    // A minimal debuginfod server, serving our debug information file.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let requests_clone = Arc::clone(&requests);
+    let debuginfo_path_clone = debuginfo_path.clone();
+    let _handle = std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut request = String::new();
+            let _cnt = reader.read_line(&mut request).unwrap();
+            loop {
+                let mut line = String::new();
+                let _cnt = reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break
+                }
+            }
+
+            let path = request.split_whitespace().nth(1).unwrap().to_string();
+            let (status, content) = if path == debuginfo_path_clone {
+                ("200 OK", content.as_slice())
+            } else {
+                ("404 Not Found", b"".as_slice())
+            };
+            let () = requests_clone.lock().unwrap().push(path);
+            let header = format!(
+                "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                content.len()
+            );
+            let () = stream.write_all(header.as_bytes()).unwrap();
+            let () = stream.write_all(content).unwrap();
+        }
+    });
+
+    let cache = tempfile::tempdir().unwrap();
+    // The environment is only evaluated when the `Symbolizer` is
+    // created.
+    let () = std::env::set_var("DEBUGINFOD_URLS", &url);
+    let () = std::env::set_var("DEBUGINFOD_CACHE_PATH", cache.path());
     let symbolizer = Symbolizer::new();
-    let result = symbolizer
-        .symbolize_single(&src, symbolize::Input::VirtOffset(0xffffffff8110ecb0))
+    let no_debuginfod = Symbolizer::builder().enable_debuginfod(false).build();
+    let () = std::env::remove_var("DEBUGINFOD_URLS");
+    let () = std::env::remove_var("DEBUGINFOD_CACHE_PATH");
+
+    let src = inspect::Source::Elf(inspect::Elf::new(&stripped));
+    let inspector = Inspector::new();
+    let results = inspector
+        .lookup(&src, &["the_answer"])
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 1);
+    let addr = results[0].addr;
+
+    let src = symbolize::Source::Elf(symbolize::Elf::new(&stripped));
+    let sym = no_debuginfod
+        .symbolize_single(&src, symbolize::Input::VirtOffset(addr))
         .unwrap()
         .into_sym()
         .unwrap();
+    assert_eq!(sym.name, "the_answer");
+    assert_eq!(sym.code_info, None);
+    assert!(!requests.lock().unwrap().contains(&debuginfo_path));
 
-    assert_eq!(result.name, "abort_creds");
-    assert_eq!(result.addr, 0xffffffff8110ecb0);
-    assert_eq!(result.code_info.as_ref().unwrap().line, Some(534));
+    for _ in 0..2 {
+        let sym = symbolizer
+            .symbolize_single(&src, symbolize::Input::VirtOffset(addr))
+            .unwrap()
+            .into_sym()
+            .unwrap();
+        assert_eq!(sym.name, "the_answer");
+        let code_info = sym.code_info.unwrap();
+        assert_eq!(code_info.file, OsStr::new("test-so.c"));
+    }
+    // Debug information is requested only once.
+    let count = requests
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|path| **path == debuginfo_path)
+        .count();
+    assert_eq!(count, 1);
+    assert!(cache.path().join(build_id).join("debuginfo").exists());
 }
 
-
-/// Symbolize an address inside a DWARF file, with and without auto-demangling
-/// enabled.
+/// Check that we can symbolize an ELF file using a separate debug
+/// information file located via build ID or `.gnu_debuglink`.
 #[test]
-fn symbolize_dwarf_demangle() {
-    fn test(test_dwarf: &Path, addr: Addr) -> Result<(), ()> {
-        let src = symbolize::Source::Elf(symbolize::Elf::new(test_dwarf));
-        let symbolizer = Symbolizer::builder().enable_demangling(false).build();
-        let result = symbolizer
+fn symbolize_elf_debug_file() {
+    fn symbolize(symbolizer: &Symbolizer, path: &Path, addr: Addr) -> Option<String> {
+        let src = symbolize::Source::Elf(symbolize::Elf::new(path));
+        let sym = symbolizer
             .symbolize_single(&src, symbolize::Input::VirtOffset(addr))
             .unwrap()
             .into_sym()
             .unwrap();
+        assert_eq!(sym.name, "the_answer");
+        sym.code_info
+            .map(|code_info| code_info.file.to_string_lossy().into_owned())
+    }
 
-        assert!(
-            result.name.contains("test13test_function"),
-            "{}",
-            result.name
-        );
+    let data = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+    let stripped = data.join("libtest-so-dwarf-stripped.so");
+    let debuglink = data.join("libtest-so-dwarf-debuglink.so");
+    let debug = data.join("libtest-so-dwarf.debug");
 
-        if result.inlined.is_empty() {
-            return Err(())
-        }
-        assert!(
-            result.inlined[0].name.contains("test12inlined_call"),
-            "{}",
-            result.inlined[0].name
-        );
+    let src = inspect::Source::Elf(inspect::Elf::new(&stripped));
+    let inspector = Inspector::new();
+    let results = inspector
+        .lookup(&src, &["the_answer"])
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 1);
+    let addr = results[0].addr;
 
-        // Do it again, this time with demangling enabled.
+    let no_dirs = Symbolizer::builder()
+        .set_debug_dirs(Option::<&Path>::None)
+        .enable_debuginfod(false)
+        .build();
+    // The stripped binary lacks a debug link and there is no debug
+    // directory to look up its build ID in.
+    assert_eq!(symbolize(&no_dirs, &stripped, addr), None);
+    // The debug link refers to a file in the same directory.
+    assert_eq!(
+        symbolize(&no_dirs, &debuglink, addr).as_deref(),
+        Some("test-so.c")
+    );
+
+    // Set up a debug directory with the debug file in the
+    // `.build-id/` hierarchy.
+    let build_id = read_elf_build_id(&stripped).unwrap().unwrap();
+    let build_id = build_id
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+    let dir = tempfile::tempdir().unwrap();
+    let build_id_dir = dir.path().join(".build-id").join(&build_id[..2]);
+    let () = create_dir_all(&build_id_dir).unwrap();
+    let _cnt = copy(
+        &debug,
+        build_id_dir.join(format!("{}.debug", &build_id[2..])),
+    )
+    .unwrap();
+
+    let symbolizer = Symbolizer::builder()
+        .set_debug_dirs([dir.path()])
+        .enable_debuginfod(false)
+        .build();
+    assert_eq!(
+        symbolize(&symbolizer, &stripped, addr).as_deref(),
+        Some("test-so.c")
+    );
+}
+
+/// Check that we can symbolize ELF files using split DWARF, with
+/// debug information residing in `.dwo` files or a `.dwp` package.
+#[test]
+fn symbolize_elf_split_dwarf() {
+    let data = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+    let paths = [
+        data.join("test-dwarf-v4-split.bin"),
+        data.join("test-dwarf-v5-split.bin"),
+        data.join("test-dwarf-v4-dwp.bin"),
+        // Split DWARF files should also be found when accessing the
+        // binary through a process' root directory.
+        Path::new("/proc/self/root").join(
+            data.strip_prefix("/")
+                .unwrap()
+                .join("test-dwarf-v4-dwp.bin"),
+        ),
+    ];
+
+    for path in paths {
+        let src = inspect::Source::Elf(inspect::Elf::new(&path));
+        let inspector = Inspector::new();
+        let results = inspector
+            .lookup(&src, &["fibonacci"])
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 1);
+        let addr = results[0].addr;
+
+        let src = symbolize::Source::Elf(symbolize::Elf::new(&path));
         let symbolizer = Symbolizer::new();
-        let result = symbolizer
+        let sym = symbolizer
             .symbolize_single(&src, symbolize::Input::VirtOffset(addr))
             .unwrap()
             .into_sym()
             .unwrap();
+        assert_eq!(sym.name, "fibonacci");
+        assert_eq!(sym.addr, addr);
+
+        let code_info = sym.code_info.as_ref().unwrap();
+        assert_eq!(code_info.dir.as_deref(), Some(data.as_path()));
+        assert_eq!(code_info.file, OsStr::new("test-exe.c"));
+        assert_eq!(code_info.line, Some(8));
+    }
+}
+
+/// Check that split DWARF files are opened using the configured
+/// `FileAccess` implementation.
+#[test]
+fn symbolize_elf_split_dwarf_file_access() {
+    #[derive(Debug, Default)]
+    struct Recording {
+        opened: Arc<Mutex<Vec<PathBuf>>>,
+    }
+
+    impl FileAccess for Recording {
+        fn open(&self, path: &Path) -> Result<FileData, blazesym::Error> {
+            let () = self.opened.lock().unwrap().push(path.to_path_buf());
+            StdFileAccess::default().open(path)
+        }
+    }
+
+    let data = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+    for (bin, ext) in [
+        ("test-dwarf-v5-split.bin", "dwo"),
+        ("test-dwarf-v4-dwp.bin", "dwp"),
+    ] {
+        let path = data.join(bin);
+        let src = inspect::Source::Elf(inspect::Elf::new(&path));
+        let inspector = Inspector::new();
+        let results = inspector
+            .lookup(&src, &["fibonacci"])
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        let addr = results[0].addr;
+
+        let fs = Recording::default();
+        let opened = fs.opened.clone();
+        let symbolizer = Symbolizer::builder().set_file_access(fs).build();
+        let src = symbolize::Source::Elf(symbolize::Elf::new(&path));
+        let sym = symbolizer
+            .symbolize_single(&src, symbolize::Input::VirtOffset(addr))
+            .unwrap()
+            .into_sym()
+            .unwrap();
+        assert_eq!(sym.code_info.as_ref().unwrap().line, Some(8));
+
+        let opened = opened.lock().unwrap();
+        assert!(
+            opened
+                .iter()
+                .any(|path| path.extension() == Some(OsStr::new(ext))),
+            "{opened:?}"
+        );
+    }
+}
+
+/// Check that we can report the source code surrounding symbolized
+/// addresses.
+#[test]
+fn symbolize_source_context() {
+    let data = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+    let src = symbolize::Source::Elf(symbolize::Elf::new(data.join("test-stable-addresses.bin")));
+    let context = symbolize::SourceContext {
+        lines: 1,
+        ..Default::default()
+    };
+    let symbolizer = Symbolizer::builder()
+        .set_source_context(Some(context.clone()))
+        .build();
+    let sym = symbolizer
+        .symbolize_single(&src, symbolize::Input::VirtOffset(0x2000100))
+        .unwrap()
+        .into_sym()
+        .unwrap();
+    assert_eq!(sym.name, "factorial");
+    let code_info = sym.code_info.as_ref().unwrap();
+    assert_eq!(code_info.line, Some(8));
+    let context = code_info.context.as_ref().unwrap();
+    assert_eq!(context.start_line, 7);
+    assert_eq!(
+        context.lines,
+        [
+            "__attribute__((section(\".text.factorial\"))) unsigned int",
+            "factorial(unsigned int n) {",
+            "  if (n == 0)",
+        ]
+    );
+
+    // Source files can be remapped to a different location.
+    let dir = tempfile::tempdir().unwrap();
+    let () = write_file(
+        dir.path().join("test-stable-addresses.c"),
+        b"1\n2\n3\n4\n5\n6\n7\n8\n",
+    )
+    .unwrap();
+    let context = symbolize::SourceContext {
+        lines: 2,
+        path_remaps: vec![(data.clone(), dir.path().to_path_buf())],
+        ..Default::default()
+    };
+    let symbolizer = Symbolizer::builder()
+        .set_source_context(Some(context))
+        .build();
+    let sym = symbolizer
+        .symbolize_single(&src, symbolize::Input::VirtOffset(0x2000100))
+        .unwrap()
+        .into_sym()
+        .unwrap();
+    let context = sym.code_info.as_ref().unwrap().context.as_ref().unwrap();
+    assert_eq!(context.start_line, 6);
+    assert_eq!(context.lines, ["6", "7", "8"]);
+
+    // No context is reported by default.
+    let symbolizer = Symbolizer::new();
+    let sym = symbolizer
+        .symbolize_single(&src, symbolize::Input::VirtOffset(0x2000100))
+        .unwrap()
+        .into_sym()
+        .unwrap();
+    assert_eq!(sym.code_info.as_ref().unwrap().context, None);
+}
+
+/// Check that we can symbolize ELF files with compressed debug
+/// sections.
+#[test]
+fn symbolize_elf_compressed_dwarf() {
+    let data = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+    let src = inspect::Source::Elf(inspect::Elf::new(data.join("test-dwarf-v4.bin")));
+    let inspector = Inspector::new();
+    let results = inspector
+        .lookup(&src, &["fibonacci"])
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 1);
+    let addr = results[0].addr;
+
+    for file in ["test-dwarf-v4-zlib.bin", "test-dwarf-v4-zstd.bin"] {
+        let path = data.join(file);
+        let src = symbolize::Source::Elf(symbolize::Elf::new(&path));
+        let symbolizer = Symbolizer::new();
+        let sym = symbolizer
+            .symbolize_single(&src, symbolize::Input::VirtOffset(addr))
+            .unwrap()
+            .into_sym()
+            .unwrap();
+        assert_eq!(sym.name, "fibonacci");
+        assert_eq!(sym.addr, addr);
+
+        let code_info = sym.code_info.as_ref().unwrap();
+        assert_eq!(code_info.file, OsStr::new("test-exe.c"));
+        assert_eq!(code_info.line, Some(8));
+    }
+}
+
+/// Check that a debug information file referenced by `.gnu_debuglink`
+/// is ignored if its checksum does not match.
+#[test]
+fn symbolize_elf_debug_link_checksum_mismatch() {
+    let data = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+    let dir = tempfile::tempdir().unwrap();
+    let debuglink = dir.path().join("libtest-so-dwarf-debuglink.so");
+    let _cnt = copy(data.join("libtest-so-dwarf-debuglink.so"), &debuglink).unwrap();
+    // A debug file with the expected name but different contents.
+    let _cnt = copy(
+        data.join("libtest-so-dwarf.so"),
+        dir.path().join("libtest-so-dwarf.debug"),
+    )
+    .unwrap();
+
+    let src = inspect::Source::Elf(inspect::Elf::new(&debuglink));
+    let inspector = Inspector::new();
+    let results = inspector
+        .lookup(&src, &["the_answer"])
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 1);
+    let addr = results[0].addr;
+
+    let symbolizer = Symbolizer::builder()
+        .set_debug_dirs(Option::<&Path>::None)
+        .enable_debuginfod(false)
+        .build();
+    let src = symbolize::Source::Elf(symbolize::Elf::new(&debuglink));
+    let sym = symbolizer
+        .symbolize_single(&src, symbolize::Input::VirtOffset(addr))
+        .unwrap()
+        .into_sym()
+        .unwrap();
+    assert_eq!(sym.name, "the_answer");
+    assert_eq!(sym.code_info, None);
+}
+
+/// Check that we can symbolize addresses inside of kernel modules.
+#[test]
+fn symbolize_kernel_modules() {
+    let data = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+    let dir = tempfile::tempdir().unwrap();
+    let module_dir = dir.path().join("6.5.0");
+    let extra_dir = module_dir.join("extra");
+    let () = create_dir_all(&extra_dir).unwrap();
+    let _cnt = copy(data.join("test-kmod.ko"), extra_dir.join("test-kmod.ko")).unwrap();
+    let _cnt = copy(
+        data.join("test-kmod.ko.xz"),
+        extra_dir.join("test-kmod-xz.ko.xz"),
+    )
+    .unwrap();
+    let () = write_file(
+        module_dir.join("modules.dep"),
+        b"extra/test-kmod.ko:\nextra/test-kmod-xz.ko.xz: extra/test-kmod.ko\n",
+    )
+    .unwrap();
+
+    let modules = dir.path().join("modules");
+    let () = write_file(
+        &modules,
+        b"\
+test_kmod 16384 0 - Live 0xffffffffc0200000 (OE)
+test_kmod_xz 16384 0 - Live 0xffffffffc0300000 (OE)
+missing 16384 0 - Live 0xffffffffc0400000
+",
+    )
+    .unwrap();
+    let kallsyms = dir.path().join("kallsyms");
+    let () = write_file(
+        &kallsyms,
+        b"\
+ffffffff81000000 T _stext
+ffffffffc0400010 t missing_fn\t[missing]
+",
+    )
+    .unwrap();
+
+    let src = symbolize::Source::from(symbolize::Kernel {
+        kallsyms: Some(kallsyms),
+        modules: Some(modules),
+        module_dir: Some(module_dir),
+        ..Default::default()
+    });
+    let symbolizer = Symbolizer::new();
+    let addrs = [
+        0xffffffffc020000d,
+        0xffffffffc0300001,
+        0xffffffffc0400012,
+        0xffffffff81000004,
+    ];
+    let syms = symbolizer
+        .symbolize(&src, symbolize::Input::AbsAddr(&addrs))
+        .unwrap()
+        .into_iter()
+        .map(|sym| sym.into_sym().unwrap())
+        .collect::<Vec<_>>();
+
+    // An address in an uncompressed module with debug information.
+    assert_eq!(syms[0].name, "test_kmod_answer");
+    assert_eq!(syms[0].addr, 0xffffffffc020000b);
+    assert_eq!(syms[0].offset, 2);
+    let code_info = syms[0].code_info.as_ref().unwrap();
+    assert_eq!(code_info.file, OsStr::new("test-kmod.c"));
+    assert_eq!(code_info.line, Some(7));
+
+    // An address in an xz compressed module.
+    assert_eq!(syms[1].name, "test_kmod_init");
+    assert_eq!(syms[1].addr, 0xffffffffc0300000);
+    assert!(syms[1].code_info.is_some());
+
+    // An address in a module whose file could not be found falls back
+    // to kallsyms.
+    assert_eq!(syms[2].name, "missing_fn");
+    assert_eq!(syms[2].offset, 2);
+    assert_eq!(syms[2].code_info, None);
+
+    // An address in the core kernel.
+    assert_eq!(syms[3].name, "_stext");
+    assert_eq!(syms[3].offset, 4);
+}
+
+/// Check that we take into account the KASLR offset of the kernel when
+/// symbolizing addresses using its image.
+#[test]
+fn symbolize_kernel_kaslr() {
+    let dir = tempfile::tempdir().unwrap();
+    let kallsyms = dir.path().join("kallsyms");
+    let () = write_file(&kallsyms, b"ffffffff82000100 T factorial\n").unwrap();
+    let image = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses.bin");
+
+    let mut kernel = symbolize::Kernel {
+        kallsyms: Some(kallsyms),
+        kernel_image: Some(image),
+        kaslr_offset: Some(0xffffffff80000000),
+        ..Default::default()
+    };
+    let src = symbolize::Source::from(kernel.clone());
+    let symbolizer = Symbolizer::new();
+    let sym = symbolizer
+        .symbolize_single(&src, symbolize::Input::AbsAddr(0xffffffff82000100))
+        .unwrap()
+        .into_sym()
+        .unwrap();
+    // The symbol itself is reported by kallsyms, but source code
+    // information is retrieved from the relocated image.
+    assert_eq!(sym.name, "factorial");
+    let code_info = sym.code_info.as_ref().unwrap();
+    assert_eq!(code_info.file, OsStr::new("test-stable-addresses.c"));
+    assert_eq!(code_info.line, Some(8));
+
+    // Without knowledge of the offset, no source code information can
+    // be found.
+    kernel.kaslr_offset = None;
+    let src = symbolize::Source::from(kernel);
+    let sym = symbolizer
+        .symbolize_single(&src, symbolize::Input::AbsAddr(0xffffffff82000100))
+        .unwrap()
+        .into_sym()
+        .unwrap();
+    assert_eq!(sym.name, "factorial");
+    assert_eq!(sym.code_info, None);
+}
+
+/// Check that symbolization is unaffected by memory mapping hints.
+#[test]
+fn symbolize_elf_mmap_advice() {
+    let advice = symbolize::MmapAdvice {
+        threshold: 0,
+        ..Default::default()
+    };
+    let symbolizer = Symbolizer::builder().set_mmap_advice(Some(advice)).build();
+
+    for (file, debug_syms) in [
+        ("test-stable-addresses-no-dwarf.bin", false),
+        ("test-stable-addresses-dwarf-only.bin", true),
+    ] {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join(file);
+        let mut elf = symbolize::Elf::new(path);
+        elf.debug_syms = debug_syms;
+        let src = symbolize::Source::Elf(elf);
+        let sym = symbolizer
+            .symbolize_single(&src, symbolize::Input::VirtOffset(0x2000100))
+            .unwrap()
+            .into_sym()
+            .unwrap();
+        assert_eq!(sym.name, "factorial");
+        assert_eq!(sym.code_info.is_some(), debug_syms);
+    }
+}
+
+/// Check that debug information is only used for addresses in the hot
+/// ranges of an ELF source, if provided.
+#[test]
+fn symbolize_elf_hot_ranges() {
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses.bin");
+    let mut elf = symbolize::Elf::new(path);
+    elf.hot_ranges = Some(vec![0x2000100..0x2000101, 0x2000213..0x2000214]);
+    let src = symbolize::Source::Elf(elf);
+    let symbolizer = Symbolizer::new();
+    let results = symbolizer
+        .symbolize(&src, symbolize::Input::VirtOffset(&[0x2000100, 0x2000034]))
+        .unwrap()
+        .into_iter()
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 2);
+
+    let sym = results[0].as_sym().unwrap();
+    assert_eq!(sym.name, "factorial");
+    assert!(sym.code_info.is_some());
+
+    // The address is outside of the hot ranges and symbolized using
+    // ELF symbols only.
+    let sym = results[1].as_sym().unwrap();
+    assert_eq!(sym.name, "factorial_wrapper");
+    assert_eq!(sym.code_info, None);
+}
+
+/// Make sure that we report (enabled) or don't report (disabled) inlined
+/// functions with DWARF and Gsym sources.
+#[test]
+fn symbolize_dwarf_gsym_inlined() {
+    fn test(src: symbolize::Source, inlined_fns: bool) {
+        let symbolizer = Symbolizer::builder()
+            .enable_inlined_fns(inlined_fns)
+            .build();
+        let result = symbolizer
+            .symbolize_single(&src, symbolize::Input::VirtOffset(0x200020a))
+            .unwrap()
+            .into_sym()
+            .unwrap();
+
+        let code_info = result.code_info.as_ref().unwrap();
+        assert_ne!(code_info.dir, None);
+        assert_eq!(code_info.file, OsStr::new("test-stable-addresses.c"));
+        // The Gsym format uses inline information to "refine" the
+        // line information associated with an address. As a result,
+        // when we ignore inline information we may end up with a
+        // slightly misleading location, namely that of the deepest
+        // inlined caller.
+        assert_eq!(code_info.line, Some(if inlined_fns { 32 } else { 21 }));
+
+        if inlined_fns {
+            assert_eq!(result.inlined.len(), 2);
+
+            let name = &result.inlined[0].name;
+            assert_eq!(*name, "factorial_inline_wrapper");
+            let frame = result.inlined[0].code_info.as_ref().unwrap();
+            assert_eq!(frame.file, OsStr::new("test-stable-addresses.c"));
+            assert_eq!(frame.line, Some(26));
+
+            let name = &result.inlined[1].name;
+            assert_eq!(*name, "factorial_2nd_layer_inline_wrapper");
+            let frame = result.inlined[1].code_info.as_ref().unwrap();
+            assert_eq!(frame.file, OsStr::new("test-stable-addresses.c"));
+            assert_eq!(frame.line, Some(21));
+        } else {
+            assert!(result.inlined.is_empty(), "{:#?}", result.inlined);
+        }
+    }
+
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses.gsym");
+    let src = symbolize::Source::from(symbolize::GsymFile::new(path));
+    test(src.clone(), true);
+    test(src, false);
+
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses-dwarf-only.bin");
+    let src = symbolize::Source::from(symbolize::Elf::new(path));
+    test(src.clone(), true);
+    test(src, false);
+}
+
+/// Check that we can symbolize the `abort_creds` function inside a
+/// kernel image properly. Inside of
+/// vmlinux-5.17.12-100.fc34.x86_64.dwarf, this function's address range
+/// and name are in separate attributes:
+/// ```text
+/// 0x01273e11: DW_TAG_subprogram
+///               DW_AT_abstract_origin     (0x0126ff5d "abort_creds")
+///               DW_AT_low_pc      (0xffffffff8110ecb0)
+///               DW_AT_high_pc     (0xffffffff8110ecce)
+///               DW_AT_frame_base  (DW_OP_call_frame_cfa)
+///               DW_AT_call_all_calls      (true)
+///               DW_AT_sibling     (0x01273f66)
+///
+/// 0x0110932c: DW_TAG_subprogram
+///               DW_AT_external    (true)
+///               DW_AT_name        ("abort_creds")
+///               DW_AT_decl_file   ("<...>/include/linux/cred.h")
+///               DW_AT_decl_line   (163)
+///               DW_AT_decl_column (0x0d)
+///               DW_AT_prototyped  (true)
+///               DW_AT_declaration (true)
+///               DW_AT_sibling     (0x0110933e)
+/// ```
+/// In the past we were unable to handle this case properly.
+#[test]
+#[cfg_attr(not(feature = "generate-large-test-files"), ignore)]
+fn symbolize_dwarf_complex() {
+    let test_dwarf = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("vmlinux-5.17.12-100.fc34.x86_64.dwarf");
+    let src = symbolize::Source::Elf(symbolize::Elf::new(test_dwarf));
+    let symbolizer = Symbolizer::new();
+    let result = symbolizer
+        .symbolize_single(&src, symbolize::Input::VirtOffset(0xffffffff8110ecb0))
+        .unwrap()
+        .into_sym()
+        .unwrap();
+
+    assert_eq!(result.name, "abort_creds");
+    assert_eq!(result.addr, 0xffffffff8110ecb0);
+    assert_eq!(result.code_info.as_ref().unwrap().line, Some(534));
+}
+
+
+/// Symbolize an address inside a DWARF file, with and without auto-demangling
+/// enabled.
+#[test]
+fn symbolize_dwarf_demangle() {
+    fn test(test_dwarf: &Path, addr: Addr) -> Result<(), ()> {
+        let src = symbolize::Source::Elf(symbolize::Elf::new(test_dwarf));
+        let symbolizer = Symbolizer::builder().enable_demangling(false).build();
+        let result = symbolizer
+            .symbolize_single(&src, symbolize::Input::VirtOffset(addr))
+            .unwrap()
+            .into_sym()
+            .unwrap();
+
+        assert!(
+            result.name.contains("test13test_function"),
+            "{}",
+            result.name
+        );
+
+        if result.inlined.is_empty() {
+            return Err(())
+        }
+        assert!(
+            result.inlined[0].name.contains("test12inlined_call"),
+            "{}",
+            result.inlined[0].name
+        );
+
+        // Do it again, this time with demangling enabled.
+        let symbolizer = Symbolizer::new();
+        let result = symbolizer
+            .symbolize_single(&src, symbolize::Input::VirtOffset(addr))
+            .unwrap()
+            .into_sym()
+            .unwrap();
+
+        assert_eq!(result.name, "test::test_function");
+        assert_eq!(result.inlined.len(), 1, "{:#?}", result.inlined);
+        assert_eq!(result.inlined[0].name, "test::inlined_call");
+        Ok(())
+    }
+
+    let test_dwarf = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-rs.bin");
+    let elf = inspect::Elf::new(&test_dwarf);
+    let src = inspect::Source::Elf(elf);
+
+    let inspector = Inspector::new();
+    let results = inspector
+        .lookup(&src, &["_RNvCs69hjMPjVIJK_4test13test_function"])
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    assert!(!results.is_empty());
+
+    let addr = results[0].addr;
+    let src = symbolize::Source::Elf(symbolize::Elf::new(&test_dwarf));
+    let symbolizer = Symbolizer::builder().enable_demangling(false).build();
+    let result = symbolizer
+        .symbolize_single(&src, symbolize::Input::VirtOffset(addr))
+        .unwrap()
+        .into_sym()
+        .unwrap();
+
+    let addr = result.addr;
+    let size = result.size.unwrap() as u64;
+    for inst_addr in addr..addr + size {
+        if test(&test_dwarf, inst_addr).is_ok() {
+            return
+        }
+    }
+
+    panic!("failed to find inlined function call");
+}
+
+/// Check that the `Inspector` reports demangled symbol names if
+/// configured to.
+#[test]
+fn inspect_elf_demangle() {
+    let test_rs = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-rs.bin");
+    let src = inspect::Source::Elf(inspect::Elf::new(&test_rs));
+    let mangled = "_RNvCs69hjMPjVIJK_4test13test_function";
+
+    let inspector = Inspector::new();
+    let results = inspector
+        .lookup(&src, &[mangled])
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].name, mangled);
+
+    let inspector = Inspector::builder().enable_demangling(true).build();
+    let results = inspector
+        .lookup(&src, &[mangled])
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].name, "test::test_function");
+
+    let found = inspector
+        .for_each(&src, false, |found, sym| {
+            found || sym.name == "test::test_function"
+        })
+        .unwrap();
+    assert!(found);
+}
+
+/// Check that we can symbolize addresses inside our own process.
+#[test]
+fn symbolize_process() {
+    let src = symbolize::Source::Process(symbolize::Process::new(Pid::Slf));
+    let addrs = [symbolize_process as Addr, Symbolizer::symbolize as Addr];
+    let symbolizer = Symbolizer::new();
+    let results = symbolizer
+        .symbolize(&src, symbolize::Input::AbsAddr(&addrs))
+        .unwrap()
+        .into_iter()
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 2);
+
+    let result = results[0].as_sym().unwrap();
+    assert!(result.name.contains("symbolize_process"), "{result:x?}");
+    assert_eq!(result.lang, symbolize::SrcLang::Rust);
+
+    let result = results[1].as_sym().unwrap();
+    // It's not entirely clear why we have seen two different demangled
+    // symbols, but they both seem legit.
+    assert!(
+        result.name == "blazesym::symbolize::symbolizer::Symbolizer::symbolize"
+            || result.name == "<blazesym::symbolize::symbolizer::Symbolizer>::symbolize",
+        "{}",
+        result.name
+    );
+}
+
+/// Check that we can symbolize addresses using a snapshot of a
+/// process' memory mappings, with files served from memory.
+#[test]
+fn symbolize_snapshot() {
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("libtest-so.so");
+    let src = inspect::Source::Elf(inspect::Elf::new(&path));
+    let inspector = Inspector::new();
+    let results = inspector
+        .lookup(&src, &["the_answer"])
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 1);
+    let file_offset = results[0].file_offset.unwrap();
+
+    // The path does not exist on the local system and the file is
+    // only accessible through the configured `FileAccess`.
+    let maps = "\
+7f0000000000-7f0000100000 r-xp 00000000 00:20 1337                       /remote/libtest-so.so
+7f0000200000-7f0000201000 rw-p 00000000 00:00 0                          [heap]
+";
+    let mut fs = MemFileAccess::new();
+    let () = fs.insert("/remote/libtest-so.so", read_file(&path).unwrap());
+    let symbolizer = Symbolizer::builder().set_file_access(fs).build();
+
+    let src = symbolize::Source::Snapshot(symbolize::Snapshot::new(maps));
+    let addrs = [0x7f0000000000 + file_offset, 0x7f0000200000, 0x1000];
+    let results = symbolizer
+        .symbolize(&src, symbolize::Input::AbsAddr(&addrs))
+        .unwrap();
+    assert_eq!(results.len(), 3);
+
+    let sym = results[0].as_sym().unwrap();
+    assert_eq!(sym.name, "the_answer");
+    assert_eq!(
+        results[1],
+        Symbolized::Unknown(Reason::SpecialRegion(symbolize::SpecialRegion::Heap))
+    );
+    assert_eq!(results[2], Symbolized::Unknown(Reason::Unmapped));
+}
+
+/// Check that symbolization results report the module, its build ID,
+/// and the address' file offset.
+#[test]
+fn symbolize_module_info() {
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("libtest-so.so");
+    let build_id = read_elf_build_id(&path).unwrap().unwrap();
+    let src = inspect::Source::Elf(inspect::Elf::new(&path));
+    let inspector = Inspector::new();
+    let results = inspector
+        .lookup(&src, &["the_answer"])
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 1);
+    let addr = results[0].addr;
+    let file_offset = results[0].file_offset.unwrap();
+
+    let symbolizer = Symbolizer::new();
+    let src = symbolize::Source::Elf(symbolize::Elf::new(&path));
+    let result = symbolizer
+        .symbolize_single(&src, symbolize::Input::VirtOffset(addr))
+        .unwrap();
+    let sym = result.as_sym().unwrap();
+    assert_eq!(sym.name, "the_answer");
+    assert_eq!(sym.module.as_deref(), Some(path.as_path()));
+    assert_eq!(sym.build_id.as_deref(), Some(build_id.as_slice()));
+    assert_eq!(sym.file_offset, Some(file_offset));
+
+    // Gsym files report their UUID, which we populate from the
+    // ELF file's build ID.
+    let data = blazesym::gsym::Builder::default().build(&path).unwrap();
+    let src = symbolize::Source::from(symbolize::GsymData::new(&data));
+    let result = symbolizer
+        .symbolize_single(&src, symbolize::Input::VirtOffset(addr))
+        .unwrap();
+    let sym = result.as_sym().unwrap();
+    assert_eq!(sym.name, "the_answer");
+    assert_eq!(sym.module, None);
+    assert_eq!(sym.build_id.as_deref(), Some(build_id.as_slice()));
+    assert_eq!(sym.file_offset, None);
+
+    // When symbolizing in a process context, the module is reported
+    // by the path it is mapped from.
+    let maps = "\
+7f0000000000-7f0000100000 r-xp 00000000 00:20 1337                       /remote/libtest-so.so
+";
+    let mut fs = MemFileAccess::new();
+    let () = fs.insert("/remote/libtest-so.so", read_file(&path).unwrap());
+    let symbolizer = Symbolizer::builder().set_file_access(fs).build();
+    let src = symbolize::Source::Snapshot(symbolize::Snapshot::new(maps));
+    let result = symbolizer
+        .symbolize_single(
+            &src,
+            symbolize::Input::AbsAddr(0x7f0000000000 + file_offset),
+        )
+        .unwrap();
+    let sym = result.as_sym().unwrap();
+    assert_eq!(sym.name, "the_answer");
+    assert_eq!(
+        sym.module.as_deref(),
+        Some(Path::new("/remote/libtest-so.so"))
+    );
+    assert_eq!(sym.build_id.as_deref(), Some(build_id.as_slice()));
+    assert_eq!(sym.file_offset, Some(file_offset));
+}
+
+/// Check that we can bound, inspect, and invalidate the data cached
+/// by a `Symbolizer`.
+#[test]
+fn symbolizer_cache_management() {
+    let test_so = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("libtest-so.so");
+    let test_bin = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses.bin");
+
+    let limits = symbolize::CacheLimits {
+        max_entries: Some(1),
+        ..Default::default()
+    };
+    let symbolizer = Symbolizer::builder()
+        .set_cache_limits(Some(limits))
+        .set_result_cache(Some(16))
+        .build();
+    let symbolize = |symbolizer: &Symbolizer, path: &Path, addr| {
+        let src = symbolize::Source::Elf(symbolize::Elf::new(path));
+        let result = symbolizer
+            .symbolize_single(&src, symbolize::Input::VirtOffset(addr))
+            .unwrap();
+        result.into_sym().unwrap().name.into_owned()
+    };
+
+    // The binary is the least recently used file and should get
+    // evicted once the shared object got loaded.
+    assert_eq!(symbolize(&symbolizer, &test_bin, 0x2000100), "factorial");
+    assert_eq!(symbolize(&symbolizer, &test_so, 0x10f9), "the_answer");
+    let stats = symbolizer.cache_stats();
+    assert_eq!(stats.misses, 2);
+    assert_eq!(stats.entries, 1);
+    assert_eq!(stats.evictions, 1);
+    let size = read_file(&test_so).unwrap().len() as u64;
+    assert_eq!(stats.bytes, size);
+
+    // Trimming a cache within its limits has no effect.
+    let () = symbolizer.trim_cache();
+    assert_eq!(symbolizer.cache_stats(), stats);
+
+    let hits = stats.hits;
+    assert_eq!(symbolize(&symbolizer, &test_so, 0x10f9), "the_answer");
+    let stats = symbolizer.cache_stats();
+    assert!(stats.hits > hits, "{stats:?}");
+    assert_eq!(stats.misses, 2);
+
+    let () = symbolizer.invalidate_file(&test_so);
+    let stats = symbolizer.cache_stats();
+    assert_eq!(stats.entries, 0);
+    assert_eq!(stats.evictions, 2);
+
+    // Invalidated files are reloaded on demand.
+    assert_eq!(symbolize(&symbolizer, &test_bin, 0x2000100), "factorial");
+    let stats = symbolizer.cache_stats();
+    assert_eq!(stats.misses, 3);
+    assert_eq!(stats.entries, 1);
+
+    // Data of other processes is unaffected by process invalidation.
+    let () = symbolizer.invalidate_process(Pid::Slf);
+    assert_eq!(symbolizer.cache_stats().entries, 1);
+
+    // Results stay valid once the data they were produced from is
+    // evicted.
+    let src = symbolize::Source::Elf(symbolize::Elf::new(&test_bin));
+    let result = symbolizer
+        .symbolize_single(&src, symbolize::Input::VirtOffset(0x2000100))
+        .unwrap();
+    let () = symbolizer.clear_cache();
+    let stats = symbolizer.cache_stats();
+    assert_eq!(stats.entries, 0);
+    assert_eq!(stats.bytes, 0);
+    assert_eq!(stats.evictions, 3);
+    assert_eq!(result.into_sym().unwrap().name, "factorial");
+}
+
+
+/// Check that invalidating a process evicts data of files accessed
+/// through its `/proc/<pid>/` directory.
+#[test]
+fn symbolizer_process_invalidation() {
+    let symbolizer = Symbolizer::new();
+    let src = symbolize::Source::Process(symbolize::Process::new(Pid::Slf));
+    let addr = symbolizer_process_invalidation as fn() as Addr;
+    let result = symbolizer
+        .symbolize_single(&src, symbolize::Input::AbsAddr(addr))
+        .unwrap();
+    assert!(result.as_sym().is_some());
+    assert_ne!(symbolizer.cache_stats().entries, 0);
+
+    let () = symbolizer.invalidate_process(Pid::Slf);
+    assert_eq!(symbolizer.cache_stats().entries, 0);
+}
+
+/// Check that parallel symbolization reports the same results as
+/// the sequential variant, in input order.
+#[test]
+fn symbolize_parallel() {
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses.bin");
+    let src = symbolize::Source::Elf(symbolize::Elf::new(path));
+    // Cover a range of addresses, in reverse order, with some of them
+    // not belonging to any symbol.
+    let addrs = (0..4096)
+        .rev()
+        .map(|i| 0x2000000 + i * 3)
+        .collect::<Vec<Addr>>();
+
+    let symbolizer = Symbolizer::new();
+    let expected = symbolizer
+        .symbolize(&src, symbolize::Input::VirtOffset(&addrs))
+        .unwrap();
+    assert!(expected.iter().any(|sym| sym.as_sym().is_some()));
+    assert!(expected.iter().any(|sym| sym.as_sym().is_none()));
+
+    let symbolizer = Symbolizer::builder()
+        .set_parallelism(NonZeroUsize::new(4))
+        .build();
+    let results = symbolizer
+        .symbolize_par(&src, symbolize::Input::VirtOffset(&addrs))
+        .unwrap();
+    assert_eq!(results, expected);
+
+    // Small batches are handled just the same.
+    let results = symbolizer
+        .symbolize_par(&src, symbolize::Input::VirtOffset(&addrs[..3]))
+        .unwrap();
+    assert_eq!(results, expected[..3]);
+}
+
+/// Check that the same ELF file accessed through different paths is
+/// only parsed once.
+#[test]
+fn symbolize_elf_shared_across_paths() {
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("libtest-so.so");
+    let dir = tempfile::tempdir().unwrap();
+    let link = dir.path().join("libtest-so-link.so");
+    let () = std::os::unix::fs::symlink(&path, &link).unwrap();
+
+    let symbolizer = Symbolizer::new();
+    for module in [&path, &link] {
+        let src = symbolize::Source::Elf(symbolize::Elf::new(module));
+        let result = symbolizer
+            .symbolize_single(&src, symbolize::Input::VirtOffset(0x10f9))
+            .unwrap()
+            .into_sym()
+            .unwrap();
+        assert_eq!(result.name, "the_answer");
+        // The module is reported by the path it was requested through.
+        assert_eq!(result.module.as_deref(), Some(module.as_path()));
+    }
+
+    let stats = symbolizer.cache_stats();
+    assert_eq!(stats.entries, 1);
+    assert_eq!(stats.misses, 1);
+    assert_eq!(stats.hits, 1);
+}
+
+/// Check that we can symbolize addresses in a process without
+/// entering its mount namespace, from multiple threads concurrently.
+#[test]
+fn symbolize_process_proc_root() {
+    let src = symbolize::Source::Process(symbolize::Process::new(Pid::Slf));
+    let addrs = [symbolize_process_proc_root as *const () as Addr];
+    let symbolizer = Symbolizer::builder()
+        .set_mount_ns_mode(symbolize::MountNsMode::ProcRoot)
+        .build();
+
+    let () = std::thread::scope(|s| {
+        for _ in 0..4 {
+            let _handle = s.spawn(|| {
+                let results = symbolizer
+                    .symbolize(&src, symbolize::Input::AbsAddr(&addrs))
+                    .unwrap();
+                assert_eq!(results.len(), 1);
+
+                let result = results[0].as_sym().unwrap();
+                assert!(
+                    result.name.contains("symbolize_process_proc_root"),
+                    "{result:x?}"
+                );
+            });
+        }
+    });
+}
+
+/// Check that we can symbolize addresses in a process residing in a
+/// different mount namespace using multiple threads, even if
+/// configured to enter the namespace.
+#[test]
+fn symbolize_par_process_foreign_mnt_ns() {
+    let mut child = Command::new("unshare")
+        .args(["--mount", "--propagation", "private", "sleep", "60"])
+        .spawn()
+        .unwrap();
+    let pid = child.id();
+
+    // Wait for `unshare` to have set up the namespace and executed
+    // `sleep`.
+    let our_ns = std::fs::read_link("/proc/self/ns/mnt").unwrap();
+    let start = Instant::now();
+    let maps = loop {
+        let ns = std::fs::read_link(format!("/proc/{pid}/ns/mnt")).unwrap();
+        let exe = std::fs::read_link(format!("/proc/{pid}/exe")).unwrap();
+        if ns != our_ns && exe.ends_with("sleep") {
+            break read_file(format!("/proc/{pid}/maps")).unwrap()
+        }
+        assert!(start.elapsed() < Duration::from_secs(10));
+        let () = sleep(Duration::from_millis(10));
+    };
+
+    // Symbolize addresses spread over the first executable mapping.
+    let maps = String::from_utf8(maps).unwrap();
+    let range = maps
+        .lines()
+        .find(|line| line.split_whitespace().nth(1) == Some("r-xp"))
+        .and_then(|line| line.split_whitespace().next())
+        .unwrap();
+    let (start, end) = range.split_once('-').unwrap();
+    let start = Addr::from_str_radix(start, 16).unwrap();
+    let end = Addr::from_str_radix(end, 16).unwrap();
+    let addrs = (0..1024)
+        .map(|i| start + (end - start) / 1024 * i)
+        .collect::<Vec<_>>();
+
+    let src = symbolize::Source::Process(symbolize::Process::new(Pid::from(pid)));
+    let symbolizer = Symbolizer::new();
+    let expected = symbolizer
+        .symbolize(&src, symbolize::Input::AbsAddr(&addrs))
+        .unwrap();
+    let par_symbolizer = Symbolizer::builder()
+        .set_mount_ns_mode(symbolize::MountNsMode::Enter)
+        .set_parallelism(NonZeroUsize::new(4))
+        .build();
+    let result = par_symbolizer.symbolize_par(&src, symbolize::Input::AbsAddr(&addrs));
+
+    let () = child.kill().unwrap();
+    let _status = child.wait().unwrap();
+
+    let results = result.unwrap();
+    assert_eq!(results.len(), addrs.len());
+    assert!(
+        results
+            .iter()
+            .all(|symbolized| !matches!(symbolized, Symbolized::Unknown(Reason::Unmapped))),
+        "{results:?}"
+    );
+    assert_eq!(results, expected);
+}
+
+/// Check that we can symbolize addresses in a process with
+/// background prefetching of its modules enabled.
+#[test]
+fn symbolize_process_prefetch() {
+    let src = symbolize::Source::Process(symbolize::Process::new(Pid::Slf));
+    let addrs = [symbolize_process_prefetch as *const () as Addr];
+    let symbolizer = Symbolizer::builder()
+        .set_prefetch(Some(symbolize::Prefetch::default()))
+        .build();
+
+    // The first request triggers prefetching, the second one may or
+    // may not benefit from it, depending on timing.
+    for _ in 0..2 {
+        let results = symbolizer
+            .symbolize(&src, symbolize::Input::AbsAddr(&addrs))
+            .unwrap()
+            .into_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 1);
+
+        let result = results[0].as_sym().unwrap();
+        assert!(
+            result.name.contains("symbolize_process_prefetch"),
+            "{result:x?}"
+        );
+    }
+}
+
+/// Check that we can symbolize addresses in a process with caching
+/// of its memory maps enabled, picking up shared objects loaded after
+/// the maps got cached.
+#[test]
+fn symbolize_process_maps_cache() {
+    let src = symbolize::Source::Process(symbolize::Process::new(Pid::Slf));
+    let symbolizer = Symbolizer::builder()
+        .set_maps_cache(Some(Duration::MAX))
+        .build();
+
+    let addr = symbolize_process_maps_cache as *const () as Addr;
+    for _ in 0..2 {
+        let result = symbolizer
+            .symbolize_single(&src, symbolize::Input::AbsAddr(addr))
+            .unwrap()
+            .into_sym()
+            .unwrap();
+        assert!(
+            result.name.contains("symbolize_process_maps_cache"),
+            "{result:x?}"
+        );
+    }
+
+    // Addresses not covered by cached entries cause them to be
+    // refreshed, picking up mappings created in the meantime.
+    let test_bin = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses.bin");
+    let file = std::fs::File::open(test_bin).unwrap();
+    let len = 4096;
+    let mapping = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE,
+            file.as_raw_fd(),
+            0,
+        )
+    };
+    assert_ne!(mapping, libc::MAP_FAILED);
+    let symbolize = || {
+        symbolizer
+            .symbolize_single(&src, symbolize::Input::AbsAddr(mapping as Addr))
+            .unwrap()
+    };
+    assert_ne!(symbolize(), Symbolized::Unknown(Reason::Unmapped));
+    let rc = unsafe { libc::munmap(mapping, len) };
+    assert_eq!(rc, 0, "{}", Error::last_os_error());
+    // Mappings removed in the meantime are only noticed once notified.
+    let () = symbolizer.notify_mmap(Pid::Slf);
+    assert_eq!(symbolize(), Symbolized::Unknown(Reason::Unmapped));
+
+    let test_so = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("libtest-so.so");
+    let so_cstr = CString::new(test_so.into_os_string().into_vec()).unwrap();
+    let handle = unsafe { libc::dlopen(so_cstr.as_ptr(), libc::RTLD_NOW) };
+    assert!(!handle.is_null());
+
+    let the_answer_addr = unsafe { libc::dlsym(handle, "the_answer\0".as_ptr().cast()) };
+    assert!(!the_answer_addr.is_null());
+
+    let result = symbolizer
+        .symbolize_single(&src, symbolize::Input::AbsAddr(the_answer_addr as Addr))
+        .unwrap()
+        .into_sym()
+        .unwrap();
+    assert_eq!(result.name, "the_answer");
+
+    let rc = unsafe { libc::dlclose(handle) };
+    assert_eq!(rc, 0, "{}", Error::last_os_error());
+
+    let () = symbolizer.notify_exec(Pid::Slf);
+    let result = symbolizer
+        .symbolize_single(&src, symbolize::Input::AbsAddr(addr))
+        .unwrap()
+        .into_sym()
+        .unwrap();
+    assert!(
+        result.name.contains("symbolize_process_maps_cache"),
+        "{result:x?}"
+    );
+}
+
+/// Check that a single `Symbolizer` can be shared between and used
+/// from multiple threads concurrently.
+#[test]
+fn symbolize_concurrently() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    let () = assert_send_sync::<Symbolizer>();
+
+    let dir = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+    let srcs = [
+        symbolize::Source::Elf(symbolize::Elf::new(
+            dir.join("test-stable-addresses-no-dwarf.bin"),
+        )),
+        symbolize::Source::Elf(symbolize::Elf::new(
+            dir.join("test-stable-addresses-dwarf-only.bin"),
+        )),
+        symbolize::Source::from(symbolize::GsymFile::new(
+            dir.join("test-stable-addresses.gsym"),
+        )),
+    ];
+    let symbolizer = Symbolizer::new();
+
+    let () = std::thread::scope(|s| {
+        for i in 0..16 {
+            let src = &srcs[i % srcs.len()];
+            let symbolizer = &symbolizer;
+            let _handle = s.spawn(move || {
+                for offset in 0..0x2b {
+                    let addr = 0x2000100 + offset;
+                    let sym = symbolizer
+                        .symbolize_single(src, symbolize::Input::VirtOffset(addr))
+                        .unwrap()
+                        .into_sym()
+                        .unwrap();
+                    assert_eq!(sym.name, "factorial");
+                    assert_eq!(sym.addr, 0x2000100);
+                }
+
+                let src = symbolize::Source::Process(symbolize::Process::new(Pid::Slf));
+                let addr = symbolize_concurrently as fn() as Addr;
+                let symbolized = symbolizer
+                    .symbolize_single(&src, symbolize::Input::AbsAddr(addr))
+                    .unwrap();
+                let sym = symbolized.as_sym().unwrap();
+                assert!(sym.name.contains("symbolize_concurrently"), "{sym:x?}");
+            });
+        }
+    });
+}
+
+/// Check that symbolization can be cancelled.
+#[test]
+fn symbolize_cancellation() {
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses-dwarf-only.bin");
+    let src = symbolize::Source::Elf(symbolize::Elf::new(path));
+    let symbolizer = Symbolizer::new();
+
+    let token = CancellationToken::new();
+    let () = token.cancel();
+    let err = symbolizer
+        .symbolize_cancellable(&src, symbolize::Input::VirtOffset(&[0x2000100]), &token)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Interrupted);
+
+    // A fresh token should not be affected by the earlier cancellation.
+    let token = CancellationToken::new();
+    let results = symbolizer
+        .symbolize_cancellable(&src, symbolize::Input::VirtOffset(&[0x2000100]), &token)
+        .unwrap();
+    assert_eq!(results[0].as_sym().unwrap().name, "factorial");
+}
+
+/// Check that symbolization honors the configured time budget.
+#[test]
+fn symbolize_time_budget() {
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses-dwarf-only.bin");
+    let src = symbolize::Source::Elf(symbolize::Elf::new(path));
+    let addrs = [0x2000100, 0x2000101];
+
+    let symbolizer = Symbolizer::builder()
+        .set_time_budget(Some(Duration::ZERO))
+        .build();
+    let results = symbolizer
+        .symbolize(&src, symbolize::Input::VirtOffset(&addrs))
+        .unwrap();
+    assert_eq!(
+        results,
+        vec![Symbolized::Unknown(Reason::TimedOut); addrs.len()]
+    );
+
+    let result = symbolizer
+        .symbolize_single(&src, symbolize::Input::VirtOffset(addrs[0]))
+        .unwrap();
+    assert_eq!(result, Symbolized::Unknown(Reason::TimedOut));
+
+    let symbolizer = Symbolizer::builder()
+        .set_time_budget(Some(Duration::from_secs(3600)))
+        .build();
+    let results = symbolizer
+        .symbolize(&src, symbolize::Input::VirtOffset(&addrs))
+        .unwrap();
+    for result in results {
+        assert_eq!(result.as_sym().unwrap().name, "factorial");
+    }
+}
+
+/// Check that the time budget is honored even if the file system is
+/// slow to respond.
+#[test]
+fn symbolize_time_budget_slow_file_access() {
+    #[derive(Debug)]
+    struct Slow;
+
+    impl FileAccess for Slow {
+        fn open(&self, path: &Path) -> Result<FileData, blazesym::Error> {
+            let () = sleep(Duration::from_secs(10));
+            StdFileAccess::default().open(path)
+        }
+    }
+
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses-no-dwarf.bin");
+    let src = symbolize::Source::Elf(symbolize::Elf::new(path));
+    let addrs = [0x2000100, 0x2000200];
+
+    let symbolizer = Symbolizer::builder()
+        .set_file_access(Slow)
+        .set_time_budget(Some(Duration::from_millis(200)))
+        .build();
+    let start = Instant::now();
+    let results = symbolizer
+        .symbolize(&src, symbolize::Input::VirtOffset(&addrs))
+        .unwrap();
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(
+        results,
+        vec![Symbolized::Unknown(Reason::TimedOut); addrs.len()]
+    );
+}
+
+/// Check that progress is reported while indexing DWARF debug
+/// information.
+#[test]
+fn symbolize_progress_reporting() {
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses-dwarf-only.bin");
+    let src = symbolize::Source::Elf(symbolize::Elf::new(path));
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let reports_ = Arc::clone(&reports);
+    let symbolizer = Symbolizer::builder()
+        .set_progress_callback(move |progress| reports_.lock().unwrap().push(progress.clone()))
+        .build();
+    let result = symbolizer
+        .symbolize_single(&src, symbolize::Input::VirtOffset(0x2000100))
+        .unwrap();
+    assert_eq!(result.as_sym().unwrap().name, "factorial");
+
+    let reports = reports.lock().unwrap();
+    assert!(!reports.is_empty());
+    assert!(reports
+        .iter()
+        .all(|progress| matches!(progress, Progress::DwarfIndex { .. })));
+    assert_eq!(reports.last().unwrap().percent(), 100.0);
+}
+
+/// Check that we can symbolize addresses in files provided by a custom
+/// `FileAccess` implementation.
+#[test]
+fn symbolize_custom_file_access() {
+    #[derive(Debug)]
+    struct InMemory {
+        files: HashMap<PathBuf, Vec<u8>>,
+    }
+
+    impl FileAccess for InMemory {
+        fn open(&self, path: &Path) -> Result<FileData, blazesym::Error> {
+            let data = self.files.get(path).ok_or_else(|| {
+                blazesym::Error::from(Error::new(
+                    std::io::ErrorKind::NotFound,
+                    path.display().to_string(),
+                ))
+            })?;
+            Ok(FileData::from_vec(data.clone(), 0))
+        }
+    }
+
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses-dwarf-only.bin");
+    let virt_path = PathBuf::from("/in-memory/test.bin");
+    let fs = InMemory {
+        files: HashMap::from([(virt_path.clone(), read_file(path).unwrap())]),
+    };
+    let symbolizer = Symbolizer::builder().set_file_access(fs).build();
+
+    let src = symbolize::Source::Elf(symbolize::Elf::new(&virt_path));
+    let sym = symbolizer
+        .symbolize_single(&src, symbolize::Input::VirtOffset(0x2000100))
+        .unwrap()
+        .into_sym()
+        .unwrap();
+    assert_eq!(sym.name, "factorial");
+    assert_eq!(
+        sym.code_info.as_ref().unwrap().file,
+        OsStr::new("test-stable-addresses.c")
+    );
+
+    // Files not provided by our implementation are not found.
+    let src = symbolize::Source::Elf(symbolize::Elf::new(
+        Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("test-stable-addresses-no-dwarf.bin"),
+    ));
+    let err = symbolizer
+        .symbolize_single(&src, symbolize::Input::VirtOffset(0x2000100))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+/// A `FileAccess` implementation that denies access to
+/// `/proc/<pid>/map_files/`, similar to what happens when we lack the
+/// necessary capabilities, and that reports substitute contents for
+/// some paths.
+#[derive(Debug, Default)]
+struct NoMapFiles {
+    substitutes: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl FileAccess for NoMapFiles {
+    fn open(&self, path: &Path) -> Result<FileData, blazesym::Error> {
+        if path.to_string_lossy().contains("/map_files/") {
+            return Err(blazesym::Error::from(Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                path.display().to_string(),
+            )))
+        }
+
+        match self.substitutes.get(path) {
+            Some(data) => Ok(FileData::from_vec(data.clone(), 0)),
+            None => StdFileAccess::default().open(path),
+        }
+    }
+}
+
+/// Check that we fall back to alternative means of accessing mapped
+/// files if `/proc/<pid>/map_files/` is inaccessible.
+#[test]
+fn symbolize_process_no_map_files() {
+    let src = symbolize::Source::Process(symbolize::Process::new(Pid::Slf));
+    let symbolizer = Symbolizer::builder()
+        .set_file_access(NoMapFiles::default())
+        .build();
+
+    // Our own executable is accessed via `/proc/self/exe`.
+    let addr = symbolize_process_no_map_files as *const () as Addr;
+    let sym = symbolizer
+        .symbolize_single(&src, symbolize::Input::AbsAddr(addr))
+        .unwrap()
+        .into_sym()
+        .unwrap();
+    assert!(sym.name.contains("symbolize_process_no_map_files"));
+    assert_eq!(
+        sym.module.as_deref(),
+        Some(std::env::current_exe().unwrap().as_path())
+    );
+
+    // Shared objects are accessed via their path on disk.
+    let dir = tempfile::tempdir().unwrap();
+    let so = dir.path().join("libtest-so.so");
+    let _cnt = copy(
+        Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join("libtest-so.so"),
+        &so,
+    )
+    .unwrap();
+    let so_cstr = CString::new(so.clone().into_os_string().into_vec()).unwrap();
+    let handle = unsafe { libc::dlopen(so_cstr.as_ptr(), libc::RTLD_NOW) };
+    assert!(!handle.is_null());
+
+    let the_answer_addr = unsafe { libc::dlsym(handle, "the_answer\0".as_ptr().cast()) };
+    assert!(!the_answer_addr.is_null());
+
+    let sym = symbolizer
+        .symbolize_single(&src, symbolize::Input::AbsAddr(the_answer_addr as Addr))
+        .unwrap()
+        .into_sym()
+        .unwrap();
+    assert_eq!(sym.name, "the_answer");
+    assert_eq!(sym.module.as_deref(), Some(so.as_path()));
+
+    // If the file on disk got replaced with a different one, we
+    // report a build ID mismatch instead of symbolizing using it.
+    let other = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("libtest-so-no-separate-code.so");
+    let fs = NoMapFiles {
+        substitutes: HashMap::from([(so.clone(), read_file(other).unwrap())]),
+    };
+    let symbolizer = Symbolizer::builder().set_file_access(fs).build();
+    let result = symbolizer
+        .symbolize_single(&src, symbolize::Input::AbsAddr(the_answer_addr as Addr))
+        .unwrap();
+
+    let rc = unsafe { libc::dlclose(handle) };
+    assert_eq!(rc, 0, "{}", Error::last_os_error());
+
+    assert_eq!(result, Symbolized::Unknown(Reason::BuildIdMismatch));
+}
+
+/// Check that we can symbolize addresses in more files than we keep
+/// open at any time.
+#[test]
+fn symbolize_max_open_files() {
+    let dir = Path::new(&env!("CARGO_MANIFEST_DIR")).join("data");
+    let srcs = [
+        "test-stable-addresses.bin",
+        "test-stable-addresses-no-dwarf.bin",
+        "test-stable-addresses-dwarf-only.bin",
+    ]
+    .map(|name| symbolize::Source::Elf(symbolize::Elf::new(dir.join(name))));
+
+    let symbolizer = Symbolizer::builder()
+        .set_file_access(StdFileAccess::with_max_open_files(1))
+        .build();
+    for _ in 0..2 {
+        for src in &srcs {
+            let sym = symbolizer
+                .symbolize_single(src, symbolize::Input::VirtOffset(0x2000100))
+                .unwrap()
+                .into_sym()
+                .unwrap();
+            assert_eq!(sym.name, "factorial");
+        }
+    }
+}
+
+/// Check that cached symbolization results match freshly created
+/// ones.
+#[test]
+fn symbolize_result_cache() {
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses.bin");
+    let src = symbolize::Source::Elf(symbolize::Elf::new(path));
+    let addrs = [0x2000100, 0x2000200, 0x2000100, 0x10];
+
+    let uncached = Symbolizer::new();
+    let expected = uncached
+        .symbolize(&src, symbolize::Input::VirtOffset(&addrs))
+        .unwrap();
+    let symbolizer = Symbolizer::builder().set_result_cache(Some(2)).build();
+    for _ in 0..3 {
+        let results = symbolizer
+            .symbolize(&src, symbolize::Input::VirtOffset(&addrs))
+            .unwrap();
+        assert_eq!(results, expected);
+    }
+
+    let arena = symbolize::Arena::new();
+    let results = symbolizer
+        .symbolize_in(&arena, &src, symbolize::Input::VirtOffset(&addrs))
+        .unwrap();
+    assert_eq!(results, expected);
+}
+
+/// Check that we report symbol table based results while debug
+/// information is indexed in the background.
+#[test]
+fn symbolize_deferred_indexing() {
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses.bin");
+    let src = symbolize::Source::Elf(symbolize::Elf::new(path));
+    let input = symbolize::Input::VirtOffset([0x2000100].as_slice());
+    let symbolizer = Symbolizer::builder().enable_deferred_indexing(true).build();
+
+    let results = symbolizer.symbolize(&src, input).unwrap();
+    let sym = results[0].as_sym().unwrap();
+    assert_eq!(sym.name, "factorial");
+    assert_eq!(sym.code_info, None);
+
+    let results = symbolizer.refine(&src, input).unwrap();
+    let sym = results[0].as_sym().unwrap();
+    assert_eq!(sym.name, "factorial");
+    assert_eq!(sym.code_info.as_ref().unwrap().line, Some(8));
+
+    // Now that debug information is indexed, regular requests report
+    // it as well.
+    let results = symbolizer.symbolize(&src, input).unwrap();
+    let sym = results[0].as_sym().unwrap();
+    assert_eq!(sym.code_info.as_ref().unwrap().line, Some(8));
+}
+
+/// Check that we can allocate symbolization results in an arena.
+#[test]
+fn symbolize_arena() {
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses.gsym");
+    let data = read_file(path).unwrap();
+    let src = symbolize::Source::from(symbolize::GsymData::new(&data));
+    let symbolizer = Symbolizer::new();
+    let mut arena = Arena::new();
+
+    for _ in 0..2 {
+        let results = symbolizer
+            .symbolize_in(
+                &arena,
+                &src,
+                symbolize::Input::VirtOffset(&[0x2000100, 0x200020a]),
+            )
+            .unwrap();
+        assert_eq!(results.len(), 2);
+
+        let sym = results[0].as_sym().unwrap();
+        assert_eq!(sym.name, "factorial");
+        assert!(matches!(sym.name, Cow::Borrowed(..)));
+        let code_info = sym.code_info.as_ref().unwrap();
+        assert_eq!(code_info.file, OsStr::new("test-stable-addresses.c"));
+        assert!(matches!(code_info.file, Cow::Borrowed(..)));
+
+        let sym = results[1].as_sym().unwrap();
+        assert_eq!(sym.name, "factorial_inline_test");
+        assert_eq!(sym.inlined.len(), 2);
+        assert!(matches!(sym.inlined[0].name, Cow::Borrowed(..)));
+
+        assert_ne!(arena.allocated_bytes(), 0);
+        drop(results);
+        let () = arena.reset();
+        assert_eq!(arena.allocated_bytes(), 0);
+    }
+}
+
+/// Check that caller provided values are passed through tagged
+/// symbolization.
+#[test]
+fn symbolize_tagged() {
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses.gsym");
+    let src = symbolize::Source::from(symbolize::GsymFile::new(path));
+    let symbolizer = Symbolizer::new();
+
+    let input = vec![(0x200020a, "second"), (0x2000100, "first")];
+    let results = symbolizer
+        .symbolize_tagged(&src, symbolize::Input::VirtOffset(input))
+        .unwrap();
+    assert_eq!(results.len(), 2);
+
+    let (sym, tag) = &results[0];
+    assert_eq!(sym.as_sym().unwrap().name, "factorial_inline_test");
+    assert_eq!(*tag, "second");
+    let (sym, tag) = &results[1];
+    assert_eq!(sym.as_sym().unwrap().name, "factorial");
+    assert_eq!(*tag, "first");
+}
+
+/// Check that we can symbolize addresses using indexes provided by the
+/// cache daemon.
+#[cfg(feature = "cache-daemon")]
+#[test]
+fn symbolize_cache_daemon() {
+    use blazesym::daemon::Server;
+
+    let dir = tempfile::tempdir().unwrap();
+    let server = Server::bind(dir.path().join("sock"), dir.path()).unwrap();
+    let symbolizer = Symbolizer::builder()
+        .set_cache_daemon(Some(server.socket().to_path_buf()))
+        .build();
+
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses-no-dwarf.bin");
+    let elf = symbolize::Source::from(symbolize::Elf {
+        debug_syms: false,
+        ..symbolize::Elf::new(path)
+    });
+    let kallsyms = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("kallsyms");
+    let kernel = symbolize::Source::from(symbolize::Kernel {
+        kallsyms: Some(kallsyms),
+        ..Default::default()
+    });
+
+    std::thread::scope(|s| {
+        let _handle = s.spawn(|| {
+            let () = server.serve_one().unwrap();
+            let () = server.serve_one().unwrap();
+        });
+
+        // Both inputs are served by the same (cached) index.
+        let syms = symbolizer
+            .symbolize(&elf, symbolize::Input::VirtOffset(&[0x2000100, 0x2000101]))
+            .unwrap();
+        for sym in syms {
+            let sym = sym.into_sym().unwrap();
+            assert_eq!(sym.name, "factorial");
+            assert_eq!(sym.addr, 0x2000100);
+        }
+        let sym = symbolizer
+            .symbolize_single(&elf, symbolize::Input::VirtOffset(0x2000100))
+            .unwrap()
+            .into_sym()
+            .unwrap();
+        assert_eq!(sym.name, "factorial");
 
-        assert_eq!(result.name, "test::test_function");
-        assert_eq!(result.inlined.len(), 1, "{:#?}", result.inlined);
-        assert_eq!(result.inlined[0].name, "test::inlined_call");
-        Ok(())
-    }
+        let sym = symbolizer
+            .symbolize_single(&kernel, symbolize::Input::AbsAddr(0xc0008001))
+            .unwrap()
+            .into_sym()
+            .unwrap();
+        assert_eq!(sym.name, "stext");
+        assert_eq!(sym.offset, 1);
+    });
+}
 
-    let test_dwarf = Path::new(&env!("CARGO_MANIFEST_DIR"))
+/// Check that parsing limits are honored in hardened parsing mode.
+#[test]
+fn symbolize_hardened_limits() {
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
         .join("data")
-        .join("test-rs.bin");
-    let elf = inspect::Elf::new(&test_dwarf);
-    let src = inspect::Source::Elf(elf);
+        .join("test-stable-addresses-dwarf-only.bin");
+    let src = symbolize::Source::from(symbolize::Elf::new(path));
+    let input = symbolize::Input::VirtOffset(0x200020a);
 
-    let inspector = Inspector::new();
-    let results = inspector
-        .lookup(&src, &["_RNvCs69hjMPjVIJK_4test13test_function"])
+    let symbolizer = Symbolizer::builder()
+        .set_limits(Some(Limits::default()))
+        .build();
+    let sym = symbolizer
+        .symbolize_single(&src, input)
         .unwrap()
-        .into_iter()
-        .flatten()
-        .collect::<Vec<_>>();
-    assert!(!results.is_empty());
-
-    let addr = results[0].addr;
-    let src = symbolize::Source::Elf(symbolize::Elf::new(&test_dwarf));
-    let symbolizer = Symbolizer::builder().enable_demangling(false).build();
-    let result = symbolizer
-        .symbolize_single(&src, symbolize::Input::VirtOffset(addr))
+        .into_sym()
+        .unwrap();
+    assert_eq!(sym.inlined.len(), 2);
+
+    let symbolizer = Symbolizer::builder()
+        .set_limits(Some(Limits {
+            max_section_size: 16,
+            ..Default::default()
+        }))
+        .build();
+    let err = symbolizer.symbolize_single(&src, input).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    let symbolizer = Symbolizer::builder()
+        .set_limits(Some(Limits {
+            max_units: 0,
+            ..Default::default()
+        }))
+        .build();
+    let err = symbolizer.symbolize_single(&src, input).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    // Inlined functions nested more deeply than allowed are ignored.
+    let symbolizer = Symbolizer::builder()
+        .set_limits(Some(Limits {
+            max_depth: 1,
+            ..Default::default()
+        }))
+        .build();
+    let sym = symbolizer
+        .symbolize_single(&src, input)
         .unwrap()
         .into_sym()
         .unwrap();
+    assert_eq!(sym.inlined.len(), 1);
+    assert_eq!(sym.inlined[0].name, "factorial_inline_wrapper");
 
-    let addr = result.addr;
-    let size = result.size.unwrap() as u64;
-    for inst_addr in addr..addr + size {
-        if test(&test_dwarf, inst_addr).is_ok() {
-            return
+    let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses.gsym");
+    let src = symbolize::Source::from(symbolize::GsymFile::new(path));
+    let symbolizer = Symbolizer::builder()
+        .set_limits(Some(Limits {
+            max_section_size: 16,
+            ..Default::default()
+        }))
+        .build();
+    let err = symbolizer.symbolize_single(&src, input).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+/// Make sure that symbolization of corrupted inputs does not panic.
+///
+/// This test deterministically mutates our test files, seeding the
+/// mutations from a fixed value. It is meant as a quick regression
+/// check; the fuzz targets in `fuzz/` explore inputs more thoroughly.
+#[test]
+fn symbolize_hardened_corrupted() {
+    /// A simple xorshift based pseudo random number generator.
+    fn next(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    let files = [
+        "test-stable-addresses.bin",
+        "test-stable-addresses-dwarf-only.bin",
+        "test-dwarf-v4.bin",
+        "test-dwarf-v5.bin",
+        "test-dwarf-v4-zlib.bin",
+        "test-dwarf-v4-zstd.bin",
+        "test-stable-addresses.gsym",
+    ];
+    let addrs = (0..32).map(|i| 0x2000000 + i * 0x20).collect::<Vec<_>>();
+    let mut state = 0x2545f4914f6cdd1d;
+
+    for file in files {
+        let path = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join(file);
+        let orig = read_file(&path).unwrap();
+        let tmpfile = tempfile::NamedTempFile::new().unwrap();
+
+        for _ in 0..500 {
+            let mut data = orig.clone();
+            for _ in 0..1 + next(&mut state) % 4 {
+                let idx = next(&mut state) as usize % data.len();
+                data[idx] = next(&mut state) as u8;
+            }
+
+            let symbolizer = Symbolizer::builder()
+                .set_limits(Some(Limits::default()))
+                .build();
+            let src = if file.ends_with(".gsym") {
+                symbolize::Source::from(symbolize::GsymData::new(&data))
+            } else {
+                let () = write_file(tmpfile.path(), &data).unwrap();
+                symbolize::Source::from(symbolize::Elf::new(tmpfile.path()))
+            };
+
+            if let Err(err) = symbolizer.symbolize(&src, symbolize::Input::VirtOffset(&addrs)) {
+                assert!(!err.to_string().contains("panicked"), "{err:?}");
+            }
         }
     }
+}
 
-    panic!("failed to find inlined function call");
+/// An anonymous memory mapping, standing in for a JIT code cache.
+struct AnonMapping {
+    addr: *mut libc::c_void,
+    len: usize,
 }
 
-/// Check that we can symbolize addresses inside our own process.
+impl AnonMapping {
+    fn new(len: usize) -> Self {
+        let addr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        assert_ne!(addr, libc::MAP_FAILED, "{}", Error::last_os_error());
+        Self { addr, len }
+    }
+
+    fn addr(&self) -> Addr {
+        self.addr as Addr
+    }
+}
+
+impl Drop for AnonMapping {
+    fn drop(&mut self) {
+        let rc = unsafe { libc::munmap(self.addr, self.len) };
+        assert_eq!(rc, 0, "{}", Error::last_os_error());
+    }
+}
+
+
+/// Check that we can symbolize JIT compiled code in a "JVM" process
+/// using a perf map.
 #[test]
-fn symbolize_process() {
-    let src = symbolize::Source::Process(symbolize::Process::new(Pid::Slf));
-    let addrs = [symbolize_process as Addr, Symbolizer::symbolize as Addr];
+fn symbolize_jvm() {
+    let code = AnonMapping::new(0x1000);
+    let base = code.addr();
+
+    let mut perf_map = tempfile::NamedTempFile::new().unwrap();
+    let () = writeln!(perf_map, "{base:x} 1000 Interpreter").unwrap();
+    let () = writeln!(perf_map, "{:x} 20 LFoo;::bar(I)V", base + 0x100).unwrap();
+
+    let mut jvm = symbolize::Jvm::new(Pid::Slf);
+    jvm.perf_map = Some(perf_map.path().to_path_buf());
+    jvm.jitdump = Some(Path::new("/does/not/exist").to_path_buf());
+
+    let src = symbolize::Source::from(jvm);
+    let addrs = [base + 0x110, base + 0x200, symbolize_jvm as fn() as Addr];
     let symbolizer = Symbolizer::new();
     let results = symbolizer
         .symbolize(&src, symbolize::Input::AbsAddr(&addrs))
         .unwrap()
         .into_iter()
         .collect::<Vec<_>>();
-    assert_eq!(results.len(), 2);
+    assert_eq!(results.len(), 3);
 
     let result = results[0].as_sym().unwrap();
-    assert!(result.name.contains("symbolize_process"), "{result:x?}");
+    assert_eq!(result.name, "LFoo;::bar(I)V");
+    assert_eq!(result.offset, 0x10);
 
     let result = results[1].as_sym().unwrap();
-    // It's not entirely clear why we have seen two different demangled
-    // symbols, but they both seem legit.
-    assert!(
-        result.name == "blazesym::symbolize::symbolizer::Symbolizer::symbolize"
-            || result.name == "<blazesym::symbolize::symbolizer::Symbolizer>::symbolize",
-        "{}",
-        result.name
+    assert_eq!(result.name, "Interpreter");
+    assert_eq!(result.offset, 0x200);
+
+    let result = results[2].as_sym().unwrap();
+    assert!(result.name.contains("symbolize_jvm"), "{result:x?}");
+}
+
+/// Create a jitdump file containing a single code load record.
+fn jitdump_data(addr: Addr, size: u64, name: &str) -> Vec<u8> {
+    let mut data = Vec::new();
+    // The header: magic, version, header size, and remaining fields.
+    let () = data.extend_from_slice(&0x4A695444u32.to_ne_bytes());
+    let () = data.extend_from_slice(&1u32.to_ne_bytes());
+    let () = data.extend_from_slice(&40u32.to_ne_bytes());
+    let () = data.extend_from_slice(&[0; 28]);
+    // The code load record: ID, record size, and timestamp, followed by
+    // PID, TID, VMA, code address, code size, index, and name.
+    let () = data.extend_from_slice(&0u32.to_ne_bytes());
+    let () = data.extend_from_slice(&(16 + 40 + name.len() as u32 + 1).to_ne_bytes());
+    let () = data.extend_from_slice(&0u64.to_ne_bytes());
+    let () = data.extend_from_slice(&[0; 8]);
+    let () = data.extend_from_slice(&addr.to_ne_bytes());
+    let () = data.extend_from_slice(&addr.to_ne_bytes());
+    let () = data.extend_from_slice(&size.to_ne_bytes());
+    let () = data.extend_from_slice(&0u64.to_ne_bytes());
+    let () = data.extend_from_slice(name.as_bytes());
+    let () = data.push(b'\0');
+    data
+}
+
+/// Check that methods reported in a jitdump file take precedence over
+/// larger code regions reported in a perf map.
+#[test]
+fn symbolize_jvm_perf_map_and_jitdump() {
+    let code = AnonMapping::new(0x1000);
+    let base = code.addr();
+
+    let mut perf_map = tempfile::NamedTempFile::new().unwrap();
+    let () = writeln!(perf_map, "{base:x} 1000 Interpreter").unwrap();
+
+    let mut jitdump = tempfile::NamedTempFile::new().unwrap();
+    let () = jitdump
+        .write_all(&jitdump_data(base + 0x100, 0x20, "LFoo;::bar(I)V"))
+        .unwrap();
+
+    let mut jvm = symbolize::Jvm::new(Pid::Slf);
+    jvm.perf_map = Some(perf_map.path().to_path_buf());
+    jvm.jitdump = Some(jitdump.path().to_path_buf());
+
+    let src = symbolize::Source::from(jvm);
+    let addrs = [base + 0x110, base + 0x200];
+    let symbolizer = Symbolizer::new();
+    let results = symbolizer
+        .symbolize(&src, symbolize::Input::AbsAddr(&addrs))
+        .unwrap();
+    assert_eq!(results[0].as_sym().unwrap().name, "LFoo;::bar(I)V");
+    assert_eq!(results[1].as_sym().unwrap().name, "Interpreter");
+}
+
+/// Check that a JVM perf map is re-read once it got updated.
+#[test]
+fn symbolize_jvm_updated_perf_map() {
+    let code = AnonMapping::new(0x1000);
+    let base = code.addr();
+
+    let mut perf_map = tempfile::NamedTempFile::new().unwrap();
+    let () = writeln!(perf_map, "{base:x} 100 LFoo;::bar(I)V").unwrap();
+
+    let mut jvm = symbolize::Jvm::new(Pid::Slf);
+    jvm.perf_map = Some(perf_map.path().to_path_buf());
+    jvm.jitdump = Some(Path::new("/does/not/exist").to_path_buf());
+    let src = symbolize::Source::from(jvm);
+    let symbolizer = Symbolizer::new();
+
+    let symbolize = |addr| {
+        symbolizer
+            .symbolize_single(&src, symbolize::Input::AbsAddr(addr))
+            .unwrap()
+            .into_sym()
+            .map(|sym| sym.name.to_string())
+    };
+
+    assert_eq!(symbolize(base + 0x10).as_deref(), Some("LFoo;::bar(I)V"));
+    assert_eq!(symbolize(base + 0x210), None);
+
+    let () = writeln!(perf_map, "{:x} 100 LFoo;::baz()V", base + 0x200).unwrap();
+    let () = perf_map.flush().unwrap();
+
+    assert_eq!(symbolize(base + 0x10).as_deref(), Some("LFoo;::bar(I)V"));
+    assert_eq!(symbolize(base + 0x210).as_deref(), Some("LFoo;::baz()V"));
+}
+
+/// Check that we can symbolize JIT compiled code in a Node.js process,
+/// honoring updates to the append-only perf map.
+#[test]
+fn symbolize_node() {
+    let code = AnonMapping::new(0x1000);
+    let base = code.addr();
+
+    let mut perf_map = tempfile::NamedTempFile::new().unwrap();
+    let () = writeln!(perf_map, "{base:x} 100 LazyCompile:~foo /app.js:1").unwrap();
+    let () = writeln!(perf_map, "{:x} 100 LazyCompile:~bar /app.js:5", base + 0x80).unwrap();
+
+    let mut node = symbolize::Node::new(Pid::Slf);
+    node.perf_map = Some(perf_map.path().to_path_buf());
+    let src = symbolize::Source::from(node);
+    let symbolizer = Symbolizer::new();
+
+    let symbolize = |addr| {
+        symbolizer
+            .symbolize_single(&src, symbolize::Input::AbsAddr(addr))
+            .unwrap()
+            .into_sym()
+            .map(|sym| sym.name.to_string())
+    };
+
+    // `foo` overlaps with the later `bar` and is considered stale.
+    assert_eq!(symbolize(base + 0x10), None);
+    assert_eq!(
+        symbolize(base + 0x90).as_deref(),
+        Some("LazyCompile:~bar /app.js:5")
     );
+
+    // `bar` got moved and `baz` now occupies its former space.
+    let () = writeln!(
+        perf_map,
+        "{:x} 100 LazyCompile:~bar /app.js:5",
+        base + 0x800
+    )
+    .unwrap();
+    let () = writeln!(perf_map, "{:x} 40 LazyCompile:~baz /app.js:9", base + 0x80).unwrap();
+    let () = perf_map.flush().unwrap();
+    assert_eq!(
+        symbolize(base + 0x90).as_deref(),
+        Some("LazyCompile:~baz /app.js:9")
+    );
+    assert_eq!(
+        symbolize(base + 0x810).as_deref(),
+        Some("LazyCompile:~bar /app.js:5")
+    );
+}
+
+/// A stand-in for CPython's evaluation loop.
+#[no_mangle]
+#[inline(never)]
+extern "C" fn _PyEval_EvalFrameDefault() {}
+
+/// Check that we can merge Python frames into a native stack.
+#[test]
+fn symbolize_enriched_python() {
+    fn py_frame(name: &str, line: u32) -> symbolize::Sym<'static> {
+        symbolize::Sym {
+            name: name.to_string().into(),
+            addr: 0,
+            offset: 0,
+            size: None,
+            lang: symbolize::SrcLang::Unknown,
+            code_info: Some(symbolize::CodeInfo {
+                dir: None,
+                file: OsStr::new("main.py").to_os_string().into(),
+                line: Some(line),
+                column: None,
+                context: None,
+                _non_exhaustive: (),
+            }),
+            inlined: Box::new([]),
+            module: None,
+            build_id: None,
+            file_offset: None,
+            _non_exhaustive: (),
+        }
+    }
+
+    let mut enricher =
+        symbolize::PythonFrames::new([vec![py_frame("inner", 3), py_frame("outer", 7)]]);
+    let src = symbolize::Source::Process(symbolize::Process::new(Pid::Slf));
+    let addrs = [
+        symbolize_enriched_python as fn() as Addr,
+        _PyEval_EvalFrameDefault as extern "C" fn() as Addr,
+        // Once we ran out of Python frames, native frames are
+        // reported again.
+        _PyEval_EvalFrameDefault as extern "C" fn() as Addr,
+    ];
+    let symbolizer = Symbolizer::new();
+    let results = symbolizer
+        .symbolize_enriched(&src, symbolize::Input::AbsAddr(&addrs), &mut enricher)
+        .unwrap();
+    let names = results
+        .iter()
+        .map(|result| result.as_sym().unwrap().name.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(names.len(), 4, "{names:?}");
+    assert!(names[0].contains("symbolize_enriched_python"), "{names:?}");
+    assert_eq!(names[1..], ["inner", "outer", "_PyEval_EvalFrameDefault"]);
+
+    let code_info = results[2].as_sym().unwrap().code_info.as_ref().unwrap();
+    assert_eq!(code_info.line, Some(7));
 }
 
 /// Check that we can normalize addresses in an ELF shared object.
@@ -442,6 +2756,101 @@ fn normalize_build_id_rading() {
 }
 
 
+/// Check that we can symbolize the outputs of address normalization,
+/// locating artifacts by build ID.
+#[test]
+fn normalize_symbolize_round_trip() {
+    let test_so = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("libtest-so.so");
+    let so_cstr = CString::new(test_so.clone().into_os_string().into_vec()).unwrap();
+    let handle = unsafe { libc::dlopen(so_cstr.as_ptr(), libc::RTLD_NOW) };
+    assert!(!handle.is_null());
+
+    let the_answer_addr = unsafe { libc::dlsym(handle, "the_answer\0".as_ptr().cast()) };
+    assert!(!the_answer_addr.is_null());
+
+    let normalizer = Normalizer::new();
+    let normalized = normalizer
+        .normalize_user_addrs(Pid::Slf, [the_answer_addr as Addr, 0x1].as_slice())
+        .unwrap();
+
+    let rc = unsafe { libc::dlclose(handle) };
+    assert_eq!(rc, 0, "{}", Error::last_os_error());
+
+    // Our "artifact store" indexes binaries by build ID.
+    let build_id = read_elf_build_id(&test_so).unwrap().unwrap();
+    let artifacts = HashMap::from([(build_id, test_so)]);
+    let locator = |meta: &UserMeta| -> Result<Option<PathBuf>, blazesym::Error> {
+        let path = meta
+            .elf()
+            .and_then(|elf| elf.build_id.as_ref())
+            .and_then(|build_id| artifacts.get(build_id))
+            .cloned();
+        Ok(path)
+    };
+
+    let symbolizer = Symbolizer::new();
+    let symbolized = symbolizer
+        .symbolize_normalized(&normalized, &locator)
+        .unwrap();
+    assert_eq!(symbolized.len(), 2);
+    let sym = symbolized[0].as_sym().unwrap();
+    assert_eq!(sym.name, "the_answer");
+    assert_eq!(symbolized[1], Symbolized::Unknown(Reason::Unmapped));
+
+    // Without artifacts, nothing can be symbolized.
+    let locator = |_meta: &UserMeta| -> Result<Option<PathBuf>, blazesym::Error> { Ok(None) };
+    let symbolized = symbolizer
+        .symbolize_normalized(&normalized, &locator)
+        .unwrap();
+    assert_eq!(symbolized[0], Symbolized::Unknown(Reason::MissingSyms));
+}
+
+
+/// Check that we can symbolize normalized addresses using binaries
+/// from a symbol directory mirroring the original file system layout.
+#[test]
+fn normalize_symbolize_symbol_dir() {
+    let test_so = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("libtest-so.so");
+    let so_cstr = CString::new(test_so.clone().into_os_string().into_vec()).unwrap();
+    let handle = unsafe { libc::dlopen(so_cstr.as_ptr(), libc::RTLD_NOW) };
+    assert!(!handle.is_null());
+
+    let the_answer_addr = unsafe { libc::dlsym(handle, "the_answer\0".as_ptr().cast()) };
+    assert!(!the_answer_addr.is_null());
+
+    let normalizer = Normalizer::new();
+    let normalized = normalizer
+        .normalize_user_addrs(Pid::Slf, [the_answer_addr as Addr].as_slice())
+        .unwrap();
+
+    let rc = unsafe { libc::dlclose(handle) };
+    assert_eq!(rc, 0, "{}", Error::last_os_error());
+
+    let symbolizer = Symbolizer::new();
+    let dir = tempfile::tempdir().unwrap();
+    let symbol_dir = SymbolDir::new(dir.path());
+    let symbolized = symbolizer
+        .symbolize_normalized(&normalized, &symbol_dir)
+        .unwrap();
+    assert_eq!(symbolized[0], Symbolized::Unknown(Reason::MissingSyms));
+
+    let mirrored = dir.path().join(test_so.strip_prefix("/").unwrap());
+    let () = create_dir_all(mirrored.parent().unwrap()).unwrap();
+    let _cnt = copy(&test_so, &mirrored).unwrap();
+
+    let symbol_dir = SymbolDir::new(dir.path());
+    let symbolized = symbolizer
+        .symbolize_normalized(&normalized, &symbol_dir)
+        .unwrap();
+    let sym = symbolized[0].as_sym().unwrap();
+    assert_eq!(sym.name, "the_answer");
+}
+
+
 /// Check that we can look up an address.
 #[test]
 fn inspect() {
@@ -515,6 +2924,124 @@ fn inspect_file_offset_elf() {
 }
 
 
+/// Check that we can inspect the object files contained in a static
+/// archive.
+#[test]
+fn inspect_archive() {
+    let archive = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("libtest-stable-addresses.a");
+    let src = inspect::Source::Archive(inspect::Archive::new(&archive));
+
+    let inspector = Inspector::new();
+    let results = inspector.lookup(&src, &["dummy", "foo"]).unwrap();
+    assert_eq!(results.len(), 2);
+
+    let dummy = &results[0];
+    assert_eq!(dummy.len(), 1);
+    let bytes = read_4bytes_at(&archive, dummy[0].file_offset.unwrap());
+    assert_eq!(bytes, [0xde, 0xad, 0xbe, 0xef]);
+    assert!(dummy[0]
+        .obj_file_name
+        .as_deref()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .ends_with("libtest-stable-addresses.a(test-stable-addresses.o)"));
+
+    let foo = &results[1];
+    assert_eq!(foo.len(), 1);
+    assert!(foo[0]
+        .obj_file_name
+        .as_deref()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .ends_with("libtest-stable-addresses.a(test-stable-addresses-cu2.o)"));
+
+    let syms = inspector
+        .for_each(&src, HashSet::<String>::new(), |mut syms, sym| {
+            let _inserted = syms.insert(sym.name.to_string());
+            syms
+        })
+        .unwrap();
+    assert!(syms.contains("main"));
+    assert!(syms.contains("factorial"));
+    assert!(syms.contains("foo"));
+}
+
+
+/// Check that we can symbolize section relative addresses in a
+/// relocatable object file using its debug information.
+#[test]
+fn symbolize_object_file() {
+    let obj = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses-cu2.o");
+    let src = inspect::Source::Elf(inspect::Elf::new(&obj));
+    let inspector = Inspector::new();
+    let results = inspector
+        .lookup(&src, &["foo"])
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 1);
+    let addr = results[0].addr;
+
+    let src = symbolize::Source::Elf(symbolize::Elf::new(obj));
+    let symbolizer = Symbolizer::new();
+    let result = symbolizer
+        .symbolize_single(&src, symbolize::Input::VirtOffset(addr))
+        .unwrap()
+        .into_sym()
+        .unwrap();
+    assert_eq!(result.name, "foo");
+    assert_eq!(result.addr, addr);
+    let code_info = result.code_info.unwrap();
+    assert_eq!(code_info.file, OsStr::new("test-stable-addresses-cu2.c"));
+    assert_eq!(code_info.line, Some(8));
+}
+
+
+/// Check that we can symbolize addresses in ELF files created for
+/// architectures other than that of the host, including those using a
+/// different byte order.
+#[test]
+fn symbolize_cross_arch_object_file() {
+    for file in ["test-cross-arch-aarch64.o", "test-cross-arch-s390x.o"] {
+        let obj = Path::new(&env!("CARGO_MANIFEST_DIR"))
+            .join("data")
+            .join(file);
+        let src = inspect::Source::Elf(inspect::Elf::new(&obj));
+        let inspector = Inspector::new();
+        let results = inspector
+            .lookup(&src, &["cross_arch_factorial", "cross_arch_main"])
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        assert_eq!(results.len(), 2, "{file}");
+        let addrs = results.iter().map(|sym| sym.addr).collect::<Vec<_>>();
+
+        let src = symbolize::Source::Elf(symbolize::Elf::new(&obj));
+        let symbolizer = Symbolizer::new();
+        let symbolized = symbolizer
+            .symbolize(&src, symbolize::Input::VirtOffset(&addrs))
+            .unwrap();
+        let expected = [("cross_arch_factorial", 6), ("cross_arch_main", 21)];
+        for ((result, addr), (name, line)) in symbolized.into_iter().zip(addrs).zip(expected) {
+            let result = result.into_sym().unwrap();
+            assert_eq!(result.name, name, "{file}");
+            assert_eq!(result.addr, addr, "{file}");
+            let code_info = result.code_info.unwrap();
+            assert_eq!(code_info.file, OsStr::new("test-cross-arch.ll"), "{file}");
+            assert_eq!(code_info.line, Some(line), "{file}");
+        }
+    }
+}
+
+
 /// Check that we can iterate over all symbols in an ELF file.
 #[test]
 fn inspect_all_symbols() {
@@ -537,3 +3064,153 @@ fn inspect_all_symbols() {
     assert!(syms.contains("factorial_wrapper"));
     assert!(syms.contains("factorial_inline_test"));
 }
+
+
+/// Check that we can look up the addresses of source code lines.
+#[test]
+fn inspect_line_addrs() {
+    let test_elf = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("test-stable-addresses.bin");
+    let src = inspect::Source::Elf(inspect::Elf::new(&test_elf));
+    let inspector = Inspector::new();
+
+    let file = Path::new("test-stable-addresses.c");
+    let addrs = inspector.lookup_line(&src, file, 11, None).unwrap();
+    assert_eq!(addrs.len(), 1);
+    // The line is covered by two line table rows, which should have
+    // been merged.
+    assert_eq!(addrs[0].addr, 0x2000118);
+    assert_eq!(addrs[0].size, 0x11);
+    assert_eq!(addrs[0].function.as_deref(), Some("factorial"));
+    assert_eq!(
+        addrs[0].code_info.file,
+        OsStr::new("test-stable-addresses.c")
+    );
+    assert_eq!(addrs[0].code_info.line, Some(11));
+    assert_eq!(addrs[0].code_info.column, Some(10));
+
+    // Code of inlined functions is attributed to the function it got
+    // inlined into.
+    let addrs = inspector.lookup_line(&src, file, 21, None).unwrap();
+    assert_eq!(addrs.len(), 1);
+    assert_eq!(addrs[0].addr, 0x2000204);
+    assert_eq!(addrs[0].function.as_deref(), Some("factorial_inline_test"));
+
+    let addrs = inspector
+        .lookup_line(&src, file, 21, Some("factorial"))
+        .unwrap();
+    assert_eq!(addrs, Vec::new());
+
+    // Files are matched by their trailing path components.
+    let addrs = inspector.lookup_line(&src, file, 8, None).unwrap();
+    assert_eq!(addrs.len(), 1);
+    assert_eq!(addrs[0].addr, 0x2000100);
+
+    let file = Path::new("data/test-stable-addresses-cu2.c");
+    let addrs = inspector.lookup_line(&src, file, 8, None).unwrap();
+    assert_eq!(addrs.len(), 1);
+    assert_eq!(addrs[0].addr, 0x2000045);
+    assert_eq!(addrs[0].function.as_deref(), Some("foo"));
+
+    let file = Path::new("cu2.c");
+    let addrs = inspector.lookup_line(&src, file, 8, None).unwrap();
+    assert_eq!(addrs, Vec::new());
+
+    // Lines without code don't have any addresses.
+    let file = Path::new("test-stable-addresses.c");
+    let addrs = inspector.lookup_line(&src, file, 1, None).unwrap();
+    assert_eq!(addrs, Vec::new());
+}
+
+
+/// Check that we can iterate over symbols in an ELF file using a
+/// filter.
+#[test]
+fn inspect_filtered_symbols() {
+    fn syms(src: &inspect::Source, filter: &SymFilter) -> Vec<(String, Option<String>)> {
+        let inspector = Inspector::new();
+        let mut syms = inspector
+            .for_each_sym(src, filter, Vec::new(), |mut syms, sym| {
+                let () = syms.push((
+                    sym.name.to_string(),
+                    sym.section.as_deref().map(str::to_string),
+                ));
+                syms
+            })
+            .unwrap();
+        let () = syms.sort();
+        syms
+    }
+
+    let test_so = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("libtest-so.so");
+    let src = inspect::Source::Elf(inspect::Elf::new(&test_so));
+
+    let all = syms(&src, &SymFilter::default());
+    assert!(all.contains(&("the_answer".to_string(), Some(".text".to_string()))));
+    assert!(all.contains(&("completed.0".to_string(), Some(".bss".to_string()))));
+
+    let filter = SymFilter {
+        sym_type: SymType::Variable,
+        binding: Some(SymBinding::Local),
+        name: Some("completed*".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        syms(&src, &filter),
+        vec![("completed.0".to_string(), Some(".bss".to_string()))]
+    );
+
+    let filter = SymFilter {
+        sym_type: SymType::Function,
+        binding: Some(SymBinding::Global),
+        visibility: Some(SymVisibility::Default),
+        ..Default::default()
+    };
+    assert_eq!(
+        syms(&src, &filter),
+        vec![("the_answer".to_string(), Some(".text".to_string()))]
+    );
+
+    let filter = SymFilter {
+        table: Some(SymTable::Dynsym),
+        ..Default::default()
+    };
+    assert_eq!(
+        syms(&src, &filter),
+        vec![("the_answer".to_string(), Some(".text".to_string()))]
+    );
+
+    let filter = SymFilter {
+        name: Some("*_tm_clones".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        syms(&src, &filter),
+        vec![
+            (
+                "deregister_tm_clones".to_string(),
+                Some(".text".to_string())
+            ),
+            ("register_tm_clones".to_string(), Some(".text".to_string())),
+        ]
+    );
+
+    // Without debug information, DWARF symbols aren't available.
+    let filter = SymFilter {
+        table: Some(SymTable::Dwarf),
+        ..Default::default()
+    };
+    assert_eq!(syms(&src, &filter), Vec::new());
+
+    let test_so = Path::new(&env!("CARGO_MANIFEST_DIR"))
+        .join("data")
+        .join("libtest-so-dwarf.so");
+    let src = inspect::Source::Elf(inspect::Elf::new(test_so));
+    assert_eq!(
+        syms(&src, &filter),
+        vec![("the_answer".to_string(), Some(".text".to_string()))]
+    );
+}